@@ -1,4 +1,15 @@
 pub use axdisplay::DisplayInfo as AxDisplayInfo;
+pub use axdisplay::{DisplayMode as AxDisplayMode, PixelFormat as AxPixelFormat, Rect as AxRect};
+
+pub use axdisplay::{
+    device_count as ax_display_count, device_name as ax_display_name, flip as ax_display_flip,
+    flush as ax_display_flush, flush_rects as ax_display_flush_rects,
+    has_cursor as ax_display_has_cursor, info as ax_display_info,
+    is_double_buffered as ax_display_is_double_buffered, mode as ax_display_mode,
+    modes as ax_display_modes, move_cursor as ax_display_move_cursor,
+    set_cursor as ax_display_set_cursor, set_double_buffered as ax_display_set_double_buffered,
+    set_mode as ax_display_set_mode,
+};
 
 /// Gets the framebuffer information.
 pub fn ax_framebuffer_info() -> AxDisplayInfo {
@@ -0,0 +1,7 @@
+pub use axinput::InputEvent as AxInputEvent;
+
+pub use axinput::{
+    device_count as ax_input_device_count, device_name as ax_input_device_name,
+    has_event_type as ax_input_has_event_type, has_events as ax_input_has_events,
+    read_events as ax_read_input_events,
+};
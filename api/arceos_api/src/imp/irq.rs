@@ -0,0 +1,7 @@
+pub use axhal::irq::IrqStat as AxIrqStat;
+
+pub fn ax_for_each_irq_stat(f: &mut dyn FnMut(&AxIrqStat)) {
+    for stat in axhal::irq::irq_stats() {
+        f(&stat);
+    }
+}
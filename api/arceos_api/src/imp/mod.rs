@@ -1,6 +1,11 @@
 mod mem;
 mod task;
 
+cfg_irq! {
+    mod irq;
+    pub use irq::*;
+}
+
 cfg_fs! {
     mod fs;
     pub use fs::*;
@@ -16,21 +21,20 @@ cfg_display! {
     pub use display::*;
 }
 
+cfg_input! {
+    mod input;
+    pub use input::*;
+}
+
 mod stdio {
     use core::fmt;
 
     pub fn ax_console_read_bytes(buf: &mut [u8]) -> crate::AxResult<usize> {
-        let len = axhal::console::read_bytes(buf);
-        for c in &mut buf[..len] {
-            if *c == b'\r' {
-                *c = b'\n';
-            }
-        }
-        Ok(len)
+        Ok(axtty::read(buf))
     }
 
     pub fn ax_console_write_bytes(buf: &[u8]) -> crate::AxResult<usize> {
-        axhal::console::write_bytes(buf);
+        axtty::write(buf);
         Ok(buf.len())
     }
 
@@ -50,5 +54,5 @@ pub use self::stdio::*;
 pub use self::task::*;
 pub use self::time::*;
 
-pub use axhal::misc::terminate as ax_terminate;
+pub use axhal::misc::{poweroff as ax_poweroff, reboot as ax_reboot, terminate as ax_terminate};
 pub use axio::PollState as AxPollState;
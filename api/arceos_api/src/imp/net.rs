@@ -2,6 +2,8 @@ use crate::io::AxPollState;
 use axerrno::AxResult;
 use axnet::{UdpSocket, TcpSocket};
 use core::net::{IpAddr, SocketAddr};
+#[cfg(feature = "async")]
+use core::task::{Context, Poll};
 
 /// A handle to a TCP socket.
 pub struct AxTcpSocketHandle(TcpSocket);
@@ -117,6 +119,80 @@ pub fn ax_udp_poll(socket: &AxUdpSocketHandle) -> AxResult<AxPollState> {
     socket.0.poll()
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// Asynchronous operations
+////////////////////////////////////////////////////////////////////////////////
+
+cfg_async! {
+    pub fn ax_tcp_start_connect(socket: &AxTcpSocketHandle, addr: SocketAddr) -> AxResult {
+        socket.0.start_connect(addr)
+    }
+
+    pub fn ax_tcp_poll_connected(socket: &AxTcpSocketHandle, cx: &mut Context<'_>) -> Poll<AxResult> {
+        socket.0.poll_connected(cx)
+    }
+
+    pub fn ax_tcp_poll_accept(
+        socket: &AxTcpSocketHandle,
+        cx: &mut Context<'_>,
+    ) -> Poll<AxResult<(AxTcpSocketHandle, SocketAddr)>> {
+        socket.0.poll_accept(cx).map(|res| {
+            let new_sock = res?;
+            let addr = new_sock.peer_addr()?;
+            Ok((AxTcpSocketHandle(new_sock), addr))
+        })
+    }
+
+    pub fn ax_tcp_poll_send(
+        socket: &AxTcpSocketHandle,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<AxResult<usize>> {
+        socket.0.poll_send(cx, buf)
+    }
+
+    pub fn ax_tcp_poll_recv(
+        socket: &AxTcpSocketHandle,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<AxResult<usize>> {
+        socket.0.poll_recv(cx, buf)
+    }
+
+    pub fn ax_udp_poll_recv_from(
+        socket: &AxUdpSocketHandle,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<AxResult<(usize, SocketAddr)>> {
+        socket.0.poll_recv_from(cx, buf)
+    }
+
+    pub fn ax_udp_poll_send_to(
+        socket: &AxUdpSocketHandle,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        addr: SocketAddr,
+    ) -> Poll<AxResult<usize>> {
+        socket.0.poll_send_to(cx, buf, addr)
+    }
+
+    pub fn ax_udp_poll_send(
+        socket: &AxUdpSocketHandle,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<AxResult<usize>> {
+        socket.0.poll_send(cx, buf)
+    }
+
+    pub fn ax_udp_poll_recv(
+        socket: &AxUdpSocketHandle,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<AxResult<usize>> {
+        socket.0.poll_recv(cx, buf)
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // Miscellaneous
 ////////////////////////////////////////////////////////////////////////////////
@@ -42,6 +42,9 @@ cfg_task! {
     /// A mask to specify the CPU affinity.
     pub use axtask::AxCpuMask;
 
+    /// Information and CPU usage statistics of a task.
+    pub use axtask::TaskInfo as AxTaskInfo;
+
     /// A handle to a wait queue.
     ///
     /// A wait queue is used to store sleeping tasks waiting for a certain event
@@ -126,6 +129,10 @@ cfg_task! {
         false
     }
 
+    pub fn ax_list_tasks() -> alloc::vec::Vec<AxTaskInfo> {
+        axtask::list_tasks()
+    }
+
     pub fn ax_wait_queue_wake(wq: &AxWaitQueueHandle, count: u32) {
         if count == u32::MAX {
             wq.0.notify_all(true);
@@ -136,3 +143,22 @@ cfg_task! {
         }
     }
 }
+
+cfg_async! {
+    /// An executor that runs many futures concurrently on a single task.
+    pub use axtask::future::Executor as AxExecutor;
+    /// A handle to spawn futures on an executor.
+    pub use axtask::future::Spawner as AxSpawner;
+    /// A handle to await the output of a spawned future.
+    pub use axtask::future::JoinHandle as AxJoinHandle;
+    /// A future that completes at a deadline.
+    pub use axtask::future::Sleep as AxSleep;
+    /// A future with a deadline.
+    pub use axtask::future::Timeout as AxTimeout;
+    /// The error returned when a [`AxTimeout`] has elapsed.
+    pub use axtask::future::TimedOut as AxTimedOut;
+
+    pub fn ax_sleep_until_async(deadline: crate::time::AxTimeValue) -> AxSleep {
+        axtask::future::sleep_until(deadline)
+    }
+}
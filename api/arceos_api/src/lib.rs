@@ -11,6 +11,7 @@
     feature = "alloc",
     feature = "fs",
     feature = "net",
+    feature = "display",
     feature = "multitask",
     feature = "dummy-if-not-enabled"
 ))]
@@ -32,6 +33,10 @@ pub mod sys {
     define_api! {
         /// Shutdown the whole system and all CPUs.
         pub fn ax_terminate() -> !;
+        /// Powers off the whole system and all CPUs.
+        pub fn ax_poweroff() -> !;
+        /// Reboots the whole system and all CPUs.
+        pub fn ax_reboot() -> !;
     }
 }
 
@@ -49,6 +54,21 @@ pub mod time {
     }
 }
 
+/// Interrupt management.
+pub mod irq {
+    define_api_type! {
+        @cfg "irq";
+        pub type AxIrqStat;
+    }
+
+    define_api! {
+        @cfg "irq";
+        /// Calls `f` with the statistics of each IRQ that has occurred or has
+        /// a named handler.
+        pub fn ax_for_each_irq_stat(f: &mut dyn FnMut(&AxIrqStat));
+    }
+}
+
 /// Memory management.
 pub mod mem {
     use core::{alloc::Layout, ptr::NonNull};
@@ -106,7 +126,11 @@ pub mod mem {
 pub mod stdio {
     use core::fmt;
     define_api! {
-        /// Reads a slice of bytes from the console, returns the number of bytes written.
+        /// Reads a slice of bytes from the console terminal, returns the number
+        /// of bytes read.
+        ///
+        /// It blocks until some input is available. In canonical mode, at most
+        /// one line is read.
         pub fn ax_console_read_bytes(buf: &mut [u8]) -> crate::AxResult<usize>;
         /// Writes a slice of bytes to the console, returns the number of bytes written.
         pub fn ax_console_write_bytes(buf: &[u8]) -> crate::AxResult<usize>;
@@ -122,6 +146,7 @@ pub mod task {
         pub type AxTaskHandle;
         pub type AxWaitQueueHandle;
         pub type AxCpuMask;
+        pub type AxTaskInfo;
     }
 
     define_api! {
@@ -175,6 +200,28 @@ pub mod task {
         /// The maximum number of tasks to wake up is specified by `count`. If
         /// `count` is `u32::MAX`, it will wake up all tasks in the wait queue.
         pub fn ax_wait_queue_wake(wq: &AxWaitQueueHandle, count: u32);
+        /// Returns the information and CPU usage statistics of all live tasks.
+        pub fn ax_list_tasks() -> alloc::vec::Vec<AxTaskInfo>;
+    }
+
+    define_api_type! {
+        @cfg "async";
+        pub type AxExecutor;
+        pub type AxSpawner;
+        pub type AxJoinHandle;
+        pub type AxSleep;
+        pub type AxTimeout;
+        pub type AxTimedOut;
+    }
+
+    define_api! {
+        @cfg "async";
+
+        /// Returns a future that completes at the given deadline.
+        ///
+        /// It's woken up by a timer event if the feature `irq` is enabled,
+        /// otherwise it polls the time repeatedly.
+        pub fn ax_sleep_until_async(deadline: crate::time::AxTimeValue) -> AxSleep;
     }
 }
 
@@ -263,6 +310,7 @@ pub mod fs {
 pub mod net {
     use crate::{AxResult, io::AxPollState};
     use core::net::{IpAddr, SocketAddr};
+    use core::task::{Context, Poll};
 
     define_api_type! {
         @cfg "net";
@@ -352,6 +400,60 @@ pub mod net {
         /// packets to the NIC.
         pub fn ax_poll_interfaces() -> AxResult;
     }
+
+    #[cfg(feature = "net")]
+    define_api! {
+        @cfg "async";
+
+        // Asynchronous operations
+        //
+        // If the operation would block, `Poll::Pending` is returned, and the
+        // waker of `cx` is woken up when the socket becomes ready.
+
+        /// Starts connecting the TCP socket to the given address and port,
+        /// without waiting for the connection to be established.
+        pub fn ax_tcp_start_connect(socket: &AxTcpSocketHandle, addr: SocketAddr) -> AxResult;
+        /// Polls for the completion of the connection started by
+        /// [`ax_tcp_start_connect`].
+        pub fn ax_tcp_poll_connected(socket: &AxTcpSocketHandle, cx: &mut Context<'_>) -> Poll<AxResult>;
+        /// Polls for a new connection on the TCP socket.
+        pub fn ax_tcp_poll_accept(
+            socket: &AxTcpSocketHandle,
+            cx: &mut Context<'_>,
+        ) -> Poll<AxResult<(AxTcpSocketHandle, SocketAddr)>>;
+        /// Polls for transmitting data in the given buffer on the TCP socket.
+        pub fn ax_tcp_poll_send(socket: &AxTcpSocketHandle, cx: &mut Context<'_>, buf: &[u8]) -> Poll<AxResult<usize>>;
+        /// Polls for receiving data on the TCP socket into the given buffer.
+        pub fn ax_tcp_poll_recv(
+            socket: &AxTcpSocketHandle,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<AxResult<usize>>;
+
+        /// Polls for receiving a single datagram message on the UDP socket.
+        pub fn ax_udp_poll_recv_from(
+            socket: &AxUdpSocketHandle,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<AxResult<(usize, SocketAddr)>>;
+        /// Polls for sending data on the UDP socket to the given address.
+        pub fn ax_udp_poll_send_to(
+            socket: &AxUdpSocketHandle,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+            addr: SocketAddr,
+        ) -> Poll<AxResult<usize>>;
+        /// Polls for sending data on the UDP socket to the remote address to
+        /// which it is connected.
+        pub fn ax_udp_poll_send(socket: &AxUdpSocketHandle, cx: &mut Context<'_>, buf: &[u8]) -> Poll<AxResult<usize>>;
+        /// Polls for receiving a single datagram message on the UDP socket
+        /// from the remote address to which it is connected.
+        pub fn ax_udp_poll_recv(
+            socket: &AxUdpSocketHandle,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<AxResult<usize>>;
+    }
 }
 
 /// Graphics manipulation operations.
@@ -359,14 +461,90 @@ pub mod display {
     define_api_type! {
         @cfg "display";
         pub type AxDisplayInfo;
+        pub type AxDisplayMode;
+        pub type AxPixelFormat;
+        pub type AxRect;
     }
 
     define_api! {
         @cfg "display";
-        /// Gets the framebuffer information.
+        /// Gets the framebuffer information of the first display.
         pub fn ax_framebuffer_info() -> AxDisplayInfo;
-        /// Flushes the framebuffer, i.e. show on the screen.
+        /// Flushes the framebuffer of the first display, i.e. show on the
+        /// screen.
         pub fn ax_framebuffer_flush();
+
+        /// Returns the number of displays.
+        pub fn ax_display_count() -> usize;
+        /// Returns the name of the given display.
+        pub fn ax_display_name(id: usize) -> crate::AxResult<&'static str>;
+        /// Gets the framebuffer information of the given display.
+        pub fn ax_display_info(id: usize) -> crate::AxResult<AxDisplayInfo>;
+        /// Returns the current mode of the given display.
+        pub fn ax_display_mode(id: usize) -> crate::AxResult<AxDisplayMode>;
+        /// Returns the modes supported by the given display, the preferred
+        /// one first.
+        pub fn ax_display_modes(id: usize) -> crate::AxResult<alloc::vec::Vec<AxDisplayMode>>;
+        /// Switches the given display to another mode, which reallocates the
+        /// framebuffer.
+        pub fn ax_display_set_mode(id: usize, mode: AxDisplayMode) -> crate::AxResult;
+        /// Flushes the whole framebuffer of the given display.
+        pub fn ax_display_flush(id: usize) -> crate::AxResult;
+        /// Flushes the given rectangles of the framebuffer of the given
+        /// display.
+        pub fn ax_display_flush_rects(id: usize, rects: &[AxRect]) -> crate::AxResult;
+        /// Enables or disables double buffering of the given display.
+        pub fn ax_display_set_double_buffered(id: usize, enabled: bool) -> crate::AxResult;
+        /// Whether double buffering is enabled on the given display.
+        pub fn ax_display_is_double_buffered(id: usize) -> crate::AxResult<bool>;
+        /// Shows the whole framebuffer of the given display, swapping the
+        /// front and back buffers if double buffering is enabled.
+        pub fn ax_display_flip(id: usize) -> crate::AxResult;
+        /// Whether the given display has a hardware cursor.
+        pub fn ax_display_has_cursor(id: usize) -> crate::AxResult<bool>;
+        /// Sets the cursor image (ARGB, 64x64 pixels) of the given display,
+        /// or hides the cursor if `image` is `None`.
+        pub fn ax_display_set_cursor(
+            id: usize,
+            image: Option<&[u32]>,
+            hot_x: u32,
+            hot_y: u32,
+        ) -> crate::AxResult;
+        /// Moves the cursor of the given display.
+        pub fn ax_display_move_cursor(id: usize, x: u32, y: u32) -> crate::AxResult;
+    }
+}
+
+/// Input device operations.
+pub mod input {
+    define_api_type! {
+        @cfg "input";
+        pub type AxInputEvent;
+    }
+
+    define_api! {
+        @cfg "input";
+        /// Returns the number of input devices.
+        pub fn ax_input_device_count() -> usize;
+        /// Returns the name of the given input device.
+        pub fn ax_input_device_name(id: usize) -> crate::AxResult<&'static str>;
+        /// Whether the given input device may report events of the given
+        /// type (e.g., `EV_KEY`).
+        pub fn ax_input_has_event_type(id: usize, event_type: u16) -> crate::AxResult<bool>;
+        /// Whether there are queued events of the given input device, i.e.,
+        /// reading events does not block.
+        pub fn ax_input_has_events(id: usize) -> crate::AxResult<bool>;
+        /// Reads events of the given input device into `buf`, and returns
+        /// the number of events read.
+        ///
+        /// If there are no events, it blocks until some arrive, or returns
+        /// [`AxError::WouldBlock`](crate::AxError::WouldBlock) if
+        /// `nonblocking` is set.
+        pub fn ax_read_input_events(
+            id: usize,
+            buf: &mut [AxInputEvent],
+            nonblocking: bool,
+        ) -> crate::AxResult<usize>;
     }
 }
 
@@ -394,10 +572,17 @@ pub mod modules {
     pub use axdisplay;
     #[cfg(feature = "dma")]
     pub use axdma;
-    #[cfg(any(feature = "fs", feature = "net", feature = "display"))]
+    #[cfg(any(
+        feature = "fs",
+        feature = "net",
+        feature = "display",
+        feature = "input"
+    ))]
     pub use axdriver;
     #[cfg(feature = "fs")]
     pub use axfs;
+    #[cfg(feature = "input")]
+    pub use axinput;
     #[cfg(feature = "paging")]
     pub use axmm;
     #[cfg(feature = "net")]
@@ -85,6 +85,10 @@ macro_rules! _cfg_common {
     }
 }
 
+macro_rules! cfg_irq {
+    ($($item:item)*) => { _cfg_common!{ "irq" $($item)* } }
+}
+
 macro_rules! cfg_alloc {
     ($($item:item)*) => { _cfg_common!{ "alloc" $($item)* } }
 }
@@ -105,6 +109,14 @@ macro_rules! cfg_display {
     ($($item:item)*) => { _cfg_common!{ "display" $($item)* } }
 }
 
+macro_rules! cfg_input {
+    ($($item:item)*) => { _cfg_common!{ "input" $($item)* } }
+}
+
 macro_rules! cfg_task {
     ($($item:item)*) => { _cfg_common!{ "multitask" $($item)* } }
 }
+
+macro_rules! cfg_async {
+    ($($item:item)*) => { _cfg_common!{ "async" $($item)* } }
+}
@@ -48,11 +48,20 @@ typedef struct {{
             "pthread_attr_t",
             "pthread_mutex_t",
             "pthread_mutexattr_t",
+            "pthread_cond_t",
+            "pthread_condattr_t",
+            "pthread_rwlock_t",
+            "pthread_rwlockattr_t",
+            "pthread_key_t",
+            "pthread_once_t",
             "epoll_event",
             "iovec",
             "clockid_t",
             "rlimit",
+            "rusage",
             "aibuf",
+            "termios",
+            "fb_.*",
         ];
         let allow_vars = [
             "CLOCK_.*",
@@ -63,11 +72,18 @@ typedef struct {{
             "FD_.*",
             "F_.*",
             "_SC_.*",
+            "RB_.*",
+            "GRND_.*",
+            "TCSA.*",
+            "CBAUD",
             "EPOLL_CTL_.*",
             "EPOLL.*",
             "RLIMIT_.*",
+            "RUSAGE_.*",
             "EAI_.*",
             "MAXADDRS",
+            "PTHREAD_.*",
+            "FB_.*",
         ];
 
         #[derive(Debug)]
@@ -1,9 +1,12 @@
 #include <fcntl.h>
+#include <linux/fb.h>
 #include <netdb.h>
 #include <netinet/in.h>
 #include <pthread.h>
 #include <stddef.h>
 #include <sys/epoll.h>
+#include <sys/random.h>
+#include <sys/reboot.h>
 #include <sys/resource.h>
 #include <sys/select.h>
 #include <sys/socket.h>
@@ -11,5 +14,6 @@
 #include <sys/time.h>
 #include <sys/types.h>
 #include <sys/uio.h>
+#include <termios.h>
 #include <time.h>
 #include <unistd.h>
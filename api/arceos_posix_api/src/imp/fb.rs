@@ -0,0 +1,283 @@
+use alloc::sync::Arc;
+use core::ffi::{c_char, c_int};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use axdisplay::{DisplayMode, PixelFormat, Rect};
+use axerrno::{LinuxError, LinuxResult};
+use axio::{PollState, SeekFrom};
+
+use super::fd_ops::{FileLike, add_file_like, get_file_like};
+use crate::ctypes;
+
+/// The path prefix of framebuffer devices, followed by the display ID.
+const FB_DEV_PREFIX: &str = "/dev/fb";
+
+/// The major device number of framebuffer devices, as on Linux.
+const FB_MAJOR: u64 = 29;
+
+/// An opened framebuffer device, i.e., `/dev/fbN`.
+///
+/// Reads and writes access the framebuffer to draw into at the file position,
+/// and the rows written are flushed immediately.
+pub struct FramebufferDevice {
+    id: usize,
+    pos: AtomicUsize,
+}
+
+impl FramebufferDevice {
+    pub(crate) fn seek(&self, pos: SeekFrom) -> LinuxResult<u64> {
+        let size = axdisplay::info(self.id)?.fb_size as i64;
+        let new_pos = match pos {
+            SeekFrom::Start(pos) => pos as i64,
+            SeekFrom::Current(off) => self.pos.load(Ordering::Relaxed) as i64 + off,
+            SeekFrom::End(off) => size + off,
+        };
+        if new_pos < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        self.pos.store(new_pos as usize, Ordering::Relaxed);
+        Ok(new_pos as u64)
+    }
+}
+
+impl FileLike for FramebufferDevice {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        let pos = self.pos.load(Ordering::Relaxed);
+        let (pos, n) = axdisplay::with_framebuffer(self.id, |fb, _| {
+            let pos = pos.min(fb.len());
+            let n = buf.len().min(fb.len() - pos);
+            buf[..n].copy_from_slice(&fb[pos..pos + n]);
+            ((pos, n), None)
+        })?;
+        self.pos.store(pos + n, Ordering::Relaxed);
+        Ok(n)
+    }
+
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let pos = self.pos.load(Ordering::Relaxed);
+        // The rows written are flushed before the framebuffer is released.
+        let n = axdisplay::with_framebuffer(self.id, |fb, mode| {
+            if pos >= fb.len() {
+                return (Err(LinuxError::ENOSPC), None);
+            }
+            let n = buf.len().min(fb.len() - pos);
+            fb[pos..pos + n].copy_from_slice(&buf[..n]);
+            let first_row = pos / mode.stride();
+            let last_row = (pos + n - 1) / mode.stride();
+            let rect = Rect {
+                x: 0,
+                y: first_row as u32,
+                width: mode.width,
+                height: (last_row - first_row + 1) as u32,
+            };
+            (Ok(n), Some(rect))
+        })??;
+        self.pos.store(pos + n, Ordering::Relaxed);
+        Ok(n)
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        let st_mode = 0o20000 | 0o660u32; // S_IFCHR | rw-rw----
+        Ok(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode,
+            st_rdev: (FB_MAJOR << 8) | self.id as u64,
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: true,
+            writable: true,
+        })
+    }
+
+    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+        Ok(())
+    }
+
+    fn flush(&self) -> LinuxResult<usize> {
+        axdisplay::flush(self.id)?;
+        Ok(0)
+    }
+}
+
+/// Opens the framebuffer device if `path` is `/dev/fbN`, and returns the file
+/// descriptor.
+///
+/// Returns `None` if `path` is not a framebuffer device.
+pub(crate) fn open_framebuffer(path: &str, _flags: c_int) -> Option<LinuxResult<c_int>> {
+    let id = path.strip_prefix(FB_DEV_PREFIX)?.parse::<usize>().ok()?;
+    Some(
+        axdisplay::device_name(id)
+            .map_err(|_| LinuxError::ENOENT)
+            .and_then(|_| {
+                add_file_like(Arc::new(FramebufferDevice {
+                    id,
+                    pos: AtomicUsize::new(0),
+                }))
+            }),
+    )
+}
+
+fn framebuffer_from_fd(fd: c_int) -> LinuxResult<Arc<FramebufferDevice>> {
+    get_file_like(fd)?
+        .into_any()
+        .downcast::<FramebufferDevice>()
+        .map_err(|_| LinuxError::ENOTTY)
+}
+
+/// Returns the color channels of `struct fb_var_screeninfo` for the format,
+/// in the order red, green, blue and transparency.
+fn channels(format: PixelFormat) -> [ctypes::fb_bitfield; 4] {
+    let field = |offset, length| ctypes::fb_bitfield {
+        offset,
+        length,
+        msb_right: 0,
+    };
+    let alpha = match format {
+        PixelFormat::Argb8888 | PixelFormat::Abgr8888 => 8,
+        PixelFormat::Xrgb8888 | PixelFormat::Xbgr8888 => 0,
+    };
+    match format {
+        PixelFormat::Argb8888 | PixelFormat::Xrgb8888 => {
+            [field(16, 8), field(8, 8), field(0, 8), field(24, alpha)]
+        }
+        PixelFormat::Abgr8888 | PixelFormat::Xbgr8888 => {
+            [field(0, 8), field(8, 8), field(16, 8), field(24, alpha)]
+        }
+    }
+}
+
+/// Returns the variable screen information of the display.
+///
+/// The back buffer of double buffering is not exposed as a virtual area below
+/// the visible one, as only the buffer to draw into is mapped, so the virtual
+/// resolution is always the visible one.
+fn var_screeninfo(id: usize) -> LinuxResult<ctypes::fb_var_screeninfo> {
+    let mode = axdisplay::mode(id)?;
+    let [red, green, blue, transp] = channels(mode.format);
+    Ok(ctypes::fb_var_screeninfo {
+        xres: mode.width,
+        yres: mode.height,
+        xres_virtual: mode.width,
+        yres_virtual: mode.height,
+        bits_per_pixel: (mode.format.bytes_per_pixel() * 8) as u32,
+        red,
+        green,
+        blue,
+        transp,
+        activate: ctypes::FB_ACTIVATE_NOW,
+        // The physical size is unknown.
+        height: u32::MAX,
+        width: u32::MAX,
+        ..Default::default()
+    })
+}
+
+fn fix_screeninfo(id: usize) -> LinuxResult<ctypes::fb_fix_screeninfo> {
+    let info = axdisplay::info(id)?;
+    let mode = axdisplay::mode(id)?;
+    let mut fix = ctypes::fb_fix_screeninfo {
+        smem_start: info.fb_base_vaddr as _,
+        smem_len: info.fb_size as u32,
+        type_: ctypes::FB_TYPE_PACKED_PIXELS,
+        visual: ctypes::FB_VISUAL_TRUECOLOR,
+        line_length: mode.stride() as u32,
+        ..Default::default()
+    };
+    let name = axdisplay::device_name(id)?.as_bytes();
+    for (dst, &src) in fix.id.iter_mut().zip(name.iter().take(15)) {
+        *dst = src as c_char;
+    }
+    Ok(fix)
+}
+
+/// Switches to the mode in `var`, which is 32 bits per pixel. Double buffering
+/// is enabled if `yres_virtual` is at least twice `yres`.
+///
+/// If double buffering cannot be set, the previous mode is restored.
+fn set_var_screeninfo(id: usize, var: &ctypes::fb_var_screeninfo) -> LinuxResult {
+    if var.bits_per_pixel != 32 {
+        return Err(LinuxError::EINVAL);
+    }
+    let current = axdisplay::mode(id)?;
+    let alpha = var.transp.length != 0;
+    let format = match (var.red.offset, var.blue.offset) {
+        (16, 0) if alpha => PixelFormat::Argb8888,
+        (16, 0) => PixelFormat::Xrgb8888,
+        (0, 16) if alpha => PixelFormat::Abgr8888,
+        (0, 16) => PixelFormat::Xbgr8888,
+        _ if var.red.length == 0 && var.blue.length == 0 => current.format,
+        _ => return Err(LinuxError::EINVAL),
+    };
+    axdisplay::set_mode(
+        id,
+        DisplayMode {
+            width: var.xres,
+            height: var.yres,
+            format,
+        },
+    )?;
+    let double_buffered = var.yres_virtual >= var.yres.saturating_mul(2);
+    if let Err(e) = axdisplay::set_double_buffered(id, double_buffered) {
+        if let Err(e) = axdisplay::set_mode(id, current) {
+            warn!("failed to restore the mode of display {}: {:?}", id, e);
+        }
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// `FBIOGET_VSCREENINFO`: gets the variable screen information.
+pub(crate) fn get_vscreeninfo(fd: c_int, var: *mut ctypes::fb_var_screeninfo) -> LinuxResult {
+    let fb = framebuffer_from_fd(fd)?;
+    if var.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    unsafe { var.write(var_screeninfo(fb.id)?) };
+    Ok(())
+}
+
+/// `FBIOPUT_VSCREENINFO`: sets the variable screen information, and writes
+/// back the one in effect.
+pub(crate) fn put_vscreeninfo(fd: c_int, var: *mut ctypes::fb_var_screeninfo) -> LinuxResult {
+    let fb = framebuffer_from_fd(fd)?;
+    if var.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    set_var_screeninfo(fb.id, unsafe { &*var })?;
+    fb.pos.store(0, Ordering::Relaxed);
+    unsafe { var.write(var_screeninfo(fb.id)?) };
+    Ok(())
+}
+
+/// `FBIOGET_FSCREENINFO`: gets the fixed screen information.
+pub(crate) fn get_fscreeninfo(fd: c_int, fix: *mut ctypes::fb_fix_screeninfo) -> LinuxResult {
+    let fb = framebuffer_from_fd(fd)?;
+    if fix.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    unsafe { fix.write(fix_screeninfo(fb.id)?) };
+    Ok(())
+}
+
+/// `FBIOPAN_DISPLAY`: flips the buffers, i.e., shows the buffer drawn into, if
+/// double buffering is enabled, or flushes the whole framebuffer otherwise.
+///
+/// There is nothing to pan since the virtual resolution is the visible one
+/// (see [`var_screeninfo`]), so the offsets are ignored.
+pub(crate) fn pan_display(fd: c_int) -> LinuxResult {
+    let fb = framebuffer_from_fd(fd)?;
+    axdisplay::flip(fb.id)?;
+    Ok(())
+}
@@ -133,6 +133,24 @@ pub fn sys_open(filename: *const c_char, flags: c_int, mode: ctypes::mode_t) ->
     let filename = char_ptr_to_str(filename);
     debug!("sys_open <= {:?} {:#o} {:#o}", filename, flags, mode);
     syscall_body!(sys_open, {
+        #[cfg(feature = "input")]
+        if let Ok(path) = filename
+            && let Some(res) = super::input::open_input_device(path, flags)
+        {
+            return res;
+        }
+        #[cfg(feature = "console")]
+        if let Ok(path) = filename
+            && let Some(res) = super::hvc::open_console_port(path, flags)
+        {
+            return res;
+        }
+        #[cfg(feature = "display")]
+        if let Ok(path) = filename
+            && let Some(res) = super::fb::open_framebuffer(path, flags)
+        {
+            return res;
+        }
         add_file_or_directory_fd(
             axfs::fops::File::open,
             axfs::fops::Directory::open_dir,
@@ -226,6 +244,13 @@ pub fn sys_lseek(fd: c_int, offset: ctypes::off_t, whence: c_int) -> ctypes::off
             2 => SeekFrom::End(offset as _),
             _ => return Err(LinuxError::EINVAL),
         };
+        #[cfg(feature = "display")]
+        if let Ok(fb) = get_file_like(fd)?
+            .into_any()
+            .downcast::<super::fb::FramebufferDevice>()
+        {
+            return fb.seek(pos);
+        }
         let off = File::from_fd(fd)?.inner.lock().seek(pos)?;
         Ok(off)
     })
@@ -0,0 +1,100 @@
+use alloc::sync::Arc;
+use core::ffi::c_int;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+
+use super::fd_ops::{FileLike, add_file_like};
+use crate::ctypes;
+
+/// The directory of console ports, whose names start with `hvc`.
+const DEV_DIR: &str = "/dev/";
+
+/// The major device number of console ports, and the minor number of the
+/// first port, as on Linux.
+const HVC_MAJOR: u64 = 229;
+const HVC_MINOR_BASE: u64 = 0;
+
+/// An opened VirtIO console port, i.e., `/dev/hvcN`.
+///
+/// Unlike the console, it's not processed by the line discipline.
+pub struct ConsolePort {
+    id: usize,
+    /// The number in the name of the port, i.e., `N` of `hvcN`.
+    number: u64,
+    nonblocking: AtomicBool,
+}
+
+impl FileLike for ConsolePort {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let n = axtty::hvc::read_port(self.id, buf)?;
+            if n > 0 {
+                return Ok(n);
+            }
+            if self.nonblocking.load(Ordering::Relaxed) {
+                return Err(LinuxError::EAGAIN);
+            }
+            // Ports do not raise input interrupts.
+            crate::sys_sched_yield();
+        }
+    }
+
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        axtty::hvc::write_port(self.id, buf)?;
+        Ok(buf.len())
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        let st_mode = 0o20000 | 0o620u32; // S_IFCHR | rw--w----
+        Ok(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode,
+            st_rdev: (HVC_MAJOR << 8) | (HVC_MINOR_BASE + self.number),
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        // Input can not be peeked, so the port is always reported readable.
+        Ok(PollState {
+            readable: true,
+            writable: true,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn flush(&self) -> LinuxResult<usize> {
+        Ok(0)
+    }
+}
+
+/// Opens the console port if `path` is `/dev/hvcN`, and returns the file
+/// descriptor.
+///
+/// Returns `None` if `path` is not a console port.
+pub(crate) fn open_console_port(path: &str, flags: c_int) -> Option<LinuxResult<c_int>> {
+    let name = path.strip_prefix(DEV_DIR)?;
+    let number = name.strip_prefix("hvc")?.parse::<u64>().ok()?;
+    let Some(id) = axtty::hvc::find_port(name) else {
+        return Some(Err(LinuxError::ENOENT));
+    };
+    Some(add_file_like(Arc::new(ConsolePort {
+        id,
+        number,
+        nonblocking: AtomicBool::new(flags & ctypes::O_NONBLOCK as c_int != 0),
+    })))
+}
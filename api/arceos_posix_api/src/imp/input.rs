@@ -0,0 +1,129 @@
+use alloc::sync::Arc;
+use core::ffi::c_int;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use axerrno::{LinuxError, LinuxResult};
+use axinput::InputEvent;
+use axio::PollState;
+
+use super::fd_ops::{FileLike, add_file_like};
+use crate::ctypes;
+
+/// The path prefix of input devices, followed by the device ID.
+const INPUT_DEV_PREFIX: &str = "/dev/input/event";
+
+/// The major device number of input devices, and the minor number of the
+/// first event device, as on Linux.
+const INPUT_MAJOR: u64 = 13;
+const EVENT_MINOR_BASE: u64 = 64;
+
+/// The maximum number of events read at a time.
+const MAX_EVENTS: usize = 16;
+
+/// The event record read from an input device, i.e., `struct input_event` on
+/// Linux.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct RawInputEvent {
+    time: ctypes::timeval,
+    type_: u16,
+    code: u16,
+    value: i32,
+}
+
+impl From<InputEvent> for RawInputEvent {
+    fn from(event: InputEvent) -> Self {
+        Self {
+            time: event.time.into(),
+            type_: event.event_type,
+            code: event.code,
+            value: event.value,
+        }
+    }
+}
+
+/// An opened input device, i.e., `/dev/input/eventN`.
+pub struct InputDevice {
+    id: usize,
+    nonblocking: AtomicBool,
+}
+
+impl FileLike for InputDevice {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        const EVENT_SIZE: usize = core::mem::size_of::<RawInputEvent>();
+        let max_events = (buf.len() / EVENT_SIZE).min(MAX_EVENTS);
+        if max_events == 0 {
+            return Err(LinuxError::EINVAL);
+        }
+
+        let mut events = [InputEvent {
+            time: Default::default(),
+            event_type: 0,
+            code: 0,
+            value: 0,
+        }; MAX_EVENTS];
+        let n = axinput::read_events(
+            self.id,
+            &mut events[..max_events],
+            self.nonblocking.load(Ordering::Relaxed),
+        )?;
+        for (event, chunk) in events[..n].iter().zip(buf.chunks_exact_mut(EVENT_SIZE)) {
+            let event = RawInputEvent::from(*event);
+            unsafe { (chunk.as_mut_ptr() as *mut RawInputEvent).write_unaligned(event) };
+        }
+        Ok(n * EVENT_SIZE)
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        let st_mode = 0o20000 | 0o660u32; // S_IFCHR | rw-rw----
+        Ok(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode,
+            st_rdev: (INPUT_MAJOR << 8) | (EVENT_MINOR_BASE + self.id as u64),
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: axinput::has_events(self.id)?,
+            writable: false,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn flush(&self) -> LinuxResult<usize> {
+        Ok(0)
+    }
+}
+
+/// Opens the input device if `path` is `/dev/input/eventN`, and returns the
+/// file descriptor.
+///
+/// Returns `None` if `path` is not an input device.
+pub(crate) fn open_input_device(path: &str, flags: c_int) -> Option<LinuxResult<c_int>> {
+    let id = path.strip_prefix(INPUT_DEV_PREFIX)?.parse::<usize>().ok()?;
+    Some(
+        axinput::device_name(id)
+            .map_err(|_| LinuxError::ENOENT)
+            .and_then(|_| {
+                add_file_like(Arc::new(InputDevice {
+                    id,
+                    nonblocking: AtomicBool::new(flags & ctypes::O_NONBLOCK as c_int != 0),
+                }))
+            }),
+    )
+}
@@ -147,6 +147,43 @@ pub unsafe fn sys_ioctl(fd: c_int, cmd: c_uint, arg: c_ulong) -> ctypes::ssize_t
                 // Follow the implementation of fcntl()
                 0
             }
+            IoctlCmd::TCGETS => {
+                super::tty::get_termios(fd, arg as *mut ctypes::termios)?;
+                return Ok(0);
+            }
+            IoctlCmd::TCSETS | IoctlCmd::TCSETSW | IoctlCmd::TCSETSF => {
+                let optional_actions = match cmd {
+                    IoctlCmd::TCSETS => ctypes::TCSANOW,
+                    IoctlCmd::TCSETSW => ctypes::TCSADRAIN,
+                    _ => ctypes::TCSAFLUSH,
+                };
+                super::tty::set_termios(
+                    fd,
+                    optional_actions as c_int,
+                    arg as *const ctypes::termios,
+                )?;
+                return Ok(0);
+            }
+            #[cfg(feature = "display")]
+            IoctlCmd::FBIOGET_VSCREENINFO => {
+                super::fb::get_vscreeninfo(fd, arg as *mut ctypes::fb_var_screeninfo)?;
+                return Ok(0);
+            }
+            #[cfg(feature = "display")]
+            IoctlCmd::FBIOPUT_VSCREENINFO => {
+                super::fb::put_vscreeninfo(fd, arg as *mut ctypes::fb_var_screeninfo)?;
+                return Ok(0);
+            }
+            #[cfg(feature = "display")]
+            IoctlCmd::FBIOGET_FSCREENINFO => {
+                super::fb::get_fscreeninfo(fd, arg as *mut ctypes::fb_fix_screeninfo)?;
+                return Ok(0);
+            }
+            #[cfg(feature = "display")]
+            IoctlCmd::FBIOPAN_DISPLAY => {
+                super::fb::pan_display(fd)?;
+                return Ok(0);
+            }
             // FIXME: ioctl operations involving blocking I/O should be able to restart if interrupted
             _ => {
                 // let file_owned = file.to_owned();
@@ -210,4 +247,16 @@ pub enum IoctlCmd {
     TIOCGPTPEER = 0x40045441,
     /// Get tdx report using TDCALL
     TDXGETREPORT = 0xc4405401,
+    /// Get the variable screen information of a framebuffer
+    #[allow(non_camel_case_types)]
+    FBIOGET_VSCREENINFO = 0x4600,
+    /// Set the variable screen information (mode) of a framebuffer
+    #[allow(non_camel_case_types)]
+    FBIOPUT_VSCREENINFO = 0x4601,
+    /// Get the fixed screen information of a framebuffer
+    #[allow(non_camel_case_types)]
+    FBIOGET_FSCREENINFO = 0x4602,
+    /// Pan the display of a framebuffer, i.e., flip the buffers
+    #[allow(non_camel_case_types)]
+    FBIOPAN_DISPLAY = 0x4606,
 }
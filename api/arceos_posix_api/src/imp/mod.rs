@@ -5,11 +5,18 @@ pub mod resources;
 pub mod sys;
 pub mod task;
 pub mod time;
+pub mod tty;
 
+#[cfg(feature = "display")]
+pub mod fb;
 #[cfg(feature = "fd")]
 pub mod fd_ops;
 #[cfg(feature = "fs")]
 pub mod fs;
+#[cfg(feature = "console")]
+pub mod hvc;
+#[cfg(feature = "input")]
+pub mod input;
 #[cfg(any(feature = "select", feature = "epoll"))]
 pub mod io_mpx;
 #[cfg(feature = "net")]
@@ -0,0 +1,199 @@
+use crate::{ctypes, utils::check_null_mut_ptr};
+
+use axerrno::{LinuxError, LinuxResult};
+
+use core::ffi::c_int;
+use core::mem::size_of;
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::time::Duration;
+
+use super::{Pthread, mutex::PthreadMutex, testcancel, wait};
+
+static_assertions::const_assert!(size_of::<PthreadCond>() <= size_of::<ctypes::pthread_cond_t>());
+
+/// The in-memory layout of `pthread_cond_t`.
+///
+/// An all-zero value is a valid condition variable using `CLOCK_REALTIME`, so
+/// that `PTHREAD_COND_INITIALIZER` works without calling into Rust.
+#[repr(C)]
+pub struct PthreadCond {
+    /// Bumped on every signal or broadcast.
+    seq: AtomicU32,
+    _reserved: [u32; 3],
+    /// The clock used by timed waits, matches `_c_clock` in `pthread.h`.
+    clock: u32,
+}
+
+impl PthreadCond {
+    const fn new(clock: u32) -> Self {
+        Self {
+            seq: AtomicU32::new(0),
+            _reserved: [0; 3],
+            clock,
+        }
+    }
+
+    fn addr(&self) -> usize {
+        self as *const _ as usize
+    }
+
+    /// Converts an absolute `abstime` on the clock of this condition variable
+    /// to a timeout relative to now.
+    fn timeout_of(&self, abstime: &ctypes::timespec) -> LinuxResult<Duration> {
+        if abstime.tv_sec < 0 || !(0..1_000_000_000).contains(&abstime.tv_nsec) {
+            return Err(LinuxError::EINVAL);
+        }
+        let now = if self.clock == ctypes::CLOCK_MONOTONIC {
+            axhal::time::monotonic_time()
+        } else {
+            axhal::time::wall_time()
+        };
+        Ok(Duration::from(*abstime).saturating_sub(now))
+    }
+
+    fn wait(&self, mutex: &PthreadMutex, timeout: Option<Duration>) -> LinuxResult {
+        testcancel_locked(mutex);
+        if timeout.is_some_and(|dur| dur.is_zero()) {
+            return Err(LinuxError::ETIMEDOUT);
+        }
+
+        let seq = self.seq.load(Ordering::Acquire);
+        let thread = Pthread::current();
+        if let Some(thread) = thread {
+            thread.set_waiting_on(self.addr());
+        }
+        mutex.unlock()?;
+        let timed_out = wait::wait_until(self.addr(), timeout, || {
+            self.seq.load(Ordering::Acquire) != seq || thread.is_some_and(|t| t.should_cancel())
+        });
+        if let Some(thread) = thread {
+            thread.set_waiting_on(0);
+        }
+        mutex.lock()?;
+        testcancel_locked(mutex);
+
+        if timed_out {
+            Err(LinuxError::ETIMEDOUT)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn notify(&self, count: usize) {
+        self.seq.fetch_add(1, Ordering::Release);
+        wait::wake(self.addr(), count);
+    }
+}
+
+/// Acts upon a pending cancellation request of the current thread, which
+/// holds `mutex`.
+///
+/// POSIX requires the mutex to be re-acquired when the wait is cancelled,
+/// and a cleanup handler of the thread to release it. Cleanup handlers are
+/// not supported, so the mutex is released here instead, lest the cancelled
+/// thread exits owning it.
+fn testcancel_locked(mutex: &PthreadMutex) {
+    if Pthread::current().is_some_and(|t| t.should_cancel()) {
+        let _ = mutex.unlock();
+        testcancel();
+    }
+}
+
+/// Initialize a condition variable.
+pub fn sys_pthread_cond_init(
+    cond: *mut ctypes::pthread_cond_t,
+    attr: *const ctypes::pthread_condattr_t,
+) -> c_int {
+    debug!("sys_pthread_cond_init <= {:#x}", cond as usize);
+    syscall_body!(sys_pthread_cond_init, {
+        check_null_mut_ptr(cond)?;
+        let clock = if attr.is_null() {
+            ctypes::CLOCK_REALTIME
+        } else {
+            unsafe { (*attr).__attr & 0x7fff_ffff }
+        };
+        unsafe {
+            cond.cast::<PthreadCond>().write(PthreadCond::new(clock));
+        }
+        Ok(0)
+    })
+}
+
+/// Destroy a condition variable.
+pub fn sys_pthread_cond_destroy(cond: *mut ctypes::pthread_cond_t) -> c_int {
+    debug!("sys_pthread_cond_destroy <= {:#x}", cond as usize);
+    syscall_body!(sys_pthread_cond_destroy, {
+        check_null_mut_ptr(cond)?;
+        wait::remove(cond as usize);
+        Ok(0)
+    })
+}
+
+/// Atomically unlock the mutex and wait on the condition variable, the mutex
+/// is locked again before returning.
+///
+/// It's a cancellation point. Unlike POSIX, a cancelled thread exits with the
+/// mutex unlocked, since cleanup handlers are not supported.
+pub fn sys_pthread_cond_wait(
+    cond: *mut ctypes::pthread_cond_t,
+    mutex: *mut ctypes::pthread_mutex_t,
+) -> c_int {
+    debug!(
+        "sys_pthread_cond_wait <= {:#x}, {:#x}",
+        cond as usize, mutex as usize
+    );
+    syscall_body!(sys_pthread_cond_wait, {
+        check_null_mut_ptr(cond)?;
+        check_null_mut_ptr(mutex)?;
+        unsafe {
+            (*cond.cast::<PthreadCond>()).wait(&*mutex.cast::<PthreadMutex>(), None)?;
+        }
+        Ok(0)
+    })
+}
+
+/// Like [`sys_pthread_cond_wait`], but returns `ETIMEDOUT` if the condition
+/// variable is not signaled before the absolute time `abstime`.
+pub unsafe fn sys_pthread_cond_timedwait(
+    cond: *mut ctypes::pthread_cond_t,
+    mutex: *mut ctypes::pthread_mutex_t,
+    abstime: *const ctypes::timespec,
+) -> c_int {
+    debug!(
+        "sys_pthread_cond_timedwait <= {:#x}, {:#x}",
+        cond as usize, mutex as usize
+    );
+    syscall_body!(sys_pthread_cond_timedwait, {
+        check_null_mut_ptr(cond)?;
+        check_null_mut_ptr(mutex)?;
+        if abstime.is_null() {
+            return Err(LinuxError::EINVAL);
+        }
+        unsafe {
+            let cond = &*cond.cast::<PthreadCond>();
+            let timeout = cond.timeout_of(&*abstime)?;
+            cond.wait(&*mutex.cast::<PthreadMutex>(), Some(timeout))?;
+        }
+        Ok(0)
+    })
+}
+
+/// Wake up one thread waiting on the condition variable.
+pub fn sys_pthread_cond_signal(cond: *mut ctypes::pthread_cond_t) -> c_int {
+    debug!("sys_pthread_cond_signal <= {:#x}", cond as usize);
+    syscall_body!(sys_pthread_cond_signal, {
+        check_null_mut_ptr(cond)?;
+        unsafe { (*cond.cast::<PthreadCond>()).notify(1) };
+        Ok(0)
+    })
+}
+
+/// Wake up all threads waiting on the condition variable.
+pub fn sys_pthread_cond_broadcast(cond: *mut ctypes::pthread_cond_t) -> c_int {
+    debug!("sys_pthread_cond_broadcast <= {:#x}", cond as usize);
+    syscall_body!(sys_pthread_cond_broadcast, {
+        check_null_mut_ptr(cond)?;
+        unsafe { (*cond.cast::<PthreadCond>()).notify(usize::MAX) };
+        Ok(0)
+    })
+}
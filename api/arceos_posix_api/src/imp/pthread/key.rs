@@ -0,0 +1,143 @@
+use alloc::vec::Vec;
+use core::ffi::{c_int, c_void};
+
+use axerrno::LinuxError;
+use axsync::spin::SpinNoIrq;
+
+use super::Pthread;
+use crate::{ctypes, utils::check_null_mut_ptr};
+
+/// Maximum number of keys, `PTHREAD_KEYS_MAX` in `limits.h`.
+const PTHREAD_KEYS_MAX: usize = 128;
+/// Maximum rounds of calling destructors at thread exit,
+/// `PTHREAD_DESTRUCTOR_ITERATIONS` in `limits.h`.
+const PTHREAD_DESTRUCTOR_ITERATIONS: usize = 4;
+
+type Destructor = extern "C" fn(*mut c_void);
+
+#[derive(Clone, Copy)]
+struct KeySlot {
+    in_use: bool,
+    /// Bumped every time the key is created, so that values set for a
+    /// deleted key are not visible through a new key in the same slot.
+    generation: u64,
+    destructor: Option<Destructor>,
+}
+
+static KEYS: SpinNoIrq<[KeySlot; PTHREAD_KEYS_MAX]> = SpinNoIrq::new(
+    [KeySlot {
+        in_use: false,
+        generation: 0,
+        destructor: None,
+    }; PTHREAD_KEYS_MAX],
+);
+
+/// A thread-specific value, tagged with the generation of its key.
+pub(super) struct SpecificValue {
+    generation: u64,
+    value: *mut c_void,
+}
+
+fn key_generation(key: ctypes::pthread_key_t) -> Option<u64> {
+    KEYS.lock()
+        .get(key as usize)
+        .filter(|slot| slot.in_use)
+        .map(|slot| slot.generation)
+}
+
+/// Calls the destructors of all non-null thread-specific values of `thread`.
+pub(super) fn run_destructors(thread: &Pthread) {
+    for _ in 0..PTHREAD_DESTRUCTOR_ITERATIONS {
+        let pending: Vec<_> = {
+            let keys = KEYS.lock();
+            let mut specific = thread.specific.lock();
+            let pending = specific
+                .iter()
+                .filter_map(|(&key, v)| {
+                    let slot = &keys[key as usize];
+                    let destructor = slot.destructor?;
+                    (slot.in_use && slot.generation == v.generation && !v.value.is_null())
+                        .then_some((destructor, v.value))
+                })
+                .collect();
+            specific.clear();
+            pending
+        };
+        if pending.is_empty() {
+            break;
+        }
+        // Destructors may set new values, so call them without holding locks.
+        for (destructor, value) in pending {
+            destructor(value);
+        }
+    }
+}
+
+/// Create a thread-specific data key, with an optional destructor called at
+/// thread exit.
+pub unsafe fn sys_pthread_key_create(
+    key: *mut ctypes::pthread_key_t,
+    destructor: Option<Destructor>,
+) -> c_int {
+    debug!("sys_pthread_key_create <= {:#x}", key as usize);
+    syscall_body!(sys_pthread_key_create, {
+        check_null_mut_ptr(key)?;
+        let mut keys = KEYS.lock();
+        let (index, slot) = keys
+            .iter_mut()
+            .enumerate()
+            .find(|(_, slot)| !slot.in_use)
+            .ok_or(LinuxError::EAGAIN)?;
+        slot.in_use = true;
+        slot.generation += 1;
+        slot.destructor = destructor;
+        unsafe { *key = index as ctypes::pthread_key_t };
+        Ok(0)
+    })
+}
+
+/// Delete a thread-specific data key.
+///
+/// Destructors are not called, values of the key in all threads are dropped.
+pub fn sys_pthread_key_delete(key: ctypes::pthread_key_t) -> c_int {
+    debug!("sys_pthread_key_delete <= {}", key);
+    syscall_body!(sys_pthread_key_delete, {
+        let mut keys = KEYS.lock();
+        match keys.get_mut(key as usize) {
+            Some(slot) if slot.in_use => {
+                slot.in_use = false;
+                slot.destructor = None;
+                Ok(0)
+            }
+            _ => Err(LinuxError::EINVAL),
+        }
+    })
+}
+
+/// Get the value of the key in the current thread, or null if not set.
+pub fn sys_pthread_getspecific(key: ctypes::pthread_key_t) -> *mut c_void {
+    let (Some(generation), Some(thread)) = (key_generation(key), Pthread::current()) else {
+        return core::ptr::null_mut();
+    };
+    match thread.specific.lock().get(&key) {
+        Some(v) if v.generation == generation => v.value,
+        _ => core::ptr::null_mut(),
+    }
+}
+
+/// Set the value of the key in the current thread.
+pub fn sys_pthread_setspecific(key: ctypes::pthread_key_t, value: *const c_void) -> c_int {
+    debug!("sys_pthread_setspecific <= {}, {:#x}", key, value as usize);
+    syscall_body!(sys_pthread_setspecific, {
+        let generation = key_generation(key).ok_or(LinuxError::EINVAL)?;
+        let thread = Pthread::current().ok_or(LinuxError::EINVAL)?;
+        thread.specific.lock().insert(
+            key,
+            SpecificValue {
+                generation,
+                value: value as *mut c_void,
+            },
+        );
+        Ok(0)
+    })
+}
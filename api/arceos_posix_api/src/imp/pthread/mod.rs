@@ -1,70 +1,161 @@
-use alloc::{boxed::Box, collections::BTreeMap, sync::Arc};
+use alloc::{boxed::Box, collections::BTreeMap, string::String};
 use core::cell::UnsafeCell;
-use core::ffi::{c_int, c_void};
+use core::ffi::{c_char, c_int, c_void};
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
 
 use axerrno::{LinuxError, LinuxResult};
-use axtask::AxTaskRef;
+use axsync::spin::SpinNoIrq;
+use axtask::{AxCpuMask, AxTaskRef, TaskInner};
 use spin::RwLock;
 
 use crate::ctypes;
+use crate::utils::char_ptr_to_str;
 
+pub mod condvar;
+pub mod key;
 pub mod mutex;
+pub mod once;
+pub mod rwlock;
+mod wait;
+
+#[cfg(test)]
+mod tests;
+
+/// The return value of a cancelled thread, `PTHREAD_CANCELED` in `pthread.h`.
+const PTHREAD_CANCELED: *mut c_void = usize::MAX as *mut c_void;
+
+/// Indices of the fields in `pthread_attr_t`, must match the `_a_*` macros
+/// in `pthread.h`.
+const ATTR_STACKSIZE: usize = 0;
+const ATTR_CPUMASK: usize = 5;
+const ATTR_DETACH: usize = 3 * size_of::<usize>() / size_of::<c_int>();
+
+/// Maximum length of a thread name, excluding the NUL terminator.
+const THREAD_NAME_MAX: usize = 15;
+
+const STATE_JOINABLE: u8 = 0;
+const STATE_DETACHED: u8 = 1;
+const STATE_EXITED: u8 = 2;
 
 lazy_static::lazy_static! {
     static ref TID_TO_PTHREAD: RwLock<BTreeMap<u64, ForceSendSync<ctypes::pthread_t>>> = {
         let mut map = BTreeMap::new();
         let main_task = axtask::current();
         let main_tid = main_task.id().as_u64();
-        let main_thread = Pthread {
-            inner: main_task.as_task_ref().clone(),
-            retval: Arc::new(Packet {
-                result: UnsafeCell::new(core::ptr::null_mut()),
-            }),
-        };
+        let main_thread = Pthread::new(main_task.as_task_ref().clone(), false);
         let ptr = Box::into_raw(Box::new(main_thread)) as *mut c_void;
         map.insert(main_tid, ForceSendSync(ptr));
         RwLock::new(map)
     };
 }
 
-struct Packet<T> {
-    result: UnsafeCell<T>,
+/// Thread creation parameters parsed from `pthread_attr_t`.
+struct ThreadAttr {
+    stack_size: usize,
+    detached: bool,
+    cpumask: Option<AxCpuMask>,
 }
 
-unsafe impl<T> Send for Packet<T> {}
-unsafe impl<T> Sync for Packet<T> {}
+impl ThreadAttr {
+    fn parse(attr: *const ctypes::pthread_attr_t) -> LinuxResult<Self> {
+        let mut res = Self {
+            stack_size: axconfig::TASK_STACK_SIZE,
+            detached: false,
+            cpumask: None,
+        };
+        if attr.is_null() {
+            return Ok(res);
+        }
+
+        let (stack_size, detach, mask_bits) = unsafe {
+            let u = &(*attr).__u;
+            (
+                u.__s[ATTR_STACKSIZE],
+                u.__i[ATTR_DETACH],
+                u.__s[ATTR_CPUMASK],
+            )
+        };
+        if stack_size != 0 {
+            res.stack_size = stack_size as usize;
+        }
+        res.detached = detach as u32 == ctypes::PTHREAD_CREATE_DETACHED;
+        // An empty mask means no affinity is set.
+        if mask_bits != 0 {
+            let mut cpumask = AxCpuMask::new();
+            for cpu in 0..axconfig::SMP.min(usize::BITS as usize) {
+                if mask_bits & (1 << cpu) != 0 {
+                    cpumask.set(cpu, true);
+                }
+            }
+            if cpumask.is_empty() {
+                return Err(LinuxError::EINVAL);
+            }
+            res.cpumask = Some(cpumask);
+        }
+        Ok(res)
+    }
+}
 
 pub struct Pthread {
     inner: AxTaskRef,
-    retval: Arc<Packet<*mut c_void>>,
+    retval: UnsafeCell<*mut c_void>,
+    /// One of `STATE_JOINABLE`, `STATE_DETACHED` and `STATE_EXITED`.
+    state: AtomicU8,
+    cancel_enabled: AtomicBool,
+    cancel_async: AtomicBool,
+    cancel_pending: AtomicBool,
+    /// Address of the object the thread is blocked on at a cancellation point,
+    /// or 0 if it is not blocked.
+    waiting_on: AtomicUsize,
+    /// Thread-specific data, indexed by `pthread_key_t`.
+    specific: SpinNoIrq<BTreeMap<ctypes::pthread_key_t, key::SpecificValue>>,
 }
 
 impl Pthread {
+    fn new(inner: AxTaskRef, detached: bool) -> Self {
+        Self {
+            inner,
+            retval: UnsafeCell::new(core::ptr::null_mut()),
+            state: AtomicU8::new(if detached {
+                STATE_DETACHED
+            } else {
+                STATE_JOINABLE
+            }),
+            cancel_enabled: AtomicBool::new(true),
+            cancel_async: AtomicBool::new(false),
+            cancel_pending: AtomicBool::new(false),
+            waiting_on: AtomicUsize::new(0),
+            specific: SpinNoIrq::new(BTreeMap::new()),
+        }
+    }
+
     fn create(
-        _attr: *const ctypes::pthread_attr_t,
+        attr: *const ctypes::pthread_attr_t,
         start_routine: extern "C" fn(arg: *mut c_void) -> *mut c_void,
         arg: *mut c_void,
     ) -> LinuxResult<ctypes::pthread_t> {
+        let attr = ThreadAttr::parse(attr)?;
         let arg_wrapper = ForceSendSync(arg);
 
-        let my_packet: Arc<Packet<*mut c_void>> = Arc::new(Packet {
-            result: UnsafeCell::new(core::ptr::null_mut()),
-        });
-        let their_packet = my_packet.clone();
-
         let main = move || {
             let arg = arg_wrapper;
+            // The creator registers the thread after spawning it, make sure
+            // that `Pthread::current()` is valid before running user code.
+            let tid = axtask::current().id().as_u64();
+            while !TID_TO_PTHREAD.read().contains_key(&tid) {
+                axtask::yield_now();
+            }
             let ret = start_routine(arg.0);
-            unsafe { *their_packet.result.get() = ret };
-            drop(their_packet);
+            Self::exit_current(ret);
         };
 
-        let task_inner = axtask::spawn(main);
+        let task = TaskInner::new(main, String::new(), attr.stack_size);
+        if let Some(cpumask) = attr.cpumask {
+            task.set_cpumask(cpumask);
+        }
+        let task_inner = axtask::spawn_task(task);
         let tid = task_inner.id().as_u64();
-        let thread = Pthread {
-            inner: task_inner,
-            retval: my_packet,
-        };
+        let thread = Pthread::new(task_inner, attr.detached);
         let ptr = Box::into_raw(Box::new(thread)) as *mut c_void;
         TID_TO_PTHREAD.write().insert(tid, ForceSendSync(ptr));
         Ok(ptr)
@@ -82,25 +173,101 @@ impl Pthread {
         unsafe { core::ptr::NonNull::new(Self::current_ptr()).map(|ptr| ptr.as_ref()) }
     }
 
+    fn from_ptr<'a>(ptr: ctypes::pthread_t) -> LinuxResult<&'a Pthread> {
+        unsafe { (ptr as *const Pthread).as_ref() }.ok_or(LinuxError::ESRCH)
+    }
+
     fn exit_current(retval: *mut c_void) -> ! {
         let thread = Self::current().expect("fail to get current thread");
-        unsafe { *thread.retval.result.get() = retval };
+        key::run_destructors(thread);
+        unsafe { *thread.retval.get() = retval };
+        if thread.state.swap(STATE_EXITED, Ordering::AcqRel) == STATE_DETACHED {
+            // Nobody will join a detached thread, reclaim it by ourselves.
+            unsafe { Self::release(thread as *const Pthread as _) };
+        }
         axtask::exit(0);
     }
 
+    /// Removes the thread from the global map and frees it.
+    unsafe fn release(ptr: *mut Pthread) {
+        let thread = unsafe { Box::from_raw(ptr) };
+        TID_TO_PTHREAD.write().remove(&thread.inner.id().as_u64());
+    }
+
     fn join(ptr: ctypes::pthread_t) -> LinuxResult<*mut c_void> {
         if core::ptr::eq(ptr, Self::current_ptr() as _) {
             return Err(LinuxError::EDEADLK);
         }
+        testcancel();
 
-        let thread = unsafe { Box::from_raw(ptr as *mut Pthread) };
+        let thread = Self::from_ptr(ptr)?;
+        if thread.state.load(Ordering::Acquire) == STATE_DETACHED {
+            return Err(LinuxError::EINVAL);
+        }
         thread.inner.join();
-        let tid = thread.inner.id().as_u64();
-        let retval = unsafe { *thread.retval.result.get() };
-        TID_TO_PTHREAD.write().remove(&tid);
-        drop(thread);
+        let retval = unsafe { *thread.retval.get() };
+        unsafe { Self::release(ptr as *mut Pthread) };
         Ok(retval)
     }
+
+    fn detach(ptr: ctypes::pthread_t) -> LinuxResult {
+        let thread = Self::from_ptr(ptr)?;
+        match thread.state.compare_exchange(
+            STATE_JOINABLE,
+            STATE_DETACHED,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => Ok(()),
+            // The thread has already exited, nobody else will reclaim it.
+            Err(STATE_EXITED) => {
+                unsafe { Self::release(ptr as *mut Pthread) };
+                Ok(())
+            }
+            Err(_) => Err(LinuxError::EINVAL),
+        }
+    }
+
+    fn cancel(&self) {
+        self.cancel_pending.store(true, Ordering::SeqCst);
+        // Wake the thread up if it's blocked at a cancellation point.
+        let addr = self.waiting_on.load(Ordering::SeqCst);
+        if addr != 0 {
+            wait::wake(addr, usize::MAX);
+        }
+    }
+
+    /// Whether a cancellation request should be acted upon.
+    fn should_cancel(&self) -> bool {
+        self.cancel_enabled.load(Ordering::Acquire) && self.cancel_pending.load(Ordering::SeqCst)
+    }
+
+    fn set_waiting_on(&self, addr: usize) {
+        self.waiting_on.store(addr, Ordering::SeqCst);
+    }
+}
+
+/// Acts upon a pending cancellation request of the current thread.
+///
+/// Only deferred cancellation is supported, so this is called at every
+/// cancellation point.
+pub(crate) fn testcancel() {
+    if Pthread::current().is_some_and(|t| t.should_cancel()) {
+        Pthread::exit_current(PTHREAD_CANCELED);
+    }
+}
+
+/// Runs `f` with cancellation of the current thread disabled, so that it's not
+/// cancelled half way at a cancellation point inside. A pending request is
+/// acted upon at the next cancellation point after it.
+pub(crate) fn without_cancel<T>(f: impl FnOnce() -> T) -> T {
+    let thread = Pthread::current();
+    let enabled = thread.map(|t| t.cancel_enabled.swap(false, Ordering::AcqRel));
+    let ret = f();
+    if let (Some(thread), Some(enabled)) = (thread, enabled) {
+        thread.cancel_enabled.store(enabled, Ordering::Release);
+    }
+    ret
 }
 
 /// Returns the `pthread` struct of current thread.
@@ -147,6 +314,100 @@ pub unsafe fn sys_pthread_join(thread: ctypes::pthread_t, retval: *mut *mut c_vo
     })
 }
 
+/// Marks the given thread as detached, its resources will be released
+/// automatically when it exits.
+pub fn sys_pthread_detach(thread: ctypes::pthread_t) -> c_int {
+    debug!("sys_pthread_detach <= {:#x}", thread as usize);
+    syscall_body!(sys_pthread_detach, {
+        Pthread::detach(thread)?;
+        Ok(0)
+    })
+}
+
+/// Sends a cancellation request to the given thread.
+///
+/// The request is acted upon when the target thread reaches a cancellation
+/// point, i.e., deferred cancellation.
+pub fn sys_pthread_cancel(thread: ctypes::pthread_t) -> c_int {
+    debug!("sys_pthread_cancel <= {:#x}", thread as usize);
+    syscall_body!(sys_pthread_cancel, {
+        Pthread::from_ptr(thread)?.cancel();
+        Ok(0)
+    })
+}
+
+/// Sets the cancelability state of the current thread, and stores the old
+/// state in `oldstate`.
+pub unsafe fn sys_pthread_setcancelstate(state: c_int, oldstate: *mut c_int) -> c_int {
+    debug!("sys_pthread_setcancelstate <= {}", state);
+    syscall_body!(sys_pthread_setcancelstate, {
+        let enable = match state as u32 {
+            ctypes::PTHREAD_CANCEL_ENABLE => true,
+            ctypes::PTHREAD_CANCEL_DISABLE => false,
+            _ => return Err(LinuxError::EINVAL),
+        };
+        let old = match Pthread::current() {
+            Some(thread) => thread.cancel_enabled.swap(enable, Ordering::AcqRel),
+            None => true,
+        };
+        if !oldstate.is_null() {
+            let old = if old {
+                ctypes::PTHREAD_CANCEL_ENABLE
+            } else {
+                ctypes::PTHREAD_CANCEL_DISABLE
+            };
+            unsafe { *oldstate = old as c_int };
+        }
+        Ok(0)
+    })
+}
+
+/// Sets the cancelability type of the current thread, and stores the old type
+/// in `oldtype`.
+///
+/// Asynchronous cancellation is accepted but acts like deferred cancellation.
+pub unsafe fn sys_pthread_setcanceltype(ty: c_int, oldtype: *mut c_int) -> c_int {
+    debug!("sys_pthread_setcanceltype <= {}", ty);
+    syscall_body!(sys_pthread_setcanceltype, {
+        let is_async = match ty as u32 {
+            ctypes::PTHREAD_CANCEL_DEFERRED => false,
+            ctypes::PTHREAD_CANCEL_ASYNCHRONOUS => true,
+            _ => return Err(LinuxError::EINVAL),
+        };
+        let old = match Pthread::current() {
+            Some(thread) => thread.cancel_async.swap(is_async, Ordering::AcqRel),
+            None => false,
+        };
+        if !oldtype.is_null() {
+            let old = if old {
+                ctypes::PTHREAD_CANCEL_ASYNCHRONOUS
+            } else {
+                ctypes::PTHREAD_CANCEL_DEFERRED
+            };
+            unsafe { *oldtype = old as c_int };
+        }
+        Ok(0)
+    })
+}
+
+/// Creates a cancellation point in the current thread.
+pub fn sys_pthread_testcancel() {
+    testcancel();
+}
+
+/// Sets the name of the given thread.
+pub fn sys_pthread_setname_np(thread: ctypes::pthread_t, name: *const c_char) -> c_int {
+    debug!("sys_pthread_setname_np <= {:#x}", thread as usize);
+    syscall_body!(sys_pthread_setname_np, {
+        let name = char_ptr_to_str(name)?;
+        if name.len() > THREAD_NAME_MAX {
+            return Err(LinuxError::ERANGE);
+        }
+        Pthread::from_ptr(thread)?.inner.set_name(name);
+        Ok(0)
+    })
+}
+
 #[derive(Clone, Copy)]
 struct ForceSendSync<T>(T);
 
@@ -0,0 +1,50 @@
+use crate::{ctypes, utils::check_null_mut_ptr};
+
+use core::ffi::c_int;
+use core::sync::atomic::{AtomicI32, Ordering};
+
+use super::{wait, without_cancel};
+
+/// `PTHREAD_ONCE_INIT`, the routine has not been run.
+const ONCE_INCOMPLETE: i32 = 0;
+/// The routine is being run by some thread.
+const ONCE_RUNNING: i32 = 1;
+/// The routine has completed.
+const ONCE_COMPLETE: i32 = 2;
+
+/// Run `init_routine` exactly once, other callers block until it completes.
+///
+/// The routine runs with cancellation disabled, since cleanup handlers are not
+/// supported to reset `once_control` if it's cancelled, which would block
+/// other callers forever.
+pub unsafe fn sys_pthread_once(
+    once_control: *mut ctypes::pthread_once_t,
+    init_routine: extern "C" fn(),
+) -> c_int {
+    debug!("sys_pthread_once <= {:#x}", once_control as usize);
+    syscall_body!(sys_pthread_once, {
+        check_null_mut_ptr(once_control)?;
+        let state = unsafe { AtomicI32::from_ptr(once_control.cast()) };
+        let addr = once_control as usize;
+        loop {
+            match state.compare_exchange(
+                ONCE_INCOMPLETE,
+                ONCE_RUNNING,
+                Ordering::Acquire,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => {
+                    without_cancel(init_routine);
+                    state.store(ONCE_COMPLETE, Ordering::Release);
+                    wait::wake(addr, usize::MAX);
+                    wait::remove(addr);
+                    return Ok(0);
+                }
+                Err(ONCE_COMPLETE) => return Ok(0),
+                Err(_) => {
+                    wait::wait_until(addr, None, || state.load(Ordering::Acquire) != ONCE_RUNNING);
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,241 @@
+use crate::{ctypes, utils::check_null_mut_ptr};
+
+use axerrno::{LinuxError, LinuxResult};
+
+use core::ffi::c_int;
+use core::mem::size_of;
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::time::Duration;
+
+use super::wait;
+
+static_assertions::const_assert!(
+    size_of::<PthreadRwLock>() <= size_of::<ctypes::pthread_rwlock_t>()
+);
+
+/// The lock state when it is held by a writer.
+const WRITER: u32 = u32::MAX;
+
+/// The in-memory layout of `pthread_rwlock_t`.
+///
+/// An all-zero value is an unlocked rwlock, so `PTHREAD_RWLOCK_INITIALIZER`
+/// works without calling into Rust.
+#[repr(C)]
+pub struct PthreadRwLock {
+    /// The number of readers holding the lock, or [`WRITER`].
+    state: AtomicU32,
+}
+
+impl PthreadRwLock {
+    const fn new() -> Self {
+        Self {
+            state: AtomicU32::new(0),
+        }
+    }
+
+    fn addr(&self) -> usize {
+        self as *const _ as usize
+    }
+
+    fn try_read(&self) -> LinuxResult {
+        let mut state = self.state.load(Ordering::Relaxed);
+        loop {
+            match state {
+                WRITER => return Err(LinuxError::EBUSY),
+                // Too many readers.
+                n if n == WRITER - 1 => return Err(LinuxError::EAGAIN),
+                n => match self.state.compare_exchange_weak(
+                    n,
+                    n + 1,
+                    Ordering::Acquire,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => return Ok(()),
+                    Err(s) => state = s,
+                },
+            }
+        }
+    }
+
+    fn try_write(&self) -> LinuxResult {
+        self.state
+            .compare_exchange(0, WRITER, Ordering::Acquire, Ordering::Relaxed)
+            .map(|_| ())
+            .map_err(|_| LinuxError::EBUSY)
+    }
+
+    /// Blocks until the lock state satisfies `condition`, returns `ETIMEDOUT`
+    /// if `deadline` (on `CLOCK_REALTIME`) has passed.
+    fn wait_until<F>(&self, deadline: Option<Duration>, condition: F) -> LinuxResult
+    where
+        F: Fn() -> bool,
+    {
+        let timeout = deadline.map(|d| d.saturating_sub(axhal::time::wall_time()));
+        if timeout.is_some_and(|t| t.is_zero()) || wait::wait_until(self.addr(), timeout, condition)
+        {
+            Err(LinuxError::ETIMEDOUT)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn read(&self, deadline: Option<Duration>) -> LinuxResult {
+        loop {
+            match self.try_read() {
+                Err(LinuxError::EBUSY) => {
+                    self.wait_until(deadline, || self.state.load(Ordering::Acquire) != WRITER)?
+                }
+                res => return res,
+            }
+        }
+    }
+
+    fn write(&self, deadline: Option<Duration>) -> LinuxResult {
+        while self.try_write().is_err() {
+            self.wait_until(deadline, || self.state.load(Ordering::Acquire) == 0)?;
+        }
+        Ok(())
+    }
+
+    fn unlock(&self) -> LinuxResult {
+        let state = self.state.load(Ordering::Relaxed);
+        let released = match state {
+            0 => return Err(LinuxError::EPERM),
+            WRITER => {
+                self.state.store(0, Ordering::Release);
+                true
+            }
+            _ => self.state.fetch_sub(1, Ordering::Release) == 1,
+        };
+        if released {
+            wait::wake(self.addr(), usize::MAX);
+        }
+        Ok(())
+    }
+}
+
+/// Validates an absolute `CLOCK_REALTIME` time.
+fn deadline_of(abstime: *const ctypes::timespec) -> LinuxResult<Duration> {
+    if abstime.is_null() {
+        return Err(LinuxError::EINVAL);
+    }
+    let abstime = unsafe { *abstime };
+    if abstime.tv_sec < 0 || !(0..1_000_000_000).contains(&abstime.tv_nsec) {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(Duration::from(abstime))
+}
+
+/// Initialize a rwlock.
+pub fn sys_pthread_rwlock_init(
+    rwlock: *mut ctypes::pthread_rwlock_t,
+    _attr: *const ctypes::pthread_rwlockattr_t,
+) -> c_int {
+    debug!("sys_pthread_rwlock_init <= {:#x}", rwlock as usize);
+    syscall_body!(sys_pthread_rwlock_init, {
+        check_null_mut_ptr(rwlock)?;
+        unsafe {
+            rwlock.cast::<PthreadRwLock>().write(PthreadRwLock::new());
+        }
+        Ok(0)
+    })
+}
+
+/// Destroy a rwlock.
+pub fn sys_pthread_rwlock_destroy(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    debug!("sys_pthread_rwlock_destroy <= {:#x}", rwlock as usize);
+    syscall_body!(sys_pthread_rwlock_destroy, {
+        check_null_mut_ptr(rwlock)?;
+        if unsafe {
+            (*rwlock.cast::<PthreadRwLock>())
+                .state
+                .load(Ordering::Acquire)
+        } != 0
+        {
+            return Err(LinuxError::EBUSY);
+        }
+        wait::remove(rwlock as usize);
+        Ok(0)
+    })
+}
+
+/// Lock the given rwlock for reading.
+pub fn sys_pthread_rwlock_rdlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    debug!("sys_pthread_rwlock_rdlock <= {:#x}", rwlock as usize);
+    syscall_body!(sys_pthread_rwlock_rdlock, {
+        check_null_mut_ptr(rwlock)?;
+        unsafe { (*rwlock.cast::<PthreadRwLock>()).read(None)? };
+        Ok(0)
+    })
+}
+
+/// Try to lock the given rwlock for reading, returns `EBUSY` if it is held by
+/// a writer.
+pub fn sys_pthread_rwlock_tryrdlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    debug!("sys_pthread_rwlock_tryrdlock <= {:#x}", rwlock as usize);
+    syscall_body!(sys_pthread_rwlock_tryrdlock, {
+        check_null_mut_ptr(rwlock)?;
+        unsafe { (*rwlock.cast::<PthreadRwLock>()).try_read()? };
+        Ok(0)
+    })
+}
+
+/// Lock the given rwlock for reading, or returns `ETIMEDOUT` if the lock can
+/// not be acquired before the absolute time `abstime`.
+pub fn sys_pthread_rwlock_timedrdlock(
+    rwlock: *mut ctypes::pthread_rwlock_t,
+    abstime: *const ctypes::timespec,
+) -> c_int {
+    debug!("sys_pthread_rwlock_timedrdlock <= {:#x}", rwlock as usize);
+    syscall_body!(sys_pthread_rwlock_timedrdlock, {
+        check_null_mut_ptr(rwlock)?;
+        let deadline = deadline_of(abstime)?;
+        unsafe { (*rwlock.cast::<PthreadRwLock>()).read(Some(deadline))? };
+        Ok(0)
+    })
+}
+
+/// Lock the given rwlock for writing.
+pub fn sys_pthread_rwlock_wrlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    debug!("sys_pthread_rwlock_wrlock <= {:#x}", rwlock as usize);
+    syscall_body!(sys_pthread_rwlock_wrlock, {
+        check_null_mut_ptr(rwlock)?;
+        unsafe { (*rwlock.cast::<PthreadRwLock>()).write(None)? };
+        Ok(0)
+    })
+}
+
+/// Try to lock the given rwlock for writing, returns `EBUSY` if it is held.
+pub fn sys_pthread_rwlock_trywrlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    debug!("sys_pthread_rwlock_trywrlock <= {:#x}", rwlock as usize);
+    syscall_body!(sys_pthread_rwlock_trywrlock, {
+        check_null_mut_ptr(rwlock)?;
+        unsafe { (*rwlock.cast::<PthreadRwLock>()).try_write()? };
+        Ok(0)
+    })
+}
+
+/// Lock the given rwlock for writing, or returns `ETIMEDOUT` if the lock can
+/// not be acquired before the absolute time `abstime`.
+pub fn sys_pthread_rwlock_timedwrlock(
+    rwlock: *mut ctypes::pthread_rwlock_t,
+    abstime: *const ctypes::timespec,
+) -> c_int {
+    debug!("sys_pthread_rwlock_timedwrlock <= {:#x}", rwlock as usize);
+    syscall_body!(sys_pthread_rwlock_timedwrlock, {
+        check_null_mut_ptr(rwlock)?;
+        let deadline = deadline_of(abstime)?;
+        unsafe { (*rwlock.cast::<PthreadRwLock>()).write(Some(deadline))? };
+        Ok(0)
+    })
+}
+
+/// Unlock the given rwlock.
+pub fn sys_pthread_rwlock_unlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    debug!("sys_pthread_rwlock_unlock <= {:#x}", rwlock as usize);
+    syscall_body!(sys_pthread_rwlock_unlock, {
+        check_null_mut_ptr(rwlock)?;
+        unsafe { (*rwlock.cast::<PthreadRwLock>()).unlock()? };
+        Ok(0)
+    })
+}
@@ -0,0 +1,371 @@
+use core::cell::UnsafeCell;
+use core::ffi::c_void;
+use core::ptr::{null, null_mut};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, Once};
+
+use axerrno::LinuxError;
+
+use super::condvar::*;
+use super::key::*;
+use super::mutex::*;
+use super::once::*;
+use super::rwlock::*;
+use super::*;
+
+static INIT: Once = Once::new();
+static SERIAL: Mutex<()> = Mutex::new(());
+
+fn setup() -> MutexGuard<'static, ()> {
+    let guard = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    INIT.call_once(axtask::init_scheduler);
+    guard
+}
+
+/// Synchronization objects shared with the spawned threads.
+struct Shared {
+    mutex: UnsafeCell<ctypes::pthread_mutex_t>,
+    cond: UnsafeCell<ctypes::pthread_cond_t>,
+    rwlock: UnsafeCell<ctypes::pthread_rwlock_t>,
+    flag: AtomicUsize,
+}
+
+unsafe impl Sync for Shared {}
+
+impl Shared {
+    fn new() -> &'static Self {
+        let shared = Box::leak(Box::new(Self {
+            mutex: UnsafeCell::new(unsafe { core::mem::zeroed() }),
+            cond: UnsafeCell::new(unsafe { core::mem::zeroed() }),
+            rwlock: UnsafeCell::new(unsafe { core::mem::zeroed() }),
+            flag: AtomicUsize::new(0),
+        }));
+        assert_eq!(sys_pthread_mutex_init(shared.mutex(), null()), 0);
+        assert_eq!(sys_pthread_cond_init(shared.cond(), null()), 0);
+        assert_eq!(sys_pthread_rwlock_init(shared.rwlock(), null()), 0);
+        shared
+    }
+
+    fn from_arg(arg: *mut c_void) -> &'static Self {
+        unsafe { &*(arg as *const Self) }
+    }
+
+    fn mutex(&self) -> *mut ctypes::pthread_mutex_t {
+        self.mutex.get()
+    }
+
+    fn cond(&self) -> *mut ctypes::pthread_cond_t {
+        self.cond.get()
+    }
+
+    fn rwlock(&self) -> *mut ctypes::pthread_rwlock_t {
+        self.rwlock.get()
+    }
+
+    fn flag(&self) -> usize {
+        self.flag.load(Ordering::Acquire)
+    }
+
+    fn set_flag(&self, value: usize) {
+        self.flag.store(value, Ordering::Release);
+    }
+}
+
+fn spawn(
+    start_routine: extern "C" fn(*mut c_void) -> *mut c_void,
+    arg: *const c_void,
+) -> ctypes::pthread_t {
+    let mut thread = null_mut();
+    let ret = unsafe { sys_pthread_create(&mut thread, null(), start_routine, arg as _) };
+    assert_eq!(ret, 0);
+    thread
+}
+
+fn join(thread: ctypes::pthread_t) -> *mut c_void {
+    let mut retval = null_mut();
+    assert_eq!(unsafe { sys_pthread_join(thread, &mut retval) }, 0);
+    retval
+}
+
+fn err(e: LinuxError) -> i32 {
+    -e.code()
+}
+
+#[test]
+fn test_cond_wait_signal() {
+    let _lock = setup();
+
+    extern "C" fn waiter(arg: *mut c_void) -> *mut c_void {
+        let shared = Shared::from_arg(arg);
+        assert_eq!(sys_pthread_mutex_lock(shared.mutex()), 0);
+        while shared.flag() == 0 {
+            assert_eq!(sys_pthread_cond_wait(shared.cond(), shared.mutex()), 0);
+        }
+        shared.set_flag(2);
+        assert_eq!(sys_pthread_mutex_unlock(shared.mutex()), 0);
+        null_mut()
+    }
+
+    let shared = Shared::new();
+    let thread = spawn(waiter, shared as *const _ as _);
+    // Let the waiter block on the condition variable.
+    axtask::yield_now();
+    assert_eq!(shared.flag(), 0);
+
+    assert_eq!(sys_pthread_mutex_lock(shared.mutex()), 0);
+    shared.set_flag(1);
+    assert_eq!(sys_pthread_cond_signal(shared.cond()), 0);
+    assert_eq!(sys_pthread_mutex_unlock(shared.mutex()), 0);
+    join(thread);
+    assert_eq!(shared.flag(), 2);
+
+    // The wait queue is freed once nobody waits on it.
+    assert!(!wait::has_queue(shared.cond() as usize));
+}
+
+#[test]
+fn test_cond_timedwait() {
+    let _lock = setup();
+
+    let shared = Shared::new();
+    let abstime = ctypes::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    assert_eq!(sys_pthread_mutex_lock(shared.mutex()), 0);
+    let ret = unsafe { sys_pthread_cond_timedwait(shared.cond(), shared.mutex(), &abstime) };
+    assert_eq!(ret, err(LinuxError::ETIMEDOUT));
+    // The mutex is still held after timing out.
+    assert_eq!(
+        sys_pthread_mutex_trylock(shared.mutex()),
+        err(LinuxError::EBUSY)
+    );
+    assert_eq!(sys_pthread_mutex_unlock(shared.mutex()), 0);
+}
+
+#[test]
+fn test_cancel_cond_wait() {
+    let _lock = setup();
+
+    extern "C" fn waiter(arg: *mut c_void) -> *mut c_void {
+        let shared = Shared::from_arg(arg);
+        assert_eq!(sys_pthread_mutex_lock(shared.mutex()), 0);
+        loop {
+            sys_pthread_cond_wait(shared.cond(), shared.mutex());
+        }
+    }
+
+    let shared = Shared::new();
+    let thread = spawn(waiter, shared as *const _ as _);
+    axtask::yield_now();
+    assert_eq!(sys_pthread_cancel(thread), 0);
+    assert_eq!(join(thread), PTHREAD_CANCELED);
+
+    // The cancelled thread does not exit owning the mutex.
+    assert_eq!(sys_pthread_mutex_trylock(shared.mutex()), 0);
+    assert_eq!(sys_pthread_mutex_unlock(shared.mutex()), 0);
+    assert!(!wait::has_queue(shared.cond() as usize));
+}
+
+#[test]
+fn test_cancel_disabled() {
+    let _lock = setup();
+
+    extern "C" fn worker(arg: *mut c_void) -> *mut c_void {
+        let shared = Shared::from_arg(arg);
+        let mut old = 0;
+        let disable = ctypes::PTHREAD_CANCEL_DISABLE as _;
+        assert_eq!(unsafe { sys_pthread_setcancelstate(disable, &mut old) }, 0);
+        assert_eq!(old, ctypes::PTHREAD_CANCEL_ENABLE as _);
+        shared.set_flag(1);
+        while shared.flag() == 1 {
+            sys_pthread_testcancel();
+            axtask::yield_now();
+        }
+        let enable = ctypes::PTHREAD_CANCEL_ENABLE as _;
+        assert_eq!(unsafe { sys_pthread_setcancelstate(enable, null_mut()) }, 0);
+        sys_pthread_testcancel();
+        unreachable!("the pending cancellation is acted upon");
+    }
+
+    let shared = Shared::new();
+    let thread = spawn(worker, shared as *const _ as _);
+    while shared.flag() == 0 {
+        axtask::yield_now();
+    }
+    assert_eq!(sys_pthread_cancel(thread), 0);
+    axtask::yield_now();
+    // Still running, as cancellation is disabled.
+    assert_eq!(shared.flag(), 1);
+    shared.set_flag(2);
+    assert_eq!(join(thread), PTHREAD_CANCELED);
+}
+
+#[test]
+fn test_rwlock() {
+    let _lock = setup();
+
+    let shared = Shared::new();
+    let rwlock = shared.rwlock();
+    assert_eq!(sys_pthread_rwlock_rdlock(rwlock), 0);
+    assert_eq!(sys_pthread_rwlock_tryrdlock(rwlock), 0);
+    assert_eq!(sys_pthread_rwlock_trywrlock(rwlock), err(LinuxError::EBUSY));
+    assert_eq!(sys_pthread_rwlock_unlock(rwlock), 0);
+    assert_eq!(sys_pthread_rwlock_unlock(rwlock), 0);
+
+    assert_eq!(sys_pthread_rwlock_wrlock(rwlock), 0);
+    assert_eq!(sys_pthread_rwlock_tryrdlock(rwlock), err(LinuxError::EBUSY));
+    assert_eq!(sys_pthread_rwlock_trywrlock(rwlock), err(LinuxError::EBUSY));
+    assert_eq!(sys_pthread_rwlock_unlock(rwlock), 0);
+    assert_eq!(sys_pthread_rwlock_destroy(rwlock), 0);
+}
+
+#[test]
+fn test_rwlock_writer_waits_for_readers() {
+    let _lock = setup();
+
+    extern "C" fn writer(arg: *mut c_void) -> *mut c_void {
+        let shared = Shared::from_arg(arg);
+        assert_eq!(sys_pthread_rwlock_wrlock(shared.rwlock()), 0);
+        shared.set_flag(1);
+        assert_eq!(sys_pthread_rwlock_unlock(shared.rwlock()), 0);
+        null_mut()
+    }
+
+    let shared = Shared::new();
+    assert_eq!(sys_pthread_rwlock_rdlock(shared.rwlock()), 0);
+    let thread = spawn(writer, shared as *const _ as _);
+    axtask::yield_now();
+    assert_eq!(shared.flag(), 0);
+    assert_eq!(sys_pthread_rwlock_unlock(shared.rwlock()), 0);
+    join(thread);
+    assert_eq!(shared.flag(), 1);
+    assert!(!wait::has_queue(shared.rwlock() as usize));
+}
+
+#[test]
+fn test_once() {
+    let _lock = setup();
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+    static mut ONCE: ctypes::pthread_once_t = 0;
+
+    extern "C" fn init_routine() {
+        CALLS.fetch_add(1, Ordering::Relaxed);
+        // Let the other caller find the routine running.
+        axtask::yield_now();
+    }
+
+    extern "C" fn caller(_arg: *mut c_void) -> *mut c_void {
+        assert_eq!(unsafe { sys_pthread_once(&raw mut ONCE, init_routine) }, 0);
+        assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+        null_mut()
+    }
+
+    let thread = spawn(caller, null());
+    assert_eq!(unsafe { sys_pthread_once(&raw mut ONCE, init_routine) }, 0);
+    join(thread);
+    assert_eq!(unsafe { sys_pthread_once(&raw mut ONCE, init_routine) }, 0);
+    assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn test_once_cancel() {
+    let _lock = setup();
+
+    static mut ONCE: ctypes::pthread_once_t = 0;
+
+    extern "C" fn init_routine() {
+        // Not a cancellation point for the routine.
+        sys_pthread_testcancel();
+    }
+
+    extern "C" fn caller(_arg: *mut c_void) -> *mut c_void {
+        assert_eq!(sys_pthread_cancel(sys_pthread_self()), 0);
+        unsafe { sys_pthread_once(&raw mut ONCE, init_routine) };
+        sys_pthread_testcancel();
+        unreachable!("the pending cancellation is acted upon");
+    }
+
+    let thread = spawn(caller, null());
+    assert_eq!(join(thread), PTHREAD_CANCELED);
+    // The routine has completed (`ONCE_COMPLETE`), others do not wait for it
+    // forever.
+    assert_eq!(unsafe { ONCE }, 2);
+    assert_eq!(unsafe { sys_pthread_once(&raw mut ONCE, init_routine) }, 0);
+}
+
+#[test]
+fn test_keys() {
+    let _lock = setup();
+
+    static DESTROYED: AtomicUsize = AtomicUsize::new(0);
+    static mut KEY: ctypes::pthread_key_t = 0;
+
+    extern "C" fn destructor(value: *mut c_void) {
+        DESTROYED.fetch_add(value as usize, Ordering::Relaxed);
+    }
+
+    extern "C" fn worker(_arg: *mut c_void) -> *mut c_void {
+        let key = unsafe { KEY };
+        assert!(sys_pthread_getspecific(key).is_null());
+        assert_eq!(sys_pthread_setspecific(key, 42 as *const c_void), 0);
+        assert_eq!(sys_pthread_getspecific(key), 42 as *mut c_void);
+        null_mut()
+    }
+
+    assert_eq!(
+        unsafe { sys_pthread_key_create(&raw mut KEY, Some(destructor)) },
+        0
+    );
+    let key = unsafe { KEY };
+    join(spawn(worker, null()));
+    assert_eq!(DESTROYED.load(Ordering::Relaxed), 42);
+
+    // Values are per thread.
+    assert!(sys_pthread_getspecific(key).is_null());
+    assert_eq!(sys_pthread_key_delete(key), 0);
+    assert_eq!(sys_pthread_key_delete(key), err(LinuxError::EINVAL));
+    assert_eq!(
+        sys_pthread_setspecific(key, 1 as *const c_void),
+        err(LinuxError::EINVAL)
+    );
+}
+
+#[test]
+fn test_detach() {
+    let _lock = setup();
+
+    extern "C" fn worker(arg: *mut c_void) -> *mut c_void {
+        let shared = Shared::from_arg(arg);
+        while shared.flag() == 0 {
+            axtask::yield_now();
+        }
+        shared.set_flag(2);
+        null_mut()
+    }
+
+    let shared = Shared::new();
+    let thread = spawn(worker, shared as *const _ as _);
+    assert_eq!(sys_pthread_detach(thread), 0);
+    assert_eq!(sys_pthread_detach(thread), err(LinuxError::EINVAL));
+    let ret = unsafe { sys_pthread_join(thread, null_mut()) };
+    assert_eq!(ret, err(LinuxError::EINVAL));
+
+    shared.set_flag(1);
+    while shared.flag() != 2 {
+        axtask::yield_now();
+    }
+}
+
+#[test]
+fn test_join_retval() {
+    let _lock = setup();
+
+    extern "C" fn worker(arg: *mut c_void) -> *mut c_void {
+        sys_pthread_exit(arg)
+    }
+
+    let thread = spawn(worker, 0x1234 as *const c_void);
+    assert_eq!(join(thread), 0x1234 as *mut c_void);
+}
@@ -0,0 +1,103 @@
+//! Wait queues keyed by the address of a pthread synchronization object.
+//!
+//! C types such as `pthread_cond_t` are plain memory that may be initialized
+//! statically, so we can not embed a [`WaitQueue`] in them. Instead, tasks
+//! block on a queue looked up by the address of the object, which exists only
+//! while there are tasks using it.
+
+use alloc::collections::{BTreeMap, btree_map::Entry};
+use alloc::sync::Arc;
+use core::time::Duration;
+
+use axsync::spin::SpinNoIrq;
+use axtask::WaitQueue;
+
+static WAIT_QUEUES: SpinNoIrq<BTreeMap<usize, Arc<WaitQueue>>> = SpinNoIrq::new(BTreeMap::new());
+
+fn queue_of(addr: usize) -> Arc<WaitQueue> {
+    WAIT_QUEUES
+        .lock()
+        .entry(addr)
+        .or_insert_with(|| Arc::new(WaitQueue::new()))
+        .clone()
+}
+
+/// Drops a reference to the queue of `addr` got from [`queue_of`], and frees
+/// the queue if nobody else is using it.
+///
+/// References are only taken and dropped with `WAIT_QUEUES` locked, so the
+/// reference count tells whether there are other waiters or wakers.
+fn put_queue(addr: usize, wq: Arc<WaitQueue>) {
+    let mut queues = WAIT_QUEUES.lock();
+    if let Entry::Occupied(entry) = queues.entry(addr)
+        && Arc::ptr_eq(entry.get(), &wq)
+        && Arc::strong_count(&wq) == 2
+    {
+        entry.remove();
+    }
+    drop(wq);
+}
+
+/// Blocks the current task on the queue of `addr` until `condition` becomes
+/// true, or `timeout` (if any) has elapsed.
+///
+/// Returns `true` if the wait timed out.
+pub fn wait_until<F>(addr: usize, timeout: Option<Duration>, condition: F) -> bool
+where
+    F: Fn() -> bool,
+{
+    let wq = queue_of(addr);
+    let timed_out = match timeout {
+        None => {
+            wq.wait_until(condition);
+            false
+        }
+        #[cfg(feature = "irq")]
+        Some(dur) => wq.wait_timeout_until(dur, condition),
+        #[cfg(not(feature = "irq"))]
+        Some(dur) => {
+            // No timer interrupts, fall back to polling.
+            let deadline = axhal::time::wall_time() + dur;
+            loop {
+                if condition() {
+                    break false;
+                }
+                if axhal::time::wall_time() >= deadline {
+                    break true;
+                }
+                axtask::yield_now();
+            }
+        }
+    };
+    put_queue(addr, wq);
+    timed_out
+}
+
+/// Wakes up at most `count` tasks waiting on `addr`.
+pub fn wake(addr: usize, count: usize) {
+    let wq = match WAIT_QUEUES.lock().get(&addr) {
+        Some(wq) => wq.clone(),
+        None => return,
+    };
+    if count == usize::MAX {
+        wq.notify_all(true);
+    } else {
+        for _ in 0..count {
+            if !wq.notify_one(true) {
+                break;
+            }
+        }
+    }
+    put_queue(addr, wq);
+}
+
+/// Releases the wait queue of `addr`, called when the object is destroyed.
+pub fn remove(addr: usize) {
+    WAIT_QUEUES.lock().remove(&addr);
+}
+
+/// Whether there is a wait queue of `addr`.
+#[cfg(test)]
+pub fn has_queue(addr: usize) -> bool {
+    WAIT_QUEUES.lock().contains_key(&addr)
+}
@@ -1,6 +1,53 @@
 use crate::ctypes;
 use axerrno::LinuxError;
-use core::ffi::c_int;
+use core::ffi::{c_int, c_long};
+use core::time::Duration;
+
+/// CPU time and context switch counts of a thread or the whole process.
+#[derive(Default)]
+pub(crate) struct CpuUsage {
+    pub user_time: Duration,
+    pub system_time: Duration,
+    pub nvcsw: u64,
+    pub nivcsw: u64,
+}
+
+impl CpuUsage {
+    #[cfg(feature = "multitask")]
+    fn add(&mut self, stats: &axtask::TaskStats) {
+        self.user_time += stats.user_time;
+        self.system_time += stats.kernel_time;
+        self.nvcsw += stats.voluntary_switches;
+        self.nivcsw += stats.involuntary_switches;
+    }
+
+    pub fn cpu_time(&self) -> Duration {
+        self.user_time + self.system_time
+    }
+}
+
+/// Returns the CPU usage of the calling thread if `thread_only`, otherwise
+/// the usage of all threads (excluding the idle tasks).
+pub(crate) fn cpu_usage(thread_only: bool) -> CpuUsage {
+    let mut usage = CpuUsage::default();
+    #[cfg(feature = "multitask")]
+    if thread_only {
+        usage.add(&axtask::current().stats());
+    } else {
+        axtask::for_each_task(|task| {
+            if !task.is_idle() {
+                usage.add(&task.stats());
+            }
+        });
+    }
+    #[cfg(not(feature = "multitask"))]
+    {
+        // The only thread has been running since booting.
+        let _ = thread_only;
+        usage.system_time = axhal::time::monotonic_time();
+    }
+    usage
+}
 
 /// Get resource limitations
 ///
@@ -49,3 +96,35 @@ pub unsafe fn sys_setrlimit(resource: c_int, rlimits: *mut crate::ctypes::rlimit
         Ok(0)
     })
 }
+
+/// Get resource usage of the calling process, thread or children
+///
+/// Only CPU times and context switch counts are reported.
+pub unsafe fn sys_getrusage(who: c_int, usage: *mut ctypes::rusage) -> c_int {
+    debug!("sys_getrusage <= {} {:#x}", who, usage as usize);
+    syscall_body!(sys_getrusage, {
+        if usage.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        const RUSAGE_SELF: c_int = ctypes::RUSAGE_SELF as c_int;
+        const RUSAGE_THREAD: c_int = ctypes::RUSAGE_THREAD as c_int;
+        const RUSAGE_CHILDREN: c_int = ctypes::RUSAGE_CHILDREN as c_int;
+        let cpu = match who {
+            RUSAGE_SELF => cpu_usage(false),
+            RUSAGE_THREAD => cpu_usage(true),
+            // There are no child processes.
+            RUSAGE_CHILDREN => CpuUsage::default(),
+            _ => return Err(LinuxError::EINVAL),
+        };
+        unsafe {
+            *usage = ctypes::rusage {
+                ru_utime: cpu.user_time.into(),
+                ru_stime: cpu.system_time.into(),
+                ru_nvcsw: cpu.nvcsw as c_long,
+                ru_nivcsw: cpu.nivcsw as c_long,
+                ..Default::default()
+            };
+        }
+        Ok(0)
+    })
+}
@@ -6,17 +6,11 @@ use axsync::Mutex;
 use {alloc::sync::Arc, axerrno::LinuxError, axerrno::LinuxResult, axio::PollState};
 
 fn console_read_bytes(buf: &mut [u8]) -> AxResult<usize> {
-    let len = axhal::console::read_bytes(buf);
-    for c in &mut buf[..len] {
-        if *c == b'\r' {
-            *c = b'\n';
-        }
-    }
-    Ok(len)
+    Ok(axtty::read(buf))
 }
 
 fn console_write_bytes(buf: &[u8]) -> AxResult<usize> {
-    axhal::console::write_bytes(buf);
+    axtty::write(buf);
     Ok(buf.len())
 }
 
@@ -24,17 +18,9 @@ struct StdinRaw;
 struct StdoutRaw;
 
 impl Read for StdinRaw {
-    // Non-blocking read, returns number of bytes read.
+    // Blocks until some input is available, returns number of bytes read.
     fn read(&mut self, buf: &mut [u8]) -> AxResult<usize> {
-        let mut read_len = 0;
-        while read_len < buf.len() {
-            let len = console_read_bytes(buf[read_len..].as_mut())?;
-            if len == 0 {
-                break;
-            }
-            read_len += len;
-        }
-        Ok(read_len)
+        console_read_bytes(buf)
     }
 }
 
@@ -53,20 +39,9 @@ pub struct Stdin {
 }
 
 impl Stdin {
-    // Block until at least one byte is read.
+    // Block until at least one byte is read, or the end of file is reached.
     fn read_blocked(&self, buf: &mut [u8]) -> AxResult<usize> {
-        let read_len = self.inner.lock().read(buf)?;
-        if buf.is_empty() || read_len > 0 {
-            return Ok(read_len);
-        }
-        // try again until we get something
-        loop {
-            let read_len = self.inner.lock().read(buf)?;
-            if read_len > 0 {
-                return Ok(read_len);
-            }
-            crate::sys_sched_yield();
-        }
+        self.inner.lock().read(buf)
     }
 }
 
@@ -131,7 +106,7 @@ impl super::fd_ops::FileLike for Stdin {
 
     fn poll(&self) -> LinuxResult<PollState> {
         Ok(PollState {
-            readable: true,
+            readable: !self.inner.lock().buffer().is_empty() || axtty::readable(),
             writable: true,
         })
     }
@@ -1,4 +1,5 @@
-use core::ffi::{c_int, c_long};
+use axerrno::LinuxError;
+use core::ffi::{c_int, c_long, c_uint, c_void};
 
 use crate::ctypes;
 
@@ -28,7 +29,7 @@ pub fn sys_sysconf(name: c_int) -> c_long {
             // Page size
             ctypes::_SC_PAGE_SIZE => Ok(PAGE_SIZE_4K),
             // Number of processors in use
-            ctypes::_SC_NPROCESSORS_ONLN => Ok(axconfig::SMP),
+            ctypes::_SC_NPROCESSORS_ONLN => Ok(axhal::cpu::cpu_num()),
             // Total physical pages
             ctypes::_SC_PHYS_PAGES => Ok(phys_pages),
             // Avaliable physical pages
@@ -40,3 +41,38 @@ pub fn sys_sysconf(name: c_int) -> c_long {
         }
     })
 }
+
+/// Reboot or power off the whole system
+///
+/// Only `RB_AUTOBOOT` and `RB_POWER_OFF` are supported, and they never return.
+pub fn sys_reboot(cmd: c_int) -> c_int {
+    debug!("sys_reboot <= {:#x}", cmd);
+    syscall_body!(sys_reboot, {
+        match cmd as u32 {
+            ctypes::RB_AUTOBOOT => axhal::misc::reboot(),
+            ctypes::RB_POWER_OFF => axhal::misc::poweroff(),
+            _ => Err::<c_int, _>(LinuxError::EINVAL),
+        }
+    })
+}
+
+/// Fill the buffer with random bytes from the kernel entropy pool.
+///
+/// It never blocks, so `GRND_NONBLOCK` and `GRND_RANDOM` make no difference.
+pub fn sys_getrandom(buf: *mut c_void, buflen: usize, flags: c_uint) -> ctypes::ssize_t {
+    debug!(
+        "sys_getrandom <= {:#x} {} {:#x}",
+        buf as usize, buflen, flags
+    );
+    syscall_body!(sys_getrandom, {
+        if buf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        if flags & !(ctypes::GRND_NONBLOCK | ctypes::GRND_RANDOM | ctypes::GRND_INSECURE) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let dst = unsafe { core::slice::from_raw_parts_mut(buf as *mut u8, buflen) };
+        axrand::getrandom(dst);
+        Ok(buflen as ctypes::ssize_t)
+    })
+}
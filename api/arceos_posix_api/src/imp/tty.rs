@@ -0,0 +1,109 @@
+use core::ffi::c_int;
+
+use axerrno::{LinuxError, LinuxResult};
+use axtty::{InputFlags, LocalFlags, Termios};
+
+use crate::ctypes;
+
+/// Terminates the application on `^C`, which is the default action of
+/// `SIGINT`, as there are no signal handlers to run.
+fn interrupt() {
+    warn!("interrupted by the terminal, terminating...");
+    axhal::misc::terminate();
+}
+
+#[ctor_bare::register_ctor]
+fn init_interrupt_handler() {
+    axtty::set_interrupt_handler(interrupt);
+}
+
+/// Checks whether `fd` refers to the console terminal.
+fn check_tty(fd: c_int) -> LinuxResult {
+    #[cfg(feature = "fd")]
+    {
+        let file = super::fd_ops::get_file_like(fd)?.into_any();
+        if file.is::<super::stdio::Stdin>() || file.is::<super::stdio::Stdout>() {
+            Ok(())
+        } else {
+            Err(LinuxError::ENOTTY)
+        }
+    }
+    #[cfg(not(feature = "fd"))]
+    match fd {
+        0..=2 => Ok(()),
+        _ => Err(LinuxError::EBADF),
+    }
+}
+
+pub(crate) fn get_termios(fd: c_int, termios_p: *mut ctypes::termios) -> LinuxResult {
+    check_tty(fd)?;
+    if termios_p.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    let termios = axtty::termios();
+    let speed = termios.cflag & ctypes::CBAUD;
+    unsafe {
+        *termios_p = ctypes::termios {
+            c_iflag: termios.iflag.bits(),
+            c_oflag: termios.oflag,
+            c_cflag: termios.cflag,
+            c_lflag: termios.lflag.bits(),
+            c_line: 0,
+            c_cc: termios.cc,
+            __c_ispeed: speed,
+            __c_ospeed: speed,
+        };
+    }
+    Ok(())
+}
+
+pub(crate) fn set_termios(
+    fd: c_int,
+    optional_actions: c_int,
+    termios_p: *const ctypes::termios,
+) -> LinuxResult {
+    check_tty(fd)?;
+    if termios_p.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    let termios = unsafe { &*termios_p };
+    match optional_actions as u32 {
+        // The output is not buffered, nothing to drain.
+        ctypes::TCSANOW | ctypes::TCSADRAIN => {}
+        ctypes::TCSAFLUSH => axtty::flush_input(),
+        _ => return Err(LinuxError::EINVAL),
+    }
+    axtty::set_termios(&Termios {
+        iflag: InputFlags::from_bits_retain(termios.c_iflag),
+        oflag: termios.c_oflag,
+        cflag: termios.c_cflag,
+        lflag: LocalFlags::from_bits_retain(termios.c_lflag),
+        cc: termios.c_cc,
+    });
+    Ok(())
+}
+
+/// Get the attributes of the terminal referred to by `fd`.
+pub unsafe fn sys_tcgetattr(fd: c_int, termios_p: *mut ctypes::termios) -> c_int {
+    debug!("sys_tcgetattr <= {} {:#x}", fd, termios_p as usize);
+    syscall_body!(sys_tcgetattr, get_termios(fd, termios_p).map(|_| 0))
+}
+
+/// Set the attributes of the terminal referred to by `fd`.
+///
+/// `optional_actions` specifies when the change takes effect, where
+/// `TCSAFLUSH` also discards the received but unread input.
+pub unsafe fn sys_tcsetattr(
+    fd: c_int,
+    optional_actions: c_int,
+    termios_p: *const ctypes::termios,
+) -> c_int {
+    debug!(
+        "sys_tcsetattr <= {} {} {:#x}",
+        fd, optional_actions, termios_p as usize
+    );
+    syscall_body!(
+        sys_tcsetattr,
+        set_termios(fd, optional_actions, termios_p).map(|_| 0)
+    )
+}
@@ -34,10 +34,11 @@ pub mod ctypes;
 pub use imp::io::{sys_fsync, sys_ioctl, sys_read, sys_readv, sys_write, sys_writev};
 #[cfg(feature = "fs")]
 pub use imp::path_link::{AT_FDCWD, FilePath, HARDLINK_MANAGER, handle_file_path};
-pub use imp::resources::{sys_getrlimit, sys_setrlimit};
-pub use imp::sys::sys_sysconf;
+pub use imp::resources::{sys_getrlimit, sys_getrusage, sys_setrlimit};
+pub use imp::sys::{sys_getrandom, sys_reboot, sys_sysconf};
 pub use imp::task::{sys_exit, sys_getpid, sys_sched_yield};
 pub use imp::time::{sys_clock_gettime, sys_get_time_of_day, sys_nanosleep};
+pub use imp::tty::{sys_tcgetattr, sys_tcsetattr};
 
 #[cfg(feature = "fd")]
 pub use imp::fd_ops::{
@@ -61,8 +62,31 @@ pub use imp::net::{
 #[cfg(feature = "pipe")]
 pub use imp::pipe::sys_pipe;
 #[cfg(feature = "multitask")]
+pub use imp::pthread::condvar::{
+    sys_pthread_cond_broadcast, sys_pthread_cond_destroy, sys_pthread_cond_init,
+    sys_pthread_cond_signal, sys_pthread_cond_timedwait, sys_pthread_cond_wait,
+};
+#[cfg(feature = "multitask")]
+pub use imp::pthread::key::{
+    sys_pthread_getspecific, sys_pthread_key_create, sys_pthread_key_delete,
+    sys_pthread_setspecific,
+};
+#[cfg(feature = "multitask")]
 pub use imp::pthread::mutex::{
-    sys_pthread_mutex_init, sys_pthread_mutex_lock, sys_pthread_mutex_unlock,
+    sys_pthread_mutex_destroy, sys_pthread_mutex_init, sys_pthread_mutex_lock,
+    sys_pthread_mutex_trylock, sys_pthread_mutex_unlock,
 };
 #[cfg(feature = "multitask")]
-pub use imp::pthread::{sys_pthread_create, sys_pthread_exit, sys_pthread_join, sys_pthread_self};
+pub use imp::pthread::once::sys_pthread_once;
+#[cfg(feature = "multitask")]
+pub use imp::pthread::rwlock::{
+    sys_pthread_rwlock_destroy, sys_pthread_rwlock_init, sys_pthread_rwlock_rdlock,
+    sys_pthread_rwlock_timedrdlock, sys_pthread_rwlock_timedwrlock, sys_pthread_rwlock_tryrdlock,
+    sys_pthread_rwlock_trywrlock, sys_pthread_rwlock_unlock, sys_pthread_rwlock_wrlock,
+};
+#[cfg(feature = "multitask")]
+pub use imp::pthread::{
+    sys_pthread_cancel, sys_pthread_create, sys_pthread_detach, sys_pthread_exit, sys_pthread_join,
+    sys_pthread_self, sys_pthread_setcancelstate, sys_pthread_setcanceltype,
+    sys_pthread_setname_np, sys_pthread_testcancel,
+};
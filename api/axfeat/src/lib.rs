@@ -12,6 +12,8 @@
 //!     - `alloc-tlsf`: Use the TLSF allocator.
 //!     - `alloc-slab`: Use the slab allocator.
 //!     - `alloc-buddy`: Use the buddy system allocator.
+//!     - `alloc-tracking`: Track live allocations and check for misuse of the
+//!       heap, for debugging.
 //!     - `paging`: Enable page table manipulation.
 //!     - `tls`: Enable thread-local storage.
 //! - Task management
@@ -19,11 +21,20 @@
 //!     - `sched_fifo`: Use the FIFO cooperative scheduler.
 //!     - `sched_rr`: Use the Round-robin preemptive scheduler.
 //!     - `sched_cfs`: Use the Completely Fair Scheduler (CFS) preemptive scheduler.
-//! - Upperlayer stacks (fs, net, display)
+//!     - `tickless`: Stop the periodic timer tick, and only program timer
+//!       interrupts for timer events and the end of time slices.
+//! - Upperlayer stacks (fs, net, display, input, console, rng)
 //!     - `fs`: Enable file system support.
+//!     - `fs-9p`: Mount directories shared by the host over 9P (virtio-9p).
 //!     - `myfs`: Allow users to define their custom filesystems to override the default.
 //!     - `net`: Enable networking support.
 //!     - `display`: Enable graphics support.
+//!     - `fbcon`: Show logs and standard output on the framebuffer text console.
+//!     - `fbcon-log`, `fbcon-stdout`: Show only logs, or only standard output,
+//!       on the framebuffer text console.
+//!     - `input`: Enable input device (keyboard, mouse, etc.) support.
+//!     - `console`: Use VirtIO console ports, the first of which becomes the console.
+//!     - `rng`: Use hardware random number generators as entropy sources.
 //! - Device drivers
 //!     - `bus-mmio`: Use device tree to probe all MMIO devices.
 //!     - `bus-pci`: Use PCI bus to probe all PCI devices.
@@ -34,6 +45,9 @@
 //!     - `log-level-off`: Disable all logging.
 //!     - `log-level-error`, `log-level-warn`, `log-level-info`, `log-level-debug`,
 //!       `log-level-trace`: Keep logging only at the specified level or higher.
+//! - Debugging
+//!     - `backtrace`: Print symbolized backtraces on panic and unhandled traps.
+//!       The kernel must be built with frame pointers (done by the makefile).
 //!
 //! [ArceOS]: https://github.com/arceos-org/arceos
 
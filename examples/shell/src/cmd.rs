@@ -24,10 +24,16 @@ const CMD_TABLE: &[(&str, CmdHandler)] = &[
     ("echo", do_echo),
     ("exit", do_exit),
     ("help", do_help),
+    #[cfg(all(feature = "axstd", feature = "irq"))]
+    ("irqstat", do_irqstat),
     ("ls", do_ls),
     ("mkdir", do_mkdir),
+    #[cfg(all(feature = "axstd", feature = "multitask"))]
+    ("ps", do_ps),
     ("pwd", do_pwd),
     ("rm", do_rm),
+    #[cfg(all(feature = "axstd", feature = "multitask"))]
+    ("top", do_top),
     ("uname", do_uname),
 ];
 
@@ -262,6 +268,102 @@ fn do_uname(_args: &str) {
     );
 }
 
+#[cfg(all(feature = "axstd", feature = "multitask"))]
+fn do_ps(_args: &str) {
+    use std::os::arceos::api::task::ax_list_tasks;
+
+    println!(
+        "{:>5} {:<16} {:<8} {:>3} {:>10} {:>10} {:>8} {:>8}",
+        "TID", "NAME", "STATE", "CPU", "USER(ms)", "SYS(ms)", "NVCSW", "NIVCSW"
+    );
+    for info in ax_list_tasks() {
+        let stats = &info.stats;
+        println!(
+            "{:>5} {:<16} {:<8} {:>3} {:>10} {:>10} {:>8} {:>8}",
+            info.id.as_u64(),
+            info.name,
+            std::format!("{:?}", info.state),
+            stats.last_cpu,
+            stats.user_time.as_millis(),
+            stats.kernel_time.as_millis(),
+            stats.voluntary_switches,
+            stats.involuntary_switches,
+        );
+    }
+}
+
+#[cfg(all(feature = "axstd", feature = "multitask"))]
+fn do_top(args: &str) {
+    use std::os::arceos::api::task::{AxTaskInfo, ax_list_tasks};
+    use std::time::{Duration, Instant};
+
+    let interval = if args.is_empty() {
+        1000
+    } else {
+        match args.parse::<u64>() {
+            Ok(ms) if ms > 0 => ms,
+            _ => {
+                print_err!("top", args, "invalid interval (in milliseconds)");
+                return;
+            }
+        }
+    };
+
+    let before = ax_list_tasks();
+    let start = Instant::now();
+    std::thread::sleep(Duration::from_millis(interval));
+    let after = ax_list_tasks();
+    let elapsed = start.elapsed().as_nanos().max(1);
+
+    let cpu_time_of = |info: &AxTaskInfo| {
+        let prev = before
+            .iter()
+            .find(|b| b.id == info.id)
+            .map_or(Duration::ZERO, |b| b.stats.cpu_time());
+        info.stats.cpu_time().saturating_sub(prev).as_nanos()
+    };
+    let mut usage: Vec<_> = after
+        .iter()
+        .map(|info| (info, cpu_time_of(info) * 1000 / elapsed))
+        .collect();
+    usage.sort_by(|a, b| b.1.cmp(&a.1));
+
+    println!(
+        "{:>5} {:<16} {:<8} {:>3} {:>6} {:>12}",
+        "TID", "NAME", "STATE", "CPU", "%CPU", "LATENCY(us)"
+    );
+    for (info, permille) in usage {
+        println!(
+            "{:>5} {:<16} {:<8} {:>3} {:>4}.{} {:>12}",
+            info.id.as_u64(),
+            info.name,
+            std::format!("{:?}", info.state),
+            info.stats.last_cpu,
+            permille / 10,
+            permille % 10,
+            info.stats.avg_wakeup_latency().as_micros(),
+        );
+    }
+}
+
+#[cfg(all(feature = "axstd", feature = "irq"))]
+fn do_irqstat(_args: &str) {
+    use std::os::arceos::api::{config::SMP, irq::ax_for_each_irq_stat};
+
+    print!("{:>18}", "IRQ");
+    for cpu_id in 0..SMP {
+        print!(" {:>10}", std::format!("CPU{}", cpu_id));
+    }
+    println!("  NAME");
+    ax_for_each_irq_stat(&mut |stat| {
+        print!("{:>#18x}", stat.irq_num);
+        for count in stat.counts {
+            print!(" {:>10}", count);
+        }
+        println!("  {}", stat.name);
+    });
+}
+
 fn do_help(_args: &str) {
     println!("Available commands:");
     for (name, _) in CMD_TABLE {
@@ -23,10 +23,6 @@ mod ramfs;
 use std::io::prelude::*;
 
 const LF: u8 = b'\n';
-const CR: u8 = b'\r';
-const DL: u8 = b'\x7f';
-const BS: u8 = b'\x08';
-const SPACE: u8 = b' ';
 
 const MAX_CMD_LEN: usize = 256;
 
@@ -41,42 +37,23 @@ fn print_prompt() {
 #[cfg_attr(feature = "axstd", unsafe(no_mangle))]
 fn main() {
     let mut stdin = std::io::stdin();
-    let mut stdout = std::io::stdout();
 
     let mut buf = [0; MAX_CMD_LEN];
-    let mut cursor = 0;
     cmd::run_cmd("help".as_bytes());
     print_prompt();
 
+    // The terminal is in canonical mode, so the input is echoed and edited by
+    // it, and each read returns at most one line.
     loop {
-        if stdin.read(&mut buf[cursor..cursor + 1]).ok() != Some(1) {
-            continue;
-        }
-        if buf[cursor] == b'\x1b' {
-            buf[cursor] = b'^';
-        }
-        match buf[cursor] {
-            CR | LF => {
-                println!();
-                if cursor > 0 {
-                    cmd::run_cmd(&buf[..cursor]);
-                    cursor = 0;
-                }
-                print_prompt();
-            }
-            BS | DL => {
-                if cursor > 0 {
-                    stdout.write_all(&[BS, SPACE, BS]).unwrap();
-                    cursor -= 1;
-                }
-            }
-            0..=31 => {}
-            c => {
-                if cursor < MAX_CMD_LEN - 1 {
-                    stdout.write_all(&[c]).unwrap();
-                    cursor += 1;
-                }
-            }
+        let len = match stdin.read(&mut buf) {
+            Ok(0) => break, // end of file
+            Ok(len) => len,
+            Err(_) => continue,
+        };
+        let line = buf[..len].strip_suffix(&[LF]).unwrap_or(&buf[..len]);
+        if !line.is_empty() {
+            cmd::run_cmd(line);
         }
+        print_prompt();
     }
 }
@@ -0,0 +1,296 @@
+//! Per-CPU caches of small memory blocks.
+//!
+//! Each CPU keeps a magazine of free blocks for every size class, so that most
+//! small allocations and deallocations only access the cache of the current
+//! CPU with IRQs disabled, instead of contending for the lock of the byte
+//! allocator. Magazines are refilled from and flushed back to the byte
+//! allocator in batches, and blocks that stay unused for a while are given
+//! back gradually, as the CPU uses the cache or becomes idle (see
+//! [`GlobalAllocator::trim_cache`]).
+//!
+//! Size classes are 16 bytes apart up to 128 bytes, and a quarter of the power
+//! of two below them apart up to 2 KB, so that at most about a fifth of a block
+//! is wasted. Blocks are only aligned to 16 bytes, layouts with larger
+//! alignments are not cached.
+
+use core::alloc::Layout;
+use core::ptr::NonNull;
+
+use allocator::{AllocResult, ByteAllocator};
+use kernel_guard::NoPreemptIrqSave;
+use kspin::SpinNoIrq;
+
+use crate::{DefaultByteAllocator, GlobalAllocator};
+
+/// The alignment of blocks in all size classes, and the step between the
+/// smallest size classes.
+const CLASS_ALIGN: usize = 16;
+/// The number of the smallest size classes, which are [`CLASS_ALIGN`] bytes
+/// apart, up to 128 bytes.
+const LINEAR_CLASSES: usize = 8;
+/// The shift of the largest size of the linear classes (128 bytes).
+const LINEAR_MAX_SHIFT: usize = 7;
+/// The number of size classes between two powers of two above the linear
+/// classes.
+const STEPS_PER_POWER: usize = 4;
+/// The size of the largest size class (2 KB).
+const MAX_CLASS_SIZE: usize = 2048;
+/// The number of size classes.
+const NUM_CLASSES: usize = LINEAR_CLASSES + 4 * STEPS_PER_POWER;
+/// The number of blocks a magazine can hold.
+const MAG_CAPACITY: usize = 32;
+/// The number of blocks moved at once when a magazine is empty or full.
+const BATCH_SIZE: usize = MAG_CAPACITY / 2;
+/// The number of cache operations on a CPU between two trims.
+const TRIM_INTERVAL: usize = 4096;
+
+/// A stack of free blocks of the same size class.
+struct Magazine {
+    /// The addresses of the blocks.
+    blocks: [usize; MAG_CAPACITY],
+    len: usize,
+    /// The minimum `len` since the last trim, i.e., the number of blocks that
+    /// have not been used since then.
+    low: usize,
+}
+
+impl Magazine {
+    const fn new() -> Self {
+        Self {
+            blocks: [0; MAG_CAPACITY],
+            len: 0,
+            low: 0,
+        }
+    }
+
+    fn push(&mut self, ptr: NonNull<u8>) {
+        self.blocks[self.len] = ptr.as_ptr() as usize;
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<NonNull<u8>> {
+        self.len = self.len.checked_sub(1)?;
+        self.low = self.low.min(self.len);
+        NonNull::new(self.blocks[self.len] as *mut u8)
+    }
+
+    /// Gives back at most `count` blocks to the byte allocator.
+    fn flush(&mut self, balloc: &mut DefaultByteAllocator, layout: Layout, count: usize) {
+        for _ in 0..count.min(self.len) {
+            if let Some(ptr) = self.pop() {
+                balloc.dealloc(ptr, layout);
+            }
+        }
+    }
+}
+
+struct CpuCache {
+    mags: [Magazine; NUM_CLASSES],
+    /// The number of operations since the last trim.
+    ops: usize,
+}
+
+impl CpuCache {
+    const fn new() -> Self {
+        Self {
+            mags: [const { Magazine::new() }; NUM_CLASSES],
+            ops: 0,
+        }
+    }
+
+    /// Counts an operation, and trims the cache once every [`TRIM_INTERVAL`]
+    /// operations.
+    fn tick(&mut self, balloc: &SpinNoIrq<DefaultByteAllocator>) {
+        self.ops += 1;
+        if self.ops >= TRIM_INTERVAL {
+            self.trim(balloc);
+        }
+    }
+
+    /// Gives back half of the blocks unused since the last trim.
+    fn trim(&mut self, balloc: &SpinNoIrq<DefaultByteAllocator>) {
+        self.ops = 0;
+        // Do not contend for the lock if there is nothing to give back.
+        if self.mags.iter().any(|mag| mag.low > 0) {
+            let mut balloc = balloc.lock();
+            for (class, mag) in self.mags.iter_mut().enumerate() {
+                mag.flush(&mut balloc, class_layout(class), mag.low.div_ceil(2));
+            }
+        }
+        for mag in &mut self.mags {
+            mag.low = mag.len;
+        }
+    }
+}
+
+#[percpu::def_percpu]
+static CPU_CACHE: CpuCache = CpuCache::new();
+
+/// Returns the smallest size class of blocks that can serve the layout, or
+/// `None` if it is too large or too aligned to be cached.
+pub(crate) fn size_class(layout: Layout) -> Option<usize> {
+    let size = layout.size();
+    if size > MAX_CLASS_SIZE || layout.align() > CLASS_ALIGN {
+        return None;
+    }
+    if size <= LINEAR_CLASSES * CLASS_ALIGN {
+        return Some(size.saturating_sub(1) / CLASS_ALIGN);
+    }
+    // The power of two below the size, and the step between classes above it.
+    let shift = (usize::BITS - 1 - (size - 1).leading_zeros()) as usize;
+    let step = 1 << (shift - 2);
+    let index = (size - 1 - (1 << shift)) / step;
+    Some(LINEAR_CLASSES + (shift - LINEAR_MAX_SHIFT) * STEPS_PER_POWER + index)
+}
+
+/// Returns the layout of blocks in the size class.
+fn class_layout(class: usize) -> Layout {
+    let size = if class < LINEAR_CLASSES {
+        (class + 1) * CLASS_ALIGN
+    } else {
+        let power = (1 << LINEAR_MAX_SHIFT) << ((class - LINEAR_CLASSES) / STEPS_PER_POWER);
+        power + ((class - LINEAR_CLASSES) % STEPS_PER_POWER + 1) * (power / STEPS_PER_POWER)
+    };
+    Layout::from_size_align(size, CLASS_ALIGN).unwrap()
+}
+
+impl GlobalAllocator {
+    /// Allocates a block of the size class from the cache of the current CPU.
+    ///
+    /// If the cache is empty, it is refilled with a batch of blocks from the
+    /// byte allocator, which grows the heap if needed.
+    pub(crate) fn alloc_cached(&self, class: usize) -> AllocResult<NonNull<u8>> {
+        let layout = class_layout(class);
+        let _guard = NoPreemptIrqSave::new();
+        // Safety: preemption and IRQs are disabled.
+        let cache = unsafe { CPU_CACHE.current_ref_mut_raw() };
+        let mag = &mut cache.mags[class];
+        if mag.len == 0 {
+            let mut balloc = self.balloc.lock();
+            while mag.len < BATCH_SIZE {
+                match balloc.alloc(layout) {
+                    Ok(ptr) => mag.push(ptr),
+                    Err(_) => break,
+                }
+            }
+        }
+        let ptr = match mag.pop() {
+            Some(ptr) => ptr,
+            None => self.alloc_uncached(layout)?,
+        };
+        cache.tick(&self.balloc);
+        Ok(ptr)
+    }
+
+    /// Gives back a block of the size class to the cache of the current CPU.
+    ///
+    /// If the cache is full, a batch of blocks is flushed back to the byte
+    /// allocator first.
+    pub(crate) fn dealloc_cached(&self, pos: NonNull<u8>, class: usize) {
+        let _guard = NoPreemptIrqSave::new();
+        // Safety: preemption and IRQs are disabled.
+        let cache = unsafe { CPU_CACHE.current_ref_mut_raw() };
+        let mag = &mut cache.mags[class];
+        if mag.len == MAG_CAPACITY {
+            mag.flush(&mut self.balloc.lock(), class_layout(class), BATCH_SIZE);
+        }
+        mag.push(pos);
+        cache.tick(&self.balloc);
+    }
+
+    /// Gives back all the blocks in the cache of the current CPU to the byte
+    /// allocator.
+    ///
+    /// The caches of other CPUs are not affected. They are trimmed gradually
+    /// as the CPUs allocate and free memory, or by [`trim_cache`].
+    ///
+    /// [`trim_cache`]: GlobalAllocator::trim_cache
+    pub fn flush_cache(&self) {
+        let _guard = NoPreemptIrqSave::new();
+        // Safety: preemption and IRQs are disabled.
+        let cache = unsafe { CPU_CACHE.current_ref_mut_raw() };
+        let mut balloc = self.balloc.lock();
+        for (class, mag) in cache.mags.iter_mut().enumerate() {
+            mag.flush(&mut balloc, class_layout(class), MAG_CAPACITY);
+        }
+    }
+
+    /// Gives back half of the blocks in the cache of the current CPU that are
+    /// unused since the last trim.
+    ///
+    /// It should be called when the CPU becomes idle, so that the caches of
+    /// CPUs that stay idle, and get no timer ticks in the tickless mode, are
+    /// trimmed as well.
+    pub fn trim_cache(&self) {
+        let _guard = NoPreemptIrqSave::new();
+        // Safety: preemption and IRQs are disabled.
+        let cache = unsafe { CPU_CACHE.current_ref_mut_raw() };
+        cache.trim(&self.balloc);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class_of(size: usize, align: usize) -> Option<usize> {
+        size_class(Layout::from_size_align(size, align).unwrap())
+    }
+
+    fn class_size(class: usize) -> usize {
+        class_layout(class).size()
+    }
+
+    #[test]
+    fn size_classes() {
+        assert_eq!(class_of(0, 1), Some(0));
+        assert_eq!(class_of(1, 1), Some(0));
+        assert_eq!(class_of(16, 8), Some(0));
+        assert_eq!(class_of(17, 8), Some(1));
+        assert_eq!(class_of(128, 8), Some(7));
+        assert_eq!(class_of(129, 8), Some(8));
+        assert_eq!(class_of(1024, 8), Some(19));
+        assert_eq!(class_of(1025, 8), Some(20));
+        assert_eq!(class_of(2048, 8), Some(NUM_CLASSES - 1));
+        assert_eq!(class_of(2049, 1), None);
+        assert_eq!(class_of(4096, 4096), None);
+    }
+
+    #[test]
+    fn align_over_class_align() {
+        assert_eq!(class_of(8, 16), Some(0));
+        assert_eq!(class_of(8, 64), None);
+        assert_eq!(class_of(1, 2048), None);
+    }
+
+    #[test]
+    fn class_layouts() {
+        assert_eq!(class_layout(0), Layout::from_size_align(16, 16).unwrap());
+        assert_eq!(
+            class_layout(NUM_CLASSES - 1),
+            Layout::from_size_align(2048, 16).unwrap()
+        );
+        let sizes: Vec<_> = (LINEAR_CLASSES - 1..LINEAR_CLASSES + 5)
+            .map(class_size)
+            .collect();
+        assert_eq!(sizes, [128, 160, 192, 224, 256, 320]);
+    }
+
+    #[test]
+    fn fitting_classes() {
+        for size in 0..=MAX_CLASS_SIZE {
+            for align in [1, 8, 16] {
+                let layout = Layout::from_size_align(size, align).unwrap();
+                let class = size_class(layout).unwrap();
+                // Blocks of the class can serve the layout.
+                let block = class_layout(class);
+                assert!(block.size() >= size && block.align() >= align);
+                // And it is the smallest such class.
+                assert!(class == 0 || class_size(class - 1) < size);
+            }
+            // At most a quarter of the size is wasted.
+            let block_size = class_size(class_of(size, 1).unwrap());
+            assert!(block_size - size <= CLASS_ALIGN.max(size / 4));
+        }
+    }
+}
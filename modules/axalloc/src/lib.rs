@@ -4,8 +4,18 @@
 //! [`core::alloc::GlobalAlloc`]. A static global variable of type
 //! [`GlobalAllocator`] is defined with the `#[global_allocator]` attribute, to
 //! be registered as the standard library’s default allocator.
+//!
+//! # Cargo Features
+//!
+//! - `tlsf`, `slab`, `buddy`: Use the TLSF (default), slab or buddy byte
+//!   allocator.
+//! - `smp`: Serve small allocations from per-CPU caches in front of the byte
+//!   allocator, to avoid contending for its lock on multi-core systems.
+//! - `tracking`: Track live allocations and check for misuse of the heap, to
+//!   debug memory leaks and corruption (see `tag_usage` and `dump_heap`). The
+//!   `AllocTrackIf` interface must be implemented.
 
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
 #[macro_use]
 extern crate log;
@@ -13,6 +23,13 @@ extern crate alloc;
 
 mod page;
 
+mod tag;
+
+#[cfg(feature = "smp")]
+mod cache;
+#[cfg(feature = "tracking")]
+mod tracking;
+
 use allocator::{AllocResult, BaseAllocator, BitmapPageAllocator, ByteAllocator, PageAllocator};
 use core::alloc::{GlobalAlloc, Layout};
 use core::ptr::NonNull;
@@ -22,6 +39,12 @@ const PAGE_SIZE: usize = 0x1000;
 const MIN_HEAP_SIZE: usize = 0x8000; // 32 K
 
 pub use page::GlobalPage;
+pub use tag::{AllocTag, tag_alloc, tag_pages};
+#[cfg(feature = "tracking")]
+pub use tracking::{
+    AllocInfo, AllocTrackIf, POISON_FREE, TagUsage, dump_heap, largest_allocations,
+    oldest_allocations, tag_usage,
+};
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "slab")] {
@@ -46,6 +69,11 @@ cfg_if::cfg_if! {
 /// Currently, [`TlsfByteAllocator`] is used as the byte allocator, while
 /// [`BitmapPageAllocator`] is used as the page allocator.
 ///
+/// With the `smp` feature, small allocations are served from per-CPU caches
+/// of free blocks, which are refilled from and flushed back to the byte
+/// allocator in batches. The caches are shared by all instances, so only the
+/// one returned by [`global_allocator`] should be used.
+///
 /// [`TlsfByteAllocator`]: allocator::TlsfByteAllocator
 pub struct GlobalAllocator {
     balloc: SpinNoIrq<DefaultByteAllocator>,
@@ -82,18 +110,24 @@ impl GlobalAllocator {
     pub fn init(&self, start_vaddr: usize, size: usize) {
         assert!(size > MIN_HEAP_SIZE);
         let init_heap_size = MIN_HEAP_SIZE;
-        self.palloc.lock().init(start_vaddr, size);
-        let heap_ptr = self
+        let mut palloc = self.palloc.lock();
+        palloc.init(start_vaddr, size);
+        let heap_ptr = palloc
             .alloc_pages(init_heap_size / PAGE_SIZE, PAGE_SIZE)
             .unwrap();
         self.balloc.lock().init(heap_ptr, init_heap_size);
+        #[cfg(feature = "tracking")]
+        tracking::add_heap_region(start_vaddr, size);
     }
 
     /// Add the given region to the allocator.
     ///
     /// It will add the whole region to the byte allocator.
     pub fn add_memory(&self, start_vaddr: usize, size: usize) -> AllocResult {
-        self.balloc.lock().add_memory(start_vaddr, size)
+        self.balloc.lock().add_memory(start_vaddr, size)?;
+        #[cfg(feature = "tracking")]
+        tracking::add_heap_region(start_vaddr, size);
+        Ok(())
     }
 
     /// Allocate arbitrary number of bytes. Returns the left bound of the
@@ -103,25 +137,68 @@ impl GlobalAllocator {
     /// memory, it asks the page allocator for more memory and adds it to the
     /// byte allocator.
     pub fn alloc(&self, layout: Layout) -> AllocResult<NonNull<u8>> {
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "tracking")] {
+                tracking::alloc(self, layout)
+            } else {
+                self.alloc_untracked(layout)
+            }
+        }
+    }
+
+    pub(crate) fn alloc_untracked(&self, layout: Layout) -> AllocResult<NonNull<u8>> {
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "smp")] {
+                let alloc = || match cache::size_class(layout) {
+                    Some(class) => self.alloc_cached(class),
+                    None => self.alloc_uncached(layout),
+                };
+                // The free memory may be held by the cache of this CPU.
+                alloc().or_else(|_| {
+                    self.flush_cache();
+                    alloc()
+                })
+            } else {
+                self.alloc_uncached(layout)
+            }
+        }
+    }
+
+    fn alloc_uncached(&self, layout: Layout) -> AllocResult<NonNull<u8>> {
         // simple two-level allocator: if no heap memory, allocate from the page allocator.
-        let mut balloc = self.balloc.lock();
         loop {
-            if let Ok(ptr) = balloc.alloc(layout) {
-                return Ok(ptr);
-            } else {
-                let old_size = balloc.total_bytes();
-                let expand_size = old_size
-                    .max(layout.size())
-                    .next_power_of_two()
-                    .max(PAGE_SIZE);
-                let heap_ptr = self.alloc_pages(expand_size / PAGE_SIZE, PAGE_SIZE)?;
-                debug!(
-                    "expand heap memory: [{:#x}, {:#x})",
-                    heap_ptr,
-                    heap_ptr + expand_size
-                );
-                balloc.add_memory(heap_ptr, expand_size)?;
+            let old_size = {
+                let mut balloc = self.balloc.lock();
+                if let Ok(ptr) = balloc.alloc(layout) {
+                    return Ok(ptr);
+                }
+                balloc.total_bytes()
+            };
+            // Allocate pages without holding the lock of the byte allocator, so
+            // that others can still use it meanwhile.
+            let expand_size = old_size
+                .max(layout.size())
+                .next_power_of_two()
+                .max(PAGE_SIZE);
+            let heap_ptr = self
+                .palloc
+                .lock()
+                .alloc_pages(expand_size / PAGE_SIZE, PAGE_SIZE)?;
+            let mut balloc = self.balloc.lock();
+            if balloc.total_bytes() != old_size {
+                // Someone else has expanded the heap, so just retry.
+                drop(balloc);
+                self.palloc
+                    .lock()
+                    .dealloc_pages(heap_ptr, expand_size / PAGE_SIZE);
+                continue;
             }
+            debug!(
+                "expand heap memory: [{:#x}, {:#x})",
+                heap_ptr,
+                heap_ptr + expand_size
+            );
+            balloc.add_memory(heap_ptr, expand_size)?;
         }
     }
 
@@ -133,6 +210,20 @@ impl GlobalAllocator {
     ///
     /// [`alloc`]: GlobalAllocator::alloc
     pub fn dealloc(&self, pos: NonNull<u8>, layout: Layout) {
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "tracking")] {
+                tracking::dealloc(self, pos, layout)
+            } else {
+                self.dealloc_untracked(pos, layout)
+            }
+        }
+    }
+
+    pub(crate) fn dealloc_untracked(&self, pos: NonNull<u8>, layout: Layout) {
+        #[cfg(feature = "smp")]
+        if let Some(class) = cache::size_class(layout) {
+            return self.dealloc_cached(pos, class);
+        }
         self.balloc.lock().dealloc(pos, layout)
     }
 
@@ -143,7 +234,10 @@ impl GlobalAllocator {
     /// `align_pow2` must be a power of 2, and the returned region bound will be
     /// aligned to it.
     pub fn alloc_pages(&self, num_pages: usize, align_pow2: usize) -> AllocResult<usize> {
-        self.palloc.lock().alloc_pages(num_pages, align_pow2)
+        let vaddr = self.palloc.lock().alloc_pages(num_pages, align_pow2)?;
+        #[cfg(feature = "tracking")]
+        tracking::record_pages(vaddr, num_pages);
+        Ok(vaddr)
     }
 
     /// Gives back the allocated pages starts from `pos` to the page allocator.
@@ -154,10 +248,14 @@ impl GlobalAllocator {
     ///
     /// [`alloc_pages`]: GlobalAllocator::alloc_pages
     pub fn dealloc_pages(&self, pos: usize, num_pages: usize) {
+        #[cfg(feature = "tracking")]
+        tracking::forget_pages(pos, num_pages);
         self.palloc.lock().dealloc_pages(pos, num_pages)
     }
 
     /// Returns the number of allocated bytes in the byte allocator.
+    ///
+    /// It includes the free blocks held by the per-CPU caches.
     pub fn used_bytes(&self) -> usize {
         self.balloc.lock().used_bytes()
     }
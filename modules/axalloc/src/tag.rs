@@ -0,0 +1,54 @@
+/// The subsystem that an allocation belongs to, for accounting.
+///
+/// Allocations are [`Untagged`] when made, and can be tagged by their owners
+/// with [`tag_alloc`] or [`tag_pages`] afterwards.
+///
+/// [`Untagged`]: AllocTag::Untagged
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AllocTag {
+    /// Not tagged by the owner.
+    #[default]
+    Untagged,
+    /// Kernel stacks of tasks.
+    TaskStack,
+    /// Buffers of network sockets.
+    NetBuffer,
+    /// Caches of file systems and disks.
+    FsCache,
+}
+
+impl AllocTag {
+    /// All the tags.
+    pub const ALL: [Self; 4] = [
+        Self::Untagged,
+        Self::TaskStack,
+        Self::NetBuffer,
+        Self::FsCache,
+    ];
+}
+
+/// Tags the heap allocation starting at `ptr`.
+///
+/// It does nothing without the `tracking` feature, or if `ptr` is not the
+/// start of a live heap allocation.
+pub fn tag_alloc<T>(ptr: *const T, tag: AllocTag) {
+    #[cfg(feature = "tracking")]
+    crate::tracking::set_heap_tag(ptr.cast(), tag);
+    #[cfg(not(feature = "tracking"))]
+    let _ = (ptr, tag);
+}
+
+/// Tags the pages starting at `vaddr`, which are allocated by
+/// [`GlobalAllocator::alloc_pages`].
+///
+/// It does nothing without the `tracking` feature, or if there is no such
+/// page allocation.
+///
+/// [`GlobalAllocator::alloc_pages`]: crate::GlobalAllocator::alloc_pages
+pub fn tag_pages(vaddr: usize, tag: AllocTag) {
+    #[cfg(feature = "tracking")]
+    crate::tracking::set_pages_tag(vaddr, tag);
+    #[cfg(not(feature = "tracking"))]
+    let _ = (vaddr, tag);
+}
@@ -0,0 +1,646 @@
+//! Allocation tracking, to find out who leaks or misuses memory.
+//!
+//! Every live allocation records its [`AllocTag`], the task that made it and
+//! when. Heap allocations keep the record in a header in front of the memory
+//! returned, and are linked in a list in the order of allocation, while page
+//! allocations are recorded in a map. The memory in use is counted per tag
+//! (see [`tag_usage`]), and the largest and oldest allocations can be listed
+//! (see [`largest_allocations`], [`oldest_allocations`] and [`dump_heap`]).
+//!
+//! Freed heap memory is filled with [`POISON_FREE`] and held in a quarantine
+//! for a while before it is reused. Double frees, frees with a wrong layout
+//! and writes to memory in the quarantine are reported by panicking.
+//!
+//! It makes allocations slower and larger, and is meant for debugging only.
+
+use alloc::collections::BTreeMap;
+use core::alloc::Layout;
+use core::fmt;
+use core::mem::{align_of, size_of};
+use core::ptr::{NonNull, null_mut};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+
+use allocator::{AllocError, AllocResult};
+use kspin::SpinNoIrq;
+
+use crate::{AllocTag, GlobalAllocator, PAGE_SIZE};
+
+/// The byte that freed heap memory is filled with.
+pub const POISON_FREE: u8 = 0x6b;
+
+const MAGIC_LIVE: u32 = 0xa110_c8ed;
+const MAGIC_FREED: u32 = 0xdead_f4ee;
+/// The number of freed heap allocations held in the quarantine.
+const QUARANTINE_LEN: usize = 256;
+/// The maximum number of memory regions of the heap.
+const MAX_HEAP_REGIONS: usize = 16;
+/// The maximum number of allocations listed by [`dump_heap`].
+const MAX_DUMP: usize = 16;
+
+/// Extern interfaces that must be implemented in other crates.
+#[crate_interface::def_interface]
+pub trait AllocTrackIf {
+    /// Gets current clock time.
+    fn current_time() -> Duration;
+
+    /// Gets current task ID, or [`None`] if no task is running.
+    fn current_task_id() -> Option<u64>;
+}
+
+/// Information of a live allocation.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AllocInfo {
+    /// The start address.
+    pub addr: usize,
+    /// The size in bytes.
+    pub size: usize,
+    /// Whether it is allocated by [`GlobalAllocator::alloc_pages`].
+    pub pages: bool,
+    /// The tag.
+    pub tag: AllocTag,
+    /// The ID of the task that made it, if any.
+    pub task_id: Option<u64>,
+    /// When it was made.
+    pub time: Duration,
+}
+
+impl fmt::Display for AllocInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:#x}: {} bytes{}, {:?}, at {:?}",
+            self.addr,
+            self.size,
+            if self.pages { " of pages" } else { "" },
+            self.tag,
+            self.time,
+        )?;
+        if let Some(id) = self.task_id {
+            write!(f, " by task {id}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The memory in use of a tag.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TagUsage {
+    /// The number of bytes, excluding the headers of heap allocations.
+    pub bytes: usize,
+    /// The number of allocations.
+    pub count: usize,
+}
+
+struct TagCounter {
+    bytes: AtomicUsize,
+    count: AtomicUsize,
+}
+
+impl TagCounter {
+    const fn new() -> Self {
+        Self {
+            bytes: AtomicUsize::new(0),
+            count: AtomicUsize::new(0),
+        }
+    }
+
+    fn add(&self, bytes: usize) {
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn sub(&self, bytes: usize) {
+        self.bytes.fetch_sub(bytes, Ordering::Relaxed);
+        self.count.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+static USAGE: [TagCounter; AllocTag::ALL.len()] =
+    [const { TagCounter::new() }; AllocTag::ALL.len()];
+
+fn usage(tag: AllocTag) -> &'static TagCounter {
+    &USAGE[tag as usize]
+}
+
+/// The record of a heap allocation, placed right before the memory returned.
+#[repr(C)]
+struct Header {
+    prev: *mut Header,
+    next: *mut Header,
+    size: usize,
+    align: usize,
+    time: Duration,
+    task_id: Option<u64>,
+    tag: AllocTag,
+    magic: u32,
+}
+
+const HEADER_SIZE: usize = size_of::<Header>();
+
+impl Header {
+    /// Returns the header of the heap allocation at `ptr`.
+    fn of(ptr: NonNull<u8>) -> *mut Header {
+        ptr.as_ptr().wrapping_sub(HEADER_SIZE).cast()
+    }
+
+    fn info(&self) -> AllocInfo {
+        AllocInfo {
+            addr: self as *const _ as usize + HEADER_SIZE,
+            size: self.size,
+            pages: false,
+            tag: self.tag,
+            task_id: self.task_id,
+            time: self.time,
+        }
+    }
+}
+
+/// Returns the layout actually allocated for `layout`, with room for the
+/// header, and the offset of the memory returned in it.
+fn outer_layout(layout: Layout) -> AllocResult<(Layout, usize)> {
+    let align = layout.align().max(align_of::<Header>());
+    let offset = HEADER_SIZE.next_multiple_of(align);
+    let size = offset
+        .checked_add(layout.size())
+        .ok_or(AllocError::NoMemory)?;
+    let outer = Layout::from_size_align(size, align).map_err(|_| AllocError::NoMemory)?;
+    Ok((outer, offset))
+}
+
+/// A misuse of the heap found when freeing.
+enum Misuse {
+    DoubleFree,
+    NotAllocated,
+    WrongLayout { size: usize, align: usize },
+}
+
+struct HeapTracker {
+    /// The oldest live allocation.
+    head: *mut Header,
+    /// The newest live allocation.
+    tail: *mut Header,
+    quarantine: [Option<(NonNull<u8>, Layout)>; QUARANTINE_LEN],
+    next_quarantine: usize,
+    regions: [(usize, usize); MAX_HEAP_REGIONS],
+    num_regions: usize,
+}
+
+// Safety: the headers are only accessed with the lock held.
+unsafe impl Send for HeapTracker {}
+
+impl HeapTracker {
+    const fn new() -> Self {
+        Self {
+            head: null_mut(),
+            tail: null_mut(),
+            quarantine: [None; QUARANTINE_LEN],
+            next_quarantine: 0,
+            regions: [(0, 0); MAX_HEAP_REGIONS],
+            num_regions: 0,
+        }
+    }
+
+    /// Returns the live header at `ptr`, if it is in the heap.
+    fn live_header(&mut self, ptr: *const u8) -> Option<&mut Header> {
+        let header = (ptr as usize).checked_sub(HEADER_SIZE)?;
+        let in_heap = self.regions[..self.num_regions]
+            .iter()
+            .any(|&(start, end)| (start..end).contains(&header));
+        if !in_heap || header % align_of::<Header>() != 0 {
+            return None;
+        }
+        // Safety: the header is in the heap, and is valid if the magic matches.
+        let header = unsafe { &mut *(header as *mut Header) };
+        (header.magic == MAGIC_LIVE).then_some(header)
+    }
+
+    fn push(&mut self, header: *mut Header) {
+        unsafe {
+            (*header).prev = self.tail;
+            (*header).next = null_mut();
+            match self.tail.as_mut() {
+                Some(tail) => tail.next = header,
+                None => self.head = header,
+            }
+        }
+        self.tail = header;
+    }
+
+    fn unlink(&mut self, header: &mut Header) {
+        unsafe {
+            match header.prev.as_mut() {
+                Some(prev) => prev.next = header.next,
+                None => self.head = header.next,
+            }
+            match header.next.as_mut() {
+                Some(next) => next.prev = header.prev,
+                None => self.tail = header.prev,
+            }
+        }
+    }
+
+    /// Validates and unlinks the heap allocation to free, and puts it into the
+    /// quarantine. Returns the one evicted from the quarantine, if any.
+    fn free(
+        &mut self,
+        ptr: NonNull<u8>,
+        layout: Layout,
+    ) -> Result<Option<(NonNull<u8>, Layout)>, Misuse> {
+        // Safety: the memory is allocated with a header if it is valid.
+        let header = unsafe { &mut *Header::of(ptr) };
+        match header.magic {
+            MAGIC_LIVE => {}
+            MAGIC_FREED => return Err(Misuse::DoubleFree),
+            _ => return Err(Misuse::NotAllocated),
+        }
+        if header.size != layout.size() || header.align != layout.align() {
+            return Err(Misuse::WrongLayout {
+                size: header.size,
+                align: header.align,
+            });
+        }
+        self.unlink(header);
+        header.magic = MAGIC_FREED;
+        usage(header.tag).sub(header.size);
+        unsafe { ptr.as_ptr().write_bytes(POISON_FREE, layout.size()) };
+
+        let slot = &mut self.quarantine[self.next_quarantine];
+        self.next_quarantine = (self.next_quarantine + 1) % QUARANTINE_LEN;
+        Ok(slot.replace((ptr, layout)))
+    }
+}
+
+/// Returns the offset of the first byte of the freed memory that is modified
+/// since it is poisoned, if any.
+fn modified_at(ptr: NonNull<u8>, size: usize) -> Option<usize> {
+    let poisoned = unsafe { core::slice::from_raw_parts(ptr.as_ptr(), size) };
+    poisoned.iter().position(|&b| b != POISON_FREE)
+}
+
+static HEAP: SpinNoIrq<HeapTracker> = SpinNoIrq::new(HeapTracker::new());
+
+struct PageRecord {
+    num_pages: usize,
+    tag: AllocTag,
+    task_id: Option<u64>,
+    time: Duration,
+}
+
+/// Page allocations by the start address.
+static PAGES: SpinNoIrq<BTreeMap<usize, PageRecord>> = SpinNoIrq::new(BTreeMap::new());
+
+fn current_time() -> Duration {
+    crate_interface::call_interface!(AllocTrackIf::current_time)
+}
+
+fn current_task_id() -> Option<u64> {
+    crate_interface::call_interface!(AllocTrackIf::current_task_id)
+}
+
+/// Adds a memory region that heap allocations may come from.
+pub(crate) fn add_heap_region(start: usize, size: usize) {
+    let mut heap = HEAP.lock();
+    let n = heap.num_regions;
+    if n == MAX_HEAP_REGIONS {
+        warn!(
+            "too many heap regions to track: [{:#x}, {:#x})",
+            start,
+            start + size
+        );
+        return;
+    }
+    heap.regions[n] = (start, start + size);
+    heap.num_regions += 1;
+}
+
+pub(crate) fn alloc(galloc: &GlobalAllocator, layout: Layout) -> AllocResult<NonNull<u8>> {
+    let (outer, offset) = outer_layout(layout)?;
+    let ptr = unsafe { galloc.alloc_untracked(outer)?.add(offset) };
+    let header = Header::of(ptr);
+    unsafe {
+        header.write(Header {
+            prev: null_mut(),
+            next: null_mut(),
+            size: layout.size(),
+            align: layout.align(),
+            time: current_time(),
+            task_id: current_task_id(),
+            tag: AllocTag::Untagged,
+            magic: MAGIC_LIVE,
+        })
+    };
+    HEAP.lock().push(header);
+    usage(AllocTag::Untagged).add(layout.size());
+    Ok(ptr)
+}
+
+pub(crate) fn dealloc(galloc: &GlobalAllocator, ptr: NonNull<u8>, layout: Layout) {
+    let res = HEAP.lock().free(ptr, layout);
+    let evicted = match res {
+        Ok(evicted) => evicted,
+        Err(Misuse::DoubleFree) => panic!("double free of {:p} ({:?})", ptr, layout),
+        Err(Misuse::NotAllocated) => {
+            panic!("freeing {:p} ({:?}), which is not allocated", ptr, layout)
+        }
+        Err(Misuse::WrongLayout { size, align }) => panic!(
+            "freeing {:p} with {:?}, but it is allocated with size {} and align {}",
+            ptr, layout, size, align
+        ),
+    };
+    if let Some((ptr, layout)) = evicted {
+        if let Some(pos) = modified_at(ptr, layout.size()) {
+            let header = unsafe { &*Header::of(ptr) };
+            panic!(
+                "use after free: {:p} ({:?}, {:?}, by task {:?}) is modified at offset {}",
+                ptr, layout, header.tag, header.task_id, pos
+            );
+        }
+        let (outer, offset) = outer_layout(layout).unwrap();
+        galloc.dealloc_untracked(unsafe { ptr.sub(offset) }, outer);
+    }
+}
+
+pub(crate) fn set_heap_tag(ptr: *const u8, tag: AllocTag) {
+    let mut heap = HEAP.lock();
+    if let Some(header) = heap.live_header(ptr) {
+        usage(header.tag).sub(header.size);
+        usage(tag).add(header.size);
+        header.tag = tag;
+    }
+}
+
+pub(crate) fn record_pages(vaddr: usize, num_pages: usize) {
+    let record = PageRecord {
+        num_pages,
+        tag: AllocTag::Untagged,
+        task_id: current_task_id(),
+        time: current_time(),
+    };
+    PAGES.lock().insert(vaddr, record);
+    usage(AllocTag::Untagged).add(num_pages * PAGE_SIZE);
+}
+
+pub(crate) fn forget_pages(vaddr: usize, num_pages: usize) {
+    let record = PAGES.lock().remove(&vaddr);
+    match record {
+        Some(record) if record.num_pages == num_pages => {
+            usage(record.tag).sub(num_pages * PAGE_SIZE);
+        }
+        Some(record) => panic!(
+            "freeing {} pages at {:#x}, but {} pages are allocated",
+            num_pages, vaddr, record.num_pages
+        ),
+        None => panic!("freeing pages at {:#x}, which are not allocated", vaddr),
+    }
+}
+
+pub(crate) fn set_pages_tag(vaddr: usize, tag: AllocTag) {
+    let mut pages = PAGES.lock();
+    if let Some(record) = pages.get_mut(&vaddr) {
+        usage(record.tag).sub(record.num_pages * PAGE_SIZE);
+        usage(tag).add(record.num_pages * PAGE_SIZE);
+        record.tag = tag;
+    }
+}
+
+/// Returns the memory in use of the tag.
+pub fn tag_usage(tag: AllocTag) -> TagUsage {
+    let counter = usage(tag);
+    TagUsage {
+        bytes: counter.bytes.load(Ordering::Relaxed),
+        count: counter.count.load(Ordering::Relaxed),
+    }
+}
+
+/// Calls `f` on every live allocation, with the allocator locked.
+fn for_each_allocation(mut f: impl FnMut(AllocInfo)) {
+    {
+        let heap = HEAP.lock();
+        let mut header = heap.head;
+        while let Some(h) = unsafe { header.as_ref() } {
+            f(h.info());
+            header = h.next;
+        }
+    }
+    for (&vaddr, record) in PAGES.lock().iter() {
+        f(AllocInfo {
+            addr: vaddr,
+            size: record.num_pages * PAGE_SIZE,
+            pages: true,
+            tag: record.tag,
+            task_id: record.task_id,
+            time: record.time,
+        });
+    }
+}
+
+/// Fills `out` with the allocations that come first in the order of
+/// `before`, and returns the number filled.
+fn select(out: &mut [AllocInfo], before: impl Fn(&AllocInfo, &AllocInfo) -> bool) -> usize {
+    let mut n = 0;
+    for_each_allocation(|info| {
+        let pos = out[..n].iter().position(|o| before(&info, o)).unwrap_or(n);
+        if pos == out.len() {
+            return;
+        }
+        n = (n + 1).min(out.len());
+        out.copy_within(pos..n - 1, pos + 1);
+        out[pos] = info;
+    });
+    n
+}
+
+/// Fills `out` with the largest live allocations, from the largest one, and
+/// returns the number filled.
+pub fn largest_allocations(out: &mut [AllocInfo]) -> usize {
+    select(out, |a, b| a.size > b.size)
+}
+
+/// Fills `out` with the oldest live allocations, from the oldest one, and
+/// returns the number filled.
+pub fn oldest_allocations(out: &mut [AllocInfo]) -> usize {
+    select(out, |a, b| a.time < b.time)
+}
+
+/// Prints the memory in use of each tag, and the `n` largest and oldest live
+/// allocations (up to 16), to the log at the info level.
+pub fn dump_heap(n: usize) {
+    info!("memory in use by tag:");
+    for tag in AllocTag::ALL {
+        let usage = tag_usage(tag);
+        info!(
+            "  {:?}: {} bytes in {} allocations",
+            tag, usage.bytes, usage.count
+        );
+    }
+
+    let mut buf = [AllocInfo::default(); MAX_DUMP];
+    let buf = &mut buf[..n.min(MAX_DUMP)];
+    let count = largest_allocations(buf);
+    info!("largest allocations:");
+    for info in &buf[..count] {
+        info!("  {}", info);
+    }
+    let count = oldest_allocations(buf);
+    info!("oldest allocations:");
+    for info in &buf[..count] {
+        info!("  {}", info);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::AtomicU64;
+
+    use super::*;
+
+    static NOW: AtomicU64 = AtomicU64::new(0);
+
+    struct AllocTrackIfImpl;
+
+    #[crate_interface::impl_interface]
+    impl AllocTrackIf for AllocTrackIfImpl {
+        fn current_time() -> Duration {
+            Duration::from_secs(NOW.load(Ordering::Relaxed))
+        }
+
+        fn current_task_id() -> Option<u64> {
+            None
+        }
+    }
+
+    /// Allocates from the host heap with a header, as [`alloc`] does.
+    fn track(heap: &mut HeapTracker, layout: Layout) -> NonNull<u8> {
+        let (outer, offset) = outer_layout(layout).unwrap();
+        let ptr = unsafe { NonNull::new(std::alloc::alloc(outer)).unwrap().add(offset) };
+        let header = Header::of(ptr);
+        unsafe {
+            header.write(Header {
+                prev: null_mut(),
+                next: null_mut(),
+                size: layout.size(),
+                align: layout.align(),
+                time: Duration::ZERO,
+                task_id: None,
+                tag: AllocTag::Untagged,
+                magic: MAGIC_LIVE,
+            })
+        };
+        heap.push(header);
+        usage(AllocTag::Untagged).add(layout.size());
+        ptr
+    }
+
+    /// Gives back the allocations in the quarantine to the host heap.
+    fn release(heap: &mut HeapTracker) {
+        for (ptr, layout) in heap.quarantine.iter_mut().filter_map(Option::take) {
+            let (outer, offset) = outer_layout(layout).unwrap();
+            unsafe { std::alloc::dealloc(ptr.as_ptr().sub(offset), outer) };
+        }
+    }
+
+    #[test]
+    fn header_room() {
+        let (outer, offset) = outer_layout(Layout::from_size_align(1, 1).unwrap()).unwrap();
+        assert_eq!(offset, HEADER_SIZE);
+        assert_eq!(outer.size(), HEADER_SIZE + 1);
+        assert_eq!(outer.align(), align_of::<Header>());
+
+        let (outer, offset) = outer_layout(Layout::from_size_align(8, 256).unwrap()).unwrap();
+        assert_eq!(offset, 256);
+        assert_eq!(outer.size(), 256 + 8);
+        assert_eq!(outer.align(), 256);
+
+        let huge = Layout::from_size_align(isize::MAX as usize, 1).unwrap();
+        assert!(outer_layout(huge).is_err());
+    }
+
+    #[test]
+    fn double_free() {
+        let mut heap = HeapTracker::new();
+        let layout = Layout::from_size_align(16, 8).unwrap();
+        let ptr = track(&mut heap, layout);
+        assert!(matches!(heap.free(ptr, layout), Ok(None)));
+        assert!(matches!(heap.free(ptr, layout), Err(Misuse::DoubleFree)));
+        release(&mut heap);
+    }
+
+    #[test]
+    fn wrong_layout() {
+        let mut heap = HeapTracker::new();
+        let layout = Layout::from_size_align(32, 8).unwrap();
+        let ptr = track(&mut heap, layout);
+        for wrong in [(16, 8), (32, 16)] {
+            let wrong = Layout::from_size_align(wrong.0, wrong.1).unwrap();
+            assert!(matches!(
+                heap.free(ptr, wrong),
+                Err(Misuse::WrongLayout { size: 32, align: 8 })
+            ));
+        }
+        // Still live and linked after the failed frees.
+        assert_eq!(heap.head, Header::of(ptr));
+        assert!(matches!(heap.free(ptr, layout), Ok(None)));
+        assert!(heap.head.is_null() && heap.tail.is_null());
+        release(&mut heap);
+    }
+
+    #[test]
+    fn quarantine_poisoning() {
+        let mut heap = HeapTracker::new();
+        let layout = Layout::from_size_align(16, 8).unwrap();
+        let ptr = track(&mut heap, layout);
+        assert!(matches!(heap.free(ptr, layout), Ok(None)));
+        assert_eq!(modified_at(ptr, layout.size()), None);
+
+        unsafe { ptr.as_ptr().add(3).write(0) };
+        for _ in 1..QUARANTINE_LEN {
+            let other = track(&mut heap, layout);
+            assert!(matches!(heap.free(other, layout), Ok(None)));
+        }
+        let last = track(&mut heap, layout);
+        let evicted = heap.free(last, layout).ok().flatten();
+        assert_eq!(evicted, Some((ptr, layout)));
+        assert_eq!(modified_at(ptr, layout.size()), Some(3));
+
+        let (outer, offset) = outer_layout(layout).unwrap();
+        unsafe { std::alloc::dealloc(ptr.as_ptr().sub(offset), outer) };
+        release(&mut heap);
+    }
+
+    #[test]
+    fn top_allocations() {
+        // Pages are listed by address, and are not accessed.
+        let pages = [
+            (0x1000_0000, 1, 3),
+            (0x2000_0000, 4, 1),
+            (0x3000_0000, 2, 2),
+            (0x4000_0000, 4, 4),
+        ];
+        for (vaddr, num_pages, time) in pages {
+            NOW.store(time, Ordering::Relaxed);
+            record_pages(vaddr, num_pages);
+        }
+        let addrs = |out: &[AllocInfo]| out.iter().map(|info| info.addr).collect::<Vec<_>>();
+
+        // Ties keep the order of listing.
+        let mut out = [AllocInfo::default(); 3];
+        assert_eq!(largest_allocations(&mut out), 3);
+        assert_eq!(addrs(&out), [0x2000_0000, 0x4000_0000, 0x3000_0000]);
+        assert_eq!(out[0].size, 4 * PAGE_SIZE);
+
+        let mut out = [AllocInfo::default(); 2];
+        assert_eq!(oldest_allocations(&mut out), 2);
+        assert_eq!(addrs(&out), [0x2000_0000, 0x3000_0000]);
+
+        let mut out = [AllocInfo::default(); 8];
+        assert_eq!(oldest_allocations(&mut out), 4);
+        let times = out[..4].iter().map(|info| info.time.as_secs());
+        assert_eq!(times.collect::<Vec<_>>(), [1, 2, 3, 4]);
+
+        for (vaddr, num_pages, _) in pages {
+            forget_pages(vaddr, num_pages);
+        }
+        assert_eq!(oldest_allocations(&mut out), 0);
+    }
+}
@@ -0,0 +1,632 @@
+//! Framebuffer text console.
+//!
+//! It shows the console output on a display, in 8x16 character cells of the
+//! built-in font (8x8 glyphs with doubled rows). It scrolls up when the last
+//! line is full, and handles the ANSI escape sequences commonly used by
+//! terminal programs and [`axlog`]:
+//!
+//! - SGR (`ESC [ n m`): the 16 colors (30-37, 40-47, 90-97, 100-107), the
+//!   default ones (39, 49), bold as bright colors (1, 22), reverse video (7,
+//!   27), and reset (0).
+//! - Cursor movement (`A`, `B`, `C`, `D`, `G`, `H`, `f`), and showing or hiding
+//!   the cursor (`ESC [ ? 25 h/l`).
+//! - Erasing the screen (`J`) and the line (`K`).
+//!
+//! Other sequences are ignored, and non-ASCII characters are shown as `?`.
+//!
+//! Output is drawn into the framebuffer with IRQs disabled, only for the rows
+//! changed, and scrolling moves the rows already drawn. It's flushed at once
+//! after IRQs are enabled again, unless the display is being used by others,
+//! in which case it is deferred to the next output. Applications should not
+//! draw on the display of the console.
+//!
+//! [`axlog`]: https://arceos-org.github.io/arceos/axlog/index.html
+
+mod font;
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use axdriver::prelude::*;
+use axerrno::AxResult;
+use kspin::SpinNoIrq;
+
+use self::font::{FIRST_CHAR, FONT};
+use crate::{DisplayMode, PixelFormat, Rect};
+
+const CELL_WIDTH: u32 = 8;
+const CELL_HEIGHT: u32 = 16;
+const TAB_WIDTH: usize = 8;
+const MAX_PARAMS: usize = 4;
+
+const DEFAULT_FG: u8 = 7;
+const DEFAULT_BG: u8 = 0;
+
+/// The 16 ANSI colors in `0xRRGGBB`, the bright ones last.
+const PALETTE: [u32; 16] = [
+    0x000000, 0xaa0000, 0x00aa00, 0xaa5500, 0x0000aa, 0xaa00aa, 0x00aaaa, 0xaaaaaa, 0x555555,
+    0xff5555, 0x55ff55, 0xffff55, 0x5555ff, 0xff55ff, 0x55ffff, 0xffffff,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Cell {
+    ch: u8,
+    fg: u8,
+    bg: u8,
+}
+
+impl Cell {
+    const BLANK: Self = Self {
+        ch: b' ',
+        fg: DEFAULT_FG,
+        bg: DEFAULT_BG,
+    };
+}
+
+/// The state of the escape sequence parser.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EscState {
+    Normal,
+    /// After `ESC`.
+    Escape,
+    /// In a control sequence, i.e., after `ESC [`.
+    Csi,
+}
+
+struct FbConsole {
+    id: usize,
+    mode: DisplayMode,
+    cols: usize,
+    rows: usize,
+    cells: Vec<Cell>,
+    cursor: (usize, usize),
+    cursor_visible: bool,
+    fg: u8,
+    bg: u8,
+    bold: bool,
+    reverse: bool,
+    esc: EscState,
+    private: bool,
+    params: [u16; MAX_PARAMS],
+    num_params: usize,
+    /// The range of rows that are changed but not drawn yet.
+    dirty: Option<(usize, usize)>,
+    /// The number of lines scrolled up since the last draw.
+    scrolled: usize,
+    /// The part of the framebuffer that is drawn but not flushed yet.
+    unflushed: Option<Rect>,
+}
+
+impl FbConsole {
+    fn new(id: usize, mode: DisplayMode) -> Self {
+        let cols = (mode.width / CELL_WIDTH).max(1) as usize;
+        let rows = (mode.height / CELL_HEIGHT).max(1) as usize;
+        Self {
+            id,
+            mode,
+            cols,
+            rows,
+            cells: vec![Cell::BLANK; cols * rows],
+            cursor: (0, 0),
+            cursor_visible: true,
+            fg: DEFAULT_FG,
+            bg: DEFAULT_BG,
+            bold: false,
+            reverse: false,
+            esc: EscState::Normal,
+            private: false,
+            params: [0; MAX_PARAMS],
+            num_params: 0,
+            dirty: Some((0, rows - 1)),
+            scrolled: 0,
+            unflushed: None,
+        }
+    }
+
+    /// Adapts to a new display mode, keeping the top-left part of the text.
+    fn resize(&mut self, mode: DisplayMode) {
+        let mut new = Self::new(self.id, mode);
+        for row in 0..self.rows.min(new.rows) {
+            for col in 0..self.cols.min(new.cols) {
+                new.cells[row * new.cols + col] = self.cells[row * self.cols + col];
+            }
+        }
+        new.cursor = (
+            self.cursor.0.min(new.cols - 1),
+            self.cursor.1.min(new.rows - 1),
+        );
+        new.cursor_visible = self.cursor_visible;
+        new.fg = self.fg;
+        new.bg = self.bg;
+        new.bold = self.bold;
+        new.reverse = self.reverse;
+        *self = new;
+    }
+
+    fn mark_dirty(&mut self, first: usize, last: usize) {
+        self.dirty = Some(match self.dirty {
+            Some((f, l)) => (f.min(first), l.max(last)),
+            None => (first, last),
+        });
+    }
+
+    fn blank(&self) -> Cell {
+        Cell {
+            ch: b' ',
+            fg: self.fg,
+            bg: self.bg,
+        }
+    }
+
+    fn clear_cells(&mut self, start: usize, end: usize) {
+        let blank = self.blank();
+        self.cells[start..end].fill(blank);
+        self.mark_dirty(start / self.cols, (end - 1) / self.cols);
+    }
+
+    fn move_cursor(&mut self, col: usize, row: usize) {
+        let row = row.min(self.rows - 1);
+        self.mark_dirty(self.cursor.1, self.cursor.1);
+        self.mark_dirty(row, row);
+        self.cursor = (col.min(self.cols - 1), row);
+    }
+
+    /// Scrolls up by a line. The rows already drawn are moved up in the
+    /// framebuffer by [`draw`](Self::draw), so only the dirty rows, which move
+    /// up too, and the new line have to be drawn.
+    fn scroll_up(&mut self) {
+        // The cursor is drawn in its row, which must be redrawn without it.
+        self.mark_dirty(self.cursor.1, self.cursor.1);
+        self.cells.copy_within(self.cols.., 0);
+        self.scrolled += 1;
+        if let Some((first, last)) = self.dirty {
+            self.dirty = Some((first.saturating_sub(1), last.saturating_sub(1)));
+        }
+        let len = self.cells.len();
+        self.clear_cells(len - self.cols, len);
+    }
+
+    fn new_line(&mut self) {
+        if self.cursor.1 + 1 == self.rows {
+            self.scroll_up();
+            self.move_cursor(self.cursor.0, self.cursor.1);
+        } else {
+            self.move_cursor(self.cursor.0, self.cursor.1 + 1);
+        }
+    }
+
+    fn put_char(&mut self, ch: u8) {
+        // The cursor stays past the last column until the next character.
+        if self.cursor.0 == self.cols {
+            self.cursor.0 = 0;
+            self.new_line();
+        }
+        let (fg, bg) = self.colors();
+        let (col, row) = self.cursor;
+        self.cells[row * self.cols + col] = Cell { ch, fg, bg };
+        self.mark_dirty(row, row);
+        self.cursor.0 += 1;
+    }
+
+    /// Returns the colors of new characters.
+    fn colors(&self) -> (u8, u8) {
+        let fg = if self.bold && self.fg < 8 {
+            self.fg + 8
+        } else {
+            self.fg
+        };
+        if self.reverse {
+            (self.bg, fg)
+        } else {
+            (fg, self.bg)
+        }
+    }
+
+    fn write_byte(&mut self, b: u8) {
+        match self.esc {
+            EscState::Normal => self.write_normal(b),
+            EscState::Escape => {
+                if b == b'[' {
+                    self.esc = EscState::Csi;
+                    self.private = false;
+                    self.params = [0; MAX_PARAMS];
+                    self.num_params = 0;
+                } else {
+                    self.esc = EscState::Normal;
+                }
+            }
+            EscState::Csi => match b {
+                b'0'..=b'9' => {
+                    if self.num_params == 0 {
+                        self.num_params = 1;
+                    }
+                    if let Some(param) = self.params.get_mut(self.num_params - 1) {
+                        *param = param.saturating_mul(10).saturating_add((b - b'0') as u16);
+                    }
+                }
+                b';' => self.num_params = (self.num_params.max(1) + 1).min(MAX_PARAMS + 1),
+                b'?' => self.private = true,
+                0x40..=0x7e => {
+                    self.esc = EscState::Normal;
+                    self.handle_csi(b);
+                }
+                // Intermediate bytes.
+                0x20..=0x2f => {}
+                _ => self.esc = EscState::Normal,
+            },
+        }
+    }
+
+    fn write_normal(&mut self, b: u8) {
+        match b {
+            0x1b => self.esc = EscState::Escape,
+            b'\n' => {
+                // Like a terminal with `ONLCR` set.
+                self.cursor.0 = 0;
+                self.new_line();
+            }
+            b'\r' => self.move_cursor(0, self.cursor.1),
+            // Backspace
+            0x08 => self.move_cursor(self.cursor.0.saturating_sub(1), self.cursor.1),
+            b'\t' => {
+                let col = (self.cursor.0 / TAB_WIDTH + 1) * TAB_WIDTH;
+                self.move_cursor(col, self.cursor.1);
+            }
+            0x20..=0x7e => self.put_char(b),
+            // Continuation bytes of UTF-8 characters.
+            0x80..=0xbf => {}
+            0xc0..=0xff => self.put_char(b'?'),
+            _ => {}
+        }
+    }
+
+    /// Returns the `i`-th parameter, or `default` if it is omitted or 0.
+    fn param(&self, i: usize, default: usize) -> usize {
+        match self.params.get(i) {
+            Some(&p) if i < self.num_params && p != 0 => p as usize,
+            _ => default,
+        }
+    }
+
+    fn handle_csi(&mut self, cmd: u8) {
+        let (col, row) = (self.cursor.0.min(self.cols - 1), self.cursor.1);
+        match cmd {
+            b'm' => self.handle_sgr(),
+            b'A' => self.move_cursor(col, row.saturating_sub(self.param(0, 1))),
+            b'B' => self.move_cursor(col, row + self.param(0, 1)),
+            b'C' => self.move_cursor(col + self.param(0, 1), row),
+            b'D' => self.move_cursor(col.saturating_sub(self.param(0, 1)), row),
+            b'G' => self.move_cursor(self.param(0, 1) - 1, row),
+            b'H' | b'f' => self.move_cursor(self.param(1, 1) - 1, self.param(0, 1) - 1),
+            b'J' => {
+                let pos = row * self.cols + col;
+                match self.param(0, 0) {
+                    0 => self.clear_cells(pos, self.cells.len()),
+                    1 => self.clear_cells(0, pos + 1),
+                    _ => self.clear_cells(0, self.cells.len()),
+                }
+            }
+            b'K' => {
+                let start = row * self.cols;
+                match self.param(0, 0) {
+                    0 => self.clear_cells(start + col, start + self.cols),
+                    1 => self.clear_cells(start, start + col + 1),
+                    _ => self.clear_cells(start, start + self.cols),
+                }
+            }
+            b'h' | b'l' if self.private && self.param(0, 0) == 25 => {
+                self.cursor_visible = cmd == b'h';
+                self.mark_dirty(row, row);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_sgr(&mut self) {
+        for i in 0..self.num_params.clamp(1, MAX_PARAMS) {
+            match self.params[i] {
+                0 => {
+                    self.fg = DEFAULT_FG;
+                    self.bg = DEFAULT_BG;
+                    self.bold = false;
+                    self.reverse = false;
+                }
+                1 => self.bold = true,
+                22 => self.bold = false,
+                7 => self.reverse = true,
+                27 => self.reverse = false,
+                p @ 30..=37 => self.fg = (p - 30) as u8,
+                39 => self.fg = DEFAULT_FG,
+                p @ 40..=47 => self.bg = (p - 40) as u8,
+                49 => self.bg = DEFAULT_BG,
+                p @ 90..=97 => self.fg = (p - 90 + 8) as u8,
+                p @ 100..=107 => self.bg = (p - 100 + 8) as u8,
+                _ => {}
+            }
+        }
+    }
+
+    fn pixel(&self, color: u8) -> u32 {
+        let rgb = PALETTE[color as usize];
+        let (r, g, b) = ((rgb >> 16) & 0xff, (rgb >> 8) & 0xff, rgb & 0xff);
+        match self.mode.format {
+            PixelFormat::Argb8888 | PixelFormat::Xrgb8888 => 0xff00_0000 | rgb,
+            PixelFormat::Abgr8888 | PixelFormat::Xbgr8888 => 0xff00_0000 | (b << 16) | (g << 8) | r,
+        }
+    }
+
+    fn draw_row(&self, fb: *mut u32, row: usize) {
+        let width = self.mode.width as usize;
+        for col in 0..self.cols {
+            let cell = self.cells[row * self.cols + col];
+            let glyph = match cell.ch {
+                FIRST_CHAR..=b'~' => &FONT[(cell.ch - FIRST_CHAR) as usize],
+                _ => &FONT[0],
+            };
+            let (fg, bg) = (self.pixel(cell.fg), self.pixel(cell.bg));
+            let cursor = self.cursor_visible && self.cursor == (col, row);
+            for y in 0..CELL_HEIGHT as usize {
+                let bits = if cursor && y >= CELL_HEIGHT as usize - 2 {
+                    0xff
+                } else {
+                    glyph[y / 2]
+                };
+                let line = (row * CELL_HEIGHT as usize + y) * width + col * CELL_WIDTH as usize;
+                for x in 0..CELL_WIDTH as usize {
+                    let color = if bits & (1 << x) != 0 { fg } else { bg };
+                    unsafe { fb.add(line + x).write_volatile(color) };
+                }
+            }
+        }
+    }
+
+    /// Draws the changes into the framebuffer at `fb`, and returns the
+    /// rectangle changed.
+    fn draw(&mut self, fb: *mut u32) -> Option<Rect> {
+        let scrolled = core::mem::take(&mut self.scrolled).min(self.rows);
+        if scrolled > 0 && scrolled < self.rows {
+            let row_pixels = CELL_HEIGHT as usize * self.mode.width as usize;
+            unsafe {
+                core::ptr::copy(
+                    fb.add(scrolled * row_pixels),
+                    fb,
+                    (self.rows - scrolled) * row_pixels,
+                )
+            };
+        }
+        let dirty = self.dirty.take();
+        if let Some((first, last)) = dirty {
+            for row in first..=last {
+                self.draw_row(fb, row);
+            }
+        }
+        // All rows are moved if it has scrolled.
+        let (first, last) = if scrolled > 0 {
+            (0, self.rows - 1)
+        } else {
+            dirty?
+        };
+        Some(Rect {
+            x: 0,
+            y: first as u32 * CELL_HEIGHT,
+            width: self.cols as u32 * CELL_WIDTH,
+            height: (last - first + 1) as u32 * CELL_HEIGHT,
+        })
+    }
+
+    fn add_unflushed(&mut self, rect: Rect) {
+        self.unflushed = Some(match self.unflushed {
+            Some(old) => {
+                let y = old.y.min(rect.y);
+                let bottom = (old.y + old.height).max(rect.y + rect.height);
+                Rect {
+                    x: 0,
+                    y,
+                    width: old.width.max(rect.width),
+                    height: bottom - y,
+                }
+            }
+            None => rect,
+        });
+    }
+
+    /// Draws the changes into the framebuffer if the display is not in use,
+    /// and returns the rectangle to flush.
+    fn render(&mut self) -> Option<Rect> {
+        if let Some(dev) = crate::display(self.id).ok().and_then(|dev| dev.try_lock()) {
+            let mode = dev.mode();
+            if mode != self.mode {
+                self.resize(mode);
+            }
+            let fb = dev.info().fb_base_vaddr as *mut u32;
+            if let Some(rect) = self.draw(fb) {
+                self.add_unflushed(rect);
+            }
+        }
+        self.unflushed.take()
+    }
+}
+
+static CONSOLE: SpinNoIrq<Option<FbConsole>> = SpinNoIrq::new(None);
+
+/// Updates the console with `f`, and shows the changes.
+///
+/// Only drawing is done with [`CONSOLE`] locked, i.e., with IRQs disabled,
+/// while flushing, which waits for the device, is done after it's unlocked.
+fn update(f: impl FnOnce(&mut FbConsole)) {
+    let (id, rect) = {
+        let mut console = CONSOLE.lock();
+        let Some(console) = console.as_mut() else {
+            return;
+        };
+        f(console);
+        match console.render() {
+            Some(rect) => (console.id, rect),
+            None => return,
+        }
+    };
+    if let Some(mut dev) = crate::display(id).ok().and_then(|dev| dev.try_lock()) {
+        let _ = dev.flush_rect(rect);
+    } else if let Some(console) = CONSOLE.lock().as_mut() {
+        console.add_unflushed(rect);
+    }
+}
+
+/// Starts the console on the given display, and clears the screen.
+pub fn init(id: usize) -> AxResult {
+    let mode = crate::mode(id)?;
+    *CONSOLE.lock() = Some(FbConsole::new(id, mode));
+    update(|_| {});
+    Ok(())
+}
+
+/// Whether the console is started.
+pub fn is_enabled() -> bool {
+    CONSOLE.lock().is_some()
+}
+
+/// Writes the given bytes to the console, if it is started.
+///
+/// It must not print logs, as it is called to print logs.
+pub fn write_bytes(bytes: &[u8]) {
+    update(|console| {
+        for &b in bytes {
+            console.write_byte(b);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLS: usize = 10;
+    const ROWS: usize = 4;
+
+    fn console() -> FbConsole {
+        FbConsole::new(
+            0,
+            DisplayMode {
+                width: COLS as u32 * CELL_WIDTH,
+                height: ROWS as u32 * CELL_HEIGHT,
+                format: PixelFormat::Xrgb8888,
+            },
+        )
+    }
+
+    fn write(console: &mut FbConsole, s: &str) {
+        for b in s.bytes() {
+            console.write_byte(b);
+        }
+    }
+
+    fn line(console: &FbConsole, row: usize) -> String {
+        let cells = &console.cells[row * console.cols..(row + 1) * console.cols];
+        let line: String = cells.iter().map(|cell| cell.ch as char).collect();
+        line.trim_end().into()
+    }
+
+    fn colors(console: &FbConsole, col: usize, row: usize) -> (u8, u8) {
+        let cell = console.cells[row * console.cols + col];
+        (cell.fg, cell.bg)
+    }
+
+    fn framebuffer(console: &FbConsole) -> Vec<u32> {
+        vec![0; console.mode.width as usize * console.mode.height as usize]
+    }
+
+    #[test]
+    fn sgr() {
+        let mut c = console();
+        write(&mut c, "\x1b[31;42mA\x1b[1mB\x1b[7mC\x1b[mD");
+        write(&mut c, "\x1b[93;104mE\x1b[39mF\x1b[49mG\x1b[1;22mH");
+        assert_eq!(colors(&c, 0, 0), (1, 2));
+        // Bold is shown as the bright color.
+        assert_eq!(colors(&c, 1, 0), (9, 2));
+        assert_eq!(colors(&c, 2, 0), (2, 9));
+        // `ESC [ m` is a reset.
+        assert_eq!(colors(&c, 3, 0), (DEFAULT_FG, DEFAULT_BG));
+        assert_eq!(colors(&c, 4, 0), (11, 12));
+        assert_eq!(colors(&c, 5, 0), (DEFAULT_FG, 12));
+        assert_eq!(colors(&c, 6, 0), (DEFAULT_FG, DEFAULT_BG));
+        assert_eq!(colors(&c, 7, 0), (DEFAULT_FG, DEFAULT_BG));
+        assert_eq!(line(&c, 0), "ABCDEFGH");
+    }
+
+    #[test]
+    fn cursor_position() {
+        let mut c = console();
+        write(&mut c, "\x1b[3;5HX");
+        assert_eq!(line(&c, 2), "    X");
+        write(&mut c, "\x1b[HY\x1b[2;2fZ");
+        assert_eq!(line(&c, 0), "Y");
+        assert_eq!(line(&c, 1), " Z");
+        // Out of range positions are clamped.
+        write(&mut c, "\x1b[99;99H");
+        assert_eq!(c.cursor, (COLS - 1, ROWS - 1));
+        write(&mut c, "\x1b[2A\x1b[3D");
+        assert_eq!(c.cursor, (COLS - 4, ROWS - 3));
+        write(&mut c, "\x1b[B\x1b[C\x1b[2G");
+        assert_eq!(c.cursor, (1, ROWS - 2));
+    }
+
+    #[test]
+    fn erase() {
+        let mut c = console();
+        for _ in 0..ROWS - 1 {
+            write(&mut c, "0123456789");
+        }
+        write(&mut c, "\x1b[1;4H\x1b[K");
+        assert_eq!(line(&c, 0), "012");
+        write(&mut c, "\x1b[2;4H\x1b[1K");
+        assert_eq!(line(&c, 1), "    456789");
+        write(&mut c, "\x1b[2K");
+        assert_eq!(line(&c, 1), "");
+        write(&mut c, "\x1b[3;4H\x1b[1J");
+        assert_eq!(line(&c, 0), "");
+        assert_eq!(line(&c, 2), "    456789");
+        write(&mut c, "\x1b[3;6H\x1b[J");
+        assert_eq!(line(&c, 2), "    4");
+
+        // Erased cells take the current background.
+        write(&mut c, "\x1b[44m\x1b[2J");
+        assert!((0..ROWS).all(|row| line(&c, row).is_empty()));
+        assert_eq!(colors(&c, COLS - 1, ROWS - 1), (DEFAULT_FG, 4));
+    }
+
+    #[test]
+    fn scroll() {
+        let mut c = console();
+        let mut fb = framebuffer(&c);
+        c.draw(fb.as_mut_ptr());
+        write(&mut c, "a\nb\nc\nd");
+        c.draw(fb.as_mut_ptr());
+
+        write(&mut c, "\ne\nf");
+        assert_eq!(
+            (0..ROWS).map(|row| line(&c, row)).collect::<Vec<_>>(),
+            ["c", "d", "e", "f"]
+        );
+        assert_eq!(c.cursor, (1, ROWS - 1));
+        // Only the new lines and the old cursor row are drawn.
+        assert_eq!(c.scrolled, 2);
+        assert_eq!(c.dirty, Some((ROWS - 3, ROWS - 1)));
+        let rect = c.draw(fb.as_mut_ptr()).unwrap();
+        assert_eq!((rect.y, rect.height), (0, ROWS as u32 * CELL_HEIGHT));
+
+        // The result is the same as drawing everything again.
+        let mut expected = framebuffer(&c);
+        c.mark_dirty(0, ROWS - 1);
+        c.draw(expected.as_mut_ptr());
+        assert!(fb == expected);
+
+        // Scrolling by more than a screen draws everything.
+        write(&mut c, "\n1\n2\n3\n4\n5");
+        assert_eq!(c.dirty, Some((0, ROWS - 1)));
+        c.draw(fb.as_mut_ptr());
+        let mut expected = framebuffer(&c);
+        c.mark_dirty(0, ROWS - 1);
+        c.draw(expected.as_mut_ptr());
+        assert!(fb == expected);
+    }
+}
@@ -1,36 +1,201 @@
 //! [ArceOS](https://github.com/arceos-org/arceos) graphics module.
 //!
-//! Currently only supports direct writing to the framebuffer.
+//! Applications draw into the framebuffer of a display directly, and then
+//! flush the whole framebuffer or the changed (dirty) rectangles of it to show
+//! them on the screen. Displays are identified by their indices, in the order
+//! they are registered, and display `N` is usually the registered device
+//! `fbN`.
+//!
+//! If double buffering is enabled on a display, the framebuffer is the back
+//! buffer, which is shown by [`flip`]. A flip takes effect when it returns,
+//! so the front buffer is never drawn into while being scanned out.
+//!
+//! # Cargo Features
+//!
+//! - `fbcon`: Enable the framebuffer text console (see [`fbcon`]).
 
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
 #[macro_use]
 extern crate log;
+extern crate alloc;
+
+#[cfg(feature = "fbcon")]
+pub mod fbcon;
+
+use alloc::vec::Vec;
 
+#[doc(no_inline)]
+pub use axdriver::display::{CURSOR_SIZE, DisplayMode, PixelFormat, Rect};
 #[doc(no_inline)]
 pub use axdriver_display::DisplayInfo;
 
-use axdriver::{AxDeviceContainer, prelude::*};
-use axsync::Mutex;
+use axdriver::registry::{self, DeviceRef};
+use axdriver::{AxDisplayDevice, prelude::*};
+use axerrno::{AxError, AxResult};
 use lazyinit::LazyInit;
 
-static MAIN_DISPLAY: LazyInit<Mutex<AxDisplayDevice>> = LazyInit::new();
+static DISPLAYS: LazyInit<Vec<DeviceRef<AxDisplayDevice>>> = LazyInit::new();
+
+fn display(id: usize) -> AxResult<&'static DeviceRef<AxDisplayDevice>> {
+    DISPLAYS
+        .get()
+        .and_then(|devs| devs.get(id))
+        .ok_or(AxError::NotFound)
+}
+
+fn dev_err(e: DevError) -> AxError {
+    match e {
+        DevError::Unsupported => AxError::Unsupported,
+        DevError::InvalidParam => AxError::InvalidInput,
+        DevError::NoMemory => AxError::NoMemory,
+        DevError::Again => AxError::WouldBlock,
+        _ => AxError::Io,
+    }
+}
 
-/// Initializes the graphics subsystem by underlayer devices.
-pub fn init_display(mut display_devs: AxDeviceContainer<AxDisplayDevice>) {
+/// Initializes the graphics subsystem by all registered graphics devices.
+pub fn init_display() {
     info!("Initialize graphics subsystem...");
 
-    let dev = display_devs.take_one().expect("No graphics device found!");
-    info!("  use graphics device 0: {:?}", dev.device_name());
-    MAIN_DISPLAY.init_once(Mutex::new(dev));
+    let displays = registry::devices::<AxDisplayDevice>();
+    if displays.is_empty() {
+        warn!("  no graphics device found!");
+    }
+    for (id, dev) in displays.iter().enumerate() {
+        let mode = dev.lock().mode();
+        info!(
+            "  use graphics device {} ({}): {}x{} {:?}",
+            id,
+            dev.name(),
+            mode.width,
+            mode.height,
+            mode.format
+        );
+    }
+    DISPLAYS.init_once(displays);
+}
+
+/// Returns the number of displays.
+pub fn device_count() -> usize {
+    DISPLAYS.get().map_or(0, Vec::len)
+}
+
+/// Returns the name of the given display.
+pub fn device_name(id: usize) -> AxResult<&'static str> {
+    Ok(display(id)?.name())
+}
+
+/// Gets the framebuffer information of the given display.
+pub fn info(id: usize) -> AxResult<DisplayInfo> {
+    Ok(display(id)?.lock().info())
+}
+
+/// Returns the current mode of the given display.
+pub fn mode(id: usize) -> AxResult<DisplayMode> {
+    Ok(display(id)?.lock().mode())
+}
+
+/// Returns the modes supported by the given display, the preferred one first.
+pub fn modes(id: usize) -> AxResult<Vec<DisplayMode>> {
+    Ok(display(id)?.lock().modes())
+}
+
+/// Switches the given display to another mode.
+///
+/// The framebuffer is reallocated, so [`info`] must be called again to get
+/// its new address.
+pub fn set_mode(id: usize, mode: DisplayMode) -> AxResult {
+    display(id)?.lock().set_mode(mode).map_err(dev_err)
+}
+
+/// Flushes the whole framebuffer of the given display, i.e. shows it on the
+/// screen.
+pub fn flush(id: usize) -> AxResult {
+    display(id)?.lock().flush().map_err(dev_err)
+}
+
+/// Flushes the given rectangles of the framebuffer of the given display.
+///
+/// Parts of the rectangles outside the framebuffer are ignored.
+pub fn flush_rects(id: usize, rects: &[Rect]) -> AxResult {
+    let mut dev = display(id)?.lock();
+    for rect in rects {
+        dev.flush_rect(*rect).map_err(dev_err)?;
+    }
+    Ok(())
+}
+
+/// Calls `f` with the framebuffer and the mode of the given display, and then
+/// flushes the rectangle returned with the result of `f`, if any.
+///
+/// The display is locked meanwhile, so the framebuffer can not be reallocated,
+/// e.g., by [`set_mode`] from another task.
+pub fn with_framebuffer<R>(
+    id: usize,
+    f: impl FnOnce(&mut [u8], DisplayMode) -> (R, Option<Rect>),
+) -> AxResult<R> {
+    let mut dev = display(id)?.lock();
+    let info = dev.info();
+    let fb =
+        unsafe { core::slice::from_raw_parts_mut(info.fb_base_vaddr as *mut u8, info.fb_size) };
+    let (ret, rect) = f(fb, dev.mode());
+    if let Some(rect) = rect {
+        dev.flush_rect(rect).map_err(dev_err)?;
+    }
+    Ok(ret)
+}
+
+/// Enables or disables double buffering of the given display.
+///
+/// When it's enabled, the framebuffer becomes the back buffer, which initially
+/// has the content on the screen; when it's disabled, the framebuffer becomes
+/// the one on the screen. In both cases, [`info`] must be called again to get
+/// the new address of the framebuffer.
+pub fn set_double_buffered(id: usize, enabled: bool) -> AxResult {
+    display(id)?
+        .lock()
+        .set_double_buffered(enabled)
+        .map_err(dev_err)
+}
+
+/// Whether double buffering is enabled on the given display.
+pub fn is_double_buffered(id: usize) -> AxResult<bool> {
+    Ok(display(id)?.lock().is_double_buffered())
+}
+
+/// Shows the whole framebuffer of the given display on the screen. If double
+/// buffering is enabled, the front and back buffers are swapped.
+pub fn flip(id: usize) -> AxResult {
+    display(id)?.lock().flip().map_err(dev_err)
+}
+
+/// Whether the given display has a hardware cursor.
+pub fn has_cursor(id: usize) -> AxResult<bool> {
+    Ok(display(id)?.lock().has_cursor())
+}
+
+/// Sets the cursor image of the given display, which is [`CURSOR_SIZE`] x
+/// [`CURSOR_SIZE`] pixels in [`PixelFormat::Argb8888`], with the hot spot at
+/// (`hot_x`, `hot_y`). Hides the cursor if `image` is `None`.
+pub fn set_cursor(id: usize, image: Option<&[u32]>, hot_x: u32, hot_y: u32) -> AxResult {
+    display(id)?
+        .lock()
+        .set_cursor(image, hot_x, hot_y)
+        .map_err(dev_err)
+}
+
+/// Moves the hot spot of the cursor of the given display to (`x`, `y`).
+pub fn move_cursor(id: usize, x: u32, y: u32) -> AxResult {
+    display(id)?.lock().move_cursor(x, y).map_err(dev_err)
 }
 
-/// Gets the framebuffer information.
+/// Gets the framebuffer information of the first display.
 pub fn framebuffer_info() -> DisplayInfo {
-    MAIN_DISPLAY.lock().info()
+    info(0).expect("No graphics device found!")
 }
 
-/// Flushes the framebuffer, i.e. show on the screen.
+/// Flushes the framebuffer of the first display, i.e. show on the screen.
 pub fn framebuffer_flush() {
-    MAIN_DISPLAY.lock().flush().unwrap();
+    flush(0).unwrap();
 }
@@ -0,0 +1,159 @@
+//! The pool of bounce buffers for streaming DMA, reserved from low memory at
+//! boot (like the swiotlb of Linux), since pages of the global allocator may
+//! be anywhere in the physical memory.
+
+use core::ptr::NonNull;
+
+use kspin::SpinNoIrq;
+use log::debug;
+use memory_addr::{PAGE_SIZE_4K, PhysAddr};
+
+use crate::DmaDevice;
+
+/// The size of the bounce pool reserved at boot.
+pub const BOUNCE_POOL_SIZE: usize = 0x40_0000; // 4 MiB
+
+const POOL_PAGES: usize = BOUNCE_POOL_SIZE / PAGE_SIZE_4K;
+
+static POOL: SpinNoIrq<BouncePool> = SpinNoIrq::new(BouncePool::new());
+
+/// A first-fit allocator of pages in the pool.
+struct BouncePool {
+    start: usize,
+    pages: usize,
+    used: [u64; POOL_PAGES / 64],
+}
+
+impl BouncePool {
+    const fn new() -> Self {
+        Self {
+            start: 0,
+            pages: 0,
+            used: [0; POOL_PAGES / 64],
+        }
+    }
+
+    fn is_used(&self, page: usize) -> bool {
+        self.used[page / 64] & (1 << (page % 64)) != 0
+    }
+
+    fn set_used(&mut self, first: usize, num_pages: usize, used: bool) {
+        for page in first..first + num_pages {
+            if used {
+                self.used[page / 64] |= 1 << (page % 64);
+            } else {
+                self.used[page / 64] &= !(1 << (page % 64));
+            }
+        }
+    }
+
+    fn alloc(&mut self, num_pages: usize) -> Option<usize> {
+        let mut free = 0;
+        for page in 0..self.pages {
+            if self.is_used(page) {
+                free = 0;
+                continue;
+            }
+            free += 1;
+            if free == num_pages {
+                let first = page + 1 - num_pages;
+                self.set_used(first, num_pages, true);
+                return Some(self.start + first * PAGE_SIZE_4K);
+            }
+        }
+        None
+    }
+
+    fn dealloc(&mut self, vaddr: usize, num_pages: usize) {
+        let first = (vaddr - self.start) / PAGE_SIZE_4K;
+        debug_assert!(first + num_pages <= self.pages);
+        self.set_used(first, num_pages, false);
+    }
+}
+
+/// Returns whether the bounce pool at `paddr` is addressable by devices with
+/// 32-bit bus addresses, which are the most common ones that need it.
+pub fn bounce_pool_fits(paddr: PhysAddr) -> bool {
+    DmaDevice::new(32).addressable(paddr, BOUNCE_POOL_SIZE)
+}
+
+/// Hands the memory reserved for the bounce pool over to this crate.
+///
+/// The region `[start_vaddr, start_vaddr + size)` should be in the linear
+/// mapping of the physical memory, and not be used by others, e.g., the
+/// global allocator. At most [`BOUNCE_POOL_SIZE`] bytes are used.
+///
+/// This function should be called only once, before any streaming mapping.
+pub fn init_bounce_pool(start_vaddr: usize, size: usize) {
+    debug!(
+        "initialize DMA bounce pool at: [{:#x}, {:#x})",
+        start_vaddr,
+        start_vaddr + size
+    );
+    let mut pool = POOL.lock();
+    pool.start = start_vaddr;
+    pool.pages = (size / PAGE_SIZE_4K).min(POOL_PAGES);
+}
+
+/// Allocates `num_pages` contiguous pages from the pool.
+pub(crate) fn alloc_pages(num_pages: usize) -> Option<NonNull<u8>> {
+    let vaddr = POOL.lock().alloc(num_pages)?;
+    NonNull::new(vaddr as *mut u8)
+}
+
+/// Frees pages allocated by [`alloc_pages`].
+pub(crate) fn dealloc_pages(ptr: NonNull<u8>, num_pages: usize) {
+    POOL.lock().dealloc(ptr.as_ptr() as usize, num_pages);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: usize = 0x10_0000;
+
+    fn pool(pages: usize) -> BouncePool {
+        let mut pool = BouncePool::new();
+        pool.start = START;
+        pool.pages = pages;
+        pool
+    }
+
+    fn page(n: usize) -> Option<usize> {
+        Some(START + n * PAGE_SIZE_4K)
+    }
+
+    #[test]
+    fn first_fit() {
+        let mut pool = pool(8);
+        assert_eq!(pool.alloc(2), page(0));
+        assert_eq!(pool.alloc(3), page(2));
+        assert_eq!(pool.alloc(1), page(5));
+
+        // The hole left by a freed buffer is reused if it's large enough.
+        pool.dealloc(START + 2 * PAGE_SIZE_4K, 3);
+        assert_eq!(pool.alloc(4), None);
+        assert_eq!(pool.alloc(2), page(2));
+        assert_eq!(pool.alloc(2), page(6));
+        assert_eq!(pool.alloc(1), page(4));
+        assert_eq!(pool.alloc(1), None);
+    }
+
+    #[test]
+    fn words() {
+        // Buffers may span words of the bitmap.
+        let mut pool = pool(POOL_PAGES);
+        assert_eq!(pool.alloc(60), page(0));
+        assert_eq!(pool.alloc(10), page(60));
+        assert!(pool.is_used(69) && !pool.is_used(70));
+        pool.dealloc(START + 60 * PAGE_SIZE_4K, 10);
+        assert!(!pool.is_used(60) && !pool.is_used(69));
+        assert_eq!(pool.alloc(POOL_PAGES - 60), page(60));
+        assert_eq!(pool.alloc(1), None);
+    }
+
+    #[test]
+    fn uninitialized() {
+        assert_eq!(BouncePool::new().alloc(1), None);
+    }
+}
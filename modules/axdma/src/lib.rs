@@ -1,10 +1,22 @@
 //! [ArceOS](https://github.com/arceos-org/arceos) global DMA allocator.
+//!
+//! It provides two kinds of DMA memory:
+//!
+//! - Coherent memory ([`alloc_coherent`]), which is uncached and can be
+//!   accessed by the CPU and devices at the same time, e.g., descriptor rings.
+//! - Streaming mappings ([`map_single`], [`map_sg`]) of existing buffers,
+//!   which are handed over between the CPU and a device, with caches
+//!   maintained and bounce buffers used according to the [`DmaDevice`].
+//!   Bounce buffers are allocated from a pool of low memory, which is
+//!   reserved at boot and passed to [`init_bounce_pool`].
 
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
 extern crate alloc;
 
+mod bounce;
 mod dma;
+mod streaming;
 
 use core::{alloc::Layout, ptr::NonNull};
 
@@ -13,6 +25,12 @@ use memory_addr::PhysAddr;
 
 use self::dma::ALLOCATOR;
 
+pub use self::bounce::{BOUNCE_POOL_SIZE, bounce_pool_fits, init_bounce_pool};
+pub use self::streaming::{
+    DmaDevice, DmaDirection, DmaMapping, IommuOps, map_sg, map_single, sync_for_cpu,
+    sync_for_device, unmap,
+};
+
 /// Converts a physical address to a bus address.
 ///
 /// It assumes that there is a linear mapping with the offset
@@ -0,0 +1,381 @@
+use core::{ops::Range, ptr::NonNull};
+
+use alloc::vec::Vec;
+use allocator::{AllocError, AllocResult};
+use axhal::{
+    arch::{clean_dcache_range, dcache_line_size, flush_dcache_range, invalidate_dcache_range},
+    mem::virt_to_phys,
+};
+use log::warn;
+use memory_addr::{PAGE_SIZE_4K, PhysAddr, VirtAddr, va};
+
+use crate::{BusAddr, bounce, phys_to_bus};
+
+/// The direction of data transfer of a streaming DMA mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DmaDirection {
+    /// The device reads the buffer, e.g., a packet to transmit.
+    ToDevice,
+    /// The device writes the buffer, e.g., a buffer to receive packets.
+    FromDevice,
+    /// The device both reads and writes the buffer.
+    Bidirectional,
+}
+
+impl DmaDirection {
+    const fn to_device(self) -> bool {
+        matches!(self, Self::ToDevice | Self::Bidirectional)
+    }
+
+    const fn from_device(self) -> bool {
+        matches!(self, Self::FromDevice | Self::Bidirectional)
+    }
+}
+
+/// Hooks of an IOMMU which translates the bus addresses of a device.
+///
+/// If a device is behind an IOMMU, buffers are mapped into its I/O virtual
+/// address space, instead of accessed by their physical addresses.
+pub trait IommuOps: Send + Sync {
+    /// Maps the physical memory `[paddr, paddr + size)` for the device, and
+    /// returns the bus address of it.
+    fn map(&self, paddr: PhysAddr, size: usize, dir: DmaDirection) -> AllocResult<BusAddr>;
+
+    /// Unmaps the memory mapped by [`IommuOps::map`].
+    fn unmap(&self, bus_addr: BusAddr, size: usize);
+}
+
+/// The DMA capabilities of a device, which decide how its buffers are mapped.
+#[derive(Clone, Copy)]
+pub struct DmaDevice {
+    addr_mask: u64,
+    coherent: bool,
+    iommu: Option<&'static dyn IommuOps>,
+}
+
+impl DmaDevice {
+    /// Creates a device that can address `addr_bits` bits of the bus, whose
+    /// DMA is not coherent with CPU caches.
+    pub const fn new(addr_bits: u32) -> Self {
+        Self {
+            addr_mask: if addr_bits >= 64 {
+                u64::MAX
+            } else {
+                (1 << addr_bits) - 1
+            },
+            coherent: false,
+            iommu: None,
+        }
+    }
+
+    /// Sets whether DMA of the device is coherent with CPU caches, in which
+    /// case caches are not maintained for its mappings.
+    pub const fn coherent(mut self, coherent: bool) -> Self {
+        self.coherent = coherent;
+        self
+    }
+
+    /// Sets the IOMMU the device is behind.
+    pub const fn iommu(mut self, iommu: &'static dyn IommuOps) -> Self {
+        self.iommu = Some(iommu);
+        self
+    }
+
+    /// Returns whether the device can access `[paddr, paddr + size)` directly.
+    pub(crate) fn addressable(&self, paddr: PhysAddr, size: usize) -> bool {
+        let end = phys_to_bus(paddr).as_u64() + size.max(1) as u64 - 1;
+        end <= self.addr_mask
+    }
+
+    /// Returns whether `[paddr, paddr + size)` must be accessed by the device
+    /// through a bounce buffer.
+    fn needs_bounce(&self, paddr: PhysAddr, size: usize) -> bool {
+        self.iommu.is_none() && !self.addressable(paddr, size)
+    }
+}
+
+/// A buffer mapped for streaming DMA by [`map_single`] or [`map_sg`].
+///
+/// The buffer belongs to the device until the mapping is passed to [`unmap`]
+/// or [`sync_for_cpu`].
+#[derive(Debug)]
+pub struct DmaMapping {
+    /// The address of the buffer mapped.
+    pub cpu_addr: NonNull<u8>,
+    /// The address at which the device accesses the buffer.
+    pub bus_addr: BusAddr,
+    /// The size of the buffer.
+    pub size: usize,
+    /// The direction of data transfer.
+    pub dir: DmaDirection,
+    /// The buffer the device accesses instead, if the buffer is out of its
+    /// addressable range.
+    bounce: Option<NonNull<u8>>,
+}
+
+unsafe impl Send for DmaMapping {}
+unsafe impl Sync for DmaMapping {}
+
+impl DmaMapping {
+    /// Returns the address of the memory the device actually accesses.
+    fn dma_addr(&self) -> VirtAddr {
+        va!(self.bounce.unwrap_or(self.cpu_addr).as_ptr() as usize)
+    }
+}
+
+/// Maps a buffer for streaming DMA, and hands it over to the device.
+///
+/// - `dev`: The DMA capabilities of the device.
+/// - `cpu_addr`, `size`: The buffer, which must be in the linear mapping of
+///   the physical memory.
+/// - `dir`: The direction of data transfer.
+///
+/// If the buffer is out of the addressable range of the device, data is
+/// transferred through a bounce buffer instead.
+///
+/// # Safety
+///
+/// The buffer must remain valid until the mapping is passed to [`unmap`], and
+/// must not be accessed by the CPU while it belongs to the device.
+pub unsafe fn map_single(
+    dev: &DmaDevice,
+    cpu_addr: NonNull<u8>,
+    size: usize,
+    dir: DmaDirection,
+) -> AllocResult<DmaMapping> {
+    let paddr = virt_to_phys(va!(cpu_addr.as_ptr() as usize));
+    let (bus_addr, bounce) = match dev.iommu {
+        Some(iommu) => (iommu.map(paddr, size, dir)?, None),
+        None if dev.needs_bounce(paddr, size) => {
+            let bounce = alloc_bounce(dev, size)?;
+            let paddr = virt_to_phys(va!(bounce.as_ptr() as usize));
+            (phys_to_bus(paddr), Some(bounce))
+        }
+        None => (phys_to_bus(paddr), None),
+    };
+    let mapping = DmaMapping {
+        cpu_addr,
+        bus_addr,
+        size,
+        dir,
+        bounce,
+    };
+    unsafe { sync_for_device(dev, &mapping) };
+    Ok(mapping)
+}
+
+/// Maps a list of buffers (a scatter-gather list) for streaming DMA, and
+/// hands them over to the device.
+///
+/// Returns a mapping for each buffer, which should be passed to [`unmap`]
+/// respectively. If any buffer fails to be mapped, the buffers mapped before
+/// are unmapped.
+///
+/// # Safety
+///
+/// See [`map_single`].
+pub unsafe fn map_sg(
+    dev: &DmaDevice,
+    bufs: &[(NonNull<u8>, usize)],
+    dir: DmaDirection,
+) -> AllocResult<Vec<DmaMapping>> {
+    let mut mappings = Vec::with_capacity(bufs.len());
+    for &(cpu_addr, size) in bufs {
+        match unsafe { map_single(dev, cpu_addr, size, dir) } {
+            Ok(mapping) => mappings.push(mapping),
+            Err(e) => {
+                for mapping in mappings {
+                    unsafe { unmap(dev, mapping) };
+                }
+                return Err(e);
+            }
+        }
+    }
+    Ok(mappings)
+}
+
+/// Unmaps a buffer mapped by [`map_single`] or [`map_sg`], and hands it back
+/// to the CPU.
+///
+/// # Safety
+///
+/// The device must have finished accessing the buffer.
+pub unsafe fn unmap(dev: &DmaDevice, mapping: DmaMapping) {
+    unsafe { sync_for_cpu(dev, &mapping) };
+    if let Some(iommu) = dev.iommu {
+        iommu.unmap(mapping.bus_addr, mapping.size);
+    }
+    free_bounce(&mapping);
+}
+
+/// Hands a mapped buffer over to the device, after the CPU has accessed it
+/// following [`sync_for_cpu`].
+///
+/// It writes the data of the buffer back from CPU caches (or into the bounce
+/// buffer) if the device reads it, and discards stale cache lines if the
+/// device writes it.
+///
+/// # Safety
+///
+/// The buffer must not be accessed by the CPU until it's handed back.
+pub unsafe fn sync_for_device(dev: &DmaDevice, mapping: &DmaMapping) {
+    if let Some(bounce) = mapping.bounce
+        && mapping.dir.to_device()
+    {
+        unsafe {
+            bounce.copy_from_nonoverlapping(mapping.cpu_addr, mapping.size);
+        }
+    }
+    if !dev.coherent {
+        let vaddr = mapping.dma_addr();
+        if mapping.dir.from_device() {
+            flush_dcache_range(vaddr, mapping.size);
+        } else {
+            clean_dcache_range(vaddr, mapping.size);
+        }
+    }
+}
+
+/// Hands a mapped buffer back to the CPU, so that it can read the data written
+/// by the device.
+///
+/// # Safety
+///
+/// The device must have finished accessing the buffer.
+pub unsafe fn sync_for_cpu(dev: &DmaDevice, mapping: &DmaMapping) {
+    if !mapping.dir.from_device() {
+        return;
+    }
+    if !dev.coherent && mapping.size > 0 {
+        // Lines may have been fetched speculatively while the device owned
+        // the buffer. The partial lines at both ends may hold data next to
+        // the buffer written by the CPU meanwhile, so they are written back
+        // rather than discarded.
+        let start = mapping.dma_addr().as_usize();
+        let [head, whole, tail] = split_lines(start..start + mapping.size, dcache_line_size());
+        for (range, partial) in [(head, true), (whole, false), (tail, true)] {
+            if range.is_empty() {
+                continue;
+            }
+            if partial {
+                flush_dcache_range(va!(range.start), range.len());
+            } else {
+                invalidate_dcache_range(va!(range.start), range.len());
+            }
+        }
+    }
+    if let Some(bounce) = mapping.bounce {
+        unsafe {
+            mapping
+                .cpu_addr
+                .copy_from_nonoverlapping(bounce, mapping.size);
+        }
+    }
+}
+
+/// Splits `range` into the partial cache line at the head, the whole lines,
+/// and the partial line at the tail. Each part may be empty.
+fn split_lines(range: Range<usize>, line_size: usize) -> [Range<usize>; 3] {
+    let head_end = range.start.next_multiple_of(line_size).min(range.end);
+    let tail_start = (range.end / line_size * line_size).max(head_end);
+    [
+        range.start..head_end,
+        head_end..tail_start,
+        tail_start..range.end,
+    ]
+}
+
+/// Allocates a bounce buffer addressable by the device from the bounce pool.
+fn alloc_bounce(dev: &DmaDevice, size: usize) -> AllocResult<NonNull<u8>> {
+    let num_pages = bounce_pages(size);
+    let Some(bounce) = bounce::alloc_pages(num_pages) else {
+        warn!("no room in the DMA bounce pool for {size:#x} bytes");
+        return Err(AllocError::NoMemory);
+    };
+    if !dev.addressable(virt_to_phys(va!(bounce.as_ptr() as usize)), size) {
+        warn!("DMA bounce pool is not addressable by the device");
+        bounce::dealloc_pages(bounce, num_pages);
+        return Err(AllocError::NoMemory);
+    }
+    Ok(bounce)
+}
+
+fn free_bounce(mapping: &DmaMapping) {
+    if let Some(bounce) = mapping.bounce {
+        bounce::dealloc_pages(bounce, bounce_pages(mapping.size));
+    }
+}
+
+const fn bounce_pages(size: usize) -> usize {
+    memory_addr::align_up_4k(size.max(1)) / PAGE_SIZE_4K
+}
+
+#[cfg(test)]
+mod tests {
+    use memory_addr::pa;
+
+    use super::*;
+
+    struct DummyIommu;
+
+    impl IommuOps for DummyIommu {
+        fn map(&self, _paddr: PhysAddr, _size: usize, _dir: DmaDirection) -> AllocResult<BusAddr> {
+            Ok(BusAddr::new(0))
+        }
+
+        fn unmap(&self, _bus_addr: BusAddr, _size: usize) {}
+    }
+
+    /// Returns the physical address at the given bus address.
+    fn paddr_at(bus_addr: u64) -> PhysAddr {
+        pa!(bus_addr as usize - axconfig::plat::PHYS_BUS_OFFSET)
+    }
+
+    #[test]
+    fn addressable() {
+        let dev = DmaDevice::new(32);
+        assert!(dev.addressable(paddr_at(0xffff_f000), 0x1000));
+        assert!(!dev.addressable(paddr_at(0xffff_f000), 0x1001));
+        assert!(!dev.addressable(paddr_at(0x1_0000_0000), 1));
+        // An empty buffer still needs its first byte to be addressable.
+        assert!(dev.addressable(paddr_at(0xffff_ffff), 0));
+        assert!(!dev.addressable(paddr_at(0x1_0000_0000), 0));
+
+        let dev = DmaDevice::new(64);
+        assert!(dev.addressable(paddr_at(0x1_0000_0000), 0x1000));
+    }
+
+    #[test]
+    fn bounce() {
+        let dev = DmaDevice::new(32);
+        assert!(!dev.needs_bounce(paddr_at(0x8000_0000), 0x1000));
+        assert!(dev.needs_bounce(paddr_at(0xffff_f000), 0x2000));
+        assert!(dev.needs_bounce(paddr_at(0x1_0000_0000), 0x1000));
+
+        // Devices behind an IOMMU can access any buffer mapped for them.
+        static IOMMU: DummyIommu = DummyIommu;
+        let dev = dev.iommu(&IOMMU);
+        assert!(!dev.needs_bounce(paddr_at(0x1_0000_0000), 0x1000));
+    }
+
+    #[test]
+    fn partial_lines() {
+        assert_eq!(
+            split_lines(0x1000..0x1100, 64),
+            [0x1000..0x1000, 0x1000..0x1100, 0x1100..0x1100]
+        );
+        assert_eq!(
+            split_lines(0x1010..0x10f0, 64),
+            [0x1010..0x1040, 0x1040..0x10c0, 0x10c0..0x10f0]
+        );
+        // Within a single line.
+        assert_eq!(
+            split_lines(0x1010..0x1020, 64),
+            [0x1010..0x1020, 0x1020..0x1020, 0x1020..0x1020]
+        );
+        assert_eq!(
+            split_lines(0x1000..0x1020, 64),
+            [0x1000..0x1000, 0x1000..0x1000, 0x1000..0x1020]
+        );
+    }
+}
@@ -0,0 +1,43 @@
+//! Common traits for block storage drivers, which extend [`BlockDriverOps`]
+//! with requests that complete asynchronously, e.g., on interrupts.
+//!
+//! Drivers that only do synchronous I/O rely on the default implementations,
+//! which report that requests are not supported.
+
+use axdriver_base::{DevError, DevResult};
+use axdriver_block::BlockDriverOps;
+
+/// Operations of block storage drivers that can submit a request, and
+/// complete it later instead of waiting in place.
+///
+/// At most one request is in flight, which must be completed by
+/// [`poll_request`](Self::poll_request) before the next one is submitted.
+pub trait BlockRequestOps: BlockDriverOps {
+    /// Returns the maximum size of a request in bytes, or 0 if requests are
+    /// not supported, and [`BlockDriverOps`] must be used instead.
+    fn max_request_size(&self) -> usize {
+        0
+    }
+
+    /// Starts to read `len` bytes of blocks starting from `block_id`.
+    ///
+    /// Returns [`DevError::Again`] if the previous request is still in flight.
+    fn submit_read(&mut self, _block_id: u64, _len: usize) -> DevResult {
+        Err(DevError::Unsupported)
+    }
+
+    /// Starts to write `buf` to blocks starting from `block_id`.
+    ///
+    /// Returns [`DevError::Again`] if the previous request is still in flight.
+    fn submit_write(&mut self, _block_id: u64, _buf: &[u8]) -> DevResult {
+        Err(DevError::Unsupported)
+    }
+
+    /// Completes the submitted request. The data of a read request is written
+    /// into `buf`, which is ignored for write requests.
+    ///
+    /// Returns [`DevError::Again`] if the device has not handled it yet.
+    fn poll_request(&mut self, _buf: &mut [u8]) -> DevResult {
+        Err(DevError::Unsupported)
+    }
+}
@@ -1,23 +1,32 @@
 #[allow(unused_imports)]
-use crate::{AllDevices, prelude::*};
+use crate::{prelude::*, registry};
 
-impl AllDevices {
-    pub(crate) fn probe_bus_devices(&mut self) {
-        // TODO: parse device tree
-        #[cfg(feature = "virtio")]
+/// Probes all VirtIO MMIO devices, and registers the supported ones.
+pub(crate) fn probe_bus_devices() {
+    #[cfg(feature = "virtio")]
+    if let Some(fdt) = axhal::fdt::info() {
+        for dev in fdt.virtio_mmio_devices() {
+            probe_mmio_device(dev.paddr, dev.size);
+        }
+    } else {
         for reg in axconfig::devices::VIRTIO_MMIO_REGIONS {
-            for_each_drivers!(type Driver, {
-                if let Some(dev) = Driver::probe_mmio(reg.0, reg.1) {
-                    info!(
-                        "registered a new {:?} device at [PA:{:#x}, PA:{:#x}): {:?}",
-                        dev.device_type(),
-                        reg.0, reg.0 + reg.1,
-                        dev.device_name(),
-                    );
-                    self.add_device(dev);
-                    continue; // skip to the next device
-                }
-            });
+            probe_mmio_device(reg.0, reg.1);
         }
     }
 }
+
+#[cfg(feature = "virtio")]
+fn probe_mmio_device(paddr: usize, size: usize) {
+    for_each_drivers!(type Driver, {
+        if let Some(dev) = Driver::probe_mmio(paddr, size) {
+            info!(
+                "found a new {:?} device at [PA:{:#x}, PA:{:#x}): {:?}",
+                dev.device_type(),
+                paddr, paddr + size,
+                dev.device_name(),
+            );
+            registry::register(dev);
+            return;
+        }
+    });
+}
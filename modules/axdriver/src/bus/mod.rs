@@ -2,3 +2,12 @@
 mod mmio;
 #[cfg(bus = "pci")]
 mod pci;
+#[cfg(all(bus = "pci", feature = "irq"))]
+mod pci_irq;
+
+#[cfg(bus = "mmio")]
+pub(crate) use self::mmio::probe_bus_devices;
+#[cfg(bus = "pci")]
+pub(crate) use self::pci::probe_bus_devices;
+#[cfg(all(bus = "pci", feature = "irq", feature = "virtio"))]
+pub(crate) use self::pci_irq::VirtIoQueueVectors;
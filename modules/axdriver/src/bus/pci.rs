@@ -1,4 +1,4 @@
-use crate::{AllDevices, prelude::*};
+use crate::{prelude::*, registry};
 use axdriver_pci::{
     BarInfo, Cam, Command, DeviceFunction, HeaderType, MemoryBarType, PciRangeAllocator, PciRoot,
 };
@@ -82,40 +82,68 @@ fn config_pci_device(
     Ok(())
 }
 
-impl AllDevices {
-    pub(crate) fn probe_bus_devices(&mut self) {
-        let base_vaddr = phys_to_virt(axconfig::devices::PCI_ECAM_BASE.into());
-        let mut root = unsafe { PciRoot::new(base_vaddr.as_mut_ptr(), Cam::Ecam) };
+/// Returns the physical address of the PCIe ECAM space and the range of
+/// buses to probe, from the ACPI MCFG table if present.
+fn pci_ecam() -> (usize, core::ops::RangeInclusive<u8>) {
+    #[cfg(target_arch = "x86_64")]
+    if let Some(ecam) = axhal::acpi::info().and_then(|info| info.pci_ecam()) {
+        return (ecam.paddr, ecam.bus_start..=ecam.bus_end);
+    }
+    (
+        axconfig::devices::PCI_ECAM_BASE,
+        0..=axconfig::devices::PCI_BUS_END as u8,
+    )
+}
 
-        // PCI 32-bit MMIO space
-        let mut allocator = axconfig::devices::PCI_RANGES
-            .get(1)
-            .map(|range| PciRangeAllocator::new(range.0 as u64, range.1 as u64));
+/// Probes all PCI devices, and registers the supported ones.
+pub(crate) fn probe_bus_devices() {
+    let (ecam_paddr, buses) = pci_ecam();
+    let base_vaddr = phys_to_virt(ecam_paddr.into());
+    let mut root = unsafe { PciRoot::new(base_vaddr.as_mut_ptr(), Cam::Ecam) };
 
-        for bus in 0..=axconfig::devices::PCI_BUS_END as u8 {
-            for (bdf, dev_info) in root.enumerate_bus(bus) {
-                debug!("PCI {}: {}", bdf, dev_info);
-                if dev_info.header_type != HeaderType::Standard {
-                    continue;
-                }
-                match config_pci_device(&mut root, bdf, &mut allocator) {
-                    Ok(_) => for_each_drivers!(type Driver, {
-                        if let Some(dev) = Driver::probe_pci(&mut root, bdf, &dev_info) {
-                            info!(
-                                "registered a new {:?} device at {}: {:?}",
-                                dev.device_type(),
-                                bdf,
-                                dev.device_name(),
+    // PCI 32-bit MMIO space
+    let mut allocator = axconfig::devices::PCI_RANGES
+        .get(1)
+        .map(|range| PciRangeAllocator::new(range.0 as u64, range.1 as u64));
+
+    for bus in buses {
+        for (bdf, dev_info) in root.enumerate_bus(bus) {
+            debug!("PCI {}: {}", bdf, dev_info);
+            if dev_info.header_type != HeaderType::Standard {
+                continue;
+            }
+            match config_pci_device(&mut root, bdf, &mut allocator) {
+                Ok(_) => for_each_drivers!(type Driver, {
+                    if let Some(dev) = Driver::probe_pci(&mut root, bdf, &dev_info) {
+                        info!(
+                            "found a new {:?} device at {}: {:?}",
+                            dev.device_type(),
+                            bdf,
+                            dev.device_name(),
+                        );
+                        let id = registry::alloc_id();
+                        #[cfg(feature = "irq")]
+                        {
+                            let dev_id = crate::irq::DeviceId {
+                                dev_type: dev.device_type(),
+                                id,
+                            };
+                            let mode = super::pci_irq::config_pci_irq(
+                                &mut root, bdf, &dev_info, dev_id,
                             );
-                            self.add_device(dev);
-                            continue; // skip to the next device
+                            match mode {
+                                Some(mode) => info!("  interrupt mode: {:?}", mode),
+                                None => info!("  no interrupt, polling mode"),
+                            }
                         }
-                    }),
-                    Err(e) => warn!(
-                        "failed to enable PCI device at {}({}): {:?}",
-                        bdf, dev_info, e
-                    ),
-                }
+                        registry::register_with_id(dev, id);
+                        continue; // skip to the next device
+                    }
+                }),
+                Err(e) => warn!(
+                    "failed to enable PCI device at {}({}): {:?}",
+                    bdf, dev_info, e
+                ),
             }
         }
     }
@@ -0,0 +1,319 @@
+//! Interrupt configuration of PCI devices.
+
+use axdriver_pci::{BarInfo, Command, DeviceFunction, DeviceFunctionInfo, PciRoot};
+use axhal::mem::phys_to_virt;
+
+use crate::irq::{DeviceId, IrqMode, IrqVector, MAX_DEVICE_VECTORS};
+
+const VIRTIO_VENDOR_ID: u16 = 0x1af4;
+
+/// The offset of the interrupt line and pin registers in the configuration
+/// space.
+const PCI_INTERRUPT_LINE: u8 = 0x3c;
+
+const PCI_CAP_ID_MSI: u8 = 0x05;
+const PCI_CAP_ID_MSIX: u8 = 0x11;
+
+const MSI_CONTROL_ENABLE: u16 = 1 << 0;
+const MSI_CONTROL_MME_MASK: u16 = 0x7 << 4;
+const MSI_CONTROL_64BIT: u16 = 1 << 7;
+
+const MSIX_CONTROL_TABLE_SIZE_MASK: u16 = 0x7ff;
+const MSIX_CONTROL_FUNCTION_MASK: u16 = 1 << 14;
+const MSIX_CONTROL_ENABLE: u16 = 1 << 15;
+const MSIX_ENTRY_SIZE: usize = 16;
+
+/// Registers of a VirtIO PCI device used to configure interrupts.
+struct VirtIoIrqRegs {
+    /// The virtual address of the common configuration structure.
+    common_cfg: usize,
+    /// The virtual address of the ISR status register.
+    isr: usize,
+}
+
+impl VirtIoIrqRegs {
+    const VIRTIO_PCI_CAP_COMMON_CFG: u8 = 1;
+    const VIRTIO_PCI_CAP_ISR_CFG: u8 = 3;
+
+    const CONFIG_MSIX_VECTOR: usize = 0x10;
+    const NUM_QUEUES: usize = 0x12;
+    const QUEUE_SELECT: usize = 0x16;
+    const QUEUE_MSIX_VECTOR: usize = 0x1a;
+
+    /// Means no MSI-X vector is used.
+    const NO_VECTOR: u16 = 0xffff;
+
+    /// Locates the registers from the vendor-specific capabilities.
+    fn probe(root: &PciRoot, bdf: DeviceFunction) -> Option<Self> {
+        let (mut common_cfg, mut isr) = (None, None);
+        for cap in root.capabilities(bdf) {
+            const PCI_CAP_ID_VNDR: u8 = 0x09;
+            if cap.id != PCI_CAP_ID_VNDR {
+                continue;
+            }
+            let cfg_type = (cap.private_header >> 8) as u8;
+            let bar = root.config_read_word(bdf, cap.offset + 4) as u8;
+            let offset = root.config_read_word(bdf, cap.offset + 8) as usize;
+            let target = match cfg_type {
+                Self::VIRTIO_PCI_CAP_COMMON_CFG => &mut common_cfg,
+                Self::VIRTIO_PCI_CAP_ISR_CFG => &mut isr,
+                _ => continue,
+            };
+            if let Ok(BarInfo::Memory { address, .. }) = root.bar_info(bdf, bar) {
+                *target = Some(phys_to_virt((address as usize + offset).into()).as_usize());
+            }
+        }
+        Some(Self {
+            common_cfg: common_cfg?,
+            isr: isr?,
+        })
+    }
+
+    fn reg(&self, offset: usize) -> *mut u16 {
+        (self.common_cfg + offset) as *mut u16
+    }
+
+    /// Returns the number of virtqueues of the device.
+    fn num_queues(&self) -> u16 {
+        unsafe { self.reg(Self::NUM_QUEUES).read_volatile() }
+    }
+
+    /// Sets the MSI-X vector of configuration changes.
+    fn set_config_msix_vector(&self, vector: u16) {
+        unsafe { self.reg(Self::CONFIG_MSIX_VECTOR).write_volatile(vector) };
+    }
+
+    /// Sets the MSI-X vector of the given virtqueue. Returns `false` if the
+    /// device fails to allocate resources for the vector.
+    fn set_queue_msix_vector(&self, queue: u16, vector: u16) -> bool {
+        unsafe {
+            self.reg(Self::QUEUE_SELECT).write_volatile(queue);
+            self.reg(Self::QUEUE_MSIX_VECTOR).write_volatile(vector);
+            self.reg(Self::QUEUE_MSIX_VECTOR).read_volatile() == vector
+        }
+    }
+}
+
+/// Configures interrupts of a probed device, prefers MSI-X, then MSI, and then
+/// legacy interrupts.
+///
+/// Legacy interrupts are level-triggered and shared, so they are only used by
+/// VirtIO devices, whose interrupts can be checked and acknowledged with the
+/// ISR status register.
+pub(super) fn config_pci_irq(
+    root: &mut PciRoot,
+    bdf: DeviceFunction,
+    dev_info: &DeviceFunctionInfo,
+    dev: DeviceId,
+) -> Option<IrqMode> {
+    let virtio = if dev_info.vendor_id == VIRTIO_VENDOR_ID {
+        VirtIoIrqRegs::probe(root, bdf)
+    } else {
+        None
+    };
+
+    let (mut msix_cap, mut msi_cap) = (None, None);
+    for cap in root.capabilities(bdf) {
+        match cap.id {
+            PCI_CAP_ID_MSI => msi_cap = Some(cap.offset),
+            PCI_CAP_ID_MSIX => msix_cap = Some(cap.offset),
+            _ => {}
+        }
+    }
+
+    if let Some(cap) = msix_cap
+        && config_msix(root, bdf, cap, dev, virtio.as_ref())
+    {
+        return Some(IrqMode::MsiX);
+    }
+    // VirtIO devices only signal queues with MSI-X or legacy interrupts.
+    if let Some(cap) = msi_cap
+        && virtio.is_none()
+        && config_msi(root, bdf, cap, dev)
+    {
+        return Some(IrqMode::Msi);
+    }
+    if let Some(regs) = virtio
+        && config_intx(root, bdf, dev, regs.isr)
+    {
+        return Some(IrqMode::Intx);
+    }
+    None
+}
+
+/// Disables legacy interrupts, which must be done when MSI or MSI-X is used.
+fn disable_intx(root: &mut PciRoot, bdf: DeviceFunction) {
+    let (_status, cmd) = root.get_status_command(bdf);
+    root.set_command(bdf, cmd | Command::INTERRUPT_DISABLE);
+}
+
+/// Allocates an MSI and returns its IRQ number and message.
+fn alloc_msi() -> Option<(usize, axhal::irq::MsiMessage)> {
+    let irq_num = axhal::irq::alloc_msi_irq()?;
+    Some((irq_num, axhal::irq::msi_message(irq_num)?))
+}
+
+fn config_msi(root: &mut PciRoot, bdf: DeviceFunction, cap: u8, dev: DeviceId) -> bool {
+    let Some((irq_num, msg)) = alloc_msi() else {
+        return false;
+    };
+    let header = root.config_read_word(bdf, cap);
+    let control = (header >> 16) as u16;
+    let data_offset = if control & MSI_CONTROL_64BIT != 0 {
+        root.config_write_word(bdf, cap + 8, (msg.address >> 32) as u32);
+        12
+    } else if msg.address >> 32 != 0 {
+        return false;
+    } else {
+        8
+    };
+    root.config_write_word(bdf, cap + 4, msg.address as u32);
+    root.config_write_word(bdf, cap + data_offset, msg.data & 0xffff);
+
+    let vector = IrqVector {
+        dev,
+        mode: IrqMode::Msi,
+        irq_num,
+        queue: None,
+        isr: None,
+    };
+    if !crate::irq::add_vector(vector) {
+        return false;
+    }
+    // A single message, i.e., the multiple message enable field is 0.
+    let control = (control & !MSI_CONTROL_MME_MASK) | MSI_CONTROL_ENABLE;
+    root.config_write_word(bdf, cap, (header & 0xffff) | (control as u32) << 16);
+    disable_intx(root, bdf);
+    true
+}
+
+/// Returns the number of entries of the MSI-X table.
+fn msix_table_size(root: &PciRoot, bdf: DeviceFunction, cap: u8) -> usize {
+    let control = (root.config_read_word(bdf, cap) >> 16) as u16;
+    (control & MSIX_CONTROL_TABLE_SIZE_MASK) as usize + 1
+}
+
+/// Whether each of the `num_queues` queues of a VirtIO device gets its own
+/// MSI-X vector, instead of sharing vector 0.
+fn msix_per_queue(num_queues: usize, table_size: usize) -> bool {
+    num_queues > 1 && num_queues <= table_size.min(MAX_DEVICE_VECTORS)
+}
+
+/// The MSI-X vectors of the queues of a VirtIO device, in the layout used by
+/// [`config_pci_irq`].
+///
+/// The vectors of queues must be set before the device is activated (i.e.,
+/// `DRIVER_OK`), as they are reset along with the device, so they are set by
+/// the transport when the driver sets up the queues.
+#[cfg(feature = "virtio")]
+pub(crate) struct VirtIoQueueVectors {
+    regs: VirtIoIrqRegs,
+    per_queue: bool,
+}
+
+#[cfg(feature = "virtio")]
+impl VirtIoQueueVectors {
+    /// Returns `None` if the device does not support MSI-X.
+    pub(crate) fn probe(root: &PciRoot, bdf: DeviceFunction) -> Option<Self> {
+        let cap = root
+            .capabilities(bdf)
+            .find(|cap| cap.id == PCI_CAP_ID_MSIX)?
+            .offset;
+        let regs = VirtIoIrqRegs::probe(root, bdf)?;
+        let per_queue = msix_per_queue(regs.num_queues() as usize, msix_table_size(root, bdf, cap));
+        Some(Self { regs, per_queue })
+    }
+
+    /// Sets the MSI-X vector of the given queue, which is used once MSI-X is
+    /// enabled by [`config_pci_irq`].
+    pub(crate) fn set_queue_vector(&self, queue: u16) {
+        self.regs.set_config_msix_vector(VirtIoIrqRegs::NO_VECTOR);
+        let vector = if self.per_queue { queue } else { 0 };
+        if !self.regs.set_queue_msix_vector(queue, vector) {
+            warn!("  failed to set MSI-X vector of queue {}", queue);
+        }
+    }
+}
+
+/// Configures MSI-X with a vector per queue for VirtIO devices if possible,
+/// otherwise a single vector for the whole device.
+///
+/// The vectors of VirtIO queues are set by the transport (see
+/// [`VirtIoQueueVectors`]) in the same layout.
+fn config_msix(
+    root: &mut PciRoot,
+    bdf: DeviceFunction,
+    cap: u8,
+    dev: DeviceId,
+    virtio: Option<&VirtIoIrqRegs>,
+) -> bool {
+    let header = root.config_read_word(bdf, cap);
+    let control = (header >> 16) as u16;
+    let table_size = msix_table_size(root, bdf, cap);
+    let table = root.config_read_word(bdf, cap + 4);
+    let (bir, table_offset) = ((table & 0x7) as u8, (table & !0x7) as usize);
+    let Ok(BarInfo::Memory { address, .. }) = root.bar_info(bdf, bir) else {
+        return false;
+    };
+    let table_vaddr = phys_to_virt((address as usize + table_offset).into()).as_usize();
+
+    let num_queues = virtio.map_or(0, |regs| regs.num_queues() as usize);
+    let per_queue = msix_per_queue(num_queues, table_size);
+    let num_vectors = if per_queue { num_queues } else { 1 };
+
+    for i in 0..num_vectors {
+        let entry = (table_vaddr + i * MSIX_ENTRY_SIZE) as *mut u32;
+        let added = alloc_msi().is_some_and(|(irq_num, msg)| {
+            unsafe {
+                entry.write_volatile(msg.address as u32);
+                entry.add(1).write_volatile((msg.address >> 32) as u32);
+                entry.add(2).write_volatile(msg.data);
+                // Unmasked
+                entry.add(3).write_volatile(0);
+            }
+            crate::irq::add_vector(IrqVector {
+                dev,
+                mode: IrqMode::MsiX,
+                irq_num,
+                queue: per_queue.then_some(i as u16),
+                isr: None,
+            })
+        });
+        if !added {
+            // Roll back the entries written so far. The allocated IRQs can
+            // not be freed, but they are never raised once masked.
+            for j in 0..=i {
+                let entry = (table_vaddr + j * MSIX_ENTRY_SIZE) as *mut u32;
+                unsafe { entry.add(3).write_volatile(1) };
+            }
+            crate::irq::remove_device(dev.id);
+            return false;
+        }
+    }
+
+    let control = (control & !MSIX_CONTROL_FUNCTION_MASK) | MSIX_CONTROL_ENABLE;
+    root.config_write_word(bdf, cap, (header & 0xffff) | (control as u32) << 16);
+    disable_intx(root, bdf);
+    true
+}
+
+/// Configures the legacy interrupt of a VirtIO device.
+///
+/// Only devices on the root bus are supported, since the routing through
+/// bridges is not known.
+fn config_intx(root: &mut PciRoot, bdf: DeviceFunction, dev: DeviceId, isr: usize) -> bool {
+    let pin = (root.config_read_word(bdf, PCI_INTERRUPT_LINE) >> 8) as u8;
+    if bdf.bus != 0 {
+        return false;
+    }
+    let Some(irq_num) = axhal::irq::pci_intx_irq(bdf.device, pin) else {
+        return false;
+    };
+    crate::irq::add_vector(IrqVector {
+        dev,
+        mode: IrqMode::Intx,
+        irq_num,
+        queue: None,
+        isr: Some(isr),
+    })
+}
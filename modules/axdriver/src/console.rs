@@ -0,0 +1,14 @@
+//! Common traits and types for console device drivers (e.g., serial ports
+//! other than the platform console).
+
+use axdriver_base::{BaseDriverOps, DevResult};
+
+/// Operations that require a console device driver to implement.
+pub trait ConsoleDriverOps: BaseDriverOps {
+    /// Reads the received bytes into `buf`, and returns the number of bytes
+    /// read. It does not block, i.e., returns 0 if nothing is received.
+    fn read(&mut self, buf: &mut [u8]) -> DevResult<usize>;
+
+    /// Writes the bytes in `buf`, and returns the number of bytes written.
+    fn write(&mut self, buf: &[u8]) -> DevResult<usize>;
+}
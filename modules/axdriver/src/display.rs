@@ -0,0 +1,162 @@
+//! Common traits and types for graphics display drivers, which extend
+//! [`DisplayDriverOps`] with mode setting, partial flushing, double buffering
+//! and hardware cursors.
+//!
+//! Drivers that only provide a fixed framebuffer implement [`mode`] and rely
+//! on the default implementations of other methods.
+//!
+//! [`mode`]: DisplayOutputOps::mode
+
+use alloc::{vec, vec::Vec};
+
+use axdriver_base::{DevError, DevResult};
+use axdriver_display::DisplayDriverOps;
+
+/// The width and height of cursor images, in pixels.
+pub const CURSOR_SIZE: u32 = 64;
+
+/// The layout of pixels in the framebuffer, named after the Linux DRM formats,
+/// i.e., from the most significant bit of a little-endian 32-bit word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// `[31:0] A:R:G:B`, i.e., bytes in order B, G, R, A.
+    Argb8888,
+    /// `[31:0] x:R:G:B`, the alpha byte is ignored.
+    Xrgb8888,
+    /// `[31:0] A:B:G:R`, i.e., bytes in order R, G, B, A.
+    Abgr8888,
+    /// `[31:0] x:B:G:R`, the alpha byte is ignored.
+    Xbgr8888,
+}
+
+impl PixelFormat {
+    /// Returns the number of bytes of a pixel.
+    pub const fn bytes_per_pixel(self) -> usize {
+        4
+    }
+}
+
+/// A display mode, i.e., the resolution and the pixel format of the
+/// framebuffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayMode {
+    /// The width in pixels.
+    pub width: u32,
+    /// The height in pixels.
+    pub height: u32,
+    /// The pixel format.
+    pub format: PixelFormat,
+}
+
+impl DisplayMode {
+    /// Returns the number of bytes of a row of pixels.
+    pub const fn stride(&self) -> usize {
+        self.width as usize * self.format.bytes_per_pixel()
+    }
+
+    /// Returns the size of the framebuffer in bytes.
+    pub const fn fb_size(&self) -> usize {
+        self.stride() * self.height as usize
+    }
+}
+
+/// A rectangle in the framebuffer, in pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Rect {
+    /// The column of the left edge.
+    pub x: u32,
+    /// The row of the top edge.
+    pub y: u32,
+    /// The width.
+    pub width: u32,
+    /// The height.
+    pub height: u32,
+}
+
+impl Rect {
+    /// Returns the part of the rectangle inside a framebuffer of the given
+    /// mode, which is empty if it's entirely outside.
+    pub fn clip(&self, mode: &DisplayMode) -> Self {
+        let x = self.x.min(mode.width);
+        let y = self.y.min(mode.height);
+        Self {
+            x,
+            y,
+            width: self.width.min(mode.width - x),
+            height: self.height.min(mode.height - y),
+        }
+    }
+
+    /// Whether the rectangle has no pixels.
+    pub const fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+}
+
+/// Operations that require a graphics display driver to implement, in
+/// addition to [`DisplayDriverOps`].
+///
+/// [`DisplayDriverOps::fb`] returns the framebuffer to draw into, which is
+/// the back buffer if double buffering is enabled.
+pub trait DisplayOutputOps: DisplayDriverOps {
+    /// Returns the current display mode.
+    fn mode(&self) -> DisplayMode;
+
+    /// Returns the display modes supported, the preferred one (e.g., the
+    /// native resolution of the monitor) first.
+    fn modes(&self) -> Vec<DisplayMode> {
+        vec![self.mode()]
+    }
+
+    /// Switches to the given display mode, which reallocates the framebuffer.
+    fn set_mode(&mut self, mode: DisplayMode) -> DevResult {
+        if mode == self.mode() {
+            Ok(())
+        } else {
+            Err(DevError::Unsupported)
+        }
+    }
+
+    /// Shows the given rectangle of the framebuffer on the screen.
+    fn flush_rect(&mut self, _rect: Rect) -> DevResult {
+        self.flush()
+    }
+
+    /// Enables or disables double buffering.
+    fn set_double_buffered(&mut self, enabled: bool) -> DevResult {
+        if enabled {
+            Err(DevError::Unsupported)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Whether double buffering is enabled.
+    fn is_double_buffered(&self) -> bool {
+        false
+    }
+
+    /// Shows the whole framebuffer on the screen. If double buffering is
+    /// enabled, the back buffer becomes the front one and vice versa, and the
+    /// new back buffer keeps its previous content.
+    fn flip(&mut self) -> DevResult {
+        self.flush()
+    }
+
+    /// Whether the device supports a hardware cursor.
+    fn has_cursor(&self) -> bool {
+        false
+    }
+
+    /// Sets the cursor image of [`CURSOR_SIZE`] x [`CURSOR_SIZE`] pixels in
+    /// [`PixelFormat::Argb8888`], with the hot spot at (`hot_x`, `hot_y`).
+    /// Hides the cursor if `image` is `None`.
+    fn set_cursor(&mut self, _image: Option<&[u32]>, _hot_x: u32, _hot_y: u32) -> DevResult {
+        Err(DevError::Unsupported)
+    }
+
+    /// Moves the hot spot of the cursor to (`x`, `y`).
+    fn move_cursor(&mut self, _x: u32, _y: u32) -> DevResult {
+        Err(DevError::Unsupported)
+    }
+}
@@ -41,14 +41,38 @@ register_net_driver!(
 
 #[cfg(block_dev = "virtio-blk")]
 register_block_driver!(
-    <virtio::VirtIoBlk as VirtIoDevMeta>::Driver,
-    <virtio::VirtIoBlk as VirtIoDevMeta>::Device
+    virtio::VirtIoRawDriver<virtio::VirtIoBlk>,
+    virtio::VirtIoBlkDev
 );
 
 #[cfg(display_dev = "virtio-gpu")]
 register_display_driver!(
-    <virtio::VirtIoGpu as VirtIoDevMeta>::Driver,
-    <virtio::VirtIoGpu as VirtIoDevMeta>::Device
+    virtio::VirtIoRawDriver<virtio::VirtIoGpu>,
+    virtio::VirtIoGpuDev
+);
+
+#[cfg(input_dev = "virtio-input")]
+register_input_driver!(
+    virtio::VirtIoRawDriver<virtio::VirtIoInput>,
+    virtio::VirtIoInputDev
+);
+
+#[cfg(console_dev = "virtio-console")]
+register_console_driver!(
+    virtio::VirtIoRawDriver<virtio::VirtIoConsole>,
+    virtio::VirtIoConsoleDev
+);
+
+#[cfg(rng_dev = "virtio-rng")]
+register_rng_driver!(
+    virtio::VirtIoRawDriver<virtio::VirtIoRng>,
+    virtio::VirtIoRngDev
+);
+
+#[cfg(ninep_dev = "virtio-9p")]
+register_ninep_driver!(
+    virtio::VirtIoRawDriver<virtio::VirtIo9p>,
+    virtio::VirtIo9pDev
 );
 
 cfg_if::cfg_if! {
@@ -56,6 +80,8 @@ cfg_if::cfg_if! {
         pub struct RamDiskDriver;
         register_block_driver!(RamDiskDriver, axdriver_block::ramdisk::RamDisk);
 
+        impl crate::block::BlockRequestOps for axdriver_block::ramdisk::RamDisk {}
+
         impl DriverProbe for RamDiskDriver {
             fn probe_global() -> Option<AxDeviceEnum> {
                 // TODO: format RAM disk
@@ -72,6 +98,8 @@ cfg_if::cfg_if! {
         pub struct BcmSdhciDriver;
         register_block_driver!(MmckDriver, axdriver_block::bcm2835sdhci::SDHCIDriver);
 
+        impl crate::block::BlockRequestOps for axdriver_block::bcm2835sdhci::SDHCIDriver {}
+
         impl DriverProbe for BcmSdhciDriver {
             fn probe_global() -> Option<AxDeviceEnum> {
                 debug!("mmc probe");
@@ -138,6 +166,7 @@ cfg_if::cfg_if! {
     }
     pub struct Vf2SdDriver;
     register_block_driver!(Vf2SdDriver, axdriver_block::visionfive2::SDHCIDriver<Vf2SdIo, Vf2Sleep>);
+    impl crate::block::BlockRequestOps for SDHCIDriver<Vf2SdIo, Vf2Sleep> {}
         impl DriverProbe for Vf2SdDriver {
             fn probe_global() -> Option<AxDeviceEnum> {
                 debug!("visionfive2 sd probe");
@@ -66,6 +66,8 @@ cfg_if! {
                 Err(DevError::Unsupported)
             }
         }
+
+        impl crate::block::BlockRequestOps for DummyBlockDev {}
     }
 }
 
@@ -98,5 +100,120 @@ cfg_if! {
                 Err(DevError::Unsupported)
             }
         }
+
+        impl crate::display::DisplayOutputOps for DummyDisplayDev {
+            fn mode(&self) -> crate::display::DisplayMode {
+                unreachable!()
+            }
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(input_dev = "dummy")] {
+        use crate::input::InputEvent;
+
+        pub struct DummyInputDev;
+        pub struct DummyInputDriver;
+        register_input_driver!(DummyInputDriver, DummyInputDev);
+
+        impl BaseDriverOps for DummyInputDev {
+            fn device_type(&self) -> DeviceType {
+                DeviceType::Char
+            }
+            fn device_name(&self) -> &str {
+                "dummy-input"
+            }
+        }
+
+        impl InputDriverOps for DummyInputDev {
+            fn has_event_type(&mut self, _: u16) -> bool {
+                false
+            }
+            fn read_event(&mut self) -> DevResult<InputEvent> {
+                Err(DevError::Unsupported)
+            }
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(console_dev = "dummy")] {
+        pub struct DummyConsoleDev;
+        pub struct DummyConsoleDriver;
+        register_console_driver!(DummyConsoleDriver, DummyConsoleDev);
+
+        impl BaseDriverOps for DummyConsoleDev {
+            fn device_type(&self) -> DeviceType {
+                DeviceType::Char
+            }
+            fn device_name(&self) -> &str {
+                "dummy-console"
+            }
+        }
+
+        impl ConsoleDriverOps for DummyConsoleDev {
+            fn read(&mut self, _: &mut [u8]) -> DevResult<usize> {
+                Err(DevError::Unsupported)
+            }
+            fn write(&mut self, _: &[u8]) -> DevResult<usize> {
+                Err(DevError::Unsupported)
+            }
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(rng_dev = "dummy")] {
+        pub struct DummyRngDev;
+        pub struct DummyRngDriver;
+        register_rng_driver!(DummyRngDriver, DummyRngDev);
+
+        impl BaseDriverOps for DummyRngDev {
+            fn device_type(&self) -> DeviceType {
+                DeviceType::Char
+            }
+            fn device_name(&self) -> &str {
+                "dummy-rng"
+            }
+        }
+
+        impl RngDriverOps for DummyRngDev {
+            fn fill_bytes(&mut self, _: &mut [u8]) -> DevResult<usize> {
+                Err(DevError::Unsupported)
+            }
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(ninep_dev = "dummy")] {
+        pub struct DummyNinePDev;
+        pub struct DummyNinePDriver;
+        register_ninep_driver!(DummyNinePDriver, DummyNinePDev);
+
+        impl BaseDriverOps for DummyNinePDev {
+            fn device_type(&self) -> DeviceType {
+                DeviceType::Char
+            }
+            fn device_name(&self) -> &str {
+                "dummy-9p"
+            }
+        }
+
+        impl NinePDriverOps for DummyNinePDev {
+            fn mount_tag(&self) -> &str {
+                ""
+            }
+            fn max_message_size(&self) -> usize {
+                0
+            }
+            fn submit(&mut self, _: &[u8]) -> DevResult {
+                Err(DevError::Unsupported)
+            }
+            fn poll_response(&mut self, _: &mut [u8]) -> DevResult<usize> {
+                Err(DevError::Unsupported)
+            }
+        }
     }
 }
@@ -0,0 +1,40 @@
+//! Common traits and types for input device drivers (keyboards, mice, tablets,
+//! etc.).
+//!
+//! Events follow the Linux input event codes, which are also used by VirtIO
+//! input devices. For example, a key press is reported as an [`EV_KEY`] event
+//! with value 1, and a batch of events is terminated by an [`EV_SYN`] event.
+
+use axdriver_base::{BaseDriverOps, DevResult};
+
+/// Synchronization event, which separates batches of events.
+pub const EV_SYN: u16 = 0x00;
+/// Key or button state change.
+pub const EV_KEY: u16 = 0x01;
+/// Relative axis change (e.g., mouse motion or wheel).
+pub const EV_REL: u16 = 0x02;
+/// Absolute axis change (e.g., tablet or touchscreen coordinates).
+pub const EV_ABS: u16 = 0x03;
+
+/// An event reported by an input device.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InputEvent {
+    /// The event type, e.g., [`EV_KEY`].
+    pub event_type: u16,
+    /// The event code, e.g., the key code of an [`EV_KEY`] event.
+    pub code: u16,
+    /// The event value, e.g., 1 for a key press and 0 for a key release.
+    pub value: u32,
+}
+
+/// Operations that require an input device driver to implement.
+pub trait InputDriverOps: BaseDriverOps {
+    /// Whether the device may report events of the given type.
+    fn has_event_type(&mut self, event_type: u16) -> bool;
+
+    /// Pops the next pending event.
+    ///
+    /// Returns [`DevError::Again`](axdriver_base::DevError::Again) if there is
+    /// no pending event.
+    fn read_event(&mut self) -> DevResult<InputEvent>;
+}
@@ -0,0 +1,237 @@
+//! Device interrupts.
+//!
+//! Interrupts of devices are configured by the bus when they are probed, and
+//! routed to callbacks registered by the users of the devices (e.g., the
+//! network stack) with [`set_irq_callback`]. A device is identified by its
+//! name in the [`registry`](crate::registry).
+//!
+//! Devices without interrupts, or whose interrupts can not be routed on the
+//! platform, still need to be polled. So do block devices whose drivers do not
+//! implement the requests of [`BlockRequestOps`], and wait for the completion
+//! in place.
+//!
+//! [`BlockRequestOps`]: crate::block::BlockRequestOps
+
+// Only PCI devices are configured with interrupts for now.
+#![cfg_attr(not(bus = "pci"), allow(dead_code))]
+
+use axdriver_base::DeviceType;
+use kspin::SpinNoIrq;
+
+/// The maximum number of distinct IRQs used by all devices.
+const MAX_LINES: usize = 16;
+
+/// The maximum number of interrupt vectors of all devices. An IRQ may be
+/// shared by multiple vectors (e.g., PCI legacy interrupts).
+const MAX_VECTORS: usize = 32;
+
+/// The maximum number of registered callbacks.
+const MAX_CALLBACKS: usize = 16;
+
+/// The maximum number of vectors of a device.
+pub(crate) const MAX_DEVICE_VECTORS: usize = 4;
+
+/// A callback invoked in the interrupt context when a device raises an
+/// interrupt.
+pub type IrqCallback = fn();
+
+/// How a device raises interrupts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrqMode {
+    /// PCI legacy interrupt, shared by all queues of the device, and possibly
+    /// by other devices.
+    Intx,
+    /// Message signaled interrupt, shared by all queues of the device.
+    Msi,
+    /// Extended message signaled interrupt, with one vector per queue if
+    /// there are enough vectors.
+    MsiX,
+}
+
+/// A probed device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct DeviceId {
+    pub dev_type: DeviceType,
+    /// The ID assigned by the registry, which is never reused.
+    pub id: usize,
+}
+
+/// An interrupt source of a device.
+#[derive(Clone, Copy)]
+pub(crate) struct IrqVector {
+    pub dev: DeviceId,
+    pub mode: IrqMode,
+    pub irq_num: usize,
+    /// The queue signaled by this vector, or `None` if it's shared by all
+    /// queues.
+    pub queue: Option<u16>,
+    /// The virtual address of the VirtIO ISR status register, which is read
+    /// to check and acknowledge a shared interrupt.
+    pub isr: Option<usize>,
+}
+
+#[derive(Clone, Copy)]
+struct Callback {
+    dev: DeviceId,
+    queue: Option<u16>,
+    callback: IrqCallback,
+}
+
+struct IrqTable {
+    lines: [Option<usize>; MAX_LINES],
+    vectors: [Option<IrqVector>; MAX_VECTORS],
+    callbacks: [Option<Callback>; MAX_CALLBACKS],
+}
+
+static IRQ_TABLE: SpinNoIrq<IrqTable> = SpinNoIrq::new(IrqTable {
+    lines: [None; MAX_LINES],
+    vectors: [None; MAX_VECTORS],
+    callbacks: [None; MAX_CALLBACKS],
+});
+
+macro_rules! line_handlers {
+    ($($i:literal)*) => {
+        [$(handle_line::<$i> as axhal::irq::IrqHandler),*]
+    };
+}
+
+/// The IRQ handlers of each line, since handlers do not know the IRQ number.
+const LINE_HANDLERS: [axhal::irq::IrqHandler; MAX_LINES] =
+    line_handlers!(0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15);
+
+fn handle_line<const I: usize>() {
+    let (vectors, callbacks) = {
+        let table = IRQ_TABLE.lock();
+        let Some(irq_num) = table.lines[I] else {
+            return;
+        };
+        let mut vectors = table.vectors;
+        for vector in &mut vectors {
+            if vector.is_some_and(|v| v.irq_num != irq_num) {
+                *vector = None;
+            }
+        }
+        (vectors, table.callbacks)
+    };
+
+    for vector in vectors.iter().flatten() {
+        if let Some(isr) = vector.isr {
+            // Reading the ISR status acknowledges the interrupt. Bit 0 is set
+            // on used buffer notifications.
+            let status = unsafe { (isr as *const u8).read_volatile() };
+            if status & 1 == 0 {
+                continue;
+            }
+        }
+        for cb in callbacks.iter().flatten() {
+            let queue_matches = match (vector.queue, cb.queue) {
+                (Some(q), Some(cb_q)) => q == cb_q,
+                _ => true,
+            };
+            if cb.dev == vector.dev && queue_matches {
+                (cb.callback)();
+            }
+        }
+    }
+}
+
+const fn irq_name(dev_type: DeviceType) -> &'static str {
+    match dev_type {
+        DeviceType::Block => "block",
+        DeviceType::Char => "char",
+        DeviceType::Net => "net",
+        DeviceType::Display => "display",
+    }
+}
+
+/// Adds an interrupt source of a device, and registers the IRQ handler if the
+/// IRQ is not used by other devices yet.
+///
+/// Returns `false` if the tables are full or the IRQ handler can not be
+/// registered.
+pub(crate) fn add_vector(vector: IrqVector) -> bool {
+    let mut table = IRQ_TABLE.lock();
+    let Some(vector_slot) = table.vectors.iter().position(Option::is_none) else {
+        warn!("too many device interrupt vectors");
+        return false;
+    };
+    if !table.lines.contains(&Some(vector.irq_num)) {
+        let Some(line) = table.lines.iter().position(Option::is_none) else {
+            warn!("too many device IRQs");
+            return false;
+        };
+        let name = irq_name(vector.dev.dev_type);
+        if !axhal::irq::register_named_handler(vector.irq_num, name, LINE_HANDLERS[line]) {
+            warn!("failed to register handler for IRQ {}", vector.irq_num);
+            return false;
+        }
+        table.lines[line] = Some(vector.irq_num);
+    }
+    table.vectors[vector_slot] = Some(vector);
+    true
+}
+
+/// Returns how the given device raises interrupts, or `None` if it does not,
+/// and must be polled.
+pub fn irq_mode(name: &str) -> Option<IrqMode> {
+    let id = crate::registry::find_id(name)?;
+    IRQ_TABLE
+        .lock()
+        .vectors
+        .iter()
+        .flatten()
+        .find(|v| v.dev.id == id)
+        .map(|v| v.mode)
+}
+
+/// Sets the callback invoked in the interrupt context when the given device
+/// raises an interrupt.
+///
+/// If `queue` is given, it's only invoked for that queue, unless the queues of
+/// the device share an interrupt. The callback should be short, e.g., waking
+/// up the waiters, and must not call into the device driver.
+///
+/// Returns `false` if the device does not raise interrupts (see [`irq_mode`]),
+/// or there are too many callbacks.
+pub fn set_irq_callback(name: &str, queue: Option<u16>, callback: IrqCallback) -> bool {
+    let Some(id) = crate::registry::find_id(name) else {
+        return false;
+    };
+    let mut table = IRQ_TABLE.lock();
+    let Some(dev) = table
+        .vectors
+        .iter()
+        .flatten()
+        .find(|v| v.dev.id == id)
+        .map(|v| v.dev)
+    else {
+        return false;
+    };
+    let Some(slot) = table.callbacks.iter_mut().find(|cb| cb.is_none()) else {
+        warn!("too many device interrupt callbacks");
+        return false;
+    };
+    *slot = Some(Callback {
+        dev,
+        queue,
+        callback,
+    });
+    true
+}
+
+/// Stops delivering interrupts of a removed device, and drops its callbacks.
+///
+/// The IRQ handlers stay registered, since the IRQs may be shared.
+pub(crate) fn remove_device(id: usize) {
+    let mut table = IRQ_TABLE.lock();
+    for vector in &mut table.vectors {
+        if vector.is_some_and(|v| v.dev.id == id) {
+            *vector = None;
+        }
+    }
+    for cb in &mut table.callbacks {
+        if cb.is_some_and(|cb| cb.dev.id == id) {
+            *cb = None;
+        }
+    }
+}
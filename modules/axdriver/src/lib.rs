@@ -2,14 +2,17 @@
 //!
 //! # Usage
 //!
-//! All detected devices are composed into a large struct [`AllDevices`]
-//! and returned by the [`init_drivers`] function. The upperlayer subsystems
-//! (e.g., the network stack) may unpack the struct to get the specified device
-//! driver they want.
+//! All detected devices are registered in the [`registry`] by the
+//! [`init_drivers`] function, with stable names such as `vda` and `eth0`. The
+//! upperlayer subsystems (e.g., the network stack) open the devices they want
+//! from the registry by name, or by category (e.g., [`registry::first`]).
+//! Devices are shared, so several subsystems may open the same device.
 //!
 //! For each device category (i.e., net, block, display, etc.), an unified type
-//! is used to represent all devices in that category. Currently, there are 3
-//! categories: [`AxNetDevice`], [`AxBlockDevice`], and [`AxDisplayDevice`].
+//! is used to represent all devices in that category. Currently, there are 7
+//! categories: [`AxNetDevice`], [`AxBlockDevice`], [`AxDisplayDevice`],
+//! [`AxInputDevice`], [`AxConsoleDevice`], [`AxRngDevice`], and
+//! [`AxNinePDevice`].
 //!
 //! # Concepts
 //!
@@ -19,12 +22,13 @@
 //!   time by corresponding cargo features. For example, [`AxNetDevice`] will be
 //!   an alias of [`VirtioNetDev`] if the `virtio-net` feature is enabled. This
 //!   model provides the best performance as it avoids dynamic dispatch. But on
-//!   limitation, only one device instance is supported for each device category.
+//!   limitation, only one driver is supported for each device category, though
+//!   it may drive several devices.
 //! - **Dynamic**: All device instance is using [trait objects] and wrapped in a
 //!   `Box<dyn Trait>`. For example, [`AxNetDevice`] will be [`Box<dyn NetDriverOps>`].
 //!   When call a method provided by the device, it uses [dynamic dispatch][dyn]
 //!   that may introduce a little overhead. But on the other hand, it is more
-//!   flexible, multiple instances of each device category are supported.
+//!   flexible, multiple drivers of each device category are supported.
 //!
 //! # Supported Devices
 //!
@@ -34,6 +38,10 @@
 //! | Block | `virtio-blk` | VirtIO block device |
 //! | Network | `virtio-net` | VirtIO network device |
 //! | Display | `virtio-gpu` | VirtIO graphics device |
+//! | Input | `virtio-input` | VirtIO input device (keyboard, mouse, tablet) |
+//! | Console | `virtio-console` | VirtIO console device |
+//! | RNG | `virtio-rng` | VirtIO entropy device |
+//! | 9P | `virtio-9p` | VirtIO 9P transport (host directory sharing) |
 //!
 //! # Other Cargo Features
 //!
@@ -42,26 +50,38 @@
 //! - `bus-pci`: use PCI bus to probe all PCI devices. This feature is
 //!   enabled by default.
 //! - `virtio`: use VirtIO devices. This is enabled if any of `virtio-blk`,
-//!   `virtio-net` or `virtio-gpu` is enabled.
+//!   `virtio-net`, `virtio-gpu`, `virtio-input`, `virtio-console`,
+//!   `virtio-rng` or `virtio-9p` is enabled.
 //! - `net`: use network devices. This is enabled if any feature of network
 //!   devices is selected. If this feature is enabled without any network device
 //!   features, a dummy struct is used for [`AxNetDevice`].
-//! - `block`: use block storage devices. Similar to the `net` feature.
-//! - `display`: use graphics display devices. Similar to the `net` feature.
+//! - `block`: use block storage devices, whose drivers also implement
+//!   [`block::BlockRequestOps`] to complete requests asynchronously. Similar
+//!   to the `net` feature.
+//! - `display`: use graphics display devices, whose drivers also implement
+//!   [`display::DisplayOutputOps`] for mode setting, partial flushing, double
+//!   buffering and hardware cursors. Similar to the `net` feature.
+//! - `input`: use input devices. Similar to the `net` feature.
+//! - `console`: use console devices. Similar to the `net` feature.
+//! - `rng`: use hardware random number generators. Similar to the `net`
+//!   feature.
+//! - `ninep`: use 9P transports. Similar to the `net` feature.
+//! - `irq`: configure interrupts of PCI devices (MSI-X, MSI, or legacy
+//!   interrupts), so that their users can register callbacks in the [`irq`]
+//!   module instead of polling them.
 //!
 //! [`VirtioNetDev`]: axdriver_virtio::VirtIoNetDev
 //! [`Box<dyn NetDriverOps>`]: axdriver_net::NetDriverOps
 //! [trait objects]: https://doc.rust-lang.org/book/ch17-02-trait-objects.html
 //! [dyn]: https://doc.rust-lang.org/std/keyword.dyn.html
 
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 #![feature(doc_auto_cfg)]
 #![feature(associated_type_defaults)]
 
 #[macro_use]
 extern crate log;
 
-#[cfg(feature = "dyn")]
 extern crate alloc;
 
 #[macro_use]
@@ -78,6 +98,23 @@ mod virtio;
 #[cfg(feature = "ixgbe")]
 mod ixgbe;
 
+#[cfg(feature = "irq")]
+pub mod irq;
+pub mod registry;
+
+#[cfg(feature = "block")]
+pub mod block;
+#[cfg(feature = "console")]
+pub mod console;
+#[cfg(feature = "display")]
+pub mod display;
+#[cfg(feature = "input")]
+pub mod input;
+#[cfg(feature = "ninep")]
+pub mod ninep;
+#[cfg(feature = "rng")]
+pub mod rng;
+
 pub mod prelude;
 
 #[allow(unused_imports)]
@@ -86,99 +123,51 @@ pub use self::structs::{AxDeviceContainer, AxDeviceEnum};
 
 #[cfg(feature = "block")]
 pub use self::structs::AxBlockDevice;
+#[cfg(feature = "console")]
+pub use self::structs::AxConsoleDevice;
 #[cfg(feature = "display")]
 pub use self::structs::AxDisplayDevice;
+#[cfg(feature = "input")]
+pub use self::structs::AxInputDevice;
 #[cfg(feature = "net")]
 pub use self::structs::AxNetDevice;
-
-/// A structure that contains all device drivers, organized by their category.
-#[derive(Default)]
-pub struct AllDevices {
-    /// All network device drivers.
-    #[cfg(feature = "net")]
-    pub net: AxDeviceContainer<AxNetDevice>,
-    /// All block device drivers.
-    #[cfg(feature = "block")]
-    pub block: AxDeviceContainer<AxBlockDevice>,
-    /// All graphics device drivers.
-    #[cfg(feature = "display")]
-    pub display: AxDeviceContainer<AxDisplayDevice>,
-}
-
-impl AllDevices {
-    /// Returns the device model used, either `dyn` or `static`.
-    ///
-    /// See the [crate-level documentation](crate) for more details.
-    pub const fn device_model() -> &'static str {
-        if cfg!(feature = "dyn") {
-            "dyn"
-        } else {
-            "static"
-        }
-    }
-
-    /// Probes all supported devices.
-    fn probe(&mut self) {
-        for_each_drivers!(type Driver, {
-            if let Some(dev) = Driver::probe_global() {
-                info!(
-                    "registered a new {:?} device: {:?}",
-                    dev.device_type(),
-                    dev.device_name(),
-                );
-                self.add_device(dev);
-            }
-        });
-
-        self.probe_bus_devices();
-    }
-
-    /// Adds one device into the corresponding container, according to its device category.
-    #[allow(dead_code)]
-    fn add_device(&mut self, dev: AxDeviceEnum) {
-        match dev {
-            #[cfg(feature = "net")]
-            AxDeviceEnum::Net(dev) => self.net.push(dev),
-            #[cfg(feature = "block")]
-            AxDeviceEnum::Block(dev) => self.block.push(dev),
-            #[cfg(feature = "display")]
-            AxDeviceEnum::Display(dev) => self.display.push(dev),
-        }
+#[cfg(feature = "ninep")]
+pub use self::structs::AxNinePDevice;
+#[cfg(feature = "rng")]
+pub use self::structs::AxRngDevice;
+
+/// Returns the device model used, either `dyn` or `static`.
+///
+/// See the [crate-level documentation](crate) for more details.
+pub const fn device_model() -> &'static str {
+    if cfg!(feature = "dyn") {
+        "dyn"
+    } else {
+        "static"
     }
 }
 
-/// Probes and initializes all device drivers, returns the [`AllDevices`] struct.
-pub fn init_drivers() -> AllDevices {
+/// Probes and initializes all device drivers, and registers the devices in the
+/// [`registry`].
+pub fn init_drivers() {
     info!("Initialize device drivers...");
-    info!("  device model: {}", AllDevices::device_model());
+    info!("  device model: {}", device_model());
+
+    for_each_drivers!(type Driver, {
+        if let Some(dev) = Driver::probe_global() {
+            info!(
+                "found a new {:?} device: {:?}",
+                dev.device_type(),
+                dev.device_name(),
+            );
+            registry::register(dev);
+        }
+    });
 
-    let mut all_devs = AllDevices::default();
-    all_devs.probe();
+    bus::probe_bus_devices();
 
-    #[cfg(feature = "net")]
-    {
-        debug!("number of NICs: {}", all_devs.net.len());
-        for (i, dev) in all_devs.net.iter().enumerate() {
-            assert_eq!(dev.device_type(), DeviceType::Net);
-            debug!("  NIC {}: {:?}", i, dev.device_name());
-        }
+    debug!("number of devices: {}", registry::all_devices().len());
+    for dev in registry::all_devices() {
+        debug!("  {:?} device {}", dev.device_type(), dev.name());
     }
-    #[cfg(feature = "block")]
-    {
-        debug!("number of block devices: {}", all_devs.block.len());
-        for (i, dev) in all_devs.block.iter().enumerate() {
-            assert_eq!(dev.device_type(), DeviceType::Block);
-            debug!("  block device {}: {:?}", i, dev.device_name());
-        }
-    }
-    #[cfg(feature = "display")]
-    {
-        debug!("number of graphics devices: {}", all_devs.display.len());
-        for (i, dev) in all_devs.display.iter().enumerate() {
-            assert_eq!(dev.device_type(), DeviceType::Display);
-            debug!("  graphics device {}: {:?}", i, dev.device_name());
-        }
-    }
-
-    all_devs
 }
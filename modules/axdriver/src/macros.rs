@@ -26,6 +26,38 @@ macro_rules! register_display_driver {
     };
 }
 
+macro_rules! register_input_driver {
+    ($driver_type:ty, $device_type:ty) => {
+        /// The unified type of the input devices.
+        #[cfg(not(feature = "dyn"))]
+        pub type AxInputDevice = $device_type;
+    };
+}
+
+macro_rules! register_console_driver {
+    ($driver_type:ty, $device_type:ty) => {
+        /// The unified type of the console devices.
+        #[cfg(not(feature = "dyn"))]
+        pub type AxConsoleDevice = $device_type;
+    };
+}
+
+macro_rules! register_rng_driver {
+    ($driver_type:ty, $device_type:ty) => {
+        /// The unified type of the random number generators.
+        #[cfg(not(feature = "dyn"))]
+        pub type AxRngDevice = $device_type;
+    };
+}
+
+macro_rules! register_ninep_driver {
+    ($driver_type:ty, $device_type:ty) => {
+        /// The unified type of the 9P transports.
+        #[cfg(not(feature = "dyn"))]
+        pub type AxNinePDevice = $device_type;
+    };
+}
+
 macro_rules! for_each_drivers {
     (type $drv_type:ident, $code:block) => {{
         #[allow(unused_imports)]
@@ -41,12 +73,32 @@ macro_rules! for_each_drivers {
         }
         #[cfg(block_dev = "virtio-blk")]
         {
-            type $drv_type = <virtio::VirtIoBlk as VirtIoDevMeta>::Driver;
+            type $drv_type = virtio::VirtIoRawDriver<virtio::VirtIoBlk>;
             $code
         }
         #[cfg(display_dev = "virtio-gpu")]
         {
-            type $drv_type = <virtio::VirtIoGpu as VirtIoDevMeta>::Driver;
+            type $drv_type = virtio::VirtIoRawDriver<virtio::VirtIoGpu>;
+            $code
+        }
+        #[cfg(input_dev = "virtio-input")]
+        {
+            type $drv_type = virtio::VirtIoRawDriver<virtio::VirtIoInput>;
+            $code
+        }
+        #[cfg(console_dev = "virtio-console")]
+        {
+            type $drv_type = virtio::VirtIoRawDriver<virtio::VirtIoConsole>;
+            $code
+        }
+        #[cfg(rng_dev = "virtio-rng")]
+        {
+            type $drv_type = virtio::VirtIoRawDriver<virtio::VirtIoRng>;
+            $code
+        }
+        #[cfg(ninep_dev = "virtio-9p")]
+        {
+            type $drv_type = virtio::VirtIoRawDriver<virtio::VirtIo9p>;
             $code
         }
         #[cfg(block_dev = "ramdisk")]
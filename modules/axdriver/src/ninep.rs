@@ -0,0 +1,46 @@
+//! Common traits and types for 9P transport drivers, which carry 9P messages
+//! to a file server on the host (e.g., a directory shared by QEMU `-virtfs`).
+
+use axdriver_base::{BaseDriverOps, DevError, DevResult};
+
+/// Operations that require a 9P transport driver to implement.
+pub trait NinePDriverOps: BaseDriverOps {
+    /// Returns the tag of the exported file system, which identifies it to
+    /// the guest, or an empty string if it's not provided.
+    fn mount_tag(&self) -> &str;
+
+    /// Returns the maximum size of a message, including the header.
+    fn max_message_size(&self) -> usize;
+
+    /// Sends the request message `req` without waiting for the response.
+    ///
+    /// Returns [`DevError::Again`] if the previous request is still in flight.
+    fn submit(&mut self, req: &[u8]) -> DevResult;
+
+    /// Writes the response to the submitted request into `resp`, and returns
+    /// its length.
+    ///
+    /// Returns [`DevError::Again`] if the response has not arrived yet.
+    fn poll_response(&mut self, resp: &mut [u8]) -> DevResult<usize>;
+
+    /// Sends the request message `req`, waits for the response and writes it
+    /// into `resp`. Returns the length of the response.
+    ///
+    /// It spins while waiting, so it should only be used if the caller can
+    /// not block, see [`submit`](Self::submit) and
+    /// [`poll_response`](Self::poll_response) otherwise.
+    fn request(&mut self, req: &[u8], resp: &mut [u8]) -> DevResult<usize> {
+        loop {
+            match self.submit(req) {
+                Err(DevError::Again) => core::hint::spin_loop(),
+                result => break result?,
+            }
+        }
+        loop {
+            match self.poll_response(resp) {
+                Err(DevError::Again) => core::hint::spin_loop(),
+                result => return result,
+            }
+        }
+    }
+}
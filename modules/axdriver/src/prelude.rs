@@ -3,8 +3,21 @@
 pub use axdriver_base::{BaseDriverOps, DevError, DevResult, DeviceType};
 
 #[cfg(feature = "block")]
-pub use {crate::structs::AxBlockDevice, axdriver_block::BlockDriverOps};
+pub use {
+    crate::block::BlockRequestOps, crate::structs::AxBlockDevice, axdriver_block::BlockDriverOps,
+};
+#[cfg(feature = "console")]
+pub use {crate::console::ConsoleDriverOps, crate::structs::AxConsoleDevice};
 #[cfg(feature = "display")]
-pub use {crate::structs::AxDisplayDevice, axdriver_display::DisplayDriverOps};
+pub use {
+    crate::display::DisplayOutputOps, crate::structs::AxDisplayDevice,
+    axdriver_display::DisplayDriverOps,
+};
+#[cfg(feature = "input")]
+pub use {crate::input::InputDriverOps, crate::structs::AxInputDevice};
+#[cfg(feature = "ninep")]
+pub use {crate::ninep::NinePDriverOps, crate::structs::AxNinePDevice};
+#[cfg(feature = "rng")]
+pub use {crate::rng::RngDriverOps, crate::structs::AxRngDevice};
 #[cfg(feature = "net")]
 pub use {crate::structs::AxNetDevice, axdriver_net::NetDriverOps};
@@ -0,0 +1,530 @@
+//! The registry of all devices.
+//!
+//! Probed devices are registered with stable names, which are kept until the
+//! devices are removed:
+//!
+//! | Device Category | Names |
+//! |-|-|
+//! | Block | `vda`, `vdb`, ..., `vdz`, `vdaa`, ... |
+//! | Network | `eth0`, `eth1`, ... |
+//! | Display | `fb0`, `fb1`, ... |
+//! | Input | `event0`, `event1`, ... |
+//! | Console | `hvc0`, `hvc1`, ... |
+//! | RNG | `hwrng0`, `hwrng1`, ... |
+//! | 9P | `9p0`, `9p1`, ... |
+//!
+//! A new device takes the lowest free number of its category, so names of
+//! removed devices may be reused.
+//!
+//! Devices are not consumed by their users. Opening a device (see [`open`])
+//! returns a shared reference, so a device may be used by several subsystems
+//! at the same time, and each operation locks the driver. Users may also be
+//! notified when devices are added or removed (see [`add_listener`]).
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use axdriver_base::{BaseDriverOps, DeviceType};
+use kspin::{SpinNoIrq, SpinNoPreempt, SpinNoPreemptGuard};
+
+use crate::AxDeviceEnum;
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+/// A registered device, shared by its users.
+pub struct Device<D> {
+    id: usize,
+    name: String,
+    removed: AtomicBool,
+    driver: SpinNoPreempt<D>,
+}
+
+/// A shared reference to a registered device.
+pub type DeviceRef<D> = Arc<Device<D>>;
+
+impl<D> Device<D> {
+    /// Returns the registered name of the device, e.g., `vda`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the device has been removed from the registry.
+    ///
+    /// The driver of a removed device is still accessible to existing users,
+    /// but operations on it may fail.
+    pub fn is_removed(&self) -> bool {
+        self.removed.load(Ordering::Acquire)
+    }
+
+    /// Locks the driver of the device, and returns a guard to operate on it.
+    pub fn lock(&self) -> SpinNoPreemptGuard<'_, D> {
+        self.driver.lock()
+    }
+
+    /// Tries to lock the driver of the device, returns `None` if it's in use.
+    pub fn try_lock(&self) -> Option<SpinNoPreemptGuard<'_, D>> {
+        self.driver.try_lock()
+    }
+}
+
+/// A registered device of any category.
+#[derive(Clone)]
+pub enum RegisteredDevice {
+    /// Network card device.
+    #[cfg(feature = "net")]
+    Net(DeviceRef<AxNetDevice>),
+    /// Block storage device.
+    #[cfg(feature = "block")]
+    Block(DeviceRef<AxBlockDevice>),
+    /// Graphic display device.
+    #[cfg(feature = "display")]
+    Display(DeviceRef<AxDisplayDevice>),
+    /// Input device.
+    #[cfg(feature = "input")]
+    Input(DeviceRef<AxInputDevice>),
+    /// Console device.
+    #[cfg(feature = "console")]
+    Console(DeviceRef<AxConsoleDevice>),
+    /// Random number generator.
+    #[cfg(feature = "rng")]
+    Rng(DeviceRef<AxRngDevice>),
+    /// 9P transport.
+    #[cfg(feature = "ninep")]
+    NineP(DeviceRef<AxNinePDevice>),
+}
+
+impl RegisteredDevice {
+    /// Returns the registered name of the device.
+    #[allow(unreachable_patterns)]
+    pub fn name(&self) -> &str {
+        match self {
+            #[cfg(feature = "net")]
+            Self::Net(dev) => dev.name(),
+            #[cfg(feature = "block")]
+            Self::Block(dev) => dev.name(),
+            #[cfg(feature = "display")]
+            Self::Display(dev) => dev.name(),
+            #[cfg(feature = "input")]
+            Self::Input(dev) => dev.name(),
+            #[cfg(feature = "console")]
+            Self::Console(dev) => dev.name(),
+            #[cfg(feature = "rng")]
+            Self::Rng(dev) => dev.name(),
+            #[cfg(feature = "ninep")]
+            Self::NineP(dev) => dev.name(),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the type of the device.
+    #[allow(unreachable_patterns)]
+    pub fn device_type(&self) -> DeviceType {
+        match self {
+            #[cfg(feature = "net")]
+            Self::Net(_) => DeviceType::Net,
+            #[cfg(feature = "block")]
+            Self::Block(_) => DeviceType::Block,
+            #[cfg(feature = "display")]
+            Self::Display(_) => DeviceType::Display,
+            #[cfg(feature = "input")]
+            Self::Input(_) => DeviceType::Char,
+            #[cfg(feature = "console")]
+            Self::Console(_) => DeviceType::Char,
+            #[cfg(feature = "rng")]
+            Self::Rng(_) => DeviceType::Char,
+            #[cfg(feature = "ninep")]
+            Self::NineP(_) => DeviceType::Char,
+            _ => unreachable!(),
+        }
+    }
+
+    #[allow(unreachable_patterns)]
+    fn id(&self) -> usize {
+        match self {
+            #[cfg(feature = "net")]
+            Self::Net(dev) => dev.id,
+            #[cfg(feature = "block")]
+            Self::Block(dev) => dev.id,
+            #[cfg(feature = "display")]
+            Self::Display(dev) => dev.id,
+            #[cfg(feature = "input")]
+            Self::Input(dev) => dev.id,
+            #[cfg(feature = "console")]
+            Self::Console(dev) => dev.id,
+            #[cfg(feature = "rng")]
+            Self::Rng(dev) => dev.id,
+            #[cfg(feature = "ninep")]
+            Self::NineP(dev) => dev.id,
+            _ => unreachable!(),
+        }
+    }
+
+    #[allow(unreachable_patterns)]
+    fn set_removed(&self) {
+        match self {
+            #[cfg(feature = "net")]
+            Self::Net(dev) => dev.removed.store(true, Ordering::Release),
+            #[cfg(feature = "block")]
+            Self::Block(dev) => dev.removed.store(true, Ordering::Release),
+            #[cfg(feature = "display")]
+            Self::Display(dev) => dev.removed.store(true, Ordering::Release),
+            #[cfg(feature = "input")]
+            Self::Input(dev) => dev.removed.store(true, Ordering::Release),
+            #[cfg(feature = "console")]
+            Self::Console(dev) => dev.removed.store(true, Ordering::Release),
+            #[cfg(feature = "rng")]
+            Self::Rng(dev) => dev.removed.store(true, Ordering::Release),
+            #[cfg(feature = "ninep")]
+            Self::NineP(dev) => dev.removed.store(true, Ordering::Release),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// A device category, implemented by the unified type of the category, e.g.,
+/// [`AxBlockDevice`].
+pub trait DeviceClass: Sized + 'static {
+    /// The prefix of device names, e.g., `eth`.
+    const NAME_PREFIX: &'static str;
+
+    /// Whether device names end with letters (`vda`) instead of numbers
+    /// (`eth0`).
+    const LETTER_SUFFIX: bool = false;
+
+    /// Returns the device if it's of this category.
+    fn downcast(dev: &RegisteredDevice) -> Option<&DeviceRef<Self>>;
+
+    /// Wraps a device of this category.
+    fn upcast(dev: DeviceRef<Self>) -> RegisteredDevice;
+}
+
+macro_rules! impl_device_class {
+    ($feature:literal, $ty:ty, $variant:ident, $prefix:literal $(, $letter:literal)?) => {
+        #[cfg(feature = $feature)]
+        impl DeviceClass for $ty {
+            const NAME_PREFIX: &'static str = $prefix;
+            $(const LETTER_SUFFIX: bool = $letter;)?
+
+            #[allow(unreachable_patterns)]
+            fn downcast(dev: &RegisteredDevice) -> Option<&DeviceRef<Self>> {
+                match dev {
+                    RegisteredDevice::$variant(dev) => Some(dev),
+                    _ => None,
+                }
+            }
+
+            fn upcast(dev: DeviceRef<Self>) -> RegisteredDevice {
+                RegisteredDevice::$variant(dev)
+            }
+        }
+    };
+}
+
+impl_device_class!("net", AxNetDevice, Net, "eth");
+impl_device_class!("block", AxBlockDevice, Block, "vd", true);
+impl_device_class!("display", AxDisplayDevice, Display, "fb");
+impl_device_class!("input", AxInputDevice, Input, "event");
+impl_device_class!("console", AxConsoleDevice, Console, "hvc");
+impl_device_class!("rng", AxRngDevice, Rng, "hwrng");
+impl_device_class!("ninep", AxNinePDevice, NineP, "9p");
+
+/// A change of the registry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceEvent {
+    /// The device is registered.
+    Added,
+    /// The device is removed.
+    Removed,
+}
+
+/// A function called when a device is added or removed.
+pub type DeviceListener = fn(DeviceEvent, &RegisteredDevice);
+
+struct Registry {
+    devices: Vec<RegisteredDevice>,
+    listeners: Vec<DeviceListener>,
+}
+
+static REGISTRY: SpinNoIrq<Registry> = SpinNoIrq::new(Registry {
+    devices: Vec::new(),
+    listeners: Vec::new(),
+});
+
+/// The ID of the next registered device. IDs are never reused, unlike names.
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Allocates the ID of a device before it's registered, e.g., to configure
+/// its interrupts.
+pub(crate) fn alloc_id() -> usize {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+fn make_name<D: DeviceClass>(index: usize) -> String {
+    if !D::LETTER_SUFFIX {
+        return format!("{}{}", D::NAME_PREFIX, index);
+    }
+    // a, b, ..., z, aa, ab, ...
+    let mut suffix = String::new();
+    let mut n = index + 1;
+    while n > 0 {
+        n -= 1;
+        suffix.insert(0, (b'a' + (n % 26) as u8) as char);
+        n /= 26;
+    }
+    format!("{}{}", D::NAME_PREFIX, suffix)
+}
+
+/// Calls `listeners`, which are taken together with the change of the registry
+/// so that each listener either sees the change in the snapshot of
+/// [`add_listener`], or is notified here, but not both.
+fn notify(listeners: &[DeviceListener], event: DeviceEvent, dev: &RegisteredDevice) {
+    for listener in listeners {
+        listener(event, dev);
+    }
+}
+
+fn register_class<D: DeviceClass>(driver: D, id: usize) -> (RegisteredDevice, Vec<DeviceListener>) {
+    let mut registry = REGISTRY.lock();
+    let name = (0..)
+        .map(make_name::<D>)
+        .find(|name| registry.devices.iter().all(|dev| dev.name() != name))
+        .unwrap();
+    let dev = D::upcast(Arc::new(Device {
+        id,
+        name,
+        removed: AtomicBool::new(false),
+        driver: SpinNoPreempt::new(driver),
+    }));
+    registry.devices.push(dev.clone());
+    (dev, registry.listeners.clone())
+}
+
+/// Registers a device with the given ID from [`alloc_id`].
+pub(crate) fn register_with_id(dev: AxDeviceEnum, id: usize) -> RegisteredDevice {
+    let driver_name = dev.device_name().to_string();
+    let (dev, listeners) = match dev {
+        #[cfg(feature = "net")]
+        AxDeviceEnum::Net(dev) => register_class(dev, id),
+        #[cfg(feature = "block")]
+        AxDeviceEnum::Block(dev) => register_class(dev, id),
+        #[cfg(feature = "display")]
+        AxDeviceEnum::Display(dev) => register_class(dev, id),
+        #[cfg(feature = "input")]
+        AxDeviceEnum::Input(dev) => register_class(dev, id),
+        #[cfg(feature = "console")]
+        AxDeviceEnum::Console(dev) => register_class(dev, id),
+        #[cfg(feature = "rng")]
+        AxDeviceEnum::Rng(dev) => register_class(dev, id),
+        #[cfg(feature = "ninep")]
+        AxDeviceEnum::NineP(dev) => register_class(dev, id),
+    };
+    info!(
+        "  registered {:?} device {}: {:?}",
+        dev.device_type(),
+        dev.name(),
+        driver_name
+    );
+    notify(&listeners, DeviceEvent::Added, &dev);
+    dev
+}
+
+/// Registers a device, e.g., a hot-plugged one, and returns its name.
+pub fn register(dev: AxDeviceEnum) -> String {
+    String::from(register_with_id(dev, alloc_id()).name())
+}
+
+/// Removes the device with the given name from the registry, e.g., when it's
+/// unplugged.
+///
+/// Existing users keep their references (see [`Device::is_removed`]), but
+/// the device can not be opened anymore, and its interrupts are no longer
+/// delivered. Returns `false` if there is no such device.
+pub fn unregister(name: &str) -> bool {
+    let (dev, listeners) = {
+        let mut registry = REGISTRY.lock();
+        let Some(pos) = registry.devices.iter().position(|dev| dev.name() == name) else {
+            return false;
+        };
+        (registry.devices.remove(pos), registry.listeners.clone())
+    };
+    dev.set_removed();
+    #[cfg(feature = "irq")]
+    crate::irq::remove_device(dev.id());
+    info!("  removed {:?} device {}", dev.device_type(), name);
+    notify(&listeners, DeviceEvent::Removed, &dev);
+    true
+}
+
+/// Opens the device of category `D` with the given name.
+pub fn open<D: DeviceClass>(name: &str) -> Option<DeviceRef<D>> {
+    REGISTRY
+        .lock()
+        .devices
+        .iter()
+        .filter(|dev| dev.name() == name)
+        .find_map(D::downcast)
+        .cloned()
+}
+
+/// Returns all devices of category `D`, in the order they are registered.
+pub fn devices<D: DeviceClass>() -> Vec<DeviceRef<D>> {
+    REGISTRY
+        .lock()
+        .devices
+        .iter()
+        .filter_map(D::downcast)
+        .cloned()
+        .collect()
+}
+
+/// Opens the first registered device of category `D`, e.g., the NIC used by
+/// default.
+pub fn first<D: DeviceClass>() -> Option<DeviceRef<D>> {
+    REGISTRY
+        .lock()
+        .devices
+        .iter()
+        .find_map(D::downcast)
+        .cloned()
+}
+
+/// Returns all registered devices, in the order they are registered.
+pub fn all_devices() -> Vec<RegisteredDevice> {
+    REGISTRY.lock().devices.clone()
+}
+
+/// Returns the ID of the device with the given name.
+#[allow(dead_code)]
+pub(crate) fn find_id(name: &str) -> Option<usize> {
+    REGISTRY
+        .lock()
+        .devices
+        .iter()
+        .find(|dev| dev.name() == name)
+        .map(RegisteredDevice::id)
+}
+
+/// Adds a function called when a device is added or removed.
+///
+/// It's called with [`DeviceEvent::Added`] for the existing devices first, and
+/// exactly once for each device registered afterwards, even concurrently.
+/// Listeners are called in the order they are added, with no locks held, so
+/// they may open the device.
+pub fn add_listener(listener: DeviceListener) {
+    let devices = {
+        let mut registry = REGISTRY.lock();
+        registry.listeners.push(listener);
+        registry.devices.clone()
+    };
+    for dev in &devices {
+        listener(DeviceEvent::Added, dev);
+    }
+}
+
+#[cfg(all(test, block_dev = "dummy"))]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::dummy::DummyBlockDev;
+
+    /// Tests share the global registry, so they must not run concurrently.
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    fn register_disk() -> String {
+        register(AxDeviceEnum::from_block(DummyBlockDev))
+    }
+
+    #[test]
+    fn letter_names() {
+        let name = make_name::<AxBlockDevice>;
+        assert_eq!(name(0), "vda");
+        assert_eq!(name(1), "vdb");
+        assert_eq!(name(25), "vdz");
+        assert_eq!(name(26), "vdaa");
+        assert_eq!(name(27), "vdab");
+        assert_eq!(name(701), "vdzz");
+        assert_eq!(name(702), "vdaaa");
+    }
+
+    #[test]
+    fn reuse_lowest_free_name() {
+        let _lock = SERIAL.lock();
+        let names: Vec<_> = (0..3).map(|_| register_disk()).collect();
+        assert_eq!(names, ["vda", "vdb", "vdc"]);
+
+        assert!(unregister("vdb"));
+        assert!(unregister("vda"));
+        assert_eq!(register_disk(), "vda");
+        assert_eq!(register_disk(), "vdb");
+        assert_eq!(register_disk(), "vdd");
+
+        for name in ["vda", "vdb", "vdc", "vdd"] {
+            assert!(unregister(name));
+        }
+    }
+
+    #[test]
+    fn unregister_device() {
+        let _lock = SERIAL.lock();
+        let name = register_disk();
+        let dev = open::<AxBlockDevice>(&name).unwrap();
+        assert!(!dev.is_removed());
+        assert!(all_devices().iter().any(|d| d.name() == name));
+        let id = find_id(&name).unwrap();
+
+        assert!(unregister(&name));
+        assert!(dev.is_removed());
+        assert!(open::<AxBlockDevice>(&name).is_none());
+        assert!(find_id(&name).is_none());
+        assert!(!all_devices().iter().any(|d| d.name() == name));
+        assert!(!unregister(&name));
+
+        // IDs are never reused, unlike names.
+        let new_name = register_disk();
+        assert_eq!(new_name, name);
+        assert_ne!(find_id(&new_name), Some(id));
+        assert!(unregister(&new_name));
+    }
+
+    #[test]
+    fn listener_order() {
+        static EVENTS: Mutex<Vec<(usize, DeviceEvent, String)>> = Mutex::new(Vec::new());
+
+        fn record(listener: usize, event: DeviceEvent, dev: &RegisteredDevice) {
+            EVENTS
+                .lock()
+                .unwrap()
+                .push((listener, event, dev.name().to_string()));
+        }
+
+        let _lock = SERIAL.lock();
+        let existing = register_disk();
+        add_listener(|event, dev| record(0, event, dev));
+        add_listener(|event, dev| record(1, event, dev));
+        let added = register_disk();
+        assert!(unregister(&existing));
+        assert!(unregister(&added));
+
+        let events = EVENTS.lock().unwrap().clone();
+        let expected = [
+            (0, DeviceEvent::Added, &existing),
+            (1, DeviceEvent::Added, &existing),
+            (0, DeviceEvent::Added, &added),
+            (1, DeviceEvent::Added, &added),
+            (0, DeviceEvent::Removed, &existing),
+            (1, DeviceEvent::Removed, &existing),
+            (0, DeviceEvent::Removed, &added),
+            (1, DeviceEvent::Removed, &added),
+        ];
+        assert_eq!(events.len(), expected.len());
+        for (event, (listener, kind, name)) in events.iter().zip(expected) {
+            assert_eq!(event, &(listener, kind, name.clone()));
+        }
+    }
+}
@@ -0,0 +1,11 @@
+//! Common traits and types for hardware random number generator drivers.
+
+use axdriver_base::{BaseDriverOps, DevResult};
+
+/// Operations that require a random number generator driver to implement.
+pub trait RngDriverOps: BaseDriverOps {
+    /// Fills `buf` with random bytes from the hardware entropy source, and
+    /// returns the number of bytes filled, which may be less than the length
+    /// of `buf`.
+    fn fill_bytes(&mut self, buf: &mut [u8]) -> DevResult<usize>;
+}
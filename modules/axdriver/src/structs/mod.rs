@@ -18,6 +18,18 @@ pub enum AxDeviceEnum {
     /// Graphic display device.
     #[cfg(feature = "display")]
     Display(AxDisplayDevice),
+    /// Input device.
+    #[cfg(feature = "input")]
+    Input(AxInputDevice),
+    /// Console device.
+    #[cfg(feature = "console")]
+    Console(AxConsoleDevice),
+    /// Random number generator.
+    #[cfg(feature = "rng")]
+    Rng(AxRngDevice),
+    /// 9P transport.
+    #[cfg(feature = "ninep")]
+    NineP(AxNinePDevice),
 }
 
 impl BaseDriverOps for AxDeviceEnum {
@@ -31,6 +43,14 @@ impl BaseDriverOps for AxDeviceEnum {
             Self::Block(_) => DeviceType::Block,
             #[cfg(feature = "display")]
             Self::Display(_) => DeviceType::Display,
+            #[cfg(feature = "input")]
+            Self::Input(_) => DeviceType::Char,
+            #[cfg(feature = "console")]
+            Self::Console(_) => DeviceType::Char,
+            #[cfg(feature = "rng")]
+            Self::Rng(_) => DeviceType::Char,
+            #[cfg(feature = "ninep")]
+            Self::NineP(_) => DeviceType::Char,
             _ => unreachable!(),
         }
     }
@@ -45,6 +65,14 @@ impl BaseDriverOps for AxDeviceEnum {
             Self::Block(dev) => dev.device_name(),
             #[cfg(feature = "display")]
             Self::Display(dev) => dev.device_name(),
+            #[cfg(feature = "input")]
+            Self::Input(dev) => dev.device_name(),
+            #[cfg(feature = "console")]
+            Self::Console(dev) => dev.device_name(),
+            #[cfg(feature = "rng")]
+            Self::Rng(dev) => dev.device_name(),
+            #[cfg(feature = "ninep")]
+            Self::NineP(dev) => dev.device_name(),
             _ => unreachable!(),
         }
     }
@@ -1,9 +1,17 @@
 #[cfg(feature = "block")]
 pub use crate::drivers::AxBlockDevice;
+#[cfg(feature = "console")]
+pub use crate::drivers::AxConsoleDevice;
 #[cfg(feature = "display")]
 pub use crate::drivers::AxDisplayDevice;
+#[cfg(feature = "input")]
+pub use crate::drivers::AxInputDevice;
 #[cfg(feature = "net")]
 pub use crate::drivers::AxNetDevice;
+#[cfg(feature = "ninep")]
+pub use crate::drivers::AxNinePDevice;
+#[cfg(feature = "rng")]
+pub use crate::drivers::AxRngDevice;
 
 impl super::AxDeviceEnum {
     /// Constructs a network device.
@@ -23,9 +31,35 @@ impl super::AxDeviceEnum {
     pub const fn from_display(dev: AxDisplayDevice) -> Self {
         Self::Display(dev)
     }
+
+    /// Constructs an input device.
+    #[cfg(feature = "input")]
+    pub const fn from_input(dev: AxInputDevice) -> Self {
+        Self::Input(dev)
+    }
+
+    /// Constructs a console device.
+    #[cfg(feature = "console")]
+    pub const fn from_console(dev: AxConsoleDevice) -> Self {
+        Self::Console(dev)
+    }
+
+    /// Constructs a random number generator.
+    #[cfg(feature = "rng")]
+    pub const fn from_rng(dev: AxRngDevice) -> Self {
+        Self::Rng(dev)
+    }
+
+    /// Constructs a 9P transport.
+    #[cfg(feature = "ninep")]
+    pub const fn from_ninep(dev: AxNinePDevice) -> Self {
+        Self::NineP(dev)
+    }
 }
 
-/// A structure that contains all device drivers of a certain category.
+/// A structure that contains device drivers of a certain category, e.g., to
+/// pass devices to a subsystem directly. Probed devices are opened from the
+/// [`registry`](crate::registry) instead.
 ///
 /// If the feature `dyn` is enabled, the inner type is [`Vec<D>`]. Otherwise,
 /// the inner type is [`Option<D>`] and at most one device can be contained.
@@ -1,16 +1,21 @@
 use core::marker::PhantomData;
 use core::ptr::NonNull;
 
+use alloc::vec::Vec;
 use axalloc::global_allocator;
 use axdriver_base::{BaseDriverOps, DevResult, DeviceType};
 use axdriver_virtio::{BufferDirection, PhysAddr, VirtIoHal};
-use axhal::mem::{phys_to_virt, virt_to_phys};
+use axhal::mem::{PAGE_SIZE_4K, phys_to_virt, virt_to_phys};
 use cfg_if::cfg_if;
+use kspin::SpinNoIrq;
 
 use crate::{AxDeviceEnum, drivers::DriverProbe};
 
 cfg_if! {
-    if #[cfg(bus = "pci")] {
+    if #[cfg(all(bus = "pci", feature = "irq"))] {
+        use axdriver_pci::{PciRoot, DeviceFunction, DeviceFunctionInfo};
+        type VirtIoTransport = PciIrqTransport;
+    } else if #[cfg(bus = "pci")] {
         use axdriver_pci::{PciRoot, DeviceFunction, DeviceFunctionInfo};
         type VirtIoTransport = axdriver_virtio::PciTransport;
     } else if #[cfg(bus =  "mmio")] {
@@ -18,6 +23,108 @@ cfg_if! {
     }
 }
 
+#[cfg(bus = "pci")]
+fn pci_transport(
+    transport: axdriver_virtio::PciTransport,
+    _root: &PciRoot,
+    _bdf: DeviceFunction,
+) -> VirtIoTransport {
+    #[cfg(feature = "irq")]
+    {
+        PciIrqTransport {
+            vectors: crate::bus::VirtIoQueueVectors::probe(_root, _bdf),
+            inner: transport,
+        }
+    }
+    #[cfg(not(feature = "irq"))]
+    transport
+}
+
+cfg_if! {
+    if #[cfg(all(bus = "pci", feature = "irq"))] {
+        use virtio_drivers::transport::Transport as _;
+        use virtio_drivers::transport::{
+            DeviceStatus as TransportStatus, DeviceType as TransportType,
+        };
+
+        /// The PCI transport that sets the MSI-X vectors of queues when they
+        /// are set up, i.e., before the device is activated.
+        pub struct PciIrqTransport {
+            inner: axdriver_virtio::PciTransport,
+            vectors: Option<crate::bus::VirtIoQueueVectors>,
+        }
+
+        impl virtio_drivers::transport::Transport for PciIrqTransport {
+            fn device_type(&self) -> TransportType {
+                self.inner.device_type()
+            }
+
+            fn read_device_features(&mut self) -> u64 {
+                self.inner.read_device_features()
+            }
+
+            fn write_driver_features(&mut self, driver_features: u64) {
+                self.inner.write_driver_features(driver_features)
+            }
+
+            fn max_queue_size(&mut self, queue: u16) -> u32 {
+                self.inner.max_queue_size(queue)
+            }
+
+            fn notify(&mut self, queue: u16) {
+                self.inner.notify(queue)
+            }
+
+            fn get_status(&self) -> TransportStatus {
+                self.inner.get_status()
+            }
+
+            fn set_status(&mut self, status: TransportStatus) {
+                self.inner.set_status(status)
+            }
+
+            fn set_guest_page_size(&mut self, guest_page_size: u32) {
+                self.inner.set_guest_page_size(guest_page_size)
+            }
+
+            fn requires_legacy_layout(&self) -> bool {
+                self.inner.requires_legacy_layout()
+            }
+
+            fn queue_set(
+                &mut self,
+                queue: u16,
+                size: u32,
+                descriptors: PhysAddr,
+                driver_area: PhysAddr,
+                device_area: PhysAddr,
+            ) {
+                if let Some(vectors) = &self.vectors {
+                    vectors.set_queue_vector(queue);
+                }
+                self.inner
+                    .queue_set(queue, size, descriptors, driver_area, device_area)
+            }
+
+            fn queue_unset(&mut self, queue: u16) {
+                self.inner.queue_unset(queue)
+            }
+
+            fn queue_used(&mut self, queue: u16) -> bool {
+                self.inner.queue_used(queue)
+            }
+
+            fn ack_interrupt(&mut self) -> bool {
+                self.inner.ack_interrupt()
+            }
+
+            fn config_space<T: 'static>(&self) -> virtio_drivers::Result<NonNull<T>> {
+                self.inner.config_space()
+            }
+        }
+    }
+}
+
 /// A trait for VirtIO device meta information.
 pub trait VirtIoDevMeta {
     const DEVICE_TYPE: DeviceType;
@@ -43,16 +150,888 @@ cfg_if! {
     }
 }
 
+cfg_if! {
+    if #[cfg(any(
+        block_dev = "virtio-blk",
+        display_dev = "virtio-gpu",
+        input_dev = "virtio-input",
+        console_dev = "virtio-console",
+        rng_dev = "virtio-rng",
+        ninep_dev = "virtio-9p"
+    ))] {
+        use axdriver_base::DevError;
+        use virtio_drivers::transport::{DeviceType as VirtIoDevType, Transport};
+
+        /// A trait for meta information of VirtIO devices that are not
+        /// supported by `axdriver_virtio`, whose drivers are built on top of
+        /// `virtio-drivers` directly.
+        pub trait VirtIoRawDevMeta {
+            const DEVICE_TYPE: VirtIoDevType;
+
+            fn try_new(transport: VirtIoTransport) -> DevResult<AxDeviceEnum>;
+        }
+
+        /// A common driver for all [`VirtIoRawDevMeta`] devices, which creates
+        /// the transport by itself since `axdriver_virtio` does not recognize
+        /// these devices.
+        pub struct VirtIoRawDriver<D: VirtIoRawDevMeta>(PhantomData<D>);
+
+        impl<D: VirtIoRawDevMeta> VirtIoRawDriver<D> {
+            fn init(transport: VirtIoTransport) -> Option<AxDeviceEnum> {
+                match D::try_new(transport) {
+                    Ok(dev) => {
+                        reserve_bounce();
+                        Some(dev)
+                    }
+                    Err(e) => {
+                        warn!(
+                            "failed to initialize VirtIO {:?} device: {:?}",
+                            D::DEVICE_TYPE, e
+                        );
+                        None
+                    }
+                }
+            }
+        }
+
+        impl<D: VirtIoRawDevMeta> DriverProbe for VirtIoRawDriver<D> {
+            #[cfg(bus = "mmio")]
+            fn probe_mmio(mmio_base: usize, _mmio_size: usize) -> Option<AxDeviceEnum> {
+                use virtio_drivers::transport::mmio::VirtIOHeader;
+
+                let base_vaddr = phys_to_virt(mmio_base.into());
+                let header = NonNull::new(base_vaddr.as_mut_ptr() as *mut VirtIOHeader)?;
+                let transport = unsafe { VirtIoTransport::new(header) }.ok()?;
+                if transport.device_type() != D::DEVICE_TYPE {
+                    return None;
+                }
+                Self::init(transport)
+            }
+
+            #[cfg(bus = "pci")]
+            fn probe_pci(
+                root: &mut PciRoot,
+                bdf: DeviceFunction,
+                dev_info: &DeviceFunctionInfo,
+            ) -> Option<AxDeviceEnum> {
+                use virtio_drivers::transport::pci::virtio_device_type;
+
+                if virtio_device_type(dev_info) != Some(D::DEVICE_TYPE) {
+                    return None;
+                }
+                let transport = match axdriver_virtio::PciTransport::new::<VirtIoHalImpl>(root, bdf)
+                {
+                    Ok(transport) => pci_transport(transport, root, bdf),
+                    Err(e) => {
+                        warn!(
+                            "failed to create transport for PCI device at {}({}): {:?}",
+                            bdf, dev_info, e
+                        );
+                        return None;
+                    }
+                };
+                Self::init(transport)
+            }
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(input_dev = "virtio-input")] {
+        use virtio_drivers::device::input::{InputConfigSelect, VirtIOInput};
+
+        use crate::input::{InputDriverOps, InputEvent};
+
+        pub struct VirtIoInput;
+
+        impl VirtIoRawDevMeta for VirtIoInput {
+            const DEVICE_TYPE: VirtIoDevType = VirtIoDevType::Input;
+
+            fn try_new(transport: VirtIoTransport) -> DevResult<AxDeviceEnum> {
+                Ok(AxDeviceEnum::from_input(VirtIoInputDev::try_new(transport)?))
+            }
+        }
+
+        /// The VirtIO input device driver.
+        pub struct VirtIoInputDev {
+            inner: VirtIOInput<VirtIoHalImpl, VirtIoTransport>,
+            name: [u8; Self::MAX_NAME_LEN],
+            name_len: usize,
+        }
+
+        impl VirtIoInputDev {
+            const MAX_NAME_LEN: usize = 64;
+
+            fn try_new(transport: VirtIoTransport) -> DevResult<Self> {
+                let mut inner = VirtIOInput::new(transport).map_err(|_| DevError::Io)?;
+                let mut name = [0; Self::MAX_NAME_LEN];
+                let name_len = inner.query_config_select(InputConfigSelect::IdName, 0, &mut name);
+                Ok(Self {
+                    inner,
+                    name,
+                    name_len: (name_len as usize).min(Self::MAX_NAME_LEN),
+                })
+            }
+        }
+
+        impl BaseDriverOps for VirtIoInputDev {
+            fn device_type(&self) -> DeviceType {
+                DeviceType::Char
+            }
+
+            fn device_name(&self) -> &str {
+                match core::str::from_utf8(&self.name[..self.name_len]) {
+                    Ok(name) if !name.is_empty() => name,
+                    _ => "virtio-input",
+                }
+            }
+        }
+
+        impl InputDriverOps for VirtIoInputDev {
+            fn has_event_type(&mut self, event_type: u16) -> bool {
+                let mut bitmap = [0; 128];
+                let Ok(event_type) = u8::try_from(event_type) else {
+                    return false;
+                };
+                self.inner
+                    .query_config_select(InputConfigSelect::EvBits, event_type, &mut bitmap)
+                    > 0
+            }
+
+            fn read_event(&mut self) -> DevResult<InputEvent> {
+                let event = self.inner.pop_pending_event().ok_or(DevError::Again)?;
+                Ok(InputEvent {
+                    event_type: event.event_type,
+                    code: event.code,
+                    value: event.value,
+                })
+            }
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(console_dev = "virtio-console")] {
+        use virtio_drivers::device::console::VirtIOConsole;
+
+        use crate::console::ConsoleDriverOps;
+
+        pub struct VirtIoConsole;
+
+        impl VirtIoRawDevMeta for VirtIoConsole {
+            const DEVICE_TYPE: VirtIoDevType = VirtIoDevType::Console;
+
+            fn try_new(transport: VirtIoTransport) -> DevResult<AxDeviceEnum> {
+                Ok(AxDeviceEnum::from_console(VirtIoConsoleDev::try_new(transport)?))
+            }
+        }
+
+        /// The VirtIO console device driver.
+        ///
+        /// Only the first port of the device is used, since the multiport
+        /// feature is not supported by `virtio-drivers`. More ports are
+        /// provided by more devices.
+        pub struct VirtIoConsoleDev {
+            inner: VirtIOConsole<VirtIoHalImpl, VirtIoTransport>,
+        }
+
+        impl VirtIoConsoleDev {
+            fn try_new(transport: VirtIoTransport) -> DevResult<Self> {
+                let inner = VirtIOConsole::new(transport).map_err(|_| DevError::Io)?;
+                Ok(Self { inner })
+            }
+        }
+
+        impl BaseDriverOps for VirtIoConsoleDev {
+            fn device_type(&self) -> DeviceType {
+                DeviceType::Char
+            }
+
+            fn device_name(&self) -> &str {
+                "virtio-console"
+            }
+        }
+
+        impl ConsoleDriverOps for VirtIoConsoleDev {
+            fn read(&mut self, buf: &mut [u8]) -> DevResult<usize> {
+                let mut len = 0;
+                while len < buf.len() {
+                    match self.inner.recv(true).map_err(|_| DevError::Io)? {
+                        Some(c) => buf[len] = c,
+                        None => break,
+                    }
+                    len += 1;
+                }
+                Ok(len)
+            }
+
+            fn write(&mut self, buf: &[u8]) -> DevResult<usize> {
+                for &c in buf {
+                    self.inner.send(c).map_err(|_| DevError::Io)?;
+                }
+                Ok(buf.len())
+            }
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(any(
+        block_dev = "virtio-blk",
+        display_dev = "virtio-gpu",
+        rng_dev = "virtio-rng",
+        ninep_dev = "virtio-9p"
+    ))] {
+        use core::sync::atomic::{Ordering, fence};
+
+        use virtio_drivers::transport::DeviceStatus;
+
+        /// A descriptor of the split virtqueue.
+        #[repr(C)]
+        struct VirtqDesc {
+            addr: u64,
+            len: u32,
+            flags: u16,
+            next: u16,
+        }
+
+        /// A buffer of a request submitted to a [`SyncQueue`].
+        struct QueueBuf {
+            /// The offset of the buffer in the request buffers.
+            offset: usize,
+            len: usize,
+            /// Whether the buffer is written by the device.
+            device_writable: bool,
+        }
+
+        /// Minimal split virtqueues with one request in flight, for devices
+        /// whose requests are handled one by one, instead of the ones of
+        /// `virtio-drivers`.
+        ///
+        /// The memory of each queue consists of the descriptor table and the
+        /// available ring in the first page, and the used ring in the second
+        /// page (required by the legacy layout). The request buffers, shared
+        /// by all queues, are in the pages following the queues.
+        struct SyncQueue {
+            transport: VirtIoTransport,
+            paddr: PhysAddr,
+            vaddr: NonNull<u8>,
+            pages: usize,
+            num_queues: u16,
+            avail_idx: [u16; Self::MAX_QUEUES as usize],
+            in_flight: [bool; Self::MAX_QUEUES as usize],
+        }
+
+        impl SyncQueue {
+            const SIZE: u16 = 4;
+            const MAX_QUEUES: u16 = 2;
+            const AVAIL_OFFSET: usize = size_of::<VirtqDesc>() * Self::SIZE as usize;
+            const USED_OFFSET: usize = 0x1000;
+            const QUEUE_STRIDE: usize = 0x2000;
+
+            const VIRTIO_F_VERSION_1: u64 = 1 << 32;
+            const VIRTQ_DESC_F_NEXT: u16 = 1;
+            const VIRTQ_DESC_F_WRITE: u16 = 2;
+
+            /// Initializes the device with the given device-specific features
+            /// if supported, and sets up its first `num_queues` queues with
+            /// `buf_size` bytes of request buffers.
+            ///
+            /// Returns the queues and the negotiated features.
+            fn new(
+                mut transport: VirtIoTransport,
+                features: u64,
+                num_queues: u16,
+                buf_size: usize,
+            ) -> DevResult<(Self, u64)> {
+                debug_assert!(num_queues > 0 && num_queues <= Self::MAX_QUEUES);
+                for queue in 0..num_queues {
+                    if (transport.max_queue_size(queue) as u16) < Self::SIZE {
+                        return Err(DevError::Unsupported);
+                    }
+                }
+                let buf_offset = Self::QUEUE_STRIDE * num_queues as usize;
+                let pages = (buf_offset + buf_size).div_ceil(0x1000);
+                let (paddr, vaddr) = VirtIoHalImpl::dma_alloc(pages, BufferDirection::Both);
+                if paddr == 0 {
+                    return Err(DevError::NoMemory);
+                }
+                unsafe { vaddr.as_ptr().write_bytes(0, buf_offset) };
+
+                let features =
+                    transport.read_device_features() & (features | Self::VIRTIO_F_VERSION_1);
+                transport.set_status(DeviceStatus::empty());
+                transport.set_status(DeviceStatus::ACKNOWLEDGE | DeviceStatus::DRIVER);
+                transport.write_driver_features(features);
+                transport.set_status(
+                    DeviceStatus::ACKNOWLEDGE | DeviceStatus::DRIVER | DeviceStatus::FEATURES_OK,
+                );
+                transport.set_guest_page_size(0x1000);
+                for queue in 0..num_queues {
+                    let paddr = paddr + Self::QUEUE_STRIDE * queue as usize;
+                    transport.queue_set(
+                        queue,
+                        Self::SIZE as u32,
+                        paddr,
+                        paddr + Self::AVAIL_OFFSET,
+                        paddr + Self::USED_OFFSET,
+                    );
+                }
+                transport.finish_init();
+
+                let queue = Self {
+                    transport,
+                    paddr,
+                    vaddr,
+                    pages,
+                    num_queues,
+                    avail_idx: [0; Self::MAX_QUEUES as usize],
+                    in_flight: [false; Self::MAX_QUEUES as usize],
+                };
+                Ok((queue, features))
+            }
+
+            fn queue_ptr<T>(&self, offset: usize) -> *mut T {
+                unsafe { self.vaddr.as_ptr().add(offset) as *mut T }
+            }
+
+            fn buf_offset(&self) -> usize {
+                Self::QUEUE_STRIDE * self.num_queues as usize
+            }
+
+            /// Returns the pointer to the request buffers at `offset`.
+            fn buf_ptr(&self, offset: usize) -> *mut u8 {
+                self.queue_ptr(self.buf_offset() + offset)
+            }
+
+            /// Whether a request submitted to `queue` has not been completed
+            /// by [`poll`](Self::poll) yet.
+            fn in_flight(&self, queue: u16) -> bool {
+                self.in_flight[queue as usize]
+            }
+
+            /// Submits a request consisting of the given buffers to `queue`,
+            /// which must not have a request in flight.
+            fn submit(&mut self, queue: u16, bufs: &[QueueBuf]) {
+                debug_assert!(!bufs.is_empty() && bufs.len() <= Self::SIZE as usize);
+                debug_assert!(!self.in_flight(queue));
+                let base = Self::QUEUE_STRIDE * queue as usize;
+                let buf_paddr = self.paddr + self.buf_offset();
+                let avail_idx = self.avail_idx[queue as usize].wrapping_add(1);
+                let slot = self.avail_idx[queue as usize] % Self::SIZE;
+                unsafe {
+                    // Every request is a chain starting from the first descriptor.
+                    for (i, buf) in bufs.iter().enumerate() {
+                        let mut flags = 0;
+                        if buf.device_writable {
+                            flags |= Self::VIRTQ_DESC_F_WRITE;
+                        }
+                        if i + 1 < bufs.len() {
+                            flags |= Self::VIRTQ_DESC_F_NEXT;
+                        }
+                        self.queue_ptr::<VirtqDesc>(base).add(i).write_volatile(VirtqDesc {
+                            addr: (buf_paddr + buf.offset) as u64,
+                            len: buf.len as u32,
+                            flags,
+                            next: (i + 1) as u16,
+                        });
+                    }
+                    // avail ring: flags, idx, ring[SIZE]
+                    self.queue_ptr::<u16>(base + Self::AVAIL_OFFSET + 4)
+                        .add(slot as usize)
+                        .write_volatile(0);
+                    fence(Ordering::SeqCst);
+                    self.queue_ptr::<u16>(base + Self::AVAIL_OFFSET + 2)
+                        .write_volatile(avail_idx);
+                    fence(Ordering::SeqCst);
+                }
+                self.avail_idx[queue as usize] = avail_idx;
+                self.in_flight[queue as usize] = true;
+                self.transport.notify(queue);
+            }
+
+            /// Returns the number of bytes written by the device if it has
+            /// handled the request in flight on `queue`, or `None` if it has
+            /// not, or there is no request in flight.
+            fn poll(&mut self, queue: u16) -> Option<usize> {
+                if !self.in_flight(queue) {
+                    return None;
+                }
+                let base = Self::QUEUE_STRIDE * queue as usize;
+                let avail_idx = self.avail_idx[queue as usize];
+                // used ring: flags, idx, ring[SIZE] of (id: u32, len: u32)
+                let used_idx = self.queue_ptr::<u16>(base + Self::USED_OFFSET + 2);
+                if unsafe { used_idx.read_volatile() } != avail_idx {
+                    return None;
+                }
+                fence(Ordering::SeqCst);
+                let slot = avail_idx.wrapping_sub(1) % Self::SIZE;
+                let elem =
+                    self.queue_ptr::<u32>(base + Self::USED_OFFSET + 4 + slot as usize * 8);
+                let written = unsafe { elem.add(1).read_volatile() } as usize;
+                self.transport.ack_interrupt();
+                self.in_flight[queue as usize] = false;
+                Some(written)
+            }
+
+            /// Submits a request consisting of the given buffers to `queue`,
+            /// and spins until the device handles it. Returns the number of
+            /// bytes written by the device.
+            fn request(&mut self, queue: u16, bufs: &[QueueBuf]) -> usize {
+                self.submit(queue, bufs);
+                loop {
+                    if let Some(written) = self.poll(queue) {
+                        return written;
+                    }
+                    core::hint::spin_loop();
+                }
+            }
+        }
+
+        impl Drop for SyncQueue {
+            fn drop(&mut self) {
+                // Reset the device before freeing the queue.
+                self.transport.set_status(DeviceStatus::empty());
+                for queue in 0..self.num_queues {
+                    self.transport.queue_unset(queue);
+                }
+                let (paddr, vaddr) = (self.paddr, self.vaddr);
+                unsafe { VirtIoHalImpl::dma_dealloc(paddr, vaddr, self.pages) };
+            }
+        }
+
+        unsafe impl Send for SyncQueue {}
+        unsafe impl Sync for SyncQueue {}
+    }
+}
+
 cfg_if! {
     if #[cfg(block_dev = "virtio-blk")] {
+        use axdriver_block::BlockDriverOps;
+
+        use crate::block::BlockRequestOps;
+
         pub struct VirtIoBlk;
 
-        impl VirtIoDevMeta for VirtIoBlk {
-            const DEVICE_TYPE: DeviceType = DeviceType::Block;
-            type Device = axdriver_virtio::VirtIoBlkDev<VirtIoHalImpl, VirtIoTransport>;
+        impl VirtIoRawDevMeta for VirtIoBlk {
+            const DEVICE_TYPE: VirtIoDevType = VirtIoDevType::Block;
 
             fn try_new(transport: VirtIoTransport) -> DevResult<AxDeviceEnum> {
-                Ok(AxDeviceEnum::from_block(Self::Device::try_new(transport)?))
+                Ok(AxDeviceEnum::from_block(VirtIoBlkDev::try_new(transport)?))
+            }
+        }
+
+        /// The VirtIO block device driver.
+        ///
+        /// Each request is a driver-readable header, followed by a data buffer
+        /// of at most [`MAX_REQUEST_SIZE`](Self::MAX_REQUEST_SIZE) bytes and a
+        /// device-writable status byte. Requests are completed asynchronously
+        /// with [`BlockRequestOps`], and the operations of [`BlockDriverOps`]
+        /// spin until the device handles them.
+        pub struct VirtIoBlkDev {
+            queue: SyncQueue,
+            num_blocks: u64,
+            features: u64,
+            /// Whether the request in flight reads from the device.
+            reading: bool,
+        }
+
+        impl VirtIoBlkDev {
+            const BLOCK_SIZE: usize = 512;
+            const MAX_REQUEST_SIZE: usize = 0x10000;
+            /// The offset of the status byte, following the 16-byte header.
+            const STATUS_OFFSET: usize = 16;
+            /// The offset of the data buffer in the request buffers.
+            const DATA_OFFSET: usize = 0x1000;
+
+            const VIRTIO_BLK_F_RO: u64 = 1 << 5;
+            const VIRTIO_BLK_F_FLUSH: u64 = 1 << 9;
+
+            const VIRTIO_BLK_T_IN: u32 = 0;
+            const VIRTIO_BLK_T_OUT: u32 = 1;
+            const VIRTIO_BLK_T_FLUSH: u32 = 4;
+
+            const VIRTIO_BLK_S_OK: u8 = 0;
+            const VIRTIO_BLK_S_UNSUPP: u8 = 2;
+
+            fn try_new(transport: VirtIoTransport) -> DevResult<Self> {
+                let (queue, features) = SyncQueue::new(
+                    transport,
+                    Self::VIRTIO_BLK_F_RO | Self::VIRTIO_BLK_F_FLUSH,
+                    1,
+                    Self::DATA_OFFSET + Self::MAX_REQUEST_SIZE,
+                )?;
+                // config: capacity: le64, in 512-byte sectors
+                let config = queue
+                    .transport
+                    .config_space::<u32>()
+                    .map_err(|_| DevError::Io)?
+                    .as_ptr();
+                let num_blocks = unsafe {
+                    config.read_volatile() as u64 | (config.add(1).read_volatile() as u64) << 32
+                };
+                Ok(Self {
+                    queue,
+                    num_blocks,
+                    features,
+                    reading: false,
+                })
+            }
+
+            fn check_request(&self, block_id: u64, len: usize) -> DevResult {
+                if len % Self::BLOCK_SIZE != 0 || len > Self::MAX_REQUEST_SIZE {
+                    return Err(DevError::InvalidParam);
+                }
+                let blocks = (len / Self::BLOCK_SIZE) as u64;
+                if block_id.checked_add(blocks).is_none_or(|end| end > self.num_blocks) {
+                    return Err(DevError::InvalidParam);
+                }
+                Ok(())
+            }
+
+            /// Submits a request of `req_type` on `sector`, with `len` bytes of
+            /// data in the data buffer.
+            fn submit(&mut self, req_type: u32, sector: u64, len: usize) -> DevResult {
+                if self.queue.in_flight(0) {
+                    return Err(DevError::Again);
+                }
+                // header: type: le32, reserved: le32, sector: le64
+                let mut header = [0; Self::STATUS_OFFSET];
+                header[..4].copy_from_slice(&req_type.to_le_bytes());
+                header[8..].copy_from_slice(&sector.to_le_bytes());
+                unsafe {
+                    core::ptr::copy_nonoverlapping(
+                        header.as_ptr(),
+                        self.queue.buf_ptr(0),
+                        header.len(),
+                    );
+                    self.queue.buf_ptr(Self::STATUS_OFFSET).write_volatile(u8::MAX);
+                }
+
+                self.reading = req_type == Self::VIRTIO_BLK_T_IN;
+                let header = QueueBuf {
+                    offset: 0,
+                    len: Self::STATUS_OFFSET,
+                    device_writable: false,
+                };
+                let status = QueueBuf {
+                    offset: Self::STATUS_OFFSET,
+                    len: 1,
+                    device_writable: true,
+                };
+                if len == 0 {
+                    self.queue.submit(0, &[header, status]);
+                } else {
+                    let data = QueueBuf {
+                        offset: Self::DATA_OFFSET,
+                        len,
+                        device_writable: self.reading,
+                    };
+                    self.queue.submit(0, &[header, data, status]);
+                }
+                Ok(())
+            }
+
+            /// Spins until the request in flight is completed, see
+            /// [`poll_request`](BlockRequestOps::poll_request).
+            fn wait(&mut self, buf: &mut [u8]) -> DevResult {
+                loop {
+                    match self.poll_request(buf) {
+                        Err(DevError::Again) => core::hint::spin_loop(),
+                        result => return result,
+                    }
+                }
+            }
+        }
+
+        impl BaseDriverOps for VirtIoBlkDev {
+            fn device_type(&self) -> DeviceType {
+                DeviceType::Block
+            }
+
+            fn device_name(&self) -> &str {
+                "virtio-blk"
+            }
+        }
+
+        impl BlockDriverOps for VirtIoBlkDev {
+            fn num_blocks(&self) -> u64 {
+                self.num_blocks
+            }
+
+            fn block_size(&self) -> usize {
+                Self::BLOCK_SIZE
+            }
+
+            fn read_block(&mut self, block_id: u64, buf: &mut [u8]) -> DevResult {
+                let blocks_per_request = (Self::MAX_REQUEST_SIZE / Self::BLOCK_SIZE) as u64;
+                for (block_id, chunk) in (block_id..)
+                    .step_by(blocks_per_request as usize)
+                    .zip(buf.chunks_mut(Self::MAX_REQUEST_SIZE))
+                {
+                    self.submit_read(block_id, chunk.len())?;
+                    self.wait(chunk)?;
+                }
+                Ok(())
+            }
+
+            fn write_block(&mut self, block_id: u64, buf: &[u8]) -> DevResult {
+                let blocks_per_request = (Self::MAX_REQUEST_SIZE / Self::BLOCK_SIZE) as u64;
+                for (block_id, chunk) in (block_id..)
+                    .step_by(blocks_per_request as usize)
+                    .zip(buf.chunks(Self::MAX_REQUEST_SIZE))
+                {
+                    self.submit_write(block_id, chunk)?;
+                    self.wait(&mut [])?;
+                }
+                Ok(())
+            }
+
+            fn flush(&mut self) -> DevResult {
+                if self.features & Self::VIRTIO_BLK_F_FLUSH == 0 {
+                    return Ok(());
+                }
+                self.submit(Self::VIRTIO_BLK_T_FLUSH, 0, 0)?;
+                self.wait(&mut [])
+            }
+        }
+
+        impl BlockRequestOps for VirtIoBlkDev {
+            fn max_request_size(&self) -> usize {
+                Self::MAX_REQUEST_SIZE
+            }
+
+            fn submit_read(&mut self, block_id: u64, len: usize) -> DevResult {
+                self.check_request(block_id, len)?;
+                self.submit(Self::VIRTIO_BLK_T_IN, block_id, len)
+            }
+
+            fn submit_write(&mut self, block_id: u64, buf: &[u8]) -> DevResult {
+                self.check_request(block_id, buf.len())?;
+                if self.features & Self::VIRTIO_BLK_F_RO != 0 {
+                    return Err(DevError::Unsupported);
+                }
+                if self.queue.in_flight(0) {
+                    return Err(DevError::Again);
+                }
+                unsafe {
+                    core::ptr::copy_nonoverlapping(
+                        buf.as_ptr(),
+                        self.queue.buf_ptr(Self::DATA_OFFSET),
+                        buf.len(),
+                    )
+                };
+                self.submit(Self::VIRTIO_BLK_T_OUT, block_id, buf.len())
+            }
+
+            fn poll_request(&mut self, buf: &mut [u8]) -> DevResult {
+                if !self.queue.in_flight(0) {
+                    return Err(DevError::BadState);
+                }
+                self.queue.poll(0).ok_or(DevError::Again)?;
+                match unsafe { self.queue.buf_ptr(Self::STATUS_OFFSET).read_volatile() } {
+                    Self::VIRTIO_BLK_S_OK => {}
+                    Self::VIRTIO_BLK_S_UNSUPP => return Err(DevError::Unsupported),
+                    _ => return Err(DevError::Io),
+                }
+                if self.reading {
+                    let len = buf.len().min(Self::MAX_REQUEST_SIZE);
+                    unsafe {
+                        core::ptr::copy_nonoverlapping(
+                            self.queue.buf_ptr(Self::DATA_OFFSET),
+                            buf.as_mut_ptr(),
+                            len,
+                        )
+                    };
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(rng_dev = "virtio-rng")] {
+        use crate::rng::RngDriverOps;
+
+        pub struct VirtIoRng;
+
+        impl VirtIoRawDevMeta for VirtIoRng {
+            const DEVICE_TYPE: VirtIoDevType = VirtIoDevType::EntropySource;
+
+            fn try_new(transport: VirtIoTransport) -> DevResult<AxDeviceEnum> {
+                Ok(AxDeviceEnum::from_rng(VirtIoRngDev::try_new(transport)?))
+            }
+        }
+
+        /// The VirtIO entropy device driver.
+        ///
+        /// Each request is a single device-writable buffer of at most one
+        /// page.
+        pub struct VirtIoRngDev {
+            queue: SyncQueue,
+        }
+
+        impl VirtIoRngDev {
+            const BUF_SIZE: usize = 0x1000;
+
+            fn try_new(transport: VirtIoTransport) -> DevResult<Self> {
+                let (queue, _) = SyncQueue::new(transport, 0, 1, Self::BUF_SIZE)?;
+                Ok(Self { queue })
+            }
+        }
+
+        impl BaseDriverOps for VirtIoRngDev {
+            fn device_type(&self) -> DeviceType {
+                DeviceType::Char
+            }
+
+            fn device_name(&self) -> &str {
+                "virtio-rng"
+            }
+        }
+
+        impl RngDriverOps for VirtIoRngDev {
+            fn fill_bytes(&mut self, buf: &mut [u8]) -> DevResult<usize> {
+                let len = buf.len().min(Self::BUF_SIZE);
+                let filled = self
+                    .queue
+                    .request(
+                        0,
+                        &[QueueBuf {
+                            offset: 0,
+                            len,
+                            device_writable: true,
+                        }],
+                    )
+                    .min(len);
+                unsafe {
+                    core::ptr::copy_nonoverlapping(self.queue.buf_ptr(0), buf.as_mut_ptr(), filled)
+                };
+                Ok(filled)
+            }
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(ninep_dev = "virtio-9p")] {
+        use alloc::string::String;
+
+        use crate::ninep::NinePDriverOps;
+
+        pub struct VirtIo9p;
+
+        impl VirtIoRawDevMeta for VirtIo9p {
+            const DEVICE_TYPE: VirtIoDevType = VirtIoDevType::_9P;
+
+            fn try_new(transport: VirtIoTransport) -> DevResult<AxDeviceEnum> {
+                Ok(AxDeviceEnum::from_ninep(VirtIo9pDev::try_new(transport)?))
+            }
+        }
+
+        /// The VirtIO 9P transport driver.
+        ///
+        /// Each request is a T-message in a driver-readable buffer, followed by
+        /// a device-writable buffer for the R-message, both of
+        /// [`MAX_MESSAGE_SIZE`](Self::MAX_MESSAGE_SIZE) bytes.
+        pub struct VirtIo9pDev {
+            queue: SyncQueue,
+            mount_tag: String,
+        }
+
+        impl VirtIo9pDev {
+            const MAX_MESSAGE_SIZE: usize = 0x10000;
+
+            const VIRTIO_9P_MOUNT_TAG: u64 = 1 << 0;
+
+            fn try_new(transport: VirtIoTransport) -> DevResult<Self> {
+                let (queue, features) = SyncQueue::new(
+                    transport,
+                    Self::VIRTIO_9P_MOUNT_TAG,
+                    1,
+                    Self::MAX_MESSAGE_SIZE * 2,
+                )?;
+                let mut mount_tag = String::new();
+                if features & Self::VIRTIO_9P_MOUNT_TAG != 0 {
+                    // config: tag_len: le16, tag: [u8; tag_len]
+                    let config = queue
+                        .transport
+                        .config_space::<u8>()
+                        .map_err(|_| DevError::Io)?
+                        .as_ptr();
+                    let tag_len = unsafe {
+                        u16::from_le_bytes([config.read_volatile(), config.add(1).read_volatile()])
+                    };
+                    for i in 0..tag_len as usize {
+                        mount_tag.push(unsafe { config.add(2 + i).read_volatile() } as char);
+                    }
+                }
+                Ok(Self { queue, mount_tag })
+            }
+        }
+
+        impl BaseDriverOps for VirtIo9pDev {
+            fn device_type(&self) -> DeviceType {
+                DeviceType::Char
+            }
+
+            fn device_name(&self) -> &str {
+                "virtio-9p"
+            }
+        }
+
+        impl NinePDriverOps for VirtIo9pDev {
+            fn mount_tag(&self) -> &str {
+                &self.mount_tag
+            }
+
+            fn max_message_size(&self) -> usize {
+                Self::MAX_MESSAGE_SIZE
+            }
+
+            fn submit(&mut self, req: &[u8]) -> DevResult {
+                if req.len() > Self::MAX_MESSAGE_SIZE {
+                    return Err(DevError::InvalidParam);
+                }
+                if self.queue.in_flight(0) {
+                    return Err(DevError::Again);
+                }
+                unsafe {
+                    core::ptr::copy_nonoverlapping(req.as_ptr(), self.queue.buf_ptr(0), req.len())
+                };
+                self.queue.submit(
+                    0,
+                    &[
+                        QueueBuf {
+                            offset: 0,
+                            len: req.len(),
+                            device_writable: false,
+                        },
+                        QueueBuf {
+                            offset: Self::MAX_MESSAGE_SIZE,
+                            len: Self::MAX_MESSAGE_SIZE,
+                            device_writable: true,
+                        },
+                    ],
+                );
+                Ok(())
+            }
+
+            fn poll_response(&mut self, resp: &mut [u8]) -> DevResult<usize> {
+                if !self.queue.in_flight(0) {
+                    return Err(DevError::BadState);
+                }
+                let written = self.queue.poll(0).ok_or(DevError::Again)?;
+                let len = written.min(resp.len());
+                unsafe {
+                    core::ptr::copy_nonoverlapping(
+                        self.queue.buf_ptr(Self::MAX_MESSAGE_SIZE),
+                        resp.as_mut_ptr(),
+                        len,
+                    )
+                };
+                Ok(len)
             }
         }
     }
@@ -60,14 +1039,685 @@ cfg_if! {
 
 cfg_if! {
     if #[cfg(display_dev = "virtio-gpu")] {
+        use axdriver_display::{DisplayDriverOps, DisplayInfo, FrameBuffer};
+
+        use crate::display::{CURSOR_SIZE, DisplayMode, DisplayOutputOps, PixelFormat, Rect};
+
         pub struct VirtIoGpu;
 
-        impl VirtIoDevMeta for VirtIoGpu {
-            const DEVICE_TYPE: DeviceType = DeviceType::Display;
-            type Device = axdriver_virtio::VirtIoGpuDev<VirtIoHalImpl, VirtIoTransport>;
+        impl VirtIoRawDevMeta for VirtIoGpu {
+            const DEVICE_TYPE: VirtIoDevType = VirtIoDevType::GPU;
 
             fn try_new(transport: VirtIoTransport) -> DevResult<AxDeviceEnum> {
-                Ok(AxDeviceEnum::from_display(Self::Device::try_new(transport)?))
+                Ok(AxDeviceEnum::from_display(VirtIoGpuDev::try_new(
+                    transport,
+                )?))
+            }
+        }
+
+        #[repr(C)]
+        #[derive(Default)]
+        struct GpuCtrlHeader {
+            hdr_type: u32,
+            flags: u32,
+            fence_id: u64,
+            ctx_id: u32,
+            ring_idx: u8,
+            padding: [u8; 3],
+        }
+
+        impl GpuCtrlHeader {
+            fn new(hdr_type: u32) -> Self {
+                Self {
+                    hdr_type,
+                    ..Default::default()
+                }
+            }
+        }
+
+        #[repr(C)]
+        #[derive(Clone, Copy, Default)]
+        struct GpuRect {
+            x: u32,
+            y: u32,
+            width: u32,
+            height: u32,
+        }
+
+        impl From<Rect> for GpuRect {
+            fn from(rect: Rect) -> Self {
+                Self {
+                    x: rect.x,
+                    y: rect.y,
+                    width: rect.width,
+                    height: rect.height,
+                }
+            }
+        }
+
+        #[repr(C)]
+        #[derive(Clone, Copy)]
+        struct GpuDisplayOne {
+            rect: GpuRect,
+            enabled: u32,
+            flags: u32,
+        }
+
+        #[repr(C)]
+        struct GpuRespDisplayInfo {
+            header: GpuCtrlHeader,
+            pmodes: [GpuDisplayOne; 16],
+        }
+
+        #[repr(C)]
+        struct GpuResourceCreate2d {
+            header: GpuCtrlHeader,
+            resource_id: u32,
+            format: u32,
+            width: u32,
+            height: u32,
+        }
+
+        /// `RESOURCE_UNREF` and `RESOURCE_DETACH_BACKING`.
+        #[repr(C)]
+        struct GpuResourceOp {
+            header: GpuCtrlHeader,
+            resource_id: u32,
+            padding: u32,
+        }
+
+        #[repr(C)]
+        struct GpuSetScanout {
+            header: GpuCtrlHeader,
+            rect: GpuRect,
+            scanout_id: u32,
+            resource_id: u32,
+        }
+
+        #[repr(C)]
+        struct GpuResourceFlush {
+            header: GpuCtrlHeader,
+            rect: GpuRect,
+            resource_id: u32,
+            padding: u32,
+        }
+
+        #[repr(C)]
+        struct GpuTransferToHost2d {
+            header: GpuCtrlHeader,
+            rect: GpuRect,
+            offset: u64,
+            resource_id: u32,
+            padding: u32,
+        }
+
+        /// `RESOURCE_ATTACH_BACKING` with a single memory entry.
+        #[repr(C)]
+        struct GpuAttachBacking {
+            header: GpuCtrlHeader,
+            resource_id: u32,
+            nr_entries: u32,
+            addr: u64,
+            length: u32,
+            padding: u32,
+        }
+
+        #[repr(C)]
+        struct GpuUpdateCursor {
+            header: GpuCtrlHeader,
+            scanout_id: u32,
+            x: u32,
+            y: u32,
+            padding: u32,
+            resource_id: u32,
+            hot_x: u32,
+            hot_y: u32,
+            padding2: u32,
+        }
+
+        const GPU_CMD_GET_DISPLAY_INFO: u32 = 0x100;
+        const GPU_CMD_RESOURCE_CREATE_2D: u32 = 0x101;
+        const GPU_CMD_RESOURCE_UNREF: u32 = 0x102;
+        const GPU_CMD_SET_SCANOUT: u32 = 0x103;
+        const GPU_CMD_RESOURCE_FLUSH: u32 = 0x104;
+        const GPU_CMD_TRANSFER_TO_HOST_2D: u32 = 0x105;
+        const GPU_CMD_RESOURCE_ATTACH_BACKING: u32 = 0x106;
+        const GPU_CMD_RESOURCE_DETACH_BACKING: u32 = 0x107;
+        const GPU_CMD_UPDATE_CURSOR: u32 = 0x300;
+        const GPU_CMD_MOVE_CURSOR: u32 = 0x301;
+        const GPU_RESP_OK_NODATA: u32 = 0x1100;
+        const GPU_RESP_OK_DISPLAY_INFO: u32 = 0x1101;
+
+        const fn gpu_format(format: PixelFormat) -> u32 {
+            match format {
+                PixelFormat::Argb8888 => 1,   // B8G8R8A8_UNORM
+                PixelFormat::Xrgb8888 => 2,   // B8G8R8X8_UNORM
+                PixelFormat::Abgr8888 => 67,  // R8G8B8A8_UNORM
+                PixelFormat::Xbgr8888 => 134, // R8G8B8X8_UNORM
+            }
+        }
+
+        /// A host resource together with its guest backing memory.
+        struct GpuBuffer {
+            resource_id: u32,
+            paddr: PhysAddr,
+            vaddr: NonNull<u8>,
+            pages: usize,
+        }
+
+        /// The VirtIO GPU device driver, which drives the first scanout.
+        ///
+        /// Control commands and their responses share a page of request
+        /// buffers. The framebuffer is a 2D resource backed by physically
+        /// contiguous memory, and double buffering uses a second resource,
+        /// which is switched to by `SET_SCANOUT` on flips.
+        pub struct VirtIoGpuDev {
+            queue: SyncQueue,
+            preferred: DisplayMode,
+            mode: DisplayMode,
+            /// The framebuffers, of which the second one exists only if double
+            /// buffering is enabled.
+            buffers: [Option<GpuBuffer>; 2],
+            /// The index of the framebuffer to draw into.
+            back: usize,
+            cursor: Option<GpuBuffer>,
+            cursor_pos: (u32, u32),
+        }
+
+        impl VirtIoGpuDev {
+            const CONTROLQ: u16 = 0;
+            const CURSORQ: u16 = 1;
+            const RESP_OFFSET: usize = 0x800;
+            const CURSOR_RESOURCE_ID: u32 = 3;
+            const MAX_FB_SIZE: usize = 64 << 20;
+            const DEFAULT_FORMAT: PixelFormat = PixelFormat::Argb8888;
+            const STANDARD_RESOLUTIONS: [(u32, u32); 6] = [
+                (1920, 1080),
+                (1280, 1024),
+                (1280, 720),
+                (1024, 768),
+                (800, 600),
+                (640, 480),
+            ];
+
+            fn try_new(transport: VirtIoTransport) -> DevResult<Self> {
+                let (queue, _) = SyncQueue::new(transport, 0, 2, 0x1000)?;
+                let mut dev = Self {
+                    queue,
+                    preferred: DisplayMode {
+                        width: 1024,
+                        height: 768,
+                        format: Self::DEFAULT_FORMAT,
+                    },
+                    mode: DisplayMode {
+                        width: 0,
+                        height: 0,
+                        format: Self::DEFAULT_FORMAT,
+                    },
+                    buffers: [None, None],
+                    back: 0,
+                    cursor: None,
+                    cursor_pos: (0, 0),
+                };
+                let info: GpuRespDisplayInfo =
+                    dev.command(GpuCtrlHeader::new(GPU_CMD_GET_DISPLAY_INFO))?;
+                if info.header.hdr_type != GPU_RESP_OK_DISPLAY_INFO {
+                    return Err(DevError::Io);
+                }
+                if let Some(pmode) = info
+                    .pmodes
+                    .iter()
+                    .find(|m| m.enabled != 0 && m.rect.width != 0 && m.rect.height != 0)
+                {
+                    dev.preferred.width = pmode.rect.width;
+                    dev.preferred.height = pmode.rect.height;
+                }
+                dev.mode = dev.preferred;
+                let mode = dev.mode;
+                dev.buffers = dev.create_framebuffers(&mode, 1)?;
+                dev.show(0)?;
+                Ok(dev)
+            }
+
+            /// Sends a control command, and returns the response.
+            fn command<Req, Resp>(&mut self, req: Req) -> DevResult<Resp> {
+                const { assert!(size_of::<Req>() <= Self::RESP_OFFSET) };
+                unsafe { (self.queue.buf_ptr(0) as *mut Req).write_unaligned(req) };
+                let written = self.queue.request(
+                    Self::CONTROLQ,
+                    &[
+                        QueueBuf {
+                            offset: 0,
+                            len: size_of::<Req>(),
+                            device_writable: false,
+                        },
+                        QueueBuf {
+                            offset: Self::RESP_OFFSET,
+                            len: size_of::<Resp>(),
+                            device_writable: true,
+                        },
+                    ],
+                );
+                if written < size_of::<GpuCtrlHeader>() {
+                    return Err(DevError::Io);
+                }
+                Ok(unsafe {
+                    (self.queue.buf_ptr(Self::RESP_OFFSET) as *const Resp).read_unaligned()
+                })
+            }
+
+            /// Sends a control command that has no data in the response.
+            fn command_nodata<Req>(&mut self, req: Req) -> DevResult {
+                let resp: GpuCtrlHeader = self.command(req)?;
+                match resp.hdr_type {
+                    GPU_RESP_OK_NODATA => Ok(()),
+                    // ERR_OUT_OF_MEMORY
+                    0x1201 => Err(DevError::NoMemory),
+                    // ERR_INVALID_* (scanout, resource, context, parameter)
+                    0x1202..=0x1205 => Err(DevError::InvalidParam),
+                    _ => Err(DevError::Io),
+                }
+            }
+
+            fn create_buffer(
+                &mut self,
+                resource_id: u32,
+                mode: &DisplayMode,
+            ) -> DevResult<GpuBuffer> {
+                let size = mode.fb_size();
+                let pages = size.div_ceil(0x1000);
+                let (paddr, vaddr) =
+                    VirtIoHalImpl::dma_alloc(pages, BufferDirection::DriverToDevice);
+                if paddr == 0 {
+                    return Err(DevError::NoMemory);
+                }
+                unsafe { vaddr.as_ptr().write_bytes(0, size) };
+                let buffer = GpuBuffer {
+                    resource_id,
+                    paddr,
+                    vaddr,
+                    pages,
+                };
+                let res = self
+                    .command_nodata(GpuResourceCreate2d {
+                        header: GpuCtrlHeader::new(GPU_CMD_RESOURCE_CREATE_2D),
+                        resource_id,
+                        format: gpu_format(mode.format),
+                        width: mode.width,
+                        height: mode.height,
+                    })
+                    .and_then(|_| {
+                        self.command_nodata(GpuAttachBacking {
+                            header: GpuCtrlHeader::new(GPU_CMD_RESOURCE_ATTACH_BACKING),
+                            resource_id,
+                            nr_entries: 1,
+                            addr: paddr as u64,
+                            length: size as u32,
+                            padding: 0,
+                        })
+                    });
+                match res {
+                    Ok(()) => Ok(buffer),
+                    Err(e) => {
+                        self.destroy_buffer(buffer);
+                        Err(e)
+                    }
+                }
+            }
+
+            fn destroy_buffer(&mut self, buffer: GpuBuffer) {
+                for cmd in [GPU_CMD_RESOURCE_DETACH_BACKING, GPU_CMD_RESOURCE_UNREF] {
+                    let _ = self.command_nodata(GpuResourceOp {
+                        header: GpuCtrlHeader::new(cmd),
+                        resource_id: buffer.resource_id,
+                        padding: 0,
+                    });
+                }
+                unsafe { VirtIoHalImpl::dma_dealloc(buffer.paddr, buffer.vaddr, buffer.pages) };
+            }
+
+            /// Returns a resource ID that is not used by the framebuffers, the
+            /// ones in `pending` or the cursor.
+            fn free_resource_id(&self, pending: &[Option<GpuBuffer>]) -> u32 {
+                (1..)
+                    .find(|&id| {
+                        id != Self::CURSOR_RESOURCE_ID
+                            && self
+                                .buffers
+                                .iter()
+                                .chain(pending)
+                                .flatten()
+                                .all(|buffer| buffer.resource_id != id)
+                    })
+                    .unwrap()
+            }
+
+            /// Creates `count` framebuffers of `mode`, without touching the
+            /// current ones. Nothing is left allocated if it fails.
+            fn create_framebuffers(
+                &mut self,
+                mode: &DisplayMode,
+                count: usize,
+            ) -> DevResult<[Option<GpuBuffer>; 2]> {
+                let mut buffers = [None, None];
+                for i in 0..count {
+                    let resource_id = self.free_resource_id(&buffers);
+                    match self.create_buffer(resource_id, mode) {
+                        Ok(buffer) => buffers[i] = Some(buffer),
+                        Err(e) => {
+                            for buffer in buffers.into_iter().flatten() {
+                                self.destroy_buffer(buffer);
+                            }
+                            return Err(e);
+                        }
+                    }
+                }
+                Ok(buffers)
+            }
+
+            /// Scans out and shows the framebuffer `index` of the current mode.
+            fn show(&mut self, index: usize) -> DevResult {
+                self.set_scanout(index)?;
+                self.flush_buffer(index, self.full_rect())
+            }
+
+            /// The index of the framebuffer on screen.
+            fn front(&self) -> usize {
+                if self.is_double_buffered() {
+                    self.back ^ 1
+                } else {
+                    self.back
+                }
+            }
+
+            fn destroy_framebuffers(&mut self) {
+                // Disable the scanout first, as resources being scanned out
+                // can't be destroyed.
+                let _ = self.command_nodata(GpuSetScanout {
+                    header: GpuCtrlHeader::new(GPU_CMD_SET_SCANOUT),
+                    rect: GpuRect::default(),
+                    scanout_id: 0,
+                    resource_id: 0,
+                });
+                for buffer in core::mem::take(&mut self.buffers).into_iter().flatten() {
+                    self.destroy_buffer(buffer);
+                }
+            }
+
+            fn full_rect(&self) -> Rect {
+                Rect {
+                    x: 0,
+                    y: 0,
+                    width: self.mode.width,
+                    height: self.mode.height,
+                }
+            }
+
+            fn buffer(&self, index: usize) -> &GpuBuffer {
+                self.buffers[index].as_ref().unwrap()
+            }
+
+            fn set_scanout(&mut self, index: usize) -> DevResult {
+                self.command_nodata(GpuSetScanout {
+                    header: GpuCtrlHeader::new(GPU_CMD_SET_SCANOUT),
+                    rect: self.full_rect().into(),
+                    scanout_id: 0,
+                    resource_id: self.buffer(index).resource_id,
+                })
+            }
+
+            /// Copies `rect` of a framebuffer to the host resource, and shows
+            /// it if the framebuffer is being scanned out.
+            fn flush_buffer(&mut self, index: usize, rect: Rect) -> DevResult {
+                let resource_id = self.buffer(index).resource_id;
+                self.command_nodata(GpuTransferToHost2d {
+                    header: GpuCtrlHeader::new(GPU_CMD_TRANSFER_TO_HOST_2D),
+                    rect: rect.into(),
+                    offset: (rect.y as usize * self.mode.stride()
+                        + rect.x as usize * self.mode.format.bytes_per_pixel())
+                        as u64,
+                    resource_id,
+                    padding: 0,
+                })?;
+                self.command_nodata(GpuResourceFlush {
+                    header: GpuCtrlHeader::new(GPU_CMD_RESOURCE_FLUSH),
+                    rect: rect.into(),
+                    resource_id,
+                    padding: 0,
+                })
+            }
+
+            fn update_cursor(&mut self, cmd: u32, resource_id: u32, hot: (u32, u32)) {
+                let req = GpuUpdateCursor {
+                    header: GpuCtrlHeader::new(cmd),
+                    scanout_id: 0,
+                    x: self.cursor_pos.0,
+                    y: self.cursor_pos.1,
+                    padding: 0,
+                    resource_id,
+                    hot_x: hot.0,
+                    hot_y: hot.1,
+                    padding2: 0,
+                };
+                unsafe { (self.queue.buf_ptr(0) as *mut GpuUpdateCursor).write_unaligned(req) };
+                // The device does not respond to cursor commands.
+                self.queue.request(
+                    Self::CURSORQ,
+                    &[QueueBuf {
+                        offset: 0,
+                        len: size_of::<GpuUpdateCursor>(),
+                        device_writable: false,
+                    }],
+                );
+            }
+        }
+
+        impl BaseDriverOps for VirtIoGpuDev {
+            fn device_type(&self) -> DeviceType {
+                DeviceType::Display
+            }
+
+            fn device_name(&self) -> &str {
+                "virtio-gpu"
+            }
+        }
+
+        impl DisplayDriverOps for VirtIoGpuDev {
+            fn info(&self) -> DisplayInfo {
+                DisplayInfo {
+                    width: self.mode.width,
+                    height: self.mode.height,
+                    fb_base_vaddr: self.buffer(self.back).vaddr.as_ptr() as usize,
+                    fb_size: self.mode.fb_size(),
+                }
+            }
+
+            fn fb(&self) -> FrameBuffer<'_> {
+                let buffer = self.buffer(self.back);
+                unsafe {
+                    FrameBuffer::from_raw_parts_mut(buffer.vaddr.as_ptr(), self.mode.fb_size())
+                }
+            }
+
+            fn need_flush(&self) -> bool {
+                true
+            }
+
+            fn flush(&mut self) -> DevResult {
+                self.flush_rect(self.full_rect())
+            }
+        }
+
+        impl DisplayOutputOps for VirtIoGpuDev {
+            fn mode(&self) -> DisplayMode {
+                self.mode
+            }
+
+            /// Returns the preferred mode, then standard resolutions no larger
+            /// than it. Other resolutions and pixel formats are also accepted
+            /// by [`set_mode`](Self::set_mode), as the host scales the
+            /// framebuffer to the scanout.
+            fn modes(&self) -> Vec<DisplayMode> {
+                let mut modes = alloc::vec![self.preferred];
+                for (width, height) in Self::STANDARD_RESOLUTIONS {
+                    if width <= self.preferred.width
+                        && height <= self.preferred.height
+                        && (width, height) != (self.preferred.width, self.preferred.height)
+                    {
+                        modes.push(DisplayMode {
+                            width,
+                            height,
+                            format: self.preferred.format,
+                        });
+                    }
+                }
+                modes
+            }
+
+            fn set_mode(&mut self, mode: DisplayMode) -> DevResult {
+                if mode.width == 0 || mode.height == 0 || mode.fb_size() > Self::MAX_FB_SIZE {
+                    return Err(DevError::InvalidParam);
+                }
+                if mode == self.mode {
+                    return Ok(());
+                }
+                // The old framebuffers are kept until the new ones are shown,
+                // so that the device is never left without framebuffers.
+                let count = if self.is_double_buffered() { 2 } else { 1 };
+                let buffers = self.create_framebuffers(&mode, count)?;
+                let old_mode = core::mem::replace(&mut self.mode, mode);
+                let old_buffers = core::mem::replace(&mut self.buffers, buffers);
+                let old_back = core::mem::replace(&mut self.back, count - 1);
+                let res = self.show(0);
+                let unused = if let Err(e) = &res {
+                    warn!("virtio-gpu: failed to set mode {:?}: {:?}", mode, e);
+                    self.mode = old_mode;
+                    self.back = old_back;
+                    let new_buffers = core::mem::replace(&mut self.buffers, old_buffers);
+                    let _ = self.show(self.front());
+                    new_buffers
+                } else {
+                    old_buffers
+                };
+                for buffer in unused.into_iter().flatten() {
+                    self.destroy_buffer(buffer);
+                }
+                res
+            }
+
+            fn flush_rect(&mut self, rect: Rect) -> DevResult {
+                let rect = rect.clip(&self.mode);
+                if rect.is_empty() {
+                    return Ok(());
+                }
+                self.flush_buffer(self.back, rect)
+            }
+
+            fn set_double_buffered(&mut self, enabled: bool) -> DevResult {
+                if enabled == self.is_double_buffered() {
+                    return Ok(());
+                }
+                if enabled {
+                    // The new back buffer starts with the content on screen.
+                    let resource_id = self.free_resource_id(&[]);
+                    let mode = self.mode;
+                    let buffer = self.create_buffer(resource_id, &mode)?;
+                    unsafe {
+                        buffer
+                            .vaddr
+                            .copy_from_nonoverlapping(self.buffer(0).vaddr, mode.fb_size())
+                    };
+                    self.buffers[1] = Some(buffer);
+                    self.back = 1;
+                } else {
+                    // Keep the buffer on screen, and draw into it from now on.
+                    let front = self.back ^ 1;
+                    self.buffers.swap(0, front);
+                    self.back = 0;
+                    let buffer = self.buffers[1].take().unwrap();
+                    self.destroy_buffer(buffer);
+                }
+                Ok(())
+            }
+
+            fn is_double_buffered(&self) -> bool {
+                self.buffers[1].is_some()
+            }
+
+            fn flip(&mut self) -> DevResult {
+                if !self.is_double_buffered() {
+                    return self.flush();
+                }
+                let back = self.back;
+                let rect = self.full_rect();
+                self.set_scanout(back)?;
+                self.flush_buffer(back, rect)?;
+                self.back ^= 1;
+                Ok(())
+            }
+
+            fn has_cursor(&self) -> bool {
+                true
+            }
+
+            fn set_cursor(&mut self, image: Option<&[u32]>, hot_x: u32, hot_y: u32) -> DevResult {
+                let Some(image) = image else {
+                    self.update_cursor(GPU_CMD_UPDATE_CURSOR, 0, (0, 0));
+                    return Ok(());
+                };
+                let pixels = (CURSOR_SIZE * CURSOR_SIZE) as usize;
+                if image.len() < pixels || hot_x >= CURSOR_SIZE || hot_y >= CURSOR_SIZE {
+                    return Err(DevError::InvalidParam);
+                }
+                if self.cursor.is_none() {
+                    let mode = DisplayMode {
+                        width: CURSOR_SIZE,
+                        height: CURSOR_SIZE,
+                        format: PixelFormat::Argb8888,
+                    };
+                    self.cursor = Some(self.create_buffer(Self::CURSOR_RESOURCE_ID, &mode)?);
+                }
+                let vaddr = self.cursor.as_ref().unwrap().vaddr;
+                unsafe {
+                    core::ptr::copy_nonoverlapping(
+                        image.as_ptr(),
+                        vaddr.as_ptr() as *mut u32,
+                        pixels,
+                    )
+                };
+                self.command_nodata(GpuTransferToHost2d {
+                    header: GpuCtrlHeader::new(GPU_CMD_TRANSFER_TO_HOST_2D),
+                    rect: GpuRect {
+                        x: 0,
+                        y: 0,
+                        width: CURSOR_SIZE,
+                        height: CURSOR_SIZE,
+                    },
+                    offset: 0,
+                    resource_id: Self::CURSOR_RESOURCE_ID,
+                    padding: 0,
+                })?;
+                self.update_cursor(
+                    GPU_CMD_UPDATE_CURSOR,
+                    Self::CURSOR_RESOURCE_ID,
+                    (hot_x, hot_y),
+                );
+                Ok(())
+            }
+
+            fn move_cursor(&mut self, x: u32, y: u32) -> DevResult {
+                self.cursor_pos = (x, y);
+                self.update_cursor(GPU_CMD_MOVE_CURSOR, 0, (0, 0));
+                Ok(())
+            }
+        }
+
+        impl Drop for VirtIoGpuDev {
+            fn drop(&mut self) {
+                self.destroy_framebuffers();
+                if let Some(cursor) = self.cursor.take() {
+                    self.destroy_buffer(cursor);
+                }
             }
         }
     }
@@ -85,7 +1735,10 @@ impl<D: VirtIoDevMeta> DriverProbe for VirtIoDriver<D> {
             && ty == D::DEVICE_TYPE
         {
             match D::try_new(transport) {
-                Ok(dev) => return Some(dev),
+                Ok(dev) => {
+                    reserve_bounce();
+                    return Some(dev);
+                }
                 Err(e) => {
                     warn!(
                         "failed to initialize MMIO device at [PA:{:#x}, PA:{:#x}): {:?}",
@@ -112,7 +1765,6 @@ impl<D: VirtIoDevMeta> DriverProbe for VirtIoDriver<D> {
         match (D::DEVICE_TYPE, dev_info.device_id) {
             (DeviceType::Net, 0x1000) | (DeviceType::Net, 0x1041) => {}
             (DeviceType::Block, 0x1001) | (DeviceType::Block, 0x1042) => {}
-            (DeviceType::Display, 0x1050) => {}
             _ => return None,
         }
 
@@ -120,8 +1772,11 @@ impl<D: VirtIoDevMeta> DriverProbe for VirtIoDriver<D> {
             axdriver_virtio::probe_pci_device::<VirtIoHalImpl>(root, bdf, dev_info)
         {
             if ty == D::DEVICE_TYPE {
-                match D::try_new(transport) {
-                    Ok(dev) => return Some(dev),
+                match D::try_new(pci_transport(transport, root, bdf)) {
+                    Ok(dev) => {
+                        reserve_bounce();
+                        return Some(dev);
+                    }
                     Err(e) => {
                         warn!(
                             "failed to initialize PCI device at {}({}): {:?}",
@@ -138,6 +1793,64 @@ impl<D: VirtIoDevMeta> DriverProbe for VirtIoDriver<D> {
 
 pub struct VirtIoHalImpl;
 
+/// The size of the bounce buffer reserved for each device in pages.
+const RESERVED_BOUNCE_PAGES: usize = 16;
+
+/// A bounce buffer reserved when a device is probed.
+struct ReservedBounce {
+    vaddr: usize,
+    in_use: bool,
+}
+
+/// Bounce buffers reserved for devices, used before the global allocator, so
+/// that sharing buffers does not fail under memory pressure.
+static RESERVED_BOUNCES: SpinNoIrq<Vec<ReservedBounce>> = SpinNoIrq::new(Vec::new());
+
+/// Reserves a bounce buffer for a newly probed device.
+fn reserve_bounce() {
+    match global_allocator().alloc_pages(RESERVED_BOUNCE_PAGES, PAGE_SIZE_4K) {
+        Ok(vaddr) => RESERVED_BOUNCES.lock().push(ReservedBounce {
+            vaddr,
+            in_use: false,
+        }),
+        Err(_) => warn!("failed to reserve bounce buffer for VirtIO device"),
+    }
+}
+
+fn alloc_bounce(pages: usize) -> Option<usize> {
+    if pages <= RESERVED_BOUNCE_PAGES
+        && let Some(bounce) = RESERVED_BOUNCES.lock().iter_mut().find(|b| !b.in_use)
+    {
+        bounce.in_use = true;
+        return Some(bounce.vaddr);
+    }
+    global_allocator().alloc_pages(pages, PAGE_SIZE_4K).ok()
+}
+
+fn free_bounce(vaddr: usize, pages: usize) {
+    if let Some(bounce) = RESERVED_BOUNCES
+        .lock()
+        .iter_mut()
+        .find(|b| b.vaddr == vaddr)
+    {
+        bounce.in_use = false;
+        return;
+    }
+    global_allocator().dealloc_pages(vaddr, pages);
+}
+
+/// Whether the buffer is in the linear mapping of physical memory, so that
+/// its physical address can be obtained by [`virt_to_phys`].
+///
+/// Other kernel memory (e.g., task stacks mapped in a separate region) must be
+/// bounced before being shared with devices.
+fn is_linear_mapped(buffer: NonNull<[u8]>) -> bool {
+    let start = phys_to_virt(axconfig::plat::PHYS_MEMORY_BASE.into()).as_usize();
+    let end = start + axconfig::plat::PHYS_MEMORY_SIZE;
+    let vaddr = buffer.as_ptr() as *mut u8 as usize;
+    vaddr >= start && vaddr + buffer.len() <= end
+}
+
 unsafe impl VirtIoHal for VirtIoHalImpl {
     fn dma_alloc(pages: usize, _direction: BufferDirection) -> (PhysAddr, NonNull<u8>) {
         let vaddr = if let Ok(vaddr) = global_allocator().alloc_pages(pages, 0x1000) {
@@ -160,12 +1873,46 @@ unsafe impl VirtIoHal for VirtIoHalImpl {
         NonNull::new(phys_to_virt(paddr.into()).as_mut_ptr()).unwrap()
     }
 
-    #[inline]
-    unsafe fn share(buffer: NonNull<[u8]>, _direction: BufferDirection) -> PhysAddr {
+    unsafe fn share(buffer: NonNull<[u8]>, direction: BufferDirection) -> PhysAddr {
         let vaddr = buffer.as_ptr() as *mut u8 as usize;
-        virt_to_phys(vaddr.into()).into()
+        if is_linear_mapped(buffer) {
+            return virt_to_phys(vaddr.into()).into();
+        }
+
+        let pages = buffer.len().div_ceil(PAGE_SIZE_4K);
+        let bounce = match alloc_bounce(pages) {
+            Some(bounce) => bounce,
+            None => {
+                // Wait for buffers shared by other requests to be unshared.
+                warn!("no bounce buffer for {:#x}, waiting...", vaddr);
+                loop {
+                    core::hint::spin_loop();
+                    if let Some(bounce) = alloc_bounce(pages) {
+                        break bounce;
+                    }
+                }
+            }
+        };
+        if !matches!(direction, BufferDirection::DeviceToDriver) {
+            unsafe {
+                core::ptr::copy_nonoverlapping(vaddr as *const u8, bounce as *mut u8, buffer.len())
+            };
+        }
+        virt_to_phys(bounce.into()).into()
     }
 
-    #[inline]
-    unsafe fn unshare(_paddr: PhysAddr, _buffer: NonNull<[u8]>, _direction: BufferDirection) {}
+    unsafe fn unshare(paddr: PhysAddr, buffer: NonNull<[u8]>, direction: BufferDirection) {
+        if is_linear_mapped(buffer) {
+            return;
+        }
+
+        let bounce = phys_to_virt(paddr.into()).as_mut_ptr();
+        if !matches!(direction, BufferDirection::DriverToDevice) {
+            unsafe {
+                core::ptr::copy_nonoverlapping(bounce, buffer.as_ptr() as *mut u8, buffer.len())
+            };
+        }
+        let pages = buffer.len().div_ceil(PAGE_SIZE_4K);
+        free_bounce(bounce as usize, pages);
+    }
 }
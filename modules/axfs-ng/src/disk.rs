@@ -1,7 +1,201 @@
 use core::mem;
 
 use alloc::{boxed::Box, vec};
-use axdriver::prelude::*;
+use axalloc::AllocTag;
+use axdriver::{
+    AxBlockDevice,
+    prelude::{DevError, DevResult},
+    registry::{Device, DeviceRef},
+};
+
+/// A block device that file systems can be created on.
+///
+/// It's implemented by block drivers owned by the file system (e.g., a RAM
+/// disk), and by [`DeviceDisk`] for devices opened from the
+/// [registry](axdriver::registry).
+pub trait BlockDevice: Send + Sync + 'static {
+    /// The number of blocks in the device.
+    fn num_blocks(&self) -> u64;
+    /// The size of each block in bytes.
+    fn block_size(&self) -> usize;
+    /// Reads blocks starting from `block_id` into `buf`.
+    fn read_block(&mut self, block_id: u64, buf: &mut [u8]) -> DevResult;
+    /// Writes blocks starting from `block_id` from `buf`.
+    fn write_block(&mut self, block_id: u64, buf: &[u8]) -> DevResult;
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "multitask")] {
+        use core::time::Duration;
+
+        use axtask::Event;
+
+        /// The maximum time to wait for a request between polls, in case its
+        /// interrupt is missed.
+        const MAX_WAIT: Duration = Duration::from_millis(10);
+
+        /// Signaled by interrupts of the block devices.
+        static EVENT: Event = Event::new();
+
+        /// Calls `f` with the driver until it no longer returns
+        /// [`DevError::Again`].
+        ///
+        /// The device is not locked in between, so that other users of the
+        /// device can make progress meanwhile. If the device raises
+        /// interrupts, the task waits for them, otherwise it yields.
+        fn wait_device<T>(
+            dev: &Device<AxBlockDevice>,
+            irq_driven: bool,
+            mut f: impl FnMut(&mut AxBlockDevice) -> DevResult<T>,
+        ) -> DevResult<T> {
+            loop {
+                let seen = EVENT.count();
+                let result = f(&mut dev.lock());
+                match result {
+                    Err(DevError::Again) if irq_driven => EVENT.wait_timeout(seen, MAX_WAIT),
+                    Err(DevError::Again) => axtask::yield_now(),
+                    result => return result,
+                }
+            }
+        }
+    } else {
+        /// Calls `f` with the driver until it no longer returns
+        /// [`DevError::Again`], spinning with the device unlocked.
+        fn wait_device<T>(
+            dev: &Device<AxBlockDevice>,
+            irq_driven: bool,
+            mut f: impl FnMut(&mut AxBlockDevice) -> DevResult<T>,
+        ) -> DevResult<T> {
+            let _ = irq_driven;
+            loop {
+                match f(&mut dev.lock()) {
+                    Err(DevError::Again) => core::hint::spin_loop(),
+                    result => return result,
+                }
+            }
+        }
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(all(feature = "irq", feature = "multitask"))] {
+        fn on_irq() {
+            EVENT.notify(true);
+        }
+
+        /// Receives interrupts of the given device, returns `false` if it
+        /// does not raise any.
+        fn init_irq(dev: &Device<AxBlockDevice>) -> bool {
+            axdriver::irq::set_irq_callback(dev.name(), None, on_irq)
+        }
+    } else {
+        fn init_irq(_dev: &Device<AxBlockDevice>) -> bool {
+            false
+        }
+    }
+}
+
+/// A block device opened from the [registry](axdriver::registry), which is
+/// locked for each operation.
+///
+/// If the driver completes requests asynchronously (see
+/// [`BlockRequestOps`]), the device is not locked while a request is in
+/// flight, and the task waits for the interrupt of the device meanwhile.
+///
+/// [`BlockRequestOps`]: axdriver::block::BlockRequestOps
+pub struct DeviceDisk {
+    dev: DeviceRef<AxBlockDevice>,
+    block_size: usize,
+    /// The maximum size of a request, or 0 if the driver does not support
+    /// requests.
+    max_request_size: usize,
+    irq_driven: bool,
+}
+
+// Implemented in a separate module where only the driver traits are in scope,
+// so that calls on the drivers are not ambiguous.
+mod imp {
+    use axdriver::prelude::*;
+    use axdriver::registry::DeviceRef;
+
+    use super::{DeviceDisk, init_irq, wait_device};
+
+    impl super::BlockDevice for AxBlockDevice {
+        fn num_blocks(&self) -> u64 {
+            self.num_blocks()
+        }
+        fn block_size(&self) -> usize {
+            self.block_size()
+        }
+        fn read_block(&mut self, block_id: u64, buf: &mut [u8]) -> DevResult {
+            self.read_block(block_id, buf)
+        }
+        fn write_block(&mut self, block_id: u64, buf: &[u8]) -> DevResult {
+            self.write_block(block_id, buf)
+        }
+    }
+
+    impl DeviceDisk {
+        /// Opens a disk on the given device.
+        pub fn new(dev: DeviceRef<AxBlockDevice>) -> Self {
+            let (block_size, max_request_size) = {
+                let driver = dev.lock();
+                (driver.block_size(), driver.max_request_size())
+            };
+            Self {
+                irq_driven: init_irq(&dev),
+                dev,
+                block_size,
+                max_request_size,
+            }
+        }
+
+        /// Returns the number of blocks of each request.
+        fn request_blocks(&self) -> usize {
+            self.max_request_size / self.block_size
+        }
+    }
+
+    impl super::BlockDevice for DeviceDisk {
+        fn num_blocks(&self) -> u64 {
+            self.dev.lock().num_blocks()
+        }
+        fn block_size(&self) -> usize {
+            self.block_size
+        }
+        fn read_block(&mut self, block_id: u64, buf: &mut [u8]) -> DevResult {
+            if self.max_request_size == 0 {
+                return self.dev.lock().read_block(block_id, buf);
+            }
+            for (block_id, chunk) in (block_id..)
+                .step_by(self.request_blocks())
+                .zip(buf.chunks_mut(self.max_request_size))
+            {
+                let len = chunk.len();
+                wait_device(&self.dev, self.irq_driven, |dev| {
+                    dev.submit_read(block_id, len)
+                })?;
+                wait_device(&self.dev, self.irq_driven, |dev| dev.poll_request(chunk))?;
+            }
+            Ok(())
+        }
+        fn write_block(&mut self, block_id: u64, buf: &[u8]) -> DevResult {
+            if self.max_request_size == 0 {
+                return self.dev.lock().write_block(block_id, buf);
+            }
+            for (block_id, chunk) in (block_id..)
+                .step_by(self.request_blocks())
+                .zip(buf.chunks(self.max_request_size))
+            {
+                wait_device(&self.dev, self.irq_driven, |dev| {
+                    dev.submit_write(block_id, chunk)
+                })?;
+                wait_device(&self.dev, self.irq_driven, |dev| dev.poll_request(&mut []))?;
+            }
+            Ok(())
+        }
+    }
+}
 
 fn take<'a>(buf: &mut &'a [u8], cnt: usize) -> &'a [u8] {
     let (first, rem) = buf.split_at(cnt);
@@ -18,7 +212,7 @@ fn take_mut<'a>(buf: &mut &'a mut [u8], cnt: usize) -> &'a mut [u8] {
 /// A disk device with a cursor.
 #[allow(unused)]
 pub struct SeekableDisk {
-    dev: AxBlockDevice,
+    dev: Box<dyn BlockDevice>,
 
     block_id: u64,
     offset: usize,
@@ -35,13 +229,16 @@ pub struct SeekableDisk {
 #[allow(unused)]
 impl SeekableDisk {
     /// Create a new disk.
-    pub fn new(dev: AxBlockDevice) -> Self {
-        assert!(dev.block_size().is_power_of_two());
-        let block_size_log2 = dev.block_size().trailing_zeros() as u8;
-        let read_buffer = vec![0u8; dev.block_size()].into_boxed_slice();
-        let write_buffer = vec![0u8; dev.block_size()].into_boxed_slice();
+    pub fn new(dev: impl BlockDevice) -> Self {
+        let block_size = dev.block_size();
+        assert!(block_size.is_power_of_two());
+        let block_size_log2 = block_size.trailing_zeros() as u8;
+        let read_buffer = vec![0u8; block_size].into_boxed_slice();
+        let write_buffer = vec![0u8; block_size].into_boxed_slice();
+        axalloc::tag_alloc(read_buffer.as_ptr(), AllocTag::FsCache);
+        axalloc::tag_alloc(write_buffer.as_ptr(), AllocTag::FsCache);
         Self {
-            dev,
+            dev: Box::new(dev),
             block_id: 0,
             offset: 0,
             block_size_log2,
@@ -1,13 +1,14 @@
 use core::cell::OnceCell;
 
-use alloc::sync::Arc;
-use axdriver::AxBlockDevice;
+use alloc::{boxed::Box, sync::Arc};
 use axfs_ng_vfs::{
     DirEntry, DirNode, Filesystem, FilesystemOps, Reference, StatFs, VfsResult, path::MAX_NAME_LEN,
 };
 use lock_api::{Mutex, MutexGuard, RawMutex};
 use lwext4_rust::ffi::EXT4_ROOT_INO;
 
+use crate::disk::BlockDevice;
+
 use super::{
     Ext4Disk, Inode,
     util::{LwExt4Filesystem, into_vfs_err},
@@ -18,11 +19,12 @@ pub struct Ext4Filesystem<M> {
     root_dir: OnceCell<DirEntry<M>>,
 }
 impl<M: RawMutex> Ext4Filesystem<M> {
-    pub fn new(dev: AxBlockDevice) -> VfsResult<Filesystem<M>>
+    pub fn new(dev: impl BlockDevice) -> VfsResult<Filesystem<M>>
     where
         M: Send + Sync + 'static,
     {
-        let ext4 = lwext4_rust::Ext4Filesystem::new(Ext4Disk(dev)).map_err(into_vfs_err)?;
+        let ext4 =
+            lwext4_rust::Ext4Filesystem::new(Ext4Disk(Box::new(dev))).map_err(into_vfs_err)?;
 
         let fs = Arc::new(Self {
             inner: Mutex::new(ext4),
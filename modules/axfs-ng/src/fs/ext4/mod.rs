@@ -5,12 +5,13 @@ mod util;
 pub use fs::*;
 pub use inode::*;
 
-use axdriver::AxBlockDevice;
-use axdriver::prelude::*;
-use lwext4_rust::{BlockDevice, EXT4_DEV_BSIZE, Ext4Error, Ext4Result, ffi::EIO};
+use alloc::boxed::Box;
+use lwext4_rust::{EXT4_DEV_BSIZE, Ext4Error, Ext4Result, ffi::EIO};
 
-pub(crate) struct Ext4Disk(AxBlockDevice);
-impl BlockDevice for Ext4Disk {
+use crate::disk::BlockDevice;
+
+pub(crate) struct Ext4Disk(Box<dyn BlockDevice>);
+impl lwext4_rust::BlockDevice for Ext4Disk {
     fn read_blocks(&mut self, block_id: u64, buf: &mut [u8]) -> Ext4Result<usize> {
         let mut block_buf = [0u8; EXT4_DEV_BSIZE];
         for (i, block) in buf.chunks_mut(EXT4_DEV_BSIZE).enumerate() {
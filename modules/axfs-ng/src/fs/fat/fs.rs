@@ -1,14 +1,13 @@
 use core::marker::PhantomPinned;
 
 use alloc::sync::Arc;
-use axdriver::AxBlockDevice;
 use axfs_ng_vfs::{
     DirEntry, Filesystem, FilesystemOps, Reference, StatFs, VfsResult, path::MAX_NAME_LEN,
 };
 use lock_api::{Mutex, MutexGuard, RawMutex};
 use slab::Slab;
 
-use crate::disk::SeekableDisk;
+use crate::disk::{BlockDevice, SeekableDisk};
 
 use super::{dir::FatDirNode, ff, util::into_vfs_err};
 
@@ -32,7 +31,7 @@ pub struct FatFilesystem<M> {
 }
 
 impl<M: RawMutex + Send + Sync + 'static> FatFilesystem<M> {
-    pub fn new(dev: AxBlockDevice) -> Filesystem<M> {
+    pub fn new(dev: impl BlockDevice) -> Filesystem<M> {
         let mut inner = FatFilesystemInner {
             inner: ff::FileSystem::new(SeekableDisk::new(dev), fatfs::FsOptions::new())
                 .expect("failed to initialize FAT filesystem"),
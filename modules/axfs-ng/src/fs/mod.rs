@@ -4,13 +4,17 @@ pub mod fat;
 #[cfg(feature = "ext4")]
 pub mod ext4;
 
-use axdriver::AxBlockDevice;
+#[cfg(feature = "ninep")]
+pub mod ninep;
+
 use axfs_ng_vfs::{Filesystem, VfsResult};
 use cfg_if::cfg_if;
 use lock_api::RawMutex;
 
+use crate::disk::BlockDevice;
+
 pub fn new_default<M: RawMutex + Send + Sync + 'static>(
-    dev: AxBlockDevice,
+    dev: impl BlockDevice,
 ) -> VfsResult<Filesystem<M>> {
     cfg_if! {
         if #[cfg(feature = "ext4")] {
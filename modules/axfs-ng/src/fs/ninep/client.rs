@@ -0,0 +1,380 @@
+use alloc::{string::String, vec, vec::Vec};
+use core::time::Duration;
+
+use axdriver::{
+    AxNinePDevice,
+    prelude::*,
+    registry::{Device, DeviceRef},
+};
+use axfs_ng_vfs::{VfsError, VfsResult};
+use axtask::Event;
+use slab::Slab;
+
+use super::proto::*;
+
+/// The maximum time to wait for a response between polls, in case its
+/// interrupt is missed.
+const MAX_WAIT: Duration = Duration::from_millis(10);
+
+/// Signaled by interrupts of the 9P transports.
+static EVENT: Event = Event::new();
+
+cfg_if::cfg_if! {
+    if #[cfg(all(feature = "irq", feature = "multitask"))] {
+        fn on_irq() {
+            EVENT.notify(true);
+        }
+
+        /// Receives interrupts of the given transport, returns `false` if it
+        /// does not raise any.
+        fn init_irq(dev: &Device<AxNinePDevice>) -> bool {
+            axdriver::irq::set_irq_callback(dev.name(), None, on_irq)
+        }
+    } else {
+        fn init_irq(_dev: &Device<AxNinePDevice>) -> bool {
+            false
+        }
+    }
+}
+
+/// Calls `f` with the driver until it no longer returns [`DevError::Again`].
+///
+/// The device is not locked in between, since a task can not block with it
+/// locked, and other users of the device can make progress meanwhile. If the
+/// device raises interrupts, the task waits for them, otherwise it yields.
+fn wait_device<T>(
+    dev: &Device<AxNinePDevice>,
+    irq_driven: bool,
+    mut f: impl FnMut(&mut AxNinePDevice) -> DevResult<T>,
+) -> DevResult<T> {
+    loop {
+        let seen = EVENT.count();
+        let result = f(&mut dev.lock());
+        match result {
+            Err(DevError::Again) if irq_driven => EVENT.wait_timeout(seen, MAX_WAIT),
+            Err(DevError::Again) => axtask::yield_now(),
+            result => return result,
+        }
+    }
+}
+
+/// A 9P2000.L client, which sends one request at a time over a transport.
+///
+/// Fids are allocated by the client, and must be clunked when they are no
+/// longer used.
+pub struct Client {
+    dev: DeviceRef<AxNinePDevice>,
+    irq_driven: bool,
+    msize: usize,
+    req: Vec<u8>,
+    resp: Vec<u8>,
+    fids: Slab<()>,
+}
+
+impl Client {
+    /// Negotiates the protocol version with the server, and attaches to the
+    /// root of the exported file system. Returns the client and the fid of
+    /// the root directory.
+    pub fn new(dev: DeviceRef<AxNinePDevice>) -> VfsResult<(Self, u32)> {
+        let msize = dev.lock().max_message_size();
+        let mut client = Self {
+            irq_driven: init_irq(&dev),
+            dev,
+            msize,
+            req: Vec::with_capacity(msize),
+            resp: vec![0; msize],
+            fids: Slab::new(),
+        };
+
+        let mut resp = client.call(TVERSION, NOTAG, |m| {
+            m.u32(msize as u32).str(VERSION);
+        })?;
+        let msize = resp.u32()? as usize;
+        if resp.str()? != VERSION {
+            return Err(VfsError::EINVAL);
+        }
+        client.msize = client.msize.min(msize);
+        // Reads and writes carry no data if the message size does not exceed
+        // their headers.
+        if client.msize <= IO_HEADER_SIZE {
+            return Err(VfsError::EINVAL);
+        }
+
+        let root = client.alloc_fid();
+        client.call(TATTACH, 0, |m| {
+            m.u32(root).u32(NOFID).str("root").str("").u32(0);
+        })?;
+        Ok((client, root))
+    }
+
+    fn alloc_fid(&mut self) -> u32 {
+        self.fids.insert(()) as u32
+    }
+
+    fn free_fid(&mut self, fid: u32) {
+        self.fids.remove(fid as usize);
+    }
+
+    /// Sends a request of the given type and tag, whose fields are written by
+    /// `body`, and returns the fields of the response.
+    fn call(
+        &mut self,
+        ty: u8,
+        tag: u16,
+        body: impl FnOnce(&mut Encoder),
+    ) -> VfsResult<Decoder<'_>> {
+        self.req.clear();
+        Encoder(&mut self.req).u32(0).u8(ty).u16(tag);
+        body(&mut Encoder(&mut self.req));
+        let size = self.req.len() as u32;
+        self.req[..4].copy_from_slice(&size.to_le_bytes());
+
+        let (req, resp) = (&self.req, &mut self.resp[..self.msize]);
+        let len = wait_device(&self.dev, self.irq_driven, |dev| dev.submit(req))
+            .and_then(|()| wait_device(&self.dev, self.irq_driven, |dev| dev.poll_response(resp)))
+            .map_err(|_| VfsError::EIO)?;
+        let mut resp = Decoder(&self.resp[..len]);
+        let size = resp.u32()? as usize;
+        let resp_ty = resp.u8()?;
+        let _tag = resp.u16()?;
+        let mut resp = Decoder(resp.bytes(size.saturating_sub(HEADER_SIZE))?);
+        if resp_ty == RLERROR {
+            let ecode = resp.u32()?;
+            return Err(VfsError::try_from(ecode as i32).unwrap_or(VfsError::EIO));
+        }
+        if resp_ty != ty + 1 {
+            return Err(VfsError::EIO);
+        }
+        Ok(resp)
+    }
+
+    /// Walks from `fid` through the given names, and returns a new fid of the
+    /// result and its qid. Clones `fid` if no names are given.
+    pub fn walk(&mut self, fid: u32, names: &[&str]) -> VfsResult<(u32, Option<Qid>)> {
+        let new_fid = self.alloc_fid();
+        let result = self
+            .call(TWALK, 0, |m| {
+                m.u32(fid).u32(new_fid).u16(names.len() as u16);
+                for name in names {
+                    m.str(name);
+                }
+            })
+            .and_then(|mut resp| {
+                let nwqid = resp.u16()? as usize;
+                let mut qid = None;
+                for _ in 0..nwqid {
+                    qid = Some(resp.qid()?);
+                }
+                // The walk stops at the first name not found.
+                if nwqid < names.len() {
+                    return Err(VfsError::ENOENT);
+                }
+                Ok(qid)
+            });
+        match result {
+            Ok(qid) => Ok((new_fid, qid)),
+            Err(err) => {
+                self.free_fid(new_fid);
+                Err(err)
+            }
+        }
+    }
+
+    /// Rebinds `fid` to the file with the given name in the directory it
+    /// refers to.
+    pub fn walk_in_place(&mut self, fid: u32, name: &str) -> VfsResult<()> {
+        self.call(TWALK, 0, |m| {
+            m.u32(fid).u32(fid).u16(1).str(name);
+        })?;
+        Ok(())
+    }
+
+    /// Releases `fid`, errors are ignored since it's released anyway.
+    pub fn clunk(&mut self, fid: u32) {
+        let _ = self.call(TCLUNK, 0, |m| {
+            m.u32(fid);
+        });
+        self.free_fid(fid);
+    }
+
+    /// Opens the file referred by `fid` for I/O.
+    pub fn lopen(&mut self, fid: u32, flags: u32) -> VfsResult<()> {
+        self.call(TLOPEN, 0, |m| {
+            m.u32(fid).u32(flags);
+        })?;
+        Ok(())
+    }
+
+    /// Creates a regular file in the directory referred by `fid`, and opens
+    /// it with `fid` for I/O.
+    pub fn lcreate(&mut self, fid: u32, name: &str, flags: u32, mode: u32) -> VfsResult<Qid> {
+        self.call(TLCREATE, 0, |m| {
+            m.u32(fid).str(name).u32(flags).u32(mode).u32(0);
+        })?
+        .qid()
+    }
+
+    pub fn mkdir(&mut self, dfid: u32, name: &str, mode: u32) -> VfsResult<Qid> {
+        self.call(TMKDIR, 0, |m| {
+            m.u32(dfid).str(name).u32(mode).u32(0);
+        })?
+        .qid()
+    }
+
+    pub fn symlink(&mut self, dfid: u32, name: &str, target: &str) -> VfsResult<Qid> {
+        self.call(TSYMLINK, 0, |m| {
+            m.u32(dfid).str(name).str(target).u32(0);
+        })?
+        .qid()
+    }
+
+    pub fn mknod(&mut self, dfid: u32, name: &str, mode: u32) -> VfsResult<Qid> {
+        self.call(TMKNOD, 0, |m| {
+            m.u32(dfid).str(name).u32(mode).u32(0).u32(0).u32(0);
+        })?
+        .qid()
+    }
+
+    pub fn link(&mut self, dfid: u32, fid: u32, name: &str) -> VfsResult<()> {
+        self.call(TLINK, 0, |m| {
+            m.u32(dfid).u32(fid).str(name);
+        })?;
+        Ok(())
+    }
+
+    pub fn renameat(
+        &mut self,
+        old_dfid: u32,
+        old_name: &str,
+        new_dfid: u32,
+        new_name: &str,
+    ) -> VfsResult<()> {
+        self.call(TRENAMEAT, 0, |m| {
+            m.u32(old_dfid).str(old_name).u32(new_dfid).str(new_name);
+        })?;
+        Ok(())
+    }
+
+    pub fn unlinkat(&mut self, dfid: u32, name: &str, flags: u32) -> VfsResult<()> {
+        self.call(TUNLINKAT, 0, |m| {
+            m.u32(dfid).str(name).u32(flags);
+        })?;
+        Ok(())
+    }
+
+    pub fn readlink(&mut self, fid: u32) -> VfsResult<String> {
+        self.call(TREADLINK, 0, |m| {
+            m.u32(fid);
+        })?
+        .str()
+        .map(String::from)
+    }
+
+    pub fn getattr(&mut self, fid: u32) -> VfsResult<Attr> {
+        self.call(TGETATTR, 0, |m| {
+            m.u32(fid).u64(GETATTR_BASIC);
+        })?
+        .attr()
+    }
+
+    pub fn setattr(&mut self, fid: u32, attr: &SetAttr) -> VfsResult<()> {
+        self.call(TSETATTR, 0, |m| {
+            m.u32(fid)
+                .u32(attr.valid)
+                .u32(attr.mode)
+                .u32(attr.uid)
+                .u32(attr.gid)
+                .u64(attr.size)
+                .time(attr.atime)
+                .time(attr.mtime);
+        })?;
+        Ok(())
+    }
+
+    pub fn statfs(&mut self, fid: u32) -> VfsResult<StatFs> {
+        self.call(TSTATFS, 0, |m| {
+            m.u32(fid);
+        })?
+        .statfs()
+    }
+
+    pub fn fsync(&mut self, fid: u32, data_only: bool) -> VfsResult<()> {
+        self.call(TFSYNC, 0, |m| {
+            m.u32(fid).u32(data_only as u32);
+        })?;
+        Ok(())
+    }
+
+    /// Reads from the opened `fid` at `offset` until `buf` is filled or the
+    /// end of the file, and returns the number of bytes read.
+    pub fn read(&mut self, fid: u32, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let max_count = self.msize - IO_HEADER_SIZE;
+        let mut read = 0;
+        while read < buf.len() {
+            let count = (buf.len() - read).min(max_count);
+            let mut resp = self.call(TREAD, 0, |m| {
+                m.u32(fid).u64(offset + read as u64).u32(count as u32);
+            })?;
+            let len = resp.u32()? as usize;
+            let data = resp.bytes(len.min(count))?;
+            buf[read..read + data.len()].copy_from_slice(data);
+            read += data.len();
+            if data.len() < count {
+                break;
+            }
+        }
+        Ok(read)
+    }
+
+    /// Writes `buf` to the opened `fid` at `offset`, and returns the number
+    /// of bytes written.
+    pub fn write(&mut self, fid: u32, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let max_count = self.msize - IO_HEADER_SIZE;
+        let mut written = 0;
+        while written < buf.len() {
+            let data = &buf[written..(written + max_count).min(buf.len())];
+            let len = self
+                .call(TWRITE, 0, |m| {
+                    m.u32(fid)
+                        .u64(offset + written as u64)
+                        .u32(data.len() as u32)
+                        .bytes(data);
+                })?
+                .u32()? as usize;
+            written += len.min(data.len());
+            if len < data.len() {
+                break;
+            }
+        }
+        Ok(written)
+    }
+
+    /// Reads the entries of the opened directory `fid` from `offset`, and
+    /// calls `f` with the name, qid, type (`DT_*`) and the offset of the next
+    /// entry of each one, until it returns `false`.
+    ///
+    /// Returns the number of entries read, which is 0 at the end of the
+    /// directory.
+    pub fn readdir(
+        &mut self,
+        fid: u32,
+        offset: u64,
+        mut f: impl FnMut(&str, Qid, u8, u64) -> bool,
+    ) -> VfsResult<usize> {
+        let count = (self.msize - IO_HEADER_SIZE) as u32;
+        let mut resp = self.call(TREADDIR, 0, |m| {
+            m.u32(fid).u64(offset).u32(count);
+        })?;
+        let len = resp.u32()? as usize;
+        let mut entries = Decoder(resp.bytes(len)?);
+        let mut read = 0;
+        while !entries.0.is_empty() {
+            let (name, qid, ty, next_offset) = entries.dirent()?;
+            read += 1;
+            if !f(name, qid, ty, next_offset) {
+                break;
+            }
+        }
+        Ok(read)
+    }
+}
@@ -0,0 +1,86 @@
+use core::cell::OnceCell;
+
+use alloc::sync::Arc;
+use axdriver::{AxNinePDevice, registry::DeviceRef};
+use axfs_ng_vfs::{
+    DirEntry, DirNode, Filesystem, FilesystemOps, NodeType, Reference, StatFs, VfsResult,
+};
+use lock_api::{Mutex, MutexGuard, RawMutex};
+
+use super::{Inode, client::Client};
+
+pub struct NinePFilesystem<M> {
+    client: Mutex<M, Client>,
+    root_fid: u32,
+    root_dir: OnceCell<DirEntry<M>>,
+}
+impl<M: RawMutex> NinePFilesystem<M> {
+    /// Attaches to the file system exported by the given 9P transport.
+    pub fn new(dev: DeviceRef<AxNinePDevice>) -> VfsResult<Filesystem<M>>
+    where
+        M: Send + Sync + 'static,
+    {
+        let (mut client, root_fid) = Client::new(dev)?;
+        let root_ino = match client.getattr(root_fid) {
+            Ok(attr) => attr.ino,
+            Err(err) => {
+                client.clunk(root_fid);
+                return Err(err);
+            }
+        };
+
+        let fs = Arc::new(Self {
+            client: Mutex::new(client),
+            root_fid,
+            root_dir: OnceCell::new(),
+        });
+        let _ = fs.root_dir.set(DirEntry::new_dir(
+            |this| {
+                DirNode::new(Inode::new(
+                    fs.clone(),
+                    root_fid,
+                    root_ino,
+                    NodeType::Directory,
+                    Some(this),
+                ))
+            },
+            Reference::root(),
+        ));
+        Ok(Filesystem::new(fs))
+    }
+
+    pub(crate) fn lock(&self) -> MutexGuard<M, Client> {
+        self.client.lock()
+    }
+}
+
+unsafe impl<M> Send for NinePFilesystem<M> {}
+unsafe impl<M> Sync for NinePFilesystem<M> {}
+
+impl<M: RawMutex + 'static> FilesystemOps<M> for NinePFilesystem<M> {
+    fn name(&self) -> &str {
+        "9p"
+    }
+
+    fn root_dir(&self) -> DirEntry<M> {
+        self.root_dir.get().unwrap().clone()
+    }
+
+    fn stat(&self) -> VfsResult<StatFs> {
+        let stat = self.lock().statfs(self.root_fid)?;
+        Ok(StatFs {
+            fs_type: stat.fs_type as _,
+            block_size: stat.block_size as _,
+            blocks: stat.blocks,
+            blocks_free: stat.blocks_free,
+            blocks_available: stat.blocks_available,
+
+            file_count: stat.files as _,
+            free_file_count: stat.files_free as _,
+
+            name_length: stat.name_len as _,
+            fragment_size: 0,
+            mount_flags: 0,
+        })
+    }
+}
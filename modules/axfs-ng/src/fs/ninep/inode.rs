@@ -0,0 +1,393 @@
+use core::{
+    any::Any,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use alloc::{borrow::ToOwned, string::String, sync::Arc};
+use axfs_ng_vfs::{
+    DeviceId, DirEntry, DirEntrySink, DirNode, DirNodeOps, FileNode, FileNodeOps, FilesystemOps,
+    Metadata, MetadataUpdate, NodeOps, NodePermission, NodeType, Reference, VfsError, VfsResult,
+    WeakDirEntry,
+};
+use lock_api::{Mutex, RawMutex};
+
+use super::{NinePFilesystem, client::Client, proto::*};
+
+pub struct Inode<M: RawMutex + 'static> {
+    fs: Arc<NinePFilesystem<M>>,
+    /// The fid referring to the file, which is never opened so that other
+    /// fids can be walked from it. For a symlink whose target is not set yet,
+    /// it refers to the parent directory.
+    fid: u32,
+    ino: AtomicU64,
+    node_type: NodeType,
+    this: Option<WeakDirEntry<M>>,
+    /// The fid opened for I/O, and whether it's opened for writing.
+    io_fid: Mutex<M, Option<(u32, bool)>>,
+    /// The name of a symlink to create when its target is set.
+    pending_symlink: Mutex<M, Option<String>>,
+}
+impl<M: RawMutex + Send + Sync + 'static> Inode<M> {
+    pub(crate) fn new(
+        fs: Arc<NinePFilesystem<M>>,
+        fid: u32,
+        ino: u64,
+        node_type: NodeType,
+        this: Option<WeakDirEntry<M>>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            fs,
+            fid,
+            ino: AtomicU64::new(ino),
+            node_type,
+            this,
+            io_fid: Mutex::new(None),
+            pending_symlink: Mutex::new(None),
+        })
+    }
+
+    /// Returns the fid referring to the file, or `ENOENT` if it's a symlink
+    /// not created yet.
+    fn fid(&self) -> VfsResult<u32> {
+        if self.pending_symlink.lock().is_some() {
+            return Err(VfsError::ENOENT);
+        }
+        Ok(self.fid)
+    }
+
+    /// Returns the fid opened for reading, or for writing if `write` is set.
+    fn open_io(&self, write: bool) -> VfsResult<u32> {
+        let fid = self.fid()?;
+        let mut io_fid = self.io_fid.lock();
+        if let Some((io, writable)) = *io_fid
+            && (writable || !write)
+        {
+            return Ok(io);
+        }
+
+        let mut client = self.fs.lock();
+        let (new_fid, _) = client.walk(fid, &[])?;
+        let result = if self.node_type == NodeType::Directory {
+            client.lopen(new_fid, O_RDONLY | O_DIRECTORY).map(|_| false)
+        } else {
+            // Files are opened for writing if possible, so that they are not
+            // reopened when they are written later.
+            client.lopen(new_fid, O_RDWR).map(|_| true).or_else(|err| {
+                if write {
+                    Err(err)
+                } else {
+                    client.lopen(new_fid, O_RDONLY).map(|_| false)
+                }
+            })
+        };
+        match result {
+            Ok(writable) => {
+                if let Some((old, _)) = io_fid.replace((new_fid, writable)) {
+                    client.clunk(old);
+                }
+                Ok(new_fid)
+            }
+            Err(err) => {
+                client.clunk(new_fid);
+                Err(err)
+            }
+        }
+    }
+
+    fn create_entry(&self, fid: u32, ino: u64, node_type: NodeType, name: &str) -> DirEntry<M> {
+        let reference = Reference::new(
+            self.this.as_ref().and_then(WeakDirEntry::upgrade),
+            name.to_owned(),
+        );
+        if node_type == NodeType::Directory {
+            DirEntry::new_dir(
+                |this| DirNode::new(Inode::new(self.fs.clone(), fid, ino, node_type, Some(this))),
+                reference,
+            )
+        } else {
+            DirEntry::new_file(
+                FileNode::new(Inode::new(self.fs.clone(), fid, ino, node_type, None)),
+                node_type,
+                reference,
+            )
+        }
+    }
+
+    fn lookup_locked(&self, client: &mut Client, dir: u32, name: &str) -> VfsResult<DirEntry<M>> {
+        let (fid, _) = client.walk(dir, &[name])?;
+        match client.getattr(fid) {
+            Ok(attr) => Ok(self.create_entry(fid, attr.ino, mode_to_type(attr.mode), name)),
+            Err(err) => {
+                client.clunk(fid);
+                Err(err)
+            }
+        }
+    }
+}
+
+impl<M: RawMutex + Send + Sync + 'static> NodeOps<M> for Inode<M> {
+    fn inode(&self) -> u64 {
+        self.ino.load(Ordering::Acquire)
+    }
+
+    fn metadata(&self) -> VfsResult<Metadata> {
+        let fid = self.fid()?;
+        let attr = self.fs.lock().getattr(fid)?;
+        Ok(Metadata {
+            inode: attr.ino,
+            device: 0,
+            nlink: attr.nlink as _,
+            mode: NodePermission::from_bits_truncate(attr.mode as u16),
+            node_type: mode_to_type(attr.mode),
+            uid: attr.uid,
+            gid: attr.gid,
+            size: attr.size,
+            block_size: attr.block_size as _,
+            blocks: attr.blocks,
+            rdev: DeviceId::default(),
+            atime: attr.atime,
+            mtime: attr.mtime,
+            ctime: attr.ctime,
+        })
+    }
+
+    fn update_metadata(&self, update: MetadataUpdate) -> VfsResult<()> {
+        let fid = self.fid()?;
+        let mut attr = SetAttr::default();
+        if let Some(mode) = update.mode {
+            attr.valid |= SETATTR_MODE;
+            attr.mode = mode.bits() as u32;
+        }
+        if let Some((uid, gid)) = update.owner {
+            attr.valid |= SETATTR_UID | SETATTR_GID;
+            attr.uid = uid as _;
+            attr.gid = gid as _;
+        }
+        if let Some(atime) = update.atime {
+            attr.valid |= SETATTR_ATIME | SETATTR_ATIME_SET;
+            attr.atime = atime;
+        }
+        if let Some(mtime) = update.mtime {
+            attr.valid |= SETATTR_MTIME | SETATTR_MTIME_SET;
+            attr.mtime = mtime;
+        }
+        if attr.valid == 0 {
+            return Ok(());
+        }
+        self.fs.lock().setattr(fid, &attr)
+    }
+
+    fn len(&self) -> VfsResult<u64> {
+        let fid = self.fid()?;
+        Ok(self.fs.lock().getattr(fid)?.size)
+    }
+
+    fn filesystem(&self) -> &dyn FilesystemOps<M> {
+        &*self.fs
+    }
+
+    fn sync(&self, data_only: bool) -> VfsResult<()> {
+        match *self.io_fid.lock() {
+            Some((io, _)) => self.fs.lock().fsync(io, data_only),
+            None => Ok(()),
+        }
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+}
+
+impl<M: RawMutex + Send + Sync + 'static> FileNodeOps<M> for Inode<M> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> VfsResult<usize> {
+        if self.node_type == NodeType::Symlink {
+            let fid = self.fid()?;
+            let target = self.fs.lock().readlink(fid)?;
+            let target = target.as_bytes();
+            let start = (offset as usize).min(target.len());
+            let len = buf.len().min(target.len() - start);
+            buf[..len].copy_from_slice(&target[start..start + len]);
+            return Ok(len);
+        }
+        let io = self.open_io(false)?;
+        self.fs.lock().read(io, offset, buf)
+    }
+
+    fn write_at(&self, buf: &[u8], offset: u64) -> VfsResult<usize> {
+        let io = self.open_io(true)?;
+        self.fs.lock().write(io, offset, buf)
+    }
+
+    fn append(&self, buf: &[u8]) -> VfsResult<(usize, u64)> {
+        let fid = self.fid()?;
+        let io = self.open_io(true)?;
+        let mut client = self.fs.lock();
+        let length = client.getattr(fid)?.size;
+        let written = client.write(io, length, buf)?;
+        Ok((written, length + written as u64))
+    }
+
+    fn set_len(&self, len: u64) -> VfsResult<()> {
+        let fid = self.fid()?;
+        let attr = SetAttr {
+            valid: SETATTR_SIZE,
+            size: len,
+            ..Default::default()
+        };
+        self.fs.lock().setattr(fid, &attr)
+    }
+
+    fn set_symlink(&self, target: &str) -> VfsResult<()> {
+        let mut pending = self.pending_symlink.lock();
+        // The target of an existing symlink can not be changed in place.
+        let Some(name) = pending.as_deref() else {
+            return Err(VfsError::EINVAL);
+        };
+        let mut client = self.fs.lock();
+        let qid = client.symlink(self.fid, name, target)?;
+        client.walk_in_place(self.fid, name)?;
+        self.ino.store(qid.path, Ordering::Release);
+        *pending = None;
+        Ok(())
+    }
+}
+
+impl<M: RawMutex + Send + Sync + 'static> DirNodeOps<M> for Inode<M> {
+    fn read_dir(&self, offset: u64, sink: &mut dyn DirEntrySink) -> VfsResult<usize> {
+        let io = self.open_io(false)?;
+        let mut client = self.fs.lock();
+        let mut offset = offset;
+        let mut count = 0;
+        loop {
+            let mut full = false;
+            let read = client.readdir(io, offset, |name, qid, ty, next_offset| {
+                if !sink.accept(name, qid.path, dirent_to_type(ty), next_offset) {
+                    full = true;
+                    return false;
+                }
+                offset = next_offset;
+                count += 1;
+                true
+            })?;
+            if read == 0 || full {
+                break;
+            }
+        }
+        Ok(count)
+    }
+
+    fn lookup(&self, name: &str) -> VfsResult<DirEntry<M>> {
+        let dir = self.fid()?;
+        let mut client = self.fs.lock();
+        self.lookup_locked(&mut client, dir, name)
+    }
+
+    fn create(
+        &self,
+        name: &str,
+        node_type: NodeType,
+        permission: NodePermission,
+    ) -> VfsResult<DirEntry<M>> {
+        let dir = self.fid()?;
+        let mode = permission.bits() as u32;
+        let mut client = self.fs.lock();
+        match node_type {
+            NodeType::RegularFile => {
+                // The new file is opened by `Tlcreate`, which is kept for I/O.
+                let (io, _) = client.walk(dir, &[])?;
+                let entry = client
+                    .lcreate(io, name, O_RDWR | O_CREAT | O_EXCL, mode)
+                    .and_then(|_| client.walk(dir, &[name]));
+                let (fid, qid) = match entry {
+                    Ok((fid, Some(qid))) => (fid, qid),
+                    Ok((fid, None)) => {
+                        client.clunk(fid);
+                        client.clunk(io);
+                        return Err(VfsError::EIO);
+                    }
+                    Err(err) => {
+                        client.clunk(io);
+                        return Err(err);
+                    }
+                };
+                let reference = Reference::new(
+                    self.this.as_ref().and_then(WeakDirEntry::upgrade),
+                    name.to_owned(),
+                );
+                let inode = Inode::new(self.fs.clone(), fid, qid.path, node_type, None);
+                *inode.io_fid.lock() = Some((io, true));
+                return Ok(DirEntry::new_file(
+                    FileNode::new(inode),
+                    node_type,
+                    reference,
+                ));
+            }
+            NodeType::Directory => {
+                client.mkdir(dir, name, mode)?;
+            }
+            NodeType::Symlink => {
+                // Symlinks are created with their targets by `Tsymlink`, so it's
+                // deferred until the target is set.
+                let (fid, _) = client.walk(dir, &[])?;
+                let inode = Inode::new(self.fs.clone(), fid, 0, node_type, None);
+                *inode.pending_symlink.lock() = Some(name.to_owned());
+                let reference = Reference::new(
+                    self.this.as_ref().and_then(WeakDirEntry::upgrade),
+                    name.to_owned(),
+                );
+                return Ok(DirEntry::new_file(
+                    FileNode::new(inode),
+                    node_type,
+                    reference,
+                ));
+            }
+            NodeType::Fifo
+            | NodeType::CharacterDevice
+            | NodeType::BlockDevice
+            | NodeType::Socket => {
+                client.mknod(dir, name, type_to_mode(node_type) | mode)?;
+            }
+            NodeType::Unknown => {
+                return Err(VfsError::EINVAL);
+            }
+        }
+        self.lookup_locked(&mut client, dir, name)
+    }
+
+    fn link(&self, name: &str, node: &DirEntry<M>) -> VfsResult<DirEntry<M>> {
+        let dir = self.fid()?;
+        let node: Arc<Self> = node.as_file()?.downcast().map_err(|_| VfsError::EXDEV)?;
+        let fid = node.fid()?;
+        let mut client = self.fs.lock();
+        client.link(dir, fid, name)?;
+        self.lookup_locked(&mut client, dir, name)
+    }
+
+    fn unlink(&self, name: &str) -> VfsResult<()> {
+        let dir = self.fid()?;
+        let mut client = self.fs.lock();
+        match client.unlinkat(dir, name, 0) {
+            Err(VfsError::EISDIR) => client.unlinkat(dir, name, AT_REMOVEDIR),
+            result => result,
+        }
+    }
+
+    fn rename(&self, src_name: &str, dst_dir: &DirNode<M>, dst_name: &str) -> VfsResult<()> {
+        let src_dir = self.fid()?;
+        let dst_dir: Arc<Self> = dst_dir.downcast().map_err(|_| VfsError::EINVAL)?;
+        let dst_dir = dst_dir.fid()?;
+        self.fs
+            .lock()
+            .renameat(src_dir, src_name, dst_dir, dst_name)
+    }
+}
+
+impl<M: RawMutex + 'static> Drop for Inode<M> {
+    fn drop(&mut self) {
+        let mut client = self.fs.lock();
+        if let Some((io, _)) = self.io_fid.get_mut().take() {
+            client.clunk(io);
+        }
+        client.clunk(self.fid);
+    }
+}
@@ -0,0 +1,20 @@
+//! A 9P2000.L client file system, which accesses a directory shared by the
+//! host (e.g., with QEMU `-virtfs`).
+
+mod client;
+mod fs;
+mod inode;
+mod proto;
+
+pub use fs::*;
+pub use inode::*;
+
+use axdriver::{AxNinePDevice, prelude::*, registry, registry::DeviceRef};
+
+/// Finds the registered 9P transport exporting the file system with the given
+/// mount tag.
+pub fn find_device(tag: &str) -> Option<DeviceRef<AxNinePDevice>> {
+    registry::devices::<AxNinePDevice>()
+        .into_iter()
+        .find(|dev| dev.lock().mount_tag() == tag)
+}
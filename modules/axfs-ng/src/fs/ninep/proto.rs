@@ -0,0 +1,423 @@
+//! Encoding and decoding of 9P2000.L messages.
+//!
+//! A message is `size[4] type[1] tag[2]` followed by the fields of its type,
+//! all in little endian. Strings are encoded as `len[2]` followed by the bytes.
+
+use core::time::Duration;
+
+use alloc::vec::Vec;
+use axfs_ng_vfs::{NodeType, VfsError, VfsResult};
+
+pub const VERSION: &str = "9P2000.L";
+
+pub const NOTAG: u16 = !0;
+pub const NOFID: u32 = !0;
+
+/// The size of `size[4] type[1] tag[2]`.
+pub const HEADER_SIZE: usize = 7;
+/// The space reserved for the headers of messages carrying data, e.g., `Twrite`
+/// and `Rreaddir`.
+pub const IO_HEADER_SIZE: usize = 24;
+
+// Types of T-messages, each of which is answered by the R-message of the next
+// type, or by `Rlerror`.
+pub const RLERROR: u8 = 7;
+pub const TSTATFS: u8 = 8;
+pub const TLOPEN: u8 = 12;
+pub const TLCREATE: u8 = 14;
+pub const TSYMLINK: u8 = 16;
+pub const TMKNOD: u8 = 18;
+pub const TREADLINK: u8 = 22;
+pub const TGETATTR: u8 = 24;
+pub const TSETATTR: u8 = 26;
+pub const TREADDIR: u8 = 40;
+pub const TFSYNC: u8 = 50;
+pub const TLINK: u8 = 70;
+pub const TMKDIR: u8 = 72;
+pub const TRENAMEAT: u8 = 74;
+pub const TUNLINKAT: u8 = 76;
+pub const TVERSION: u8 = 100;
+pub const TATTACH: u8 = 104;
+pub const TWALK: u8 = 110;
+pub const TREAD: u8 = 116;
+pub const TWRITE: u8 = 118;
+pub const TCLUNK: u8 = 120;
+
+/// The type bit of directories in [`Qid::ty`].
+pub const QTDIR: u8 = 0x80;
+
+// Flags of `Tlopen` and `Tlcreate`, which are the same as Linux.
+pub const O_RDONLY: u32 = 0;
+pub const O_RDWR: u32 = 2;
+pub const O_CREAT: u32 = 0o100;
+pub const O_EXCL: u32 = 0o200;
+pub const O_DIRECTORY: u32 = 0o200000;
+
+/// The flag of `Tunlinkat` to remove a directory.
+pub const AT_REMOVEDIR: u32 = 0x200;
+
+/// The fields of `Rgetattr` in [`Attr`].
+pub const GETATTR_BASIC: u64 = 0x7ff;
+
+// Fields to set in `Tsetattr`.
+pub const SETATTR_MODE: u32 = 0x1;
+pub const SETATTR_UID: u32 = 0x2;
+pub const SETATTR_GID: u32 = 0x4;
+pub const SETATTR_SIZE: u32 = 0x8;
+pub const SETATTR_ATIME: u32 = 0x10;
+pub const SETATTR_MTIME: u32 = 0x20;
+pub const SETATTR_ATIME_SET: u32 = 0x80;
+pub const SETATTR_MTIME_SET: u32 = 0x100;
+
+/// The unique identification of a file on the server.
+#[derive(Debug, Clone, Copy)]
+pub struct Qid {
+    pub ty: u8,
+    /// Unique among all files of the server, used as the inode number.
+    pub path: u64,
+}
+
+/// The attributes of a file, from `Rgetattr`.
+pub struct Attr {
+    pub ino: u64,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub nlink: u64,
+    pub size: u64,
+    pub block_size: u64,
+    pub blocks: u64,
+    pub atime: Duration,
+    pub mtime: Duration,
+    pub ctime: Duration,
+}
+
+/// The attributes to set in `Tsetattr`.
+#[derive(Default)]
+pub struct SetAttr {
+    /// `SETATTR_*` flags of the fields to set.
+    pub valid: u32,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub size: u64,
+    pub atime: Duration,
+    pub mtime: Duration,
+}
+
+/// The statistics of a file system, from `Rstatfs`.
+pub struct StatFs {
+    pub fs_type: u32,
+    pub block_size: u32,
+    pub blocks: u64,
+    pub blocks_free: u64,
+    pub blocks_available: u64,
+    pub files: u64,
+    pub files_free: u64,
+    pub name_len: u32,
+}
+
+/// Returns the type of the node with the given mode.
+pub fn mode_to_type(mode: u32) -> NodeType {
+    match mode & 0o170000 {
+        0o010000 => NodeType::Fifo,
+        0o020000 => NodeType::CharacterDevice,
+        0o040000 => NodeType::Directory,
+        0o060000 => NodeType::BlockDevice,
+        0o100000 => NodeType::RegularFile,
+        0o120000 => NodeType::Symlink,
+        0o140000 => NodeType::Socket,
+        _ => NodeType::Unknown,
+    }
+}
+
+/// Returns the mode bits of the given node type.
+pub fn type_to_mode(ty: NodeType) -> u32 {
+    match ty {
+        NodeType::Fifo => 0o010000,
+        NodeType::CharacterDevice => 0o020000,
+        NodeType::Directory => 0o040000,
+        NodeType::BlockDevice => 0o060000,
+        NodeType::RegularFile => 0o100000,
+        NodeType::Symlink => 0o120000,
+        NodeType::Socket => 0o140000,
+        NodeType::Unknown => 0,
+    }
+}
+
+/// Returns the type of the node with the given directory entry type (`DT_*`).
+pub fn dirent_to_type(ty: u8) -> NodeType {
+    match ty {
+        1 => NodeType::Fifo,
+        2 => NodeType::CharacterDevice,
+        4 => NodeType::Directory,
+        6 => NodeType::BlockDevice,
+        8 => NodeType::RegularFile,
+        10 => NodeType::Symlink,
+        12 => NodeType::Socket,
+        _ => NodeType::Unknown,
+    }
+}
+
+/// Writes the fields of a message.
+pub struct Encoder<'a>(pub &'a mut Vec<u8>);
+
+impl Encoder<'_> {
+    pub fn u8(&mut self, v: u8) -> &mut Self {
+        self.0.push(v);
+        self
+    }
+
+    pub fn u16(&mut self, v: u16) -> &mut Self {
+        self.0.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    pub fn u32(&mut self, v: u32) -> &mut Self {
+        self.0.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    pub fn u64(&mut self, v: u64) -> &mut Self {
+        self.0.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    pub fn str(&mut self, s: &str) -> &mut Self {
+        self.u16(s.len() as u16);
+        self.0.extend_from_slice(s.as_bytes());
+        self
+    }
+
+    pub fn bytes(&mut self, data: &[u8]) -> &mut Self {
+        self.0.extend_from_slice(data);
+        self
+    }
+
+    pub fn time(&mut self, time: Duration) -> &mut Self {
+        self.u64(time.as_secs()).u64(time.subsec_nanos() as u64)
+    }
+}
+
+/// Reads the fields of a message.
+pub struct Decoder<'a>(pub &'a [u8]);
+
+impl<'a> Decoder<'a> {
+    pub fn bytes(&mut self, len: usize) -> VfsResult<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(VfsError::EIO);
+        }
+        let (data, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(data)
+    }
+
+    fn array<const N: usize>(&mut self) -> VfsResult<[u8; N]> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    pub fn u8(&mut self) -> VfsResult<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> VfsResult<u16> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    pub fn u32(&mut self) -> VfsResult<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    pub fn u64(&mut self) -> VfsResult<u64> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    pub fn str(&mut self) -> VfsResult<&'a str> {
+        let len = self.u16()? as usize;
+        core::str::from_utf8(self.bytes(len)?).map_err(|_| VfsError::EINVAL)
+    }
+
+    pub fn time(&mut self) -> VfsResult<Duration> {
+        let secs = self.u64()?;
+        let nanos = self.u64()?;
+        Ok(Duration::new(secs, nanos as u32))
+    }
+
+    pub fn qid(&mut self) -> VfsResult<Qid> {
+        let ty = self.u8()?;
+        let _version = self.u32()?;
+        let path = self.u64()?;
+        Ok(Qid { ty, path })
+    }
+
+    /// Decodes an entry in the data of `Rreaddir`, and returns its name, qid,
+    /// type (`DT_*`) and the offset of the next entry.
+    pub fn dirent(&mut self) -> VfsResult<(&'a str, Qid, u8, u64)> {
+        let qid = self.qid()?;
+        let next_offset = self.u64()?;
+        let ty = self.u8()?;
+        let name = self.str()?;
+        Ok((name, qid, ty, next_offset))
+    }
+
+    pub fn attr(&mut self) -> VfsResult<Attr> {
+        let _valid = self.u64()?;
+        let qid = self.qid()?;
+        let mode = self.u32()?;
+        let uid = self.u32()?;
+        let gid = self.u32()?;
+        let nlink = self.u64()?;
+        let _rdev = self.u64()?;
+        let size = self.u64()?;
+        let block_size = self.u64()?;
+        let blocks = self.u64()?;
+        let atime = self.time()?;
+        let mtime = self.time()?;
+        let ctime = self.time()?;
+        // btime, gen and data_version are not used.
+        Ok(Attr {
+            ino: qid.path,
+            mode,
+            uid,
+            gid,
+            nlink,
+            size,
+            block_size,
+            blocks,
+            atime,
+            mtime,
+            ctime,
+        })
+    }
+
+    pub fn statfs(&mut self) -> VfsResult<StatFs> {
+        let fs_type = self.u32()?;
+        let block_size = self.u32()?;
+        let blocks = self.u64()?;
+        let blocks_free = self.u64()?;
+        let blocks_available = self.u64()?;
+        let files = self.u64()?;
+        let files_free = self.u64()?;
+        let _fsid = self.u64()?;
+        let name_len = self.u32()?;
+        Ok(StatFs {
+            fs_type,
+            block_size,
+            blocks,
+            blocks_free,
+            blocks_available,
+            files,
+            files_free,
+            name_len,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    fn encode_qid(m: &mut Encoder, ty: u8, path: u64) {
+        m.u8(ty).u32(0).u64(path);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut buf = Vec::new();
+        Encoder(&mut buf)
+            .u8(0x12)
+            .u16(0x1234)
+            .u32(0x1234_5678)
+            .u64(0x1234_5678_9abc_def0)
+            .str("name")
+            .str("")
+            .bytes(b"data")
+            .time(Duration::new(5, 6));
+        assert_eq!(&buf[1..3], &[0x34, 0x12]); // little endian
+        assert_eq!(&buf[15..21], b"\x04\x00name");
+
+        let mut m = Decoder(&buf);
+        assert_eq!(m.u8().unwrap(), 0x12);
+        assert_eq!(m.u16().unwrap(), 0x1234);
+        assert_eq!(m.u32().unwrap(), 0x1234_5678);
+        assert_eq!(m.u64().unwrap(), 0x1234_5678_9abc_def0);
+        assert_eq!(m.str().unwrap(), "name");
+        assert_eq!(m.str().unwrap(), "");
+        assert_eq!(m.bytes(4).unwrap(), b"data");
+        assert_eq!(m.time().unwrap(), Duration::new(5, 6));
+        assert!(m.0.is_empty());
+    }
+
+    #[test]
+    fn truncated() {
+        assert!(matches!(Decoder(&[1, 2, 3]).u32(), Err(VfsError::EIO)));
+        // The length of the string exceeds the message.
+        assert!(matches!(Decoder(&[5, 0, b'a']).str(), Err(VfsError::EIO)));
+        assert!(matches!(
+            Decoder(&[2, 0, 0xff, 0xfe]).str(),
+            Err(VfsError::EINVAL)
+        ));
+
+        let mut m = Decoder(&[1, 2]);
+        assert!(m.bytes(3).is_err());
+        // Nothing is consumed on errors.
+        assert_eq!(m.bytes(2).unwrap(), &[1, 2]);
+    }
+
+    #[test]
+    fn getattr() {
+        let mut buf = Vec::new();
+        let m = &mut Encoder(&mut buf);
+        m.u64(GETATTR_BASIC);
+        encode_qid(m, 0, 42);
+        m.u32(0o100644) // mode
+            .u32(1000) // uid
+            .u32(100) // gid
+            .u64(1) // nlink
+            .u64(0) // rdev
+            .u64(12345) // size
+            .u64(4096) // block size
+            .u64(32) // blocks
+            .time(Duration::new(1, 2))
+            .time(Duration::new(3, 4))
+            .time(Duration::new(5, 6))
+            .time(Duration::ZERO) // btime
+            .u64(0) // gen
+            .u64(0); // data version
+
+        let attr = Decoder(&buf).attr().unwrap();
+        assert_eq!(attr.ino, 42);
+        assert_eq!(attr.mode, 0o100644);
+        assert!(mode_to_type(attr.mode) == NodeType::RegularFile);
+        assert_eq!((attr.uid, attr.gid, attr.nlink), (1000, 100, 1));
+        assert_eq!((attr.size, attr.block_size, attr.blocks), (12345, 4096, 32));
+        assert_eq!(attr.atime, Duration::new(1, 2));
+        assert_eq!(attr.mtime, Duration::new(3, 4));
+        assert_eq!(attr.ctime, Duration::new(5, 6));
+
+        assert!(Decoder(&buf[..40]).attr().is_err());
+    }
+
+    #[test]
+    fn readdir() {
+        let mut buf = Vec::new();
+        let m = &mut Encoder(&mut buf);
+        encode_qid(m, QTDIR, 1);
+        m.u64(1).u8(4).str("..");
+        encode_qid(m, 0, 2);
+        m.u64(2).u8(8).str("file");
+
+        let mut m = Decoder(&buf);
+        let (name, qid, ty, next_offset) = m.dirent().unwrap();
+        assert_eq!((name, qid.ty, qid.path, next_offset), ("..", QTDIR, 1, 1));
+        assert!(dirent_to_type(ty) == NodeType::Directory);
+        let (name, qid, ty, next_offset) = m.dirent().unwrap();
+        assert_eq!((name, qid.ty, qid.path, next_offset), ("file", 0, 2, 2));
+        assert!(dirent_to_type(ty) == NodeType::RegularFile);
+        assert!(m.0.is_empty());
+
+        assert!(Decoder(&buf[..20]).dirent().is_err());
+    }
+}
@@ -6,4 +6,5 @@ mod disk;
 pub mod fs;
 mod highlevel;
 
+pub use disk::{BlockDevice, DeviceDisk};
 pub use highlevel::*;
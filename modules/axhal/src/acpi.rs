@@ -0,0 +1,587 @@
+//! ACPI table parsing (x86_64 only).
+//!
+//! The tables are parsed once at boot, when all the low 4 GiB of physical
+//! memory is still mapped, so that the firmware memory holding them needs not
+//! to be mapped later. Only the information used by the kernel is kept.
+//!
+//! Supported tables: RSDP, RSDT/XSDT, MADT, HPET, MCFG, FADT and the `\_S5`
+//! object in the DSDT.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use lazyinit::LazyInit;
+use x86_64::instructions::port::Port;
+
+use crate::fdt::FixedVec;
+use crate::mem::phys_to_virt;
+
+/// The maximum number of CPUs (local APICs) recorded from the MADT.
+pub const MAX_CPUS: usize = 256;
+/// The maximum number of I/O APICs recorded from the MADT.
+pub const MAX_IO_APICS: usize = 8;
+/// The maximum number of interrupt source overrides recorded from the MADT.
+pub const MAX_IRQ_OVERRIDES: usize = 16;
+
+const RSDP_SIGNATURE: &[u8; 8] = b"RSD PTR ";
+/// The end of the physical memory mapped by the boot page table.
+const BOOT_MAPPED_MEMORY_END: usize = 0x1_0000_0000;
+const SDT_HEADER_SIZE: usize = 36;
+
+const MADT_LOCAL_APIC: u8 = 0;
+const MADT_IO_APIC: u8 = 1;
+const MADT_IRQ_OVERRIDE: u8 = 2;
+const MADT_LOCAL_X2APIC: u8 = 9;
+const MADT_ENABLED: u32 = 1 << 0;
+
+const FADT_RESET_REG_SUP: u32 = 1 << 10;
+const GAS_SYSTEM_IO: u8 = 1;
+
+const PM1_SCI_EN: u16 = 1 << 0;
+const PM1_SLP_EN: u16 = 1 << 13;
+const PM1_SLP_TYP_SHIFT: u16 = 10;
+
+static ACPI_INFO: LazyInit<AcpiInfo> = LazyInit::new();
+
+/// An I/O APIC described in the MADT.
+#[derive(Debug, Clone, Copy)]
+pub struct IoApicInfo {
+    /// The I/O APIC ID.
+    pub id: u8,
+    /// The physical address of the registers.
+    pub paddr: usize,
+    /// The first global system interrupt (GSI) number it handles.
+    pub gsi_base: u32,
+}
+
+/// An ISA IRQ that is not identity-mapped to a GSI, described in the MADT.
+#[derive(Debug, Clone, Copy)]
+pub struct IrqOverride {
+    /// The ISA IRQ number.
+    pub source: u8,
+    /// The GSI number it's connected to.
+    pub gsi: u32,
+    /// The MPS INTI flags (polarity and trigger mode).
+    pub flags: u16,
+}
+
+/// The PCIe ECAM space of the PCI segment group 0, described in the MCFG.
+#[derive(Debug, Clone, Copy)]
+pub struct PciEcam {
+    /// The physical address of the ECAM space.
+    pub paddr: usize,
+    /// The first bus number decoded by the host bridge.
+    pub bus_start: u8,
+    /// The last bus number decoded by the host bridge.
+    pub bus_end: u8,
+}
+
+impl PciEcam {
+    /// The size in bytes of the ECAM space of the decoded buses.
+    pub fn size(&self) -> usize {
+        (self.bus_end as usize + 1) << 20
+    }
+}
+
+/// Platform resources discovered from the ACPI tables.
+#[derive(Debug)]
+pub struct AcpiInfo {
+    revision: u8,
+    local_apic_paddr: usize,
+    apic_ids: FixedVec<u32, MAX_CPUS>,
+    io_apics: FixedVec<IoApicInfo, MAX_IO_APICS>,
+    irq_overrides: FixedVec<IrqOverride, MAX_IRQ_OVERRIDES>,
+    hpet_paddr: Option<usize>,
+    pci_ecam: Option<PciEcam>,
+    smi_cmd: u16,
+    acpi_enable: u8,
+    pm1a_cnt: u16,
+    pm1b_cnt: u16,
+    s5_sleep_types: Option<(u16, u16)>,
+    reset_port: Option<(u16, u8)>,
+}
+
+impl AcpiInfo {
+    fn new(revision: u8) -> Self {
+        Self {
+            revision,
+            local_apic_paddr: 0,
+            apic_ids: FixedVec::new(0),
+            io_apics: FixedVec::new(IoApicInfo {
+                id: 0,
+                paddr: 0,
+                gsi_base: 0,
+            }),
+            irq_overrides: FixedVec::new(IrqOverride {
+                source: 0,
+                gsi: 0,
+                flags: 0,
+            }),
+            hpet_paddr: None,
+            pci_ecam: None,
+            smi_cmd: 0,
+            acpi_enable: 0,
+            pm1a_cnt: 0,
+            pm1b_cnt: 0,
+            s5_sleep_types: None,
+            reset_port: None,
+        }
+    }
+
+    /// The revision of the RSDP (0 for ACPI 1.0, 2 for ACPI 2.0+).
+    pub fn revision(&self) -> u8 {
+        self.revision
+    }
+
+    /// The physical address of the local APIC registers.
+    pub fn local_apic_paddr(&self) -> usize {
+        self.local_apic_paddr
+    }
+
+    /// The number of enabled CPUs.
+    pub fn cpu_count(&self) -> usize {
+        self.apic_ids.as_slice().len()
+    }
+
+    /// The APIC IDs of the enabled CPUs, indexed by the logical CPU IDs.
+    pub fn apic_ids(&self) -> &[u32] {
+        self.apic_ids.as_slice()
+    }
+
+    /// The I/O APICs.
+    pub fn io_apics(&self) -> &[IoApicInfo] {
+        self.io_apics.as_slice()
+    }
+
+    /// The interrupt source overrides of ISA IRQs.
+    pub fn irq_overrides(&self) -> &[IrqOverride] {
+        self.irq_overrides.as_slice()
+    }
+
+    /// The physical address of the HPET registers.
+    pub fn hpet_paddr(&self) -> Option<usize> {
+        self.hpet_paddr
+    }
+
+    /// The PCIe ECAM space of the PCI segment group 0.
+    pub fn pci_ecam(&self) -> Option<PciEcam> {
+        self.pci_ecam
+    }
+
+    /// Returns an iterator over the MMIO regions `(paddr, size)` of the
+    /// described devices.
+    pub fn mmio_regions(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.io_apics
+            .as_slice()
+            .iter()
+            .map(|io_apic| (io_apic.paddr, 0x1000))
+            .chain(self.hpet_paddr.map(|paddr| (paddr, 0x1000)))
+            .chain(self.pci_ecam.map(|ecam| (ecam.paddr, ecam.size())))
+    }
+}
+
+/// Returns the platform resources discovered from the ACPI tables.
+///
+/// Returns `None` if no valid RSDP is found.
+pub fn info() -> Option<&'static AcpiInfo> {
+    ACPI_INFO.get()
+}
+
+/// Returns the logical CPU ID of the given APIC ID.
+#[allow(dead_code)]
+pub(crate) fn cpu_id_of(apic_id: u32) -> Option<usize> {
+    info()?.apic_ids().iter().position(|&id| id == apic_id)
+}
+
+/// Returns the APIC ID of the given logical CPU ID.
+#[allow(dead_code)]
+pub(crate) fn apic_id_of(cpu_id: usize) -> Option<u32> {
+    info()?.apic_ids().get(cpu_id).copied()
+}
+
+/// Returns the byte slice of the given physical memory.
+///
+/// # Safety
+///
+/// The memory must be readable through the linear mapping.
+unsafe fn phys_slice(paddr: usize, len: usize) -> &'static [u8] {
+    unsafe { core::slice::from_raw_parts(phys_to_virt(pa!(paddr)).as_ptr(), len) }
+}
+
+fn read_u8(data: &[u8], offset: usize) -> Option<u8> {
+    data.get(offset).copied()
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset + 8)?;
+    Some(u64::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn checksum_ok(data: &[u8]) -> bool {
+    data.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)) == 0
+}
+
+/// Searches the RSDP in the first 1 KiB of the EBDA and in the BIOS ROM.
+fn find_rsdp() -> Option<&'static [u8]> {
+    let ebda = unsafe { phys_slice(0x40e, 2) };
+    let ebda_paddr = (read_u16(ebda, 0)? as usize) << 4;
+    let search = |start: usize, end: usize| {
+        (start..end).step_by(16).find_map(|paddr| {
+            let rsdp = unsafe { phys_slice(paddr, 20) };
+            (rsdp.starts_with(RSDP_SIGNATURE) && checksum_ok(rsdp)).then_some(paddr)
+        })
+    };
+    let paddr = (ebda_paddr != 0)
+        .then(|| search(ebda_paddr, ebda_paddr + 0x400))
+        .flatten()
+        .or_else(|| search(0xe_0000, 0x10_0000))?;
+    let rsdp = unsafe { phys_slice(paddr, 36) };
+    if read_u8(rsdp, 15)? >= 2 {
+        // ACPI 2.0+: the extended checksum covers the whole structure.
+        let len = read_u32(rsdp, 20)? as usize;
+        let rsdp = unsafe { phys_slice(paddr, len) };
+        checksum_ok(rsdp).then_some(rsdp)
+    } else {
+        Some(unsafe { phys_slice(paddr, 20) })
+    }
+}
+
+/// Returns the system description table at `paddr` if it's valid.
+fn sdt(paddr: usize) -> Option<&'static [u8]> {
+    if paddr == 0 || paddr >= BOOT_MAPPED_MEMORY_END {
+        return None;
+    }
+    let header = unsafe { phys_slice(paddr, SDT_HEADER_SIZE) };
+    let len = read_u32(header, 4)? as usize;
+    if len < SDT_HEADER_SIZE {
+        return None;
+    }
+    let table = unsafe { phys_slice(paddr, len) };
+    checksum_ok(table).then_some(table)
+}
+
+/// Returns an iterator over the tables referred by the RSDT or XSDT.
+fn tables(rsdp: &[u8]) -> impl Iterator<Item = &'static [u8]> {
+    let xsdt = read_u64(rsdp, 24).and_then(|paddr| sdt(paddr as usize));
+    let rsdt = read_u32(rsdp, 16).and_then(|paddr| sdt(paddr as usize));
+    let (root, entry_size) = match (xsdt, rsdt) {
+        (Some(xsdt), _) => (xsdt, 8),
+        (None, Some(rsdt)) => (rsdt, 4),
+        (None, None) => (&[][..], 4),
+    };
+    root.get(SDT_HEADER_SIZE..)
+        .unwrap_or_default()
+        .chunks_exact(entry_size)
+        .filter_map(|entry| {
+            let paddr = entry
+                .iter()
+                .rev()
+                .fold(0, |acc, &b| (acc << 8) | b as usize);
+            sdt(paddr)
+        })
+}
+
+fn parse_madt(madt: &[u8], info: &mut AcpiInfo) {
+    info.local_apic_paddr = read_u32(madt, 36).unwrap_or_default() as usize;
+    let mut offset = 44;
+    while let (Some(ty), Some(len)) = (read_u8(madt, offset), read_u8(madt, offset + 1)) {
+        let entry = &madt[offset..madt.len().min(offset + len as usize)];
+        match ty {
+            MADT_LOCAL_APIC => {
+                if read_u32(entry, 4).is_some_and(|flags| flags & MADT_ENABLED != 0) {
+                    info.apic_ids.push(entry[3] as u32);
+                }
+            }
+            MADT_LOCAL_X2APIC => {
+                if read_u32(entry, 8).is_some_and(|flags| flags & MADT_ENABLED != 0) {
+                    info.apic_ids.push(read_u32(entry, 4).unwrap());
+                }
+            }
+            MADT_IO_APIC => {
+                if let (Some(paddr), Some(gsi_base)) = (read_u32(entry, 4), read_u32(entry, 8)) {
+                    info.io_apics.push(IoApicInfo {
+                        id: entry[2],
+                        paddr: paddr as usize,
+                        gsi_base,
+                    });
+                }
+            }
+            MADT_IRQ_OVERRIDE => {
+                if let (Some(gsi), Some(flags)) = (read_u32(entry, 4), read_u16(entry, 8)) {
+                    info.irq_overrides.push(IrqOverride {
+                        source: entry[3],
+                        gsi,
+                        flags,
+                    });
+                }
+            }
+            _ => {}
+        }
+        if len < 2 {
+            break;
+        }
+        offset += len as usize;
+    }
+}
+
+fn parse_mcfg(mcfg: &[u8], info: &mut AcpiInfo) {
+    info.pci_ecam = mcfg
+        .get(44..)
+        .unwrap_or_default()
+        .chunks_exact(16)
+        .find(|entry| read_u16(entry, 8) == Some(0))
+        .map(|entry| PciEcam {
+            paddr: read_u64(entry, 0).unwrap() as usize,
+            bus_start: entry[10],
+            bus_end: entry[11],
+        });
+}
+
+fn parse_fadt(fadt: &[u8], info: &mut AcpiInfo) {
+    info.smi_cmd = read_u32(fadt, 48).unwrap_or_default() as u16;
+    info.acpi_enable = read_u8(fadt, 52).unwrap_or_default();
+    info.pm1a_cnt = read_u32(fadt, 64).unwrap_or_default() as u16;
+    info.pm1b_cnt = read_u32(fadt, 68).unwrap_or_default() as u16;
+
+    let flags = read_u32(fadt, 112).unwrap_or_default();
+    if flags & FADT_RESET_REG_SUP != 0 && read_u8(fadt, 116) == Some(GAS_SYSTEM_IO) {
+        if let (Some(port), Some(value)) = (read_u64(fadt, 120), read_u8(fadt, 128)) {
+            info.reset_port = Some((port as u16, value));
+        }
+    }
+
+    let dsdt_paddr = read_u64(fadt, 140)
+        .filter(|&paddr| paddr != 0)
+        .or_else(|| read_u32(fadt, 40).map(Into::into));
+    if let Some(dsdt) = dsdt_paddr.and_then(|paddr| sdt(paddr as usize)) {
+        info.s5_sleep_types = find_s5_sleep_types(dsdt);
+    }
+}
+
+/// Finds the `SLP_TYPa` and `SLP_TYPb` values of the S5 (soft-off) sleep
+/// state, by looking for the `Name (\_S5, Package () {a, b, ...})` object in
+/// the AML code of the DSDT.
+fn find_s5_sleep_types(dsdt: &[u8]) -> Option<(u16, u16)> {
+    const NAME_OP: u8 = 0x08;
+    const PACKAGE_OP: u8 = 0x12;
+    const BYTE_PREFIX: u8 = 0x0a;
+
+    let aml = dsdt.get(SDT_HEADER_SIZE..)?;
+    let pos = aml.windows(4).enumerate().find_map(|(i, name)| {
+        // `NameOp _S5_` or `NameOp \_S5_`
+        let is_name = match i {
+            0 => false,
+            1 => aml[0] == NAME_OP,
+            _ => aml[i - 1] == NAME_OP || (aml[i - 1] == b'\\' && aml[i - 2] == NAME_OP),
+        };
+        (name == b"_S5_" && is_name && aml.get(i + 4) == Some(&PACKAGE_OP)).then_some(i + 5)
+    })?;
+    // Skip the PkgLength (whose length is encoded in the top 2 bits of the
+    // first byte) and the NumElements.
+    let mut pos = pos + ((*aml.get(pos)? as usize) >> 6) + 2;
+    let mut read_integer = || {
+        if *aml.get(pos)? == BYTE_PREFIX {
+            pos += 1;
+        }
+        let value = *aml.get(pos)?;
+        pos += 1;
+        Some(value as u16)
+    };
+    Some((read_integer()?, read_integer()?))
+}
+
+/// Parses the ACPI tables.
+///
+/// It must be called by the primary CPU at boot. Does nothing if no valid
+/// RSDP is found.
+#[allow(dead_code)]
+pub(crate) fn init() {
+    let Some(rsdp) = find_rsdp() else {
+        return;
+    };
+    let mut info = AcpiInfo::new(rsdp[15]);
+    for table in tables(rsdp) {
+        match &table[..4] {
+            b"APIC" => parse_madt(table, &mut info),
+            b"MCFG" => parse_mcfg(table, &mut info),
+            b"FACP" => parse_fadt(table, &mut info),
+            b"HPET" => info.hpet_paddr = read_u64(table, 44).map(|paddr| paddr as usize),
+            _ => {}
+        }
+    }
+    ACPI_INFO.init_once(info);
+}
+
+/// Switches the system from legacy mode to ACPI mode, if not yet.
+fn enable_acpi_mode(info: &AcpiInfo) {
+    static ENABLED: AtomicBool = AtomicBool::new(false);
+    if ENABLED.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    let mut pm1a_cnt = Port::<u16>::new(info.pm1a_cnt);
+    unsafe {
+        if pm1a_cnt.read() & PM1_SCI_EN != 0 || info.smi_cmd == 0 || info.acpi_enable == 0 {
+            return;
+        }
+        Port::<u8>::new(info.smi_cmd).write(info.acpi_enable);
+        for _ in 0..1_000_000 {
+            if pm1a_cnt.read() & PM1_SCI_EN != 0 {
+                break;
+            }
+            core::hint::spin_loop();
+        }
+    }
+}
+
+/// Powers off the system by entering the S5 sleep state.
+///
+/// Returns if it's not supported.
+#[allow(dead_code)]
+pub(crate) fn poweroff() {
+    let Some(info) = info() else {
+        return;
+    };
+    let Some((slp_typ_a, slp_typ_b)) = info.s5_sleep_types else {
+        return;
+    };
+    if info.pm1a_cnt == 0 {
+        return;
+    }
+    enable_acpi_mode(info);
+    unsafe {
+        Port::<u16>::new(info.pm1a_cnt).write((slp_typ_a << PM1_SLP_TYP_SHIFT) | PM1_SLP_EN);
+        if info.pm1b_cnt != 0 {
+            Port::<u16>::new(info.pm1b_cnt).write((slp_typ_b << PM1_SLP_TYP_SHIFT) | PM1_SLP_EN);
+        }
+    }
+}
+
+/// Resets the system with the FADT reset register, or with the keyboard
+/// controller if it's not supported.
+///
+/// Returns if neither works.
+#[allow(dead_code)]
+pub(crate) fn reset() {
+    if let Some((port, value)) = info().and_then(|info| info.reset_port) {
+        unsafe { Port::<u8>::new(port).write(value) };
+    }
+    // Pulse the CPU reset line.
+    unsafe { Port::<u8>::new(0x64).write(0xfe) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a table with the signature and the body after the header.
+    fn table(signature: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut table = vec![0; SDT_HEADER_SIZE];
+        table[..4].copy_from_slice(signature);
+        table.extend_from_slice(body);
+        let len = table.len() as u32;
+        table[4..8].copy_from_slice(&len.to_le_bytes());
+        table[9] = table.iter().fold(0u8, |sum, &b| sum.wrapping_sub(b));
+        table
+    }
+
+    #[test]
+    fn checksum() {
+        let mut madt = table(b"APIC", &[1, 2, 3]);
+        assert!(checksum_ok(&madt));
+        madt[37] ^= 1;
+        assert!(!checksum_ok(&madt));
+    }
+
+    #[test]
+    fn madt() {
+        let mut body = Vec::new();
+        body.extend_from_slice(&0xfee0_0000u32.to_le_bytes()); // local APIC address
+        body.extend_from_slice(&1u32.to_le_bytes()); // flags
+        // Local APICs: enabled, disabled, enabled.
+        body.extend_from_slice(&[MADT_LOCAL_APIC, 8, 0, 0, 1, 0, 0, 0]);
+        body.extend_from_slice(&[MADT_LOCAL_APIC, 8, 1, 1, 0, 0, 0, 0]);
+        body.extend_from_slice(&[MADT_LOCAL_APIC, 8, 2, 4, 1, 0, 0, 0]);
+        // Local x2APIC with a 32-bit ID.
+        body.extend_from_slice(&[MADT_LOCAL_X2APIC, 16, 0, 0]);
+        body.extend_from_slice(&0x100u32.to_le_bytes());
+        body.extend_from_slice(&1u32.to_le_bytes());
+        body.extend_from_slice(&3u32.to_le_bytes());
+        // I/O APIC.
+        body.extend_from_slice(&[MADT_IO_APIC, 12, 5, 0]);
+        body.extend_from_slice(&0xfec0_0000u32.to_le_bytes());
+        body.extend_from_slice(&0u32.to_le_bytes());
+        // Interrupt source override: ISA IRQ 0 -> GSI 2.
+        body.extend_from_slice(&[MADT_IRQ_OVERRIDE, 10, 0, 0]);
+        body.extend_from_slice(&2u32.to_le_bytes());
+        body.extend_from_slice(&0u16.to_le_bytes());
+        // A malformed entry stops the parsing.
+        body.extend_from_slice(&[MADT_LOCAL_APIC, 0]);
+        body.extend_from_slice(&[MADT_LOCAL_APIC, 8, 3, 8, 1, 0, 0, 0]);
+
+        let mut info = AcpiInfo::new(2);
+        parse_madt(&table(b"APIC", &body), &mut info);
+        assert_eq!(info.local_apic_paddr(), 0xfee0_0000);
+        assert_eq!(info.apic_ids(), [0, 4, 0x100]);
+        assert_eq!(info.cpu_count(), 3);
+        let io_apics = info.io_apics();
+        assert_eq!(io_apics.len(), 1);
+        assert_eq!((io_apics[0].id, io_apics[0].paddr), (5, 0xfec0_0000));
+        let overrides = info.irq_overrides();
+        assert_eq!(overrides.len(), 1);
+        assert_eq!((overrides[0].source, overrides[0].gsi), (0, 2));
+    }
+
+    #[test]
+    fn mcfg() {
+        let entry = |paddr: u64, segment: u16, bus_end: u8| {
+            let mut entry = paddr.to_le_bytes().to_vec();
+            entry.extend_from_slice(&segment.to_le_bytes());
+            entry.extend_from_slice(&[0, bus_end, 0, 0, 0, 0]);
+            entry
+        };
+        let mut body = vec![0; 8];
+        body.extend(entry(0xc000_0000, 1, 0xff));
+        body.extend(entry(0xb000_0000, 0, 0x3f));
+
+        let mut info = AcpiInfo::new(2);
+        parse_mcfg(&table(b"MCFG", &body), &mut info);
+        let ecam = info.pci_ecam().unwrap();
+        assert_eq!(
+            (ecam.paddr, ecam.bus_start, ecam.bus_end),
+            (0xb000_0000, 0, 0x3f)
+        );
+        assert_eq!(ecam.size(), 0x400_0000);
+    }
+
+    #[test]
+    fn s5_sleep_types() {
+        let dsdt = |aml: &[u8]| table(b"DSDT", aml);
+        // Name (_S5_, Package (0x04) {0x05, 0x05, 0x00, 0x00})
+        let aml = [
+            0x08, b'_', b'S', b'5', b'_', 0x12, 0x0a, 0x04, 0x0a, 0x05, 0x0a, 0x05, 0, 0,
+        ];
+        assert_eq!(find_s5_sleep_types(&dsdt(&aml)), Some((5, 5)));
+        // Name (\_S5_, Package (0x04) {0x07, Zero, ...}) after a reference to
+        // `_S5_` that is not a name definition, with a 2-byte PkgLength.
+        let aml = [
+            0x70, b'_', b'S', b'5', b'_', 0x60, // Store (_S5_, Local0)
+            0x08, b'\\', b'_', b'S', b'5', b'_', 0x12, 0x40, 0x00, 0x04, 0x07, 0x00, 0, 0,
+        ];
+        assert_eq!(find_s5_sleep_types(&dsdt(&aml)), Some((7, 0)));
+        // No `_S5_` object, or a truncated one.
+        assert_eq!(
+            find_s5_sleep_types(&dsdt(&[0x08, b'_', b'S', b'4', b'_'])),
+            None
+        );
+        assert_eq!(find_s5_sleep_types(&dsdt(&aml[..16])), None);
+    }
+}
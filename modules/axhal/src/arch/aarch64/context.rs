@@ -98,6 +98,11 @@ impl TrapFrame {
         self.usp = sp as _;
     }
 
+    /// Gets the frame pointer.
+    pub const fn fp(&self) -> usize {
+        self.r[29] as _
+    }
+
     /// Gets the return value register.
     pub const fn retval(&self) -> usize {
         self.r[0] as _
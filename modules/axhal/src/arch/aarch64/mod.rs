@@ -39,6 +39,18 @@ pub fn wait_for_irqs() {
     aarch64_cpu::asm::wfi();
 }
 
+/// Enables interrupts and waits for them atomically.
+///
+/// It must be called with interrupts disabled. Unlike [`enable_irqs`] followed
+/// by [`wait_for_irqs`], an interrupt that arrives in between is not missed.
+/// It returns with interrupts enabled, after the pending one is handled.
+#[inline]
+pub fn enable_irqs_and_wait() {
+    // WFI wakes up on pending interrupts even if they are masked.
+    aarch64_cpu::asm::wfi();
+    enable_irqs();
+}
+
 /// Halt the current CPU.
 #[inline]
 pub fn halt() {
@@ -120,6 +132,53 @@ pub fn flush_dcache_line(vaddr: VirtAddr) {
     unsafe { asm!("dc ivac, {0:x}; dsb sy; isb", in(reg) vaddr.as_usize()) };
 }
 
+/// Returns the size of data cache lines, i.e., the alignment for buffers not
+/// to share cache lines with other data.
+#[inline]
+pub fn dcache_line_size() -> usize {
+    // `CTR_EL0.DminLine` is log2 of the smallest line size in words.
+    let ctr: u64;
+    unsafe { asm!("mrs {}, ctr_el0", out(reg) ctr) };
+    4 << ((ctr >> 16) & 0xf)
+}
+
+/// Applies the data cache maintenance instruction `dc <op>` to every line
+/// overlapping `[vaddr, vaddr + size)`.
+macro_rules! dcache_range {
+    ($op:literal, $vaddr:expr, $size:expr) => {{
+        let line_size = dcache_line_size();
+        let start = $vaddr.as_usize() & !(line_size - 1);
+        let end = $vaddr.as_usize() + $size;
+        for addr in (start..end).step_by(line_size) {
+            unsafe { asm!(concat!("dc ", $op, ", {0:x}"), in(reg) addr) };
+        }
+        unsafe { asm!("dsb sy") };
+    }};
+}
+
+/// Writes back the data cache lines of the given range to memory, so that
+/// devices can read the data written by the CPU.
+#[inline]
+pub fn clean_dcache_range(vaddr: VirtAddr, size: usize) {
+    dcache_range!("cvac", vaddr, size);
+}
+
+/// Discards the data cache lines of the given range, so that the CPU can read
+/// the data written by devices.
+///
+/// Dirty lines are discarded without being written back, so the range should
+/// not share cache lines with other data.
+#[inline]
+pub fn invalidate_dcache_range(vaddr: VirtAddr, size: usize) {
+    dcache_range!("ivac", vaddr, size);
+}
+
+/// Writes back and then discards the data cache lines of the given range.
+#[inline]
+pub fn flush_dcache_range(vaddr: VirtAddr, size: usize) {
+    dcache_range!("civac", vaddr, size);
+}
+
 /// Reads the thread pointer of the current CPU.
 ///
 /// It is used to implement TLS (Thread Local Storage).
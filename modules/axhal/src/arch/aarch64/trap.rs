@@ -39,6 +39,10 @@ impl TrapSource {
 
 #[unsafe(no_mangle)]
 fn invalid_exception(tf: &TrapFrame, kind: TrapKind, source: TrapSource) {
+    #[cfg(feature = "backtrace")]
+    if !source.is_from_user() {
+        crate::backtrace::record_trap(tf);
+    }
     panic!(
         "Invalid exception {:?} from {:?}:\n{:#x?}",
         kind, source, tf
@@ -62,6 +66,10 @@ fn handle_instruction_abort(tf: &TrapFrame, iss: u64, is_user: bool) {
     if !matches!(iss & 0b111100, 0b0100 | 0b1100) // IFSC or DFSC bits
         || !handle_trap!(PAGE_FAULT, vaddr, access_flags, is_user)
     {
+        #[cfg(feature = "backtrace")]
+        if !is_user {
+            crate::backtrace::record_trap(tf);
+        }
         panic!(
             "Unhandled {} Instruction Abort @ {:#x}, fault_vaddr={:#x}, ISS={:#x} ({:?}):\n{:#x?}",
             if is_user { "EL0" } else { "EL1" },
@@ -91,6 +99,10 @@ fn handle_data_abort(tf: &TrapFrame, iss: u64, is_user: bool) {
     if !matches!(iss & 0b111100, 0b0100 | 0b1100) // IFSC or DFSC bits
         || !handle_trap!(PAGE_FAULT, vaddr, access_flags, is_user)
     {
+        #[cfg(feature = "backtrace")]
+        if !is_user {
+            crate::backtrace::record_trap(tf);
+        }
         panic!(
             "Unhandled {} Data Abort @ {:#x}, fault_vaddr={:#x}, ISS=0b{:08b} ({:?}):\n{:#x?}",
             if is_user { "EL0" } else { "EL1" },
@@ -124,6 +136,10 @@ fn handle_sync_exception(tf: &mut TrapFrame, source: TrapSource) {
             tf.elr += 4;
         }
         _ => {
+            #[cfg(feature = "backtrace")]
+            if !source.is_from_user() {
+                crate::backtrace::record_trap(tf);
+            }
             panic!(
                 "Unhandled synchronous exception @ {:#x}: ESR={:#x} (EC {:#08b}, ISS {:#x})",
                 tf.elr,
@@ -148,6 +148,11 @@ impl TrapFrame {
         self.regs.sp = sp;
     }
 
+    /// Gets the frame pointer.
+    pub const fn fp(&self) -> usize {
+        self.regs.fp
+    }
+
     /// Gets the return value register.
     pub const fn retval(&self) -> usize {
         self.regs.a0
@@ -40,6 +40,18 @@ pub fn wait_for_irqs() {
     unsafe { loongArch64::asm::idle() }
 }
 
+/// Enables interrupts and waits for them atomically.
+///
+/// It must be called with interrupts disabled. Unlike [`enable_irqs`] followed
+/// by [`wait_for_irqs`], an interrupt that arrives in between is not missed.
+/// It returns with interrupts enabled, after the pending one is handled.
+#[inline]
+pub fn enable_irqs_and_wait() {
+    // IDLE wakes up on pending interrupts even if `CRMD.IE` is cleared.
+    unsafe { loongArch64::asm::idle() };
+    enable_irqs();
+}
+
 /// Halt the current CPU.
 #[inline]
 pub fn halt() {
@@ -178,6 +190,31 @@ pub fn init_tlb() {
     tlbrentry::set_tlbrentry(paddr.as_usize());
 }
 
+/// Returns the size of data cache lines, i.e., the alignment for buffers not
+/// to share cache lines with other data.
+#[inline]
+pub const fn dcache_line_size() -> usize {
+    64
+}
+
+/// Writes back the data cache lines of the given range to memory.
+///
+/// It does nothing since DMA is cache coherent on LoongArch.
+#[inline]
+pub fn clean_dcache_range(_vaddr: VirtAddr, _size: usize) {}
+
+/// Discards the data cache lines of the given range.
+///
+/// It does nothing since DMA is cache coherent on LoongArch.
+#[inline]
+pub fn invalidate_dcache_range(_vaddr: VirtAddr, _size: usize) {}
+
+/// Writes back and then discards the data cache lines of the given range.
+///
+/// It does nothing since DMA is cache coherent on LoongArch.
+#[inline]
+pub fn flush_dcache_range(_vaddr: VirtAddr, _size: usize) {}
+
 /// Reads the thread pointer of the current CPU.
 ///
 /// It is used to implement TLS (Thread Local Storage).
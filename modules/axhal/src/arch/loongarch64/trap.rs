@@ -22,6 +22,10 @@ fn handle_page_fault(tf: &TrapFrame, mut access_flags: MappingFlags, is_user: bo
     }
     let vaddr = va!(badv::read().raw());
     if !handle_trap!(PAGE_FAULT, vaddr, access_flags, is_user) {
+        #[cfg(feature = "backtrace")]
+        if !is_user {
+            crate::backtrace::record_trap(tf);
+        }
         panic!(
             "Unhandled {} Page Fault @ {:#x}, fault_vaddr={:#x} ({:?}):\n{:#x?}",
             if is_user { "PLV3" } else { "PLV0" },
@@ -66,6 +70,10 @@ fn loongarch64_trap_handler(tf: &mut TrapFrame, from_user: bool) {
             handle_trap!(IRQ, irq_num);
         }
         _ => {
+            #[cfg(feature = "backtrace")]
+            if !from_user {
+                crate::backtrace::record_trap(tf);
+            }
             panic!(
                 "Unhandled trap {:?} @ {:#x}:\n{:#x?}",
                 estat.cause(),
@@ -38,6 +38,18 @@ pub fn wait_for_irqs() {
     riscv::asm::wfi()
 }
 
+/// Enables interrupts and waits for them atomically.
+///
+/// It must be called with interrupts disabled. Unlike [`enable_irqs`] followed
+/// by [`wait_for_irqs`], an interrupt that arrives in between is not missed.
+/// It returns with interrupts enabled, after the pending one is handled.
+#[inline]
+pub fn enable_irqs_and_wait() {
+    // WFI wakes up on pending interrupts even if `sstatus.SIE` is cleared.
+    riscv::asm::wfi();
+    enable_irqs();
+}
+
 /// Halt the current CPU.
 #[inline]
 pub fn halt() {
@@ -80,6 +92,66 @@ pub fn flush_tlb(vaddr: Option<VirtAddr>) {
     }
 }
 
+/// Returns the size of cache blocks operated by the Zicbom extension, or
+/// `None` if the extension is not present, in which case DMA is assumed to be
+/// coherent (e.g., on QEMU).
+#[inline]
+fn cbom_block_size() -> Option<usize> {
+    crate::fdt::info()?.cbom_block_size()
+}
+
+/// Returns the size of data cache lines, i.e., the alignment for buffers not
+/// to share cache lines with other data.
+#[inline]
+pub fn dcache_line_size() -> usize {
+    cbom_block_size().unwrap_or(64)
+}
+
+/// Applies the Zicbom instruction `cbo.<op>` (encoded by `$funct12`) to every
+/// cache block overlapping `[vaddr, vaddr + size)`, or does nothing if the
+/// extension is not present.
+macro_rules! cbo_range {
+    ($funct12:literal, $vaddr:expr, $size:expr) => {{
+        let Some(block_size) = cbom_block_size() else {
+            return;
+        };
+        let start = $vaddr.as_usize() & !(block_size - 1);
+        let end = $vaddr.as_usize() + $size;
+        for addr in (start..end).step_by(block_size) {
+            // The instructions are encoded directly, since the assembler may
+            // not enable the Zicbom extension.
+            unsafe {
+                core::arch::asm!(concat!(".insn i 0x0f, 2, x0, {}, ", $funct12), in(reg) addr)
+            };
+        }
+        unsafe { core::arch::asm!("fence rw, rw") };
+    }};
+}
+
+/// Writes back the data cache blocks of the given range to memory
+/// (`cbo.clean`), so that devices can read the data written by the CPU.
+#[inline]
+pub fn clean_dcache_range(vaddr: VirtAddr, size: usize) {
+    cbo_range!(1, vaddr, size);
+}
+
+/// Discards the data cache blocks of the given range (`cbo.inval`), so that
+/// the CPU can read the data written by devices.
+///
+/// Dirty blocks may be discarded without being written back, so the range
+/// should not share cache blocks with other data.
+#[inline]
+pub fn invalidate_dcache_range(vaddr: VirtAddr, size: usize) {
+    cbo_range!(0, vaddr, size);
+}
+
+/// Writes back and then discards the data cache blocks of the given range
+/// (`cbo.flush`).
+#[inline]
+pub fn flush_dcache_range(vaddr: VirtAddr, size: usize) {
+    cbo_range!(2, vaddr, size);
+}
+
 /// Writes Supervisor Trap Vector Base Address Register (`stvec`).
 #[inline]
 pub fn set_trap_vector_base(stvec: usize) {
@@ -27,6 +27,10 @@ fn handle_page_fault(
         access_flags |= MappingFlags::USER;
     }
     if !handle_trap!(PAGE_FAULT, vaddr, access_flags, is_user) {
+        #[cfg(feature = "backtrace")]
+        if !is_user {
+            crate::backtrace::record_trap(tf);
+        }
         panic!(
             "Unhandled {} Page Fault @ {:#x}, fault_vaddr={:#x} ({:?}):\n{:#x?}",
             if is_user { "User" } else { "Supervisor" },
@@ -68,6 +72,10 @@ fn riscv_trap_handler(tf: &mut TrapFrame, from_user: bool) {
                 handle_trap!(IRQ, scause.bits());
             }
             _ => {
+                #[cfg(feature = "backtrace")]
+                if !from_user {
+                    crate::backtrace::record_trap(tf);
+                }
                 panic!("Unhandled trap {:?} @ {:#x}:\n{:#x?}", cause, tf.sepc, tf);
             }
         }
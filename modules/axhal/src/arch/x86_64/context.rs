@@ -123,6 +123,11 @@ impl TrapFrame {
         self.rsp = rsp as _;
     }
 
+    /// Gets the frame pointer.
+    pub const fn fp(&self) -> usize {
+        self.rbp as _
+    }
+
     /// Gets the return value register.
     pub const fn retval(&self) -> usize {
         self.rax as _
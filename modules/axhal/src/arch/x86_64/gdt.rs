@@ -14,6 +14,17 @@ static TSS: TaskStateSegment = TaskStateSegment::new();
 #[percpu::def_percpu]
 static GDT: LazyInit<GdtStruct> = LazyInit::new();
 
+/// Size of the per-CPU stack for handling double faults.
+const DOUBLE_FAULT_STACK_SIZE: usize = 0x4000;
+
+/// Index of the double fault stack in the Interrupt Stack Table (IST).
+pub(super) const DOUBLE_FAULT_IST_INDEX: u16 = 0;
+
+/// The stack used to handle double faults, so that they can be reported even
+/// if the kernel stack is broken (e.g, overflowed into a guard page).
+#[percpu::def_percpu]
+static DOUBLE_FAULT_STACK: [u8; DOUBLE_FAULT_STACK_SIZE] = [0; DOUBLE_FAULT_STACK_SIZE];
+
 /// A wrapper of the Global Descriptor Table (GDT) with maximum 16 entries.
 #[repr(align(16))]
 pub struct GdtStruct {
@@ -103,6 +114,10 @@ impl fmt::Debug for GdtStruct {
 /// current CPU.
 pub fn init_gdt() {
     unsafe {
+        let stack_top = DOUBLE_FAULT_STACK.current_ptr() as usize + DOUBLE_FAULT_STACK_SIZE;
+        TSS.current_ref_mut_raw().interrupt_stack_table[DOUBLE_FAULT_IST_INDEX as usize] =
+            VirtAddr::new((stack_top & !0xf) as u64);
+
         let gdt = GDT.current_ref_raw();
         gdt.init_once(GdtStruct::new(TSS.current_ref_raw()));
         gdt.load();
@@ -64,6 +64,21 @@ pub fn wait_for_irqs() {
     }
 }
 
+/// Enables interrupts and waits for them atomically.
+///
+/// It must be called with interrupts disabled. Unlike [`enable_irqs`] followed
+/// by [`wait_for_irqs`], an interrupt that arrives in between is not missed.
+/// It returns with interrupts enabled, after the pending one is handled.
+#[inline]
+pub fn enable_irqs_and_wait() {
+    if cfg!(target_os = "none") {
+        // Interrupts are not recognized until the instruction after STI.
+        unsafe { asm!("sti; hlt") }
+    } else {
+        core::hint::spin_loop()
+    }
+}
+
 /// Halt the current CPU.
 #[inline]
 pub fn halt() {
@@ -105,6 +120,31 @@ pub fn flush_tlb(vaddr: Option<VirtAddr>) {
     }
 }
 
+/// Returns the size of data cache lines, i.e., the alignment for buffers not
+/// to share cache lines with other data.
+#[inline]
+pub const fn dcache_line_size() -> usize {
+    64
+}
+
+/// Writes back the data cache lines of the given range to memory.
+///
+/// It does nothing since DMA is cache coherent on x86_64.
+#[inline]
+pub fn clean_dcache_range(_vaddr: VirtAddr, _size: usize) {}
+
+/// Discards the data cache lines of the given range.
+///
+/// It does nothing since DMA is cache coherent on x86_64.
+#[inline]
+pub fn invalidate_dcache_range(_vaddr: VirtAddr, _size: usize) {}
+
+/// Writes back and then discards the data cache lines of the given range.
+///
+/// It does nothing since DMA is cache coherent on x86_64.
+#[inline]
+pub fn flush_dcache_range(_vaddr: VirtAddr, _size: usize) {}
+
 /// Reads the thread pointer of the current CPU.
 ///
 /// It is used to implement TLS (Thread Local Storage).
@@ -17,6 +17,10 @@ fn handle_page_fault(tf: &TrapFrame) {
         .unwrap_or_else(|e| panic!("Invalid #PF error code: {:#x}", e));
     let vaddr = va!(unsafe { cr2() });
     if !handle_trap!(PAGE_FAULT, vaddr, access_flags, tf.is_user()) {
+        #[cfg(feature = "backtrace")]
+        if !tf.is_user() {
+            crate::backtrace::record_trap(tf);
+        }
         panic!(
             "Unhandled {} #PF @ {:#x}, fault_vaddr={:#x}, error_code={:#x} ({:?}):\n{:#x?}",
             if tf.is_user() { "user" } else { "kernel" },
@@ -29,17 +33,39 @@ fn handle_page_fault(tf: &TrapFrame) {
     }
 }
 
+fn handle_double_fault(tf: &TrapFrame) {
+    // A double fault in the kernel is usually caused by a page fault while
+    // delivering another page fault, e.g., the kernel stack overflowed into
+    // the guard page. Give the page fault handlers a chance to report it.
+    let vaddr = va!(unsafe { cr2() });
+    if !tf.is_user() {
+        handle_trap!(PAGE_FAULT, vaddr, MappingFlags::WRITE, false);
+    }
+    panic!(
+        "#DF @ {:#x}, last_fault_vaddr={:#x}:\n{:#x?}",
+        tf.rip, vaddr, tf
+    );
+}
+
 #[unsafe(no_mangle)]
 fn x86_trap_handler(tf: &mut TrapFrame) {
     #[cfg(feature = "uspace")]
     super::tls::switch_to_kernel_fs_base(tf);
-    if !matches!(tf.vector as u8, IRQ_VECTOR_START..=IRQ_VECTOR_END) {
+    if !matches!(
+        tf.vector as u8,
+        IRQ_VECTOR_START..=IRQ_VECTOR_END | DOUBLE_FAULT_VECTOR
+    ) {
         unmask_irqs(tf);
     }
     match tf.vector as u8 {
         PAGE_FAULT_VECTOR => handle_page_fault(tf),
+        DOUBLE_FAULT_VECTOR => handle_double_fault(tf),
         BREAKPOINT_VECTOR => debug!("#BP @ {:#x} ", tf.rip),
         GENERAL_PROTECTION_FAULT_VECTOR => {
+            #[cfg(feature = "backtrace")]
+            if !tf.is_user() {
+                crate::backtrace::record_trap(tf);
+            }
             panic!(
                 "#GP @ {:#x}, error_code={:#x}:\n{:#x?}",
                 tf.rip, tf.error_code, tf
@@ -51,6 +77,10 @@ fn x86_trap_handler(tf: &mut TrapFrame) {
             handle_trap!(IRQ, tf.vector as _);
         }
         _ => {
+            #[cfg(feature = "backtrace")]
+            if !tf.is_user() {
+                crate::backtrace::record_trap(tf);
+            }
             panic!(
                 "Unhandled exception {} ({}, error_code={:#x}) @ {:#x}:\n{:#x?}",
                 tf.vector,
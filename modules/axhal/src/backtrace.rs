@@ -0,0 +1,331 @@
+//! Stack unwinding and symbolized backtraces.
+//!
+//! The call chain is walked with frame pointers, so the kernel must be built
+//! with `-C force-frame-pointers=yes`. Return addresses are symbolized with the
+//! kernel symbol table, which is embedded into the `.ksyms` section after
+//! linking (see `scripts/make/build.mk`). If the table is not embedded, raw
+//! addresses are printed.
+//!
+//! The symbol table is plain text, one symbol per line in the format of
+//! `<hex address> <name>`, sorted by address.
+//!
+//! When the kernel panics on a fatal trap, the trap handler records the trapped
+//! context, so that [`print_panic_backtrace`] prints the backtrace from where
+//! the trap occurred rather than from the trap handler.
+//!
+//! Frames are only read from the stack that the walk starts in, which is
+//! either the stack of the current task (see [`set_current_stack`]) or one of
+//! the boot stacks.
+
+use core::fmt;
+use core::ops::Range;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::arch::TrapFrame;
+
+/// The maximum number of frames to walk.
+const MAX_FRAMES: usize = 64;
+
+/// The space reserved for the kernel symbol table. The build fails if the
+/// table is larger than it.
+const KSYMS_SIZE: usize = 0x10_0000;
+
+/// Placeholder of the kernel symbol table, filled after linking.
+#[used]
+#[unsafe(link_section = ".ksyms")]
+static KSYMS: [u8; KSYMS_SIZE] = [0; KSYMS_SIZE];
+
+cfg_if::cfg_if! {
+    if #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))] {
+        // The frame pointer points to the saved frame pointer, followed by the
+        // return address.
+        const SAVED_FP_OFFSET: isize = 0;
+        const RETURN_ADDR_OFFSET: isize = 8;
+    } else {
+        // The frame pointer points to the top of the frame, below which are
+        // the return address and the saved frame pointer.
+        const SAVED_FP_OFFSET: isize = -16;
+        const RETURN_ADDR_OFFSET: isize = -8;
+    }
+}
+
+/// Reads the frame pointer of the caller.
+#[inline(always)]
+fn read_fp() -> usize {
+    let fp: usize;
+    unsafe {
+        #[cfg(target_arch = "x86_64")]
+        core::arch::asm!("mov {}, rbp", out(reg) fp);
+        #[cfg(target_arch = "aarch64")]
+        core::arch::asm!("mov {}, x29", out(reg) fp);
+        #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+        core::arch::asm!("mv {}, s0", out(reg) fp);
+        #[cfg(target_arch = "loongarch64")]
+        core::arch::asm!("move {}, $fp", out(reg) fp);
+    }
+    fp
+}
+
+/// Whether the stack of the current task has been set on any CPU, so that the
+/// per-CPU data is not accessed before it is initialized.
+static TASK_STACK_SET: AtomicBool = AtomicBool::new(false);
+
+/// The start of the stack of the task running on this CPU.
+#[percpu::def_percpu]
+static TASK_STACK_START: usize = 0;
+
+/// The end of the stack of the task running on this CPU.
+#[percpu::def_percpu]
+static TASK_STACK_END: usize = 0;
+
+/// Sets the stack of the task running on this CPU, in which the call chain
+/// is walked. An empty range means that the task runs on the boot stack.
+///
+/// It should be called with IRQs disabled when switching tasks.
+pub fn set_current_stack(stack: Range<usize>) {
+    unsafe {
+        TASK_STACK_START.write_current_raw(stack.start);
+        TASK_STACK_END.write_current_raw(stack.end);
+    }
+    TASK_STACK_SET.store(true, Ordering::Release);
+}
+
+/// The boot stacks of all CPUs, which are all in the `.bss.stack` section.
+fn boot_stacks() -> Range<usize> {
+    unsafe extern "C" {
+        fn boot_stack();
+        fn boot_stack_top();
+    }
+    boot_stack as usize..boot_stack_top as usize
+}
+
+/// Returns the stack containing `fp`, or an empty range if it is neither in
+/// the stack of the current task nor in the boot stacks.
+fn stack_of(fp: usize) -> Range<usize> {
+    if TASK_STACK_SET.load(Ordering::Acquire) {
+        // Safety: it is only read to bound the walk, a stale value caused by
+        // migration at most stops the walk early.
+        let task_stack =
+            unsafe { TASK_STACK_START.read_current_raw()..TASK_STACK_END.read_current_raw() };
+        if task_stack.contains(&fp) {
+            return task_stack;
+        }
+    }
+    let boot_stacks = boot_stacks();
+    if boot_stacks.contains(&fp) {
+        return boot_stacks;
+    }
+    0..0
+}
+
+fn is_kernel_text(addr: usize) -> bool {
+    unsafe extern "C" {
+        fn _stext();
+        fn _etext();
+    }
+    (_stext as usize.._etext as usize).contains(&addr)
+}
+
+/// A frame in the call chain.
+#[derive(Debug, Clone, Copy)]
+pub struct Frame {
+    /// The frame pointer.
+    pub fp: usize,
+    /// The return address, i.e., where the execution continues in the caller.
+    pub ra: usize,
+}
+
+/// An iterator over the call chain, from the innermost frame outwards.
+///
+/// Only frames in the stack containing the starting frame are walked. It stops
+/// at the first frame whose record (the saved frame pointer and the return
+/// address) is not entirely in that stack, or that does not return to the
+/// kernel code.
+pub struct Frames {
+    fp: usize,
+    stack: Range<usize>,
+    depth: usize,
+}
+
+impl Frames {
+    /// Creates an iterator over the call chain starting at the frame pointed by
+    /// `fp`, which should be in the stack of the current task or a boot stack.
+    pub fn new(fp: usize) -> Self {
+        Self {
+            fp,
+            stack: stack_of(fp),
+            depth: 0,
+        }
+    }
+
+    /// Creates an iterator over the call chain of the caller.
+    #[inline(always)]
+    pub fn current() -> Self {
+        Self::new(read_fp())
+    }
+}
+
+impl Iterator for Frames {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame> {
+        let fp = self.fp;
+        if fp == 0 || fp % size_of::<usize>() != 0 || self.depth >= MAX_FRAMES {
+            return None;
+        }
+        // The saved frame pointer and the return address are adjacent, check
+        // that both of them are in the stack before reading.
+        let saved_fp_addr = fp.checked_add_signed(SAVED_FP_OFFSET)?;
+        let ra_addr = fp.checked_add_signed(RETURN_ADDR_OFFSET)?;
+        let record_start = saved_fp_addr.min(ra_addr);
+        let record_end = saved_fp_addr.max(ra_addr).checked_add(size_of::<usize>())?;
+        if record_start < self.stack.start || record_end > self.stack.end {
+            return None;
+        }
+        let (saved_fp, ra) =
+            unsafe { (*(saved_fp_addr as *const usize), *(ra_addr as *const usize)) };
+        if !is_kernel_text(ra) {
+            return None;
+        }
+        // The stack grows downwards, so the frames of callers are at higher
+        // addresses.
+        self.fp = if saved_fp > fp { saved_fp } else { 0 };
+        self.depth += 1;
+        Some(Frame { fp, ra })
+    }
+}
+
+fn ksyms() -> &'static [u8] {
+    // Prevent the compiler from assuming the table is all zeros.
+    let table: &[u8] = core::hint::black_box(&KSYMS);
+    // The unused space is filled with zeros.
+    let len = table.iter().position(|&b| b == 0).unwrap_or(table.len());
+    &table[..len]
+}
+
+/// Looks up the kernel symbol containing `addr`, returns its name and the
+/// offset of `addr` from the start of the symbol.
+///
+/// It returns `None` if `addr` is not in the kernel code, or the symbol table
+/// is not embedded.
+pub fn lookup_symbol(addr: usize) -> Option<(&'static str, usize)> {
+    if !is_kernel_text(addr) {
+        return None;
+    }
+    let mut found = None;
+    for line in ksyms().split(|&b| b == b'\n') {
+        let Ok(line) = core::str::from_utf8(line) else {
+            continue;
+        };
+        let Some((sym_addr, name)) = line.split_once(' ') else {
+            continue;
+        };
+        let Ok(sym_addr) = usize::from_str_radix(sym_addr, 16) else {
+            continue;
+        };
+        if sym_addr > addr {
+            break;
+        }
+        found = Some((name, addr - sym_addr));
+    }
+    found
+}
+
+/// An address in the kernel code, displayed with its symbol if available.
+struct Symbolized {
+    addr: usize,
+    /// Whether it is a return address, which points to the instruction after
+    /// the call, and may belong to the next symbol.
+    is_return: bool,
+}
+
+impl fmt::Display for Symbolized {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#018x}", self.addr)?;
+        let adjust = self.is_return as usize;
+        if let Some((name, offset)) = lookup_symbol(self.addr - adjust) {
+            write!(f, " in {}+{:#x}", name, offset + adjust)?;
+        }
+        Ok(())
+    }
+}
+
+fn print_frames(title: &str, pc: Option<usize>, frames: Frames) {
+    axlog::ax_println!("{}:", title);
+    let pc = pc.map(|addr| Symbolized {
+        addr,
+        is_return: false,
+    });
+    let return_addrs = frames.map(|frame| Symbolized {
+        addr: frame.ra,
+        is_return: true,
+    });
+    for (i, addr) in pc.into_iter().chain(return_addrs).enumerate() {
+        axlog::ax_println!("  #{:<2} {}", i, addr);
+    }
+}
+
+/// Prints the backtrace of the caller.
+#[inline(never)]
+pub fn print_backtrace() {
+    print_frames("Backtrace", None, Frames::current());
+}
+
+/// Whether a trapped context has been recorded on any CPU, so that the
+/// per-CPU records are not accessed when nothing is recorded, e.g., if it
+/// panics before the per-CPU data is initialized.
+static TRAP_RECORDED: AtomicBool = AtomicBool::new(false);
+
+/// The instruction pointer of the trapped context recorded on this CPU, or
+/// zero if there is none.
+#[percpu::def_percpu]
+static TRAPPED_IP: usize = 0;
+
+/// The frame pointer of the trapped context recorded on this CPU.
+#[percpu::def_percpu]
+static TRAPPED_FP: usize = 0;
+
+/// Records the kernel context interrupted by a fatal trap, before panicking
+/// on it.
+pub(crate) fn record_trap(tf: &TrapFrame) {
+    // Safety: IRQs are disabled in trap handlers.
+    unsafe {
+        TRAPPED_IP.write_current_raw(tf.ip());
+        TRAPPED_FP.write_current_raw(tf.fp());
+    }
+    TRAP_RECORDED.store(true, Ordering::Release);
+}
+
+/// Prints the backtrace for a panic.
+///
+/// If the panic is caused by a fatal trap of the kernel on this CPU, it is
+/// the backtrace of the trapped context (see [`print_trap_backtrace`]).
+/// Otherwise, it is the backtrace of the caller.
+#[inline(never)]
+pub fn print_panic_backtrace() {
+    if TRAP_RECORDED.load(Ordering::Acquire) {
+        // Safety: trap handlers panic with IRQs disabled, so a record of this
+        // CPU is read on the CPU that made it.
+        let (ip, fp) = unsafe { (TRAPPED_IP.read_current_raw(), TRAPPED_FP.read_current_raw()) };
+        if ip != 0 {
+            unsafe { TRAPPED_IP.write_current_raw(0) };
+            print_frames(
+                "Backtrace of the trapped context",
+                Some(ip),
+                Frames::new(fp),
+            );
+            return;
+        }
+    }
+    print_frames("Backtrace", None, Frames::current());
+}
+
+/// Prints the backtrace of the kernel context interrupted by a trap, starting
+/// from the trapped instruction.
+pub fn print_trap_backtrace(tf: &TrapFrame) {
+    print_frames(
+        "Backtrace of the trapped context",
+        Some(tf.ip()),
+        Frames::new(tf.fp()),
+    );
+}
@@ -0,0 +1,20 @@
+//! Console input and output.
+
+pub use crate::platform::console::*;
+
+/// Registers a handler for console input interrupts, and enables them.
+///
+/// The handler is called when input is available, and it must drain the input
+/// with [`read_bytes`] to clear the interrupt. It returns `false` if console
+/// input of the platform is not interrupt-driven, or the registration failed.
+#[cfg(feature = "irq")]
+pub fn register_input_handler(handler: crate::irq::IrqHandler) -> bool {
+    let Some(irq_num) = crate::platform::console::INPUT_IRQ_NUM else {
+        return false;
+    };
+    if !crate::irq::register_named_handler(irq_num, "console", handler) {
+        return false;
+    }
+    crate::platform::console::enable_input_irq();
+    true
+}
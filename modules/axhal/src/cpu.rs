@@ -15,6 +15,18 @@ pub fn this_cpu_id() -> usize {
     CPU_ID.read_current()
 }
 
+/// Returns the number of CPUs that the system runs on.
+///
+/// It's the number of CPUs in the device tree (or the ACPI MADT on x86_64)
+/// if present, but no more than [`axconfig::SMP`].
+pub fn cpu_num() -> usize {
+    #[cfg(target_arch = "x86_64")]
+    let count = crate::acpi::info().map(|info| info.cpu_count());
+    #[cfg(not(target_arch = "x86_64"))]
+    let count = crate::fdt::info().map(|info| info.cpu_count());
+    count.map_or(axconfig::SMP, |count| count.clamp(1, axconfig::SMP))
+}
+
 /// Returns whether the current CPU is the primary CPU (aka the bootstrap
 /// processor or BSP)
 #[inline]
@@ -0,0 +1,879 @@
+//! Platform discovery from the flattened device tree (FDT).
+//!
+//! Platforms booted with a device tree blob (DTB), such as the QEMU virt
+//! machines, parse it once at boot to find the RAM, reserved memory, CPUs and
+//! common devices. The parser does not allocate, as it runs before the global
+//! allocator is initialized, so the number of discovered items is bounded.
+//! Items beyond the bounds are counted, and too many reserved memory regions
+//! are fatal, as the memory dropped from the list would be allocated.
+
+use core::fmt;
+
+use lazyinit::LazyInit;
+
+use crate::mem::phys_to_virt;
+
+const FDT_MAGIC: u32 = 0xd00d_feed;
+const FDT_HEADER_SIZE: usize = 40;
+
+const FDT_BEGIN_NODE: u32 = 0x1;
+const FDT_END_NODE: u32 = 0x2;
+const FDT_PROP: u32 = 0x3;
+const FDT_NOP: u32 = 0x4;
+const FDT_END: u32 = 0x9;
+
+/// The maximum depth of nodes in the device tree.
+const MAX_DEPTH: usize = 16;
+
+/// The maximum number of RAM regions.
+pub const MAX_MEMORY_REGIONS: usize = 8;
+/// The maximum number of reserved memory regions.
+pub const MAX_RESERVED_REGIONS: usize = 16;
+/// The maximum number of VirtIO MMIO devices.
+pub const MAX_VIRTIO_MMIO_DEVICES: usize = 32;
+
+const UART_COMPATIBLES: &[&str] = &["ns16550a", "ns16550", "snps,dw-apb-uart", "arm,pl011"];
+const GIC_COMPATIBLES: &[&str] = &["arm,cortex-a15-gic", "arm,cortex-a9-gic", "arm,gic-400"];
+const PLIC_COMPATIBLES: &[&str] = &["riscv,plic0", "sifive,plic-1.0.0"];
+const RTC_COMPATIBLES: &[&str] = &["google,goldfish-rtc", "arm,pl031"];
+const VIRTIO_MMIO_COMPATIBLES: &[&str] = &["virtio,mmio"];
+
+static FDT_INFO: LazyInit<FdtInfo> = LazyInit::new();
+
+/// A list with a fixed capacity, items beyond it are dropped.
+#[derive(Clone, Copy)]
+pub(crate) struct FixedVec<T: Copy, const N: usize> {
+    items: [T; N],
+    len: usize,
+}
+
+impl<T: Copy, const N: usize> FixedVec<T, N> {
+    pub(crate) const fn new(init: T) -> Self {
+        Self {
+            items: [init; N],
+            len: 0,
+        }
+    }
+
+    /// Appends an item, returns `false` if the list is full.
+    pub(crate) fn push(&mut self, item: T) -> bool {
+        if self.len < N {
+            self.items[self.len] = item;
+            self.len += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub(crate) fn as_slice(&self) -> &[T] {
+        &self.items[..self.len]
+    }
+
+    pub(crate) fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.items[..self.len]
+    }
+}
+
+impl<T: Copy, const N: usize> IntoIterator for FixedVec<T, N> {
+    type Item = T;
+    type IntoIter = core::iter::Take<core::array::IntoIter<T, N>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter().take(self.len)
+    }
+}
+
+impl<T: Copy + fmt::Debug, const N: usize> fmt::Debug for FixedVec<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.as_slice()).finish()
+    }
+}
+
+/// A device discovered from the device tree.
+#[derive(Debug, Clone, Copy)]
+pub struct FdtDevice {
+    /// The physical address of the MMIO registers.
+    pub paddr: usize,
+    /// The size in bytes of the MMIO registers.
+    pub size: usize,
+    /// The IRQ number in the numbering of the platform's interrupt
+    /// controller, if any.
+    pub irq: Option<usize>,
+}
+
+impl FdtDevice {
+    const EMPTY: Self = Self {
+        paddr: 0,
+        size: 0,
+        irq: None,
+    };
+}
+
+/// Platform resources discovered from the device tree.
+///
+/// Memory regions are `(paddr, size)` pairs.
+#[derive(Debug)]
+pub struct FdtInfo {
+    dtb: (usize, usize),
+    memory: FixedVec<(usize, usize), MAX_MEMORY_REGIONS>,
+    reserved: FixedVec<(usize, usize), MAX_RESERVED_REGIONS>,
+    cpu_count: usize,
+    timebase_frequency: Option<u64>,
+    cbom_block_size: Option<usize>,
+    uart: Option<FdtDevice>,
+    gicd: Option<FdtDevice>,
+    gicc: Option<FdtDevice>,
+    plic: Option<FdtDevice>,
+    rtc: Option<FdtDevice>,
+    virtio_mmio: FixedVec<FdtDevice, MAX_VIRTIO_MMIO_DEVICES>,
+    dropped_memory: usize,
+    dropped_reserved: usize,
+    dropped_virtio_mmio: usize,
+}
+
+impl FdtInfo {
+    /// The physical address and size of the device tree blob.
+    pub fn dtb_region(&self) -> (usize, usize) {
+        self.dtb
+    }
+
+    /// The RAM regions (`/memory` nodes).
+    pub fn memory_regions(&self) -> &[(usize, usize)] {
+        self.memory.as_slice()
+    }
+
+    /// The memory regions that must not be used for allocation.
+    ///
+    /// It includes the memory reservation block, the `/reserved-memory`
+    /// nodes, and the device tree blob itself.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than [`MAX_RESERVED_REGIONS`] of them.
+    pub fn reserved_regions(&self) -> &[(usize, usize)] {
+        assert!(
+            self.dropped_reserved == 0,
+            "{} reserved memory regions in the device tree are beyond the limit of {}",
+            self.dropped_reserved,
+            MAX_RESERVED_REGIONS
+        );
+        self.reserved.as_slice()
+    }
+
+    /// The number of RAM regions ignored as there are more than
+    /// [`MAX_MEMORY_REGIONS`].
+    pub fn dropped_memory_regions(&self) -> usize {
+        self.dropped_memory
+    }
+
+    /// The number of enabled CPUs (`/cpus/cpu` nodes).
+    pub fn cpu_count(&self) -> usize {
+        self.cpu_count
+    }
+
+    /// The frequency of the timer used by `time`, in Hz (RISC-V only).
+    pub fn timebase_frequency(&self) -> Option<u64> {
+        self.timebase_frequency
+    }
+
+    /// The size of cache blocks operated by the Zicbom extension, or `None` if
+    /// not all CPUs support the extension (RISC-V only).
+    pub fn cbom_block_size(&self) -> Option<usize> {
+        self.cbom_block_size
+    }
+
+    /// The console UART, referred by `/chosen/stdout-path` if present.
+    pub fn uart(&self) -> Option<FdtDevice> {
+        self.uart
+    }
+
+    /// The GIC distributor (ARM only).
+    pub fn gicd(&self) -> Option<FdtDevice> {
+        self.gicd
+    }
+
+    /// The GIC CPU interface (ARM only).
+    pub fn gicc(&self) -> Option<FdtDevice> {
+        self.gicc
+    }
+
+    /// The platform-level interrupt controller (RISC-V only).
+    pub fn plic(&self) -> Option<FdtDevice> {
+        self.plic
+    }
+
+    /// The real time clock.
+    pub fn rtc(&self) -> Option<FdtDevice> {
+        self.rtc
+    }
+
+    /// The VirtIO MMIO transports, including the unused ones.
+    pub fn virtio_mmio_devices(&self) -> &[FdtDevice] {
+        self.virtio_mmio.as_slice()
+    }
+
+    /// The number of VirtIO MMIO transports ignored as there are more than
+    /// [`MAX_VIRTIO_MMIO_DEVICES`].
+    pub fn dropped_virtio_mmio_devices(&self) -> usize {
+        self.dropped_virtio_mmio
+    }
+
+    /// Returns an iterator over all the discovered devices.
+    pub fn devices(&self) -> impl Iterator<Item = FdtDevice> + '_ {
+        [self.uart, self.gicd, self.gicc, self.plic, self.rtc]
+            .into_iter()
+            .flatten()
+            .chain(self.virtio_mmio.as_slice().iter().copied())
+    }
+}
+
+fn read_be32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_be64(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset.checked_add(8)?)?;
+    Some(u64::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+/// Reads a number made of big-endian 32-bit cells.
+fn read_cells(cells: &[u8]) -> usize {
+    cells.chunks_exact(4).fold(0, |acc, c| {
+        (acc << 32) | u32::from_be_bytes(c.try_into().unwrap()) as u64
+    }) as usize
+}
+
+/// Reads a null-terminated string at `offset`.
+fn read_cstr(data: &[u8], offset: usize) -> Option<&str> {
+    let bytes = data.get(offset..)?;
+    let len = bytes.iter().position(|&b| b == 0)?;
+    core::str::from_utf8(&bytes[..len]).ok()
+}
+
+const fn align4(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+/// Returns the string value of a property, without the null terminator.
+fn prop_str(value: &[u8]) -> &[u8] {
+    value.strip_suffix(b"\0").unwrap_or(value)
+}
+
+/// A node, with the properties that precede its children.
+#[derive(Clone, Copy)]
+struct Node<'a> {
+    name: &'a str,
+    props: &'a [u8],
+    strings: &'a [u8],
+    /// `#address-cells` for the children.
+    address_cells: usize,
+    /// `#size-cells` for the children.
+    size_cells: usize,
+}
+
+impl<'a> Node<'a> {
+    const EMPTY: Self = Self {
+        name: "",
+        props: &[],
+        strings: &[],
+        address_cells: 2,
+        size_cells: 1,
+    };
+
+    fn prop(&self, name: &str) -> Option<&'a [u8]> {
+        let mut offset = 0;
+        while offset < self.props.len() {
+            match read_be32(self.props, offset)? {
+                FDT_PROP => {
+                    let len = read_be32(self.props, offset + 4)? as usize;
+                    let name_offset = read_be32(self.props, offset + 8)? as usize;
+                    let value = self.props.get(offset + 12..offset + 12 + len)?;
+                    if read_cstr(self.strings, name_offset)? == name {
+                        return Some(value);
+                    }
+                    offset = align4(offset + 12 + len);
+                }
+                FDT_NOP => offset += 4,
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    fn prop_u32(&self, name: &str) -> Option<u32> {
+        read_be32(self.prop(name)?, 0)
+    }
+
+    /// The node name without the unit address.
+    fn base_name(&self) -> &'a str {
+        self.name.split('@').next().unwrap_or(self.name)
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.prop("status")
+            .is_none_or(|s| matches!(prop_str(s), b"okay" | b"ok"))
+    }
+
+    fn is_device_type(&self, device_type: &str) -> bool {
+        self.prop("device_type")
+            .is_some_and(|s| prop_str(s) == device_type.as_bytes())
+    }
+
+    fn is_compatible(&self, compatibles: &[&str]) -> bool {
+        self.prop("compatible").is_some_and(|list| {
+            list.split(|&b| b == 0)
+                .any(|c| compatibles.iter().any(|s| s.as_bytes() == c))
+        })
+    }
+
+    /// Returns an iterator over the `(address, size)` pairs in the `reg`
+    /// property, where `parent` defines the number of cells.
+    fn reg(&self, parent: &Node) -> impl Iterator<Item = (usize, usize)> {
+        let address_len = parent.address_cells * 4;
+        let stride = address_len + parent.size_cells * 4;
+        self.prop("reg")
+            .unwrap_or_default()
+            .chunks_exact(stride.max(4))
+            .map(move |entry| {
+                let (address, size) = entry.split_at(address_len.min(entry.len()));
+                (read_cells(address), read_cells(size))
+            })
+    }
+
+    /// Returns the first IRQ in the `interrupts` property.
+    fn irq(&self) -> Option<usize> {
+        let value = self.prop("interrupts")?;
+        let cell = |i: usize| read_be32(value, i * 4).map(|c| c as usize);
+        if cfg!(target_arch = "aarch64") && value.len() >= 12 {
+            // GIC: <type number flags>, SPIs start from 32 and PPIs from 16.
+            match cell(0)? {
+                0 => Some(cell(1)? + 32),
+                1 => Some(cell(1)? + 16),
+                _ => None,
+            }
+        } else {
+            cell(0)
+        }
+    }
+
+    /// Returns the `riscv,cbom-block-size` of a CPU node if the CPU supports
+    /// the Zicbom extension, in either `riscv,isa-extensions` or the
+    /// `riscv,isa` string.
+    fn cbom_block_size(&self) -> Option<usize> {
+        let has_zicbom = match self.prop("riscv,isa-extensions") {
+            Some(exts) => exts.split(|&b| b == 0).any(|ext| ext == b"zicbom"),
+            None => self.prop("riscv,isa").is_some_and(|isa| {
+                // e.g. `rv64imafdc_zicbom_zicboz`
+                prop_str(isa)
+                    .split(|&b| b == b'_')
+                    .skip(1)
+                    .any(|ext| ext.eq_ignore_ascii_case(b"zicbom"))
+            }),
+        };
+        if !has_zicbom {
+            return None;
+        }
+        let size = self.prop_u32("riscv,cbom-block-size")? as usize;
+        size.is_power_of_two().then_some(size)
+    }
+
+    /// Returns the device at the `index`-th `reg` entry.
+    fn device(&self, parent: &Node, index: usize) -> Option<FdtDevice> {
+        let (paddr, size) = self.reg(parent).nth(index)?;
+        Some(FdtDevice {
+            paddr,
+            size,
+            irq: self.irq(),
+        })
+    }
+}
+
+/// Whether `path` (e.g., `/soc/serial@10000000`) refers to `node`.
+fn path_matches(path: &[u8], parents: &[Node], node: &Node) -> bool {
+    let mut components = path.split(|&b| b == b'/').filter(|c| !c.is_empty());
+    parents
+        .iter()
+        .skip(1) // the root node
+        .chain(core::iter::once(node))
+        .all(|n| components.next() == Some(n.name.as_bytes()))
+        && components.next().is_none()
+}
+
+struct Fdt<'a> {
+    total_size: usize,
+    structs: &'a [u8],
+    strings: &'a [u8],
+    mem_rsvmap: &'a [u8],
+}
+
+impl<'a> Fdt<'a> {
+    /// Validates the header of the device tree blob at `ptr`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to readable memory of at least the size in the
+    /// header if the magic number matches.
+    unsafe fn from_ptr(ptr: *const u8) -> Option<Self> {
+        let header = unsafe { core::slice::from_raw_parts(ptr, FDT_HEADER_SIZE) };
+        if read_be32(header, 0)? != FDT_MAGIC {
+            return None;
+        }
+        let field = |offset| read_be32(header, offset).map(|v| v as usize);
+        let total_size = field(4)?;
+        let data = unsafe { core::slice::from_raw_parts(ptr, total_size) };
+        let (off_struct, size_struct) = (field(8)?, field(36)?);
+        let (off_strings, size_strings) = (field(12)?, field(32)?);
+        Some(Self {
+            total_size,
+            structs: data.get(off_struct..off_struct.checked_add(size_struct)?)?,
+            strings: data.get(off_strings..off_strings.checked_add(size_strings)?)?,
+            mem_rsvmap: data.get(field(16)?..)?,
+        })
+    }
+
+    /// Returns an iterator over the `(address, size)` entries in the memory
+    /// reservation block.
+    fn mem_reservations(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..)
+            .map_while(|i| {
+                let address = read_be64(self.mem_rsvmap, i * 16)?;
+                let size = read_be64(self.mem_rsvmap, i * 16 + 8)?;
+                Some((address as usize, size as usize))
+            })
+            .take_while(|&entry| entry != (0, 0))
+    }
+
+    /// Walks the structure block, calls `visit` with the ancestors (from the
+    /// root) and the properties of each node.
+    ///
+    /// Returns `None` if the structure block is malformed.
+    fn walk(&self, mut visit: impl FnMut(&[Node<'a>], &Node<'a>)) -> Option<()> {
+        let mut stack = [Node::EMPTY; MAX_DEPTH];
+        let mut depth = 0;
+        let mut offset = 0;
+        // The offset of the properties of the innermost node, or `None` if
+        // they have been visited.
+        let mut props_start = None;
+
+        let mut visit_props = |stack: &mut [Node<'a>], start: usize, end: usize| {
+            let (node, parents) = stack.split_last_mut()?;
+            node.props = self.structs.get(start..end)?;
+            node.address_cells = node.prop_u32("#address-cells").map_or(2, |v| v as usize);
+            node.size_cells = node.prop_u32("#size-cells").map_or(1, |v| v as usize);
+            visit(parents, node);
+            Some(())
+        };
+
+        loop {
+            match read_be32(self.structs, offset)? {
+                FDT_BEGIN_NODE => {
+                    if let Some(start) = props_start.take() {
+                        visit_props(&mut stack[..depth], start, offset)?;
+                    }
+                    if depth == MAX_DEPTH {
+                        return None;
+                    }
+                    let name = read_cstr(self.structs, offset + 4)?;
+                    offset = align4(offset + 4 + name.len() + 1);
+                    stack[depth] = Node {
+                        name,
+                        strings: self.strings,
+                        ..Node::EMPTY
+                    };
+                    depth += 1;
+                    props_start = Some(offset);
+                }
+                FDT_END_NODE => {
+                    if depth == 0 {
+                        return None;
+                    }
+                    if let Some(start) = props_start.take() {
+                        visit_props(&mut stack[..depth], start, offset)?;
+                    }
+                    depth -= 1;
+                    offset += 4;
+                }
+                FDT_PROP => {
+                    let len = read_be32(self.structs, offset + 4)? as usize;
+                    offset = align4(offset + 12 + len);
+                }
+                FDT_NOP => offset += 4,
+                FDT_END => return Some(()),
+                _ => return None,
+            }
+        }
+    }
+
+    fn parse(&self, dtb_paddr: usize) -> Option<FdtInfo> {
+        let mut info = FdtInfo {
+            dtb: (dtb_paddr, self.total_size),
+            memory: FixedVec::new((0, 0)),
+            reserved: FixedVec::new((0, 0)),
+            cpu_count: 0,
+            timebase_frequency: None,
+            cbom_block_size: None,
+            uart: None,
+            gicd: None,
+            gicc: None,
+            plic: None,
+            rtc: None,
+            virtio_mmio: FixedVec::new(FdtDevice::EMPTY),
+            dropped_memory: 0,
+            dropped_reserved: 0,
+            dropped_virtio_mmio: 0,
+        };
+        info.reserved.push(info.dtb);
+        for entry in self.mem_reservations() {
+            if !info.reserved.push(entry) {
+                info.dropped_reserved += 1;
+            }
+        }
+
+        // The first pass finds the path of the console, which may be an alias.
+        let mut stdout_path = None;
+        let mut aliases = None;
+        self.walk(|parents, node| {
+            if parents.len() == 1 && node.name == "chosen" {
+                stdout_path = node
+                    .prop("stdout-path")
+                    .or_else(|| node.prop("linux,stdout-path"));
+            } else if parents.len() == 1 && node.name == "aliases" {
+                aliases = Some(*node);
+            }
+        })?;
+        // Strip the options, e.g. `serial0:115200n8`.
+        let stdout_path = stdout_path
+            .map(|path| {
+                prop_str(path)
+                    .split(|&b| b == b':')
+                    .next()
+                    .unwrap_or_default()
+            })
+            .map(|path| match (path.first(), aliases) {
+                (Some(b'/'), _) | (_, None) => path,
+                (_, Some(aliases)) => core::str::from_utf8(path)
+                    .ok()
+                    .and_then(|alias| aliases.prop(alias))
+                    .map_or(path, prop_str),
+            });
+
+        self.walk(|parents, node| {
+            let Some(parent) = parents.last() else {
+                return; // the root node
+            };
+            if !node.is_enabled() {
+                return;
+            }
+            if parents.len() == 1 && node.is_device_type("memory") {
+                for entry in node.reg(parent) {
+                    if !info.memory.push(entry) {
+                        info.dropped_memory += 1;
+                    }
+                }
+            } else if parents.len() == 2 && parent.name == "reserved-memory" {
+                for entry in node.reg(parent) {
+                    if !info.reserved.push(entry) {
+                        info.dropped_reserved += 1;
+                    }
+                }
+            } else if parents.len() == 1 && node.name == "cpus" {
+                info.timebase_frequency = node.prop_u32("timebase-frequency").map(Into::into);
+            } else if parents.len() == 2 && parent.name == "cpus" && node.base_name() == "cpu" {
+                // Cache maintenance is only done if all CPUs agree on it.
+                let cbom_block_size = node.cbom_block_size();
+                if info.cpu_count == 0 {
+                    info.cbom_block_size = cbom_block_size;
+                } else if info.cbom_block_size != cbom_block_size {
+                    info.cbom_block_size = None;
+                }
+                info.cpu_count += 1;
+            } else if node.is_compatible(UART_COMPATIBLES) {
+                let is_stdout = stdout_path.is_some_and(|path| path_matches(path, parents, node));
+                if is_stdout || (stdout_path.is_none() && info.uart.is_none()) {
+                    info.uart = node.device(parent, 0);
+                }
+            } else if node.is_compatible(GIC_COMPATIBLES) {
+                info.gicd = node.device(parent, 0);
+                info.gicc = node.device(parent, 1);
+            } else if node.is_compatible(PLIC_COMPATIBLES) {
+                info.plic = node.device(parent, 0);
+            } else if node.is_compatible(RTC_COMPATIBLES) {
+                info.rtc = node.device(parent, 0);
+            } else if node.is_compatible(VIRTIO_MMIO_COMPATIBLES) {
+                let dropped = node
+                    .device(parent, 0)
+                    .is_some_and(|dev| !info.virtio_mmio.push(dev));
+                if dropped {
+                    info.dropped_virtio_mmio += 1;
+                }
+            }
+        })?;
+        // Probe devices in the order of addresses, as their order in the
+        // device tree is unspecified.
+        info.virtio_mmio
+            .as_mut_slice()
+            .sort_unstable_by_key(|dev| dev.paddr);
+        Some(info)
+    }
+}
+
+/// Returns the platform resources discovered from the device tree.
+///
+/// Returns `None` if the platform does not boot with a valid device tree.
+pub fn info() -> Option<&'static FdtInfo> {
+    FDT_INFO.get()
+}
+
+/// Parses the device tree blob at the given physical address.
+///
+/// It must be called by the primary CPU at boot, before the memory regions
+/// are used. Does nothing if there is no valid device tree.
+#[allow(dead_code)]
+pub(crate) fn init(dtb: usize) {
+    if dtb == 0 || FDT_INFO.is_inited() {
+        return;
+    }
+    let ptr = phys_to_virt(pa!(dtb)).as_ptr();
+    if let Some(info) = unsafe { Fdt::from_ptr(ptr) }.and_then(|fdt| fdt.parse(dtb)) {
+        FDT_INFO.init_once(info);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DTB_PADDR: usize = 0x4800_0000;
+
+    /// Builds device tree blobs.
+    #[derive(Default)]
+    struct Builder {
+        structs: Vec<u8>,
+        strings: Vec<u8>,
+        mem_rsvmap: Vec<(u64, u64)>,
+    }
+
+    impl Builder {
+        fn token(&mut self, token: u32) -> &mut Self {
+            self.structs.extend(token.to_be_bytes());
+            self
+        }
+
+        fn pad(&mut self) {
+            self.structs.resize(align4(self.structs.len()), 0);
+        }
+
+        fn begin(&mut self, name: &str) -> &mut Self {
+            self.token(FDT_BEGIN_NODE);
+            self.structs.extend(name.as_bytes());
+            self.structs.push(0);
+            self.pad();
+            self
+        }
+
+        fn end(&mut self) -> &mut Self {
+            self.token(FDT_END_NODE)
+        }
+
+        fn prop(&mut self, name: &str, value: &[u8]) -> &mut Self {
+            let name_offset = self.strings.len() as u32;
+            self.strings.extend(name.as_bytes());
+            self.strings.push(0);
+            self.token(FDT_PROP)
+                .token(value.len() as u32)
+                .token(name_offset);
+            self.structs.extend(value);
+            self.pad();
+            self
+        }
+
+        fn prop_str(&mut self, name: &str, value: &str) -> &mut Self {
+            self.prop(name, format!("{value}\0").as_bytes())
+        }
+
+        fn prop_cells(&mut self, name: &str, cells: &[u32]) -> &mut Self {
+            let value: Vec<u8> = cells.iter().flat_map(|c| c.to_be_bytes()).collect();
+            self.prop(name, &value)
+        }
+
+        /// Adds a node with `reg` entries of one address cell and one size
+        /// cell.
+        fn reg_node(&mut self, name: &str, compatible: &str, regs: &[(u32, u32)]) -> &mut Self {
+            let cells: Vec<u32> = regs.iter().flat_map(|&(a, s)| [a, s]).collect();
+            self.begin(name);
+            if !compatible.is_empty() {
+                self.prop_str("compatible", compatible);
+            }
+            self.prop_cells("reg", &cells).end()
+        }
+
+        fn build(&mut self) -> Vec<u8> {
+            self.token(FDT_END);
+            let off_rsvmap = FDT_HEADER_SIZE;
+            let off_struct = off_rsvmap + (self.mem_rsvmap.len() + 1) * 16;
+            let off_strings = off_struct + self.structs.len();
+            let total_size = off_strings + self.strings.len();
+            let header = [
+                FDT_MAGIC,
+                total_size as u32,
+                off_struct as u32,
+                off_strings as u32,
+                off_rsvmap as u32,
+                17,
+                16,
+                0,
+                self.strings.len() as u32,
+                self.structs.len() as u32,
+            ];
+            let mut blob: Vec<u8> = header.iter().flat_map(|v| v.to_be_bytes()).collect();
+            for &(address, size) in self.mem_rsvmap.iter().chain(&[(0, 0)]) {
+                blob.extend(address.to_be_bytes());
+                blob.extend(size.to_be_bytes());
+            }
+            blob.extend(&self.structs);
+            blob.extend(&self.strings);
+            blob
+        }
+    }
+
+    fn parse(blob: &[u8]) -> Option<FdtInfo> {
+        unsafe { Fdt::from_ptr(blob.as_ptr()) }?.parse(DTB_PADDR)
+    }
+
+    /// The root node with one address cell and one size cell.
+    fn root() -> Builder {
+        let mut b = Builder::default();
+        b.begin("")
+            .prop_cells("#address-cells", &[1])
+            .prop_cells("#size-cells", &[1]);
+        b
+    }
+
+    #[test]
+    fn discover() {
+        let mut b = root();
+        b.mem_rsvmap.push((0x4000_0000, 0x1000));
+        b.begin("chosen")
+            .prop_str("stdout-path", "serial0:115200n8")
+            .end();
+        b.begin("aliases")
+            .prop_str("serial0", "/soc/serial@10000000")
+            .end();
+        b.begin("memory@40000000")
+            .prop_str("device_type", "memory")
+            .prop_cells("reg", &[0x4000_0000, 0x800_0000])
+            .end();
+        b.begin("reserved-memory")
+            .prop_cells("#address-cells", &[1])
+            .prop_cells("#size-cells", &[1])
+            .reg_node("firmware@40100000", "", &[(0x4010_0000, 0x2_0000)])
+            .end();
+        b.begin("cpus")
+            .prop_cells("timebase-frequency", &[10_000_000]);
+        b.begin("cpu@0").end().begin("cpu@1").end();
+        b.begin("cpu@2").prop_str("status", "disabled").end();
+        b.end();
+        b.begin("soc")
+            .prop_cells("#address-cells", &[1])
+            .prop_cells("#size-cells", &[1])
+            .reg_node("serial@9000000", "ns16550a", &[(0x900_0000, 0x100)]);
+        b.begin("serial@10000000")
+            .prop_str("compatible", "ns16550a")
+            .prop_cells("reg", &[0x1000_0000, 0x100])
+            .prop_cells("interrupts", &[10])
+            .end();
+        b.reg_node(
+            "virtio_mmio@10002000",
+            "virtio,mmio",
+            &[(0x1000_2000, 0x1000)],
+        )
+        .reg_node(
+            "virtio_mmio@10001000",
+            "virtio,mmio",
+            &[(0x1000_1000, 0x1000)],
+        )
+        .end();
+        let blob = b.end().build();
+
+        let info = parse(&blob).unwrap();
+        assert_eq!(info.dtb_region(), (DTB_PADDR, blob.len()));
+        assert_eq!(info.memory_regions(), [(0x4000_0000, 0x800_0000)]);
+        assert_eq!(
+            info.reserved_regions(),
+            [
+                (DTB_PADDR, blob.len()),
+                (0x4000_0000, 0x1000),
+                (0x4010_0000, 0x2_0000),
+            ]
+        );
+        assert_eq!(info.cpu_count(), 2);
+        assert_eq!(info.timebase_frequency(), Some(10_000_000));
+        // The console referred by the alias, rather than the first UART.
+        let uart = info.uart().unwrap();
+        assert_eq!(
+            (uart.paddr, uart.size, uart.irq),
+            (0x1000_0000, 0x100, Some(10))
+        );
+        // Sorted by addresses.
+        let virtio: Vec<_> = info.virtio_mmio_devices().iter().map(|d| d.paddr).collect();
+        assert_eq!(virtio, [0x1000_1000, 0x1000_2000]);
+        assert_eq!(info.dropped_memory_regions(), 0);
+        assert_eq!(info.dropped_virtio_mmio_devices(), 0);
+    }
+
+    #[test]
+    fn count_dropped() {
+        let regs: Vec<_> = (0..MAX_MEMORY_REGIONS as u32 + 1)
+            .flat_map(|i| [0x4000_0000 + i * 0x100_0000, 0x10_0000])
+            .collect();
+        let mut b = root();
+        b.begin("memory@40000000")
+            .prop_str("device_type", "memory")
+            .prop_cells("reg", &regs)
+            .end();
+        for i in 0..MAX_VIRTIO_MMIO_DEVICES as u32 + 2 {
+            let paddr = 0x1000_0000 + i * 0x1000;
+            let name = format!("virtio_mmio@{paddr:x}");
+            b.reg_node(&name, "virtio,mmio", &[(paddr, 0x1000)]);
+        }
+        let info = parse(&b.end().build()).unwrap();
+        assert_eq!(info.memory_regions().len(), MAX_MEMORY_REGIONS);
+        assert_eq!(info.memory_regions()[0], (0x4000_0000, 0x10_0000));
+        assert_eq!(info.dropped_memory_regions(), 1);
+        assert_eq!(info.virtio_mmio_devices().len(), MAX_VIRTIO_MMIO_DEVICES);
+        assert_eq!(info.dropped_virtio_mmio_devices(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "reserved memory regions")]
+    fn too_many_reserved() {
+        let mut b = root();
+        // One more than the limit with the device tree blob itself.
+        b.mem_rsvmap = (0..MAX_RESERVED_REGIONS as u64)
+            .map(|i| (0x4000_0000 + i * 0x1000, 0x1000))
+            .collect();
+        let info = parse(&b.end().build()).unwrap();
+        info.reserved_regions();
+    }
+
+    #[test]
+    fn malformed() {
+        let mut b = root();
+        let mut blob = b.end().build();
+        assert!(parse(&blob).is_some());
+
+        // Bad magic number.
+        blob[0] = 0;
+        assert!(parse(&blob).is_none());
+
+        // Unbalanced nodes.
+        let mut b = root();
+        assert!(parse(&b.end().end().build()).is_none());
+
+        // Unknown token.
+        let mut b = root();
+        assert!(parse(&b.token(0x7).end().build()).is_none());
+    }
+}
@@ -1,25 +1,151 @@
 //! Interrupt management.
 
-use core::sync::atomic::{AtomicU32, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 
+use axconfig::SMP;
 use handler_table::HandlerTable;
+use kspin::SpinNoIrq;
 
 use crate::platform::irq::{MAX_IRQ_COUNT, dispatch_irq};
-use crate::trap::{IRQ, register_trap_handler};
+use crate::trap::{IRQ, IrqTrapHandler, TrapHandler, register_trap_handler};
 
-pub use crate::platform::irq::{register_handler, set_enable};
+pub use crate::platform::irq::{
+    alloc_msi_irq, msi_message, pci_intx_irq, register_handler, set_enable, set_irq_affinity,
+};
 
 /// The type if an IRQ handler.
 pub type IrqHandler = handler_table::Handler;
 
 static IRQ_HANDLER_TABLE: HandlerTable<MAX_IRQ_COUNT> = HandlerTable::new();
 
-pub static IRQ_STATISTICS: [AtomicU32; 512] = [const { AtomicU32::new(0) }; 512];
+/// The maximum number of IRQs that statistics are kept for.
+const MAX_IRQ_STATS: usize = 64;
+
+/// `irq_num` of an unused [`IrqStatSlot`].
+const UNUSED_SLOT: usize = usize::MAX;
+
+struct IrqStatSlot {
+    irq_num: AtomicUsize,
+    name: SpinNoIrq<&'static str>,
+    counts: [AtomicU64; SMP],
+}
+
+/// Slots are taken in order and never released, so the used ones are always
+/// at the front.
+static IRQ_STAT_SLOTS: [IrqStatSlot; MAX_IRQ_STATS] = [const {
+    IrqStatSlot {
+        irq_num: AtomicUsize::new(UNUSED_SLOT),
+        name: SpinNoIrq::new(""),
+        counts: [const { AtomicU64::new(0) }; SMP],
+    }
+}; MAX_IRQ_STATS];
+
+/// Whether an IRQ has been found without a free statistics slot.
+static IRQ_STATS_FULL: AtomicBool = AtomicBool::new(false);
+
+/// Returns the statistics slot of the given IRQ, takes a new one if it does
+/// not exist.
+///
+/// It's called on every IRQ, so the used slots are only loaded, and a compare
+/// and exchange is done only to take the first unused slot.
+fn irq_stat_slot(irq_num: usize) -> Option<&'static IrqStatSlot> {
+    for slot in &IRQ_STAT_SLOTS {
+        let mut num = slot.irq_num.load(Ordering::Acquire);
+        if num == UNUSED_SLOT {
+            num = match slot.irq_num.compare_exchange(
+                UNUSED_SLOT,
+                irq_num,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => irq_num,
+                Err(num) => num,
+            };
+        }
+        if num == irq_num {
+            return Some(slot);
+        }
+    }
+    if !IRQ_STATS_FULL.swap(true, Ordering::Relaxed) {
+        warn!(
+            "no statistics for IRQ {}: more than {} IRQs",
+            irq_num, MAX_IRQ_STATS
+        );
+    }
+    None
+}
+
+/// Counts an occurrence of the IRQ on the current CPU.
+pub(crate) fn count_irq(irq_num: usize) {
+    if let Some(slot) = irq_stat_slot(irq_num) {
+        slot.counts[crate::cpu::this_cpu_id()].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Statistics of an IRQ.
+#[derive(Debug, Clone)]
+pub struct IrqStat {
+    /// The IRQ number, as used in [`register_handler`].
+    pub irq_num: usize,
+    /// The name of the handler, or empty if it is not given.
+    pub name: &'static str,
+    /// The number of occurrences on each CPU.
+    pub counts: [u64; SMP],
+}
+
+impl IrqStat {
+    /// The total number of occurrences on all CPUs.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+/// Returns the statistics of all IRQs that have occurred or have a named
+/// handler, in the order of their first appearance.
+pub fn irq_stats() -> impl Iterator<Item = IrqStat> {
+    IRQ_STAT_SLOTS
+        .iter()
+        .map_while(|slot| {
+            let irq_num = slot.irq_num.load(Ordering::Acquire);
+            (irq_num != UNUSED_SLOT).then_some((irq_num, slot))
+        })
+        .map(|(irq_num, slot)| IrqStat {
+            irq_num,
+            name: *slot.name.lock(),
+            counts: core::array::from_fn(|cpu_id| slot.counts[cpu_id].load(Ordering::Relaxed)),
+        })
+}
+
+/// Registers an IRQ handler for the given IRQ, and names it in the
+/// statistics (see [`irq_stats`]).
+///
+/// It also enables the IRQ if the registration succeeds. It returns `false` if
+/// the registration failed.
+pub fn register_named_handler(irq_num: usize, name: &'static str, handler: IrqHandler) -> bool {
+    if !register_handler(irq_num, handler) {
+        return false;
+    }
+    if let Some(slot) = irq_stat_slot(irq_num) {
+        *slot.name.lock() = name;
+    }
+    true
+}
+
+/// The message written by a device to signal a message signaled interrupt
+/// (MSI or MSI-X).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MsiMessage {
+    /// The address to write.
+    pub address: u64,
+    /// The data to write.
+    pub data: u32,
+}
 
 /// Platform-independent IRQ dispatching.
 #[allow(dead_code)]
 pub(crate) fn dispatch_irq_common(irq_num: usize) {
     trace!("IRQ {}", irq_num);
+    count_irq(irq_num);
     if !IRQ_HANDLER_TABLE.handle(irq_num) {
         warn!("Unhandled IRQ {}", irq_num);
     }
@@ -40,10 +166,10 @@ pub(crate) fn register_handler_common(irq_num: usize, handler: IrqHandler) -> bo
 }
 
 #[register_trap_handler(IRQ)]
+static IRQ_TRAP_HANDLER: TrapHandler<IrqTrapHandler> = TrapHandler::new(handler_irq);
+
 fn handler_irq(irq_num: usize) -> bool {
     let guard = kernel_guard::NoPreempt::new();
-    //为什么%512? riscv scause.bits()
-    IRQ_STATISTICS[irq_num % 512].fetch_add(1, Ordering::Relaxed);
     dispatch_irq(irq_num);
     drop(guard); // rescheduling may occur when preemption is re-enabled.
     true
@@ -20,11 +20,12 @@
 //! - `fp_simd`: Enable floating-point and SIMD support.
 //! - `paging`: Enable page table manipulation.
 //! - `irq`: Enable interrupt handling support.
+//! - `backtrace`: Enable stack unwinding and symbolized backtraces.
 //!
 //! [ArceOS]: https://github.com/arceos-org/arceos
 //! [cargo test]: https://doc.rust-lang.org/cargo/guide/tests.html
 
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 #![feature(doc_auto_cfg)]
 #![feature(sync_unsafe_cell)]
 
@@ -41,37 +42,46 @@ mod platform;
 #[macro_use]
 pub mod trap;
 
+#[cfg(target_arch = "x86_64")]
+pub mod acpi;
 pub mod arch;
 pub mod cpu;
+pub mod fdt;
 pub mod mem;
 pub mod time;
 
 #[cfg(feature = "tls")]
 pub mod tls;
 
+#[cfg(feature = "backtrace")]
+pub mod backtrace;
+
 #[cfg(feature = "irq")]
 pub mod irq;
 
 #[cfg(feature = "paging")]
 pub mod paging;
 
-/// Console input and output.
-pub mod console {
-    pub use super::platform::console::*;
-}
+pub mod console;
 
-/// Miscellaneous operation, e.g. terminate the system.
-pub mod misc {
-    pub use super::platform::misc::*;
-}
+pub mod misc;
 
-/// Multi-core operations.
 #[cfg(feature = "smp")]
-pub mod mp {
-    pub use super::platform::mp::*;
-}
+pub mod mp;
 
-pub use self::platform::platform_init;
+/// Initializes the platform devices for the primary CPU.
+///
+/// For example, the interrupt controller and the timer.
+pub fn platform_init() {
+    self::platform::platform_init();
+    #[cfg(all(feature = "smp", feature = "irq"))]
+    self::mp::init_primary();
+}
 
+/// Initializes the platform devices for secondary CPUs.
 #[cfg(feature = "smp")]
-pub use self::platform::platform_init_secondary;
+pub fn platform_init_secondary() {
+    self::platform::platform_init_secondary();
+    #[cfg(feature = "irq")]
+    self::mp::init_secondary();
+}
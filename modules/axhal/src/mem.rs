@@ -4,6 +4,8 @@ use core::fmt;
 
 use axconfig::plat::{PHYS_MEMORY_BASE, PHYS_MEMORY_SIZE, PHYS_VIRT_OFFSET};
 
+use crate::fdt::FixedVec;
+
 #[doc(no_inline)]
 pub use memory_addr::{MemoryAddr, PAGE_SIZE_4K, PhysAddr, VirtAddr};
 
@@ -138,6 +140,112 @@ pub(crate) fn default_free_regions() -> impl Iterator<Item = MemRegion> {
     })
 }
 
+/// The maximum number of memory regions derived from the device tree or the
+/// ACPI tables.
+const MAX_DISCOVERED_REGIONS: usize = 64;
+
+/// Returns the free memory regions in the RAM discovered from the device tree,
+/// except the kernel image and the reserved memory.
+///
+/// RAM beyond `mapped_end` is ignored, as it's not mapped by the boot page
+/// table. Falls back to the default free memory regions if there is no device
+/// tree.
+#[allow(dead_code)]
+pub(crate) fn fdt_free_regions(mapped_end: usize) -> impl Iterator<Item = MemRegion> {
+    let kernel_end = virt_to_phys((_ekernel as usize).into()).as_usize();
+    let mut free = FixedVec::<(usize, usize), MAX_DISCOVERED_REGIONS>::new((0, 0));
+    if let Some(info) = crate::fdt::info() {
+        for &(base, size) in info.memory_regions() {
+            let start = base.max(kernel_end);
+            let end = base.saturating_add(size).min(mapped_end);
+            if start < end {
+                free.push((start, end));
+            }
+        }
+        for &(base, size) in info.reserved_regions() {
+            let (rsv_start, rsv_end) = (base, base.saturating_add(size));
+            let mut remaining = FixedVec::new((0, 0));
+            for (start, end) in free {
+                if rsv_end <= start || rsv_start >= end {
+                    remaining.push((start, end));
+                    continue;
+                }
+                if start < rsv_start {
+                    remaining.push((start, rsv_start));
+                }
+                if rsv_end < end {
+                    remaining.push((rsv_end, end));
+                }
+            }
+            free = remaining;
+        }
+    } else {
+        free.push((kernel_end, PHYS_MEMORY_BASE + PHYS_MEMORY_SIZE));
+    }
+    free.into_iter().filter_map(|(start, end)| {
+        let start = pa!(start).align_up_4k();
+        let end = pa!(end).align_down_4k();
+        (start < end).then(|| MemRegion {
+            paddr: start,
+            size: end.as_usize() - start.as_usize(),
+            flags: MemRegionFlags::FREE | MemRegionFlags::READ | MemRegionFlags::WRITE,
+            name: "free memory",
+        })
+    })
+}
+
+/// Returns the MMIO regions of devices discovered from the device tree that
+/// are not in [`axconfig::devices::MMIO_REGIONS`].
+#[allow(dead_code)]
+pub(crate) fn fdt_mmio_regions() -> impl Iterator<Item = MemRegion> {
+    let devices = crate::fdt::info()
+        .into_iter()
+        .flat_map(|info| info.devices());
+    extra_mmio_regions(devices.map(|dev| (dev.paddr, dev.size)))
+}
+
+/// Returns the page-aligned MMIO regions in `devices` (`(paddr, size)` pairs)
+/// that do not overlap with [`axconfig::devices::MMIO_REGIONS`].
+#[allow(dead_code)]
+pub(crate) fn extra_mmio_regions(
+    devices: impl Iterator<Item = (usize, usize)>,
+) -> impl Iterator<Item = MemRegion> {
+    let overlapped = |start: usize, end: usize| {
+        axconfig::devices::MMIO_REGIONS
+            .iter()
+            .any(|reg| reg.0 < end && start < reg.0 + reg.1)
+    };
+    let mut regions = FixedVec::<(usize, usize), MAX_DISCOVERED_REGIONS>::new((0, 0));
+    for (paddr, size) in devices.filter(|&(_, size)| size != 0) {
+        let start = pa!(paddr).align_down_4k().as_usize();
+        let end = pa!(paddr + size).align_up_4k().as_usize();
+        if !overlapped(start, end) {
+            regions.push((start, end));
+        }
+    }
+    // Merge the overlapping regions, as a page can only be mapped once.
+    regions.as_mut_slice().sort_unstable();
+    let mut merged = FixedVec::<(usize, usize), MAX_DISCOVERED_REGIONS>::new((0, 0));
+    for (start, end) in regions {
+        match merged.as_mut_slice().last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => {
+                merged.push((start, end));
+            }
+        }
+    }
+    merged.into_iter().map(|(start, end)| MemRegion {
+        paddr: start.into(),
+        size: end - start,
+        flags: MemRegionFlags::RESERVED
+            | MemRegionFlags::DEVICE
+            | MemRegionFlags::READ
+            | MemRegionFlags::WRITE
+            | MemRegionFlags::UNCACHED,
+        name: "mmio",
+    })
+}
+
 /// Fills the `.bss` section with zeros.
 #[allow(dead_code)]
 pub(crate) fn clear_bss() {
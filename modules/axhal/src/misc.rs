@@ -0,0 +1,60 @@
+//! Miscellaneous operation, e.g. terminate, power off or reboot the system.
+
+use crate::platform::misc::{system_off, system_reset};
+
+#[allow(unused_imports)]
+pub use crate::platform::misc::*;
+
+/// Halts the current CPU forever, after the platform failed to power off or
+/// reset the system.
+fn halt_forever(expected: &str) -> ! {
+    warn!("It should {}!", expected);
+    loop {
+        crate::arch::halt();
+    }
+}
+
+/// Shutdown the whole system, including all CPUs.
+///
+/// The system is powered off, or reset on `x86_64-pc-oslab` after a keypress.
+pub fn terminate() -> ! {
+    #[cfg(platform = "x86_64-pc-oslab")]
+    {
+        axlog::ax_println!("System will reboot, press any key to continue ...");
+        let mut buffer = [0u8; 1];
+        while crate::console::read_bytes(&mut buffer) == 0 {}
+        reboot()
+    }
+
+    #[cfg(not(platform = "x86_64-pc-oslab"))]
+    poweroff()
+}
+
+/// Powers off the whole system, including all CPUs.
+///
+/// The current CPU is halted forever if the platform cannot power itself off.
+pub fn poweroff() -> ! {
+    info!("Shutting down...");
+    system_off();
+    halt_forever("shutdown")
+}
+
+/// Reboots the whole system, including all CPUs.
+///
+/// The current CPU is halted forever if the platform cannot reset itself.
+pub fn reboot() -> ! {
+    info!("Rebooting...");
+    system_reset();
+    halt_forever("reboot")
+}
+
+/// Resets the whole system immediately from a panic.
+///
+/// Unlike [`reboot`], it does not log anything, as the logger may be locked
+/// by the panicking code.
+pub fn reset_on_panic() -> ! {
+    system_reset();
+    loop {
+        crate::arch::halt();
+    }
+}
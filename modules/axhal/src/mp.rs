@@ -0,0 +1,9 @@
+//! Multi-core operations.
+
+pub use super::platform::mp::*;
+
+#[cfg(feature = "irq")]
+mod ipi;
+
+#[cfg(feature = "irq")]
+pub use self::ipi::*;
@@ -0,0 +1,298 @@
+//! Inter-processor interrupts (IPIs) and cross-CPU function calls.
+//!
+//! All IPIs are delivered with the same platform-specific interrupt (e.g., an
+//! SGI on GIC or the supervisor software interrupt on RISC-V), and are
+//! multiplexed by software-defined vectors, which are pending in a per-CPU
+//! bitmap until the target CPU handles them.
+
+use core::ptr::null_mut;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+
+use axconfig::SMP;
+use handler_table::HandlerTable;
+
+use crate::cpu::this_cpu_id;
+use crate::irq::IrqHandler;
+use crate::platform::irq::IPI_IRQ_NUM;
+
+/// The maximum number of IPI vectors.
+pub const MAX_IPI_VECTORS: usize = usize::BITS as usize;
+
+/// The IPI vector reserved for cross-CPU function calls (see [`run_on_cpu`]).
+pub const CALL_FUNCTION_VECTOR: usize = 0;
+
+/// The IPI vector that asks the target CPU to reschedule.
+///
+/// Any IPI wakes up an idle CPU, so this vector is also used to notify a CPU
+/// that new tasks are ready.
+pub const RESCHEDULE_VECTOR: usize = 1;
+
+static IPI_HANDLERS: HandlerTable<MAX_IPI_VECTORS> = HandlerTable::new();
+
+/// Bitmaps of pending vectors, one for each CPU.
+static PENDING_VECTORS: [AtomicUsize; SMP] = [const { AtomicUsize::new(0) }; SMP];
+
+/// Whether the CPU is able to receive IPIs.
+static ONLINE_CPUS: [AtomicBool; SMP] = [const { AtomicBool::new(false) }; SMP];
+
+/// Whether an IPI to the offline CPU has been reported, to warn only once.
+static OFFLINE_WARNED: [AtomicBool; SMP] = [const { AtomicBool::new(false) }; SMP];
+
+/// A cross-CPU function call.
+///
+/// It lives on the stack of the calling CPU, which waits until all target
+/// CPUs have run the function.
+struct CallData<'a> {
+    func: &'a (dyn Fn() + Sync),
+    remaining: AtomicUsize,
+}
+
+/// Mailboxes of cross-CPU function calls among `N` CPUs.
+///
+/// `slots[target][source]` points to the [`CallData`] sent from `source` to
+/// `target`, if any. A CPU has at most one outstanding call at a time, as it
+/// waits for the call to complete with preemption disabled.
+struct CallTable<const N: usize> {
+    slots: [[AtomicPtr<()>; N]; N],
+}
+
+impl<const N: usize> CallTable<N> {
+    const fn new() -> Self {
+        Self {
+            slots: [const { [const { AtomicPtr::new(null_mut()) }; N] }; N],
+        }
+    }
+
+    /// Posts `call` from `source` to `target`, which runs it in
+    /// [`Self::handle`].
+    fn post(&self, source: usize, target: usize, call: &CallData) {
+        call.remaining.fetch_add(1, Ordering::Relaxed);
+        let call_ptr = call as *const CallData as *mut ();
+        self.slots[target][source].store(call_ptr, Ordering::Release);
+    }
+
+    /// Runs the calls posted to `cpu_id`.
+    fn handle(&self, cpu_id: usize) {
+        for slot in self.slots[cpu_id].iter() {
+            let call_ptr = slot.swap(null_mut(), Ordering::Acquire);
+            if !call_ptr.is_null() {
+                // SAFETY: the caller waits for `remaining` to drop to zero
+                // before releasing the `CallData`.
+                let call = unsafe { &*(call_ptr as *const CallData) };
+                (call.func)();
+                call.remaining.fetch_sub(1, Ordering::Release);
+            }
+        }
+    }
+
+    /// Waits until all targets of `call` have run it.
+    ///
+    /// Calls posted to `cpu_id` (the caller) are served in the meantime, in
+    /// case that their senders are waiting for us with IRQs disabled.
+    fn wait(&self, cpu_id: usize, call: &CallData) {
+        while call.remaining.load(Ordering::Acquire) != 0 {
+            self.handle(cpu_id);
+            core::hint::spin_loop();
+        }
+    }
+}
+
+static CALLS: CallTable<SMP> = CallTable::new();
+
+/// Whether the given CPU is able to receive IPIs.
+pub fn is_cpu_online(cpu_id: usize) -> bool {
+    cpu_id < SMP && ONLINE_CPUS[cpu_id].load(Ordering::Acquire)
+}
+
+/// Sends an IPI with the given vector to the given CPU.
+///
+/// The IPI is dropped if the CPU is not online.
+pub fn send_ipi(cpu_id: usize, vector: usize) {
+    assert!(vector < MAX_IPI_VECTORS, "invalid IPI vector {}", vector);
+    if !is_cpu_online(cpu_id) {
+        if cpu_id >= SMP || !OFFLINE_WARNED[cpu_id].swap(true, Ordering::Relaxed) {
+            warn!("send IPI {} to offline CPU {}", vector, cpu_id);
+        }
+        return;
+    }
+    trace!("send IPI {} to CPU {}", vector, cpu_id);
+    PENDING_VECTORS[cpu_id].fetch_or(1 << vector, Ordering::Release);
+    crate::platform::irq::send_ipi(cpu_id);
+}
+
+/// Sends an IPI with the given vector to all other online CPUs.
+pub fn broadcast_ipi(vector: usize) {
+    let this_cpu = this_cpu_id();
+    for cpu_id in (0..SMP).filter(|&id| id != this_cpu && is_cpu_online(id)) {
+        send_ipi(cpu_id, vector);
+    }
+}
+
+/// Registers a handler for the given IPI vector.
+///
+/// The handler is called in the interrupt context of the target CPU. It
+/// returns `false` if the vector is reserved, or the registration failed.
+pub fn register_ipi_handler(vector: usize, handler: IrqHandler) -> bool {
+    if vector != CALL_FUNCTION_VECTOR && IPI_HANDLERS.register_handler(vector, handler) {
+        return true;
+    }
+    warn!("register handler for IPI vector {} failed", vector);
+    false
+}
+
+/// Runs `func` on the given CPU, and waits for its completion.
+///
+/// `func` is run in the interrupt context of the target CPU, or with IRQs
+/// disabled if the target is the current CPU. It returns `false` if the
+/// target CPU is not online.
+///
+/// The caller must not hold a lock that the target CPU may wait for with IRQs
+/// disabled, otherwise a deadlock occurs.
+pub fn run_on_cpu<F: Fn() + Sync>(cpu_id: usize, func: F) -> bool {
+    let _guard = kernel_guard::NoPreempt::new();
+    if cpu_id == this_cpu_id() {
+        let _guard = kernel_guard::IrqSave::new();
+        func();
+        true
+    } else if is_cpu_online(cpu_id) {
+        call_on_cpus(core::iter::once(cpu_id), &func);
+        true
+    } else {
+        false
+    }
+}
+
+/// Runs `func` on all other online CPUs, and waits for their completion.
+///
+/// See [`run_on_cpu`] for details.
+pub fn run_on_other_cpus<F: Fn() + Sync>(func: F) {
+    let _guard = kernel_guard::NoPreempt::new();
+    let this_cpu = this_cpu_id();
+    call_on_cpus(
+        (0..SMP).filter(|&id| id != this_cpu && is_cpu_online(id)),
+        &func,
+    );
+}
+
+/// Runs `func` on all online CPUs, including the current one, and waits for
+/// their completion.
+///
+/// See [`run_on_cpu`] for details.
+pub fn run_on_each_cpu<F: Fn() + Sync>(func: F) {
+    let _guard = kernel_guard::NoPreempt::new();
+    run_on_other_cpus(&func);
+    let _guard = kernel_guard::IrqSave::new();
+    func();
+}
+
+/// Sends a call to each of `targets`, and waits until all of them are done.
+///
+/// Preemption must be disabled, and `targets` must not contain the current
+/// CPU.
+fn call_on_cpus(targets: impl Iterator<Item = usize>, func: &(dyn Fn() + Sync)) {
+    let this_cpu = this_cpu_id();
+    let call = CallData {
+        func,
+        remaining: AtomicUsize::new(0),
+    };
+    for cpu_id in targets {
+        CALLS.post(this_cpu, cpu_id, &call);
+        send_ipi(cpu_id, CALL_FUNCTION_VECTOR);
+    }
+    CALLS.wait(this_cpu, &call);
+}
+
+fn handle_ipi() {
+    let cpu_id = this_cpu_id();
+    let mut pending = PENDING_VECTORS[cpu_id].swap(0, Ordering::Acquire);
+    while pending != 0 {
+        let vector = pending.trailing_zeros() as usize;
+        pending &= pending - 1;
+        trace!("IPI {} on CPU {}", vector, cpu_id);
+        if vector == CALL_FUNCTION_VECTOR {
+            CALLS.handle(cpu_id);
+        } else if !IPI_HANDLERS.handle(vector) {
+            warn!("Unhandled IPI vector {}", vector);
+        }
+    }
+}
+
+/// Registers the IPI handler, and enables IPIs on the primary CPU.
+pub(crate) fn init_primary() {
+    info!("Initialize IPIs...");
+    crate::irq::register_named_handler(IPI_IRQ_NUM, "ipi", handle_ipi);
+    init_secondary();
+}
+
+/// Enables IPIs on secondary CPUs.
+pub(crate) fn init_secondary() {
+    crate::irq::set_enable(IPI_IRQ_NUM, true);
+    ONLINE_CPUS[this_cpu_id()].store(true, Ordering::Release);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Barrier, Mutex};
+
+    use super::*;
+
+    #[test]
+    fn call_and_wait() {
+        static TABLE: CallTable<2> = CallTable::new();
+        let ran = AtomicUsize::new(0);
+        let func = || {
+            ran.fetch_add(1, Ordering::Relaxed);
+        };
+        let call = CallData {
+            func: &func,
+            remaining: AtomicUsize::new(0),
+        };
+        TABLE.post(0, 1, &call);
+        assert_eq!(call.remaining.load(Ordering::Relaxed), 1);
+        TABLE.handle(0);
+        assert_eq!(ran.load(Ordering::Relaxed), 0);
+        TABLE.handle(1);
+        assert_eq!(ran.load(Ordering::Relaxed), 1);
+        TABLE.wait(0, &call);
+        // The call is run only once.
+        TABLE.handle(1);
+        assert_eq!(ran.load(Ordering::Relaxed), 1);
+    }
+
+    /// Two CPUs calling each other at the same time, e.g., both sending TLB
+    /// shootdowns, must not wait for each other forever.
+    #[test]
+    fn cross_calls() {
+        static TABLE: CallTable<2> = CallTable::new();
+        static DONE: AtomicUsize = AtomicUsize::new(0);
+        let barrier = Arc::new(Barrier::new(2));
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let threads: Vec<_> = (0..2)
+            .map(|cpu_id| {
+                let barrier = barrier.clone();
+                let log = log.clone();
+                std::thread::spawn(move || {
+                    let func = || log.lock().unwrap().push(1 - cpu_id);
+                    let call = CallData {
+                        func: &func,
+                        remaining: AtomicUsize::new(0),
+                    };
+                    barrier.wait();
+                    TABLE.post(cpu_id, 1 - cpu_id, &call);
+                    TABLE.wait(cpu_id, &call);
+                    // Keep serving calls as the IPI handler does.
+                    DONE.fetch_add(1, Ordering::AcqRel);
+                    while DONE.load(Ordering::Acquire) < 2 {
+                        TABLE.handle(cpu_id);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let mut log = log.lock().unwrap().clone();
+        log.sort();
+        assert_eq!(log, [0, 1]);
+    }
+}
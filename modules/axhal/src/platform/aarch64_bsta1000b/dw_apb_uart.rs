@@ -53,14 +53,12 @@ pub fn init_early() {
     UART.lock().init();
 }
 
-/// Set UART IRQ Enable
+/// The IRQ number of console input.
 #[cfg(feature = "irq")]
-pub fn init_irq() {
-    UART.lock().set_ier(true);
-    crate::irq::register_handler(crate::platform::irq::UART_IRQ_NUM, handle);
-}
+pub(crate) const INPUT_IRQ_NUM: Option<usize> = Some(crate::platform::irq::UART_IRQ_NUM);
 
-/// UART IRQ Handler
-pub fn handle() {
-    trace!("Uart IRQ Handler");
+/// Enables the receive interrupt of the UART.
+#[cfg(feature = "irq")]
+pub(crate) fn enable_input_irq() {
+    UART.lock().set_ier(true);
 }
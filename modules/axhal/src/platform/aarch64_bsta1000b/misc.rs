@@ -1,4 +1,4 @@
-pub use crate::platform::aarch64_common::psci::system_off as terminate;
+pub(crate) use crate::platform::aarch64_common::psci::{system_off, system_reset};
 
 use crate::mem::phys_to_virt;
 use crate::time::{Duration, busy_wait};
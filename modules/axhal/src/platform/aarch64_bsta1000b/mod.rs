@@ -46,8 +46,6 @@ pub fn platform_init() {
     #[cfg(feature = "irq")]
     super::aarch64_common::gic::init_primary();
     super::aarch64_common::generic_timer::init_percpu();
-    #[cfg(feature = "irq")]
-    dw_apb_uart::init_irq();
 }
 
 /// Initializes the platform devices for secondary CPUs.
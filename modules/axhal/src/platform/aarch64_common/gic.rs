@@ -10,9 +10,17 @@ pub const MAX_IRQ_COUNT: usize = 1024;
 /// The timer IRQ number.
 pub const TIMER_IRQ_NUM: usize = translate_irq(14, InterruptType::PPI).unwrap();
 
+/// The IRQ number of inter-processor interrupts (SGI 1).
+#[cfg(feature = "smp")]
+pub const IPI_IRQ_NUM: usize = translate_irq(1, InterruptType::SGI).unwrap();
+
 /// The UART IRQ number.
 pub const UART_IRQ_NUM: usize = translate_irq(UART_IRQ, InterruptType::SPI).unwrap();
 
+/// The offset of the interrupt processor targets registers (`GICD_ITARGETSR`)
+/// in GICD, one byte per IRQ.
+const GICD_ITARGETSR_OFFSET: usize = 0x800;
+
 const GICD_BASE: PhysAddr = pa!(GICD_PADDR);
 const GICC_BASE: PhysAddr = pa!(GICC_PADDR);
 
@@ -28,6 +36,51 @@ pub fn set_enable(irq_num: usize, enabled: bool) {
     GICD.lock().set_enable(irq_num as _, enabled);
 }
 
+/// Routes the given IRQ to the given CPU.
+///
+/// Only SPIs can be routed. It returns `false` if the IRQ is not an SPI or the
+/// CPU does not exist.
+pub fn set_irq_affinity(irq_num: usize, cpu_id: usize) -> bool {
+    // GICv2 supports at most 8 CPUs.
+    if !(32..MAX_IRQ_COUNT).contains(&irq_num) || cpu_id >= axconfig::SMP.min(8) {
+        return false;
+    }
+    trace!("GICD set affinity: {} -> CPU {}", irq_num, cpu_id);
+    let reg = phys_to_virt(GICD_BASE + GICD_ITARGETSR_OFFSET + irq_num).as_mut_ptr();
+    // Accesses to GICD are serialized by its lock.
+    let _gicd = GICD.lock();
+    unsafe { reg.write_volatile(1 << cpu_id) };
+    true
+}
+
+/// Allocates an IRQ for a message signaled interrupt (MSI or MSI-X).
+///
+/// MSIs are not supported, so it always returns `None`.
+pub fn alloc_msi_irq() -> Option<usize> {
+    None
+}
+
+/// Returns the message that a device writes to raise the given MSI.
+///
+/// MSIs are not supported, so it always returns `None`.
+pub fn msi_message(_irq_num: usize) -> Option<crate::irq::MsiMessage> {
+    None
+}
+
+/// Returns the IRQ number of a PCI legacy interrupt (INTx).
+///
+/// `device` is the device number on the root bus, and `pin` is the value of
+/// the interrupt pin register (1 for INTA, and so on). It returns `None` if
+/// the device does not use INTx, or the platform has no PCI interrupts.
+pub fn pci_intx_irq(device: u8, pin: u8) -> Option<usize> {
+    let irqs = axconfig::devices::PCI_INTX_IRQS;
+    if !(1..=4).contains(&pin) || irqs.len() != 4 {
+        return None;
+    }
+    let spi = irqs[(device as usize + pin as usize - 1) % 4];
+    translate_irq(spi, InterruptType::SPI)
+}
+
 /// Registers an IRQ handler for the given IRQ.
 ///
 /// It also enables the IRQ if the registration succeeds. It returns `false` if
@@ -46,6 +99,12 @@ pub fn dispatch_irq(_unused: usize) {
     GICC.handle_irq(|irq_num| crate::irq::dispatch_irq_common(irq_num as _));
 }
 
+/// Sends an inter-processor interrupt to the given CPU.
+#[cfg(feature = "smp")]
+pub(crate) fn send_ipi(cpu_id: usize) {
+    GICD.lock().send_sgi(cpu_id, IPI_IRQ_NUM);
+}
+
 /// Initializes GICD, GICC on the primary CPU.
 pub(crate) fn init_primary() {
     info!("Initialize GICv2...");
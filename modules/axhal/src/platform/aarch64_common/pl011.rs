@@ -55,19 +55,20 @@ pub fn init_early() {
     UART.lock().init();
 }
 
-/// Set UART IRQ Enable
-pub fn init() {
-    #[cfg(feature = "irq")]
-    crate::irq::set_enable(crate::platform::irq::UART_IRQ_NUM, true);
-}
+/// The IRQ number of console input.
+#[cfg(feature = "irq")]
+pub(crate) const INPUT_IRQ_NUM: Option<usize> = Some(crate::platform::irq::UART_IRQ_NUM);
 
-/// UART IRQ Handler
-pub fn handle() {
-    let is_receive_interrupt = UART.lock().is_receive_interrupt();
-    UART.lock().ack_interrupts();
-    if is_receive_interrupt {
-        while let Some(c) = getchar() {
-            putchar(c);
-        }
-    }
+/// Enables the receive and receive timeout interrupts of the UART.
+///
+/// The interrupts are cleared once the receive FIFO is drained.
+#[cfg(feature = "irq")]
+pub(crate) fn enable_input_irq() {
+    const UARTIMSC: usize = 0x38;
+    const RXIM: u32 = 1 << 4;
+    const RTIM: u32 = 1 << 6;
+
+    let _uart = UART.lock();
+    let imsc = (phys_to_virt(UART_BASE).as_usize() + UARTIMSC) as *mut u32;
+    unsafe { imsc.write_volatile(imsc.read_volatile() | RXIM | RTIM) };
 }
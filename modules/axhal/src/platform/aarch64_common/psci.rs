@@ -93,14 +93,18 @@ fn psci_call(func: u32, arg0: usize, arg1: usize, arg2: usize) -> Result<(), Psc
     }
 }
 
-/// Shutdown the whole system, including all CPUs.
-pub fn system_off() -> ! {
-    info!("Shutting down...");
+/// Powers off the whole system, including all CPUs.
+///
+/// It returns only if the firmware does not support `SYSTEM_OFF`.
+pub fn system_off() {
     psci_call(PSCI_0_2_FN_SYSTEM_OFF, 0, 0, 0).ok();
-    warn!("It should shutdown!");
-    loop {
-        crate::arch::halt();
-    }
+}
+
+/// Resets the whole system, including all CPUs.
+///
+/// It returns only if the firmware does not support `SYSTEM_RESET`.
+pub fn system_reset() {
+    psci_call(PSCI_0_2_FN_SYSTEM_RESET, 0, 0, 0).ok();
 }
 
 /// Power up a core. This call is used to power up cores that either:
@@ -17,12 +17,7 @@ pub mod time {
 }
 
 pub mod misc {
-    pub fn terminate() -> ! {
-        info!("Shutting down...");
-        loop {
-            crate::arch::halt();
-        }
-    }
+    pub(crate) use crate::platform::aarch64_common::psci::{system_off, system_reset};
 }
 
 unsafe extern "C" {
@@ -56,7 +51,6 @@ pub fn platform_init() {
     #[cfg(feature = "irq")]
     super::aarch64_common::gic::init_primary();
     super::aarch64_common::generic_timer::init_percpu();
-    super::aarch64_common::pl011::init();
 }
 
 /// Initializes the platform devices for secondary CPUs.
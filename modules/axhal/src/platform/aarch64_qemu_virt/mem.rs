@@ -1,9 +1,16 @@
 use crate::mem::MemRegion;
 use page_table_entry::{GenericPTE, MappingFlags, aarch64::A64PTE};
 
+/// The end of the physical memory mapped by the boot page table.
+const BOOT_MAPPED_MEMORY_END: usize = 0x8_0000_0000;
+
 /// Returns platform-specific memory regions.
+///
+/// The RAM and devices are discovered from the device tree.
 pub(crate) fn platform_regions() -> impl Iterator<Item = MemRegion> {
-    crate::mem::default_free_regions().chain(crate::mem::default_mmio_regions())
+    crate::mem::fdt_free_regions(BOOT_MAPPED_MEMORY_END)
+        .chain(crate::mem::default_mmio_regions())
+        .chain(crate::mem::fdt_mmio_regions())
 }
 
 pub(crate) unsafe fn init_boot_page_table(
@@ -20,10 +27,14 @@ pub(crate) unsafe fn init_boot_page_table(
         MappingFlags::READ | MappingFlags::WRITE | MappingFlags::DEVICE,
         true,
     );
-    // 0x0000_4000_0000..0x0000_8000_0000, 1G block, normal memory
-    boot_pt_l1[1] = A64PTE::new_page(
-        pa!(0x4000_0000),
-        MappingFlags::READ | MappingFlags::WRITE | MappingFlags::EXECUTE,
-        true,
-    );
+    // 0x0000_4000_0000..BOOT_MAPPED_MEMORY_END, 1G blocks, normal memory, so
+    // that all the RAM discovered from the device tree is accessible
+    let normal_memory = boot_pt_l1[..BOOT_MAPPED_MEMORY_END >> 30].iter_mut();
+    for (i, pte) in normal_memory.enumerate().skip(1) {
+        *pte = A64PTE::new_page(
+            pa!(i << 30),
+            MappingFlags::READ | MappingFlags::WRITE | MappingFlags::EXECUTE,
+            true,
+        );
+    }
 }
@@ -17,10 +17,31 @@ pub mod time {
 }
 
 pub mod misc {
-    pub fn terminate() -> ! {
-        info!("Shutting down...");
-        loop {
-            crate::arch::halt();
+    use crate::mem::phys_to_virt;
+    use memory_addr::pa;
+
+    /// Base address of the power management (PM) block, which contains the
+    /// watchdog.
+    const PM_BASE: usize = phys_to_virt(pa!(0xFE10_0000)).as_usize();
+
+    const PM_RSTC: usize = 0x1c;
+    const PM_WDOG: usize = 0x24;
+    const PM_PASSWORD: u32 = 0x5a00_0000;
+    const PM_RSTC_WRCFG_CLR: u32 = 0xffff_ffcf;
+    const PM_RSTC_WRCFG_FULL_RESET: u32 = 0x20;
+
+    /// The Raspberry Pi cannot power itself off, so it just returns.
+    pub(crate) fn system_off() {}
+
+    /// Resets the whole system by letting the watchdog expire after a few
+    /// ticks.
+    pub(crate) fn system_reset() {
+        let rstc = (PM_BASE + PM_RSTC) as *mut u32;
+        let wdog = (PM_BASE + PM_WDOG) as *mut u32;
+        unsafe {
+            wdog.write_volatile(PM_PASSWORD | 10);
+            let val = rstc.read_volatile() & PM_RSTC_WRCFG_CLR;
+            rstc.write_volatile(PM_PASSWORD | val | PM_RSTC_WRCFG_FULL_RESET);
         }
     }
 }
@@ -52,7 +73,6 @@ pub fn platform_init() {
     #[cfg(feature = "irq")]
     super::aarch64_common::gic::init_primary();
     super::aarch64_common::generic_timer::init_percpu();
-    super::aarch64_common::pl011::init();
 }
 
 /// Initializes the platform devices for secondary CPUs.
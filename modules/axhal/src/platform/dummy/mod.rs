@@ -12,11 +12,24 @@ pub mod console {
     pub fn read_bytes(_bytes: &mut [u8]) -> usize {
         unimplemented!()
     }
+
+    /// The IRQ number of console input.
+    #[cfg(feature = "irq")]
+    pub(crate) const INPUT_IRQ_NUM: Option<usize> = None;
+
+    /// Enables the input interrupt of the console.
+    #[cfg(feature = "irq")]
+    pub(crate) fn enable_input_irq() {}
 }
 
 pub mod misc {
-    /// Shutdown the whole system, including all CPUs.
-    pub fn terminate() -> ! {
+    /// Powers off the whole system, including all CPUs.
+    pub(crate) fn system_off() {
+        unimplemented!()
+    }
+
+    /// Resets the whole system, including all CPUs.
+    pub(crate) fn system_reset() {
         unimplemented!()
     }
 }
@@ -69,9 +82,33 @@ pub mod irq {
     /// The timer IRQ number.
     pub const TIMER_IRQ_NUM: usize = 0;
 
+    /// The IRQ number of inter-processor interrupts.
+    #[cfg(feature = "smp")]
+    pub const IPI_IRQ_NUM: usize = 1;
+
     /// Enables or disables the given IRQ.
     pub fn set_enable(irq_num: usize, enabled: bool) {}
 
+    /// Routes the given IRQ to the given CPU.
+    pub fn set_irq_affinity(irq_num: usize, cpu_id: usize) -> bool {
+        false
+    }
+
+    /// Allocates an IRQ for a message signaled interrupt (MSI or MSI-X).
+    pub fn alloc_msi_irq() -> Option<usize> {
+        None
+    }
+
+    /// Returns the message that a device writes to raise the given MSI.
+    pub fn msi_message(irq_num: usize) -> Option<crate::irq::MsiMessage> {
+        None
+    }
+
+    /// Returns the IRQ number of a PCI legacy interrupt (INTx).
+    pub fn pci_intx_irq(device: u8, pin: u8) -> Option<usize> {
+        None
+    }
+
     /// Registers an IRQ handler for the given IRQ.
     pub fn register_handler(irq_num: usize, handler: crate::irq::IrqHandler) -> bool {
         false
@@ -83,6 +120,10 @@ pub mod irq {
     /// up in the IRQ handler table and calls the corresponding handler. If
     /// necessary, it also acknowledges the interrupt controller after handling.
     pub fn dispatch_irq(irq_num: usize) {}
+
+    /// Sends an inter-processor interrupt to the given CPU.
+    #[cfg(feature = "smp")]
+    pub(crate) fn send_ipi(cpu_id: usize) {}
 }
 
 /// Initializes the platform devices for the primary CPU.
@@ -41,3 +41,10 @@ pub(super) fn init_early() {
     let vaddr = phys_to_virt(UART_BASE);
     UART.init_once(SpinNoIrq::new(Uart::new(vaddr.as_usize())));
 }
+
+/// Console input is not interrupt-driven, it must be polled.
+#[cfg(feature = "irq")]
+pub(crate) const INPUT_IRQ_NUM: Option<usize> = None;
+
+#[cfg(feature = "irq")]
+pub(crate) fn enable_input_irq() {}
@@ -4,21 +4,78 @@ use loongArch64::register::{
 };
 
 /// The maximum number of IRQs.
-pub const MAX_IRQ_COUNT: usize = 12;
+pub const MAX_IRQ_COUNT: usize = 13;
 
 /// The timer IRQ number.
 pub const TIMER_IRQ_NUM: usize = estat::Interrupt::Timer as usize;
 
+/// The IRQ number of inter-processor interrupts.
+pub const IPI_IRQ_NUM: usize = 12;
+
+const IOCSR_IPI_STATUS: usize = 0x1000;
+const IOCSR_IPI_ENABLE: usize = 0x1004;
+const IOCSR_IPI_CLEAR: usize = 0x100c;
+
+/// The IPI action (bit in `IOCSR_IPI_STATUS`) used by [`send_ipi`]. Action 1
+/// is used to boot secondary CPUs.
+#[cfg(feature = "smp")]
+const IPI_ACTION: u32 = 2;
+
+fn iocsr_read_w(reg: usize) -> u32 {
+    let val: u32;
+    unsafe { core::arch::asm!("iocsrrd.w {}, {}", out(reg) val, in(reg) reg) };
+    val
+}
+
+fn iocsr_write_w(reg: usize, val: u32) {
+    unsafe { core::arch::asm!("iocsrwr.w {}, {}", in(reg) val, in(reg) reg) };
+}
+
 /// Enables or disables the given IRQ.
 pub fn set_enable(irq_num: usize, enabled: bool) {
-    if irq_num == TIMER_IRQ_NUM {
-        let old_value = ecfg::read().lie();
-        let new_value = match enabled {
-            true => old_value | LineBasedInterrupt::TIMER,
-            false => old_value & !LineBasedInterrupt::TIMER,
-        };
-        ecfg::set_lie(new_value);
-    }
+    let line = match irq_num {
+        TIMER_IRQ_NUM => LineBasedInterrupt::TIMER,
+        IPI_IRQ_NUM => {
+            iocsr_write_w(IOCSR_IPI_ENABLE, if enabled { u32::MAX } else { 0 });
+            LineBasedInterrupt::IPI
+        }
+        _ => return,
+    };
+    let old_value = ecfg::read().lie();
+    let new_value = match enabled {
+        true => old_value | line,
+        false => old_value & !line,
+    };
+    ecfg::set_lie(new_value);
+}
+
+/// Routes the given IRQ to the given CPU.
+///
+/// All IRQs are per-CPU, so it is not supported and always returns `false`.
+pub fn set_irq_affinity(_irq_num: usize, _cpu_id: usize) -> bool {
+    false
+}
+
+/// Allocates an IRQ for a message signaled interrupt (MSI or MSI-X).
+///
+/// MSIs are not supported, so it always returns `None`.
+pub fn alloc_msi_irq() -> Option<usize> {
+    None
+}
+
+/// Returns the message that a device writes to raise the given MSI.
+///
+/// MSIs are not supported, so it always returns `None`.
+pub fn msi_message(_irq_num: usize) -> Option<crate::irq::MsiMessage> {
+    None
+}
+
+/// Returns the IRQ number of a PCI legacy interrupt (INTx).
+///
+/// External interrupt controllers are not supported, so it always returns
+/// `None`.
+pub fn pci_intx_irq(_device: u8, _pin: u8) -> Option<usize> {
+    None
 }
 
 /// Registers an IRQ handler for the given IRQ.
@@ -32,8 +89,16 @@ pub fn register_handler(irq_num: usize, handler: crate::irq::IrqHandler) -> bool
 /// up in the IRQ handler table and calls the corresponding handler. If
 /// necessary, it also acknowledges the interrupt controller after handling.
 pub fn dispatch_irq(irq_num: usize) {
-    if irq_num == TIMER_IRQ_NUM {
-        ticlr::clear_timer_interrupt();
+    match irq_num {
+        TIMER_IRQ_NUM => ticlr::clear_timer_interrupt(),
+        IPI_IRQ_NUM => iocsr_write_w(IOCSR_IPI_CLEAR, iocsr_read_w(IOCSR_IPI_STATUS)),
+        _ => {}
     }
     crate::irq::dispatch_irq_common(irq_num)
 }
+
+/// Sends an inter-processor interrupt to the given CPU.
+#[cfg(feature = "smp")]
+pub(crate) fn send_ipi(cpu_id: usize) {
+    loongArch64::ipi::send_ipi_single(cpu_id, IPI_ACTION);
+}
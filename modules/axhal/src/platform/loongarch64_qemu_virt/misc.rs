@@ -1,15 +1,22 @@
 use crate::mem::phys_to_virt;
 use memory_addr::pa;
 
-const HALT_ADDR: *mut u8 = phys_to_virt(pa!(axconfig::devices::GED_PADDR)).as_mut_ptr();
-
-/// Shutdown the whole system, including all CPUs.
-pub fn terminate() -> ! {
-    info!("Shutting down...");
-    unsafe { HALT_ADDR.write_volatile(0x34) };
-    crate::arch::halt();
-    warn!("It should shutdown!");
-    loop {
-        crate::arch::halt();
-    }
+/// Base address of the Generic Event Device (GED) registers.
+const GED_BASE: usize = phys_to_virt(pa!(axconfig::devices::GED_PADDR)).as_usize();
+
+const GED_REG_SLEEP_CTL: usize = 0;
+const GED_REG_RESET: usize = 2;
+
+/// `SLP_TYP` of S5 (soft off) with `SLP_EN` set.
+const GED_SLEEP_CTL_S5: u8 = 0x34;
+const GED_RESET_VALUE: u8 = 0x42;
+
+/// Powers off the whole system, including all CPUs.
+pub(crate) fn system_off() {
+    unsafe { ((GED_BASE + GED_REG_SLEEP_CTL) as *mut u8).write_volatile(GED_SLEEP_CTL_S5) };
+}
+
+/// Resets the whole system, including all CPUs.
+pub(crate) fn system_reset() {
+    unsafe { ((GED_BASE + GED_REG_RESET) as *mut u8).write_volatile(GED_RESET_VALUE) };
 }
@@ -0,0 +1,185 @@
+//! Interrupt management for RISC-V platforms.
+//!
+//! The timer interrupt is identified by its `scause` value ([`TIMER_IRQ_NUM`]),
+//! while external interrupts are identified by their PLIC source numbers
+//! (e.g., [`UART_IRQ_NUM`]).
+
+use crate::irq::IrqHandler;
+use lazyinit::LazyInit;
+use riscv::register::{sie, sip};
+
+use super::plic;
+
+/// `Interrupt` bit in `scause`
+pub(crate) const INTC_IRQ_BASE: usize = 1 << (usize::BITS - 1);
+
+//S_SOFT,S_TIMER来自于clint
+/// Supervisor software interrupt in `scause`
+pub(crate) const S_SOFT: usize = INTC_IRQ_BASE + 1;
+
+/// Supervisor timer interrupt in `scause`
+pub(crate) const S_TIMER: usize = INTC_IRQ_BASE + 5;
+//以上来自clint
+
+/// Supervisor external interrupt in `scause`
+pub(crate) const S_EXT: usize = INTC_IRQ_BASE + 9;
+
+static TIMER_HANDLER: LazyInit<IrqHandler> = LazyInit::new();
+
+static IPI_HANDLER: LazyInit<IrqHandler> = LazyInit::new();
+
+/// The maximum number of IRQs.
+pub const MAX_IRQ_COUNT: usize = plic::MAX_SOURCE_COUNT;
+
+/// The timer IRQ number (supervisor timer interrupt in `scause`).
+pub const TIMER_IRQ_NUM: usize = S_TIMER;
+
+/// The IRQ number of inter-processor interrupts (supervisor software
+/// interrupt in `scause`).
+pub const IPI_IRQ_NUM: usize = S_SOFT;
+
+/// The UART IRQ number.
+pub const UART_IRQ_NUM: usize = axconfig::devices::UART_IRQ;
+
+/// The RTC IRQ number.
+pub const RTC_IRQ_NUM: usize = axconfig::devices::RTC_IRQ;
+
+/// The IRQ numbers of VirtIO MMIO devices, in the same order as
+/// [`axconfig::devices::VIRTIO_MMIO_REGIONS`].
+pub const VIRTIO_MMIO_IRQ_NUMS: &[usize] = axconfig::devices::VIRTIO_MMIO_IRQS;
+
+/// Enables or disables the given IRQ.
+pub fn set_enable(irq_num: usize, enabled: bool) {
+    match irq_num {
+        S_TIMER => unsafe {
+            if enabled {
+                sie::set_stimer();
+            } else {
+                sie::clear_stimer();
+            }
+        },
+        S_SOFT => unsafe {
+            if enabled {
+                sie::set_ssoft();
+            } else {
+                sie::clear_ssoft();
+            }
+        },
+        _ => plic::set_enable(irq_num, enabled),
+    }
+}
+
+/// Routes the given IRQ to the given CPU.
+///
+/// Only enabled external interrupts can be routed. It returns `false` if the
+/// IRQ is a local one, is not enabled, or the CPU does not exist.
+pub fn set_irq_affinity(irq_num: usize, cpu_id: usize) -> bool {
+    match irq_num {
+        S_TIMER | S_SOFT => false,
+        _ => plic::set_affinity(irq_num, cpu_id),
+    }
+}
+
+/// Allocates an IRQ for a message signaled interrupt (MSI or MSI-X).
+///
+/// MSIs are not supported, so it always returns `None`.
+pub fn alloc_msi_irq() -> Option<usize> {
+    None
+}
+
+/// Returns the message that a device writes to raise the given MSI.
+///
+/// MSIs are not supported, so it always returns `None`.
+pub fn msi_message(_irq_num: usize) -> Option<crate::irq::MsiMessage> {
+    None
+}
+
+/// Returns the IRQ number of a PCI legacy interrupt (INTx).
+///
+/// `device` is the device number on the root bus, and `pin` is the value of
+/// the interrupt pin register (1 for INTA, and so on). It returns `None` if
+/// the device does not use INTx, or the platform has no PCI interrupts.
+pub fn pci_intx_irq(device: u8, pin: u8) -> Option<usize> {
+    let irqs = axconfig::devices::PCI_INTX_IRQS;
+    if !(1..=4).contains(&pin) || irqs.len() != 4 {
+        return None;
+    }
+    Some(irqs[(device as usize + pin as usize - 1) % 4])
+}
+
+/// Registers an IRQ handler for the given IRQ.
+///
+/// It also enables the IRQ if the registration succeeds. It returns `false` if
+/// the registration failed.
+pub fn register_handler(irq_num: usize, handler: IrqHandler) -> bool {
+    match irq_num {
+        S_TIMER | S_SOFT => {
+            let slot = if irq_num == S_TIMER {
+                &TIMER_HANDLER
+            } else {
+                &IPI_HANDLER
+            };
+            if !slot.is_inited() {
+                slot.init_once(handler);
+                true
+            } else {
+                false
+            }
+        }
+        _ => crate::irq::register_handler_common(irq_num, handler),
+    }
+}
+
+/// Dispatches the IRQ.
+///
+/// This function is called by the common interrupt handler. It looks
+/// up in the IRQ handler table and calls the corresponding handler. If
+/// necessary, it also acknowledges the interrupt controller after handling.
+pub fn dispatch_irq(scause: usize) {
+    match scause {
+        S_TIMER => {
+            trace!("IRQ: timer");
+            crate::irq::count_irq(S_TIMER);
+            TIMER_HANDLER();
+        }
+        S_SOFT => {
+            trace!("IRQ: IPI");
+            unsafe { sip::clear_ssoft() };
+            crate::irq::count_irq(S_SOFT);
+            if let Some(handler) = IPI_HANDLER.get() {
+                handler();
+            }
+        }
+        S_EXT => {
+            if let Some(irq_num) = plic::claim() {
+                crate::irq::dispatch_irq_common(irq_num);
+                plic::complete(irq_num);
+            }
+        }
+        _ => panic!("invalid trap cause: {:#x}", scause),
+    }
+}
+
+/// Sends an inter-processor interrupt to the given CPU.
+#[cfg(feature = "smp")]
+pub(crate) fn send_ipi(cpu_id: usize) {
+    let hart_mask = sbi_rt::HartMask::from_mask_base(1, super::hart_id(cpu_id));
+    let ret = sbi_rt::send_ipi(hart_mask);
+    if ret.error != 0 {
+        warn!(
+            "failed to send IPI to CPU {}: error {:#x}",
+            cpu_id, ret.error
+        );
+    }
+}
+
+/// Initializes the interrupt controller on the current CPU.
+pub(crate) fn init_percpu() {
+    plic::init_percpu();
+    // enable soft interrupts, timer interrupts, and external interrupts
+    unsafe {
+        sie::set_ssoft();
+        sie::set_stimer();
+        sie::set_sext();
+    }
+}
@@ -0,0 +1,16 @@
+#[cfg(feature = "irq")]
+pub mod irq;
+
+#[cfg(feature = "irq")]
+mod plic;
+
+/// Returns the hart ID of the given CPU.
+#[allow(dead_code)]
+pub(crate) const fn hart_id(cpu_id: usize) -> usize {
+    if cfg!(platform_family = "riscv64-vf2") {
+        // Hart 0 (the S7 monitor core) is not used.
+        cpu_id + 1
+    } else {
+        cpu_id
+    }
+}
@@ -0,0 +1,160 @@
+//! RISC-V Platform-Level Interrupt Controller (PLIC).
+//!
+//! External interrupts are routed to the supervisor-mode context of the CPU
+//! that enables them, with the same priority. Each CPU accepts interrupts of
+//! any non-zero priority.
+
+use axconfig::devices::PLIC_PADDR;
+use kspin::SpinNoIrq;
+use memory_addr::PhysAddr;
+
+use crate::mem::phys_to_virt;
+
+/// The maximum number of interrupt sources supported by the PLIC, including
+/// the reserved source 0.
+pub const MAX_SOURCE_COUNT: usize = 1024;
+
+const PLIC_BASE: PhysAddr = pa!(PLIC_PADDR);
+
+const PRIORITY_OFFSET: usize = 0;
+const ENABLE_OFFSET: usize = 0x2000;
+const ENABLE_STRIDE: usize = 0x80;
+const CONTEXT_OFFSET: usize = 0x20_0000;
+const CONTEXT_STRIDE: usize = 0x1000;
+const THRESHOLD_OFFSET: usize = 0;
+const CLAIM_OFFSET: usize = 4;
+
+/// The priority of all enabled sources. Sources with priority 0 never
+/// interrupt.
+const DEFAULT_PRIORITY: u32 = 1;
+
+/// Returns the PLIC context of the supervisor mode of the given CPU.
+const fn s_mode_context(cpu_id: usize) -> usize {
+    let hart_id = super::hart_id(cpu_id);
+    if cfg!(platform_family = "riscv64-vf2") {
+        // Hart 0 (S7) only has an M-mode context, so the M-mode and S-mode
+        // contexts of other harts are `2 * hart - 1` and `2 * hart`.
+        2 * hart_id
+    } else {
+        // Each hart has an M-mode and an S-mode context.
+        2 * hart_id + 1
+    }
+}
+
+struct Plic {
+    base: usize,
+}
+
+impl Plic {
+    const fn new(base: usize) -> Self {
+        Self { base }
+    }
+
+    fn reg(&self, offset: usize) -> *mut u32 {
+        (self.base + offset) as *mut u32
+    }
+
+    fn set_priority(&self, irq_num: usize, priority: u32) {
+        let reg = self.reg(PRIORITY_OFFSET + irq_num * 4);
+        unsafe { reg.write_volatile(priority) };
+    }
+
+    fn set_enable(&self, context: usize, irq_num: usize, enabled: bool) {
+        let reg = self.reg(ENABLE_OFFSET + context * ENABLE_STRIDE + irq_num / 32 * 4);
+        let mask = 1 << (irq_num % 32);
+        unsafe {
+            let val = reg.read_volatile();
+            reg.write_volatile(if enabled { val | mask } else { val & !mask });
+        }
+    }
+
+    fn is_enabled(&self, context: usize, irq_num: usize) -> bool {
+        let reg = self.reg(ENABLE_OFFSET + context * ENABLE_STRIDE + irq_num / 32 * 4);
+        unsafe { reg.read_volatile() & (1 << (irq_num % 32)) != 0 }
+    }
+
+    fn set_threshold(&self, context: usize, threshold: u32) {
+        let reg = self.reg(CONTEXT_OFFSET + context * CONTEXT_STRIDE + THRESHOLD_OFFSET);
+        unsafe { reg.write_volatile(threshold) };
+    }
+
+    fn claim(&self, context: usize) -> Option<usize> {
+        let reg = self.reg(CONTEXT_OFFSET + context * CONTEXT_STRIDE + CLAIM_OFFSET);
+        match unsafe { reg.read_volatile() } {
+            0 => None,
+            irq_num => Some(irq_num as usize),
+        }
+    }
+
+    fn complete(&self, context: usize, irq_num: usize) {
+        let reg = self.reg(CONTEXT_OFFSET + context * CONTEXT_STRIDE + CLAIM_OFFSET);
+        unsafe { reg.write_volatile(irq_num as u32) };
+    }
+}
+
+static PLIC: Plic = Plic::new(phys_to_virt(PLIC_BASE).as_usize());
+
+// Enable bits are updated with read-modify-write, which must not interleave.
+static ENABLE_LOCK: SpinNoIrq<()> = SpinNoIrq::new(());
+
+/// Enables or disables the given interrupt source.
+///
+/// An enabled source is routed to the current CPU. Disabling a source
+/// disables it on all CPUs.
+pub(crate) fn set_enable(irq_num: usize, enabled: bool) {
+    if irq_num == 0 || irq_num >= MAX_SOURCE_COUNT {
+        warn!("PLIC: invalid interrupt source {}", irq_num);
+        return;
+    }
+    trace!("PLIC set enable: {} {}", irq_num, enabled);
+    let _guard = ENABLE_LOCK.lock();
+    if enabled {
+        PLIC.set_priority(irq_num, DEFAULT_PRIORITY);
+        PLIC.set_enable(s_mode_context(crate::cpu::this_cpu_id()), irq_num, true);
+    } else {
+        for cpu_id in 0..axconfig::SMP {
+            PLIC.set_enable(s_mode_context(cpu_id), irq_num, false);
+        }
+    }
+}
+
+/// Routes the given interrupt source to the given CPU only.
+///
+/// The source must have been enabled, since enabling a source routes it to
+/// the current CPU. It returns `false` otherwise.
+pub(crate) fn set_affinity(irq_num: usize, cpu_id: usize) -> bool {
+    if irq_num == 0 || irq_num >= MAX_SOURCE_COUNT || cpu_id >= axconfig::SMP {
+        return false;
+    }
+    let _guard = ENABLE_LOCK.lock();
+    if !(0..axconfig::SMP).any(|cpu_id| PLIC.is_enabled(s_mode_context(cpu_id), irq_num)) {
+        return false;
+    }
+    trace!("PLIC set affinity: {} -> CPU {}", irq_num, cpu_id);
+    for other in 0..axconfig::SMP {
+        PLIC.set_enable(s_mode_context(other), irq_num, other == cpu_id);
+    }
+    true
+}
+
+/// Claims the pending interrupt with the highest priority on the current
+/// CPU, returns its source number.
+///
+/// Returns `None` if there is no pending interrupt, e.g. it was claimed by
+/// another CPU.
+pub(crate) fn claim() -> Option<usize> {
+    // claim/complete registers are per-context, no lock
+    PLIC.claim(s_mode_context(crate::cpu::this_cpu_id()))
+}
+
+/// Signals the completion of handling a claimed interrupt.
+pub(crate) fn complete(irq_num: usize) {
+    PLIC.complete(s_mode_context(crate::cpu::this_cpu_id()), irq_num);
+}
+
+/// Initializes the PLIC context of the current CPU.
+pub(crate) fn init_percpu() {
+    let context = s_mode_context(crate::cpu::this_cpu_id());
+    info!("Initialize PLIC context {}...", context);
+    PLIC.set_threshold(context, 0);
+}
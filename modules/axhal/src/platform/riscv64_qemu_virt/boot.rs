@@ -2,6 +2,8 @@ use riscv::register::satp;
 
 use axconfig::{TASK_STACK_SIZE, plat::PHYS_VIRT_OFFSET};
 
+use super::mem::BOOT_MAPPED_MEMORY_END;
+
 #[unsafe(link_section = ".bss.stack")]
 static mut BOOT_STACK: [u8; TASK_STACK_SIZE] = [0; TASK_STACK_SIZE];
 
@@ -12,12 +14,16 @@ static mut BOOT_PT_SV39: [u64; 512] = [0; 512];
 unsafe fn init_boot_page_table() {
     // 0x0000_0000..0x4000_0000, VRWX_GAD, 1G block
     BOOT_PT_SV39[0] = (0x0 << 10) | 0xef;
-    // 0x8000_0000..0xc000_0000, VRWX_GAD, 1G block
-    BOOT_PT_SV39[2] = (0x80000 << 10) | 0xef;
     // 0xffff_ffc0_0000_0000..0xffff_ffc0_4000_0000, VRWX_GAD, 1G block
     BOOT_PT_SV39[0x100] = (0x0 << 10) | 0xef;
-    // 0xffff_ffc0_8000_0000..0xffff_ffc0_c000_0000, VRWX_GAD, 1G block
-    BOOT_PT_SV39[0x102] = (0x80000 << 10) | 0xef;
+    // 0x8000_0000..BOOT_MAPPED_MEMORY_END, VRWX_GAD, 1G blocks, so that all
+    // the RAM discovered from the device tree is accessible
+    for i in 2..BOOT_MAPPED_MEMORY_END >> 30 {
+        let ppn = (i << 30) >> 12;
+        BOOT_PT_SV39[i] = (ppn << 10) as u64 | 0xef;
+        // and the high address 0xffff_ffc0_0000_0000 + (i << 30)
+        BOOT_PT_SV39[0x100 + i] = (ppn << 10) as u64 | 0xef;
+    }
 }
 
 unsafe fn init_mmu() {
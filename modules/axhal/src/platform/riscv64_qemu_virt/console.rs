@@ -50,6 +50,21 @@ pub fn write_bytes(bytes: &[u8]) {
     }
 }
 
+/// The IRQ number of console input.
+#[cfg(feature = "irq")]
+pub(crate) const INPUT_IRQ_NUM: Option<usize> = Some(crate::platform::irq::UART_IRQ_NUM);
+
+/// Enables the "received data available" interrupt of the NS16550 UART, which
+/// SBI reads and writes on our behalf.
+///
+/// The interrupt is cleared once the received data are read.
+#[cfg(feature = "irq")]
+pub(crate) fn enable_input_irq() {
+    const UART_IER: usize = 1;
+    let uart_base = crate::mem::phys_to_virt(pa!(axconfig::devices::UART_PADDR));
+    unsafe { ((uart_base.as_usize() + UART_IER) as *mut u8).write_volatile(0x01) };
+}
+
 /// Reads bytes from the console into the given mutable slice.
 /// Returns the number of bytes read.
 // pub fn read_bytes(bytes: &mut [u8]) -> usize {
@@ -1,6 +1,13 @@
 use crate::mem::MemRegion;
 
+/// The end of the physical memory mapped by the boot page table.
+pub(crate) const BOOT_MAPPED_MEMORY_END: usize = 0x8_0000_0000;
+
 /// Returns platform-specific memory regions.
+///
+/// The RAM and devices are discovered from the device tree.
 pub(crate) fn platform_regions() -> impl Iterator<Item = MemRegion> {
-    crate::mem::default_free_regions().chain(crate::mem::default_mmio_regions())
+    crate::mem::fdt_free_regions(BOOT_MAPPED_MEMORY_END)
+        .chain(crate::mem::default_mmio_regions())
+        .chain(crate::mem::fdt_mmio_regions())
 }
@@ -1,9 +1,9 @@
-/// Shutdown the whole system, including all CPUs.
-pub fn terminate() -> ! {
-    info!("Shutting down...");
+/// Powers off the whole system with SBI SRST, including all CPUs.
+pub(crate) fn system_off() {
     sbi_rt::system_reset(sbi_rt::Shutdown, sbi_rt::NoReason);
-    warn!("It should shutdown!");
-    loop {
-        crate::arch::halt();
-    }
+}
+
+/// Resets the whole system with SBI SRST, including all CPUs.
+pub(crate) fn system_reset() {
+    sbi_rt::system_reset(sbi_rt::ColdReboot, sbi_rt::NoReason);
 }
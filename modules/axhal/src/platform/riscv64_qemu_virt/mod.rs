@@ -6,7 +6,9 @@ pub mod misc;
 pub mod time;
 
 #[cfg(feature = "irq")]
-pub mod irq;
+pub mod irq {
+    pub use crate::platform::riscv64_common::irq::*;
+}
 
 #[cfg(feature = "smp")]
 pub mod mp;
@@ -19,6 +21,7 @@ unsafe extern "C" {
 
 unsafe extern "C" fn rust_entry(cpu_id: usize, dtb: usize) {
     crate::mem::clear_bss();
+    crate::fdt::init(dtb);
     crate::cpu::init_primary(cpu_id);
     #[cfg(feature = "uspace")]
     riscv::register::sstatus::set_sum();
@@ -39,7 +42,7 @@ unsafe extern "C" fn rust_entry_secondary(cpu_id: usize) {
 /// For example, the interrupt controller and the timer.
 pub fn platform_init() {
     #[cfg(feature = "irq")]
-    self::irq::init_percpu();
+    super::riscv64_common::irq::init_percpu();
     self::time::init_percpu();
 }
 
@@ -47,6 +50,6 @@ pub fn platform_init() {
 #[cfg(feature = "smp")]
 pub fn platform_init_secondary() {
     #[cfg(feature = "irq")]
-    self::irq::init_percpu();
+    super::riscv64_common::irq::init_percpu();
     self::time::init_percpu();
 }
@@ -91,3 +91,10 @@ pub fn read_bytes(bytes: &mut [u8]) -> usize {
     ))
     .value
 }
+
+/// Console input is not interrupt-driven, it must be polled.
+#[cfg(feature = "irq")]
+pub(crate) const INPUT_IRQ_NUM: Option<usize> = None;
+
+#[cfg(feature = "irq")]
+pub(crate) fn enable_input_irq() {}
@@ -1,5 +1,6 @@
 #![allow(dead_code)]
 
+use core::sync::atomic::{AtomicU8, Ordering};
 use core::{cell::SyncUnsafeCell, mem::MaybeUninit};
 
 use kspin::SpinNoIrq;
@@ -13,9 +14,16 @@ use self::vectors::*;
 use crate::mem::phys_to_virt;
 
 pub(super) mod vectors {
+    /// The vector of IO APIC input 0. Input `n` is mapped to vector
+    /// `IO_APIC_VECTOR_BASE + n`.
+    pub const IO_APIC_VECTOR_BASE: u8 = 0x20;
+    /// Vectors from `MSI_VECTOR_BASE` to `APIC_TIMER_VECTOR` are allocated
+    /// to message signaled interrupts.
+    pub const MSI_VECTOR_BASE: u8 = 0x40;
     pub const APIC_TIMER_VECTOR: u8 = 0xf0;
     pub const APIC_SPURIOUS_VECTOR: u8 = 0xf1;
     pub const APIC_ERROR_VECTOR: u8 = 0xf2;
+    pub const APIC_IPI_VECTOR: u8 = 0xf3;
 }
 
 /// The maximum number of IRQs.
@@ -24,28 +32,104 @@ pub const MAX_IRQ_COUNT: usize = 256;
 /// The timer IRQ number.
 pub const TIMER_IRQ_NUM: usize = APIC_TIMER_VECTOR as usize;
 
+/// The IRQ number of inter-processor interrupts.
+#[cfg(feature = "smp")]
+pub const IPI_IRQ_NUM: usize = APIC_IPI_VECTOR as usize;
+
+/// The UART (COM1) IRQ number, i.e., the vector of ISA IRQ 4.
+pub const UART_IRQ_NUM: usize = IO_APIC_VECTOR_BASE as usize + 4;
+
 const IO_APIC_BASE: PhysAddr = pa!(0xFEC0_0000);
 
+/// The base of MSI addresses, which targets local APICs.
+const MSI_ADDRESS_BASE: u64 = 0xFEE0_0000;
+
 static LOCAL_APIC: SyncUnsafeCell<MaybeUninit<LocalApic>> =
     SyncUnsafeCell::new(MaybeUninit::uninit());
 static mut IS_X2APIC: bool = false;
 static IO_APIC: LazyInit<SpinNoIrq<IoApic>> = LazyInit::new();
+static NEXT_MSI_VECTOR: AtomicU8 = AtomicU8::new(MSI_VECTOR_BASE);
 
 /// Enables or disables the given IRQ.
 #[cfg(feature = "irq")]
 pub fn set_enable(vector: usize, enabled: bool) {
     // should not affect LAPIC interrupts
-    if vector < APIC_TIMER_VECTOR as _ {
+    if (IO_APIC_VECTOR_BASE as usize..MSI_VECTOR_BASE as usize).contains(&vector) {
+        let irq = vector as u8 - IO_APIC_VECTOR_BASE;
         unsafe {
             if enabled {
-                IO_APIC.lock().enable_irq(vector as u8);
+                IO_APIC.lock().enable_irq(irq);
             } else {
-                IO_APIC.lock().disable_irq(vector as u8);
+                IO_APIC.lock().disable_irq(irq);
             }
         }
     }
 }
 
+/// Routes the given IRQ to the given CPU.
+///
+/// Only IRQs from the IO APIC can be routed. It returns `false` if the IRQ is
+/// a local APIC one or the CPU does not exist.
+#[cfg(feature = "irq")]
+pub fn set_irq_affinity(vector: usize, cpu_id: usize) -> bool {
+    if !(IO_APIC_VECTOR_BASE as usize..MSI_VECTOR_BASE as usize).contains(&vector)
+        || cpu_id >= axconfig::SMP
+    {
+        return false;
+    }
+    let irq = vector as u8 - IO_APIC_VECTOR_BASE;
+    // The destination field in physical mode is the 8-bit APIC ID.
+    let apic_id = crate::acpi::apic_id_of(cpu_id).unwrap_or(cpu_id as u32);
+    let Ok(dest) = u8::try_from(apic_id) else {
+        return false;
+    };
+    let mut io_apic = IO_APIC.lock();
+    unsafe {
+        let mut entry = io_apic.table_entry(irq);
+        entry.set_dest(dest);
+        io_apic.set_table_entry(irq, entry);
+    }
+    true
+}
+
+/// Allocates a vector for a message signaled interrupt (MSI or MSI-X).
+///
+/// Returns `None` if all vectors are used.
+#[cfg(feature = "irq")]
+pub fn alloc_msi_irq() -> Option<usize> {
+    NEXT_MSI_VECTOR
+        .fetch_update(Ordering::AcqRel, Ordering::Acquire, |vector| {
+            (vector < APIC_TIMER_VECTOR).then_some(vector + 1)
+        })
+        .ok()
+        .map(|vector| vector as usize)
+}
+
+/// Returns the message that a device writes to raise the given MSI, which is
+/// delivered to the primary CPU.
+#[cfg(feature = "irq")]
+pub fn msi_message(vector: usize) -> Option<crate::irq::MsiMessage> {
+    if !(MSI_VECTOR_BASE as usize..APIC_TIMER_VECTOR as usize).contains(&vector) {
+        return None;
+    }
+    // The destination ID field in the address is the 8-bit APIC ID.
+    let apic_id = crate::acpi::apic_id_of(0).unwrap_or(0) as u64;
+    Some(crate::irq::MsiMessage {
+        address: MSI_ADDRESS_BASE | (apic_id & 0xff) << 12,
+        // Fixed delivery mode, edge-triggered.
+        data: vector as u32,
+    })
+}
+
+/// Returns the IRQ number of a PCI legacy interrupt (INTx).
+///
+/// PCI interrupts are routed by ACPI tables that are not parsed, so devices
+/// must use MSI or MSI-X, and it always returns `None`.
+#[cfg(feature = "irq")]
+pub fn pci_intx_irq(_device: u8, _pin: u8) -> Option<usize> {
+    None
+}
+
 /// Registers an IRQ handler for the given IRQ.
 ///
 /// It also enables the IRQ if the registration succeeds. It returns `false` if
@@ -66,19 +150,30 @@ pub fn dispatch_irq(vector: usize) {
     unsafe { local_apic().end_of_interrupt() };
 }
 
+/// Sends an inter-processor interrupt to the given CPU.
+#[cfg(all(feature = "irq", feature = "smp"))]
+pub(crate) fn send_ipi(cpu_id: usize) {
+    unsafe { local_apic().send_ipi(APIC_IPI_VECTOR, cpu_apic_id(cpu_id)) };
+}
+
 pub(super) fn local_apic<'a>() -> &'a mut LocalApic {
     // It's safe as `LOCAL_APIC` is initialized in `init_primary`.
     unsafe { LOCAL_APIC.get().as_mut().unwrap().assume_init_mut() }
 }
 
-pub(super) fn raw_apic_id(id_u8: u8) -> u32 {
+fn raw_apic_id(apic_id: u32) -> u32 {
     if unsafe { IS_X2APIC } {
-        id_u8 as u32
+        apic_id
     } else {
-        (id_u8 as u32) << 24
+        apic_id << 24
     }
 }
 
+/// Returns the raw APIC ID of the given logical CPU, used as IPI destinations.
+pub(super) fn cpu_apic_id(cpu_id: usize) -> u32 {
+    raw_apic_id(crate::acpi::apic_id_of(cpu_id).unwrap_or(cpu_id as u32))
+}
+
 fn cpu_has_x2apic() -> bool {
     match raw_cpuid::CpuId::new().get_feature_info() {
         Some(finfo) => finfo.has_x2apic(),
@@ -116,8 +211,13 @@ pub(super) fn init_primary() {
         LOCAL_APIC.get().as_mut().unwrap().write(lapic);
     }
 
-    info!("Initialize IO APIC...");
-    let io_apic = unsafe { IoApic::new(phys_to_virt(IO_APIC_BASE).as_usize() as u64) };
+    let io_apic_base = crate::acpi::info()
+        .and_then(|info| info.io_apics().first())
+        .map_or(IO_APIC_BASE, |io_apic| pa!(io_apic.paddr));
+    info!("Initialize IO APIC at {:#x}...", io_apic_base);
+    let mut io_apic = unsafe { IoApic::new(phys_to_virt(io_apic_base).as_usize() as u64) };
+    // All inputs are masked, and routed to the primary CPU.
+    unsafe { io_apic.init(IO_APIC_VECTOR_BASE) };
     IO_APIC.init_once(SpinNoIrq::new(io_apic));
 }
 
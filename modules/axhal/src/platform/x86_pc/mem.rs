@@ -12,4 +12,9 @@ pub(crate) fn platform_regions() -> impl Iterator<Item = MemRegion> {
     })
     .chain(crate::mem::default_free_regions())
     .chain(crate::mem::default_mmio_regions())
+    .chain(crate::mem::extra_mmio_regions(
+        crate::acpi::info()
+            .into_iter()
+            .flat_map(|info| info.mmio_regions()),
+    ))
 }
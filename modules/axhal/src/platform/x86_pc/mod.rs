@@ -24,17 +24,38 @@ unsafe extern "C" {
     fn rust_main_secondary(cpu_id: usize) -> !;
 }
 
+/// Returns the APIC ID of the current CPU.
+///
+/// The initial APIC ID in CPUID leaf 0x1 has only 8 bits, so the 32-bit x2APIC
+/// ID in the extended topology leaf (0x1F or 0xB) is preferred if available.
+fn current_apic_id() -> u32 {
+    let cpuid = raw_cpuid::CpuId::new();
+    let x2apic_id = cpuid
+        .get_extended_topology_info_v2()
+        .and_then(|mut levels| levels.next())
+        .or_else(|| {
+            cpuid
+                .get_extended_topology_info()
+                .and_then(|mut levels| levels.next())
+        })
+        .map(|level| level.x2apic_id());
+    x2apic_id.unwrap_or_else(|| {
+        cpuid
+            .get_feature_info()
+            .map_or(0, |finfo| finfo.initial_local_apic_id() as u32)
+    })
+}
+
 fn current_cpu_id() -> usize {
-    match raw_cpuid::CpuId::new().get_feature_info() {
-        Some(finfo) => finfo.initial_local_apic_id() as usize,
-        None => 0,
-    }
+    let apic_id = current_apic_id();
+    crate::acpi::cpu_id_of(apic_id).unwrap_or(apic_id as usize)
 }
 
 unsafe extern "C" fn rust_entry(magic: usize, _mbi: usize) {
     // TODO: handle multiboot info
     if magic == self::boot::MULTIBOOT_BOOTLOADER_MAGIC {
         crate::mem::clear_bss();
+        crate::acpi::init();
         crate::cpu::init_primary(current_cpu_id());
         self::uart16550::init();
         self::time::init_early();
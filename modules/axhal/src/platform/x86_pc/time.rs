@@ -1,10 +1,27 @@
 use raw_cpuid::CpuId;
+use x86_64::instructions::port::Port;
 
 #[cfg(feature = "irq")]
 use int_ratio::Ratio;
 
+use crate::mem::phys_to_virt;
+
+/// The duration of the calibration of TSC and the local APIC timer.
+const CALIBRATION_MILLIS: u64 = 10;
+
+const PIT_FREQUENCY: u64 = 1_193_182;
+
+const HPET_GENERAL_CAPS: usize = 0x0;
+const HPET_GENERAL_CONFIG: usize = 0x10;
+const HPET_MAIN_COUNTER: usize = 0xf0;
+const HPET_COUNT_SIZE_CAP: u64 = 1 << 13;
+const HPET_ENABLE_CNF: u64 = 1 << 0;
+/// The maximum period of the HPET counter allowed by the specification.
+const HPET_MAX_PERIOD_FS: u64 = 100_000_000;
+
+/// The frequency of the local APIC timer, if the calibration fails.
 #[cfg(feature = "irq")]
-const LAPIC_TICKS_PER_SEC: u64 = 1_000_000_000; // TODO: need to calibrate
+const DEFAULT_LAPIC_TICKS_PER_SEC: u64 = 1_000_000_000;
 
 #[cfg(feature = "irq")]
 static mut NANOS_TO_LAPIC_TICKS_RATIO: Ratio = Ratio::zero();
@@ -38,6 +55,8 @@ pub fn epochoffset_nanos() -> u64 {
 /// Set a one-shot timer.
 ///
 /// A timer interrupt will be triggered at the specified monotonic time deadline (in nanoseconds).
+/// If the deadline is beyond the range of the LAPIC timer, the interrupt is
+/// triggered earlier.
 #[cfg(feature = "irq")]
 pub fn set_oneshot_timer(deadline_ns: u64) {
     let lapic = super::apic::local_apic();
@@ -45,16 +64,84 @@ pub fn set_oneshot_timer(deadline_ns: u64) {
     unsafe {
         if now_ns < deadline_ns {
             let apic_ticks = NANOS_TO_LAPIC_TICKS_RATIO.mul_trunc(deadline_ns - now_ns);
-            assert!(apic_ticks <= u32::MAX as u64);
-            lapic.set_timer_initial(apic_ticks.max(1) as u32);
+            lapic.set_timer_initial(apic_ticks.clamp(1, u32::MAX as u64) as u32);
         } else {
             lapic.set_timer_initial(1);
         }
     }
 }
 
+/// Busy-waits for the given number of milliseconds with the HPET counter.
+///
+/// Returns `false` if the HPET is not available.
+fn hpet_wait_millis(millis: u64) -> bool {
+    let Some(paddr) = crate::acpi::info().and_then(|info| info.hpet_paddr()) else {
+        return false;
+    };
+    let base = phys_to_virt(pa!(paddr)).as_usize();
+    let reg = |offset: usize| (base + offset) as *mut u64;
+    unsafe {
+        let caps = reg(HPET_GENERAL_CAPS).read_volatile();
+        let period_fs = caps >> 32;
+        if period_fs == 0 || period_fs > HPET_MAX_PERIOD_FS {
+            return false;
+        }
+        let mask = if caps & HPET_COUNT_SIZE_CAP != 0 {
+            u64::MAX
+        } else {
+            u32::MAX as u64
+        };
+        let config = reg(HPET_GENERAL_CONFIG).read_volatile();
+        reg(HPET_GENERAL_CONFIG).write_volatile(config | HPET_ENABLE_CNF);
+
+        let ticks = millis * 1_000_000_000_000 / period_fs;
+        let start = reg(HPET_MAIN_COUNTER).read_volatile();
+        while reg(HPET_MAIN_COUNTER).read_volatile().wrapping_sub(start) & mask < ticks {
+            core::hint::spin_loop();
+        }
+    }
+    true
+}
+
+/// Busy-waits for the given number of milliseconds (up to 50) with the
+/// channel 2 of the legacy PIT.
+fn pit_wait_millis(millis: u64) {
+    let count = PIT_FREQUENCY * millis / 1000;
+    assert!(count <= u16::MAX as u64);
+    let mut port_b = Port::<u8>::new(0x61);
+    unsafe {
+        // Enable the gate of channel 2, and disable the speaker.
+        let val = port_b.read();
+        port_b.write((val & !0x02) | 0x01);
+        // Channel 2, lobyte/hibyte, mode 0 (interrupt on terminal count).
+        Port::<u8>::new(0x43).write(0xb0);
+        let mut channel2 = Port::<u8>::new(0x42);
+        channel2.write(count as u8);
+        channel2.write((count >> 8) as u8);
+        // The output of channel 2 goes high when the count reaches zero.
+        while port_b.read() & 0x20 == 0 {
+            core::hint::spin_loop();
+        }
+    }
+}
+
+/// Runs `f` before and after waiting for [`CALIBRATION_MILLIS`] with the HPET
+/// (or the PIT if there is no HPET), returns the number of `f`'s ticks per
+/// second.
+fn calibrate(mut f: impl FnMut() -> u64) -> u64 {
+    let start = f();
+    if !hpet_wait_millis(CALIBRATION_MILLIS) {
+        pit_wait_millis(CALIBRATION_MILLIS);
+    }
+    f().wrapping_sub(start) * 1000 / CALIBRATION_MILLIS
+}
+
 pub(super) fn init_early() {
-    if let Some(freq) = CpuId::new()
+    let tsc_freq = calibrate(|| unsafe { core::arch::x86_64::_rdtsc() });
+    if tsc_freq >= 1_000_000 {
+        axlog::ax_println!("Calibrated TSC frequency: {} MHz", tsc_freq / 1_000_000);
+        unsafe { CPU_FREQ_MHZ = tsc_freq / 1_000_000 }
+    } else if let Some(freq) = CpuId::new()
         .get_processor_frequency_info()
         .map(|info| info.processor_base_frequency())
         && freq > 0
@@ -89,9 +176,22 @@ pub(super) fn init_primary() {
         lapic.set_timer_divide(TimerDivide::Div256); // indeed it is Div1, the name is confusing.
         lapic.enable_timer();
 
-        // TODO: calibrate with HPET
+        // The timer counts down from the initial count.
+        lapic.set_timer_initial(u32::MAX);
+        let lapic_ticks_per_sec = match calibrate(|| (u32::MAX - lapic.timer_current()) as u64) {
+            0 => {
+                warn!("Failed to calibrate LAPIC timer");
+                DEFAULT_LAPIC_TICKS_PER_SEC
+            }
+            freq => {
+                info!("Calibrated LAPIC timer frequency: {} Hz", freq);
+                freq
+            }
+        };
+        lapic.set_timer_initial(0);
+
         NANOS_TO_LAPIC_TICKS_RATIO = Ratio::new(
-            LAPIC_TICKS_PER_SEC as u32,
+            lapic_ticks_per_sec as u32,
             crate::time::NANOS_PER_SEC as u32,
         );
     }
@@ -81,6 +81,11 @@ impl Uart16550 {
             None
         }
     }
+
+    #[cfg(feature = "irq")]
+    fn enable_rx_interrupt(&mut self) {
+        unsafe { self.int_en.write(0x01) };
+    }
 }
 
 /// Writes a byte to the console.
@@ -122,6 +127,18 @@ pub fn read_bytes(bytes: &mut [u8]) -> usize {
     read_len
 }
 
+/// The IRQ number of console input.
+#[cfg(feature = "irq")]
+pub(crate) const INPUT_IRQ_NUM: Option<usize> = Some(super::apic::UART_IRQ_NUM);
+
+/// Enables the "received data available" interrupt of the UART.
+///
+/// The interrupt is cleared once the received data are read.
+#[cfg(feature = "irq")]
+pub(crate) fn enable_input_irq() {
+    COM1.lock().enable_rx_interrupt();
+}
+
 pub(super) fn init() {
     COM1.lock().init(115200);
 }
@@ -8,35 +8,108 @@ pub use linkme::distributed_slice as register_trap_handler;
 
 use crate::arch::TrapFrame;
 
-/// A slice of IRQ handler functions.
+/// A trap handler with its priority.
+///
+/// Multiple handlers can be registered for the same trap. They are called in
+/// the order of their priorities (the smaller the earlier, handlers with the
+/// same priority are called in an unspecified order), until one of them claims
+/// the event.
+///
+/// # Example
+///
+/// ```ignore
+/// use axhal::trap::{PAGE_FAULT, TrapHandler, register_trap_handler};
+///
+/// #[register_trap_handler(PAGE_FAULT)]
+/// static MY_PAGE_FAULT_HANDLER: TrapHandler<PageFaultHandler> =
+///     TrapHandler::with_priority(-10, handle_page_fault);
+/// ```
+#[derive(Debug)]
+pub struct TrapHandler<F> {
+    /// The priority, handlers with smaller values are called earlier.
+    pub priority: i32,
+    /// The handler function.
+    pub handler: F,
+}
+
+impl<F> TrapHandler<F> {
+    /// The default priority.
+    pub const DEFAULT_PRIORITY: i32 = 0;
+
+    /// Creates a trap handler with the default priority.
+    pub const fn new(handler: F) -> Self {
+        Self::with_priority(Self::DEFAULT_PRIORITY, handler)
+    }
+
+    /// Creates a trap handler with the given priority.
+    pub const fn with_priority(priority: i32, handler: F) -> Self {
+        Self { priority, handler }
+    }
+}
+
+/// IRQ handler, returns whether the IRQ is handled.
+pub type IrqTrapHandler = fn(usize) -> bool;
+
+/// Page fault handler, returns whether the page fault is handled.
+///
+/// The arguments are the fault address, the access flags, and whether the
+/// fault is from user space.
+pub type PageFaultHandler = fn(VirtAddr, MappingFlags, bool) -> bool;
+
+/// Syscall handler, returns `None` if the syscall is not handled.
+#[cfg(feature = "uspace")]
+pub type SyscallHandler = fn(&mut TrapFrame, usize) -> Option<isize>;
+
+/// A slice of IRQ handlers.
 #[def_trap_handler]
-pub static IRQ: [fn(usize) -> bool];
+pub static IRQ: [TrapHandler<IrqTrapHandler>];
 
-/// A slice of page fault handler functions.
+/// A slice of page fault handlers.
 #[def_trap_handler]
-pub static PAGE_FAULT: [fn(VirtAddr, MappingFlags, bool) -> bool];
+pub static PAGE_FAULT: [TrapHandler<PageFaultHandler>];
 
-/// A slice of syscall handler functions.
+/// A slice of syscall handlers.
 #[cfg(feature = "uspace")]
 #[def_trap_handler]
-pub static SYSCALL: [fn(&mut TrapFrame, usize) -> isize];
+pub static SYSCALL: [TrapHandler<SyscallHandler>];
 
 /// A slice of callbacks to be invoked after a trap.
 #[linkme::distributed_slice]
 pub static POST_TRAP: [fn(&mut TrapFrame, bool)];
 
+/// Calls `f` with the handlers in the order of their priorities, until it
+/// returns `Some`.
+pub(crate) fn dispatch<F: Copy, R>(
+    handlers: &[TrapHandler<F>],
+    mut f: impl FnMut(F) -> Option<R>,
+) -> Option<R> {
+    // The handlers are sorted by `(priority, index)`. There are only a few
+    // handlers per trap, so find the next one by a linear search each time,
+    // instead of sorting them with extra storage.
+    let mut last = None;
+    loop {
+        let next = handlers
+            .iter()
+            .enumerate()
+            .map(|(idx, h)| (h.priority, idx))
+            .filter(|&key| last.is_none_or(|last| key > last))
+            .min()?;
+        if let Some(ret) = f(handlers[next.1].handler) {
+            return Some(ret);
+        }
+        last = Some(next);
+    }
+}
+
 #[allow(unused_macros)]
 macro_rules! handle_trap {
     ($trap:ident, $($args:tt)*) => {{
-        let mut iter = $crate::trap::$trap.iter();
-        if let Some(func) = iter.next() {
-            if iter.next().is_some() {
-                warn!("Multiple handlers for trap {} are not currently supported", stringify!($trap));
-            }
-            func($($args)*)
-        } else {
+        let handlers = &$crate::trap::$trap;
+        if handlers.is_empty() {
             warn!("No registered handler for trap {}", stringify!($trap));
             false
+        } else {
+            $crate::trap::dispatch(handlers, |handler| handler($($args)*).then_some(())).is_some()
         }
     }}
 }
@@ -48,8 +121,62 @@ pub(crate) fn post_trap_callback(tf: &mut TrapFrame, from_user: bool) {
     }
 }
 
-/// Call the external syscall handler.
+/// Call the external syscall handlers.
+///
+/// It returns `-ENOSYS` if no handler handles the syscall.
 #[cfg(feature = "uspace")]
 pub(crate) fn handle_syscall(tf: &mut TrapFrame, syscall_num: usize) -> isize {
-    SYSCALL[0](tf, syscall_num)
+    dispatch(&SYSCALL, |handler| handler(tf, syscall_num)).unwrap_or_else(|| {
+        warn!("Unhandled syscall {}", syscall_num);
+        -(axerrno::LinuxError::ENOSYS as isize)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A handler that is identified by a number, and claims the event or not.
+    type TestHandler = (usize, bool);
+
+    /// Dispatches to the handlers, returns the handlers called in order and
+    /// the one that claims the event.
+    fn run(handlers: &[TrapHandler<TestHandler>]) -> (Vec<usize>, Option<usize>) {
+        let mut called = Vec::new();
+        let claimed = dispatch(handlers, |(id, claims)| {
+            called.push(id);
+            claims.then_some(id)
+        });
+        (called, claimed)
+    }
+
+    #[test]
+    fn priority_order() {
+        let handlers = [
+            TrapHandler::new((0, false)),
+            TrapHandler::with_priority(-10, (1, false)),
+            TrapHandler::with_priority(5, (2, false)),
+            TrapHandler::new((3, false)),
+        ];
+        // Handlers with the same priority are called in the order of
+        // registration here.
+        assert_eq!(run(&handlers), (vec![1, 0, 3, 2], None));
+    }
+
+    #[test]
+    fn fall_through() {
+        let handlers = [
+            TrapHandler::new((0, true)),
+            TrapHandler::with_priority(-1, (1, true)),
+            TrapHandler::with_priority(-2, (2, false)),
+        ];
+        // Stops at the first handler that claims the event.
+        assert_eq!(run(&handlers), (vec![2, 1], Some(1)));
+        assert_eq!(run(&handlers[..1]), (vec![0], Some(0)));
+    }
+
+    #[test]
+    fn no_handlers() {
+        assert_eq!(run(&[]), (vec![], None));
+    }
 }
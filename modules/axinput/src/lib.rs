@@ -0,0 +1,266 @@
+//! [ArceOS](https://github.com/arceos-org/arceos) input module.
+//!
+//! Events reported by input devices (keyboards, mice, tablets, etc.) are
+//! timestamped and queued per device, and read by applications in order, like
+//! reading `/dev/input/eventN` on Linux. Devices are identified by their
+//! indices, in the order they are registered, and device `N` is usually the
+//! registered device `eventN`.
+//!
+//! All readers of a device share its queue, i.e., an event is consumed by only
+//! one of them.
+//!
+//! # Cargo Features
+//!
+//! - `irq`: Wake up blocked readers on device interrupts. Devices that do not
+//!   raise interrupts are polled periodically. Requires `multitask` to take
+//!   effect.
+//! - `multitask`: Blocked readers yield the CPU to other tasks.
+
+#![no_std]
+
+#[macro_use]
+extern crate log;
+extern crate alloc;
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use axdriver::registry::{self, DeviceRef};
+use axdriver::{AxInputDevice, prelude::*};
+use axerrno::{AxError, AxResult};
+use kspin::SpinNoPreempt;
+use lazyinit::LazyInit;
+
+#[doc(no_inline)]
+pub use axdriver::input::{EV_ABS, EV_KEY, EV_REL, EV_SYN};
+
+/// The code of an [`EV_SYN`] event which tells that events were dropped
+/// since the queue was full, and the state of the device should be
+/// resynchronized.
+pub const SYN_DROPPED: u16 = 3;
+
+/// The maximum number of queued events of a device.
+const QUEUE_CAPACITY: usize = 256;
+
+/// An event reported by an input device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputEvent {
+    /// The monotonic time when the event was received.
+    pub time: Duration,
+    /// The event type, e.g., [`EV_KEY`].
+    pub event_type: u16,
+    /// The event code, e.g., the key code of an [`EV_KEY`] event.
+    pub code: u16,
+    /// The event value, e.g., 1 for a key press, or the (signed) motion of an
+    /// [`EV_REL`] event.
+    pub value: i32,
+}
+
+struct DeviceState {
+    driver: DeviceRef<AxInputDevice>,
+    queue: VecDeque<InputEvent>,
+}
+
+impl DeviceState {
+    /// Moves the pending events of the driver to the queue.
+    ///
+    /// If the queue overflows, the queued events are discarded, and replaced
+    /// by a [`SYN_DROPPED`] event.
+    fn fetch(&mut self) {
+        loop {
+            let event = match self.driver.lock().read_event() {
+                Ok(event) => event,
+                Err(DevError::Again) => break,
+                Err(e) => {
+                    warn!("failed to read input event: {:?}", e);
+                    break;
+                }
+            };
+            let time = axhal::time::monotonic_time();
+            if self.queue.len() == QUEUE_CAPACITY {
+                self.queue.clear();
+                self.queue.push_back(InputEvent {
+                    time,
+                    event_type: EV_SYN,
+                    code: SYN_DROPPED,
+                    value: 0,
+                });
+            }
+            self.queue.push_back(InputEvent {
+                time,
+                event_type: event.event_type,
+                code: event.code,
+                value: event.value as i32,
+            });
+        }
+    }
+}
+
+struct InputDevice {
+    name: String,
+    /// Bitmap of the event types that the device may report.
+    event_types: u32,
+    state: SpinNoPreempt<DeviceState>,
+}
+
+static DEVICES: LazyInit<Vec<InputDevice>> = LazyInit::new();
+
+fn device(id: usize) -> AxResult<&'static InputDevice> {
+    DEVICES
+        .get()
+        .and_then(|devs| devs.get(id))
+        .ok_or(AxError::NotFound)
+}
+
+/// Initializes the input subsystem by all registered input devices.
+pub fn init_input() {
+    info!("Initialize input subsystem...");
+
+    let mut devices = Vec::new();
+    for dev in registry::devices::<AxInputDevice>() {
+        let (name, event_types) = {
+            let mut driver = dev.lock();
+            let event_types = [EV_SYN, EV_KEY, EV_REL, EV_ABS]
+                .into_iter()
+                .filter(|&ty| driver.has_event_type(ty))
+                .fold(0, |bits, ty| bits | (1 << ty));
+            (String::from(driver.device_name()), event_types)
+        };
+        info!(
+            "  use input device {} ({}): {:?}, event types {:#x}",
+            devices.len(),
+            dev.name(),
+            name,
+            event_types
+        );
+        wait::init_device(dev.name());
+        devices.push(InputDevice {
+            name,
+            event_types,
+            state: SpinNoPreempt::new(DeviceState {
+                driver: dev,
+                queue: VecDeque::with_capacity(QUEUE_CAPACITY),
+            }),
+        });
+    }
+    DEVICES.init_once(devices);
+}
+
+/// Returns the number of input devices.
+pub fn device_count() -> usize {
+    DEVICES.get().map_or(0, Vec::len)
+}
+
+/// Returns the name of the given input device.
+pub fn device_name(id: usize) -> AxResult<&'static str> {
+    Ok(device(id)?.name.as_str())
+}
+
+/// Whether the given input device may report events of the given type, e.g.,
+/// a keyboard reports [`EV_KEY`] events, while a mouse reports [`EV_REL`]
+/// events too.
+pub fn has_event_type(id: usize, event_type: u16) -> AxResult<bool> {
+    let dev = device(id)?;
+    Ok(event_type < 32 && dev.event_types & (1 << event_type) != 0)
+}
+
+/// Whether there are queued events of the given input device, i.e., reading
+/// events does not block.
+pub fn has_events(id: usize) -> AxResult<bool> {
+    let mut state = device(id)?.state.lock();
+    state.fetch();
+    Ok(!state.queue.is_empty())
+}
+
+/// Reads events of the given input device into `buf`, and returns the number
+/// of events read.
+///
+/// If there are no events, it blocks until some arrive, or returns
+/// [`AxError::WouldBlock`] if `nonblocking` is set.
+pub fn read_events(id: usize, buf: &mut [InputEvent], nonblocking: bool) -> AxResult<usize> {
+    let dev = device(id)?;
+    if buf.is_empty() {
+        return Ok(0);
+    }
+    loop {
+        let seen = wait::current_events();
+        {
+            let mut state = dev.state.lock();
+            state.fetch();
+            if !state.queue.is_empty() {
+                let n = buf.len().min(state.queue.len());
+                for (slot, event) in buf.iter_mut().zip(state.queue.drain(..n)) {
+                    *slot = event;
+                }
+                return Ok(n);
+            }
+        }
+        if nonblocking {
+            return Err(AxError::WouldBlock);
+        }
+        wait::wait_for_event(seen);
+    }
+}
+
+/// Reads one event of the given input device.
+///
+/// See [`read_events`] for the blocking behavior.
+pub fn read_event(id: usize, nonblocking: bool) -> AxResult<InputEvent> {
+    let mut event = [InputEvent {
+        time: Duration::ZERO,
+        event_type: EV_SYN,
+        code: 0,
+        value: 0,
+    }];
+    read_events(id, &mut event, nonblocking)?;
+    Ok(event[0])
+}
+
+mod wait {
+    //! Waiting for input events, i.e., interrupts of input devices.
+    //!
+    //! The interrupt callbacks must not call into the drivers, so they only
+    //! wake up the readers, who then fetch the events from the drivers.
+
+    use core::time::Duration;
+
+    use axtask::Event;
+
+    /// The interval to poll devices that do not raise interrupts.
+    const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+    static EVENT: Event = Event::new();
+
+    cfg_if::cfg_if! {
+        if #[cfg(all(feature = "irq", feature = "multitask"))] {
+            fn on_input_irq() {
+                EVENT.notify(true);
+            }
+
+            /// Receives interrupts of the given input device, if it raises any.
+            pub(super) fn init_device(name: &str) {
+                if axdriver::irq::set_irq_callback(name, None, on_input_irq) {
+                    info!("  input device {} is interrupt-driven.", name);
+                }
+            }
+        } else {
+            pub(super) fn init_device(_name: &str) {}
+        }
+    }
+
+    /// Returns the number of events so far, which is passed to
+    /// [`wait_for_event`] later.
+    pub(super) fn current_events() -> usize {
+        EVENT.count()
+    }
+
+    /// Blocks until an input interrupt occurs after [`current_events`]
+    /// returned `seen`, or the polling interval elapses.
+    ///
+    /// Without interrupts, it only yields the CPU.
+    pub(super) fn wait_for_event(seen: usize) {
+        EVENT.wait_timeout(seen, POLL_INTERVAL);
+    }
+}
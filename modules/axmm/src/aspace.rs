@@ -9,6 +9,7 @@ use memory_set::{MemoryArea, MemorySet};
 use crate::backend::Backend;
 use crate::mapping_err_to_ax_err;
 use crate::page_iter_wrapper::{PAGE_SIZE_4K, PageIterWrapper};
+use crate::tlb::TlbFlush;
 
 #[cfg(feature = "cow")]
 use crate::backend::{alloc_frame, dealloc_frame};
@@ -20,6 +21,10 @@ pub struct AddrSpace {
     va_range: VirtAddrRange,
     areas: MemorySet<Backend>,
     pt: PageTable,
+    /// Remote TLB flushes that have not been sent yet.
+    tlb_flush: TlbFlush,
+    /// Whether remote TLB flushes are deferred until [`Self::take_tlb_flush`].
+    tlb_flush_deferred: bool,
 }
 
 use alloc::sync::Arc;
@@ -66,9 +71,40 @@ impl AddrSpace {
             va_range: VirtAddrRange::from_start_size(base, size),
             areas: MemorySet::new(),
             pt: PageTable::try_new().map_err(|_| AxError::NoMemory)?,
+            tlb_flush: TlbFlush::None,
+            tlb_flush_deferred: false,
         })
     }
 
+    /// Defers remote TLB flushes until they are taken by
+    /// [`Self::take_tlb_flush`], instead of sending them in place.
+    ///
+    /// It is used when the address space is protected by a lock that other
+    /// CPUs may spin on with IRQs disabled, so that the shootdown is sent after
+    /// the lock is released.
+    pub(crate) fn defer_tlb_flush(&mut self) {
+        self.tlb_flush_deferred = true;
+    }
+
+    /// Takes the deferred remote TLB flushes, to be sent by the caller.
+    pub(crate) fn take_tlb_flush(&mut self) -> TlbFlush {
+        core::mem::take(&mut self.tlb_flush)
+    }
+
+    /// Invalidates the TLB entries in `range` (or the entire TLBs if `range`
+    /// is `None`) on other CPUs, unless remote flushes are deferred.
+    fn flush_remote(&mut self, range: Option<VirtAddrRange>) {
+        self.tlb_flush.add(range);
+        self.commit_tlb_flush();
+    }
+
+    /// Sends the pending remote TLB flushes, unless they are deferred.
+    fn commit_tlb_flush(&mut self) {
+        if !self.tlb_flush_deferred {
+            self.tlb_flush.send();
+        }
+    }
+
     ///通过虚拟地址，查询物理地址
     pub fn query_paddr(&mut self, vaddr: VirtAddr) -> LinuxResult<PhysAddr> {
         if let Ok(x) = self.pt.query(vaddr) {
@@ -397,12 +433,14 @@ impl AddrSpace {
         self.areas
             .unmap(start, size, &mut self.pt)
             .map_err(mapping_err_to_ax_err)?;
+        self.flush_remote(Some(VirtAddrRange::from_start_size(start, size)));
         Ok(())
     }
 
     /// To remove user area mappings from address space.
     pub fn unmap_user_areas(&mut self) -> AxResult {
         self.areas.clear(&mut self.pt).unwrap();
+        self.flush_remote(None);
         Ok(())
     }
 
@@ -499,6 +537,7 @@ impl AddrSpace {
         self.areas
             .protect(start, size, |_| Some(flags), &mut self.pt)
             .map_err(mapping_err_to_ax_err)?;
+        self.flush_remote(Some(VirtAddrRange::from_start_size(start, size)));
 
         Ok(())
     }
@@ -506,6 +545,7 @@ impl AddrSpace {
     /// Removes all mappings in the address space.
     pub fn clear(&mut self) {
         self.areas.clear(&mut self.pt).unwrap();
+        self.flush_remote(None);
     }
 
     /// Checks whether an access to the specified memory region is valid.
@@ -582,13 +622,16 @@ impl AddrSpace {
                     // 1. page fault caused by write
                     // 2. pte exists
                     // 3. Not shared memory
-                    return Self::handle_cow_fault(
+                    let handled = Self::handle_cow_fault(
                         vaddr,
                         paddr,
                         orig_flags,
                         page_size,
                         &mut self.pt,
+                        &mut self.tlb_flush,
                     );
+                    self.commit_tlb_flush();
+                    return handled;
                 }
 
                 return area
@@ -714,7 +757,13 @@ impl AddrSpace {
                     Err(_) => return Err(AxError::BadAddress),
                 };
             }
+
+            // Pages in the old address space have become read-only.
+            #[cfg(feature = "cow")]
+            self.tlb_flush.add(Some(area.va_range()));
         }
+        #[cfg(feature = "cow")]
+        self.commit_tlb_flush();
         Ok(new_aspace)
     }
 
@@ -726,6 +775,7 @@ impl AddrSpace {
     /// - `flags`: vma flags.
     /// - `align`: Alignment requirement for the allocated memory, must be a multiple of 4KiB.
     /// - `pt`: A mutable reference to the page table that should be updated.
+    /// - `tlb_flush`: The remote TLB flush to add the remapped page to.
     ///
     /// # Returns
     /// - `true` if the page fault was handled successfully.
@@ -737,6 +787,7 @@ impl AddrSpace {
         flags: MappingFlags,
         align: PageSize,
         pt: &mut PageTable,
+        tlb_flush: &mut TlbFlush,
     ) -> bool {
         assert!(flags.contains(MappingFlags::WRITE));
 
@@ -761,11 +812,20 @@ impl AddrSpace {
 
                     dealloc_frame(paddr, align);
 
-                    pt.remap(vaddr, new_frame, flags)
+                    let remapped = pt
+                        .remap(vaddr, new_frame, flags)
                         .map(|(_, tlb)| {
                             tlb.flush();
                         })
-                        .is_ok()
+                        .is_ok();
+                    if remapped {
+                        // Other CPUs may still map the page to the old frame.
+                        tlb_flush.add(Some(VirtAddrRange::from_start_size(
+                            vaddr.align_down(align),
+                            align.into(),
+                        )));
+                    }
+                    remapped
                 }
                 None => false,
             },
@@ -786,5 +846,6 @@ impl fmt::Debug for AddrSpace {
 impl Drop for AddrSpace {
     fn drop(&mut self) {
         self.clear();
+        self.tlb_flush.send();
     }
 }
@@ -1,6 +1,6 @@
 //! [ArceOS](https://github.com/arceos-org/arceos) memory management module.
 
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
 #[macro_use]
 extern crate log;
@@ -10,20 +10,73 @@ mod aspace;
 mod backend;
 #[cfg(feature = "cow")]
 mod frameinfo;
+mod tlb;
 
 pub mod page_iter_wrapper;
 pub use self::aspace::AddrSpace;
 pub use self::backend::Backend;
 
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+
 use axerrno::{AxError, AxResult};
 use axhal::mem::phys_to_virt;
 use axhal::paging::PageSize;
-use kspin::SpinNoIrq;
+use kspin::{SpinNoIrq, SpinNoIrqGuard};
 use lazyinit::LazyInit;
 use memory_addr::{PhysAddr, va};
 use memory_set::MappingError;
 
-static KERNEL_ASPACE: LazyInit<SpinNoIrq<AddrSpace>> = LazyInit::new();
+static KERNEL_ASPACE: LazyInit<KernelAspace> = LazyInit::new();
+
+/// The lock of the kernel address space.
+///
+/// Other CPUs may spin on it with IRQs disabled, so they cannot answer TLB
+/// shootdowns sent while it is held. Shootdowns are deferred instead, and are
+/// sent when the guard returned by [`KernelAspace::lock`] is dropped, after
+/// the lock is released.
+pub struct KernelAspace(SpinNoIrq<AddrSpace>);
+
+impl KernelAspace {
+    fn new(mut aspace: AddrSpace) -> Self {
+        aspace.defer_tlb_flush();
+        Self(SpinNoIrq::new(aspace))
+    }
+
+    /// Locks the kernel address space.
+    pub fn lock(&self) -> KernelAspaceGuard<'_> {
+        KernelAspaceGuard(ManuallyDrop::new(self.0.lock()))
+    }
+}
+
+/// A guard of the locked kernel address space.
+///
+/// It sends the TLB shootdowns deferred by the operations on the address
+/// space after unlocking it.
+pub struct KernelAspaceGuard<'a>(ManuallyDrop<SpinNoIrqGuard<'a, AddrSpace>>);
+
+impl Deref for KernelAspaceGuard<'_> {
+    type Target = AddrSpace;
+
+    fn deref(&self) -> &AddrSpace {
+        &self.0
+    }
+}
+
+impl DerefMut for KernelAspaceGuard<'_> {
+    fn deref_mut(&mut self) -> &mut AddrSpace {
+        &mut self.0
+    }
+}
+
+impl Drop for KernelAspaceGuard<'_> {
+    fn drop(&mut self) {
+        let mut flush = self.0.take_tlb_flush();
+        // SAFETY: the guard is not used after being dropped.
+        unsafe { ManuallyDrop::drop(&mut self.0) };
+        flush.send();
+    }
+}
 
 fn mapping_err_to_ax_err(err: MappingError) -> AxError {
     warn!("Mapping error: {:?}", err);
@@ -53,7 +106,7 @@ pub fn new_kernel_aspace() -> AxResult<AddrSpace> {
 }
 
 /// Returns the globally unique kernel address space.
-pub fn kernel_aspace() -> &'static SpinNoIrq<AddrSpace> {
+pub fn kernel_aspace() -> &'static KernelAspace {
     &KERNEL_ASPACE
 }
 
@@ -71,7 +124,7 @@ pub fn init_memory_management() {
 
     let kernel_aspace = new_kernel_aspace().expect("failed to initialize kernel address space");
     debug!("kernel address space init OK: {:#x?}", kernel_aspace);
-    KERNEL_ASPACE.init_once(SpinNoIrq::new(kernel_aspace));
+    KERNEL_ASPACE.init_once(KernelAspace::new(kernel_aspace));
     axhal::paging::set_kernel_page_table_root(kernel_page_table_root());
 }
 
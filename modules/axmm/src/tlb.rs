@@ -0,0 +1,130 @@
+//! TLB shootdown.
+//!
+//! Page table operations only flush the TLB of the current CPU. When mappings
+//! are removed or restricted, stale entries must also be invalidated on other
+//! CPUs, as the address space may be active on them.
+
+use axhal::mem::PAGE_SIZE_4K;
+use memory_addr::VirtAddrRange;
+
+/// Flushing the TLB page by page is slower than flushing the entire TLB if
+/// the range is larger than this number of pages.
+const MAX_FLUSH_PAGES: usize = 64;
+
+/// Invalidates the TLB entries in `range` on all other CPUs, or the entire
+/// TLBs if `range` is `None`.
+pub(crate) fn flush_remote(range: Option<VirtAddrRange>) {
+    #[cfg(all(feature = "smp", feature = "irq"))]
+    axhal::mp::run_on_other_cpus(|| flush_local(range));
+    #[cfg(not(all(feature = "smp", feature = "irq")))]
+    let _ = range;
+}
+
+#[cfg(all(feature = "smp", feature = "irq"))]
+fn flush_local(range: Option<VirtAddrRange>) {
+    use axhal::arch::flush_tlb;
+    use memory_addr::MemoryAddr;
+
+    match range {
+        Some(range) if range.size() <= MAX_FLUSH_PAGES * PAGE_SIZE_4K => {
+            let mut vaddr = range.start.align_down_4k();
+            while vaddr < range.end {
+                flush_tlb(Some(vaddr));
+                vaddr += PAGE_SIZE_4K;
+            }
+        }
+        _ => flush_tlb(None),
+    }
+}
+
+/// A remote TLB flush that is accumulated and sent later.
+///
+/// It is used when the shootdown cannot be sent in place, e.g., while holding
+/// a lock that other CPUs may spin on with IRQs disabled, as they would never
+/// answer the shootdown.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TlbFlush {
+    /// Nothing to flush.
+    #[default]
+    None,
+    /// Flush the entries in the range.
+    Range(VirtAddrRange),
+    /// Flush the entire TLBs.
+    All,
+}
+
+impl TlbFlush {
+    /// Adds `range` to the flush, or the entire TLBs if `range` is `None`.
+    ///
+    /// Ranges are merged into the smallest range covering them, which turns
+    /// into a full flush once it is too large to be flushed page by page.
+    pub fn add(&mut self, range: Option<VirtAddrRange>) {
+        let merged = match (*self, range) {
+            (Self::All, _) | (_, None) => None,
+            (Self::None, Some(range)) => Some(range),
+            (Self::Range(old), Some(range)) => Some(VirtAddrRange::new(
+                old.start.min(range.start),
+                old.end.max(range.end),
+            )),
+        };
+        *self = match merged {
+            Some(range) if range.size() <= MAX_FLUSH_PAGES * PAGE_SIZE_4K => Self::Range(range),
+            _ => Self::All,
+        };
+    }
+
+    /// Sends the accumulated flush to all other CPUs, and resets it.
+    pub fn send(&mut self) {
+        match core::mem::take(self) {
+            Self::None => {}
+            Self::Range(range) => flush_remote(Some(range)),
+            Self::All => flush_remote(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use memory_addr::va_range;
+
+    use super::*;
+
+    #[test]
+    fn merge_ranges() {
+        let mut flush = TlbFlush::default();
+        flush.add(Some(va_range!(0x3000..0x4000)));
+        assert_eq!(flush, TlbFlush::Range(va_range!(0x3000..0x4000)));
+        flush.add(Some(va_range!(0x1000..0x2000)));
+        assert_eq!(flush, TlbFlush::Range(va_range!(0x1000..0x4000)));
+        flush.add(Some(va_range!(0x2000..0x3000)));
+        assert_eq!(flush, TlbFlush::Range(va_range!(0x1000..0x4000)));
+    }
+
+    #[test]
+    fn merge_into_full_flush() {
+        let mut flush = TlbFlush::default();
+        flush.add(Some(va_range!(0x1000..0x2000)));
+        flush.add(None);
+        assert_eq!(flush, TlbFlush::All);
+        flush.add(Some(va_range!(0x1000..0x2000)));
+        assert_eq!(flush, TlbFlush::All);
+
+        // Too far apart to be flushed page by page.
+        let mut flush = TlbFlush::default();
+        flush.add(Some(va_range!(0x1000..0x2000)));
+        let far = 0x1000 + MAX_FLUSH_PAGES * PAGE_SIZE_4K;
+        flush.add(Some(VirtAddrRange::from_start_size(
+            far.into(),
+            PAGE_SIZE_4K,
+        )));
+        assert_eq!(flush, TlbFlush::All);
+    }
+
+    #[test]
+    fn send_resets() {
+        let mut flush = TlbFlush::default();
+        flush.add(Some(va_range!(0x1000..0x2000)));
+        flush.send();
+        assert_eq!(flush, TlbFlush::None);
+    }
+}
@@ -14,6 +14,14 @@
 //!
 //! - `smoltcp`: Use [smoltcp] as the underlying network stack. This is enabled
 //!   by default.
+//! - `async`: Enable the `poll_*` methods of sockets for asynchronous I/O.
+//!   Pending operations are woken up when the sockets become ready, by a
+//!   background task polling the network stack.
+//! - `irq`: Interrupts are enabled. The background task of `async` sleeps
+//!   between polls, instead of yielding the CPU.
+//! - `multitask`: With `irq`, blocking operations sleep until the NIC raises
+//!   an interrupt, instead of polling the network stack repeatedly, if the NIC
+//!   supports interrupts.
 //!
 //! [smoltcp]: https://github.com/smoltcp-rs/smoltcp
 
@@ -35,13 +43,13 @@ pub use self::net_impl::UdpSocket;
 pub use self::net_impl::{bench_receive, bench_transmit};
 pub use self::net_impl::{dns_query, poll_interfaces};
 
-use axdriver::{AxDeviceContainer, prelude::*};
+use axdriver::{AxNetDevice, registry};
 
-/// Initializes the network subsystem by NIC devices.
-pub fn init_network(mut net_devs: AxDeviceContainer<AxNetDevice>) {
+/// Initializes the network subsystem by the first registered NIC.
+pub fn init_network() {
     info!("Initialize network subsystem...");
 
-    let dev = net_devs.take_one().expect("No NIC device found!");
-    info!("  use NIC 0: {:?}", dev.device_name());
+    let dev = registry::first::<AxNetDevice>().expect("No NIC device found!");
+    info!("  use NIC {}", dev.name());
     net_impl::init(dev);
 }
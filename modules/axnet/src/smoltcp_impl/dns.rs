@@ -7,6 +7,7 @@ use smoltcp::socket::dns::{self, GetQueryResultError, StartQueryError};
 use smoltcp::wire::DnsQueryType;
 
 use super::addr::into_core_ipaddr;
+use super::event::{current_events, wait_for_event};
 use super::{ETH0, SOCKET_SET, SocketSetWrapper};
 
 /// A DNS socket.
@@ -52,6 +53,7 @@ impl DnsSocket {
                 }
             })?;
         loop {
+            let seen = current_events();
             SOCKET_SET.poll_interfaces();
             match SOCKET_SET.with_socket_mut::<dns::Socket, _, _>(handle, |socket| {
                 socket.get_query_result(query_handle).map_err(|e| match e {
@@ -68,7 +70,7 @@ impl DnsSocket {
                     }
                     return Ok(res);
                 }
-                Err(AxError::WouldBlock) => axtask::yield_now(),
+                Err(AxError::WouldBlock) => wait_for_event(seen),
                 Err(e) => return Err(e),
             }
         }
@@ -0,0 +1,73 @@
+//! Waiting for network events, i.e., NIC interrupts and loopback packets,
+//! which may make sockets ready.
+//!
+//! If the NIC is not interrupt-driven, blocking operations poll the network
+//! stack repeatedly, and yield the CPU in between.
+
+use core::time::Duration;
+
+use axtask::Event;
+
+/// The maximum time to wait for an event, so that the timers of the network
+/// stack (e.g., TCP retransmission and delayed ACK) are handled in time.
+const MAX_WAIT: Duration = Duration::from_millis(10);
+
+static EVENT: Event = Event::new();
+
+cfg_if::cfg_if! {
+    if #[cfg(all(feature = "irq", feature = "multitask"))] {
+        use core::sync::atomic::{AtomicBool, Ordering};
+
+        static IRQ_DRIVEN: AtomicBool = AtomicBool::new(false);
+
+        fn on_nic_irq() {
+            EVENT.notify(true);
+        }
+
+        /// Whether the NIC raises interrupts, so that [`wait_for_event`] does
+        /// not need to poll.
+        pub(crate) fn irq_driven() -> bool {
+            IRQ_DRIVEN.load(Ordering::Acquire)
+        }
+
+        /// Receives interrupts of the NIC in use, if it raises any.
+        pub(crate) fn init(nic_name: &str) {
+            if axdriver::irq::set_irq_callback(nic_name, None, on_nic_irq) {
+                IRQ_DRIVEN.store(true, Ordering::Release);
+                info!("  NIC is interrupt-driven.");
+            }
+        }
+    } else {
+        pub(crate) fn irq_driven() -> bool {
+            false
+        }
+
+        pub(crate) fn init(_nic_name: &str) {}
+    }
+}
+
+/// Returns the number of events so far, which is passed to [`wait_for_event`]
+/// later.
+///
+/// It should be taken before polling the network stack, so that no event
+/// after the poll is missed.
+pub(crate) fn current_events() -> usize {
+    EVENT.count()
+}
+
+/// Records an event that is not signaled by the NIC (e.g., a loopback
+/// packet), and wakes up the waiters.
+pub(crate) fn notify_event() {
+    EVENT.notify(false);
+}
+
+/// Blocks until an event occurs after [`current_events`] returned `seen`.
+///
+/// If the NIC is not interrupt-driven, it only yields the CPU.
+pub(crate) fn wait_for_event(seen: usize) {
+    if irq_driven() {
+        EVENT.wait_timeout(seen, MAX_WAIT);
+    } else {
+        axtask::yield_now();
+    }
+}
@@ -1,5 +1,7 @@
 use alloc::{boxed::Box, collections::VecDeque};
 use core::ops::{Deref, DerefMut};
+#[cfg(feature = "async")]
+use core::task::Waker;
 
 use axerrno::{AxError, AxResult, ax_err};
 use axsync::Mutex;
@@ -14,6 +16,10 @@ const PORT_NUM: usize = 65536;
 struct ListenTableEntry {
     listen_endpoint: IpListenEndpoint,
     syn_queue: VecDeque<SocketHandle>,
+    /// The waker of the pending asynchronous `accept`, registered in all
+    /// sockets in the SYN queue.
+    #[cfg(feature = "async")]
+    waker: Option<Waker>,
 }
 
 impl ListenTableEntry {
@@ -21,6 +27,8 @@ impl ListenTableEntry {
         Self {
             listen_endpoint,
             syn_queue: VecDeque::with_capacity(LISTEN_QUEUE_SIZE),
+            #[cfg(feature = "async")]
+            waker: None,
         }
     }
 
@@ -110,6 +118,32 @@ impl ListenTable {
         }
     }
 
+    /// Like [`accept`](Self::accept), but if there is no connection ready,
+    /// `waker` is woken up when any of the pending connections is established.
+    #[cfg(feature = "async")]
+    pub fn poll_accept(
+        &self,
+        port: u16,
+        waker: &Waker,
+    ) -> AxResult<(SocketHandle, (IpEndpoint, IpEndpoint))> {
+        let res = self.accept(port);
+        if matches!(res, Err(AxError::WouldBlock)) {
+            if let Some(entry) = self.tcp[port as usize].lock().deref_mut() {
+                entry.waker = Some(waker.clone());
+                for &handle in &entry.syn_queue {
+                    SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                        socket.register_recv_waker(waker);
+                        // Established after the check above.
+                        if !matches!(socket.state(), State::Listen | State::SynReceived) {
+                            waker.wake_by_ref();
+                        }
+                    });
+                }
+            }
+        }
+        res
+    }
+
     pub fn incoming_tcp_packet(
         &self,
         src: IpEndpoint,
@@ -127,6 +161,10 @@ impl ListenTable {
                 return;
             }
             let mut socket = SocketSetWrapper::new_tcp_socket();
+            #[cfg(feature = "async")]
+            if let Some(waker) = &entry.waker {
+                socket.register_recv_waker(waker);
+            }
             if socket.listen(entry.listen_endpoint).is_ok() {
                 let handle = sockets.add(socket);
                 debug!(
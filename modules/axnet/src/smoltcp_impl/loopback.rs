@@ -87,6 +87,7 @@ impl<'a> TxToken for LBTxToken<'a> {
         let mut buffer = vec![0u8; len];
         let result = f(&mut buffer);
         self.queue.push_back(buffer);
+        super::event::notify_event();
         result
     }
 }
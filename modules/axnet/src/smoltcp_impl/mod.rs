@@ -1,16 +1,22 @@
 mod addr;
 mod bench;
 mod dns;
+mod event;
 mod listen_table;
 mod loopback;
+#[cfg(feature = "async")]
+mod reactor;
 mod tcp;
 mod udp;
 
+use alloc::string::String;
 use alloc::vec;
-use core::cell::RefCell;
+use alloc::vec::Vec;
 use core::ops::DerefMut;
 
+use axalloc::AllocTag;
 use axdriver::prelude::*;
+use axdriver::registry::{Device as AxDevice, DeviceRef};
 use axdriver_net::{DevError, NetBufPtr};
 use axhal::time::{NANOS_PER_MICROS, wall_time_nanos};
 use axsync::Mutex;
@@ -45,8 +51,6 @@ const IP_PREFIX: u8 = 24;
 
 const STANDARD_MTU: usize = 1500;
 
-const RANDOM_SEED: u64 = 0xA2CE_05A2_CE05_A2CE;
-
 const TCP_RX_BUF_LEN: usize = 64 * 1024;
 const TCP_TX_BUF_LEN: usize = 64 * 1024;
 const UDP_RX_BUF_LEN: usize = 64 * 1024;
@@ -60,11 +64,11 @@ static ETH0: LazyInit<InterfaceWrapper> = LazyInit::new();
 struct SocketSetWrapper<'a>(Mutex<SocketSet<'a>>);
 
 struct DeviceWrapper {
-    inner: RefCell<AxNetDevice>, // use `RefCell` is enough since it's wrapped in `Mutex` in `InterfaceWrapper`.
+    inner: DeviceRef<AxNetDevice>,
 }
 
 struct InterfaceWrapper {
-    name: &'static str,
+    name: String,
     ether_addr: EthernetAddress,
     dev: Mutex<DeviceWrapper>,
     iface: Mutex<Interface>,
@@ -76,19 +80,19 @@ impl<'a> SocketSetWrapper<'a> {
     }
 
     pub fn new_tcp_socket() -> socket::tcp::Socket<'a> {
-        let tcp_rx_buffer = socket::tcp::SocketBuffer::new(vec![0; TCP_RX_BUF_LEN]);
-        let tcp_tx_buffer = socket::tcp::SocketBuffer::new(vec![0; TCP_TX_BUF_LEN]);
+        let tcp_rx_buffer = socket::tcp::SocketBuffer::new(socket_buffer(TCP_RX_BUF_LEN));
+        let tcp_tx_buffer = socket::tcp::SocketBuffer::new(socket_buffer(TCP_TX_BUF_LEN));
         socket::tcp::Socket::new(tcp_rx_buffer, tcp_tx_buffer)
     }
 
     pub fn new_udp_socket() -> socket::udp::Socket<'a> {
         let udp_rx_buffer = socket::udp::PacketBuffer::new(
             vec![socket::udp::PacketMetadata::EMPTY; 8],
-            vec![0; UDP_RX_BUF_LEN],
+            socket_buffer(UDP_RX_BUF_LEN),
         );
         let udp_tx_buffer = socket::udp::PacketBuffer::new(
             vec![socket::udp::PacketMetadata::EMPTY; 8],
-            vec![0; UDP_TX_BUF_LEN],
+            socket_buffer(UDP_TX_BUF_LEN),
         );
         socket::udp::Socket::new(udp_rx_buffer, udp_tx_buffer)
     }
@@ -138,10 +142,14 @@ impl<'a> SocketSetWrapper<'a> {
 }
 
 impl InterfaceWrapper {
-    fn new(name: &'static str, dev: AxNetDevice, ether_addr: EthernetAddress) -> Self {
+    fn new(dev: DeviceRef<AxNetDevice>, ether_addr: EthernetAddress) -> Self {
         let mut config = Config::new(HardwareAddress::Ethernet(ether_addr));
-        config.random_seed = RANDOM_SEED;
+        // The seed decides TCP initial sequence numbers and local ports, so it
+        // must not be predictable.
+        axrand::add_entropy(ether_addr.as_bytes());
+        config.random_seed = axrand::random_u64();
 
+        let name = String::from(dev.name());
         let mut dev = DeviceWrapper::new(dev);
         let iface = Mutex::new(Interface::new(config, &mut dev, Self::current_time()));
         Self {
@@ -157,7 +165,7 @@ impl InterfaceWrapper {
     }
 
     pub fn name(&self) -> &str {
-        self.name
+        &self.name
     }
 
     pub fn ethernet_address(&self) -> EthernetAddress {
@@ -189,10 +197,8 @@ impl InterfaceWrapper {
 }
 
 impl DeviceWrapper {
-    fn new(inner: AxNetDevice) -> Self {
-        Self {
-            inner: RefCell::new(inner),
-        }
+    fn new(inner: DeviceRef<AxNetDevice>) -> Self {
+        Self { inner }
     }
 }
 
@@ -207,7 +213,7 @@ impl Device for DeviceWrapper {
         Self: 'a;
 
     fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
-        let mut dev = self.inner.borrow_mut();
+        let mut dev = self.inner.lock();
         if let Err(e) = dev.recycle_tx_buffers() {
             warn!("recycle_tx_buffers failed: {:?}", e);
             return None;
@@ -229,7 +235,7 @@ impl Device for DeviceWrapper {
     }
 
     fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
-        let mut dev = self.inner.borrow_mut();
+        let mut dev = self.inner.lock();
         if let Err(e) = dev.recycle_tx_buffers() {
             warn!("recycle_tx_buffers failed: {:?}", e);
             return None;
@@ -250,8 +256,8 @@ impl Device for DeviceWrapper {
     }
 }
 
-struct AxNetRxToken<'a>(&'a RefCell<AxNetDevice>, NetBufPtr);
-struct AxNetTxToken<'a>(&'a RefCell<AxNetDevice>);
+struct AxNetRxToken<'a>(&'a AxDevice<AxNetDevice>, NetBufPtr);
+struct AxNetTxToken<'a>(&'a AxDevice<AxNetDevice>);
 
 impl RxToken for AxNetRxToken<'_> {
     fn preprocess(&self, sockets: &mut SocketSet<'_>) {
@@ -269,7 +275,7 @@ impl RxToken for AxNetRxToken<'_> {
             rx_buf.packet()
         );
         let result = f(rx_buf.packet_mut());
-        self.0.borrow_mut().recycle_rx_buffer(rx_buf).unwrap();
+        self.0.lock().recycle_rx_buffer(rx_buf).unwrap();
         result
     }
 }
@@ -279,7 +285,7 @@ impl TxToken for AxNetTxToken<'_> {
     where
         F: FnOnce(&mut [u8]) -> R,
     {
-        let mut dev = self.0.borrow_mut();
+        let mut dev = self.0.lock();
         let mut tx_buf = dev.alloc_tx_buffer(len).unwrap();
         let ret = f(tx_buf.packet_mut());
         trace!("SEND {} bytes: {:02X?}", len, tx_buf.packet());
@@ -288,6 +294,13 @@ impl TxToken for AxNetTxToken<'_> {
     }
 }
 
+/// Allocates a zeroed payload buffer of a socket.
+fn socket_buffer(len: usize) -> Vec<u8> {
+    let buf = vec![0; len];
+    axalloc::tag_alloc(buf.as_ptr(), AllocTag::NetBuffer);
+    buf
+}
+
 fn snoop_tcp_packet(buf: &[u8], sockets: &mut SocketSet<'_>) -> Result<(), smoltcp::wire::Error> {
     use smoltcp::wire::{EthernetFrame, IpProtocol, Ipv4Packet, TcpPacket};
 
@@ -326,7 +339,7 @@ pub fn bench_receive() {
 }
 static LOOPBACK_DEV: LazyInit<Mutex<LBDEV>> = LazyInit::new();
 static LOOPBACK: LazyInit<Mutex<Interface>> = LazyInit::new();
-pub(crate) fn init(net_dev: AxNetDevice) {
+pub(crate) fn init(net_dev: DeviceRef<AxNetDevice>) {
     let mut loopback_dev = LBDEV::new();
     let lbconfig = Config::new(smoltcp::wire::HardwareAddress::Ip);
     let mut lbiface = Interface::new(
@@ -342,8 +355,8 @@ pub(crate) fn init(net_dev: AxNetDevice) {
     LOOPBACK.init_once(Mutex::new(lbiface));
     LOOPBACK_DEV.init_once(Mutex::new(loopback_dev));
 
-    let ether_addr = EthernetAddress(net_dev.mac_address().0);
-    let eth0 = InterfaceWrapper::new("eth0", net_dev, ether_addr);
+    let ether_addr = EthernetAddress(net_dev.lock().mac_address().0);
+    let eth0 = InterfaceWrapper::new(net_dev, ether_addr);
 
     let ip = IP.parse().expect("invalid IP address");
     let gateway = GATEWAY.parse().expect("invalid gateway IP address");
@@ -353,6 +366,7 @@ pub(crate) fn init(net_dev: AxNetDevice) {
     ETH0.init_once(eth0);
     SOCKET_SET.init_once(SocketSetWrapper::new());
     LISTEN_TABLE.init_once(ListenTable::new());
+    event::init(ETH0.name());
 
     info!("created net interface {:?}:", ETH0.name());
     info!("  ether:    {}", ETH0.ethernet_address());
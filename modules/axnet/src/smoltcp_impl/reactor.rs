@@ -0,0 +1,179 @@
+//! Readiness-driven support for asynchronous sockets.
+//!
+//! An asynchronous operation first tries to complete immediately. If it would
+//! block, the waker of the caller is registered in the smoltcp socket, which
+//! wakes it up when the socket state changes during the next poll of the
+//! network stack.
+//!
+//! A background reactor task polls the network stack, as long as there are
+//! sockets that have been used asynchronously. It sleeps until the next
+//! network event if the NIC is interrupt-driven, otherwise it polls
+//! periodically.
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use core::task::{Context, Poll, Waker};
+
+use axerrno::{AxError, AxResult};
+use axtask::WaitQueue;
+use smoltcp::iface::SocketHandle;
+use smoltcp::socket::{AnySocket, tcp, udp};
+
+use super::SOCKET_SET;
+use super::event::{current_events, irq_driven, wait_for_event};
+
+/// The interval between two polls of the network stack by the reactor.
+#[cfg(feature = "irq")]
+const POLL_INTERVAL: core::time::Duration = core::time::Duration::from_millis(1);
+const REACTOR_STACK_SIZE: usize = 0x10000;
+
+/// Number of live sockets that have been used asynchronously.
+static ASYNC_SOCKETS: AtomicUsize = AtomicUsize::new(0);
+static REACTOR_STARTED: AtomicBool = AtomicBool::new(false);
+static REACTOR_WQ: WaitQueue = WaitQueue::new();
+
+fn reactor_main() {
+    loop {
+        REACTOR_WQ.wait_until(|| ASYNC_SOCKETS.load(Ordering::Acquire) > 0);
+        let seen = current_events();
+        SOCKET_SET.poll_interfaces();
+        if irq_driven() {
+            wait_for_event(seen);
+            continue;
+        }
+        #[cfg(feature = "irq")]
+        axtask::sleep(POLL_INTERVAL);
+        #[cfg(not(feature = "irq"))]
+        axtask::yield_now();
+    }
+}
+
+/// Keeps the reactor running while an asynchronous socket is alive.
+///
+/// It's embedded in each socket, and activated when the socket is first
+/// polled asynchronously.
+pub(crate) struct ReactorHandle {
+    active: AtomicBool,
+}
+
+impl ReactorHandle {
+    pub const fn new() -> Self {
+        Self {
+            active: AtomicBool::new(false),
+        }
+    }
+
+    fn activate(&self) {
+        if self.active.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        ASYNC_SOCKETS.fetch_add(1, Ordering::AcqRel);
+        if !REACTOR_STARTED.swap(true, Ordering::AcqRel) {
+            axtask::spawn_raw(reactor_main, "net-reactor".into(), REACTOR_STACK_SIZE);
+        }
+        REACTOR_WQ.notify_one(false);
+    }
+}
+
+impl Drop for ReactorHandle {
+    fn drop(&mut self) {
+        if *self.active.get_mut() {
+            ASYNC_SOCKETS.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+}
+
+/// smoltcp sockets that can wake up wakers on readiness.
+pub(crate) trait AsyncSocket<'a>: AnySocket<'a> {
+    fn can_recv(&self) -> bool;
+    fn register_recv_waker(&mut self, waker: &Waker);
+    fn register_send_waker(&mut self, waker: &Waker);
+}
+
+impl<'a> AsyncSocket<'a> for tcp::Socket<'a> {
+    fn can_recv(&self) -> bool {
+        tcp::Socket::can_recv(self)
+    }
+
+    fn register_recv_waker(&mut self, waker: &Waker) {
+        tcp::Socket::register_recv_waker(self, waker)
+    }
+
+    fn register_send_waker(&mut self, waker: &Waker) {
+        tcp::Socket::register_send_waker(self, waker)
+    }
+}
+
+impl<'a> AsyncSocket<'a> for udp::Socket<'a> {
+    fn can_recv(&self) -> bool {
+        udp::Socket::can_recv(self)
+    }
+
+    fn register_recv_waker(&mut self, waker: &Waker) {
+        udp::Socket::register_recv_waker(self, waker)
+    }
+
+    fn register_send_waker(&mut self, waker: &Waker) {
+        udp::Socket::register_send_waker(self, waker)
+    }
+}
+
+/// The readiness that an asynchronous operation waits for.
+#[derive(Clone, Copy)]
+pub(crate) enum Interest {
+    Readable,
+    Writable,
+}
+
+/// Tries `op` on the socket `handle` after polling the network stack.
+///
+/// If it returns [`Err(WouldBlock)`](AxError::WouldBlock), the waker of `cx`
+/// is registered in the socket for the given `interest`, with the socket set
+/// locked, so that no readiness event can be missed in between.
+pub(crate) fn poll_socket<S, T, F>(
+    reactor: &ReactorHandle,
+    handle: SocketHandle,
+    cx: &mut Context<'_>,
+    interest: Interest,
+    op: F,
+) -> Poll<AxResult<T>>
+where
+    S: AsyncSocket<'static>,
+    F: FnOnce(&mut S) -> AxResult<T>,
+{
+    SOCKET_SET.poll_interfaces();
+    let res = SOCKET_SET.with_socket_mut::<S, _, _>(handle, |socket| {
+        let res = op(socket);
+        if matches!(res, Err(AxError::WouldBlock)) {
+            match interest {
+                Interest::Readable => {
+                    socket.register_recv_waker(cx.waker());
+                    // Some data was skipped (e.g. filtered out by the peer
+                    // address), and more is ready to be received.
+                    if socket.can_recv() {
+                        cx.waker().wake_by_ref();
+                    }
+                }
+                Interest::Writable => socket.register_send_waker(cx.waker()),
+            }
+        }
+        res
+    });
+    pending_on_would_block(reactor, res)
+}
+
+/// Converts [`Err(WouldBlock)`](AxError::WouldBlock) to [`Poll::Pending`], and
+/// makes sure the reactor is running in that case.
+///
+/// The waker must have been registered before calling this function.
+pub(crate) fn pending_on_would_block<T>(
+    reactor: &ReactorHandle,
+    res: AxResult<T>,
+) -> Poll<AxResult<T>> {
+    match res {
+        Err(AxError::WouldBlock) => {
+            reactor.activate();
+            Poll::Pending
+        }
+        res => Poll::Ready(res),
+    }
+}
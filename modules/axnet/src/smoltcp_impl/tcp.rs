@@ -2,6 +2,8 @@ use core::cell::UnsafeCell;
 
 use core::net::SocketAddr;
 use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+#[cfg(feature = "async")]
+use core::task::{Context, Poll, ready};
 
 use axerrno::{AxError, AxResult, ax_err, ax_err_type};
 use axio::PollState;
@@ -12,6 +14,9 @@ use smoltcp::socket::tcp::{self, ConnectError, State};
 use smoltcp::wire::{IpEndpoint, IpListenEndpoint};
 
 use super::addr::{UNSPECIFIED_ENDPOINT, from_core_sockaddr, into_core_sockaddr, is_unspecified};
+use super::event::{current_events, wait_for_event};
+#[cfg(feature = "async")]
+use super::reactor::{Interest, ReactorHandle, pending_on_would_block, poll_socket};
 use super::{ETH0, LISTEN_TABLE, SOCKET_SET, SocketSetWrapper};
 
 // State transitions:
@@ -42,6 +47,8 @@ pub struct TcpSocket {
     local_addr: UnsafeCell<IpEndpoint>,
     peer_addr: UnsafeCell<IpEndpoint>,
     nonblock: AtomicBool,
+    #[cfg(feature = "async")]
+    reactor: ReactorHandle,
 }
 
 unsafe impl Sync for TcpSocket {}
@@ -55,6 +62,8 @@ impl TcpSocket {
             local_addr: UnsafeCell::new(UNSPECIFIED_ENDPOINT),
             peer_addr: UnsafeCell::new(UNSPECIFIED_ENDPOINT),
             nonblock: AtomicBool::new(false),
+            #[cfg(feature = "async")]
+            reactor: ReactorHandle::new(),
         }
     }
 
@@ -70,6 +79,8 @@ impl TcpSocket {
             local_addr: UnsafeCell::new(local_addr),
             peer_addr: UnsafeCell::new(peer_addr),
             nonblock: AtomicBool::new(false),
+            #[cfg(feature = "async")]
+            reactor: ReactorHandle::new(),
         }
     }
 
@@ -121,6 +132,30 @@ impl TcpSocket {
     ///
     /// The local port is generated automatically.
     pub fn connect(&self, remote_addr: SocketAddr) -> AxResult {
+        self.start_connect(remote_addr)?;
+        // Here our state must be `CONNECTING`, and only one thread can run here.
+        if self.is_nonblocking() {
+            //error!("debug connect AxError::WouldBlock");
+            Err(AxError::WouldBlock)
+        } else {
+            self.block_on(|| {
+                let PollState { writable, .. } = self.poll_connect()?;
+                if !writable {
+                    Err(AxError::WouldBlock)
+                } else if self.get_state() == STATE_CONNECTED {
+                    Ok(())
+                } else {
+                    ax_err!(ConnectionRefused, "socket connect() failed")
+                }
+            })
+        }
+    }
+
+    /// Starts connecting to the given address and port, without waiting for
+    /// the connection to be established.
+    ///
+    /// The socket is in the `CONNECTING` state after it returns successfully.
+    pub fn start_connect(&self, remote_addr: SocketAddr) -> AxResult {
         self.update_state(STATE_CLOSED, STATE_CONNECTING, || {
             // SAFETY: no other threads can read or write these fields.
             let handle = unsafe { self.handle.get().read() }
@@ -145,7 +180,6 @@ impl TcpSocket {
                                 ax_err!(BadState, "socket connect() failed")
                             }
                             ConnectError::Unaddressable => {
-                                
                                 ax_err!(ConnectionRefused, "socket connect() failed")
                             }
                         })?;
@@ -165,24 +199,7 @@ impl TcpSocket {
 
             Ok(())
         })
-        .unwrap_or_else(|_| ax_err!(AlreadyExists, "socket connect() failed: already connected"))?; // EISCONN
-        //?
-        // Here our state must be `CONNECTING`, and only one thread can run here.
-        if self.is_nonblocking() {
-            //error!("debug connect AxError::WouldBlock");
-            Err(AxError::WouldBlock)
-        } else {
-            self.block_on(|| {
-                let PollState { writable, .. } = self.poll_connect()?;
-                if !writable {
-                    Err(AxError::WouldBlock)
-                } else if self.get_state() == STATE_CONNECTED {
-                    Ok(())
-                } else {
-                    ax_err!(ConnectionRefused, "socket connect() failed")
-                }
-            })
-        }
+        .unwrap_or_else(|_| ax_err!(AlreadyExists, "socket connect() failed: already connected")) // EISCONN
     }
 
     /// Binds an unbound socket to the given address and port.
@@ -294,25 +311,7 @@ impl TcpSocket {
         // SAFETY: `self.handle` should be initialized in a connected socket.
         let handle = unsafe { self.handle.get().read().unwrap() };
         self.block_on(|| {
-            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
-                if !socket.is_active() {
-                    // not open
-                    ax_err!(ConnectionRefused, "socket recv() failed")
-                } else if !socket.may_recv() {
-                    // connection closed
-                    Ok(0)
-                } else if socket.recv_queue() > 0 {
-                    // data available
-                    // TODO: use socket.recv(|buf| {...})
-                    let len = socket
-                        .recv_slice(buf)
-                        .map_err(|_| ax_err_type!(BadState, "socket recv() failed"))?;
-                    Ok(len)
-                } else {
-                    // no more data
-                    Err(AxError::WouldBlock)
-                }
-            })
+            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| recv_impl(socket, buf))
         })
     }
 
@@ -327,22 +326,7 @@ impl TcpSocket {
         // SAFETY: `self.handle` should be initialized in a connected socket.
         let handle = unsafe { self.handle.get().read().unwrap() };
         self.block_on(|| {
-            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
-                if !socket.is_active() || !socket.may_send() {
-                    // closed by remote
-                    ax_err!(ConnectionReset, "socket send() failed")
-                } else if socket.can_send() {
-                    // connected, and the tx buffer is not full
-                    // TODO: use socket.send(|buf| {...})
-                    let len = socket
-                        .send_slice(buf)
-                        .map_err(|_| ax_err_type!(BadState, "socket send() failed"))?;
-                    Ok(len)
-                } else {
-                    // tx buffer is full
-                    Err(AxError::WouldBlock)
-                }
-            })
+            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| send_impl(socket, buf))
         })
     }
 
@@ -436,33 +420,40 @@ impl TcpSocket {
     fn poll_connect(&self) -> AxResult<PollState> {
         // SAFETY: `self.handle` should be initialized above.
         let handle = unsafe { self.handle.get().read().unwrap() };
-        let writable =
-            SOCKET_SET.with_socket::<tcp::Socket, _, _>(handle, |socket| match socket.state() {
-                State::SynSent => false, // wait for connection
-                State::Established => {
-                    self.set_state(STATE_CONNECTED); // connected
-                    debug!(
-                        "TCP socket {}: connected to {}",
-                        handle,
-                        socket.remote_endpoint().unwrap(),
-                    );
-                    true
-                }
-                _ => {
-                    unsafe {
-                        self.local_addr.get().write(UNSPECIFIED_ENDPOINT);
-                        self.peer_addr.get().write(UNSPECIFIED_ENDPOINT);
-                    }
-                    self.set_state(STATE_CLOSED); // connection failed
-                    true
-                }
-            });
+        let writable = SOCKET_SET.with_socket::<tcp::Socket, _, _>(handle, |socket| {
+            self.update_connect_state(handle, socket)
+        });
         Ok(PollState {
             readable: false,
             writable,
         })
     }
 
+    /// Updates the state of a connecting socket by the state of the smoltcp
+    /// socket. Returns `false` if the connection is still in progress.
+    fn update_connect_state(&self, handle: SocketHandle, socket: &tcp::Socket) -> bool {
+        match socket.state() {
+            State::SynSent => false, // wait for connection
+            State::Established => {
+                self.set_state(STATE_CONNECTED); // connected
+                debug!(
+                    "TCP socket {}: connected to {}",
+                    handle,
+                    socket.remote_endpoint().unwrap(),
+                );
+                true
+            }
+            _ => {
+                unsafe {
+                    self.local_addr.get().write(UNSPECIFIED_ENDPOINT);
+                    self.peer_addr.get().write(UNSPECIFIED_ENDPOINT);
+                }
+                self.set_state(STATE_CLOSED); // connection failed
+                true
+            }
+        }
+    }
+
     fn poll_stream(&self) -> AxResult<PollState> {
         // SAFETY: `self.handle` should be initialized in a connected socket.
         let handle = unsafe { self.handle.get().read().unwrap() };
@@ -496,10 +487,11 @@ impl TcpSocket {
             f()
         } else {
             loop {
+                let seen = current_events();
                 SOCKET_SET.poll_interfaces();
                 match f() {
                     Ok(t) => return Ok(t),
-                    Err(AxError::WouldBlock) => axtask::yield_now(),
+                    Err(AxError::WouldBlock) => wait_for_event(seen),
                     Err(e) => return Err(e),
                 }
             }
@@ -507,6 +499,120 @@ impl TcpSocket {
     }
 }
 
+/// Asynchronous operations, driven by the readiness of the socket.
+///
+/// Each `poll_*` method tries to complete the operation immediately. If it
+/// would block, it returns [`Poll::Pending`] and arranges for the waker of `cx`
+/// to be woken up when the socket becomes ready. They ignore the nonblocking
+/// mode of the socket.
+#[cfg(feature = "async")]
+impl TcpSocket {
+    /// Polls for the completion of a connection started by
+    /// [`start_connect`](Self::start_connect).
+    ///
+    /// Returns `Ok` immediately if the socket is already connected.
+    pub fn poll_connected(&self, cx: &mut Context<'_>) -> Poll<AxResult> {
+        match self.get_state() {
+            STATE_CONNECTED => Poll::Ready(Ok(())),
+            STATE_CONNECTING => {
+                // SAFETY: `self.handle` should be initialized in a connecting socket.
+                let handle = unsafe { self.handle.get().read().unwrap() };
+                poll_socket::<tcp::Socket, _, _>(
+                    &self.reactor,
+                    handle,
+                    cx,
+                    Interest::Writable,
+                    |socket| {
+                        if !self.update_connect_state(handle, socket) {
+                            Err(AxError::WouldBlock)
+                        } else if self.is_connected() {
+                            Ok(())
+                        } else {
+                            ax_err!(ConnectionRefused, "socket connect() failed")
+                        }
+                    },
+                )
+            }
+            _ => Poll::Ready(ax_err!(NotConnected, "socket connect() failed")),
+        }
+    }
+
+    /// Polls for a new connection on a listening socket.
+    pub fn poll_accept(&self, cx: &mut Context<'_>) -> Poll<AxResult<TcpSocket>> {
+        if !self.is_listening() {
+            return Poll::Ready(ax_err!(InvalidInput, "socket accept() failed: not listen"));
+        }
+
+        // SAFETY: `self.local_addr` should be initialized after `bind()`.
+        let local_port = unsafe { self.local_addr.get().read().port };
+        SOCKET_SET.poll_interfaces();
+        let res = LISTEN_TABLE.poll_accept(local_port, cx.waker());
+        pending_on_would_block(&self.reactor, res).map_ok(|(handle, (local_addr, peer_addr))| {
+            debug!("TCP socket accepted a new connection {}", peer_addr);
+            TcpSocket::new_connected(handle, local_addr, peer_addr)
+        })
+    }
+
+    /// Polls for receiving data into the given buffer.
+    ///
+    /// Returns `Ok(0)` if the connection is closed by the peer.
+    pub fn poll_recv(&self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<AxResult<usize>> {
+        ready!(self.poll_connected(cx))?;
+        // SAFETY: `self.handle` should be initialized in a connected socket.
+        let handle = unsafe { self.handle.get().read().unwrap() };
+        poll_socket::<tcp::Socket, _, _>(&self.reactor, handle, cx, Interest::Readable, |socket| {
+            recv_impl(socket, buf)
+        })
+    }
+
+    /// Polls for transmitting data in the given buffer.
+    pub fn poll_send(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<AxResult<usize>> {
+        ready!(self.poll_connected(cx))?;
+        // SAFETY: `self.handle` should be initialized in a connected socket.
+        let handle = unsafe { self.handle.get().read().unwrap() };
+        poll_socket::<tcp::Socket, _, _>(&self.reactor, handle, cx, Interest::Writable, |socket| {
+            send_impl(socket, buf)
+        })
+    }
+}
+
+fn recv_impl(socket: &mut tcp::Socket, buf: &mut [u8]) -> AxResult<usize> {
+    if !socket.is_active() {
+        // not open
+        ax_err!(ConnectionRefused, "socket recv() failed")
+    } else if !socket.may_recv() {
+        // connection closed
+        Ok(0)
+    } else if socket.recv_queue() > 0 {
+        // data available
+        // TODO: use socket.recv(|buf| {...})
+        let len = socket
+            .recv_slice(buf)
+            .map_err(|_| ax_err_type!(BadState, "socket recv() failed"))?;
+        Ok(len)
+    } else {
+        // no more data
+        Err(AxError::WouldBlock)
+    }
+}
+
+fn send_impl(socket: &mut tcp::Socket, buf: &[u8]) -> AxResult<usize> {
+    if !socket.is_active() || !socket.may_send() {
+        // closed by remote
+        ax_err!(ConnectionReset, "socket send() failed")
+    } else if socket.can_send() {
+        // connected, and the tx buffer is not full
+        // TODO: use socket.send(|buf| {...})
+        let len = socket
+            .send_slice(buf)
+            .map_err(|_| ax_err_type!(BadState, "socket send() failed"))?;
+        Ok(len)
+    } else {
+        // tx buffer is full
+        Err(AxError::WouldBlock)
+    }
+}
+
 impl Drop for TcpSocket {
     fn drop(&mut self) {
         self.shutdown().ok();
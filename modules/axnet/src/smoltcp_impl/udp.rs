@@ -1,5 +1,7 @@
 use core::net::SocketAddr;
 use core::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "async")]
+use core::task::{Context, Poll};
 
 use axerrno::{AxError, AxResult, ax_err, ax_err_type};
 use axio::PollState;
@@ -11,6 +13,9 @@ use smoltcp::socket::udp::{self, BindError, SendError};
 use smoltcp::wire::{IpEndpoint, IpListenEndpoint};
 
 use super::addr::{UNSPECIFIED_ENDPOINT, from_core_sockaddr, into_core_sockaddr, is_unspecified};
+use super::event::{current_events, wait_for_event};
+#[cfg(feature = "async")]
+use super::reactor::{Interest, ReactorHandle, poll_socket};
 use super::{SOCKET_SET, SocketSetWrapper};
 
 /// A UDP socket that provides POSIX-like APIs.
@@ -19,6 +24,8 @@ pub struct UdpSocket {
     local_addr: RwLock<Option<IpEndpoint>>,
     peer_addr: RwLock<Option<IpEndpoint>>,
     nonblock: AtomicBool,
+    #[cfg(feature = "async")]
+    reactor: ReactorHandle,
 }
 
 impl UdpSocket {
@@ -32,6 +39,8 @@ impl UdpSocket {
             local_addr: RwLock::new(None),
             peer_addr: RwLock::new(None),
             nonblock: AtomicBool::new(false),
+            #[cfg(feature = "async")]
+            reactor: ReactorHandle::new(),
         }
     }
 
@@ -104,19 +113,13 @@ impl UdpSocket {
     /// Sends data on the socket to the given address. On success, returns the
     /// number of bytes written.
     pub fn send_to(&self, buf: &[u8], remote_addr: SocketAddr) -> AxResult<usize> {
-        if remote_addr.port() == 0 || remote_addr.ip().is_unspecified() {
-            return ax_err!(InvalidInput, "socket send_to() failed: invalid address");
-        }
-        self.send_impl(buf, from_core_sockaddr(remote_addr))
+        self.send_impl(buf, check_remote_addr(remote_addr)?)
     }
 
     /// Receives a single datagram message on the socket. On success, returns
     /// the number of bytes read and the origin.
     pub fn recv_from(&self, buf: &mut [u8]) -> AxResult<(usize, SocketAddr)> {
-        self.recv_impl(|socket| match socket.recv_slice(buf) {
-            Ok((len, meta)) => Ok((len, into_core_sockaddr(meta.endpoint))),
-            Err(_) => ax_err!(BadState, "socket recv_from() failed"),
-        })
+        self.recv_impl(|socket| recv_from_op(socket, buf))
     }
 
     /// Receives a single datagram message on the socket, without removing it from
@@ -157,18 +160,7 @@ impl UdpSocket {
     /// to which it is connected. On success, returns the number of bytes read.
     pub fn recv(&self, buf: &mut [u8]) -> AxResult<usize> {
         let remote_endpoint = self.remote_endpoint()?;
-        self.recv_impl(|socket| {
-            let (len, meta) = socket
-                .recv_slice(buf)
-                .map_err(|_| ax_err_type!(BadState, "socket recv() failed"))?;
-            if !is_unspecified(remote_endpoint.addr) && remote_endpoint.addr != meta.endpoint.addr {
-                return Err(AxError::WouldBlock);
-            }
-            if remote_endpoint.port != 0 && remote_endpoint.port != meta.endpoint.port {
-                return Err(AxError::WouldBlock);
-            }
-            Ok(len)
-        })
+        self.recv_impl(|socket| recv_connected_op(socket, buf, remote_endpoint))
     }
 
     /// Close the socket.
@@ -214,20 +206,7 @@ impl UdpSocket {
 
         self.block_on(|| {
             SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-                if socket.can_send() {
-                    socket
-                        .send_slice(buf, remote_endpoint)
-                        .map_err(|e| match e {
-                            SendError::BufferFull => AxError::WouldBlock,
-                            SendError::Unaddressable => {
-                                ax_err_type!(ConnectionRefused, "socket send() failed")
-                            }
-                        })?;
-                    Ok(buf.len())
-                } else {
-                    // tx buffer is full
-                    Err(AxError::WouldBlock)
-                }
+                send_op(socket, buf, remote_endpoint)
             })
         })
     }
@@ -242,13 +221,7 @@ impl UdpSocket {
 
         self.block_on(|| {
             SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-                if socket.can_recv() {
-                    // data available
-                    op(socket)
-                } else {
-                    // no more data
-                    Err(AxError::WouldBlock)
-                }
+                recv_op(socket, &mut op)
             })
         })
     }
@@ -261,10 +234,11 @@ impl UdpSocket {
             f()
         } else {
             loop {
+                let seen = current_events();
                 SOCKET_SET.poll_interfaces();
                 match f() {
                     Ok(t) => return Ok(t),
-                    Err(AxError::WouldBlock) => axtask::yield_now(),
+                    Err(AxError::WouldBlock) => wait_for_event(seen),
                     Err(e) => return Err(e),
                 }
             }
@@ -272,6 +246,142 @@ impl UdpSocket {
     }
 }
 
+/// Asynchronous operations, driven by the readiness of the socket.
+///
+/// See the asynchronous operations of [`TcpSocket`](super::TcpSocket) for
+/// details.
+#[cfg(feature = "async")]
+impl UdpSocket {
+    /// Polls for sending data on the socket to the given address.
+    pub fn poll_send_to(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        remote_addr: SocketAddr,
+    ) -> Poll<AxResult<usize>> {
+        let remote_endpoint = check_remote_addr(remote_addr)?;
+        self.poll_send_impl(cx, buf, remote_endpoint)
+    }
+
+    /// Polls for receiving a single datagram message on the socket.
+    pub fn poll_recv_from(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<AxResult<(usize, SocketAddr)>> {
+        self.poll_recv_impl(cx, |socket| recv_from_op(socket, buf))
+    }
+
+    /// Polls for sending data on the socket to the remote address to which it
+    /// is connected.
+    pub fn poll_send(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<AxResult<usize>> {
+        let remote_endpoint = self.remote_endpoint()?;
+        self.poll_send_impl(cx, buf, remote_endpoint)
+    }
+
+    /// Polls for receiving a single datagram message on the socket from the
+    /// remote address to which it is connected.
+    pub fn poll_recv(&self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<AxResult<usize>> {
+        let remote_endpoint = self.remote_endpoint()?;
+        self.poll_recv_impl(cx, |socket| recv_connected_op(socket, buf, remote_endpoint))
+    }
+
+    fn poll_send_impl(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        remote_endpoint: IpEndpoint,
+    ) -> Poll<AxResult<usize>> {
+        if self.local_addr.read().is_none() {
+            return Poll::Ready(ax_err!(NotConnected, "socket send() failed"));
+        }
+        poll_socket::<udp::Socket, _, _>(
+            &self.reactor,
+            self.handle,
+            cx,
+            Interest::Writable,
+            |socket| send_op(socket, buf, remote_endpoint),
+        )
+    }
+
+    fn poll_recv_impl<F, T>(&self, cx: &mut Context<'_>, mut op: F) -> Poll<AxResult<T>>
+    where
+        F: FnMut(&mut udp::Socket) -> AxResult<T>,
+    {
+        if self.local_addr.read().is_none() {
+            return Poll::Ready(ax_err!(NotConnected, "socket recv() failed"));
+        }
+        poll_socket::<udp::Socket, _, _>(
+            &self.reactor,
+            self.handle,
+            cx,
+            Interest::Readable,
+            |socket| recv_op(socket, &mut op),
+        )
+    }
+}
+
+fn check_remote_addr(remote_addr: SocketAddr) -> AxResult<IpEndpoint> {
+    if remote_addr.port() == 0 || remote_addr.ip().is_unspecified() {
+        return ax_err!(InvalidInput, "socket send_to() failed: invalid address");
+    }
+    Ok(from_core_sockaddr(remote_addr))
+}
+
+fn send_op(socket: &mut udp::Socket, buf: &[u8], remote_endpoint: IpEndpoint) -> AxResult<usize> {
+    if socket.can_send() {
+        socket
+            .send_slice(buf, remote_endpoint)
+            .map_err(|e| match e {
+                SendError::BufferFull => AxError::WouldBlock,
+                SendError::Unaddressable => {
+                    ax_err_type!(ConnectionRefused, "socket send() failed")
+                }
+            })?;
+        Ok(buf.len())
+    } else {
+        // tx buffer is full
+        Err(AxError::WouldBlock)
+    }
+}
+
+fn recv_op<F, T>(socket: &mut udp::Socket, op: &mut F) -> AxResult<T>
+where
+    F: FnMut(&mut udp::Socket) -> AxResult<T>,
+{
+    if socket.can_recv() {
+        // data available
+        op(socket)
+    } else {
+        // no more data
+        Err(AxError::WouldBlock)
+    }
+}
+
+fn recv_from_op(socket: &mut udp::Socket, buf: &mut [u8]) -> AxResult<(usize, SocketAddr)> {
+    match socket.recv_slice(buf) {
+        Ok((len, meta)) => Ok((len, into_core_sockaddr(meta.endpoint))),
+        Err(_) => ax_err!(BadState, "socket recv_from() failed"),
+    }
+}
+
+fn recv_connected_op(
+    socket: &mut udp::Socket,
+    buf: &mut [u8],
+    remote_endpoint: IpEndpoint,
+) -> AxResult<usize> {
+    let (len, meta) = socket
+        .recv_slice(buf)
+        .map_err(|_| ax_err_type!(BadState, "socket recv() failed"))?;
+    if !is_unspecified(remote_endpoint.addr) && remote_endpoint.addr != meta.endpoint.addr {
+        return Err(AxError::WouldBlock);
+    }
+    if remote_endpoint.port != 0 && remote_endpoint.port != meta.endpoint.port {
+        return Err(AxError::WouldBlock);
+    }
+    Ok(len)
+}
+
 impl Drop for UdpSocket {
     fn drop(&mut self) {
         self.shutdown().ok();
@@ -0,0 +1,221 @@
+//! [ArceOS](https://github.com/arceos-org/arceos) random number generation
+//! module.
+//!
+//! Random bytes are generated by a ChaCha20 stream, whose key (the entropy
+//! pool) is mixed with:
+//!
+//! - timer jitter, i.e., the varying time to run a small loop, sampled when the
+//!   pool is first used and whenever it's reseeded;
+//! - hardware random number generators (e.g., virtio-rng), if any;
+//! - other entropy added with [`add_entropy`].
+//!
+//! The pool is reseeded after every [`RESEED_INTERVAL`] bytes of output, and
+//! the key is replaced after every request, so that earlier outputs can not
+//! be recovered from the state.
+//!
+//! Without a hardware generator, the quality of the output depends on the
+//! resolution of the platform timer.
+//!
+//! # Cargo Features
+//!
+//! - `rng`: Use the registered hardware random number generators as entropy
+//!   sources, after [`init_hwrng`] is called.
+
+#![no_std]
+
+#[macro_use]
+extern crate log;
+#[cfg(feature = "rng")]
+extern crate alloc;
+
+use kspin::SpinNoIrq;
+use rand_chacha::ChaCha20Rng;
+use rand_chacha::rand_core::{RngCore, SeedableRng};
+
+/// The number of bytes generated between two reseeds.
+pub const RESEED_INTERVAL: usize = 1 << 20;
+
+/// The number of timer samples taken for each reseed.
+const JITTER_SAMPLES: usize = 256;
+
+const KEY_SIZE: usize = 32;
+
+struct Pool {
+    key: [u8; KEY_SIZE],
+    /// Whether the key has been seeded.
+    seeded: bool,
+    /// The number of bytes generated since the last reseed.
+    output: usize,
+}
+
+impl Pool {
+    const fn new() -> Self {
+        Self {
+            key: [0; KEY_SIZE],
+            seeded: false,
+            output: 0,
+        }
+    }
+
+    /// Mixes `data` into the key, by XOR-ing each chunk into the key and
+    /// replacing the key with the first output block of ChaCha20 keyed by the
+    /// result.
+    fn mix(&mut self, data: &[u8]) {
+        for chunk in data.chunks(KEY_SIZE) {
+            for (k, b) in self.key.iter_mut().zip(chunk) {
+                *k ^= b;
+            }
+            ChaCha20Rng::from_seed(self.key).fill_bytes(&mut self.key);
+        }
+    }
+
+    fn needs_reseed(&self) -> bool {
+        !self.seeded || self.output >= RESEED_INTERVAL
+    }
+
+    fn reseed(&mut self, seed: &Seed) {
+        self.mix(&seed.time);
+        self.mix(&seed.jitter);
+        #[cfg(feature = "rng")]
+        if let Some(hwrng) = &seed.hwrng {
+            self.mix(hwrng);
+        }
+        self.seeded = true;
+        self.output = 0;
+    }
+
+    fn fill_bytes(&mut self, buf: &mut [u8]) {
+        let mut rng = ChaCha20Rng::from_seed(self.key);
+        rng.fill_bytes(buf);
+        // Replace the key, so that the output can not be reproduced.
+        rng.fill_bytes(&mut self.key);
+        self.output = self.output.saturating_add(buf.len());
+    }
+}
+
+static POOL: SpinNoIrq<Pool> = SpinNoIrq::new(Pool::new());
+
+/// Entropy collected for a reseed.
+struct Seed {
+    time: [u8; KEY_SIZE],
+    jitter: [u8; KEY_SIZE],
+    #[cfg(feature = "rng")]
+    hwrng: Option<[u8; KEY_SIZE]>,
+}
+
+impl Seed {
+    /// Collects entropy from all sources. It takes a while and locks the
+    /// hardware generators, so it must be called without holding [`POOL`].
+    fn collect() -> Self {
+        let mut time = [0; KEY_SIZE];
+        time[..8].copy_from_slice(&axhal::time::wall_time_nanos().to_le_bytes());
+        Self {
+            time,
+            jitter: timer_jitter(),
+            #[cfg(feature = "rng")]
+            hwrng: {
+                let mut buf = [0; KEY_SIZE];
+                hwrng::fill(&mut buf).then_some(buf)
+            },
+        }
+    }
+}
+
+/// Samples the time taken by a small loop, whose length depends on the
+/// previous sample, and collects the low bits of the samples.
+fn timer_jitter() -> [u8; KEY_SIZE] {
+    let mut out = [0u8; KEY_SIZE];
+    let mut prev = axhal::time::monotonic_time_nanos();
+    for i in 0..JITTER_SAMPLES {
+        let mut x = prev;
+        for _ in 0..(prev & 0xf) + 1 {
+            x = core::hint::black_box(x.rotate_left(7) ^ 0x9e37_79b9_7f4a_7c15);
+        }
+        let now = axhal::time::monotonic_time_nanos();
+        let delta = now.wrapping_sub(prev);
+        out[i % KEY_SIZE] ^= (delta ^ (delta >> 8)) as u8;
+        out[(i + 7) % KEY_SIZE] = out[(i + 7) % KEY_SIZE].wrapping_add(x as u8);
+        prev = now;
+    }
+    out
+}
+
+#[cfg(feature = "rng")]
+mod hwrng {
+    use alloc::vec::Vec;
+
+    use axdriver::registry::{self, DeviceRef};
+    use axdriver::{AxRngDevice, prelude::*};
+    use lazyinit::LazyInit;
+
+    static HWRNGS: LazyInit<Vec<DeviceRef<AxRngDevice>>> = LazyInit::new();
+
+    pub(super) fn init() -> bool {
+        let devs = registry::devices::<AxRngDevice>();
+        for dev in &devs {
+            info!("  use RNG device {}", dev.name());
+        }
+        let found = !devs.is_empty();
+        HWRNGS.init_once(devs);
+        found
+    }
+
+    /// Fills `buf` from the hardware generators, and returns whether it's
+    /// filled completely by any of them.
+    pub(super) fn fill(buf: &mut [u8]) -> bool {
+        let Some(devs) = HWRNGS.get() else {
+            return false;
+        };
+        devs.iter().any(|dev| {
+            let mut driver = dev.lock();
+            let mut filled = 0;
+            while filled < buf.len() {
+                match driver.fill_bytes(&mut buf[filled..]) {
+                    Ok(0) | Err(_) => return false,
+                    Ok(n) => filled += n,
+                }
+            }
+            true
+        })
+    }
+}
+
+/// Uses the registered hardware random number generators as entropy sources,
+/// and reseeds the pool with them.
+#[cfg(feature = "rng")]
+pub fn init_hwrng() {
+    info!("Initialize random number generator...");
+    if hwrng::init() {
+        let seed = Seed::collect();
+        POOL.lock().reseed(&seed);
+    } else {
+        warn!("  no RNG device found, use timer jitter only");
+    }
+}
+
+/// Mixes the given data into the entropy pool.
+///
+/// The data does not need to be secret or random, it never reduces the
+/// entropy of the pool.
+pub fn add_entropy(data: &[u8]) {
+    POOL.lock().mix(data);
+}
+
+/// Fills `buf` with random bytes.
+pub fn getrandom(buf: &mut [u8]) {
+    // The lock guard must be dropped before collecting the seed.
+    let needs_reseed = POOL.lock().needs_reseed();
+    let seed = needs_reseed.then(Seed::collect);
+    let mut pool = POOL.lock();
+    if let Some(seed) = &seed {
+        pool.reseed(seed);
+    }
+    pool.fill_bytes(buf);
+}
+
+/// Returns a random `u64`.
+pub fn random_u64() -> u64 {
+    let mut buf = [0; 8];
+    getrandom(&mut buf);
+    u64::from_le_bytes(buf)
+}
@@ -0,0 +1,74 @@
+//! Mounting of the root and other file systems.
+
+use axdriver::{AxBlockDevice, registry};
+use axfs_ng::FsContext;
+use axfs_ng_vfs::{Filesystem, Mountpoint};
+use axsync::RawMutex;
+
+/// Mounts the file system on the first block device as the root.
+///
+/// With the `fs-9p` feature, the first 9p share becomes the root instead if
+/// there is no block device, and other shares are mounted at `/mnt/<tag>`.
+pub(crate) fn init_filesystems() {
+    #[cfg(feature = "fs-9p")]
+    let mut shares = registry::devices::<axdriver::AxNinePDevice>().into_iter();
+    let fs: Filesystem<RawMutex> = match registry::first::<AxBlockDevice>() {
+        Some(dev) => {
+            info!("Block device: {}", dev.name());
+            axfs_ng::fs::new_default(axfs_ng::DeviceDisk::new(dev))
+                .expect("Failed to initialize filesystem")
+        }
+        #[cfg(feature = "fs-9p")]
+        None => {
+            let dev = shares.next().expect("No block device or 9p share found!");
+            info!("9p share: {}", dev.name());
+            axfs_ng::fs::ninep::NinePFilesystem::new(dev).expect("Failed to initialize filesystem")
+        }
+        #[cfg(not(feature = "fs-9p"))]
+        None => panic!("No block device found!"),
+    };
+    let mount = Mountpoint::new_root(&fs);
+    let ctx = FsContext::new(mount.root_location());
+
+    #[cfg(feature = "fs-9p")]
+    mount_9p_shares(&ctx, shares);
+
+    axfs_ng::FS_CONTEXT.init_new(axsync::Mutex::new(ctx));
+}
+
+#[cfg(feature = "fs-9p")]
+fn mount_9p_shares(
+    ctx: &FsContext<RawMutex>,
+    shares: impl Iterator<Item = registry::DeviceRef<axdriver::AxNinePDevice>>,
+) {
+    use alloc::{format, string::String};
+    use axdriver::prelude::*;
+    use axfs_ng::fs::ninep::NinePFilesystem;
+    use axfs_ng_vfs::{NodePermission, VfsResult};
+
+    for dev in shares {
+        // Shares without a tag are named after the device instead.
+        let tag = String::from(dev.lock().mount_tag());
+        let path = if tag.is_empty() {
+            format!("/mnt/{}", dev.name())
+        } else {
+            format!("/mnt/{tag}")
+        };
+        let result: VfsResult<()> = (|| {
+            let mode = NodePermission::from_bits_truncate(0o755);
+            if ctx.resolve("/mnt").is_err() {
+                ctx.create_dir("/mnt", mode)?;
+            }
+            let target = match ctx.resolve(&path) {
+                Ok(target) => target,
+                Err(_) => ctx.create_dir(&path, mode)?,
+            };
+            target.mount(&NinePFilesystem::new(dev.clone())?)?;
+            Ok(())
+        })();
+        match result {
+            Ok(()) => info!("9p share {} mounted at {path}", dev.name()),
+            Err(err) => warn!("Failed to mount 9p share {} at {path}: {err:?}", dev.name()),
+        }
+    }
+}
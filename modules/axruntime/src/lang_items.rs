@@ -3,5 +3,10 @@ use core::panic::PanicInfo;
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
     ax_println!("{}", info);
+    #[cfg(feature = "backtrace")]
+    axhal::backtrace::print_panic_backtrace();
+    #[cfg(feature = "reboot-on-panic")]
+    axhal::misc::reset_on_panic();
+    #[cfg(not(feature = "reboot-on-panic"))]
     axhal::misc::terminate()
 }
@@ -6,13 +6,24 @@
 //! # Cargo Features
 //!
 //! - `alloc`: Enable global memory allocator.
+//! - `alloc-tracking`: Track live allocations of the global memory allocator.
 //! - `paging`: Enable page table manipulation support.
 //! - `irq`: Enable interrupt handling support.
 //! - `multitask`: Enable multi-threading support.
 //! - `smp`: Enable SMP (symmetric multiprocessing) support.
 //! - `fs`: Enable filesystem support.
+//! - `fs-9p`: Mount directories shared by the host over 9P, as the root if
+//!   there is no block device, or at `/mnt/<tag>` otherwise.
 //! - `net`: Enable networking support.
 //! - `display`: Enable graphics support.
+//! - `fbcon`: Show the console output on the framebuffer text console of the
+//!   first display.
+//! - `fbcon-log`, `fbcon-stdout`: Show only logs, or only the other console
+//!   output, on the framebuffer text console.
+//! - `input`: Enable input device support.
+//! - `console`: Use VirtIO console ports, the first of which becomes the
+//!   console.
+//! - `rng`: Use hardware random number generators as entropy sources.
 //!
 //! All the features are optional and disabled by default.
 
@@ -22,12 +33,18 @@
 #[macro_use]
 extern crate axlog;
 
+#[cfg(feature = "fs-9p")]
+extern crate alloc;
+
 #[cfg(all(target_os = "none", not(test)))]
 mod lang_items;
 
 #[cfg(feature = "smp")]
 mod mp;
 
+#[cfg(feature = "fs")]
+mod fs;
+
 #[cfg(feature = "smp")]
 pub use self::mp::rust_main_secondary;
 
@@ -51,7 +68,7 @@ struct LogIfImpl;
 #[crate_interface::impl_interface]
 impl axlog::LogIf for LogIfImpl {
     fn console_write_str(s: &str) {
-        axhal::console::write_bytes(s.as_bytes());
+        axtty::write_log(s.as_bytes());
     }
 
     fn current_time() -> core::time::Duration {
@@ -83,12 +100,27 @@ impl axlog::LogIf for LogIfImpl {
     }
 }
 
+#[cfg(feature = "alloc-tracking")]
+struct AllocTrackIfImpl;
+
+#[cfg(feature = "alloc-tracking")]
+#[crate_interface::impl_interface]
+impl axalloc::AllocTrackIf for AllocTrackIfImpl {
+    fn current_time() -> core::time::Duration {
+        axhal::time::monotonic_time()
+    }
+
+    fn current_task_id() -> Option<u64> {
+        <LogIfImpl as axlog::LogIf>::current_task_id()
+    }
+}
+
 use core::sync::atomic::{AtomicUsize, Ordering};
 
 static INITED_CPUS: AtomicUsize = AtomicUsize::new(0);
 
 fn is_init_ok() -> bool {
-    INITED_CPUS.load(Ordering::Acquire) == axconfig::SMP
+    INITED_CPUS.load(Ordering::Acquire) == axhal::cpu::cpu_num()
 }
 
 /// The main entry point of the ArceOS runtime.
@@ -129,6 +161,30 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
     axlog::set_max_level(option_env!("AX_LOG").unwrap_or("")); // no effect if set `log-level-*` features
     info!("Logging is enabled.");
     info!("Primary CPU {} started, dtb = {:#x}.", cpu_id, dtb);
+    if let Some(fdt) = axhal::fdt::info() {
+        info!(
+            "Found {} CPUs and {} VirtIO MMIO slots in the device tree.",
+            fdt.cpu_count(),
+            fdt.virtio_mmio_devices().len()
+        );
+        if fdt.dropped_memory_regions() > 0 {
+            warn!(
+                "Ignored {} RAM regions in the device tree beyond the limit of {}.",
+                fdt.dropped_memory_regions(),
+                axhal::fdt::MAX_MEMORY_REGIONS
+            );
+        }
+        if fdt.dropped_virtio_mmio_devices() > 0 {
+            warn!(
+                "Ignored {} VirtIO MMIO slots in the device tree beyond the limit of {}.",
+                fdt.dropped_virtio_mmio_devices(),
+                axhal::fdt::MAX_VIRTIO_MMIO_DEVICES
+            );
+        }
+        for dev in [fdt.uart(), fdt.gicd(), fdt.gicc(), fdt.plic(), fdt.rtc()] {
+            debug!("  device: {:x?}", dev);
+        }
+    }
 
     info!("Found physcial memory regions:");
     for r in axhal::mem::memory_regions() {
@@ -153,32 +209,37 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
     #[cfg(feature = "multitask")]
     axtask::init_scheduler();
 
-    #[cfg(any(feature = "fs", feature = "net", feature = "display"))]
+    #[cfg(any(
+        feature = "fs",
+        feature = "net",
+        feature = "display",
+        feature = "input",
+        feature = "console",
+        feature = "rng"
+    ))]
     {
-        #[allow(unused_variables)]
-        let mut all_devices = axdriver::init_drivers();
+        axdriver::init_drivers();
+
+        // Seed the entropy pool before other modules use it.
+        #[cfg(feature = "rng")]
+        axrand::init_hwrng();
 
         #[cfg(feature = "fs")]
-        {
-            use axdriver::prelude::*;
-
-            let dev = all_devices
-                .block
-                .take_one()
-                .expect("No block device found!");
-            info!("Block device: {}", dev.device_name());
-            let fs = axfs_ng::fs::new_default(dev).expect("Failed to initialize filesystem");
-            let mount = axfs_ng_vfs::Mountpoint::new_root(&fs);
-            axfs_ng::FS_CONTEXT.init_new(axsync::Mutex::new(axfs_ng::FsContext::new(
-                mount.root_location(),
-            )));
-        }
+        self::fs::init_filesystems();
 
         #[cfg(feature = "net")]
-        axnet::init_network(all_devices.net);
+        axnet::init_network();
 
         #[cfg(feature = "display")]
-        axdisplay::init_display(all_devices.display);
+        axdisplay::init_display();
+        #[cfg(any(feature = "fbcon-log", feature = "fbcon-stdout"))]
+        axtty::init_fbcon();
+
+        #[cfg(feature = "input")]
+        axinput::init_input();
+
+        #[cfg(feature = "console")]
+        axtty::init_hvc();
     }
 
     #[cfg(feature = "smp")]
@@ -190,6 +251,8 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
         init_interrupt();
     }
 
+    axtty::init();
+
     #[cfg(all(feature = "tls", not(feature = "multitask")))]
     {
         info!("Initialize thread local storage...");
@@ -218,60 +281,106 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
 
 #[cfg(feature = "alloc")]
 fn init_allocator() {
-    use axhal::mem::{MemRegionFlags, memory_regions, phys_to_virt};
+    use axhal::mem::phys_to_virt;
 
     info!("Initialize global memory allocator...");
     info!("  use {} allocator.", axalloc::global_allocator().name());
 
     let mut max_region_size = 0;
     let mut max_region_paddr = 0.into();
-    for r in memory_regions() {
-        if r.flags.contains(MemRegionFlags::FREE) && r.size > max_region_size {
-            max_region_size = r.size;
-            max_region_paddr = r.paddr;
+    for (paddr, size) in heap_regions() {
+        if size > max_region_size {
+            max_region_size = size;
+            max_region_paddr = paddr;
         }
     }
-    for r in memory_regions() {
-        if r.flags.contains(MemRegionFlags::FREE) && r.paddr == max_region_paddr {
-            axalloc::global_init(phys_to_virt(r.paddr).as_usize(), r.size);
+    for (paddr, size) in heap_regions() {
+        if paddr == max_region_paddr {
+            axalloc::global_init(phys_to_virt(paddr).as_usize(), size);
             break;
         }
     }
-    for r in memory_regions() {
-        if r.flags.contains(MemRegionFlags::FREE) && r.paddr != max_region_paddr {
-            axalloc::global_add_memory(phys_to_virt(r.paddr).as_usize(), r.size)
+    for (paddr, size) in heap_regions() {
+        if paddr != max_region_paddr {
+            axalloc::global_add_memory(phys_to_virt(paddr).as_usize(), size)
                 .expect("add heap memory region failed");
         }
     }
+
+    #[cfg(feature = "dma")]
+    match bounce_pool_paddr() {
+        Some(paddr) => {
+            axdma::init_bounce_pool(phys_to_virt(paddr).as_usize(), axdma::BOUNCE_POOL_SIZE)
+        }
+        None => warn!("no low memory for the DMA bounce pool"),
+    }
+}
+
+/// Returns the free memory regions for the global allocator, i.e., excluding
+/// the DMA bounce pool.
+#[cfg(feature = "alloc")]
+fn heap_regions() -> impl Iterator<Item = (axhal::mem::PhysAddr, usize)> {
+    use axhal::mem::{MemRegionFlags, memory_regions};
+
+    memory_regions()
+        .filter(|r| r.flags.contains(MemRegionFlags::FREE))
+        .map(|r| {
+            #[cfg(feature = "dma")]
+            if Some(r.paddr) == bounce_pool_paddr() {
+                let size = axdma::BOUNCE_POOL_SIZE;
+                return (r.paddr + size, r.size - size);
+            }
+            (r.paddr, r.size)
+        })
+}
+
+/// Returns the start of the free memory region that the DMA bounce pool is
+/// carved from, i.e., the lowest one where it's addressable by most devices.
+#[cfg(feature = "dma")]
+fn bounce_pool_paddr() -> Option<axhal::mem::PhysAddr> {
+    use axhal::mem::{MemRegionFlags, memory_regions};
+
+    memory_regions()
+        .filter(|r| r.flags.contains(MemRegionFlags::FREE) && r.size > axdma::BOUNCE_POOL_SIZE)
+        .map(|r| r.paddr)
+        .filter(|&paddr| axdma::bounce_pool_fits(paddr))
+        .min()
 }
 
 #[cfg(feature = "irq")]
 fn init_interrupt() {
     use axhal::time::TIMER_IRQ_NUM;
 
-    // Setup timer interrupt handler
-    const PERIODIC_INTERVAL_NANOS: u64 =
-        axhal::time::NANOS_PER_SEC / axconfig::TICKS_PER_SEC as u64;
+    // The task manager programs the one-shot timer by itself.
+    #[cfg(feature = "tickless")]
+    axhal::irq::register_named_handler(TIMER_IRQ_NUM, "timer", axtask::on_timer_tick);
 
-    #[percpu::def_percpu]
-    static NEXT_DEADLINE: u64 = 0;
-
-    fn update_timer() {
-        let now_ns = axhal::time::monotonic_time_nanos();
-        // Safety: we have disabled preemption in IRQ handler.
-        let mut deadline = unsafe { NEXT_DEADLINE.read_current_raw() };
-        if now_ns >= deadline {
-            deadline = now_ns + PERIODIC_INTERVAL_NANOS;
+    // Setup periodic timer interrupt handler
+    #[cfg(not(feature = "tickless"))]
+    {
+        const PERIODIC_INTERVAL_NANOS: u64 =
+            axhal::time::NANOS_PER_SEC / axconfig::TICKS_PER_SEC as u64;
+
+        #[percpu::def_percpu]
+        static NEXT_DEADLINE: u64 = 0;
+
+        fn update_timer() {
+            let now_ns = axhal::time::monotonic_time_nanos();
+            // Safety: we have disabled preemption in IRQ handler.
+            let mut deadline = unsafe { NEXT_DEADLINE.read_current_raw() };
+            if now_ns >= deadline {
+                deadline = now_ns + PERIODIC_INTERVAL_NANOS;
+            }
+            unsafe { NEXT_DEADLINE.write_current_raw(deadline + PERIODIC_INTERVAL_NANOS) };
+            axhal::time::set_oneshot_timer(deadline);
         }
-        unsafe { NEXT_DEADLINE.write_current_raw(deadline + PERIODIC_INTERVAL_NANOS) };
-        axhal::time::set_oneshot_timer(deadline);
-    }
 
-    axhal::irq::register_handler(TIMER_IRQ_NUM, || {
-        update_timer();
-        #[cfg(feature = "multitask")]
-        axtask::on_timer_tick();
-    });
+        axhal::irq::register_named_handler(TIMER_IRQ_NUM, "timer", || {
+            update_timer();
+            #[cfg(feature = "multitask")]
+            axtask::on_timer_tick();
+        });
+    }
 
     // Enable IRQs before starting app
     axhal::arch::enable_irqs();
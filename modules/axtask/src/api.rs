@@ -3,12 +3,15 @@
 use alloc::{
     string::String,
     sync::{Arc, Weak},
+    vec::Vec,
 };
 
 use kernel_guard::NoPreemptIrqSave;
 
 pub(crate) use crate::run_queue::{current_run_queue, select_run_queue};
 
+#[doc(cfg(feature = "multitask"))]
+pub use crate::stats::{TaskInfo, TaskStats};
 #[doc(cfg(feature = "multitask"))]
 pub use crate::task::{CurrentTask, TaskId, TaskInner};
 #[doc(cfg(feature = "multitask"))]
@@ -18,7 +21,7 @@ pub use crate::wait_queue::WaitQueue;
 
 #[cfg(feature = "irq")]
 #[doc(cfg(feature = "irq"))]
-pub use crate::timers::{set_alarm_callback, set_alarm_wakeup};
+pub use crate::timers::{AlarmTicket, cancel_alarm, set_alarm_callback, set_alarm_wakeup};
 
 /// The reference type of a task.
 pub type AxTaskRef = Arc<AxTask>;
@@ -87,6 +90,10 @@ pub fn init_scheduler() {
     crate::run_queue::init();
     #[cfg(feature = "irq")]
     crate::timers::init();
+    #[cfg(all(feature = "smp", feature = "irq"))]
+    axhal::mp::register_ipi_handler(axhal::mp::RESCHEDULE_VECTOR, || {
+        // Nothing to do, the idle task reschedules after being woken up.
+    });
 
     info!("  use {} scheduler.", Scheduler::scheduler_name());
 }
@@ -101,11 +108,19 @@ pub fn init_scheduler_secondary() {
 /// Handles periodic timer ticks for the task manager.
 ///
 /// For example, advance scheduler states, checks timed events, etc.
+///
+/// With the `tickless` feature, it should be called on every timer interrupt,
+/// and it programs the next one-shot timer by itself.
 #[cfg(feature = "irq")]
 #[doc(cfg(feature = "irq"))]
 pub fn on_timer_tick() {
     use kernel_guard::NoOp;
+    #[cfg(not(feature = "tickless"))]
     crate::timers::check_events();
+    #[cfg(feature = "tickless")]
+    if !crate::timers::on_timer_irq() {
+        return;
+    }
     // Since irq and preemption are both disabled here,
     // we can get current run queue with the default `kernel_guard::NoOp`.
     current_run_queue::<NoOp>().scheduler_timer_tick();
@@ -216,14 +231,68 @@ pub fn exit(exit_code: i32) -> ! {
     current_run_queue::<NoPreemptIrqSave>().exit_current(exit_code)
 }
 
+/// Calls `f` on every live task, in the order of task IDs.
+///
+/// Tasks that are spawned or dropped during the iteration may or may not be
+/// visited.
+pub fn for_each_task(mut f: impl FnMut(&AxTaskRef)) {
+    for task in crate::task::all_tasks() {
+        f(&task);
+    }
+}
+
+/// Returns the information and statistics of all live tasks, in the order of
+/// task IDs.
+pub fn list_tasks() -> Vec<TaskInfo> {
+    crate::task::all_tasks()
+        .iter()
+        .map(|task| task.info())
+        .collect()
+}
+
+/// Marks that the current task is returning to user mode, so the CPU time
+/// from now on is accounted as user time.
+///
+/// It should be called by the user-space runtime right before entering user
+/// mode, paired with [`account_exit_user`].
+pub fn account_enter_user() {
+    current().accounting().set_in_user(true);
+}
+
+/// Marks that the current task has trapped into kernel mode from user mode,
+/// so the CPU time from now on is accounted as kernel time.
+///
+/// It should be called by the user-space runtime at the beginning of the
+/// handling of traps (syscalls, interrupts, exceptions) from user mode.
+pub fn account_exit_user() {
+    current().accounting().set_in_user(false);
+}
+
 /// The idle task routine.
 ///
 /// It runs an infinite loop that keeps calling [`yield_now()`].
 pub fn run_idle() -> ! {
     loop {
         yield_now();
-        debug!("idle task: waiting for IRQs...");
+        // Give back the memory cached by this CPU while it is idle, since no
+        // timer ticks arrive to trim it in the tickless mode.
+        #[cfg(feature = "smp")]
+        axalloc::global_allocator().trim_cache();
         #[cfg(feature = "irq")]
-        axhal::arch::wait_for_irqs();
+        {
+            // Check the run queue again with IRQs disabled, then wait for IRQs
+            // with them enabled atomically. Otherwise, a task unblocked by an
+            // IRQ (or IPI) that arrives in between would wait for the next one.
+            axhal::arch::disable_irqs();
+            // Other CPUs send IPIs only to idle CPUs when they unblock tasks
+            // here, so check again for tasks unblocked before being marked.
+            #[cfg(feature = "smp")]
+            crate::run_queue::set_cpu_idle(true);
+            yield_now();
+            debug!("idle task: waiting for IRQs...");
+            axhal::arch::enable_irqs_and_wait();
+            #[cfg(feature = "smp")]
+            crate::run_queue::set_cpu_idle(false);
+        }
     }
 }
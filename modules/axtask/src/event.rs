@@ -0,0 +1,68 @@
+//! Events signaled by interrupt handlers, such as device interrupts, which
+//! wake up the tasks waiting for them.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+
+/// An event counter that tasks can wait on.
+///
+/// Interrupt handlers usually can not tell which condition became true, so
+/// they only [`notify`](Event::notify) the event. A waiter takes the
+/// [`count`](Event::count) before checking its condition, and then waits for
+/// the count to change, so that no event after the check is missed.
+pub struct Event {
+    count: AtomicUsize,
+    #[cfg(all(feature = "multitask", feature = "irq"))]
+    wq: crate::WaitQueue,
+}
+
+impl Event {
+    /// Creates a new event.
+    pub const fn new() -> Self {
+        Self {
+            count: AtomicUsize::new(0),
+            #[cfg(all(feature = "multitask", feature = "irq"))]
+            wq: crate::WaitQueue::new(),
+        }
+    }
+
+    /// Returns the number of times the event has been signaled, which is
+    /// passed to [`Event::wait_timeout`] later.
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::Acquire)
+    }
+
+    /// Signals the event, and wakes up all the waiters.
+    ///
+    /// If `resched` is true, the current task will be preempted when the
+    /// preemption is enabled, e.g., on returning from the interrupt handler.
+    pub fn notify(&self, resched: bool) {
+        self.count.fetch_add(1, Ordering::Release);
+        #[cfg(all(feature = "multitask", feature = "irq"))]
+        self.wq.notify_all(resched);
+        #[cfg(not(all(feature = "multitask", feature = "irq")))]
+        let _ = resched;
+    }
+
+    /// Blocks until the event is signaled after [`Event::count`] returned
+    /// `seen`, or `timeout` has elapsed.
+    ///
+    /// Without interrupts (i.e., the `multitask` and `irq` features), or if
+    /// IRQs are disabled (e.g., during boot), nothing can wake the task up, so
+    /// it only yields the CPU.
+    pub fn wait_timeout(&self, seen: usize, timeout: Duration) {
+        #[cfg(all(feature = "multitask", feature = "irq"))]
+        if axhal::arch::irqs_enabled() {
+            self.wq.wait_timeout_until(timeout, || self.count() != seen);
+            return;
+        }
+        let _ = (seen, timeout);
+        crate::yield_now();
+    }
+}
+
+impl Default for Event {
+    fn default() -> Self {
+        Self::new()
+    }
+}
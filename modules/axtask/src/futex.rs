@@ -0,0 +1,286 @@
+//! Futex-style wait/wake primitives keyed by memory addresses.
+//!
+//! A futex is a 32-bit word in memory. Tasks block on the futex only if the
+//! word still contains an expected value, and are woken up by other tasks
+//! after they change the word. Since the check and the enqueueing are atomic
+//! with respect to wakers, no wakeup can be lost.
+//!
+//! Futexes are identified by a [`FutexKey`], which is either the virtual
+//! address of the word in an address space, or its physical address if the
+//! word lives in memory shared by several address spaces.
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use core::time::Duration;
+
+use kspin::SpinNoIrq;
+
+use crate::WaitQueue;
+
+/// The bitset that matches all waiters, used by [`wait`] and [`wake`].
+pub const FUTEX_BITSET_MATCH_ANY: u32 = u32::MAX;
+
+/// The identifier of a futex.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FutexKey {
+    /// A futex private to an address space, keyed by its virtual address.
+    Private {
+        /// An identifier of the address space, e.g. the physical address of
+        /// its root page table, or 0 if there is only one address space.
+        aspace: usize,
+        /// The virtual address of the futex word.
+        vaddr: usize,
+    },
+    /// A futex in memory shared by several address spaces, keyed by its
+    /// physical address.
+    Shared {
+        /// The physical address of the futex word.
+        paddr: usize,
+    },
+}
+
+impl FutexKey {
+    /// Creates a key for a futex at `vaddr` in the kernel address space.
+    pub const fn private(vaddr: usize) -> Self {
+        Self::Private { aspace: 0, vaddr }
+    }
+
+    /// Creates a key for a futex at `vaddr` in the address space `aspace`.
+    pub const fn private_in(aspace: usize, vaddr: usize) -> Self {
+        Self::Private { aspace, vaddr }
+    }
+
+    /// Creates a key for a futex in shared memory at physical address `paddr`.
+    pub const fn shared(paddr: usize) -> Self {
+        Self::Shared { paddr }
+    }
+}
+
+/// Errors returned by futex operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FutexError {
+    /// The futex word does not contain the expected value (`EAGAIN`).
+    WouldBlock,
+    /// The timeout expired before the task was woken up (`ETIMEDOUT`).
+    TimedOut,
+    /// An invalid argument, e.g. a zero bitset (`EINVAL`).
+    InvalidInput,
+}
+
+/// The result type of futex operations.
+pub type FutexResult<T = ()> = Result<T, FutexError>;
+
+struct FutexWaiter {
+    /// The futex the waiter is currently queued on, changed by requeueing.
+    key: SpinNoIrq<FutexKey>,
+    bitset: u32,
+    /// Set (with [`FUTEX_QUEUES`] locked) when removed from the queue by a waker.
+    woken: AtomicBool,
+    wq: WaitQueue,
+}
+
+/// Waiters of all futexes, in FIFO order for each key.
+///
+/// Every operation holds the lock while inspecting the futex word and the
+/// queues, which makes the value check and the enqueueing atomic.
+static FUTEX_QUEUES: SpinNoIrq<BTreeMap<FutexKey, VecDeque<Arc<FutexWaiter>>>> =
+    SpinNoIrq::new(BTreeMap::new());
+
+/// Removes at most `count` waiters matching `bitset` from the queue of `key`,
+/// and marks them as woken.
+fn dequeue_waiters(
+    queues: &mut BTreeMap<FutexKey, VecDeque<Arc<FutexWaiter>>>,
+    key: FutexKey,
+    count: usize,
+    bitset: u32,
+) -> Vec<Arc<FutexWaiter>> {
+    let mut woken = Vec::new();
+    let Some(queue) = queues.get_mut(&key) else {
+        return woken;
+    };
+    queue.retain(|w| {
+        if woken.len() < count && w.bitset & bitset != 0 {
+            w.woken.store(true, Ordering::Release);
+            woken.push(w.clone());
+            false
+        } else {
+            true
+        }
+    });
+    if queue.is_empty() {
+        queues.remove(&key);
+    }
+    woken
+}
+
+fn notify_waiters(waiters: Vec<Arc<FutexWaiter>>) -> usize {
+    let count = waiters.len();
+    for waiter in waiters {
+        waiter.wq.notify_one(true);
+    }
+    count
+}
+
+/// Blocks the current task on the futex `key` if `futex` contains `expected`,
+/// until it is woken up by [`wake`] or the `timeout` (if any) has elapsed.
+///
+/// `futex` must be the word identified by `key`.
+pub fn wait(
+    key: FutexKey,
+    futex: &AtomicU32,
+    expected: u32,
+    timeout: Option<Duration>,
+) -> FutexResult {
+    wait_bitset(key, futex, expected, timeout, FUTEX_BITSET_MATCH_ANY)
+}
+
+/// Like [`wait`], but the task can only be woken up by [`wake_bitset`] calls
+/// whose bitset intersects `bitset`.
+pub fn wait_bitset(
+    key: FutexKey,
+    futex: &AtomicU32,
+    expected: u32,
+    timeout: Option<Duration>,
+    bitset: u32,
+) -> FutexResult {
+    if bitset == 0 {
+        return Err(FutexError::InvalidInput);
+    }
+    let waiter = Arc::new(FutexWaiter {
+        key: SpinNoIrq::new(key),
+        bitset,
+        woken: AtomicBool::new(false),
+        wq: WaitQueue::new(),
+    });
+    {
+        let mut queues = FUTEX_QUEUES.lock();
+        if futex.load(Ordering::SeqCst) != expected {
+            return Err(FutexError::WouldBlock);
+        }
+        queues.entry(key).or_default().push_back(waiter.clone());
+    }
+
+    let woken = || waiter.woken.load(Ordering::Acquire);
+    match timeout {
+        None => waiter.wq.wait_until(woken),
+        #[cfg(feature = "irq")]
+        Some(dur) => {
+            waiter.wq.wait_timeout_until(dur, woken);
+        }
+        #[cfg(not(feature = "irq"))]
+        Some(dur) => {
+            // No timer interrupts, fall back to polling.
+            let deadline = axhal::time::wall_time() + dur;
+            while !woken() && axhal::time::wall_time() < deadline {
+                crate::yield_now();
+            }
+        }
+    }
+    if woken() {
+        return Ok(());
+    }
+
+    // Timed out, dequeue ourselves unless a waker has just done it.
+    let mut queues = FUTEX_QUEUES.lock();
+    if woken() {
+        return Ok(());
+    }
+    let key = *waiter.key.lock();
+    if let Some(queue) = queues.get_mut(&key) {
+        queue.retain(|w| !Arc::ptr_eq(w, &waiter));
+        if queue.is_empty() {
+            queues.remove(&key);
+        }
+    }
+    Err(FutexError::TimedOut)
+}
+
+/// Wakes up at most `count` tasks waiting on the futex `key`.
+///
+/// Returns the number of tasks woken up.
+pub fn wake(key: FutexKey, count: usize) -> usize {
+    let waiters = dequeue_waiters(&mut FUTEX_QUEUES.lock(), key, count, FUTEX_BITSET_MATCH_ANY);
+    notify_waiters(waiters)
+}
+
+/// Wakes up at most `count` tasks waiting on the futex `key`, whose bitsets
+/// intersect `bitset`.
+///
+/// Returns the number of tasks woken up.
+pub fn wake_bitset(key: FutexKey, count: usize, bitset: u32) -> FutexResult<usize> {
+    if bitset == 0 {
+        return Err(FutexError::InvalidInput);
+    }
+    let waiters = dequeue_waiters(&mut FUTEX_QUEUES.lock(), key, count, bitset);
+    Ok(notify_waiters(waiters))
+}
+
+fn requeue_common(
+    key: FutexKey,
+    check: Option<(&AtomicU32, u32)>,
+    nr_wake: usize,
+    key2: FutexKey,
+    nr_requeue: usize,
+) -> FutexResult<(usize, usize)> {
+    let (waiters, requeued) = {
+        let mut queues = FUTEX_QUEUES.lock();
+        if let Some((futex, expected)) = check {
+            if futex.load(Ordering::SeqCst) != expected {
+                return Err(FutexError::WouldBlock);
+            }
+        }
+        let waiters = dequeue_waiters(&mut queues, key, nr_wake, FUTEX_BITSET_MATCH_ANY);
+        let moved: Vec<_> = match queues.get_mut(&key) {
+            Some(queue) if key != key2 => {
+                let moved = queue.drain(..nr_requeue.min(queue.len())).collect();
+                if queue.is_empty() {
+                    queues.remove(&key);
+                }
+                moved
+            }
+            _ => Vec::new(),
+        };
+        let requeued = moved.len();
+        if requeued > 0 {
+            for waiter in moved.iter() {
+                *waiter.key.lock() = key2;
+            }
+            queues.entry(key2).or_default().extend(moved);
+        }
+        (waiters, requeued)
+    };
+    Ok((notify_waiters(waiters), requeued))
+}
+
+/// Wakes up at most `nr_wake` tasks waiting on the futex `key`, and moves at
+/// most `nr_requeue` of the remaining waiters to the futex `key2`.
+///
+/// Returns the number of tasks woken up.
+pub fn requeue(key: FutexKey, nr_wake: usize, key2: FutexKey, nr_requeue: usize) -> usize {
+    requeue_common(key, None, nr_wake, key2, nr_requeue)
+        .map(|(woken, _)| woken)
+        .unwrap_or(0)
+}
+
+/// Like [`requeue`], but only if `futex` (the word identified by `key`) still
+/// contains `expected`.
+///
+/// Returns the total number of tasks woken up or requeued.
+pub fn cmp_requeue(
+    key: FutexKey,
+    futex: &AtomicU32,
+    expected: u32,
+    nr_wake: usize,
+    key2: FutexKey,
+    nr_requeue: usize,
+) -> FutexResult<usize> {
+    requeue_common(key, Some((futex, expected)), nr_wake, key2, nr_requeue)
+        .map(|(woken, requeued)| woken + requeued)
+}
+
+/// Returns the number of tasks waiting on the futex `key`.
+pub fn waiter_count(key: FutexKey) -> usize {
+    FUTEX_QUEUES.lock().get(&key).map_or(0, |q| q.len())
+}
@@ -0,0 +1,272 @@
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::task::Wake;
+use core::cell::{Cell, UnsafeCell};
+use core::future::Future;
+use core::marker::PhantomData;
+use core::pin::{Pin, pin};
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Context, Poll, Waker};
+
+use kspin::SpinNoIrq;
+
+use super::Parker;
+
+type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// States shared by the executor, its spawners and the wakers of its tasks.
+struct Shared {
+    /// Spawned futures that have been woken up and are waiting to be polled.
+    ready: SpinNoIrq<VecDeque<Arc<Task>>>,
+    /// Whether the future passed to [`Executor::block_on`] has been woken up.
+    main_woken: AtomicBool,
+    parker: Parker,
+}
+
+impl Wake for Shared {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.main_woken.store(true, Ordering::Release);
+        self.parker.unpark();
+    }
+}
+
+/// A spawned future.
+struct Task {
+    /// Only accessed by the executor that runs the task. The [`Task::queued`]
+    /// flag guarantees that there is at most one reference to the task in the
+    /// ready queue, so it can not be polled reentrantly.
+    future: UnsafeCell<Option<BoxFuture>>,
+    /// Whether the task is in the ready queue.
+    queued: AtomicBool,
+    shared: Arc<Shared>,
+}
+
+unsafe impl Sync for Task {}
+
+impl Task {
+    /// Polls the future once, and drops it if it's completed.
+    ///
+    /// # Safety
+    ///
+    /// Must only be called by the executor owning the task.
+    unsafe fn run(self: Arc<Self>) {
+        let slot = unsafe { &mut *self.future.get() };
+        if let Some(future) = slot.as_mut() {
+            let waker = Waker::from(self.clone());
+            let mut cx = Context::from_waker(&waker);
+            if future.as_mut().poll(&mut cx).is_ready() {
+                *slot = None;
+            }
+        }
+    }
+}
+
+impl Wake for Task {
+    fn wake(self: Arc<Self>) {
+        if !self.queued.swap(true, Ordering::AcqRel) {
+            let shared = self.shared.clone();
+            shared.ready.lock().push_back(self);
+            shared.parker.unpark();
+        }
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.clone().wake();
+    }
+}
+
+/// An executor that runs many futures concurrently on a single task.
+///
+/// Futures are spawned with a [`Spawner`] obtained from [`Executor::spawner`],
+/// and are run while [`Executor::block_on`] is running. When there is no
+/// future ready to make progress, the task running the executor is blocked.
+///
+/// The executor can be moved to another task, but it can not be shared
+/// between tasks.
+pub struct Executor {
+    shared: Arc<Shared>,
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+impl Executor {
+    /// Creates a new executor.
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                ready: SpinNoIrq::new(VecDeque::new()),
+                main_woken: AtomicBool::new(false),
+                parker: Parker::new(),
+            }),
+            _not_sync: PhantomData,
+        }
+    }
+
+    /// Returns a handle to spawn futures on this executor.
+    pub fn spawner(&self) -> Spawner {
+        Spawner {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Spawns a future on this executor.
+    ///
+    /// It's a shortcut of `self.spawner().spawn(future)`.
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        spawn_on(&self.shared, future)
+    }
+
+    /// Runs the executor until the given future completes, and returns its
+    /// output.
+    ///
+    /// Spawned futures are polled in the meantime. Those that are still
+    /// pending when it returns are kept, and continue to run in the next call.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        let shared = &self.shared;
+        let mut future = pin!(future);
+        let main_waker = Waker::from(shared.clone());
+        let mut main_cx = Context::from_waker(&main_waker);
+        shared.main_woken.store(true, Ordering::Release);
+
+        loop {
+            if shared.main_woken.swap(false, Ordering::AcqRel) {
+                if let Poll::Ready(output) = future.as_mut().poll(&mut main_cx) {
+                    return output;
+                }
+            }
+
+            // Only run tasks that are ready at this point, so that the main
+            // future is not starved by tasks waking up themselves.
+            let mut budget = shared.ready.lock().len();
+            while budget > 0 {
+                let Some(task) = shared.ready.lock().pop_front() else {
+                    break;
+                };
+                task.queued.store(false, Ordering::Release);
+                // SAFETY: this is the only executor running the task.
+                unsafe { task.run() };
+                budget -= 1;
+            }
+
+            if !shared.main_woken.load(Ordering::Acquire) && shared.ready.lock().is_empty() {
+                shared.parker.park();
+            }
+        }
+    }
+}
+
+impl Default for Executor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Executor {
+    fn drop(&mut self) {
+        // Break the reference cycles between the queued tasks and `Shared`.
+        let tasks = core::mem::take(&mut *self.shared.ready.lock());
+        drop(tasks);
+    }
+}
+
+/// A handle to spawn futures on an [`Executor`].
+///
+/// It can be cloned and sent to other tasks.
+#[derive(Clone)]
+pub struct Spawner {
+    shared: Arc<Shared>,
+}
+
+impl Spawner {
+    /// Spawns a future on the executor, and returns a [`JoinHandle`] to await
+    /// its output.
+    ///
+    /// The future starts running the next time the executor runs. Dropping the
+    /// [`JoinHandle`] detaches the future, it still runs to completion.
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        spawn_on(&self.shared, future)
+    }
+}
+
+fn spawn_on<F>(shared: &Arc<Shared>, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let join = Arc::new(JoinState {
+        output: SpinNoIrq::new(None),
+        waker: SpinNoIrq::new(None),
+        done: AtomicBool::new(false),
+    });
+    let state = join.clone();
+    let task = Arc::new(Task {
+        future: UnsafeCell::new(Some(Box::pin(async move {
+            let output = future.await;
+            state.complete(output);
+        }))),
+        queued: AtomicBool::new(false),
+        shared: shared.clone(),
+    });
+    task.wake();
+    JoinHandle { state: join }
+}
+
+struct JoinState<T> {
+    output: SpinNoIrq<Option<T>>,
+    waker: SpinNoIrq<Option<Waker>>,
+    done: AtomicBool,
+}
+
+impl<T> JoinState<T> {
+    fn complete(&self, output: T) {
+        *self.output.lock() = Some(output);
+        self.done.store(true, Ordering::Release);
+        let waker = self.waker.lock().take();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// A handle to await the output of a spawned future.
+pub struct JoinHandle<T> {
+    state: Arc<JoinState<T>>,
+}
+
+impl<T> JoinHandle<T> {
+    /// Whether the spawned future has completed.
+    pub fn is_finished(&self) -> bool {
+        self.state.done.load(Ordering::Acquire)
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let state = &self.state;
+        if !state.done.load(Ordering::Acquire) {
+            *state.waker.lock() = Some(cx.waker().clone());
+            // Check again in case it completed before the waker was stored.
+            if !state.done.load(Ordering::Acquire) {
+                return Poll::Pending;
+            }
+        }
+        match state.output.lock().take() {
+            Some(output) => Poll::Ready(output),
+            None => panic!("`JoinHandle` polled after completion"),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! A lightweight `async` runtime on top of the task scheduler.
+//!
+//! Futures are driven by an [`Executor`] running in an ordinary task. When no
+//! future can make progress, the task blocks on a [`WaitQueue`] until some
+//! [`Waker`] is called, e.g. by a timer (see [`sleep`]) or by a device driver
+//! reporting I/O readiness. Thus a single task can multiplex a large number of
+//! concurrent operations.
+//!
+//! # Examples
+//!
+//! ```
+//! use axtask::future::{Executor, block_on};
+//!
+//! axtask::init_scheduler();
+//! let executor = Executor::new();
+//! let spawner = executor.spawner();
+//! let sum = executor.block_on(async {
+//!     let handles: Vec<_> = (0..10).map(|i| spawner.spawn(async move { i * 2 })).collect();
+//!     let mut sum = 0;
+//!     for h in handles {
+//!         sum += h.await;
+//!     }
+//!     sum
+//! });
+//! assert_eq!(sum, 90);
+//! assert_eq!(block_on(async { 42 }), 42);
+//! ```
+
+mod executor;
+mod time;
+
+use alloc::sync::Arc;
+use alloc::task::Wake;
+use core::future::Future;
+use core::pin::pin;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Context, Poll, Waker};
+
+use crate::WaitQueue;
+
+pub use self::executor::{Executor, JoinHandle, Spawner};
+pub use self::time::{Sleep, TimedOut, Timeout, sleep, sleep_until, timeout, timeout_at};
+
+/// Blocks the task that owns it until it is unparked.
+///
+/// An unpark before the park is remembered, so no wakeup can be lost.
+pub(crate) struct Parker {
+    notified: AtomicBool,
+    wq: WaitQueue,
+}
+
+impl Parker {
+    pub(crate) const fn new() -> Self {
+        Self {
+            notified: AtomicBool::new(false),
+            wq: WaitQueue::new(),
+        }
+    }
+
+    /// Blocks the current task until [`unpark`](Self::unpark) is called.
+    pub(crate) fn park(&self) {
+        while !self.notified.swap(false, Ordering::Acquire) {
+            self.wq.wait_until(|| self.notified.load(Ordering::Acquire));
+        }
+    }
+
+    /// Wakes up the parked task. It can be called in the interrupt context.
+    pub(crate) fn unpark(&self) {
+        self.notified.store(true, Ordering::Release);
+        self.wq.notify_one(false);
+    }
+}
+
+impl Wake for Parker {
+    fn wake(self: Arc<Self>) {
+        self.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.unpark();
+    }
+}
+
+/// Runs a future to completion on the current task.
+///
+/// The current task blocks whenever the future is pending, until its waker is
+/// called. To run several futures concurrently, use an [`Executor`].
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let parker = Arc::new(Parker::new());
+    let waker = Waker::from(parker.clone());
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        parker.park();
+    }
+}
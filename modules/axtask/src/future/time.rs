@@ -0,0 +1,183 @@
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+
+use axhal::time::{TimeValue, wall_time};
+
+/// Returns a future that completes after `dur` has elapsed.
+pub fn sleep(dur: Duration) -> Sleep {
+    sleep_until(wall_time() + dur)
+}
+
+/// Returns a future that completes at `deadline`.
+pub fn sleep_until(deadline: TimeValue) -> Sleep {
+    Sleep {
+        deadline,
+        #[cfg(feature = "irq")]
+        alarm: None,
+    }
+}
+
+/// Requires `future` to complete within `dur`.
+///
+/// If the future does not complete in time, the returned future resolves to
+/// [`Err(TimedOut)`](TimedOut), and `future` is dropped.
+pub fn timeout<F: Future>(dur: Duration, future: F) -> Timeout<F> {
+    timeout_at(wall_time() + dur, future)
+}
+
+/// Requires `future` to complete before `deadline`.
+///
+/// See [`timeout`] for details.
+pub fn timeout_at<F: Future>(deadline: TimeValue, future: F) -> Timeout<F> {
+    Timeout::new(future, deadline)
+}
+
+#[cfg(feature = "irq")]
+mod alarm {
+    use alloc::sync::Arc;
+    use core::task::Waker;
+
+    use kspin::SpinNoIrq;
+
+    use crate::timers::{AlarmTicket, cancel_alarm, set_alarm_callback};
+
+    /// A timer event of a [`Sleep`](super::Sleep), which wakes up the waker
+    /// shared with the timer callback.
+    pub struct Alarm {
+        waker: Arc<SpinNoIrq<Option<Waker>>>,
+        ticket: AlarmTicket,
+    }
+
+    impl Alarm {
+        /// Sets a timer that wakes up the latest registered waker at
+        /// `deadline`.
+        pub fn set(deadline: axhal::time::TimeValue) -> Self {
+            let waker = Arc::new(SpinNoIrq::new(None::<Waker>));
+            let slot = waker.clone();
+            let ticket = set_alarm_callback(deadline, move || {
+                let waker = slot.lock().take();
+                if let Some(waker) = waker {
+                    waker.wake();
+                }
+            });
+            Self { waker, ticket }
+        }
+
+        pub fn register(&self, waker: &Waker) {
+            let mut slot = self.waker.lock();
+            if !slot.as_ref().is_some_and(|w| w.will_wake(waker)) {
+                *slot = Some(waker.clone());
+            }
+        }
+    }
+
+    impl Drop for Alarm {
+        fn drop(&mut self) {
+            // If the timer event cannot be removed, it finds no waker to wake.
+            self.waker.lock().take();
+            cancel_alarm(self.ticket);
+        }
+    }
+}
+
+/// A future that completes at a deadline, returned by [`sleep`] and
+/// [`sleep_until`].
+///
+/// If the `irq` feature is enabled, it's woken up by a timer event, which is
+/// cancelled when it's dropped. Otherwise, it polls the time repeatedly,
+/// yielding the CPU between polls.
+pub struct Sleep {
+    deadline: TimeValue,
+    #[cfg(feature = "irq")]
+    alarm: Option<alarm::Alarm>,
+}
+
+impl Sleep {
+    /// Returns the instant at which the future completes.
+    pub fn deadline(&self) -> TimeValue {
+        self.deadline
+    }
+
+    /// Whether the deadline has passed.
+    pub fn is_elapsed(&self) -> bool {
+        wall_time() >= self.deadline
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.is_elapsed() {
+            return Poll::Ready(());
+        }
+        #[cfg(feature = "irq")]
+        {
+            let deadline = self.deadline;
+            let this = self.get_mut();
+            this.alarm
+                .get_or_insert_with(|| alarm::Alarm::set(deadline))
+                .register(cx.waker());
+            // The timer may have fired before the waker was registered.
+            if this.is_elapsed() {
+                return Poll::Ready(());
+            }
+        }
+        #[cfg(not(feature = "irq"))]
+        {
+            // No timer interrupts, fall back to polling.
+            crate::yield_now();
+            cx.waker().wake_by_ref();
+        }
+        Poll::Pending
+    }
+}
+
+/// The error returned by [`Timeout`] when the deadline has passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimedOut;
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("deadline has elapsed")
+    }
+}
+
+/// A future with a deadline, returned by [`timeout`] and [`timeout_at`].
+pub struct Timeout<F> {
+    future: F,
+    sleep: Sleep,
+}
+
+impl<F> Timeout<F> {
+    /// Wraps `future` to complete before `deadline`.
+    pub fn new(future: F, deadline: TimeValue) -> Self {
+        Self {
+            future,
+            sleep: sleep_until(deadline),
+        }
+    }
+
+    /// Consumes the timeout, returning the inner future.
+    pub fn into_inner(self) -> F {
+        self.future
+    }
+}
+
+impl<F: Future> Future for Timeout<F> {
+    type Output = Result<F::Output, TimedOut>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: `future` is never moved out of a pinned `Timeout`, and
+        // `sleep` is `Unpin`.
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        if let Poll::Ready(output) = future.poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+        Pin::new(&mut this.sleep).poll(cx).map(|_| Err(TimedOut))
+    }
+}
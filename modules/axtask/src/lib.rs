@@ -13,6 +13,12 @@
 //!   APIs can be used, such as [`sleep`], [`sleep_until`], and
 //!   [`WaitQueue::wait_timeout`].
 //! - `preempt`: Enable preemptive scheduling.
+//! - `tickless`: Do not tick periodically. The one-shot timer is programmed to
+//!   the next timer event or the end of the time slice, and idle CPUs are not
+//!   woken up by ticks. It also enables the `multitask` and `irq` features.
+//! - `paging`: Map task stacks in a dedicated virtual region with guard pages
+//!   to detect stack overflows. Otherwise, overflows are detected by checking
+//!   a canary at the bottom of the stack on context switches.
 //! - `sched_fifo`: Use the [FIFO cooperative scheduler][1]. It also enables the
 //!   `multitask` feature if it is enabled. This feature is enabled by default,
 //!   and it can be overriden by other scheduler features.
@@ -33,6 +39,10 @@
 #[cfg(test)]
 mod tests;
 
+mod event;
+
+pub use self::event::Event;
+
 cfg_if::cfg_if! {
     if #[cfg(feature = "multitask")] {
         #[macro_use]
@@ -41,6 +51,8 @@ cfg_if::cfg_if! {
 
         #[macro_use]
         mod run_queue;
+        mod stack;
+        mod stats;
         mod task;
         mod task_ext;
         mod api;
@@ -49,6 +61,11 @@ cfg_if::cfg_if! {
         #[cfg(feature = "irq")]
         mod timers;
 
+        #[doc(cfg(feature = "multitask"))]
+        pub mod futex;
+        #[doc(cfg(feature = "multitask"))]
+        pub mod future;
+
         #[doc(cfg(feature = "multitask"))]
         pub use self::api::*;
         pub use self::api::{sleep, sleep_until, yield_now};
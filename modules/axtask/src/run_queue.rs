@@ -55,6 +55,21 @@ static mut RUN_QUEUES: [MaybeUninit<&'static mut AxRunQueue>; axconfig::SMP] =
 #[allow(clippy::declare_interior_mutable_const)] // It's ok because it's used only for initialization `RUN_QUEUES`.
 const ARRAY_REPEAT_VALUE: MaybeUninit<&'static mut AxRunQueue> = MaybeUninit::uninit();
 
+/// Whether each CPU is (about to be) waiting for IRQs in the idle task, and
+/// thus needs an IPI to notice tasks unblocked on its run queue.
+#[cfg(all(feature = "smp", feature = "irq"))]
+static IDLE_CPUS: [core::sync::atomic::AtomicBool; axconfig::SMP] =
+    [const { core::sync::atomic::AtomicBool::new(false) }; axconfig::SMP];
+
+/// Marks whether the current CPU is waiting for IRQs in the idle task.
+///
+/// The idle task must check its run queue again after marking itself idle,
+/// as tasks unblocked before that do not send IPIs.
+#[cfg(all(feature = "smp", feature = "irq"))]
+pub(crate) fn set_cpu_idle(idle: bool) {
+    IDLE_CPUS[this_cpu_id()].store(idle, core::sync::atomic::Ordering::SeqCst);
+}
+
 /// Returns a reference to the current run queue in [`CurrentRunQueueRef`].
 ///
 /// ## Safety
@@ -93,21 +108,23 @@ pub(crate) fn current_run_queue<G: BaseGuard>() -> CurrentRunQueueRef<'static, G
 ///
 /// ## Panics
 ///
-/// This function will panic if `cpu_mask` is empty, indicating that there are no available CPUs for task execution.
+/// This function will panic if `cpu_mask` contains none of the running CPUs, indicating that there are no available CPUs for task execution.
 ///
 #[cfg(feature = "smp")]
-// The modulo operation is safe here because `axconfig::SMP` is always greater than 1 with "smp" enabled.
-#[allow(clippy::modulo_one)]
 #[inline]
 fn select_run_queue_index(cpumask: AxCpuMask) -> usize {
     use core::sync::atomic::{AtomicUsize, Ordering};
     static RUN_QUEUE_INDEX: AtomicUsize = AtomicUsize::new(0);
 
-    assert!(!cpumask.is_empty(), "No available CPU for task execution");
+    let cpu_num = axhal::cpu::cpu_num();
+    assert!(
+        (0..cpu_num).any(|i| cpumask.get(i)),
+        "No available CPU for task execution"
+    );
 
     // Round-robin selection of the run queue index.
     loop {
-        let index = RUN_QUEUE_INDEX.fetch_add(1, Ordering::SeqCst) % axconfig::SMP;
+        let index = RUN_QUEUE_INDEX.fetch_add(1, Ordering::SeqCst) % cpu_num;
         if cpumask.get(index) {
             return index;
         }
@@ -266,6 +283,14 @@ impl<G: BaseGuard> AxRunQueueRef<'_, G> {
                 #[cfg(feature = "preempt")]
                 crate::current().set_preempt_pending(true);
             }
+            // Wake up the target CPU if it's idle. A busy CPU picks up the
+            // task when it reschedules.
+            #[cfg(all(feature = "smp", feature = "irq"))]
+            if cpu_id != this_cpu_id()
+                && IDLE_CPUS[cpu_id].load(core::sync::atomic::Ordering::SeqCst)
+            {
+                axhal::mp::send_ipi(cpu_id, axhal::mp::RESCHEDULE_VECTOR);
+            }
         }
     }
 }
@@ -479,6 +504,7 @@ impl AxRunQueue {
             // If the task is blocked, wait for the task to finish its scheduling process.
             // See `unblock_task()` for details.
             if current_state == TaskState::Blocked {
+                task.accounting().mark_woken();
                 // Wait for next task's scheduling process to complete.
                 // If the owning (remote) CPU is still in the middle of schedule() with
                 // this task (next task) as prev, wait until it's done referencing the task.
@@ -538,6 +564,8 @@ impl AxRunQueue {
         #[cfg(feature = "preempt")]
         next_task.set_preempt_pending(false);
         next_task.set_state(TaskState::Running);
+        #[cfg(feature = "tickless")]
+        crate::timers::on_task_switch(next_task.is_idle());
         if !matches!(prev_task.name(), "main" | "gc" | "idle" | "migration-task") {
             crate_interface::call_interface!(AxTaskExtIf::switch_to_task);
         }
@@ -546,6 +574,16 @@ impl AxRunQueue {
             return;
         }
 
+        #[cfg(not(feature = "paging"))]
+        prev_task.check_stack_overflow();
+
+        // The previous task switches out voluntarily if it is blocked or
+        // exited, rather than put back to the run queue.
+        prev_task
+            .accounting()
+            .switch_out(!matches!(prev_task.state(), TaskState::Ready));
+        next_task.accounting().switch_in(self.cpu_id);
+
         // Claim the task as running, we do this before switching to it
         // such that any running task will have this set.
         #[cfg(feature = "smp")]
@@ -0,0 +1,254 @@
+//! Kernel stacks of tasks, with stack overflow detection.
+//!
+//! If the `paging` feature is enabled, each stack is mapped in a dedicated
+//! virtual region with an unmapped guard page below it, so that an overflow
+//! triggers a page fault instead of silently corrupting the memory nearby.
+//! A small overflow area is mapped below the guard page, on which the trap
+//! handler can still run and report the overflow.
+//!
+//! Otherwise, stacks are allocated from the heap with a canary at the bottom,
+//! which is checked when the task is switched out.
+
+#[cfg(feature = "paging")]
+use memory_addr::VirtAddr;
+
+#[cfg(feature = "paging")]
+pub(crate) use self::guarded::TaskStack;
+#[cfg(not(feature = "paging"))]
+pub(crate) use self::heap::TaskStack;
+
+#[cfg(not(feature = "paging"))]
+impl crate::TaskInner {
+    /// Panics if the kernel stack of the task is found to be overflowed.
+    pub(crate) fn check_stack_overflow(&self) {
+        if self.kernel_stack().is_some_and(|s| !s.canary_intact()) {
+            panic!("stack overflow in task {}", self.id_name());
+        }
+    }
+}
+
+/// Reports a stack overflow if `vaddr` is in the guard page of the current
+/// task's stack.
+#[cfg(feature = "paging")]
+fn check_guard_page(vaddr: VirtAddr) {
+    if let Some(curr) = crate::current_may_uninit() {
+        if curr
+            .kernel_stack()
+            .is_some_and(|s| s.guard_range().contains(vaddr))
+        {
+            panic!(
+                "stack overflow in task {}: guard page accessed at {:#x}",
+                curr.id_name(),
+                vaddr
+            );
+        }
+    }
+}
+
+/// Runs before other page fault handlers, to report stack overflows instead of
+/// letting them handle the guard page.
+#[cfg(feature = "paging")]
+#[axhal::trap::register_trap_handler(axhal::trap::PAGE_FAULT)]
+static STACK_GUARD_PAGE_FAULT: axhal::trap::TrapHandler<axhal::trap::PageFaultHandler> =
+    axhal::trap::TrapHandler::with_priority(-100, stack_guard_page_fault);
+
+#[cfg(feature = "paging")]
+fn stack_guard_page_fault(
+    vaddr: VirtAddr,
+    _access_flags: axhal::paging::MappingFlags,
+    is_user: bool,
+) -> bool {
+    if !is_user && guarded::stack_region().contains(vaddr) {
+        check_guard_page(vaddr);
+    }
+    false
+}
+
+#[cfg(feature = "paging")]
+mod guarded {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use axalloc::{AllocTag, global_allocator};
+    use axhal::mem::{PAGE_SIZE_4K, virt_to_phys};
+    use axhal::paging::{MappingFlags, PageSize};
+    use memory_addr::{VirtAddr, VirtAddrRange, va};
+
+    /// Size of the virtual region for all task stacks.
+    const STACK_REGION_SIZE: usize = 0x4000_0000; // 1 GiB
+    /// Size of the unmapped guard area below each stack.
+    const GUARD_SIZE: usize = PAGE_SIZE_4K;
+    /// Size of the mapped area below each guard page, used as the stack of the
+    /// trap handler when the stack overflows.
+    const OVERFLOW_AREA_SIZE: usize = 2 * PAGE_SIZE_4K;
+
+    /// Where to start searching for free space for the next stack.
+    ///
+    /// Allocating in a round-robin manner delays reusing the virtual addresses
+    /// of freed stacks, which may still be cached in the TLBs of other CPUs.
+    static NEXT_STACK_HINT: AtomicUsize = AtomicUsize::new(0);
+
+    /// The virtual region for task stacks, at the top of the kernel address
+    /// space.
+    ///
+    /// It is aligned to its size, so that it is covered by a single top-level
+    /// page table entry. The entry is created when the first stack is mapped at
+    /// boot time, thus stacks mapped later are also visible in the address
+    /// spaces that copied the kernel mappings before.
+    pub(super) fn stack_region() -> VirtAddrRange {
+        let aspace_end = axconfig::plat::KERNEL_ASPACE_BASE + axconfig::plat::KERNEL_ASPACE_SIZE;
+        let end = aspace_end & !(STACK_REGION_SIZE - 1);
+        VirtAddrRange::from_start_size(va!(end - STACK_REGION_SIZE), STACK_REGION_SIZE)
+    }
+
+    /// A kernel stack mapped in the stack region, with the following layout:
+    ///
+    /// ```text
+    /// | overflow area | guard page | stack           |
+    /// ^ slot_start                 ^ bottom          ^ top
+    /// ```
+    ///
+    /// The overflow area and the stack are backed by physically contiguous
+    /// pages allocated from the global allocator.
+    pub(crate) struct TaskStack {
+        slot_start: VirtAddr,
+        top: VirtAddr,
+        /// The address of the backing pages in the linear mapping.
+        pages_vaddr: usize,
+        num_pages: usize,
+    }
+
+    impl TaskStack {
+        pub fn alloc(size: usize) -> Self {
+            let slot_size = OVERFLOW_AREA_SIZE + GUARD_SIZE + size;
+            let num_pages = (OVERFLOW_AREA_SIZE + size) / PAGE_SIZE_4K;
+            let pages_vaddr = global_allocator()
+                .alloc_pages(num_pages, PAGE_SIZE_4K)
+                .expect("failed to allocate task stack");
+            axalloc::tag_pages(pages_vaddr, AllocTag::TaskStack);
+            let paddr = virt_to_phys(pages_vaddr.into());
+
+            let region = stack_region();
+            let mut aspace = axmm::kernel_aspace().lock();
+            let hint = va!(NEXT_STACK_HINT.load(Ordering::Relaxed)).max(region.start);
+            let slot_start = aspace
+                .find_free_area(hint, slot_size, region, PageSize::Size4K)
+                .or_else(|| {
+                    aspace.find_free_area(region.start, slot_size, region, PageSize::Size4K)
+                })
+                .expect("no free virtual space for task stacks");
+            let bottom = slot_start + OVERFLOW_AREA_SIZE + GUARD_SIZE;
+            let flags = MappingFlags::READ | MappingFlags::WRITE;
+            aspace
+                .map_linear(
+                    slot_start,
+                    paddr,
+                    OVERFLOW_AREA_SIZE,
+                    flags,
+                    PageSize::Size4K,
+                )
+                .and_then(|_| {
+                    aspace.map_linear(
+                        bottom,
+                        paddr + OVERFLOW_AREA_SIZE,
+                        size,
+                        flags,
+                        PageSize::Size4K,
+                    )
+                })
+                .expect("failed to map task stack");
+            NEXT_STACK_HINT.store((bottom + size).as_usize(), Ordering::Relaxed);
+
+            Self {
+                slot_start,
+                top: bottom + size,
+                pages_vaddr,
+                num_pages,
+            }
+        }
+
+        pub const fn top(&self) -> VirtAddr {
+            self.top
+        }
+
+        /// The lowest address of the stack, right above the guard page.
+        #[cfg(feature = "backtrace")]
+        pub fn bottom(&self) -> VirtAddr {
+            self.slot_start + OVERFLOW_AREA_SIZE + GUARD_SIZE
+        }
+
+        /// The virtual address range of the guard page.
+        pub fn guard_range(&self) -> VirtAddrRange {
+            VirtAddrRange::from_start_size(self.slot_start + OVERFLOW_AREA_SIZE, GUARD_SIZE)
+        }
+    }
+
+    impl Drop for TaskStack {
+        fn drop(&mut self) {
+            let slot_size = self.top - self.slot_start;
+            axmm::kernel_aspace()
+                .lock()
+                .unmap(self.slot_start, slot_size)
+                .expect("failed to unmap task stack");
+            global_allocator().dealloc_pages(self.pages_vaddr, self.num_pages);
+        }
+    }
+}
+
+#[cfg(not(feature = "paging"))]
+mod heap {
+    use core::{alloc::Layout, ptr::NonNull};
+
+    use axalloc::AllocTag;
+    use memory_addr::VirtAddr;
+
+    /// The value filled at the bottom of each stack to detect overflows.
+    const STACK_CANARY: u64 = 0xdead_beef_cafe_f00d;
+    /// Number of canary words at the bottom of each stack.
+    const CANARY_WORDS: usize = 4;
+
+    /// A kernel stack allocated from the heap, with a canary at the bottom.
+    pub(crate) struct TaskStack {
+        ptr: NonNull<u8>,
+        layout: Layout,
+    }
+
+    impl TaskStack {
+        pub fn alloc(size: usize) -> Self {
+            let layout = Layout::from_size_align(size, 16).unwrap();
+            let ptr = NonNull::new(unsafe { alloc::alloc::alloc(layout) }).unwrap();
+            axalloc::tag_alloc(ptr.as_ptr(), AllocTag::TaskStack);
+            let stack = Self { ptr, layout };
+            for word in stack.canary() {
+                unsafe { word.write_volatile(STACK_CANARY) };
+            }
+            stack
+        }
+
+        pub const fn top(&self) -> VirtAddr {
+            unsafe { core::mem::transmute(self.ptr.as_ptr().add(self.layout.size())) }
+        }
+
+        /// The lowest address of the stack, where the canary is.
+        #[cfg(any(test, feature = "backtrace"))]
+        pub fn bottom(&self) -> VirtAddr {
+            VirtAddr::from(self.ptr.as_ptr() as usize)
+        }
+
+        fn canary(&self) -> impl Iterator<Item = *mut u64> {
+            let bottom = self.ptr.as_ptr().cast::<u64>();
+            (0..CANARY_WORDS).map(move |i| unsafe { bottom.add(i) })
+        }
+
+        /// Whether the canary at the bottom of the stack is not overwritten.
+        pub fn canary_intact(&self) -> bool {
+            self.canary()
+                .all(|word| unsafe { word.read_volatile() } == STACK_CANARY)
+        }
+    }
+
+    impl Drop for TaskStack {
+        fn drop(&mut self) {
+            unsafe { alloc::alloc::dealloc(self.ptr.as_ptr(), self.layout) }
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! Per-task CPU time accounting and scheduling statistics.
+
+use alloc::string::String;
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use core::time::Duration;
+
+use crate::{TaskId, TaskState};
+
+/// A snapshot of the CPU time and scheduling statistics of a task.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TaskStats {
+    /// Time spent running in user mode.
+    pub user_time: Duration,
+    /// Time spent running in kernel mode.
+    pub kernel_time: Duration,
+    /// Number of context switches because the task blocked or exited.
+    pub voluntary_switches: u64,
+    /// Number of context switches because the task was preempted or yielded.
+    pub involuntary_switches: u64,
+    /// Number of times the task has been woken up from the blocked state.
+    pub wakeups: u64,
+    /// Total time between the wakeups and the task actually running.
+    pub total_wakeup_latency: Duration,
+    /// Maximum time between a wakeup and the task actually running.
+    pub max_wakeup_latency: Duration,
+    /// The CPU on which the task ran most recently.
+    pub last_cpu: usize,
+}
+
+impl TaskStats {
+    /// Total CPU time consumed by the task, in both user and kernel mode.
+    pub fn cpu_time(&self) -> Duration {
+        self.user_time + self.kernel_time
+    }
+
+    /// Average time between a wakeup and the task actually running.
+    pub fn avg_wakeup_latency(&self) -> Duration {
+        match self.wakeups {
+            0 => Duration::ZERO,
+            n => Duration::from_nanos((self.total_wakeup_latency.as_nanos() / n as u128) as u64),
+        }
+    }
+}
+
+/// Information about a live task, returned by [`list_tasks`](crate::list_tasks).
+#[derive(Debug, Clone)]
+pub struct TaskInfo {
+    /// The task ID.
+    pub id: TaskId,
+    /// The task name.
+    pub name: String,
+    /// The task state when the snapshot was taken.
+    pub state: TaskState,
+    /// Whether it is the idle task of some CPU.
+    pub is_idle: bool,
+    /// CPU time and scheduling statistics.
+    pub stats: TaskStats,
+}
+
+/// Statistics counters embedded in each task.
+///
+/// All timestamps are in nanoseconds of [`axhal::time::monotonic_time_nanos`].
+/// The counters are only updated by the CPU running the task (or switching
+/// it in/out), but can be read from any CPU, so relaxed atomics are enough.
+pub(crate) struct TaskAccounting {
+    user_ns: AtomicU64,
+    kernel_ns: AtomicU64,
+    nvcsw: AtomicU64,
+    nivcsw: AtomicU64,
+    wakeups: AtomicU64,
+    wakeup_latency_ns: AtomicU64,
+    max_wakeup_latency_ns: AtomicU64,
+    last_cpu: AtomicUsize,
+    /// Start of the current running interval, or 0 if the task is not running.
+    checkpoint_ns: AtomicU64,
+    /// Whether the current running interval is in user mode.
+    in_user: AtomicBool,
+    /// When the task became ready after being woken up, or 0.
+    ready_since_ns: AtomicU64,
+}
+
+#[inline]
+fn now_ns() -> u64 {
+    // Never returns 0, which is reserved for "no timestamp".
+    axhal::time::monotonic_time_nanos().max(1)
+}
+
+impl TaskAccounting {
+    pub(crate) const fn new() -> Self {
+        Self {
+            user_ns: AtomicU64::new(0),
+            kernel_ns: AtomicU64::new(0),
+            nvcsw: AtomicU64::new(0),
+            nivcsw: AtomicU64::new(0),
+            wakeups: AtomicU64::new(0),
+            wakeup_latency_ns: AtomicU64::new(0),
+            max_wakeup_latency_ns: AtomicU64::new(0),
+            last_cpu: AtomicUsize::new(0),
+            checkpoint_ns: AtomicU64::new(0),
+            in_user: AtomicBool::new(false),
+            ready_since_ns: AtomicU64::new(0),
+        }
+    }
+
+    /// Charges the time since the last checkpoint to user or kernel mode, and
+    /// starts a new interval at `now`.
+    fn charge(&self, now: u64) {
+        let start = self.checkpoint_ns.swap(now, Ordering::Relaxed);
+        if start != 0 && now > start {
+            let counter = if self.in_user.load(Ordering::Relaxed) {
+                &self.user_ns
+            } else {
+                &self.kernel_ns
+            };
+            counter.fetch_add(now - start, Ordering::Relaxed);
+        }
+    }
+
+    /// Called when the task starts running on `cpu_id`.
+    pub(crate) fn switch_in(&self, cpu_id: usize) {
+        let now = now_ns();
+        self.checkpoint_ns.store(now, Ordering::Relaxed);
+        self.last_cpu.store(cpu_id, Ordering::Relaxed);
+
+        let ready_since = self.ready_since_ns.swap(0, Ordering::Relaxed);
+        if ready_since != 0 {
+            let latency = now.saturating_sub(ready_since);
+            self.wakeups.fetch_add(1, Ordering::Relaxed);
+            self.wakeup_latency_ns.fetch_add(latency, Ordering::Relaxed);
+            self.max_wakeup_latency_ns
+                .fetch_max(latency, Ordering::Relaxed);
+        }
+    }
+
+    /// Called when the task stops running.
+    ///
+    /// `voluntary` is `true` if the task gave up the CPU because it blocked
+    /// or exited, rather than being preempted or yielding.
+    pub(crate) fn switch_out(&self, voluntary: bool) {
+        self.charge(now_ns());
+        self.checkpoint_ns.store(0, Ordering::Relaxed);
+        if voluntary {
+            self.nvcsw.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.nivcsw.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Called when the task is woken up and put into a run queue.
+    pub(crate) fn mark_woken(&self) {
+        self.ready_since_ns.store(now_ns(), Ordering::Relaxed);
+    }
+
+    /// Switches the accounting mode of the running task between user and
+    /// kernel mode.
+    pub(crate) fn set_in_user(&self, in_user: bool) {
+        if self.in_user.load(Ordering::Relaxed) != in_user {
+            self.charge(now_ns());
+            self.in_user.store(in_user, Ordering::Relaxed);
+        }
+    }
+
+    /// Takes a snapshot of the statistics, including the running interval
+    /// that has not been charged yet.
+    pub(crate) fn snapshot(&self) -> TaskStats {
+        let mut user_ns = self.user_ns.load(Ordering::Relaxed);
+        let mut kernel_ns = self.kernel_ns.load(Ordering::Relaxed);
+        let start = self.checkpoint_ns.load(Ordering::Relaxed);
+        if start != 0 {
+            let running = now_ns().saturating_sub(start);
+            if self.in_user.load(Ordering::Relaxed) {
+                user_ns += running;
+            } else {
+                kernel_ns += running;
+            }
+        }
+        TaskStats {
+            user_time: Duration::from_nanos(user_ns),
+            kernel_time: Duration::from_nanos(kernel_ns),
+            voluntary_switches: self.nvcsw.load(Ordering::Relaxed),
+            involuntary_switches: self.nivcsw.load(Ordering::Relaxed),
+            wakeups: self.wakeups.load(Ordering::Relaxed),
+            total_wakeup_latency: Duration::from_nanos(
+                self.wakeup_latency_ns.load(Ordering::Relaxed),
+            ),
+            max_wakeup_latency: Duration::from_nanos(
+                self.max_wakeup_latency_ns.load(Ordering::Relaxed),
+            ),
+            last_cpu: self.last_cpu.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -1,7 +1,8 @@
-use alloc::{boxed::Box, string::String, sync::Arc};
+use alloc::collections::BTreeMap;
+use alloc::{boxed::Box, string::String, sync::Arc, vec::Vec};
 use core::ops::Deref;
 use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU8, AtomicU64, Ordering};
-use core::{alloc::Layout, cell::UnsafeCell, fmt, ptr::NonNull};
+use core::{cell::UnsafeCell, fmt};
 
 #[cfg(feature = "preempt")]
 use core::sync::atomic::AtomicUsize;
@@ -13,8 +14,10 @@ use axhal::arch::TaskContext;
 #[cfg(feature = "tls")]
 use axhal::tls::TlsArea;
 
+use crate::stack::TaskStack;
+use crate::stats::{TaskAccounting, TaskInfo, TaskStats};
 use crate::task_ext::AxTaskExt;
-use crate::{AxCpuMask, AxTask, AxTaskRef, WaitQueue};
+use crate::{AxCpuMask, AxTask, AxTaskRef, WaitQueue, WeakAxTaskRef};
 
 /// A unique identifier for a thread.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -69,6 +72,9 @@ pub struct TaskInner {
     exit_code: AtomicI32,
     wait_for_exit: WaitQueue,
 
+    /// CPU time and scheduling statistics.
+    stats: TaskAccounting,
+
     kstack: Option<TaskStack>,
     ctx: UnsafeCell<TaskContext>,
     task_ext: AxTaskExt,
@@ -230,6 +236,32 @@ impl TaskInner {
     pub fn exit_code(&self) -> i32 {
         self.exit_code.load(Ordering::Acquire)
     }
+
+    /// Whether the task is the idle task of some CPU.
+    #[inline]
+    pub const fn is_idle(&self) -> bool {
+        self.is_idle
+    }
+
+    /// Returns a snapshot of the CPU time and scheduling statistics of the
+    /// task.
+    ///
+    /// The CPU time includes the current running interval if the task is
+    /// running.
+    pub fn stats(&self) -> TaskStats {
+        self.stats.snapshot()
+    }
+
+    /// Returns the information about the task, including its statistics.
+    pub fn info(&self) -> TaskInfo {
+        TaskInfo {
+            id: self.id,
+            name: String::from(self.name()),
+            state: self.state(),
+            is_idle: self.is_idle,
+            stats: self.stats(),
+        }
+    }
 }
 
 // private methods
@@ -255,6 +287,7 @@ impl TaskInner {
             preempt_disable_count: AtomicUsize::new(0),
             exit_code: AtomicI32::new(0),
             wait_for_exit: WaitQueue::new(),
+            stats: TaskAccounting::new(),
             kstack: None,
             ctx: UnsafeCell::new(TaskContext::new()),
             task_ext: AxTaskExt::empty(),
@@ -276,6 +309,8 @@ impl TaskInner {
         t.is_init = true;
         #[cfg(feature = "smp")]
         t.set_on_cpu(true);
+        // It is already running on the current CPU.
+        t.stats.switch_in(axhal::cpu::this_cpu_id());
         if t.name() == "idle" {
             t.is_idle = true;
         }
@@ -283,7 +318,11 @@ impl TaskInner {
     }
 
     pub(crate) fn into_arc(self) -> AxTaskRef {
-        Arc::new(AxTask::new(self))
+        let task = Arc::new(AxTask::new(self));
+        ALL_TASKS
+            .lock()
+            .insert(task.id.as_u64(), Arc::downgrade(&task));
+        task
     }
 
     /// Returns the task's current state.
@@ -328,11 +367,6 @@ impl TaskInner {
         self.is_init
     }
 
-    #[inline]
-    pub(crate) const fn is_idle(&self) -> bool {
-        self.is_idle
-    }
-
     #[inline]
     pub(crate) fn in_wait_queue(&self) -> bool {
         self.in_wait_queue.load(Ordering::Acquire)
@@ -416,6 +450,27 @@ impl TaskInner {
         self.wait_for_exit.notify_all(false);
     }
 
+    #[inline]
+    pub(crate) fn kernel_stack(&self) -> Option<&TaskStack> {
+        self.kstack.as_ref()
+    }
+
+    /// Bounds backtraces on this CPU by the kernel stack of the task, or the
+    /// boot stack if it has none.
+    #[cfg(feature = "backtrace")]
+    fn set_backtrace_stack(&self) {
+        let stack = self
+            .kstack
+            .as_ref()
+            .map_or(0..0, |s| s.bottom().as_usize()..s.top().as_usize());
+        axhal::backtrace::set_current_stack(stack);
+    }
+
+    #[inline]
+    pub(crate) fn accounting(&self) -> &TaskAccounting {
+        &self.stats
+    }
+
     #[inline]
     pub(crate) const unsafe fn ctx_mut_ptr(&self) -> *mut TaskContext {
         self.ctx.get()
@@ -454,32 +509,25 @@ impl fmt::Debug for TaskInner {
 impl Drop for TaskInner {
     fn drop(&mut self) {
         debug!("task drop: {}", self.id_name());
+        ALL_TASKS.lock().remove(&self.id.as_u64());
     }
 }
 
-struct TaskStack {
-    ptr: NonNull<u8>,
-    layout: Layout,
-}
-
-impl TaskStack {
-    pub fn alloc(size: usize) -> Self {
-        let layout = Layout::from_size_align(size, 16).unwrap();
-        Self {
-            ptr: NonNull::new(unsafe { alloc::alloc::alloc(layout) }).unwrap(),
-            layout,
-        }
-    }
-
-    pub const fn top(&self) -> VirtAddr {
-        unsafe { core::mem::transmute(self.ptr.as_ptr().add(self.layout.size())) }
-    }
-}
-
-impl Drop for TaskStack {
-    fn drop(&mut self) {
-        unsafe { alloc::alloc::dealloc(self.ptr.as_ptr(), self.layout) }
-    }
+/// All live tasks, indexed by task ID.
+///
+/// A task is registered by [`TaskInner::into_arc`] and unregistered when it is
+/// dropped.
+static ALL_TASKS: SpinNoIrq<BTreeMap<u64, WeakAxTaskRef>> = SpinNoIrq::new(BTreeMap::new());
+
+/// Returns references to all live tasks, ordered by task ID.
+pub(crate) fn all_tasks() -> Vec<AxTaskRef> {
+    // Do not drop the upgraded references with the lock held, as it may drop
+    // the task and try to lock `ALL_TASKS` again.
+    ALL_TASKS
+        .lock()
+        .values()
+        .filter_map(|task| task.upgrade())
+        .collect()
 }
 
 use core::mem::ManuallyDrop;
@@ -522,6 +570,8 @@ impl CurrentTask {
         unsafe {
             axhal::arch::write_thread_pointer(init_task.tls.tls_ptr() as usize);
         }
+        #[cfg(feature = "backtrace")]
+        init_task.set_backtrace_stack();
         let ptr = Arc::into_raw(init_task);
         unsafe {
             axhal::cpu::set_current_task_ptr(ptr);
@@ -531,6 +581,8 @@ impl CurrentTask {
     pub(crate) unsafe fn set_current(prev: Self, next: AxTaskRef) {
         let Self(arc) = prev;
         ManuallyDrop::into_inner(arc); // `call Arc::drop()` to decrease prev task reference count.
+        #[cfg(feature = "backtrace")]
+        next.set_backtrace_stack();
         let ptr = Arc::into_raw(next);
         unsafe {
             axhal::cpu::set_current_task_ptr(ptr);
@@ -1,7 +1,7 @@
 use core::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Mutex, Once};
 
-use crate::{WaitQueue, api as axtask, current};
+use crate::{Event, WaitQueue, api as axtask, current};
 
 static INIT: Once = Once::new();
 static SERIAL: Mutex<()> = Mutex::new(());
@@ -104,6 +104,21 @@ fn test_wait_queue() {
     assert!(!current().in_wait_queue());
 }
 
+#[test]
+fn test_event() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    static EVENT: Event = Event::new();
+
+    let seen = EVENT.count();
+    axtask::spawn(|| EVENT.notify(true));
+    while EVENT.count() == seen {
+        EVENT.wait_timeout(seen, core::time::Duration::from_millis(10));
+    }
+    assert_eq!(EVENT.count(), seen + 1);
+}
+
 #[test]
 fn test_task_join() {
     let _lock = SERIAL.lock();
@@ -128,3 +143,187 @@ fn test_task_join() {
         assert_eq!(tasks[i].join(), Some(i as _));
     }
 }
+
+#[test]
+fn test_futex() {
+    use crate::futex::{self, FutexError, FutexKey};
+    use core::sync::atomic::AtomicU32;
+
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    const NUM_TASKS: usize = 4;
+    static FUTEX: AtomicU32 = AtomicU32::new(0);
+    static FUTEX2: AtomicU32 = AtomicU32::new(0);
+    static WOKEN: AtomicUsize = AtomicUsize::new(0);
+    let key = FutexKey::private(&FUTEX as *const _ as usize);
+    let key2 = FutexKey::private(&FUTEX2 as *const _ as usize);
+
+    // The value does not match, return immediately.
+    assert_eq!(
+        futex::wait(key, &FUTEX, 1, None),
+        Err(FutexError::WouldBlock)
+    );
+
+    for _ in 0..NUM_TASKS {
+        axtask::spawn(move || {
+            futex::wait(key, &FUTEX, 0, None).unwrap();
+            WOKEN.fetch_add(1, Ordering::Relaxed);
+        });
+    }
+    while futex::waiter_count(key) < NUM_TASKS {
+        axtask::yield_now();
+    }
+
+    // Wake one and move the others to `key2`.
+    FUTEX.store(1, Ordering::SeqCst);
+    assert_eq!(
+        futex::cmp_requeue(key, &FUTEX, 0, 1, key2, usize::MAX),
+        Err(FutexError::WouldBlock)
+    );
+    assert_eq!(
+        futex::cmp_requeue(key, &FUTEX, 1, 1, key2, usize::MAX),
+        Ok(NUM_TASKS)
+    );
+    assert_eq!(futex::waiter_count(key), 0);
+    assert_eq!(futex::waiter_count(key2), NUM_TASKS - 1);
+    while WOKEN.load(Ordering::Relaxed) < 1 {
+        axtask::yield_now();
+    }
+
+    assert_eq!(futex::wake(key, usize::MAX), 0);
+    assert_eq!(futex::wake(key2, usize::MAX), NUM_TASKS - 1);
+    while WOKEN.load(Ordering::Relaxed) < NUM_TASKS {
+        axtask::yield_now();
+    }
+}
+
+#[test]
+fn test_task_stats() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    let wq = std::sync::Arc::new(WaitQueue::new());
+    let wq2 = wq.clone();
+    let task = axtask::spawn_raw(
+        move || {
+            for _ in 0..3 {
+                axtask::yield_now();
+            }
+            wq2.wait();
+        },
+        "stats-test".into(),
+        0x1000,
+    );
+    let id = task.id();
+
+    while !axtask::list_tasks()
+        .iter()
+        .any(|info| info.id == id && matches!(info.state, crate::TaskState::Blocked))
+    {
+        axtask::yield_now();
+    }
+    let info = axtask::list_tasks()
+        .into_iter()
+        .find(|info| info.id == id)
+        .unwrap();
+    assert_eq!(info.name, "stats-test");
+    assert!(!info.is_idle);
+    assert!(info.stats.involuntary_switches >= 3);
+    assert_eq!(info.stats.voluntary_switches, 1);
+
+    wq.notify_one(true);
+    assert_eq!(task.join(), Some(0));
+    let stats = task.stats();
+    assert_eq!(stats.wakeups, 1);
+    assert!(stats.max_wakeup_latency <= stats.total_wakeup_latency);
+}
+
+#[test]
+fn test_executor() {
+    use core::future::poll_fn;
+    use core::sync::atomic::AtomicBool;
+    use core::task::Poll;
+    use core::time::Duration;
+
+    use crate::future::{Executor, TimedOut, block_on, sleep, timeout};
+
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    // A future woken up by another task.
+    static FLAG: AtomicBool = AtomicBool::new(false);
+    static WAKER: std::sync::Mutex<Option<core::task::Waker>> = std::sync::Mutex::new(None);
+    let task = axtask::spawn(|| {
+        while WAKER.lock().unwrap().is_none() {
+            axtask::yield_now();
+        }
+        FLAG.store(true, Ordering::Release);
+        WAKER.lock().unwrap().take().unwrap().wake();
+    });
+    block_on(poll_fn(|cx| {
+        if FLAG.load(Ordering::Acquire) {
+            Poll::Ready(())
+        } else {
+            *WAKER.lock().unwrap() = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }));
+    assert_eq!(task.join(), Some(0));
+
+    let executor = Executor::new();
+    let spawner = executor.spawner();
+    let sum = executor.block_on(async {
+        let handles: Vec<_> = (0..10u64)
+            .map(|i| {
+                spawner.spawn(async move {
+                    sleep(Duration::from_millis(10 - i)).await;
+                    i
+                })
+            })
+            .collect();
+        let mut sum = 0;
+        for h in handles {
+            sum += h.await;
+        }
+        sum
+    });
+    assert_eq!(sum, 45);
+
+    let res = executor.block_on(timeout(
+        Duration::from_millis(5),
+        sleep(Duration::from_secs(10)),
+    ));
+    assert_eq!(res, Err(TimedOut));
+    let res = executor.block_on(timeout(Duration::from_secs(10), async { 1 }));
+    assert_eq!(res, Ok(1));
+}
+
+#[test]
+#[cfg(not(feature = "paging"))]
+fn test_stack_overflow_canary() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    let task = axtask::spawn_raw(
+        || {
+            let curr = current();
+            let bottom = curr.kernel_stack().unwrap().bottom().as_mut_ptr() as *mut u64;
+            // Overflow the stack on purpose by clobbering its bottom, as deep
+            // calls would do.
+            let saved = unsafe { bottom.read_volatile() };
+            unsafe { bottom.write_volatile(0) };
+            let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                curr.check_stack_overflow()
+            }));
+            unsafe { bottom.write_volatile(saved) };
+            let msg = res.unwrap_err().downcast::<String>().unwrap();
+            assert!(msg.starts_with("stack overflow in task"), "{msg}");
+            // Not reported once the canary is restored.
+            curr.check_stack_overflow();
+        },
+        "overflow-test".into(),
+        0x10000,
+    );
+    assert_eq!(task.join(), Some(0));
+}
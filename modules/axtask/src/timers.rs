@@ -13,8 +13,20 @@ static TIMER_TICKET_ID: AtomicU64 = AtomicU64::new(1);
 
 percpu_static! {
     TIMER_LIST: LazyInit<TimerList<TimerEventType>> = LazyInit::new(),
+    /// The monotonic deadline (in nanoseconds) of the one-shot timer
+    /// programmed on this CPU, or `u64::MAX` if there is none.
+    #[cfg(feature = "tickless")]
+    PROGRAMMED_DEADLINE: u64 = u64::MAX,
+    /// The monotonic deadline (in nanoseconds) of the next scheduler tick on
+    /// this CPU, or `u64::MAX` if the scheduler tick is stopped.
+    #[cfg(feature = "tickless")]
+    SCHED_TICK_DEADLINE: u64 = u64::MAX,
 }
 
+/// The interval between two scheduler ticks when a task is running.
+#[cfg(feature = "tickless")]
+const SCHED_TICK_INTERVAL_NANOS: u64 = axhal::time::NANOS_PER_SEC / axconfig::TICKS_PER_SEC as u64;
+
 /// Timer event types that can be scheduled
 pub enum TimerEventType {
     /// Wake up a task
@@ -56,6 +68,7 @@ impl TimerEvent for TaskWakeupEvent {
 
 /// Timer callback event that executes a function
 pub struct TimerCallbackEvent {
+    ticket_id: u64,
     callback: Box<dyn FnOnce() + Send + 'static>,
 }
 
@@ -68,27 +81,69 @@ impl TimerEvent for TimerCallbackEvent {
 
 /// Set a timer to wake up a task at the specified deadline
 pub fn set_alarm_wakeup(deadline: TimeValue, task: AxTaskRef) {
-    TIMER_LIST.with_current(|timer_list| {
-        let ticket_id = TIMER_TICKET_ID.fetch_add(1, Ordering::AcqRel);
-        task.set_timer_ticket(ticket_id);
-        timer_list.set(
-            deadline,
-            TimerEventType::TaskWakeup(TaskWakeupEvent { ticket_id, task }),
-        );
-    })
+    let ticket_id = TIMER_TICKET_ID.fetch_add(1, Ordering::AcqRel);
+    task.set_timer_ticket(ticket_id);
+    set_alarm(
+        deadline,
+        TimerEventType::TaskWakeup(TaskWakeupEvent { ticket_id, task }),
+    );
+}
+
+/// Identifies a timer event set by [`set_alarm_callback`], to cancel it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlarmTicket {
+    id: u64,
+    cpu_id: usize,
 }
 
 /// Set a timer to execute a callback function at the specified deadline
-pub fn set_alarm_callback<F>(deadline: TimeValue, callback: F)
+///
+/// Returns a ticket that can be passed to [`cancel_alarm`].
+pub fn set_alarm_callback<F>(deadline: TimeValue, callback: F) -> AlarmTicket
 where
     F: FnOnce() + Send + 'static,
 {
-    TIMER_LIST.with_current(|timer_list| {
-        let callback_event = TimerCallbackEvent {
-            callback: Box::new(callback),
-        };
-        timer_list.set(deadline, TimerEventType::Callback(callback_event));
-    })
+    let ticket_id = TIMER_TICKET_ID.fetch_add(1, Ordering::AcqRel);
+    let callback_event = TimerCallbackEvent {
+        ticket_id,
+        callback: Box::new(callback),
+    };
+    let cpu_id = set_alarm(deadline, TimerEventType::Callback(callback_event));
+    AlarmTicket {
+        id: ticket_id,
+        cpu_id,
+    }
+}
+
+/// Cancels the timer event set by [`set_alarm_callback`], if it has not been
+/// executed.
+///
+/// Timer lists are per-CPU, so the event is only removed if it was set on the
+/// current CPU. Otherwise, it stays in the timer list of the other CPU until
+/// the deadline, thus the callback should do nothing if it is cancelled.
+pub fn cancel_alarm(ticket: AlarmTicket) {
+    let _guard = kernel_guard::NoPreemptIrqSave::new();
+    if ticket.cpu_id != axhal::cpu::this_cpu_id() {
+        return;
+    }
+    // Safety: preemption and IRQs are disabled at this time.
+    unsafe { TIMER_LIST.current_ref_mut_raw() }
+        .cancel(|event| matches!(event, TimerEventType::Callback(e) if e.ticket_id == ticket.id));
+}
+
+/// Adds the event to the timer list of the current CPU, and programs the
+/// one-shot timer of the same CPU for it in the tickless mode. Returns the ID
+/// of the CPU.
+///
+/// Both are done without migrating to other CPUs, otherwise the event may be
+/// added to a CPU whose timer is never programmed for it.
+fn set_alarm(deadline: TimeValue, event: TimerEventType) -> usize {
+    let _guard = kernel_guard::NoPreemptIrqSave::new();
+    // Safety: preemption and IRQs are disabled at this time.
+    unsafe { TIMER_LIST.current_ref_mut_raw() }.set(deadline, event);
+    #[cfg(feature = "tickless")]
+    reprogram();
+    axhal::cpu::this_cpu_id()
 }
 
 /// Check and process expired timer events
@@ -108,9 +163,79 @@ pub fn check_events() {
     }
 }
 
+/// Handles a timer interrupt in the tickless mode.
+///
+/// It processes expired timer events, advances the scheduler if the slice
+/// deadline has passed, and programs the one-shot timer to the next deadline.
+/// Returns whether the scheduler tick is due.
+///
+/// IRQs must be disabled.
+#[cfg(feature = "tickless")]
+pub(crate) fn on_timer_irq() -> bool {
+    // Safety: IRQs are disabled at this time.
+    unsafe { PROGRAMMED_DEADLINE.write_current_raw(u64::MAX) };
+    check_events();
+
+    let now_ns = axhal::time::monotonic_time_nanos();
+    let sched_tick_due = unsafe { SCHED_TICK_DEADLINE.read_current_raw() } <= now_ns;
+    if sched_tick_due {
+        unsafe { SCHED_TICK_DEADLINE.write_current_raw(now_ns + SCHED_TICK_INTERVAL_NANOS) };
+    }
+    reprogram();
+    if unsafe { PROGRAMMED_DEADLINE.read_current_raw() } == u64::MAX {
+        // Nothing to wait for. Push the timer away, otherwise a level-triggered
+        // timer interrupt keeps firing.
+        axhal::time::set_oneshot_timer(u64::MAX);
+    }
+    sched_tick_due
+}
+
+/// Starts or stops the scheduler tick when switching to the next task.
+///
+/// The tick is only needed for preemption, so it is stopped when the CPU
+/// becomes idle, or if the scheduler is not preemptive.
+///
+/// IRQs must be disabled.
+#[cfg(feature = "tickless")]
+pub(crate) fn on_task_switch(next_is_idle: bool) {
+    if next_is_idle || cfg!(not(feature = "preempt")) {
+        // Safety: IRQs are disabled at this time.
+        unsafe { SCHED_TICK_DEADLINE.write_current_raw(u64::MAX) };
+    } else if unsafe { SCHED_TICK_DEADLINE.read_current_raw() } == u64::MAX {
+        let now_ns = axhal::time::monotonic_time_nanos();
+        unsafe { SCHED_TICK_DEADLINE.write_current_raw(now_ns + SCHED_TICK_INTERVAL_NANOS) };
+        reprogram();
+    }
+}
+
+/// Programs the one-shot timer to the earliest of the next timer event and
+/// the next scheduler tick, if it is earlier than the programmed one.
+///
+/// IRQs must be disabled.
+#[cfg(feature = "tickless")]
+fn reprogram() {
+    // Safety: IRQs are disabled at this time.
+    let next_event_ns = unsafe { TIMER_LIST.current_ref_raw() }
+        .next_deadline()
+        .map_or(u64::MAX, |deadline| {
+            (deadline.as_nanos() as u64).saturating_sub(axhal::time::epochoffset_nanos())
+        });
+    let deadline_ns = next_event_ns.min(unsafe { SCHED_TICK_DEADLINE.read_current_raw() });
+    if deadline_ns < unsafe { PROGRAMMED_DEADLINE.read_current_raw() } {
+        unsafe { PROGRAMMED_DEADLINE.write_current_raw(deadline_ns) };
+        axhal::time::set_oneshot_timer(deadline_ns);
+    }
+}
+
 /// Initialize the per-CPU timer list
 pub fn init() {
     TIMER_LIST.with_current(|timer_list| {
         timer_list.init_once(TimerList::new());
     });
+    // Start the scheduler tick if the CPU starts with a non-idle task.
+    #[cfg(feature = "tickless")]
+    {
+        let _guard = kernel_guard::IrqSave::new();
+        on_task_switch(crate::current().is_idle());
+    }
 }
@@ -0,0 +1,98 @@
+//! Console ports of VirtIO console devices, i.e., `hvc0`, `hvc1`, etc.
+//!
+//! Ports are the registered console devices `hvc0`, `hvc1`, etc., in the
+//! order they are registered. The first port is used as the system console
+//! instead of the platform UART, once [`init_hvc`](crate::init_hvc) finds it.
+//!
+//! Ports do not raise input interrupts, so they are polled when reading.
+
+use alloc::vec::Vec;
+
+use axdriver::registry::{self, DeviceRef};
+use axdriver::{AxConsoleDevice, prelude::*};
+use axerrno::{AxError, AxResult};
+use lazyinit::LazyInit;
+
+static PORTS: LazyInit<Vec<DeviceRef<AxConsoleDevice>>> = LazyInit::new();
+
+fn port(id: usize) -> AxResult<&'static DeviceRef<AxConsoleDevice>> {
+    PORTS
+        .get()
+        .and_then(|ports| ports.get(id))
+        .ok_or(AxError::NotFound)
+}
+
+pub(crate) fn init() -> usize {
+    let ports = registry::devices::<AxConsoleDevice>();
+    for port in &ports {
+        info!("  use console port {}", port.name());
+    }
+    let count = ports.len();
+    PORTS.init_once(ports);
+    count
+}
+
+/// Returns the number of console ports.
+pub fn port_count() -> usize {
+    PORTS.get().map_or(0, Vec::len)
+}
+
+/// Returns the name of the given console port, e.g., `hvc0`.
+pub fn port_name(id: usize) -> AxResult<&'static str> {
+    Ok(port(id)?.name())
+}
+
+/// Returns the ID of the console port with the given name.
+pub fn find_port(name: &str) -> Option<usize> {
+    PORTS.get()?.iter().position(|port| port.name() == name)
+}
+
+/// Reads the available input of the given console port into `buf`, and
+/// returns the number of bytes read. It does not block.
+pub fn read_port(id: usize, buf: &mut [u8]) -> AxResult<usize> {
+    port(id)?.lock().read(buf).map_err(|_| AxError::Io)
+}
+
+/// Writes all the given bytes to the given console port.
+///
+/// If the port is full, it yields until the host consumes the output.
+pub fn write_port(id: usize, buf: &[u8]) -> AxResult {
+    let port = port(id)?;
+    let mut written = 0;
+    while written < buf.len() {
+        // Do not hold the lock while yielding.
+        let res = port.lock().write(&buf[written..]);
+        match res {
+            Ok(0) => axtask::yield_now(),
+            Ok(n) => written += n,
+            Err(_) => return Err(AxError::Io),
+        }
+    }
+    Ok(())
+}
+
+/// Writes to the system console port, or returns `false` if the port is in
+/// use, e.g., when logging from the driver.
+///
+/// It may be called with IRQs disabled, so the output is dropped instead of
+/// waiting if the port is full.
+pub(crate) fn write_console(buf: &[u8]) -> bool {
+    let Ok(port) = port(0) else {
+        return false;
+    };
+    let Some(mut dev) = port.try_lock() else {
+        return false;
+    };
+    let mut written = 0;
+    while written < buf.len() {
+        match dev.write(&buf[written..]) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => written += n,
+        }
+    }
+    true
+}
+
+pub(crate) fn read_console(buf: &mut [u8]) -> usize {
+    read_port(0, buf).unwrap_or(0)
+}
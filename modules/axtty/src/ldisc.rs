@@ -0,0 +1,292 @@
+//! The line discipline, which processes input characters according to the
+//! terminal attributes.
+
+use crate::termios::{InputFlags, LocalFlags, Termios, VEOF, VERASE, VINTR, VKILL, VMIN};
+
+/// The capacity of the buffer of characters ready to be read.
+const INPUT_BUF_SIZE: usize = 4096;
+
+/// The maximum length of a line in canonical mode, including the NL.
+const MAX_CANON: usize = 255;
+
+/// The maximum number of complete lines that are not read yet.
+const MAX_LINES: usize = 64;
+
+/// A fixed-capacity FIFO queue.
+pub(crate) struct RingBuffer<T: Copy, const N: usize> {
+    buf: [T; N],
+    head: usize,
+    len: usize,
+}
+
+impl<T: Copy, const N: usize> RingBuffer<T, N> {
+    pub const fn new(init: T) -> Self {
+        Self {
+            buf: [init; N],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub const fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Appends an item to the back, returns `false` if the buffer is full.
+    pub fn push(&mut self, item: T) -> bool {
+        if self.is_full() {
+            return false;
+        }
+        self.buf[(self.head + self.len) % N] = item;
+        self.len += 1;
+        true
+    }
+
+    /// Removes the item at the front.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        let item = self.buf[self.head];
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        Some(item)
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(&mut self.buf[self.head])
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+}
+
+/// Whether the character is echoed as `^X` with `ECHOCTL`.
+const fn is_ctrl(c: u8) -> bool {
+    (c < b' ' && c != b'\n' && c != b'\t') || c == 0x7f
+}
+
+/// The line discipline of a terminal.
+pub(crate) struct LineDiscipline {
+    termios: Termios,
+    /// Characters ready to be read.
+    input: RingBuffer<u8, INPUT_BUF_SIZE>,
+    /// Lengths of the complete lines in `input` in canonical mode. A line of
+    /// zero length is an end of file.
+    lines: RingBuffer<usize, MAX_LINES>,
+    /// The line being edited in canonical mode.
+    line: [u8; MAX_CANON],
+    line_len: usize,
+}
+
+impl LineDiscipline {
+    pub const fn new() -> Self {
+        Self {
+            termios: Termios::new(),
+            input: RingBuffer::new(0),
+            lines: RingBuffer::new(0),
+            line: [0; MAX_CANON],
+            line_len: 0,
+        }
+    }
+
+    pub const fn termios(&self) -> &Termios {
+        &self.termios
+    }
+
+    /// Updates the terminal attributes.
+    ///
+    /// When leaving canonical mode, the line being edited becomes readable.
+    /// When entering it, all readable characters are regarded as a line.
+    pub fn set_termios(&mut self, termios: &Termios) {
+        match (self.termios.is_canonical(), termios.is_canonical()) {
+            (true, false) => {
+                for &c in &self.line[..self.line_len] {
+                    self.input.push(c);
+                }
+                self.line_len = 0;
+                self.lines.clear();
+            }
+            (false, true) => {
+                self.lines.clear();
+                if !self.input.is_empty() {
+                    self.lines.push(self.input.len());
+                }
+            }
+            _ => {}
+        }
+        self.termios = *termios;
+    }
+
+    /// Discards all received but unread characters.
+    pub fn flush_input(&mut self) {
+        self.input.clear();
+        self.lines.clear();
+        self.line_len = 0;
+    }
+
+    /// Whether a read would return without blocking.
+    pub fn readable(&self) -> bool {
+        if self.termios.is_canonical() {
+            !self.lines.is_empty()
+        } else {
+            !self.input.is_empty() || self.termios.cc[VMIN] == 0
+        }
+    }
+
+    /// Reads characters into `buf`, or returns `None` if the read should
+    /// block.
+    ///
+    /// In canonical mode, at most one line is read, and it returns `Some(0)`
+    /// at end of file.
+    pub fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        let avail = if self.termios.is_canonical() {
+            *self.lines.front_mut()?
+        } else if self.input.is_empty() {
+            return (self.termios.cc[VMIN] == 0).then_some(0);
+        } else {
+            self.input.len()
+        };
+
+        let len = avail.min(buf.len());
+        for b in &mut buf[..len] {
+            *b = self.input.pop().unwrap();
+        }
+        if self.termios.is_canonical() {
+            match self.lines.front_mut() {
+                Some(line_len) if *line_len > len => *line_len -= len,
+                _ => {
+                    self.lines.pop();
+                }
+            }
+        }
+        Some(len)
+    }
+
+    /// Processes a received character, and echoes it with `echo` if needed.
+    ///
+    /// Returns `true` if it is the interrupt character, in which case all
+    /// pending input is discarded.
+    pub fn receive(&mut self, mut c: u8, echo: &mut impl FnMut(&[u8])) -> bool {
+        let iflag = self.termios.iflag;
+        if c == b'\r' {
+            if iflag.contains(InputFlags::IGNCR) {
+                return false;
+            }
+            if iflag.contains(InputFlags::ICRNL) {
+                c = b'\n';
+            }
+        } else if c == b'\n' && iflag.contains(InputFlags::INLCR) {
+            c = b'\r';
+        }
+
+        if self.termios.lflag.contains(LocalFlags::ISIG) && self.termios.is_cc(c, VINTR) {
+            self.flush_input();
+            self.echo_char(c, echo);
+            return true;
+        }
+
+        if self.termios.is_canonical() {
+            self.receive_canonical(c, echo);
+        } else if self.input.push(c) {
+            self.echo_char(c, echo);
+        }
+        false
+    }
+
+    fn receive_canonical(&mut self, c: u8, echo: &mut impl FnMut(&[u8])) {
+        let lflag = self.termios.lflag;
+        if self.termios.is_cc(c, VERASE) {
+            if self.line_len > 0 {
+                self.line_len -= 1;
+                if lflag.contains(LocalFlags::ECHO | LocalFlags::ECHOE) {
+                    self.echo_erase(self.line[self.line_len], echo);
+                } else {
+                    self.echo_char(c, echo);
+                }
+            }
+        } else if self.termios.is_cc(c, VKILL) {
+            if lflag.contains(LocalFlags::ECHO | LocalFlags::ECHOE) {
+                while self.line_len > 0 {
+                    self.line_len -= 1;
+                    self.echo_erase(self.line[self.line_len], echo);
+                }
+            } else {
+                self.echo_char(c, echo);
+                if lflag.contains(LocalFlags::ECHOK) {
+                    self.echo_char(b'\n', echo);
+                }
+            }
+            self.line_len = 0;
+        } else if self.termios.is_cc(c, VEOF) {
+            self.commit_line();
+        } else if c == b'\n' {
+            // The last slot is reserved for NL.
+            self.line[self.line_len] = c;
+            self.line_len += 1;
+            if lflag.contains(LocalFlags::ECHONL) && !lflag.contains(LocalFlags::ECHO) {
+                echo(b"\n");
+            } else {
+                self.echo_char(c, echo);
+            }
+            self.commit_line();
+        } else if self.line_len < MAX_CANON - 1 {
+            self.line[self.line_len] = c;
+            self.line_len += 1;
+            self.echo_char(c, echo);
+        }
+    }
+
+    /// Makes the line being edited readable. The line is dropped if there is
+    /// no room for it.
+    fn commit_line(&mut self) {
+        let len = self.line_len;
+        self.line_len = 0;
+        if self.lines.is_full() || self.input.len() + len > INPUT_BUF_SIZE {
+            warn!("TTY input buffer is full, line dropped");
+            return;
+        }
+        for &c in &self.line[..len] {
+            self.input.push(c);
+        }
+        self.lines.push(len);
+    }
+
+    fn echo_char(&self, c: u8, echo: &mut impl FnMut(&[u8])) {
+        let lflag = self.termios.lflag;
+        if !lflag.contains(LocalFlags::ECHO) {
+            return;
+        }
+        if is_ctrl(c) && lflag.contains(LocalFlags::ECHOCTL) {
+            echo(&[b'^', c ^ 0x40]);
+        } else {
+            echo(&[c]);
+        }
+    }
+
+    /// Erases the echo of the character `c` on the screen.
+    fn echo_erase(&self, c: u8, echo: &mut impl FnMut(&[u8])) {
+        let width = if is_ctrl(c) && self.termios.lflag.contains(LocalFlags::ECHOCTL) {
+            2
+        } else {
+            1
+        };
+        for _ in 0..width {
+            echo(b"\x08 \x08");
+        }
+    }
+}
@@ -0,0 +1,241 @@
+//! [ArceOS](https://github.com/arceos-org/arceos) terminal (TTY) module.
+//!
+//! It makes the console a terminal: input characters are received from the
+//! console, processed by a line discipline according to the [`Termios`]
+//! attributes (e.g., echo, line editing, `^C`), and buffered until they are
+//! read.
+//!
+//! # Cargo Features
+//!
+//! - `irq`: Receive input in the console interrupt handler, if the platform
+//!   supports it. Otherwise, the console is polled when reading, see [`read`].
+//! - `multitask`: Readers sleep on a wait queue until input is available,
+//!   instead of yielding the CPU repeatedly.
+//! - `hvc`: Support VirtIO console ports (see [`hvc`]), and use the first one
+//!   as the console if any.
+//! - `fbcon`: Also show the console output, i.e., logs, standard output and
+//!   echoed input, on the framebuffer text console of the first display.
+//! - `fbcon-log`: Only show logs on the framebuffer text console.
+//! - `fbcon-stdout`: Only show standard output and echoed input on the
+//!   framebuffer text console.
+
+#![cfg_attr(not(test), no_std)]
+
+#[macro_use]
+extern crate log;
+#[cfg(feature = "hvc")]
+extern crate alloc;
+
+#[cfg(feature = "hvc")]
+pub mod hvc;
+mod ldisc;
+mod termios;
+
+#[cfg(test)]
+mod tests;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use kspin::SpinNoIrq;
+
+use self::ldisc::LineDiscipline;
+
+pub use self::termios::*;
+
+static LDISC: SpinNoIrq<LineDiscipline> = SpinNoIrq::new(LineDiscipline::new());
+
+static INTERRUPT_HANDLER: SpinNoIrq<Option<fn()>> = SpinNoIrq::new(None);
+
+/// Whether input is received in the console interrupt handler.
+static IRQ_DRIVEN: AtomicBool = AtomicBool::new(false);
+
+/// Whether the console is a VirtIO console port instead of the UART.
+#[cfg(feature = "hvc")]
+static HVC_CONSOLE: AtomicBool = AtomicBool::new(false);
+
+#[cfg(feature = "multitask")]
+static INPUT_WAIT_QUEUE: axtask::WaitQueue = axtask::WaitQueue::new();
+
+fn console_write(bytes: &[u8]) {
+    #[cfg(feature = "hvc")]
+    if HVC_CONSOLE.load(Ordering::Acquire) && hvc::write_console(bytes) {
+        return;
+    }
+    axhal::console::write_bytes(bytes);
+}
+
+fn console_read(buf: &mut [u8]) -> usize {
+    #[cfg(feature = "hvc")]
+    if HVC_CONSOLE.load(Ordering::Acquire) {
+        return hvc::read_console(buf);
+    }
+    axhal::console::read_bytes(buf)
+}
+
+fn echo(bytes: &[u8]) {
+    write(bytes);
+}
+
+/// Drains the input from the console into the line discipline.
+fn receive_input() {
+    let mut buf = [0; 64];
+    let mut interrupted = false;
+    loop {
+        let len = console_read(&mut buf);
+        if len == 0 {
+            break;
+        }
+        let mut ldisc = LDISC.lock();
+        for &c in &buf[..len] {
+            interrupted |= ldisc.receive(c, &mut echo);
+        }
+    }
+    if interrupted {
+        debug!("TTY interrupted");
+        if let Some(handler) = *INTERRUPT_HANDLER.lock() {
+            handler();
+        }
+    }
+}
+
+#[cfg(feature = "irq")]
+fn handle_input_irq() {
+    receive_input();
+    #[cfg(feature = "multitask")]
+    if LDISC.lock().readable() {
+        INPUT_WAIT_QUEUE.notify_all(true);
+    }
+}
+
+fn wait_for_input() {
+    #[cfg(feature = "multitask")]
+    if IRQ_DRIVEN.load(Ordering::Acquire) {
+        INPUT_WAIT_QUEUE.wait_until(|| LDISC.lock().readable());
+        return;
+    }
+    axtask::yield_now();
+}
+
+/// Reads input from the terminal, blocks until some is available.
+///
+/// In canonical mode, at most one line is read, and it returns 0 at end of
+/// file (`VEOF` at the beginning of a line). In non-canonical mode, it reads
+/// the available characters, and does not block if `VMIN` is 0.
+///
+/// If the console input is not interrupt-driven, e.g., the `irq` feature is
+/// disabled, or the console of the platform has no input interrupt (LoongArch
+/// and VisionFive 2), it busy-waits: the console is polled and the CPU is
+/// yielded repeatedly until input is available.
+pub fn read(buf: &mut [u8]) -> usize {
+    if buf.is_empty() {
+        return 0;
+    }
+    loop {
+        if !IRQ_DRIVEN.load(Ordering::Acquire) {
+            receive_input();
+        }
+        if let Some(len) = LDISC.lock().read(buf) {
+            return len;
+        }
+        wait_for_input();
+    }
+}
+
+/// Whether [`read`] would return without blocking.
+pub fn readable() -> bool {
+    if !IRQ_DRIVEN.load(Ordering::Acquire) {
+        receive_input();
+    }
+    LDISC.lock().readable()
+}
+
+/// Writes the given bytes to the terminal.
+pub fn write(buf: &[u8]) {
+    #[cfg(feature = "fbcon-stdout")]
+    axdisplay::fbcon::write_bytes(buf);
+    console_write(buf);
+}
+
+/// Writes logs to the terminal.
+///
+/// It falls back to the UART if the console port is in use, e.g., when
+/// logging from the console driver.
+pub fn write_log(buf: &[u8]) {
+    #[cfg(feature = "fbcon-log")]
+    axdisplay::fbcon::write_bytes(buf);
+    console_write(buf);
+}
+
+/// Returns the current terminal attributes.
+pub fn termios() -> Termios {
+    *LDISC.lock().termios()
+}
+
+/// Updates the terminal attributes.
+pub fn set_termios(termios: &Termios) {
+    LDISC.lock().set_termios(termios);
+    // Readers may be able to proceed in the new mode.
+    #[cfg(feature = "multitask")]
+    INPUT_WAIT_QUEUE.notify_all(false);
+}
+
+/// Discards all received but unread input.
+pub fn flush_input() {
+    LDISC.lock().flush_input();
+}
+
+/// Sets the function called when the interrupt character (`VINTR`) is
+/// received with `ISIG` set.
+///
+/// It may be called in the interrupt context. The POSIX API sets it to
+/// terminate the application.
+pub fn set_interrupt_handler(handler: fn()) {
+    *INTERRUPT_HANDLER.lock() = Some(handler);
+}
+
+/// Initializes the terminal, and receives input in the console interrupt
+/// handler if possible.
+pub fn init() {
+    info!("Initialize TTY...");
+    #[cfg(feature = "hvc")]
+    if HVC_CONSOLE.load(Ordering::Acquire) {
+        return;
+    }
+    #[cfg(feature = "irq")]
+    if axhal::console::register_input_handler(handle_input_irq) {
+        IRQ_DRIVEN.store(true, Ordering::Release);
+        info!("  console input is interrupt-driven.");
+    }
+}
+
+/// Starts the framebuffer text console on the first display, if any, to show
+/// the console output in addition to the UART.
+///
+/// It should be called after the graphics subsystem is initialized.
+#[cfg(any(feature = "fbcon-log", feature = "fbcon-stdout"))]
+pub fn init_fbcon() {
+    info!("Initialize framebuffer console...");
+    if axdisplay::device_count() == 0 {
+        return;
+    }
+    match axdisplay::fbcon::init(0) {
+        Ok(()) => info!(
+            "  show console output on {}.",
+            axdisplay::device_name(0).unwrap()
+        ),
+        Err(e) => warn!("  failed to start framebuffer console: {:?}", e),
+    }
+}
+
+/// Makes the first registered VirtIO console port, if any, the console, whose
+/// input is polled when reading.
+///
+/// It should be called before [`init`].
+#[cfg(feature = "hvc")]
+pub fn init_hvc() {
+    info!("Initialize console ports...");
+    if hvc::init() > 0 {
+        info!("  switch console to {}.", hvc::port_name(0).unwrap());
+        HVC_CONSOLE.store(true, Ordering::Release);
+    }
+}
@@ -0,0 +1,129 @@
+//! Terminal attributes, with the same flag values and control character
+//! indices as Linux.
+
+bitflags::bitflags! {
+    /// Input modes (`c_iflag`).
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct InputFlags: u32 {
+        /// Translate NL to CR on input.
+        const INLCR = 0o100;
+        /// Ignore CR on input.
+        const IGNCR = 0o200;
+        /// Translate CR to NL on input.
+        const ICRNL = 0o400;
+    }
+}
+
+bitflags::bitflags! {
+    /// Local modes (`c_lflag`).
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct LocalFlags: u32 {
+        /// Generate an interrupt on the `VINTR` character.
+        const ISIG = 0o1;
+        /// Canonical mode, i.e., input is made available line by line, and
+        /// can be edited with the `VERASE` and `VKILL` characters.
+        const ICANON = 0o2;
+        /// Echo input characters.
+        const ECHO = 0o10;
+        /// Make `VERASE` erase the preceding character on the screen.
+        const ECHOE = 0o20;
+        /// Make `VKILL` erase the current line on the screen.
+        const ECHOK = 0o40;
+        /// Echo NL even if `ECHO` is not set.
+        const ECHONL = 0o100;
+        /// Echo control characters as `^X`.
+        const ECHOCTL = 0o1000;
+    }
+}
+
+/// The number of control characters.
+pub const NCCS: usize = 32;
+
+/// Index of the interrupt character (`^C`) in [`Termios::cc`].
+pub const VINTR: usize = 0;
+/// Index of the quit character (`^\`) in [`Termios::cc`]. It is not handled.
+pub const VQUIT: usize = 1;
+/// Index of the erase character (`DEL`) in [`Termios::cc`].
+pub const VERASE: usize = 2;
+/// Index of the kill-line character (`^U`) in [`Termios::cc`].
+pub const VKILL: usize = 3;
+/// Index of the end-of-file character (`^D`) in [`Termios::cc`].
+pub const VEOF: usize = 4;
+/// Index of the timeout of non-canonical reads in [`Termios::cc`]. It is not
+/// handled.
+pub const VTIME: usize = 5;
+/// Index of the minimum number of bytes of non-canonical reads in
+/// [`Termios::cc`]. Only whether it is zero (non-blocking) matters.
+pub const VMIN: usize = 6;
+
+/// A control character with this value is disabled.
+pub const VDISABLE: u8 = 0;
+
+/// Terminal attributes.
+///
+/// The output and control modes are kept for applications, but have no
+/// effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Termios {
+    /// Input modes.
+    pub iflag: InputFlags,
+    /// Output modes.
+    pub oflag: u32,
+    /// Control modes.
+    pub cflag: u32,
+    /// Local modes.
+    pub lflag: LocalFlags,
+    /// Control characters.
+    pub cc: [u8; NCCS],
+}
+
+impl Termios {
+    /// Creates the default attributes of Linux, i.e., canonical mode with
+    /// echo.
+    pub const fn new() -> Self {
+        const OPOST: u32 = 0o1;
+        const ONLCR: u32 = 0o4;
+        const B38400: u32 = 0o17;
+        const CS8: u32 = 0o60;
+        const CREAD: u32 = 0o200;
+
+        let mut cc = [VDISABLE; NCCS];
+        cc[VINTR] = 0x03;
+        cc[VQUIT] = 0x1c;
+        cc[VERASE] = 0x7f;
+        cc[VKILL] = 0x15;
+        cc[VEOF] = 0x04;
+        cc[VMIN] = 1;
+        Self {
+            iflag: InputFlags::ICRNL,
+            oflag: OPOST | ONLCR,
+            cflag: B38400 | CS8 | CREAD,
+            lflag: LocalFlags::from_bits_retain(
+                LocalFlags::ISIG.bits()
+                    | LocalFlags::ICANON.bits()
+                    | LocalFlags::ECHO.bits()
+                    | LocalFlags::ECHOE.bits()
+                    | LocalFlags::ECHOK.bits()
+                    | LocalFlags::ECHOCTL.bits(),
+            ),
+            cc,
+        }
+    }
+
+    /// Returns whether the terminal is in canonical mode.
+    pub const fn is_canonical(&self) -> bool {
+        self.lflag.contains(LocalFlags::ICANON)
+    }
+
+    /// Returns whether the given character is the special character at
+    /// `index` of [`Termios::cc`].
+    pub(crate) const fn is_cc(&self, c: u8, index: usize) -> bool {
+        c != VDISABLE && c == self.cc[index]
+    }
+}
+
+impl Default for Termios {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,81 @@
+use std::vec::Vec;
+
+use crate::ldisc::LineDiscipline;
+use crate::termios::{LocalFlags, Termios, VMIN};
+
+fn receive_all(ldisc: &mut LineDiscipline, input: &[u8]) -> (Vec<u8>, bool) {
+    let mut echoed = Vec::new();
+    let mut interrupted = false;
+    for &c in input {
+        interrupted |= ldisc.receive(c, &mut |bytes| echoed.extend_from_slice(bytes));
+    }
+    (echoed, interrupted)
+}
+
+fn read_all(ldisc: &mut LineDiscipline) -> Option<Vec<u8>> {
+    let mut buf = [0; 64];
+    ldisc.read(&mut buf).map(|len| buf[..len].to_vec())
+}
+
+#[test]
+fn test_canonical_line_editing() {
+    let mut ldisc = LineDiscipline::new();
+    let (echoed, _) = receive_all(&mut ldisc, b"lx\x7fs");
+    assert_eq!(echoed, b"lx\x08 \x08s");
+    assert!(!ldisc.readable());
+    assert_eq!(read_all(&mut ldisc), None);
+
+    receive_all(&mut ldisc, b"\r");
+    assert_eq!(read_all(&mut ldisc).unwrap(), b"ls\n");
+    assert!(!ldisc.readable());
+}
+
+#[test]
+fn test_canonical_reads_one_line_at_a_time() {
+    let mut ldisc = LineDiscipline::new();
+    receive_all(&mut ldisc, b"foo\nbar\n\x04");
+    let mut buf = [0; 2];
+    assert_eq!(ldisc.read(&mut buf), Some(2));
+    assert_eq!(&buf, b"fo");
+    assert_eq!(read_all(&mut ldisc).unwrap(), b"o\n");
+    assert_eq!(read_all(&mut ldisc).unwrap(), b"bar\n");
+    // `VEOF` at the beginning of a line.
+    assert_eq!(read_all(&mut ldisc).unwrap(), b"");
+    assert_eq!(read_all(&mut ldisc), None);
+}
+
+#[test]
+fn test_kill_and_interrupt() {
+    let mut ldisc = LineDiscipline::new();
+    let (echoed, _) = receive_all(&mut ldisc, b"ab\x15");
+    assert_eq!(echoed, b"ab\x08 \x08\x08 \x08");
+
+    let (echoed, interrupted) = receive_all(&mut ldisc, b"cd\x03");
+    assert!(interrupted);
+    assert_eq!(echoed, b"cd^C");
+    receive_all(&mut ldisc, b"\n");
+    assert_eq!(read_all(&mut ldisc).unwrap(), b"\n");
+}
+
+#[test]
+fn test_raw_mode() {
+    let mut ldisc = LineDiscipline::new();
+    receive_all(&mut ldisc, b"ab");
+
+    let mut termios = Termios::new();
+    termios.lflag.remove(LocalFlags::ICANON | LocalFlags::ECHO);
+    ldisc.set_termios(&termios);
+    // The pending line becomes readable.
+    assert_eq!(read_all(&mut ldisc).unwrap(), b"ab");
+
+    let (echoed, _) = receive_all(&mut ldisc, b"\x7f\x03");
+    assert!(echoed.is_empty());
+    assert_eq!(read_all(&mut ldisc), None);
+
+    termios.lflag.remove(LocalFlags::ISIG);
+    termios.cc[VMIN] = 0;
+    ldisc.set_termios(&termios);
+    receive_all(&mut ldisc, b"\x03");
+    assert_eq!(read_all(&mut ldisc).unwrap(), b"\x03");
+    assert_eq!(read_all(&mut ldisc).unwrap(), b"");
+}
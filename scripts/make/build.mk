@@ -37,11 +37,33 @@ else ifeq ($(filter $(MAKECMDGOALS),defconfig oldconfig clippy),)
   export RUSTFLAGS
 endif
 
+# Embed the kernel symbol table into the `.ksyms` section of the ELF, for
+# symbolized backtraces. Each line is `<hex address> <name>` of a code symbol,
+# with the Rust symbol hash stripped. The section is a placeholder of a fixed
+# size, so the build fails if the table does not fit in it.
+define embed_ksyms
+  @printf "    $(CYAN_C)Embedding$(END_C) kernel symbols into $(1)\n"
+  @$(NM) -n -C --defined-only $(1) \
+    | awk '$$2 ~ /^[tTwW]$$/ { name = substr($$0, length($$1) + length($$2) + 3); sub(/::h[0-9a-f]+$$/, "", name); print $$1, name }' \
+    > $(1).ksyms
+  @$(OBJCOPY) $(1) --dump-section .ksyms=$(1).ksyms.old
+  @size=$$(wc -c < $(1).ksyms); max=$$(wc -c < $(1).ksyms.old); rm -f $(1).ksyms.old; \
+    if [ $$size -gt $$max ]; then \
+      echo "error: the kernel symbol table ($$size bytes) is larger than the .ksyms section ($$max bytes)," \
+        "increase KSYMS_SIZE in modules/axhal/src/backtrace.rs" >&2; \
+      exit 1; \
+    fi
+  $(call run_cmd,$(OBJCOPY),$(1) --update-section .ksyms=$(1).ksyms)
+endef
+
 _cargo_build: oldconfig
 	@printf "    $(GREEN_C)Building$(END_C) App: $(APP_NAME), Arch: $(ARCH), Platform: $(PLAT_NAME), App type: $(APP_TYPE)\n"
 ifeq ($(APP_TYPE), rust)
 	$(call cargo_build,$(APP),$(AX_FEAT) $(LIB_FEAT) $(APP_FEAT))
 	@cp $(rust_elf) $(OUT_ELF)
+  ifneq ($(findstring backtrace,$(FEATURES)),)
+	$(call embed_ksyms,$(OUT_ELF))
+  endif
 else ifeq ($(APP_TYPE), c)
 	$(call cargo_build,ulib/axlibc,$(AX_FEAT) $(LIB_FEAT))
 endif
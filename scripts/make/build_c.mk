@@ -25,6 +25,10 @@ ifeq ($(MODE), release)
   CFLAGS += -O3
 endif
 
+ifneq ($(findstring backtrace,$(FEATURES)),)
+  CFLAGS += -fno-omit-frame-pointer
+endif
+
 ifeq ($(ARCH), riscv64)
   CFLAGS += -march=rv64gc -mabi=lp64d -mcmodel=medany
 endif
@@ -71,6 +75,9 @@ $(APP)/%.o: $(APP)/%.c $(ulib_hdr)
 $(OUT_ELF): $(libgcc) $(app-objs) $(c_lib) $(rust_lib)
 	@printf "    $(CYAN_C)Linking$(END_C) $(OUT_ELF)\n"
 	$(call run_cmd,$(LD),$(LDFLAGS) $^ -o $@)
+ifneq ($(findstring backtrace,$(FEATURES)),)
+	$(call embed_ksyms,$@)
+endif
 
 $(APP)/axbuild.mk: ;
 
@@ -18,6 +18,10 @@ build_args := \
   $(verbose)
 
 RUSTFLAGS:= -A unsafe_op_in_unsafe_fn
+ifneq ($(findstring backtrace,$(FEATURES)),)
+  # Required by the frame-pointer based stack unwinding
+  RUSTFLAGS += -C force-frame-pointers=yes
+endif
 RUSTFLAGS_LINK_ARGS := -C link-arg=-T$(LD_SCRIPT) -C link-arg=-no-pie -C link-arg=-znostart-stop-gc
 RUSTDOCFLAGS := -Z unstable-options --enable-index-page -D rustdoc::broken_intra_doc_links
 
@@ -78,6 +78,25 @@ qemu_args-$(GRAPHIC) += \
   -device virtio-gpu-$(vdev-suffix) -vga none \
   -serial mon:stdio
 
+qemu_args-$(INPUT) += \
+  -device virtio-keyboard-$(vdev-suffix) \
+  -device virtio-mouse-$(vdev-suffix)
+
+qemu_args-$(HVC) += \
+  -device virtio-serial-$(vdev-suffix) \
+  -chardev pty,id=hvc0 \
+  -device virtconsole,chardev=hvc0
+
+qemu_args-$(RNG) += \
+  -object rng-random,id=rng0,filename=/dev/urandom \
+  -device virtio-rng-$(vdev-suffix),rng=rng0
+
+ifneq ($(VIRTFS),)
+  qemu_args-y += \
+    -fsdev local,id=fsdev0,path=$(VIRTFS),security_model=none \
+    -device virtio-9p-$(vdev-suffix),fsdev=fsdev0,mount_tag=$(VIRTFS_TAG)
+endif
+
 ifeq ($(GRAPHIC), n)
   qemu_args-y += -nographic
 endif
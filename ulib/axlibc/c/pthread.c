@@ -3,78 +3,45 @@
 #include <errno.h>
 #include <limits.h>
 #include <pthread.h>
+#include <sched.h>
 #include <stdio.h>
+#include <string.h>
 #include <unistd.h>
 
-int pthread_setcancelstate(int new, int *old)
+int pthread_condattr_init(pthread_condattr_t *a)
 {
-    unimplemented();
+    *a = (pthread_condattr_t){0};
     return 0;
 }
 
-int pthread_setcanceltype(int new, int *old)
+int pthread_condattr_destroy(pthread_condattr_t *a)
 {
-    unimplemented();
     return 0;
 }
 
-// TODO
-void pthread_testcancel(void)
+int pthread_condattr_getclock(const pthread_condattr_t *restrict a, clockid_t *restrict clk)
 {
-    unimplemented();
-    return;
-}
-
-// TODO
-int pthread_cancel(pthread_t t)
-{
-    unimplemented();
+    *clk = a->__attr & 0x7fffffff;
     return 0;
 }
 
-// TODO
-int pthread_mutex_trylock(pthread_mutex_t *m)
+int pthread_condattr_setclock(pthread_condattr_t *a, clockid_t clk)
 {
-    unimplemented();
-    return 0;
-}
-
-// TODO
-int pthread_setname_np(pthread_t thread, const char *name)
-{
-    unimplemented();
-    return 0;
-}
-
-int pthread_cond_init(pthread_cond_t *restrict c, const pthread_condattr_t *restrict a)
-{
-    *c = (pthread_cond_t){0};
-    if (a) {
-        c->_c_clock = a->__attr & 0x7fffffff;
-        if (a->__attr >> 31)
-            c->_c_shared = (void *)-1;
-    }
-    return 0;
-}
-
-// TODO
-int pthread_cond_signal(pthread_cond_t *__cond)
-{
-    unimplemented();
+    if (clk != CLOCK_REALTIME && clk != CLOCK_MONOTONIC)
+        return EINVAL;
+    a->__attr &= 0x80000000;
+    a->__attr |= clk;
     return 0;
 }
 
-// TODO
-int pthread_cond_wait(pthread_cond_t *__restrict__ __cond, pthread_mutex_t *__restrict__ __mutex)
+int pthread_rwlockattr_init(pthread_rwlockattr_t *a)
 {
-    unimplemented();
+    *a = (pthread_rwlockattr_t){0};
     return 0;
 }
 
-// TODO
-int pthread_cond_broadcast(pthread_cond_t *c)
+int pthread_rwlockattr_destroy(pthread_rwlockattr_t *a)
 {
-    unimplemented();
     return 0;
 }
 
@@ -92,6 +59,11 @@ int pthread_attr_init(pthread_attr_t *a)
     return 0;
 }
 
+int pthread_attr_destroy(pthread_attr_t *a)
+{
+    return 0;
+}
+
 int pthread_attr_getstacksize(const pthread_attr_t *restrict a, size_t *restrict size)
 {
     *size = a->_a_stacksize;
@@ -107,4 +79,45 @@ int pthread_attr_setstacksize(pthread_attr_t *a, size_t size)
     return 0;
 }
 
+int pthread_attr_getdetachstate(const pthread_attr_t *a, int *state)
+{
+    *state = a->_a_detach;
+    return 0;
+}
+
+int pthread_attr_setdetachstate(pthread_attr_t *a, int state)
+{
+    if (state != PTHREAD_CREATE_JOINABLE && state != PTHREAD_CREATE_DETACHED)
+        return EINVAL;
+    a->_a_detach = state;
+    return 0;
+}
+
+// Only the first `sizeof(unsigned long) * 8` CPUs can be set in the attribute.
+int pthread_attr_getaffinity_np(const pthread_attr_t *a, size_t size, cpu_set_t *set)
+{
+    if (size < sizeof(unsigned long))
+        return EINVAL;
+    memset(set, 0, size);
+    if (a->_a_cpumask)
+        ((unsigned long *)set)[0] = a->_a_cpumask;
+    else
+        memset(set, 0xff, sizeof(unsigned long));
+    return 0;
+}
+
+int pthread_attr_setaffinity_np(pthread_attr_t *a, size_t size, const cpu_set_t *set)
+{
+    if (size < sizeof(unsigned long))
+        return EINVAL;
+    for (size_t i = sizeof(unsigned long); i < size; i++) {
+        if (((const unsigned char *)set)[i])
+            return EINVAL;
+    }
+    if (!((const unsigned long *)set)[0])
+        return EINVAL;
+    a->_a_cpumask = ((const unsigned long *)set)[0];
+    return 0;
+}
+
 #endif // AX_CONFIG_MULTITASK
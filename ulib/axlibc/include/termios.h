@@ -1,8 +1,60 @@
 #ifndef _TERMIOS_H
 #define _TERMIOS_H
 
+typedef unsigned char cc_t;
+typedef unsigned int speed_t;
+typedef unsigned int tcflag_t;
+
+#define NCCS 32
+
+struct termios {
+    tcflag_t c_iflag;
+    tcflag_t c_oflag;
+    tcflag_t c_cflag;
+    tcflag_t c_lflag;
+    cc_t c_line;
+    cc_t c_cc[NCCS];
+    speed_t __c_ispeed;
+    speed_t __c_ospeed;
+};
+
 struct winsize {
     unsigned short ws_row, ws_col, ws_xpixel, ws_ypixel;
 };
 
+#define VINTR  0
+#define VQUIT  1
+#define VERASE 2
+#define VKILL  3
+#define VEOF   4
+#define VTIME  5
+#define VMIN   6
+
+#define INLCR 0000100
+#define IGNCR 0000200
+#define ICRNL 0000400
+
+#define OPOST 0000001
+#define ONLCR 0000004
+
+#define CBAUD  0010017
+#define B38400 0000017
+#define CS8    0000060
+#define CREAD  0000200
+
+#define ISIG    0000001
+#define ICANON  0000002
+#define ECHO    0000010
+#define ECHOE   0000020
+#define ECHOK   0000040
+#define ECHONL  0000100
+#define ECHOCTL 0001000
+
+#define TCSANOW   0
+#define TCSADRAIN 1
+#define TCSAFLUSH 2
+
+int tcgetattr(int, struct termios *);
+int tcsetattr(int, int, const struct termios *);
+
 #endif // _TERMIOS_H
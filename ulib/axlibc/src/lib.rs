@@ -14,7 +14,18 @@
 //!     - `multitask`: Enable multi-threading support.
 //! - Upperlayer stacks
 //!     - `fs`: Enable file system support.
+//!     - `fs-9p`: Mount directories shared by the host over 9P (virtio-9p).
 //!     - `net`: Enable networking support.
+//!     - `input`: Enable input devices, which are opened as
+//!       `/dev/input/eventN` (requires `fs`).
+//!     - `display`: Enable displays, which are opened as `/dev/fbN` (requires
+//!       `fs`).
+//!     - `fbcon`: Show logs and standard output on the framebuffer text console.
+//!     - `fbcon-log`, `fbcon-stdout`: Show only logs, or only standard output,
+//!       on the framebuffer text console.
+//!     - `console`: Enable VirtIO console ports, which are opened as
+//!       `/dev/hvcN` (requires `fs`). The first port becomes the console.
+//!     - `rng`: Use hardware random number generators as entropy sources.
 //! - Lib C functions
 //!     - `fd`: Enable file descriptor table.
 //!     - `pipe`: Enable pipe support.
@@ -74,6 +85,7 @@ mod rand;
 mod resource;
 mod setjmp;
 mod sys;
+mod termios;
 mod time;
 mod unistd;
 
@@ -84,9 +96,10 @@ pub use self::io::{read, writev};
 pub use self::errno::strerror;
 pub use self::mktime::mktime;
 pub use self::rand::{rand, random, srand};
-pub use self::resource::{getrlimit, setrlimit};
+pub use self::resource::{getrlimit, getrusage, setrlimit};
 pub use self::setjmp::{longjmp, setjmp};
-pub use self::sys::sysconf;
+pub use self::sys::{reboot, sysconf};
+pub use self::termios::{tcgetattr, tcsetattr};
 pub use self::time::{clock_gettime, nanosleep};
 pub use self::unistd::{abort, exit, getpid};
 
@@ -108,9 +121,30 @@ pub use self::net::{
 };
 
 #[cfg(feature = "multitask")]
-pub use self::pthread::{pthread_create, pthread_exit, pthread_join, pthread_self};
+pub use self::pthread::{
+    pthread_cancel, pthread_create, pthread_detach, pthread_exit, pthread_join, pthread_self,
+    pthread_setcancelstate, pthread_setcanceltype, pthread_setname_np, pthread_testcancel,
+};
+#[cfg(feature = "multitask")]
+pub use self::pthread::{
+    pthread_cond_broadcast, pthread_cond_destroy, pthread_cond_init, pthread_cond_signal,
+    pthread_cond_timedwait, pthread_cond_wait,
+};
+#[cfg(feature = "multitask")]
+pub use self::pthread::{
+    pthread_getspecific, pthread_key_create, pthread_key_delete, pthread_once, pthread_setspecific,
+};
+#[cfg(feature = "multitask")]
+pub use self::pthread::{
+    pthread_mutex_destroy, pthread_mutex_init, pthread_mutex_lock, pthread_mutex_trylock,
+    pthread_mutex_unlock,
+};
 #[cfg(feature = "multitask")]
-pub use self::pthread::{pthread_mutex_init, pthread_mutex_lock, pthread_mutex_unlock};
+pub use self::pthread::{
+    pthread_rwlock_destroy, pthread_rwlock_init, pthread_rwlock_rdlock, pthread_rwlock_timedrdlock,
+    pthread_rwlock_timedwrlock, pthread_rwlock_tryrdlock, pthread_rwlock_trywrlock,
+    pthread_rwlock_unlock, pthread_rwlock_wrlock,
+};
 
 #[cfg(feature = "pipe")]
 pub use self::pipe::pipe;
@@ -1,6 +1,9 @@
-use crate::{ctypes, utils::e};
+use crate::ctypes;
 use arceos_posix_api as api;
-use core::ffi::{c_int, c_void};
+use core::ffi::{c_char, c_int, c_void};
+
+// Unlike most libc functions, pthread functions return the error number
+// directly instead of setting `errno`.
 
 /// Returns the `pthread` struct of current thread.
 #[unsafe(no_mangle)]
@@ -19,7 +22,7 @@ pub unsafe extern "C" fn pthread_create(
     start_routine: extern "C" fn(arg: *mut c_void) -> *mut c_void,
     arg: *mut c_void,
 ) -> c_int {
-    e(api::sys_pthread_create(res, attr, start_routine, arg))
+    -api::sys_pthread_create(res, attr, start_routine, arg)
 }
 
 /// Exits the current thread. The value `retval` will be returned to the joiner.
@@ -34,7 +37,7 @@ pub unsafe extern "C" fn pthread_join(
     thread: ctypes::pthread_t,
     retval: *mut *mut c_void,
 ) -> c_int {
-    e(api::sys_pthread_join(thread, retval))
+    -api::sys_pthread_join(thread, retval)
 }
 
 /// Initialize a mutex.
@@ -43,17 +46,216 @@ pub unsafe extern "C" fn pthread_mutex_init(
     mutex: *mut ctypes::pthread_mutex_t,
     attr: *const ctypes::pthread_mutexattr_t,
 ) -> c_int {
-    e(api::sys_pthread_mutex_init(mutex, attr))
+    -api::sys_pthread_mutex_init(mutex, attr)
 }
 
 /// Lock the given mutex.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn pthread_mutex_lock(mutex: *mut ctypes::pthread_mutex_t) -> c_int {
-    e(api::sys_pthread_mutex_lock(mutex))
+    -api::sys_pthread_mutex_lock(mutex)
 }
 
 /// Unlock the given mutex.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn pthread_mutex_unlock(mutex: *mut ctypes::pthread_mutex_t) -> c_int {
-    e(api::sys_pthread_mutex_unlock(mutex))
+    -api::sys_pthread_mutex_unlock(mutex)
+}
+
+/// Marks the given thread as detached.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_detach(thread: ctypes::pthread_t) -> c_int {
+    -api::sys_pthread_detach(thread)
+}
+
+/// Sends a cancellation request to the given thread.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_cancel(thread: ctypes::pthread_t) -> c_int {
+    -api::sys_pthread_cancel(thread)
+}
+
+/// Sets the cancelability state of the current thread.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_setcancelstate(state: c_int, oldstate: *mut c_int) -> c_int {
+    -api::sys_pthread_setcancelstate(state, oldstate)
+}
+
+/// Sets the cancelability type of the current thread.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_setcanceltype(ty: c_int, oldtype: *mut c_int) -> c_int {
+    -api::sys_pthread_setcanceltype(ty, oldtype)
+}
+
+/// Creates a cancellation point in the current thread.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_testcancel() {
+    api::sys_pthread_testcancel()
+}
+
+/// Sets the name of the given thread.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_setname_np(
+    thread: ctypes::pthread_t,
+    name: *const c_char,
+) -> c_int {
+    -api::sys_pthread_setname_np(thread, name)
+}
+
+/// Try to lock the given mutex.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_mutex_trylock(mutex: *mut ctypes::pthread_mutex_t) -> c_int {
+    -api::sys_pthread_mutex_trylock(mutex)
+}
+
+/// Destroy the given mutex.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_mutex_destroy(mutex: *mut ctypes::pthread_mutex_t) -> c_int {
+    -api::sys_pthread_mutex_destroy(mutex)
+}
+
+/// Initialize a condition variable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_cond_init(
+    cond: *mut ctypes::pthread_cond_t,
+    attr: *const ctypes::pthread_condattr_t,
+) -> c_int {
+    -api::sys_pthread_cond_init(cond, attr)
+}
+
+/// Destroy a condition variable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_cond_destroy(cond: *mut ctypes::pthread_cond_t) -> c_int {
+    -api::sys_pthread_cond_destroy(cond)
+}
+
+/// Wait on the condition variable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_cond_wait(
+    cond: *mut ctypes::pthread_cond_t,
+    mutex: *mut ctypes::pthread_mutex_t,
+) -> c_int {
+    -api::sys_pthread_cond_wait(cond, mutex)
+}
+
+/// Wait on the condition variable until the absolute time `abstime`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_cond_timedwait(
+    cond: *mut ctypes::pthread_cond_t,
+    mutex: *mut ctypes::pthread_mutex_t,
+    abstime: *const ctypes::timespec,
+) -> c_int {
+    -api::sys_pthread_cond_timedwait(cond, mutex, abstime)
+}
+
+/// Wake up one thread waiting on the condition variable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_cond_signal(cond: *mut ctypes::pthread_cond_t) -> c_int {
+    -api::sys_pthread_cond_signal(cond)
+}
+
+/// Wake up all threads waiting on the condition variable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_cond_broadcast(cond: *mut ctypes::pthread_cond_t) -> c_int {
+    -api::sys_pthread_cond_broadcast(cond)
+}
+
+/// Initialize a rwlock.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_rwlock_init(
+    rwlock: *mut ctypes::pthread_rwlock_t,
+    attr: *const ctypes::pthread_rwlockattr_t,
+) -> c_int {
+    -api::sys_pthread_rwlock_init(rwlock, attr)
+}
+
+/// Destroy a rwlock.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_rwlock_destroy(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    -api::sys_pthread_rwlock_destroy(rwlock)
+}
+
+/// Lock the given rwlock for reading.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_rwlock_rdlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    -api::sys_pthread_rwlock_rdlock(rwlock)
+}
+
+/// Try to lock the given rwlock for reading.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_rwlock_tryrdlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    -api::sys_pthread_rwlock_tryrdlock(rwlock)
+}
+
+/// Lock the given rwlock for reading until the absolute time `abstime`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_rwlock_timedrdlock(
+    rwlock: *mut ctypes::pthread_rwlock_t,
+    abstime: *const ctypes::timespec,
+) -> c_int {
+    -api::sys_pthread_rwlock_timedrdlock(rwlock, abstime)
+}
+
+/// Lock the given rwlock for writing.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_rwlock_wrlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    -api::sys_pthread_rwlock_wrlock(rwlock)
+}
+
+/// Try to lock the given rwlock for writing.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_rwlock_trywrlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    -api::sys_pthread_rwlock_trywrlock(rwlock)
+}
+
+/// Lock the given rwlock for writing until the absolute time `abstime`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_rwlock_timedwrlock(
+    rwlock: *mut ctypes::pthread_rwlock_t,
+    abstime: *const ctypes::timespec,
+) -> c_int {
+    -api::sys_pthread_rwlock_timedwrlock(rwlock, abstime)
+}
+
+/// Unlock the given rwlock.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_rwlock_unlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    -api::sys_pthread_rwlock_unlock(rwlock)
+}
+
+/// Run `init_routine` exactly once.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_once(
+    once_control: *mut ctypes::pthread_once_t,
+    init_routine: extern "C" fn(),
+) -> c_int {
+    -api::sys_pthread_once(once_control, init_routine)
+}
+
+/// Create a thread-specific data key.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_key_create(
+    key: *mut ctypes::pthread_key_t,
+    destructor: Option<extern "C" fn(*mut c_void)>,
+) -> c_int {
+    -api::sys_pthread_key_create(key, destructor)
+}
+
+/// Delete a thread-specific data key.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_key_delete(key: ctypes::pthread_key_t) -> c_int {
+    -api::sys_pthread_key_delete(key)
+}
+
+/// Get the value of the key in the current thread.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_getspecific(key: ctypes::pthread_key_t) -> *mut c_void {
+    api::sys_pthread_getspecific(key)
+}
+
+/// Set the value of the key in the current thread.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_setspecific(
+    key: ctypes::pthread_key_t,
+    value: *const c_void,
+) -> c_int {
+    -api::sys_pthread_setspecific(key, value)
 }
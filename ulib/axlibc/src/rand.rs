@@ -1,30 +1,48 @@
 //! Random number generator.
+//!
+//! Unlike the C standard, which requires the sequence without `srand` to be
+//! the same as with `srand(1)`, the generator is seeded from the kernel
+//! entropy pool until `srand` is called.
 
 use core::{
-    ffi::{c_int, c_long, c_uint},
-    sync::atomic::{AtomicU64, Ordering::SeqCst},
+    ffi::{c_int, c_long, c_uint, c_void},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering::SeqCst},
 };
 
+use arceos_posix_api::sys_getrandom;
+
 static SEED: AtomicU64 = AtomicU64::new(0xa2ce_a2ce);
 
+/// Whether the seed has been set, by `srand` or from the entropy pool.
+static SEEDED: AtomicBool = AtomicBool::new(false);
+
+fn next_seed() -> u64 {
+    if !SEEDED.swap(true, SeqCst) {
+        let mut seed = 0u64;
+        if sys_getrandom(&mut seed as *mut u64 as *mut c_void, 8, 0) == 8 {
+            SEED.store(seed, SeqCst);
+        }
+    }
+    let new_seed = SEED.load(SeqCst).wrapping_mul(6364136223846793005) + 1;
+    SEED.store(new_seed, SeqCst);
+    new_seed
+}
+
 /// Sets the seed for the random number generator.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn srand(seed: c_uint) {
+    SEEDED.store(true, SeqCst);
     SEED.store(seed.wrapping_sub(1) as u64, SeqCst);
 }
 
 /// Returns a 32-bit unsigned pseudo random interger.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn rand() -> c_int {
-    let new_seed = SEED.load(SeqCst).wrapping_mul(6364136223846793005) + 1;
-    SEED.store(new_seed, SeqCst);
-    (new_seed >> 33) as c_int
+    (next_seed() >> 33) as c_int
 }
 
 /// Returns a 64-bit unsigned pseudo random number.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn random() -> c_long {
-    let new_seed = SEED.load(SeqCst).wrapping_mul(6364136223846793005) + 1;
-    SEED.store(new_seed, SeqCst);
-    new_seed as c_long
+    next_seed() as c_long
 }
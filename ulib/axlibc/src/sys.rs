@@ -1,5 +1,7 @@
-use arceos_posix_api::sys_sysconf;
-use core::ffi::{c_int, c_long};
+use arceos_posix_api::{sys_getrandom, sys_reboot, sys_sysconf};
+use core::ffi::{c_int, c_long, c_uint, c_void};
+
+use crate::ctypes;
 
 /// Return system configuration infomation
 ///
@@ -8,3 +10,19 @@ use core::ffi::{c_int, c_long};
 pub unsafe extern "C" fn sysconf(name: c_int) -> c_long {
     sys_sysconf(name)
 }
+
+/// Reboot or power off the whole system
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn reboot(cmd: c_int) -> c_int {
+    sys_reboot(cmd)
+}
+
+/// Fill the buffer with random bytes from the kernel entropy pool
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn getrandom(
+    buf: *mut c_void,
+    buflen: usize,
+    flags: c_uint,
+) -> ctypes::ssize_t {
+    sys_getrandom(buf, buflen, flags)
+}
@@ -0,0 +1,21 @@
+use core::ffi::c_int;
+
+use arceos_posix_api::{sys_tcgetattr, sys_tcsetattr};
+
+use crate::{ctypes, utils::e};
+
+/// Get the attributes of the terminal referred to by `fd`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tcgetattr(fd: c_int, termios_p: *mut ctypes::termios) -> c_int {
+    e(unsafe { sys_tcgetattr(fd, termios_p) })
+}
+
+/// Set the attributes of the terminal referred to by `fd`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tcsetattr(
+    fd: c_int,
+    optional_actions: c_int,
+    termios_p: *const ctypes::termios,
+) -> c_int {
+    e(unsafe { sys_tcsetattr(fd, optional_actions, termios_p) })
+}
@@ -0,0 +1,122 @@
+//! Graphics displays, which are drawn by writing their framebuffers directly.
+//!
+//! Changes to a framebuffer are shown on the screen after being flushed, either
+//! entirely or only the changed (dirty) rectangles. With double buffering, the
+//! framebuffer is the back buffer, which is shown by [`Display::flip`].
+
+use crate::io;
+use crate::vec::Vec;
+
+use arceos_api::display as api;
+
+#[doc(no_inline)]
+pub use arceos_api::display::{
+    AxDisplayInfo as DisplayInfo, AxDisplayMode as DisplayMode, AxPixelFormat as PixelFormat,
+    AxRect as Rect,
+};
+
+/// The width and height of cursor images, in pixels.
+pub const CURSOR_SIZE: u32 = 64;
+
+/// A graphics display.
+///
+/// All handles of the same display share its framebuffer.
+pub struct Display {
+    id: usize,
+}
+
+impl Display {
+    /// Returns the number of displays. Their IDs are `0..count()`.
+    pub fn count() -> usize {
+        api::ax_display_count()
+    }
+
+    /// Opens the display with the given ID.
+    pub fn open(id: usize) -> io::Result<Self> {
+        api::ax_display_name(id)?;
+        Ok(Self { id })
+    }
+
+    /// Returns the ID of this display.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Returns the name of this display.
+    pub fn name(&self) -> &'static str {
+        api::ax_display_name(self.id).unwrap()
+    }
+
+    /// Gets the framebuffer information.
+    pub fn info(&self) -> DisplayInfo {
+        api::ax_display_info(self.id).unwrap()
+    }
+
+    /// Returns the current mode.
+    pub fn mode(&self) -> DisplayMode {
+        api::ax_display_mode(self.id).unwrap()
+    }
+
+    /// Returns the supported modes, the preferred one first.
+    pub fn modes(&self) -> Vec<DisplayMode> {
+        api::ax_display_modes(self.id).unwrap()
+    }
+
+    /// Switches to another mode, which reallocates the framebuffer.
+    pub fn set_mode(&mut self, mode: DisplayMode) -> io::Result<()> {
+        api::ax_display_set_mode(self.id, mode)
+    }
+
+    /// Returns the framebuffer to draw into, whose rows are
+    /// [`DisplayMode::stride`] bytes long.
+    pub fn framebuffer(&mut self) -> &mut [u8] {
+        let info = self.info();
+        unsafe { core::slice::from_raw_parts_mut(info.fb_base_vaddr as *mut u8, info.fb_size) }
+    }
+
+    /// Shows the whole framebuffer on the screen.
+    pub fn flush(&mut self) -> io::Result<()> {
+        api::ax_display_flush(self.id)
+    }
+
+    /// Shows the given rectangles of the framebuffer on the screen.
+    pub fn flush_rects(&mut self, rects: &[Rect]) -> io::Result<()> {
+        api::ax_display_flush_rects(self.id, rects)
+    }
+
+    /// Enables or disables double buffering.
+    ///
+    /// Returns [`Unsupported`](io::Error::Unsupported) if the display does not
+    /// support it.
+    pub fn set_double_buffered(&mut self, enabled: bool) -> io::Result<()> {
+        api::ax_display_set_double_buffered(self.id, enabled)
+    }
+
+    /// Whether double buffering is enabled.
+    pub fn is_double_buffered(&self) -> bool {
+        api::ax_display_is_double_buffered(self.id).unwrap()
+    }
+
+    /// Shows the whole framebuffer on the screen. If double buffering is
+    /// enabled, the back buffer becomes the front one and vice versa.
+    pub fn flip(&mut self) -> io::Result<()> {
+        api::ax_display_flip(self.id)
+    }
+
+    /// Whether the display has a hardware cursor.
+    pub fn has_cursor(&self) -> bool {
+        api::ax_display_has_cursor(self.id).unwrap()
+    }
+
+    /// Sets the cursor image of [`CURSOR_SIZE`] x [`CURSOR_SIZE`] pixels in
+    /// [`PixelFormat::Argb8888`], with the hot spot at (`hot_x`, `hot_y`).
+    /// Hides the cursor if `image` is `None`.
+    pub fn set_cursor(&mut self, image: Option<&[u32]>, hot_x: u32, hot_y: u32) -> io::Result<()> {
+        api::ax_display_set_cursor(self.id, image, hot_x, hot_y)
+    }
+
+    /// Moves the hot spot of the cursor to (`x`, `y`).
+    pub fn move_cursor(&mut self, x: u32, y: u32) -> io::Result<()> {
+        api::ax_display_move_cursor(self.id, x, y)
+    }
+}
@@ -0,0 +1,76 @@
+//! Asynchronous values and a lightweight runtime to run them.
+//!
+//! Futures are run by an [`Executor`] on the current thread, which sleeps when
+//! no future can make progress. Many futures, such as the asynchronous
+//! sockets in [`net`](crate::net), can be multiplexed on a single thread.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use std::future::{Executor, sleep};
+//! use std::time::Duration;
+//!
+//! let executor = Executor::new();
+//! let spawner = executor.spawner();
+//! executor.block_on(async {
+//!     let handle = spawner.spawn(async {
+//!         sleep(Duration::from_millis(10)).await;
+//!         42
+//!     });
+//!     assert_eq!(handle.await, 42);
+//! });
+//! ```
+
+#[doc(no_inline)]
+pub use core::future::*;
+
+use arceos_api::task as api;
+use arceos_api::time::AxTimeValue;
+
+use crate::time::Duration;
+
+/// An executor that runs many futures concurrently on the current thread.
+///
+/// Spawned futures only make progress while [`Executor::block_on`] is
+/// running.
+pub use api::AxExecutor as Executor;
+
+/// A handle to spawn futures on an [`Executor`], which can be sent to other
+/// threads.
+pub use api::AxSpawner as Spawner;
+
+/// A handle to await the output of a spawned future.
+pub use api::AxJoinHandle as JoinHandle;
+
+/// A future that completes at a deadline, returned by [`sleep`] and
+/// [`sleep_until`].
+pub use api::AxSleep as Sleep;
+
+/// A future with a deadline, returned by [`timeout`].
+pub use api::AxTimeout as Timeout;
+
+/// The error returned by [`Timeout`] when the deadline has passed.
+pub use api::AxTimedOut as TimedOut;
+
+/// Runs a future to completion on the current thread.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    Executor::new().block_on(future)
+}
+
+/// Returns a future that completes after `dur` has elapsed.
+pub fn sleep(dur: Duration) -> Sleep {
+    sleep_until(arceos_api::time::ax_wall_time() + dur)
+}
+
+/// Returns a future that completes at `deadline`.
+pub fn sleep_until(deadline: AxTimeValue) -> Sleep {
+    api::ax_sleep_until_async(deadline)
+}
+
+/// Requires `future` to complete within `dur`.
+///
+/// If the future does not complete in time, the returned future resolves to
+/// [`Err(TimedOut)`](TimedOut), and `future` is dropped.
+pub fn timeout<F: Future>(dur: Duration, future: F) -> Timeout<F> {
+    Timeout::new(future, arceos_api::time::ax_wall_time() + dur)
+}
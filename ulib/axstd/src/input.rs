@@ -0,0 +1,95 @@
+//! Input devices, such as keyboards, mice and tablets.
+//!
+//! Events follow the Linux input event codes, like reading
+//! `/dev/input/eventN` on Linux.
+
+use crate::io;
+
+use arceos_api::input as api;
+
+#[doc(no_inline)]
+pub use arceos_api::input::AxInputEvent as InputEvent;
+
+/// Synchronization event, which separates batches of events.
+pub const EV_SYN: u16 = 0x00;
+/// Key or button state change.
+pub const EV_KEY: u16 = 0x01;
+/// Relative axis change (e.g., mouse motion or wheel).
+pub const EV_REL: u16 = 0x02;
+/// Absolute axis change (e.g., tablet or touchscreen coordinates).
+pub const EV_ABS: u16 = 0x03;
+
+/// An input device, which events are read from.
+///
+/// All handles of the same device share its event queue.
+pub struct InputDevice {
+    id: usize,
+    nonblocking: bool,
+}
+
+impl InputDevice {
+    /// Returns the number of input devices. Their IDs are `0..count()`.
+    pub fn count() -> usize {
+        api::ax_input_device_count()
+    }
+
+    /// Opens the input device with the given ID.
+    pub fn open(id: usize) -> io::Result<Self> {
+        api::ax_input_device_name(id)?;
+        Ok(Self {
+            id,
+            nonblocking: false,
+        })
+    }
+
+    /// Returns the ID of this device.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Returns the name of this device.
+    pub fn name(&self) -> &'static str {
+        api::ax_input_device_name(self.id).unwrap()
+    }
+
+    /// Whether this device may report events of the given type, e.g.,
+    /// [`EV_KEY`].
+    pub fn has_event_type(&self, event_type: u16) -> bool {
+        api::ax_input_has_event_type(self.id, event_type).unwrap()
+    }
+
+    /// Moves this device into or out of nonblocking mode.
+    ///
+    /// In nonblocking mode, reading events returns
+    /// [`WouldBlock`](io::Error::WouldBlock) if there are no events.
+    pub fn set_nonblocking(&mut self, nonblocking: bool) {
+        self.nonblocking = nonblocking;
+    }
+
+    /// Whether there are queued events, i.e., reading events does not block.
+    pub fn has_events(&self) -> bool {
+        api::ax_input_has_events(self.id).unwrap()
+    }
+
+    /// Reads events into `buf`, and returns the number of events read.
+    ///
+    /// If there are no events, it blocks until some arrive, unless this
+    /// device is in nonblocking mode.
+    pub fn read_events(&self, buf: &mut [InputEvent]) -> io::Result<usize> {
+        api::ax_read_input_events(self.id, buf, self.nonblocking)
+    }
+
+    /// Reads one event.
+    ///
+    /// See [`read_events`](Self::read_events) for the blocking behavior.
+    pub fn read_event(&self) -> io::Result<InputEvent> {
+        let mut event = [InputEvent {
+            time: Default::default(),
+            event_type: EV_SYN,
+            code: 0,
+            value: 0,
+        }];
+        self.read_events(&mut event)?;
+        Ok(event[0])
+    }
+}
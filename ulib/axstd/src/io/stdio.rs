@@ -8,17 +8,9 @@ struct StdinRaw;
 struct StdoutRaw;
 
 impl Read for StdinRaw {
-    // Non-blocking read, returns number of bytes read.
+    // Blocks until some input is available, returns number of bytes read.
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        let mut read_len = 0;
-        while read_len < buf.len() {
-            let len = arceos_api::stdio::ax_console_read_bytes(buf[read_len..].as_mut())?;
-            if len == 0 {
-                break;
-            }
-            read_len += len;
-        }
-        Ok(read_len)
+        arceos_api::stdio::ax_console_read_bytes(buf)
     }
 }
 
@@ -64,20 +56,9 @@ impl Stdin {
 }
 
 impl Read for Stdin {
-    // Block until at least one byte is read.
+    // Block until at least one byte is read, or the end of file is reached.
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        let read_len = self.inner.lock().read(buf)?;
-        if buf.is_empty() || read_len > 0 {
-            return Ok(read_len);
-        }
-        // try again until we got something
-        loop {
-            let read_len = self.inner.lock().read(buf)?;
-            if read_len > 0 {
-                return Ok(read_len);
-            }
-            crate::thread::yield_now();
-        }
+        self.inner.lock().read(buf)
     }
 }
 
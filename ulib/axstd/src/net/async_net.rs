@@ -0,0 +1,187 @@
+use core::future::poll_fn;
+use core::task::{Context, Poll};
+
+use super::{SocketAddr, ToSocketAddrs};
+use crate::io;
+
+use arceos_api::net::{self as api, AxTcpSocketHandle, AxUdpSocketHandle};
+
+/// An asynchronous TCP stream between a local and a remote socket.
+///
+/// Reads and writes are driven by the readiness of the socket, so many
+/// streams can be served concurrently by a single thread running an
+/// [`Executor`](crate::future::Executor).
+pub struct AsyncTcpStream(AxTcpSocketHandle);
+
+/// An asynchronous TCP socket server, listening for connections.
+pub struct AsyncTcpListener(AxTcpSocketHandle);
+
+/// An asynchronous UDP socket.
+pub struct AsyncUdpSocket(AxUdpSocketHandle);
+
+impl AsyncTcpStream {
+    /// Opens a TCP connection to a remote host.
+    ///
+    /// If `addr` yields multiple addresses, `connect` will be attempted with
+    /// each of the addresses until a connection is successful. If none of
+    /// the addresses result in a successful connection, the error returned from
+    /// the last connection attempt (the last address) is returned.
+    pub async fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<AsyncTcpStream> {
+        let mut last_err = None;
+        for addr in addr.to_socket_addrs()? {
+            let socket = api::ax_tcp_socket();
+            let res = match api::ax_tcp_start_connect(&socket, addr) {
+                Ok(()) => poll_fn(|cx| api::ax_tcp_poll_connected(&socket, cx)).await,
+                Err(e) => Err(e),
+            };
+            match res {
+                Ok(()) => return Ok(AsyncTcpStream(socket)),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| {
+            axerrno::ax_err_type!(InvalidInput, "could not resolve to any addresses")
+        }))
+    }
+
+    /// Returns the socket address of the local half of this TCP connection.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        api::ax_tcp_socket_addr(&self.0)
+    }
+
+    /// Returns the socket address of the remote peer of this TCP connection.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        api::ax_tcp_peer_addr(&self.0)
+    }
+
+    /// Shuts down the connection.
+    pub fn shutdown(&self) -> io::Result<()> {
+        api::ax_tcp_shutdown(&self.0)
+    }
+
+    /// Reads some bytes into `buf`, returns the number of bytes read.
+    ///
+    /// Returns `Ok(0)` if the connection is closed by the peer.
+    pub async fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        poll_fn(|cx| self.poll_read(cx, buf)).await
+    }
+
+    /// Writes some bytes from `buf`, returns the number of bytes written.
+    pub async fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        poll_fn(|cx| self.poll_write(cx, buf)).await
+    }
+
+    /// Writes the entire `buf` to the stream.
+    pub async fn write_all(&self, mut buf: &[u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.write(buf).await? {
+                0 => return axerrno::ax_err!(WriteZero, "failed to write whole buffer"),
+                n => buf = &buf[n..],
+            }
+        }
+        Ok(())
+    }
+
+    /// Polls for reading some bytes into `buf`.
+    pub fn poll_read(&self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        api::ax_tcp_poll_recv(&self.0, cx, buf)
+    }
+
+    /// Polls for writing some bytes from `buf`.
+    pub fn poll_write(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        api::ax_tcp_poll_send(&self.0, cx, buf)
+    }
+}
+
+impl AsyncTcpListener {
+    /// Creates a new `AsyncTcpListener` which will be bound to the specified
+    /// address.
+    ///
+    /// See [`TcpListener::bind`](super::TcpListener::bind) for details.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<AsyncTcpListener> {
+        super::each_addr(addr, |addr: io::Result<&SocketAddr>| {
+            let addr = addr?;
+            let backlog = 128;
+            let socket = api::ax_tcp_socket();
+            api::ax_tcp_bind(&socket, *addr)?;
+            api::ax_tcp_listen(&socket, backlog)?;
+            Ok(AsyncTcpListener(socket))
+        })
+    }
+
+    /// Returns the local socket address of this listener.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        api::ax_tcp_socket_addr(&self.0)
+    }
+
+    /// Accepts a new incoming connection from this listener.
+    ///
+    /// The returned future completes when a new TCP connection is
+    /// established, with the corresponding [`AsyncTcpStream`] and the remote
+    /// peer's address.
+    pub async fn accept(&self) -> io::Result<(AsyncTcpStream, SocketAddr)> {
+        poll_fn(|cx| api::ax_tcp_poll_accept(&self.0, cx))
+            .await
+            .map(|(a, b)| (AsyncTcpStream(a), b))
+    }
+}
+
+impl AsyncUdpSocket {
+    /// Creates a UDP socket from the given address.
+    ///
+    /// See [`UdpSocket::bind`](super::UdpSocket::bind) for details.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<AsyncUdpSocket> {
+        super::each_addr(addr, |addr: io::Result<&SocketAddr>| {
+            let addr = addr?;
+            let socket = api::ax_udp_socket();
+            api::ax_udp_bind(&socket, *addr)?;
+            Ok(AsyncUdpSocket(socket))
+        })
+    }
+
+    /// Returns the socket address that this socket was created from.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        api::ax_udp_socket_addr(&self.0)
+    }
+
+    /// Returns the socket address of the remote peer this socket was connected to.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        api::ax_udp_peer_addr(&self.0)
+    }
+
+    /// Connects this UDP socket to a remote address.
+    ///
+    /// See [`UdpSocket::connect`](super::UdpSocket::connect) for details.
+    pub fn connect(&self, addr: SocketAddr) -> io::Result<()> {
+        api::ax_udp_connect(&self.0, addr)
+    }
+
+    /// Receives a single datagram message on the socket. On success, returns
+    /// the number of bytes read and the origin.
+    pub async fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        poll_fn(|cx| api::ax_udp_poll_recv_from(&self.0, cx, buf)).await
+    }
+
+    /// Sends data on the socket to the given address. On success, returns the
+    /// number of bytes written.
+    ///
+    /// It is possible for `addr` to yield multiple addresses, but `send_to`
+    /// will only send data to the first address yielded by `addr`.
+    pub async fn send_to<A: ToSocketAddrs>(&self, buf: &[u8], addr: A) -> io::Result<usize> {
+        match addr.to_socket_addrs()?.next() {
+            Some(addr) => poll_fn(|cx| api::ax_udp_poll_send_to(&self.0, cx, buf, addr)).await,
+            None => axerrno::ax_err!(InvalidInput, "no addresses to send data to"),
+        }
+    }
+
+    /// Sends data on the socket to the remote address to which it is connected.
+    pub async fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        poll_fn(|cx| api::ax_udp_poll_send(&self.0, cx, buf)).await
+    }
+
+    /// Receives a single datagram message on the socket from the remote address
+    /// to which it is connected. On success, returns the number of bytes read.
+    pub async fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        poll_fn(|cx| api::ax_udp_poll_recv(&self.0, cx, buf)).await
+    }
+}
@@ -13,7 +13,11 @@
 //!   and [`SocketAddrV6`] are respectively IPv4 and IPv6 socket addresses
 //! * [`ToSocketAddrs`] is a trait that is used for generic address resolution when interacting
 //!   with networking objects like [`TcpListener`], [`TcpStream`] or [`UdpSocket`]
+//! * `AsyncTcpListener`, `AsyncTcpStream` and `AsyncUdpSocket` are asynchronous variants of the
+//!   above, available with the `async` feature
 
+#[cfg(feature = "async")]
+mod async_net;
 mod socket_addr;
 mod tcp;
 mod udp;
@@ -23,6 +27,9 @@ pub use self::socket_addr::{SocketAddr, SocketAddrV4, SocketAddrV6, ToSocketAddr
 pub use self::tcp::{TcpListener, TcpStream};
 pub use self::udp::UdpSocket;
 
+#[cfg(feature = "async")]
+pub use self::async_net::{AsyncTcpListener, AsyncTcpStream, AsyncUdpSocket};
+
 use crate::io;
 
 fn each_addr<A: ToSocketAddrs, F, T>(addr: A, mut f: F) -> io::Result<T>